@@ -1,4 +1,7 @@
 use crate::error::{Result, WasmrunError};
+use crate::logging::LogFormat;
+use crate::server::access_log::AccessLogFormat;
+use crate::utils::wasm_optimize::OptLevel;
 use crate::utils::PathResolver;
 use clap::{Parser, Subcommand};
 
@@ -31,16 +34,23 @@ pub struct Args {
     #[arg(index = 1, value_hint = clap::ValueHint::AnyPath)]
     pub positional_path: Option<String>,
 
-    /// Port to serve (default: 8420)
+    /// Port to serve (default: 8420, or the port set in wasmrun.toml)
     // TODO: Apply to web server as well if provided.
     #[arg(
         short = 'P',
         long,
-        default_value_t = 8420,
         value_parser = clap::value_parser!(u16).range(1..=65535),
         help = "Server port number"
     )]
-    pub port: u16,
+    pub port: Option<u16>,
+
+    /// Path prefix when served behind a reverse proxy, e.g. /myapp/
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Serve under a path prefix, e.g. /myapp/"
+    )]
+    pub base: Option<String>,
 
     /// Interpret path as a WebAssembly file (instead of a project directory)
     #[arg(short = 'w', long, help = "Run WASM file directly")]
@@ -54,10 +64,253 @@ pub struct Args {
     #[arg(long, global = true, help = "Show detailed debug information")]
     pub debug: bool,
 
+    /// More log output: -v for debug messages, -vv for trace messages
+    #[arg(
+        short = 'v',
+        long,
+        action = clap::ArgAction::Count,
+        help = "Verbose logging (-v debug, -vv trace)"
+    )]
+    pub verbose: u8,
+
+    /// Only show warnings and errors
+    #[arg(
+        short = 'q',
+        long,
+        global = true,
+        help = "Only show warnings and errors (overrides -v)"
+    )]
+    pub quiet: bool,
+
+    /// Log message format; json writes one object per line to stderr
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = LogFormat::Text,
+        value_name = "FORMAT",
+        help = "Log format: text or json (per-target levels via WASMRUN_LOG)"
+    )]
+    pub log_format: LogFormat,
+
+    /// Never use the network; fail fast when something would need it
+    #[arg(
+        long,
+        global = true,
+        help = "Disable all network access (also WASMRUN_OFFLINE=1)"
+    )]
+    pub offline: bool,
+
+    /// Configuration profile from wasmrun.toml to apply (e.g. ci, demo)
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        help = "Apply a [profile.<NAME>] section from wasmrun.toml"
+    )]
+    pub profile: Option<String>,
+
     /// Serve the UI in browser (default: false)
     #[arg(short = 's', long, help = "Open UI in browser when server starts")]
     pub serve: bool,
 
+    /// Serve over HTTPS, with a generated self-signed certificate unless --cert/--key are given
+    #[arg(
+        long,
+        global = true,
+        help = "Serve over HTTPS (needed for SharedArrayBuffer, service workers, ...)"
+    )]
+    pub tls: bool,
+
+    /// PEM certificate to serve HTTPS with (implies --tls)
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        requires = "key",
+        value_hint = clap::ValueHint::FilePath,
+        help = "TLS certificate (PEM), used with --key"
+    )]
+    pub cert: Option<String>,
+
+    /// PEM private key for --cert
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        requires = "cert",
+        value_hint = clap::ValueHint::FilePath,
+        help = "TLS private key (PEM), used with --cert"
+    )]
+    pub key: Option<String>,
+
+    /// Send Cross-Origin-Opener/Embedder-Policy headers for SharedArrayBuffer and wasm threads
+    #[arg(
+        long,
+        global = true,
+        help = "Cross-origin isolate served pages (needed for wasm threads)"
+    )]
+    pub coi: bool,
+
+    /// Send no ETag/Last-Modified validators and tell browsers not to cache served files
+    #[arg(
+        long,
+        global = true,
+        help = "Disable browser caching of served files (no 304 responses)"
+    )]
+    pub no_cache: bool,
+
+    /// How the page instantiates the module; overrides `loading.instantiate`
+    #[arg(
+        long,
+        global = true,
+        value_name = "MODE",
+        value_parser = ["streaming", "buffer"],
+        help = "Instantiate the module while it downloads (streaming) or from a fetched buffer"
+    )]
+    pub instantiate: Option<String>,
+
+    /// When the page loads the module; overrides `loading.trigger`
+    #[arg(
+        long,
+        global = true,
+        value_name = "WHEN",
+        value_parser = ["eager", "gesture"],
+        help = "Load the module right away (eager) or on the first click or key press (gesture)"
+    )]
+    pub load_trigger: Option<String>,
+
+    /// Keep compiled modules in IndexedDB; same as `loading.module_cache = true`
+    #[arg(
+        long,
+        global = true,
+        help = "Cache compiled modules in the browser's IndexedDB between loads"
+    )]
+    pub module_cache: bool,
+
+    /// Requests per minute a single client may make; overrides `server.rate_limit`
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Answer 429 once a client makes more than N requests per minute"
+    )]
+    pub rate_limit: Option<u32>,
+
+    /// Requests held open at once; overrides `server.max_connections`
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        help = "Answer 429 while N requests are already open"
+    )]
+    pub max_connections: Option<usize>,
+
+    /// Forward requests under a path prefix to a backend, e.g. /api=http://localhost:8080
+    #[arg(
+        long,
+        global = true,
+        value_name = "PREFIX=URL",
+        help = "Proxy a path prefix to a backend, e.g. /api=http://localhost:8080 (repeatable, overrides server.proxy)"
+    )]
+    pub proxy: Vec<String>,
+
+    /// Add a header to every response, e.g. "Cache-Control: no-store"
+    #[arg(
+        long = "header",
+        global = true,
+        value_name = "NAME: VALUE",
+        help = "Send a header with every response (repeatable, overrides server.headers)"
+    )]
+    pub headers: Vec<String>,
+
+    /// Serve read-only for others to view: GET/HEAD only, token access, rate-limited,
+    /// no control interface and no development panels
+    #[arg(
+        long,
+        global = true,
+        help = "Read-only demo mode: token access, no mutations or debug panels"
+    )]
+    pub demo: bool,
+
+    /// Access token for `--demo`; a random one is generated when omitted
+    #[arg(
+        long,
+        global = true,
+        value_name = "TOKEN",
+        help = "Access token for demo mode (implies --demo)"
+    )]
+    pub demo_token: Option<String>,
+
+    /// Name for the server, so several can run at once and be told apart
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        help = "Name this server instance (see wasmrun ps, wasmrun stop <NAME>)"
+    )]
+    pub name: Option<String>,
+
+    /// Track wasmrun's own CPU and memory use per subsystem and report it on exit
+    #[arg(
+        long,
+        global = true,
+        help = "Report wasmrun's own CPU and memory use on exit"
+    )]
+    pub self_profile: bool,
+
+    /// Put the server's URL on the clipboard once it is listening
+    #[arg(
+        long,
+        global = true,
+        help = "Copy the server URL to the clipboard at startup"
+    )]
+    pub copy_url: bool,
+
+    /// Write an access log line for every response, to PATH or stdout
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        num_args = 0..=1,
+        default_missing_value = "-",
+        value_hint = clap::ValueHint::FilePath,
+        help = "Log every response in Apache combined format, to PATH or stdout"
+    )]
+    pub access_log: Option<String>,
+
+    /// Layout of `--access-log` lines
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = AccessLogFormat::Combined,
+        value_name = "FORMAT",
+        help = "Access log format: combined (with user agent and duration) or common"
+    )]
+    pub access_log_format: AccessLogFormat,
+
+    /// Print a line for every request served; `off` for scripts and benchmarks
+    #[arg(
+        long,
+        global = true,
+        value_name = "on|off",
+        default_value = "on",
+        value_parser = clap::builder::BoolishValueParser::new(),
+        action = clap::ArgAction::Set,
+        help = "Print each request served (on/off)"
+    )]
+    pub log_requests: bool,
+
+    /// Show a terminal dashboard instead of scrolling logs while the dev server runs
+    #[arg(
+        long,
+        global = true,
+        help = "Monitor builds, requests and clients in a terminal UI"
+    )]
+    pub tui: bool,
+
     /// Language to use for compilation (auto-detect if not specified)
     #[arg(
         short = 'l',
@@ -68,96 +321,755 @@ pub struct Args {
     pub language: Option<String>,
 }
 
-#[derive(Subcommand, Debug)]
-pub enum Commands {
-    /// Stop any running Wasmrun server instance
-    #[command(alias = "kill")]
-    Stop,
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Stop a running Wasmrun server instance, or all of them
+    #[command(alias = "kill")]
+    Stop {
+        /// Name or port of the server to stop
+        #[arg(
+            index = 1,
+            value_name = "NAME|PORT",
+            help = "Name or port of the server to stop (default: all servers)"
+        )]
+        target: Option<String>,
+    },
+
+    /// List the running Wasmrun servers
+    Ps,
+
+    /// Compile a project to WebAssembly with optimization options
+    #[command(aliases = ["build", "c"])]
+    Compile {
+        /// Path to the project directory
+        #[arg(
+            short = 'p',
+            long,
+            value_hint = clap::ValueHint::DirPath,
+            help = "Project directory to compile"
+        )]
+        path: Option<String>,
+
+        /// Project directory path (positional argument)
+        #[arg(index = 1, value_hint = clap::ValueHint::DirPath)]
+        positional_path: Option<String>,
+
+        /// Output directory for the WASM file (default: current directory)
+        #[arg(
+            short = 'o',
+            long,
+            value_hint = clap::ValueHint::DirPath,
+            help = "Output directory for compiled files"
+        )]
+        output: Option<String>,
+
+        /// Enable verbose output; repeat (-vv) for trace logging
+        #[arg(short = 'v', long, action = clap::ArgAction::Count, help = "Show detailed compilation output")]
+        verbose: u8,
+
+        /// Cargo binary target to build, for crates with several [[bin]] targets
+        #[arg(
+            long,
+            value_name = "NAME",
+            conflicts_with = "example",
+            help = "Build this Cargo binary target"
+        )]
+        bin: Option<String>,
+
+        /// Cargo example to build
+        #[arg(long, value_name = "NAME", help = "Build this Cargo example")]
+        example: Option<String>,
+
+        /// Optimization level: debug, release, size (default: release, or wasmrun.toml)
+        #[arg(
+            long,
+            value_parser = ["debug", "release", "size"],
+            help = "Compilation optimization level"
+        )]
+        optimization: Option<String>,
+
+        /// Run wasm-opt on the module at LEVEL (O2, O3, Os or Oz; Os when omitted)
+        #[arg(
+            long,
+            value_enum,
+            value_name = "LEVEL",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "Os",
+            help = "Shrink the module with wasm-opt, e.g. --optimize=Oz"
+        )]
+        optimize: Option<OptLevel>,
+
+        /// Write the build's errors and warnings to FILE in SARIF, also when it fails
+        #[arg(
+            long,
+            value_name = "FILE",
+            value_hint = clap::ValueHint::FilePath,
+            help = "Write build errors and warnings to FILE as SARIF"
+        )]
+        sarif: Option<String>,
+
+        /// Print the build steps and commands instead of running them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Build a project and write its page, module and assets to a directory for any static host
+    Bundle {
+        /// Path to the project or WASM file
+        #[arg(
+            short = 'p',
+            long,
+            value_hint = clap::ValueHint::AnyPath,
+            help = "Project directory or WASM file to bundle"
+        )]
+        path: Option<String>,
+
+        /// Project path (positional argument)
+        #[arg(index = 1, value_hint = clap::ValueHint::AnyPath)]
+        positional_path: Option<String>,
+
+        /// Directory to write the bundle to
+        #[arg(
+            short = 'o',
+            long,
+            default_value = "dist",
+            value_hint = clap::ValueHint::DirPath,
+            help = "Output directory for the bundle"
+        )]
+        out: String,
+
+        /// Enable verbose output; repeat (-vv) for trace logging
+        #[arg(short = 'v', long, action = clap::ArgAction::Count, help = "Show detailed build output")]
+        verbose: u8,
+
+        /// Optimization level: debug, release, size (default: release, or wasmrun.toml)
+        #[arg(
+            long,
+            value_parser = ["debug", "release", "size"],
+            help = "Compilation optimization level"
+        )]
+        optimization: Option<String>,
+
+        /// Run wasm-opt on the module at LEVEL (O2, O3, Os or Oz; Os when omitted)
+        #[arg(
+            long,
+            value_enum,
+            value_name = "LEVEL",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "Os",
+            help = "Shrink the module with wasm-opt, e.g. --optimize=Oz"
+        )]
+        optimize: Option<OptLevel>,
+
+        /// Path prefix the bundle will be hosted under, e.g. /myapp/
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "Host under a path prefix, e.g. /myapp/"
+        )]
+        base: Option<String>,
+
+        /// Write a landing page describing the module as index.html, and the runner page as run.html
+        #[arg(
+            long,
+            help = "Add a landing page with the module's name, version, exports and size"
+        )]
+        with_demo_page: bool,
+
+        /// Sign SHA256SUMS and the bundled modules with this minisign secret key
+        #[arg(
+            long,
+            value_name = "KEY",
+            value_hint = clap::ValueHint::FilePath,
+            help = "Sign the bundle with a minisign secret key"
+        )]
+        sign: Option<String>,
+
+        /// Minisign public key to check the signatures with, shipped as minisign.pub
+        #[arg(
+            long,
+            value_name = "FILE",
+            requires = "sign",
+            value_hint = clap::ValueHint::FilePath,
+            help = "Check the signatures with this public key and add it to the bundle"
+        )]
+        public_key: Option<String>,
+
+        /// Print the build steps and the files it would write instead of bundling
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Verify WebAssembly file format and structure
+    Verify {
+        /// Path to the WASM file
+        #[arg(
+            short = 'p',
+            long,
+            value_hint = clap::ValueHint::FilePath,
+            help = "WASM file to verify"
+        )]
+        path: Option<String>,
+
+        /// WASM file path (positional argument)
+        #[arg(index = 1, value_hint = clap::ValueHint::FilePath)]
+        positional_path: Option<String>,
+
+        /// Show detailed information about the WASM module
+        #[arg(short = 'd', long, help = "Show detailed verification results")]
+        detailed: bool,
+    },
+
+    /// Perform detailed inspection on a WebAssembly file
+    Inspect {
+        /// Path to the WASM file
+        #[arg(
+            short = 'p',
+            long,
+            value_hint = clap::ValueHint::FilePath,
+            help = "WASM file to inspect"
+        )]
+        path: Option<String>,
+
+        /// WASM file path (positional argument)
+        #[arg(index = 1, value_hint = clap::ValueHint::FilePath)]
+        positional_path: Option<String>,
+    },
+
+    /// Shrink a WebAssembly file with Binaryen's wasm-opt and report the size change
+    Optimize {
+        /// Path to the WASM file
+        #[arg(
+            short = 'p',
+            long,
+            value_hint = clap::ValueHint::FilePath,
+            help = "WASM file to optimize"
+        )]
+        path: Option<String>,
+
+        /// WASM file path (positional argument)
+        #[arg(index = 1, value_hint = clap::ValueHint::FilePath)]
+        positional_path: Option<String>,
+
+        /// Optimization level: O2, O3, Os or Oz, also written -O2 ... -Oz
+        #[arg(
+            short = 'O',
+            long,
+            value_enum,
+            value_name = "LEVEL",
+            default_value_t = OptLevel::Os,
+            help = "Optimization level: O2, O3, Os or Oz"
+        )]
+        level: OptLevel,
+
+        /// File to write the optimized module to (default: overwrite the input)
+        #[arg(short = 'o', long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+        output: Option<String>,
+    },
+
+    /// Compile and run a project with live development server
+    #[command(alias = "dev")]
+    Run {
+        /// Path to the project
+        #[arg(
+            short = 'p',
+            long,
+            value_hint = clap::ValueHint::DirPath,
+            help = "Project directory to run"
+        )]
+        path: Option<String>,
+
+        /// Project path (positional argument)
+        #[arg(index = 1, value_hint = clap::ValueHint::DirPath)]
+        positional_path: Option<String>,
+
+        /// Port to serve (default: 8420, or the port set in wasmrun.toml)
+        #[arg(
+            short = 'P',
+            long,
+            value_parser = clap::value_parser!(u16).range(1..=65535),
+            help = "Development server port"
+        )]
+        port: Option<u16>,
+
+        /// Language to use for compilation (auto-detect if not specified)
+        #[arg(
+            short = 'l',
+            long,
+            value_parser = ["rust", "go", "c", "asc", "python"],
+            help = "Force specific language for compilation"
+        )]
+        language: Option<String>,
+
+        /// Enable watch mode for live-reloading on file changes
+        #[arg(long, help = "Watch for changes and auto-reload")]
+        watch: bool,
+
+        /// Have open pages compile a rebuilt module in the background before they reload
+        #[arg(
+            long,
+            help = "Compile rebuilt modules in open pages before reloading them"
+        )]
+        prewarm: bool,
+
+        /// Enable verbose output; repeat (-vv) for trace logging
+        #[arg(short = 'v', long, action = clap::ArgAction::Count, help = "Show detailed build output")]
+        verbose: u8,
+
+        /// Serve the UI in browser (default: false)
+        #[arg(short = 's', long, help = "Open UI in browser when server starts")]
+        serve: bool,
+
+        /// Audit the served page with axe-core in a headless browser, then exit
+        #[arg(
+            long,
+            help = "Run an axe-core accessibility audit in a headless browser"
+        )]
+        a11y_audit: bool,
+
+        /// Cargo binary target to build, for crates with several [[bin]] targets
+        #[arg(
+            long,
+            value_name = "NAME",
+            conflicts_with = "example",
+            help = "Build this Cargo binary target"
+        )]
+        bin: Option<String>,
+
+        /// Cargo example to build
+        #[arg(long, value_name = "NAME", help = "Build this Cargo example")]
+        example: Option<String>,
+
+        /// Replay a recorded input sequence once the page loads (opens the browser)
+        #[arg(long, value_name = "NAME", help = "Replay a saved input recording")]
+        replay: Option<String>,
+
+        /// Replay speed factor, e.g. 0.5 or 2x
+        #[arg(
+            long,
+            value_name = "SPEED",
+            default_value = "1",
+            requires = "replay",
+            help = "Speed of --replay, e.g. 0.5 or 2x"
+        )]
+        replay_speed: String,
+
+        /// Path prefix when served behind a reverse proxy, e.g. /myapp/
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "Serve under a path prefix, e.g. /myapp/"
+        )]
+        base: Option<String>,
+
+        /// Wrap functions with timing hooks and show a profile on the page; comma-separated
+        /// names, `prefix*` patterns, or `*` for every function
+        #[arg(
+            long,
+            value_name = "FUNCS",
+            help = "Profile these functions in the served module, e.g. render,update or *"
+        )]
+        instrument: Option<String>,
+    },
+
+    /// Build a release snapshot and serve it with production-like headers
+    Preview {
+        /// Path to the project or WASM file
+        #[arg(
+            short = 'p',
+            long,
+            value_hint = clap::ValueHint::AnyPath,
+            help = "Project directory or WASM file to preview"
+        )]
+        path: Option<String>,
+
+        /// Project path (positional argument)
+        #[arg(index = 1, value_hint = clap::ValueHint::AnyPath)]
+        positional_path: Option<String>,
+
+        /// Port to serve (default: 8420, or the port set in wasmrun.toml)
+        #[arg(
+            short = 'P',
+            long,
+            value_parser = clap::value_parser!(u16).range(1..=65535),
+            help = "Preview server port"
+        )]
+        port: Option<u16>,
+
+        /// Enable verbose output; repeat (-vv) for trace logging
+        #[arg(short = 'v', long, action = clap::ArgAction::Count, help = "Show detailed build output")]
+        verbose: u8,
+
+        /// Serve the UI in browser (default: false)
+        #[arg(short = 's', long, help = "Open UI in browser when server starts")]
+        serve: bool,
+
+        /// Audit the served page with axe-core in a headless browser, then exit
+        #[arg(
+            long,
+            help = "Run an axe-core accessibility audit in a headless browser"
+        )]
+        a11y_audit: bool,
+
+        /// Path prefix when served behind a reverse proxy, e.g. /myapp/
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "Serve under a path prefix, e.g. /myapp/"
+        )]
+        base: Option<String>,
+    },
+
+    /// Run projects in browser-based multi-language OS mode
+    Os {
+        /// Path to the project
+        #[arg(
+            short = 'p',
+            long,
+            value_hint = clap::ValueHint::DirPath,
+            help = "Project directory to run in OS mode"
+        )]
+        path: Option<String>,
+
+        /// Project path (positional argument)
+        #[arg(index = 1, value_hint = clap::ValueHint::DirPath)]
+        positional_path: Option<String>,
+
+        /// Port to serve (default: 8420, or the port set in wasmrun.toml)
+        #[arg(
+            short = 'P',
+            long,
+            value_parser = clap::value_parser!(u16).range(1..=65535),
+            help = "OS mode server port"
+        )]
+        port: Option<u16>,
+
+        /// Language to use for OS mode execution (auto-detect if not specified)
+        #[arg(
+            short = 'l',
+            long,
+            value_parser = ["nodejs", "python"],
+            help = "Force specific language for OS mode execution"
+        )]
+        language: Option<String>,
+
+        /// Enable watch mode for live-reloading on file changes
+        #[arg(long, help = "Watch for changes and auto-reload")]
+        watch: bool,
+
+        /// Enable verbose output; repeat (-vv) for trace logging
+        #[arg(short = 'v', long, action = clap::ArgAction::Count, help = "Show detailed build output")]
+        verbose: u8,
+    },
+
+    /// Run a WASI module or command component in the terminal with the built-in runtime
+    Exec {
+        /// WASI module or wasi:cli command component to run (.wasm or .wat)
+        #[arg(index = 1, value_hint = clap::ValueHint::FilePath)]
+        wasm: String,
+
+        /// Arguments passed to the module
+        #[arg(index = 2, trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+
+        /// Host directory the module may access, as HOST or HOST::GUEST (repeatable)
+        #[arg(
+            long = "dir",
+            value_name = "HOST[::GUEST]",
+            value_hint = clap::ValueHint::DirPath
+        )]
+        dirs: Vec<String>,
+
+        /// Environment variable for the module (repeatable)
+        #[arg(long = "env", value_name = "KEY=VALUE")]
+        envs: Vec<String>,
+    },
+
+    /// Plugin management commands
+    #[command(subcommand)]
+    Plugin(PluginSubcommands),
+
+    /// Register projects by name to launch them from anywhere with `wasmrun run <name>`
+    #[command(subcommand)]
+    Project(ProjectSubcommands),
+
+    /// Inspect wasmrun configuration
+    #[command(subcommand)]
+    Config(ConfigSubcommands),
+
+    // TODO: Implement project initialization command
+    // This will create new WebAssembly projects from templates (rust, go, c, asc, python)
+    // /// Initialize a new Wasmrun project from template
+    // #[command(alias = "new")]
+    // Init {
+    //     /// Project name
+    //     #[arg(index = 1, help = "Name of the new project")]
+    //     name: Option<String>,
+
+    //     /// Template to use (rust, go, c, asc)
+    //     #[arg(
+    //         short = 't',
+    //         long,
+    //         default_value = "rust",
+    //         value_parser = ["rust", "go", "c", "asc", "python"],
+    //         help = "Project template to use"
+    //     )]
+    //     template: String,
+
+    //     /// Target directory (default: project name)
+    //     #[arg(
+    //         short = 'd',
+    //         long,
+    //         value_hint = clap::ValueHint::DirPath,
+    //         help = "Directory to create project in"
+    //     )]
+    //     directory: Option<String>,
+    // },
+    /// Clean build artifacts and temporary files
+    #[command(aliases = ["clear", "reset"])]
+    Clean {
+        /// Path to the project directory
+        #[arg(
+            short = 'p',
+            long,
+            value_hint = clap::ValueHint::DirPath,
+            help = "Project directory to clean"
+        )]
+        path: Option<String>,
+
+        /// Project directory path (positional argument)
+        #[arg(index = 1, value_hint = clap::ValueHint::DirPath)]
+        positional_path: Option<String>,
+
+        /// Clean everything (project artifacts and temp directories)
+        #[arg(
+            short = 'a',
+            long,
+            help = "Clean both project artifacts and temp directories"
+        )]
+        all: bool,
+
+        /// Print what would be removed instead of removing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Remove cached project workspaces and unused stored artifacts beyond the size limit
+    Gc {
+        /// Size limit in megabytes (defaults to workspace_max_size_mb from the global config)
+        #[arg(
+            long = "max-size",
+            value_name = "MB",
+            help = "Keep workspaces within this many megabytes"
+        )]
+        max_size: Option<u64>,
+
+        /// Remove every workspace regardless of size
+        #[arg(short = 'a', long, help = "Remove all workspaces")]
+        all: bool,
+    },
+
+    /// Show artifact store and workspace cache statistics
+    Stats,
+
+    /// Build, serve and rebuild a sample project per language to check this machine's setup
+    Selftest {
+        /// Test the projects in each subdirectory of DIR instead of the built-in examples
+        #[arg(
+            long,
+            value_name = "DIR",
+            value_hint = clap::ValueHint::DirPath,
+            help = "Test the projects in DIR instead of the built-in examples"
+        )]
+        examples: Option<String>,
+
+        /// Only test examples in this language (repeatable)
+        #[arg(
+            short,
+            long = "language",
+            value_name = "LANG",
+            value_parser = ["rust", "go", "asc", "c", "python"],
+            help = "Only test examples in this language"
+        )]
+        languages: Vec<String>,
+
+        /// Keep the scratch copies of the examples and their server logs
+        #[arg(long, help = "Keep the example projects and logs")]
+        keep: bool,
+    },
+
+    /// Serve a build output directory such as ./dist, with every file at its relative path
+    Serve {
+        /// Directory to serve
+        #[arg(
+            short = 'p',
+            long,
+            value_hint = clap::ValueHint::DirPath,
+            help = "Directory to serve"
+        )]
+        path: Option<String>,
+
+        /// Directory path (positional argument)
+        #[arg(index = 1, value_hint = clap::ValueHint::DirPath)]
+        positional_path: Option<String>,
+
+        /// WASM entry relative to the directory (found automatically when omitted)
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "WASM entry, relative to the directory"
+        )]
+        entry: Option<String>,
+
+        /// List every module as a card with its own run page, described by module.toml files
+        #[arg(long, help = "Serve a gallery of every module in the directory")]
+        gallery: bool,
+
+        /// List the entries of directories that have no index.html instead of answering 404
+        #[arg(long, help = "List directories without an index.html")]
+        listings: bool,
+
+        /// Port to serve (default: 8420, or the port set in wasmrun.toml)
+        #[arg(
+            short = 'P',
+            long,
+            value_parser = clap::value_parser!(u16).range(1..=65535),
+            help = "Server port"
+        )]
+        port: Option<u16>,
+
+        /// Path prefix when served behind a reverse proxy, e.g. /myapp/
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "Serve under a path prefix, e.g. /myapp/"
+        )]
+        base: Option<String>,
+    },
+
+    /// Serve the builds of a wasmrun server on another machine, reloading when it rebuilds
+    Join {
+        /// URL of the build machine's server, e.g. http://192.168.1.20:8420
+        #[arg(index = 1, value_name = "URL", value_hint = clap::ValueHint::Url)]
+        url: String,
+
+        /// Port to serve (default: 8420, or the port set in wasmrun.toml)
+        #[arg(
+            short = 'P',
+            long,
+            value_parser = clap::value_parser!(u16).range(1..=65535),
+            help = "Viewer server port"
+        )]
+        port: Option<u16>,
+    },
+
+    /// Serve two WASM builds side by side for A/B comparison
+    Compare {
+        /// Baseline WASM file
+        #[arg(index = 1, value_hint = clap::ValueHint::FilePath)]
+        old: String,
+
+        /// WASM file to compare against the baseline
+        #[arg(index = 2, value_hint = clap::ValueHint::FilePath)]
+        new: String,
 
-    /// Compile a project to WebAssembly with optimization options
-    #[command(aliases = ["build", "c"])]
-    Compile {
-        /// Path to the project directory
+        /// Port to serve (default: 8420, or the port set in wasmrun.toml)
         #[arg(
-            short = 'p',
+            short = 'P',
             long,
-            value_hint = clap::ValueHint::DirPath,
-            help = "Project directory to compile"
+            value_parser = clap::value_parser!(u16).range(1..=65535),
+            help = "Comparison server port"
         )]
-        path: Option<String>,
-
-        /// Project directory path (positional argument)
-        #[arg(index = 1, value_hint = clap::ValueHint::DirPath)]
-        positional_path: Option<String>,
+        port: Option<u16>,
 
-        /// Output directory for the WASM file (default: current directory)
+        /// Path prefix when served behind a reverse proxy, e.g. /myapp/
         #[arg(
-            short = 'o',
             long,
-            value_hint = clap::ValueHint::DirPath,
-            help = "Output directory for compiled files"
+            value_name = "PATH",
+            help = "Serve under a path prefix, e.g. /myapp/"
         )]
-        output: Option<String>,
+        base: Option<String>,
+
+        /// Instead of serving, call this export natively in both builds and
+        /// compare the time spent in each function
+        #[arg(long, value_name = "EXPORT")]
+        bench: Option<String>,
 
-        /// Enable verbose output
-        #[arg(short = 'v', long, help = "Show detailed compilation output")]
-        verbose: bool,
+        /// Arguments for the benchmarked export, comma-separated
+        #[arg(
+            long = "bench-args",
+            value_name = "ARGS",
+            value_delimiter = ',',
+            allow_hyphen_values = true,
+            requires = "bench"
+        )]
+        bench_args: Vec<String>,
 
-        /// Optimization level: debug, release, size
+        /// Timed calls to the benchmarked export in each build
         #[arg(
             long,
-            default_value = "release",
-            value_parser = ["debug", "release", "size"],
-            help = "Compilation optimization level"
+            default_value_t = 100,
+            value_parser = clap::value_parser!(u32).range(1..),
+            requires = "bench"
         )]
-        optimization: String,
-    },
+        iterations: u32,
 
-    /// Verify WebAssembly file format and structure
-    Verify {
-        /// Path to the WASM file
+        /// Write every timed call of both builds to FILE in the Chrome trace
+        /// format, for chrome://tracing or Perfetto
         #[arg(
-            short = 'p',
             long,
+            value_name = "FILE",
             value_hint = clap::ValueHint::FilePath,
-            help = "WASM file to verify"
+            requires = "bench"
         )]
-        path: Option<String>,
+        trace: Option<String>,
+    },
 
-        /// WASM file path (positional argument)
-        #[arg(index = 1, value_hint = clap::ValueHint::FilePath)]
-        positional_path: Option<String>,
+    /// Report wasm artifact sizes, budgets and test results as CI annotations
+    CiReport {
+        /// WASM files or directories to report on (default: current directory)
+        #[arg(value_hint = clap::ValueHint::AnyPath)]
+        paths: Vec<String>,
 
-        /// Show detailed information about the WASM module
-        #[arg(short = 'd', long, help = "Show detailed verification results")]
-        detailed: bool,
-    },
+        /// Base branch sizes: a snapshot written with --save, or base branch WASM files
+        #[arg(long, value_name = "PATH", value_hint = clap::ValueHint::AnyPath)]
+        base: Option<String>,
 
-    /// Perform detailed inspection on a WebAssembly file
-    Inspect {
-        /// Path to the WASM file
+        /// Write a size snapshot to use as --base in later runs
+        #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+        save: Option<String>,
+
+        /// Per-artifact size budget (default: budget.max_size_kb in wasmrun.toml)
         #[arg(
-            short = 'p',
             long,
-            value_hint = clap::ValueHint::FilePath,
-            help = "WASM file to inspect"
+            value_name = "KB",
+            help = "Size budget per artifact in kilobytes"
         )]
-        path: Option<String>,
+        budget: Option<u64>,
 
-        /// WASM file path (positional argument)
-        #[arg(index = 1, value_hint = clap::ValueHint::FilePath)]
-        positional_path: Option<String>,
+        /// JUnit XML test results to include (repeatable)
+        #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+        junit: Vec<String>,
+
+        /// Output format (detected from GITHUB_ACTIONS / GITLAB_CI when omitted)
+        #[arg(
+            long,
+            value_parser = ["github", "gitlab", "text"],
+            help = "Annotation format"
+        )]
+        format: Option<String>,
     },
 
-    /// Compile and run a project with live development server
-    #[command(aliases = ["dev", "serve"])]
-    Run {
+    /// Report the licenses of dependencies compiled into a Rust or Go wasm build
+    Licenses {
         /// Path to the project
         #[arg(
             short = 'p',
             long,
             value_hint = clap::ValueHint::DirPath,
-            help = "Project directory to run"
+            help = "Project directory to report on"
         )]
         path: Option<String>,
 
@@ -165,136 +1077,121 @@ pub enum Commands {
         #[arg(index = 1, value_hint = clap::ValueHint::DirPath)]
         positional_path: Option<String>,
 
-        /// Port to serve (default: 8420)
-        #[arg(
-            short = 'P',
-            long,
-            default_value_t = 8420,
-            value_parser = clap::value_parser!(u16).range(1..=65535),
-            help = "Development server port"
-        )]
-        port: u16,
+        /// File to write the report to (printed to stdout when omitted)
+        #[arg(short = 'o', long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+        output: Option<String>,
 
-        /// Language to use for compilation (auto-detect if not specified)
+        /// Report format (json when the output file ends in .json, markdown otherwise)
         #[arg(
-            short = 'l',
             long,
-            value_parser = ["rust", "go", "c", "asc", "python"],
-            help = "Force specific language for compilation"
+            value_parser = ["markdown", "json"],
+            help = "Report format"
         )]
-        language: Option<String>,
-
-        /// Enable watch mode for live-reloading on file changes
-        #[arg(long, help = "Watch for changes and auto-reload")]
-        watch: bool,
-
-        /// Enable verbose output
-        #[arg(short = 'v', long, help = "Show detailed build output")]
-        verbose: bool,
-
-        /// Serve the UI in browser (default: false)
-        #[arg(short = 's', long, help = "Open UI in browser when server starts")]
-        serve: bool,
+        format: Option<String>,
     },
 
-    /// Run projects in browser-based multi-language OS mode
-    Os {
-        /// Path to the project
+    /// Check a build for missing security headers, permissive CORS, unsigned artifacts and debug sections
+    Audit {
+        /// Path to the build output or project
         #[arg(
             short = 'p',
             long,
-            value_hint = clap::ValueHint::DirPath,
-            help = "Project directory to run in OS mode"
+            value_hint = clap::ValueHint::AnyPath,
+            help = "Build output directory, project or WASM file to audit"
         )]
         path: Option<String>,
 
-        /// Project path (positional argument)
-        #[arg(index = 1, value_hint = clap::ValueHint::DirPath)]
+        /// Path (positional argument)
+        #[arg(index = 1, value_hint = clap::ValueHint::AnyPath)]
         positional_path: Option<String>,
 
-        /// Port to serve (default: 8420)
+        /// Run the security audit
         #[arg(
-            short = 'P',
             long,
-            default_value_t = 8420,
-            value_parser = clap::value_parser!(u16).range(1..=65535),
-            help = "OS mode server port"
+            required = true,
+            help = "Audit security headers, CORS, signatures and debug sections"
         )]
-        port: u16,
+        security: bool,
+    },
 
-        /// Language to use for OS mode execution (auto-detect if not specified)
+    /// Rank the likely causes of a module failing to compile or instantiate in the browser
+    Why {
+        /// Path to the module or project
         #[arg(
-            short = 'l',
+            short = 'p',
             long,
-            value_parser = ["nodejs", "python"],
-            help = "Force specific language for OS mode execution"
+            value_hint = clap::ValueHint::AnyPath,
+            help = "WASM file or project whose module fails to load"
         )]
-        language: Option<String>,
-
-        /// Enable watch mode for live-reloading on file changes
-        #[arg(long, help = "Watch for changes and auto-reload")]
-        watch: bool,
+        path: Option<String>,
 
-        /// Enable verbose output
-        #[arg(short = 'v', long, help = "Show detailed build output")]
-        verbose: bool,
+        /// Path (positional argument)
+        #[arg(index = 1, value_hint = clap::ValueHint::AnyPath)]
+        positional_path: Option<String>,
     },
 
-    /// Plugin management commands
-    #[command(subcommand)]
-    Plugin(PluginSubcommands),
-
-    // TODO: Implement project initialization command
-    // This will create new WebAssembly projects from templates (rust, go, c, asc, python)
-    // /// Initialize a new Wasmrun project from template
-    // #[command(alias = "new")]
-    // Init {
-    //     /// Project name
-    //     #[arg(index = 1, help = "Name of the new project")]
-    //     name: Option<String>,
-
-    //     /// Template to use (rust, go, c, asc)
-    //     #[arg(
-    //         short = 't',
-    //         long,
-    //         default_value = "rust",
-    //         value_parser = ["rust", "go", "c", "asc", "python"],
-    //         help = "Project template to use"
-    //     )]
-    //     template: String,
-
-    //     /// Target directory (default: project name)
-    //     #[arg(
-    //         short = 'd',
-    //         long,
-    //         value_hint = clap::ValueHint::DirPath,
-    //         help = "Directory to create project in"
-    //     )]
-    //     directory: Option<String>,
-    // },
-    /// Clean build artifacts and temporary files
-    #[command(aliases = ["clear", "reset"])]
-    Clean {
-        /// Path to the project directory
+    /// Print a module's exported API as TypeScript definitions
+    Types {
+        /// Path to the module or project
         #[arg(
             short = 'p',
             long,
-            value_hint = clap::ValueHint::DirPath,
-            help = "Project directory to clean"
+            value_hint = clap::ValueHint::AnyPath,
+            help = "WASM file or project to describe"
         )]
         path: Option<String>,
 
-        /// Project directory path (positional argument)
-        #[arg(index = 1, value_hint = clap::ValueHint::DirPath)]
+        /// Path (positional argument)
+        #[arg(index = 1, value_hint = clap::ValueHint::AnyPath)]
         positional_path: Option<String>,
 
-        /// Clean everything (project artifacts and temp directories)
+        /// File to write the definitions to (printed to stdout when omitted)
+        #[arg(short = 'o', long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+        output: Option<String>,
+    },
+
+    /// Describe wasmrun error codes and their exit codes
+    Explain {
+        /// Error code to describe, e.g. E0302 (lists every code if omitted)
+        #[arg(index = 1, value_name = "CODE")]
+        code: Option<String>,
+    },
+
+    /// Control a running server without restarting it
+    Ctl {
+        /// Port of the server to control (needed when several are running)
         #[arg(
-            short = 'a',
+            short = 'P',
             long,
-            help = "Clean both project artifacts and temp directories"
+            value_parser = clap::value_parser!(u16).range(1..=65535),
+            help = "Port of the server to control"
         )]
-        all: bool,
+        port: Option<u16>,
+
+        #[command(subcommand)]
+        action: CtlAction,
+    },
+}
+
+/// Commands sent to a running server
+#[derive(Subcommand, Debug)]
+pub enum CtlAction {
+    /// Reload every open page
+    Reload,
+
+    /// Rebuild the project, then reload open pages
+    Rebuild,
+
+    /// Open the page in the browser
+    Open,
+
+    /// Add a header to every response, or remove it when no value is given
+    SetHeader {
+        /// Header name, e.g. Cache-Control
+        name: String,
+
+        /// Header value (omit to remove the header)
+        value: Option<String>,
     },
 }
 
@@ -316,12 +1213,24 @@ pub enum PluginSubcommands {
         /// Specific version to install (for crates.io plugins)
         #[arg(short, long)]
         version: Option<String>,
+
+        /// SHA-256 a library downloaded from a URL must match
+        #[arg(long, value_name = "DIGEST")]
+        sha256: Option<String>,
+
+        /// Print the install steps instead of running them
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Uninstall a plugin
     Uninstall {
         /// Plugin name to uninstall
         plugin: String,
+
+        /// Print what would be removed instead of removing it
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Update a plugin
@@ -345,19 +1254,102 @@ pub enum PluginSubcommands {
         /// Plugin name
         plugin: String,
     },
-    // TODO: Implement plugin search with proper plugin registry system
-    // /// Search for available plugins
-    // Search {
-    //     /// Search query
-    //     query: String,
-    // },
+
+    /// Search crates.io for plugins
+    Search {
+        /// Only show plugins whose name or description contains this text
+        query: Option<String>,
+
+        /// Query crates.io even if cached results are recent
+        #[arg(long)]
+        refresh: bool,
+    },
+}
+
+/// Configuration subcommands
+#[derive(Subcommand, Debug)]
+pub enum ConfigSubcommands {
+    /// Print the config files, or with --resolved the merged settings and where each comes from
+    Show {
+        /// Path to the project
+        #[arg(
+            short = 'p',
+            long,
+            value_hint = clap::ValueHint::AnyPath,
+            help = "Project whose wasmrun.toml to use"
+        )]
+        path: Option<String>,
+
+        /// Project path (positional argument)
+        #[arg(index = 1, value_hint = clap::ValueHint::AnyPath)]
+        positional_path: Option<String>,
+
+        /// Merge defaults, global config, wasmrun.toml, profile and flags
+        #[arg(long, help = "Show merged values annotated with their source")]
+        resolved: bool,
+
+        /// Port as passed to run, to see it applied
+        #[arg(
+            short = 'P',
+            long,
+            value_parser = clap::value_parser!(u16).range(1..=65535),
+            help = "Resolve as if --port were given"
+        )]
+        port: Option<u16>,
+
+        /// Path prefix as passed to run, to see it applied
+        #[arg(long, value_name = "PATH", help = "Resolve as if --base were given")]
+        base: Option<String>,
+
+        /// Optimization level as passed to compile, to see it applied
+        #[arg(
+            long,
+            value_parser = ["debug", "release", "size"],
+            help = "Resolve as if --optimization were given"
+        )]
+        optimization: Option<String>,
+    },
+}
+
+/// Named project subcommands
+#[derive(Subcommand, Debug)]
+pub enum ProjectSubcommands {
+    /// Register a project directory or WASM file under a name
+    Add {
+        /// Name to launch the project with
+        name: String,
+
+        /// Project directory or WASM file
+        #[arg(value_hint = clap::ValueHint::AnyPath)]
+        path: String,
+
+        /// Port to serve the project on when run by name
+        #[arg(
+            short = 'P',
+            long,
+            value_parser = clap::value_parser!(u16).range(1..=65535),
+            help = "Port assigned to the project"
+        )]
+        port: Option<u16>,
+    },
+
+    /// List named projects with their last build and assigned port
+    #[command(alias = "ls")]
+    List,
+
+    /// Forget a named project (its files are left alone)
+    #[command(alias = "rm")]
+    Remove {
+        /// Project name
+        name: String,
+    },
 }
 
 /// Argument resolution with validation
 #[derive(Debug)]
 pub struct ResolvedArgs {
     pub path: String,
-    pub port: u16,
+    pub port: Option<u16>,
     pub wasm: bool,
     pub watch: bool,
     #[allow(dead_code)] // TODO: Used for debug output control
@@ -389,16 +1381,17 @@ impl ResolvedArgs {
     #[allow(dead_code)] // TODO: Future argument validation system
     pub fn validate(&self) -> Result<()> {
         // Validate port range
-        if self.port == 0 {
-            return Err(WasmrunError::from(format!(
-                "Invalid port number: {}. Must be between 1-65535",
-                self.port
-            )));
+        if self.port == Some(0) {
+            return Err(WasmrunError::from(
+                "Invalid port number: 0. Must be between 1-65535".to_string(),
+            ));
         }
 
         // Validate path based on context
         match &self.command {
-            Some(Commands::Verify { .. }) | Some(Commands::Inspect { .. }) => {
+            Some(Commands::Verify { .. })
+            | Some(Commands::Inspect { .. })
+            | Some(Commands::Optimize { .. }) => {
                 // These commands expect WASM files
                 PathResolver::validate_wasm_file(&self.path)?;
             }
@@ -449,12 +1442,27 @@ impl CommandArgs for Commands {
                 path,
                 positional_path,
                 ..
+            }
+            | Commands::Optimize {
+                path,
+                positional_path,
+                ..
             } => PathResolver::resolve_input_path(positional_path.clone(), path.clone()),
             Commands::Run {
                 path,
                 positional_path,
                 ..
             } => PathResolver::resolve_input_path(positional_path.clone(), path.clone()),
+            Commands::Preview {
+                path,
+                positional_path,
+                ..
+            }
+            | Commands::Bundle {
+                path,
+                positional_path,
+                ..
+            } => PathResolver::resolve_input_path(positional_path.clone(), path.clone()),
             Commands::Os {
                 path,
                 positional_path,
@@ -465,6 +1473,25 @@ impl CommandArgs for Commands {
                 positional_path,
                 ..
             } => PathResolver::resolve_input_path(positional_path.clone(), path.clone()),
+            Commands::Licenses {
+                path,
+                positional_path,
+                ..
+            } => PathResolver::resolve_input_path(positional_path.clone(), path.clone()),
+            Commands::Audit {
+                path,
+                positional_path,
+                ..
+            } => PathResolver::resolve_input_path(positional_path.clone(), path.clone()),
+            Commands::Why {
+                path,
+                positional_path,
+            }
+            | Commands::Types {
+                path,
+                positional_path,
+                ..
+            } => PathResolver::resolve_input_path(positional_path.clone(), path.clone()),
             // TODO: Implement Init command
             // Commands::Init {
             //     name, directory, ..
@@ -472,8 +1499,28 @@ impl CommandArgs for Commands {
             //     name.clone()
             //         .unwrap_or_else(|| "my-wasmrun-project".to_string())
             // }),
-            Commands::Plugin(_) => "./".to_string(),
-            Commands::Stop => "./".to_string(),
+            Commands::Plugin(_) | Commands::Project(_) => "./".to_string(),
+            Commands::Serve {
+                path,
+                positional_path,
+                ..
+            } => PathResolver::resolve_input_path(positional_path.clone(), path.clone()),
+            Commands::Config(ConfigSubcommands::Show {
+                path,
+                positional_path,
+                ..
+            }) => PathResolver::resolve_input_path(positional_path.clone(), path.clone()),
+            Commands::Compare { old, .. } => old.clone(),
+            Commands::Exec { wasm, .. } => wasm.clone(),
+            Commands::Stop { .. }
+            | Commands::Ps
+            | Commands::Gc { .. }
+            | Commands::Stats
+            | Commands::Selftest { .. }
+            | Commands::CiReport { .. }
+            | Commands::Explain { .. }
+            | Commands::Join { .. }
+            | Commands::Ctl { .. } => "./".to_string(),
         }
     }
 }
@@ -553,6 +1600,20 @@ impl CommandValidator {
     }
 }
 
+impl Args {
+    /// Number of `-v` flags, given before or after the subcommand
+    pub fn verbosity(&self) -> u8 {
+        let subcommand = match &self.command {
+            Some(Commands::Compile { verbose, .. })
+            | Some(Commands::Run { verbose, .. })
+            | Some(Commands::Preview { verbose, .. })
+            | Some(Commands::Os { verbose, .. }) => *verbose,
+            _ => 0,
+        };
+        self.verbose.saturating_add(subcommand)
+    }
+}
+
 /// Get version string
 fn get_version_string() -> &'static str {
     env!("CARGO_PKG_VERSION")
@@ -0,0 +1,436 @@
+//! Security audit of a build before release
+//!
+//! `wasmrun audit --security` checks what would ship: the response headers
+//! `wasmrun preview` sends once `server.headers` from `wasmrun.toml` is
+//! applied, the CORS policy, a detached signature next to each `.wasm`
+//! artifact, and debug sections left in the modules. Any failed check exits
+//! with code 12, so the audit can gate a release.
+
+use super::ci_report::collect_artifacts;
+use crate::config::active_settings;
+use crate::config::project::ServerSection;
+use crate::error::{Result, WasmrunError};
+use crate::server::preview::CONTENT_SECURITY_POLICY;
+use crate::utils::{ModuleInspection, PathResolver};
+use std::path::Path;
+
+/// Extensions of detached signatures: GPG, minisign and plain `.sig` (cosign, signify)
+const SIGNATURE_EXTENSIONS: &[&str] = &["sig", "asc", "minisig"];
+
+/// Custom sections that only carry debugging information
+const DEBUG_INFO_SECTIONS: &[&str] = &["sourceMappingURL", "external_debug_info"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    fn icon(self) -> &'static str {
+        match self {
+            Status::Pass => "✅",
+            Status::Warn => "⚠️ ",
+            Status::Fail => "❌",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Check {
+    status: Status,
+    title: String,
+    detail: String,
+}
+
+impl Check {
+    fn new(status: Status, title: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            status,
+            title: title.into(),
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Handle audit command
+pub fn handle_audit_command(path: &Option<String>, positional_path: &Option<String>) -> Result<()> {
+    let audit_path = PathResolver::resolve_input_path(positional_path.clone(), path.clone());
+    if !Path::new(&audit_path).exists() {
+        return Err(WasmrunError::FileNotFound { path: audit_path });
+    }
+
+    let settings = active_settings();
+    let headers = served_headers(&settings.server);
+    let artifacts = collect_artifacts(std::slice::from_ref(&audit_path))?;
+    let sections = [
+        ("Headers", header_checks(&headers)),
+        ("CORS", cors_checks(&headers)),
+        ("Signatures", signature_checks(&artifacts)),
+        ("Debug sections", debug_checks(&artifacts)),
+    ];
+
+    println!("🔒 Security audit: {audit_path}");
+    for (name, checks) in &sections {
+        println!("\n  {name}");
+        for check in checks {
+            println!(
+                "    {} {}: {}",
+                check.status.icon(),
+                check.title,
+                check.detail
+            );
+        }
+    }
+
+    let count = |status| {
+        sections
+            .iter()
+            .flat_map(|(_, checks)| checks)
+            .filter(|check| check.status == status)
+            .count()
+    };
+    let failed = count(Status::Fail);
+    println!(
+        "\n{} passed, {} warning(s), {failed} failed",
+        count(Status::Pass),
+        count(Status::Warn)
+    );
+    if failed > 0 {
+        return Err(WasmrunError::ChecksFailed { count: failed });
+    }
+    Ok(())
+}
+
+/// Headers `wasmrun preview` sends, with `server.headers` taking precedence
+fn served_headers(server: &ServerSection) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = [
+        ("Content-Security-Policy", CONTENT_SECURITY_POLICY),
+        ("X-Content-Type-Options", "nosniff"),
+    ]
+    .iter()
+    .map(|(name, value)| (name.to_string(), value.to_string()))
+    .collect();
+    for (name, value) in &server.headers {
+        headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+        headers.push((name.clone(), value.clone()));
+    }
+    headers
+}
+
+/// Value of a header, treating an empty value as unset
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+        .filter(|value| !value.is_empty())
+}
+
+/// Sources of a CSP directive, e.g. `["'self'", "blob:"]` for `worker-src`
+pub(super) fn csp_sources<'a>(policy: &'a str, directive: &str) -> Option<Vec<&'a str>> {
+    policy.split(';').find_map(|entry| {
+        let mut parts = entry.split_whitespace();
+        parts
+            .next()
+            .filter(|name| name.eq_ignore_ascii_case(directive))
+            .map(|_| parts.collect())
+    })
+}
+
+fn header_checks(headers: &[(String, String)]) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let policy = header(headers, "Content-Security-Policy");
+
+    checks.push(match policy {
+        None => Check::new(
+            Status::Fail,
+            "Content-Security-Policy",
+            "not sent; any injected script can run",
+        ),
+        Some(policy) => {
+            let scripts =
+                csp_sources(policy, "script-src").or_else(|| csp_sources(policy, "default-src"));
+            match scripts {
+                None => Check::new(
+                    Status::Fail,
+                    "Content-Security-Policy",
+                    "no script-src or default-src, so scripts load from anywhere",
+                ),
+                Some(sources)
+                    if sources
+                        .iter()
+                        .any(|source| ["*", "http:", "https:", "data:"].contains(source)) =>
+                {
+                    Check::new(
+                        Status::Fail,
+                        "Content-Security-Policy",
+                        format!("scripts may load from any origin ({})", sources.join(" ")),
+                    )
+                }
+                Some(sources) if sources.contains(&"'unsafe-eval'") => Check::new(
+                    Status::Warn,
+                    "Content-Security-Policy",
+                    "'unsafe-eval' allows eval(); 'wasm-unsafe-eval' is enough to compile wasm",
+                ),
+                Some(_) => Check::new(Status::Pass, "Content-Security-Policy", policy),
+            }
+        }
+    });
+
+    checks.push(match header(headers, "X-Content-Type-Options") {
+        Some(value) if value.eq_ignore_ascii_case("nosniff") => {
+            Check::new(Status::Pass, "X-Content-Type-Options", value)
+        }
+        Some(value) => Check::new(
+            Status::Fail,
+            "X-Content-Type-Options",
+            format!("'{value}' should be 'nosniff'"),
+        ),
+        None => Check::new(
+            Status::Fail,
+            "X-Content-Type-Options",
+            "not sent; browsers may sniff content types",
+        ),
+    });
+
+    let frame_ancestors = policy.and_then(|policy| csp_sources(policy, "frame-ancestors"));
+    checks.push(
+        match (frame_ancestors, header(headers, "X-Frame-Options")) {
+            (Some(sources), _) => Check::new(
+                Status::Pass,
+                "Framing",
+                format!("frame-ancestors {}", sources.join(" ")),
+            ),
+            (None, Some(value)) => {
+                Check::new(Status::Pass, "Framing", format!("X-Frame-Options {value}"))
+            }
+            (None, None) => Check::new(
+                Status::Warn,
+                "Framing",
+                "any site can embed the page; set frame-ancestors or X-Frame-Options",
+            ),
+        },
+    );
+
+    for (name, hint) in [
+        ("Referrer-Policy", "e.g. strict-origin-when-cross-origin"),
+        ("Strict-Transport-Security", "needed when served over HTTPS"),
+    ] {
+        checks.push(match header(headers, name) {
+            Some(value) => Check::new(Status::Pass, name, value),
+            None => Check::new(Status::Warn, name, format!("not sent ({hint})")),
+        });
+    }
+    checks
+}
+
+fn cors_checks(headers: &[(String, String)]) -> Vec<Check> {
+    let credentials = header(headers, "Access-Control-Allow-Credentials")
+        .is_some_and(|value| value.eq_ignore_ascii_case("true"));
+    let check = match header(headers, "Access-Control-Allow-Origin") {
+        None => Check::new(
+            Status::Pass,
+            "Access-Control-Allow-Origin",
+            "not sent; only same-origin pages can read responses",
+        ),
+        Some(origin @ ("*" | "null")) => Check::new(
+            Status::Fail,
+            "Access-Control-Allow-Origin",
+            if credentials {
+                format!("'{origin}' with credentials lets any site read authenticated responses")
+            } else {
+                format!("'{origin}' lets any site read every response")
+            },
+        ),
+        Some(origin) => Check::new(Status::Pass, "Access-Control-Allow-Origin", origin),
+    };
+    vec![check]
+}
+
+/// A detached signature next to `artifact` that is at least as new as the artifact
+fn signature_checks(artifacts: &[String]) -> Vec<Check> {
+    if artifacts.is_empty() {
+        return vec![Check::new(
+            Status::Fail,
+            "Artifacts",
+            "no .wasm files found to audit",
+        )];
+    }
+    artifacts
+        .iter()
+        .map(|artifact| {
+            let modified = Path::new(artifact)
+                .metadata()
+                .and_then(|meta| meta.modified());
+            let signatures: Vec<(String, bool)> = SIGNATURE_EXTENSIONS
+                .iter()
+                .map(|extension| format!("{artifact}.{extension}"))
+                .filter_map(|signature| {
+                    let signed = Path::new(&signature)
+                        .metadata()
+                        .and_then(|meta| meta.modified())
+                        .ok()?;
+                    let current = modified.as_ref().is_ok_and(|modified| signed >= *modified);
+                    Some((signature, current))
+                })
+                .collect();
+
+            match signatures.iter().find(|(_, current)| *current) {
+                Some((signature, _)) => Check::new(Status::Pass, artifact, file_name(signature)),
+                None if !signatures.is_empty() => Check::new(
+                    Status::Fail,
+                    artifact,
+                    format!(
+                        "{} is older than the artifact; sign the new build",
+                        file_name(&signatures[0].0)
+                    ),
+                ),
+                None => Check::new(
+                    Status::Fail,
+                    artifact,
+                    format!(
+                        "unsigned (no .{} file next to it)",
+                        SIGNATURE_EXTENSIONS.join(", .")
+                    ),
+                ),
+            }
+        })
+        .collect()
+}
+
+fn debug_checks(artifacts: &[String]) -> Vec<Check> {
+    artifacts
+        .iter()
+        .map(|artifact| {
+            let inspection = match ModuleInspection::from_file(artifact) {
+                Ok(inspection) => inspection,
+                Err(e) => return Check::new(Status::Fail, artifact, e),
+            };
+            debug_check(artifact, &inspection)
+        })
+        .collect()
+}
+
+fn debug_check(artifact: &str, inspection: &ModuleInspection) -> Check {
+    let sections = &inspection.custom_sections;
+    let dwarf: Vec<_> = sections
+        .iter()
+        .filter(|section| section.name.starts_with(".debug_"))
+        .collect();
+    if !dwarf.is_empty() {
+        let size: usize = dwarf.iter().map(|section| section.size).sum();
+        return Check::new(
+            Status::Fail,
+            artifact,
+            format!(
+                "{} DWARF section(s), {size} bytes; build in release mode or run wasm-opt --strip-debug",
+                dwarf.len()
+            ),
+        );
+    }
+
+    let leftovers: Vec<&str> = sections
+        .iter()
+        .map(|section| section.name.as_str())
+        .filter(|name| *name == "name" || DEBUG_INFO_SECTIONS.contains(name))
+        .collect();
+    if leftovers.is_empty() {
+        Check::new(Status::Pass, artifact, "no debug sections")
+    } else {
+        Check::new(
+            Status::Warn,
+            artifact,
+            format!(
+                "{} section(s) kept; they reveal function names or debug file locations",
+                leftovers.join(", ")
+            ),
+        )
+    }
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use tempfile::tempdir;
+
+    fn statuses(checks: &[Check]) -> Vec<(&str, Status)> {
+        checks
+            .iter()
+            .map(|check| (check.title.as_str(), check.status))
+            .collect()
+    }
+
+    #[test]
+    fn test_header_checks() {
+        let headers = served_headers(&ServerSection::default());
+        assert_eq!(
+            statuses(&header_checks(&headers)),
+            [
+                ("Content-Security-Policy", Status::Pass),
+                ("X-Content-Type-Options", Status::Pass),
+                ("Framing", Status::Warn),
+                ("Referrer-Policy", Status::Warn),
+                ("Strict-Transport-Security", Status::Warn),
+            ]
+        );
+        assert_eq!(
+            statuses(&cors_checks(&headers)),
+            [("Access-Control-Allow-Origin", Status::Pass)]
+        );
+
+        let server = ServerSection {
+            headers: BTreeMap::from([
+                (
+                    "content-security-policy".to_string(),
+                    "default-src *".to_string(),
+                ),
+                ("X-Content-Type-Options".to_string(), String::new()),
+                ("Access-Control-Allow-Origin".to_string(), "*".to_string()),
+            ]),
+            ..ServerSection::default()
+        };
+        let headers = served_headers(&server);
+        let checks = header_checks(&headers);
+        assert_eq!(checks[0].status, Status::Fail);
+        assert_eq!(checks[1].status, Status::Fail);
+        assert_eq!(cors_checks(&headers)[0].status, Status::Fail);
+    }
+
+    #[test]
+    fn test_csp_sources() {
+        let policy = "default-src 'self'; frame-ancestors 'none'";
+        assert_eq!(csp_sources(policy, "frame-ancestors"), Some(vec!["'none'"]));
+        assert_eq!(csp_sources(policy, "script-src"), None);
+    }
+
+    #[test]
+    fn test_signature_and_debug_checks() {
+        let dir = tempdir().unwrap();
+        let wasm = dir.path().join("app.wasm");
+        std::fs::write(&wasm, b"\0asm\x01\0\0\0").unwrap();
+        let artifacts = vec![wasm.to_string_lossy().to_string()];
+
+        assert_eq!(signature_checks(&artifacts)[0].status, Status::Fail);
+        std::fs::write(dir.path().join("app.wasm.minisig"), b"sig").unwrap();
+        assert_eq!(signature_checks(&artifacts)[0].status, Status::Pass);
+        assert_eq!(signature_checks(&[])[0].status, Status::Fail);
+
+        assert_eq!(debug_checks(&artifacts)[0].status, Status::Pass);
+        // A custom section is id 0, then the section size, the name length and the name
+        let mut module = b"\0asm\x01\0\0\0".to_vec();
+        module.extend([0, 12, 11]);
+        module.extend(b".debug_info");
+        std::fs::write(&wasm, module).unwrap();
+        assert_eq!(debug_checks(&artifacts)[0].status, Status::Fail);
+    }
+}
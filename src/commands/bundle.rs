@@ -0,0 +1,719 @@
+//! Export a project as static files (`wasmrun bundle`)
+//!
+//! Builds the project like `wasmrun run` and writes what the dev server would
+//! serve for it to a directory: the runner page as `index.html`, the module,
+//! its glue and the other build output, the `node_modules` packages the page
+//! imports and the `/assets/` files it references. Nothing in the bundle needs
+//! wasmrun, so it can be uploaded to any static host as is.
+//!
+//! The same build gives the same bundle: files are written in path order with
+//! their modification times set to `SOURCE_DATE_EPOCH` (1980-01-01 when
+//! unset), and a `SHA256SUMS` manifest in `sha256sum` format lists every
+//! file, so a deployment can check it with `sha256sum -c SHA256SUMS`.
+
+use super::licenses;
+use crate::commands::{plan_build, run_compile};
+use crate::compiler::builder::OptimizationLevel;
+use crate::error::{Result, WasmrunError};
+use crate::server::landing::{self, ModuleInfo};
+use crate::server::{bundle, component, site, wasm};
+use crate::utils::wasm_optimize::OptLevel;
+use crate::utils::{ArtifactStore, CommandExecutor, DryRun, PathResolver, Workspace};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs::{self, FileTimes};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Directory the dev server answers `/assets/` requests from
+const ASSETS_DIR: &str = "assets";
+
+/// Checksums of the bundled files, written last
+const CHECKSUMS_FILE: &str = "SHA256SUMS";
+
+/// Where signed bundles carry the public key their signatures check against
+const PUBLIC_KEY_FILE: &str = "minisign.pub";
+
+/// Extension minisign gives the signature of each file
+const SIGNATURE_EXTENSION: &str = "minisig";
+
+/// A minisign secret key to sign a bundle with (`bundle --sign`)
+#[derive(Debug, Clone)]
+pub struct Signing {
+    pub secret_key: String,
+    /// Checked against the signatures, then shipped as `minisign.pub`
+    pub public_key: Option<String>,
+}
+
+/// Modification time of bundled files when `SOURCE_DATE_EPOCH` is unset:
+/// 1980-01-01, the earliest time zip archives can hold
+const DEFAULT_SOURCE_DATE: u64 = 315_532_800;
+
+/// Handle bundle command
+#[allow(clippy::too_many_arguments)]
+pub fn handle_bundle_command(
+    path: &Option<String>,
+    positional_path: &Option<String>,
+    out: &str,
+    optimization_level: OptimizationLevel,
+    optimize: Option<OptLevel>,
+    with_demo_page: bool,
+    signing: Option<&Signing>,
+    verbose: bool,
+) -> Result<()> {
+    // Before building, not after
+    if signing.is_some() && !CommandExecutor::is_tool_installed("minisign") {
+        return Err(WasmrunError::missing_tools(vec!["minisign".to_string()]));
+    }
+    let resolved_path = PathResolver::resolve_input_path(positional_path.clone(), path.clone());
+    let is_wasm_file = Path::new(&resolved_path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("wasm"));
+    let build_kind = format!("{optimization_level} build");
+
+    let (wasm_path, js_path, project_path) = if is_wasm_file {
+        PathResolver::validate_wasm_file(&resolved_path)?;
+        let wasm_dir = Path::new(&resolved_path)
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        check_out_dir(out, &wasm_dir.to_string_lossy())?;
+        let js_path = wasm::glue_for(&resolved_path);
+        (resolved_path, js_path, None)
+    } else {
+        PathResolver::validate_directory_exists(&resolved_path)?;
+        check_out_dir(out, &resolved_path)?;
+        let workspace = Workspace::for_project(&resolved_path)?;
+        workspace.enforce_retention();
+        let build_dir = workspace.fresh_bundle_dir()?;
+
+        println!("📦 Building {resolved_path}");
+        let result = run_compile(
+            resolved_path.clone(),
+            build_dir,
+            optimization_level,
+            verbose,
+        )?;
+        let js_path = result.js_path.or_else(|| wasm::glue_for(&result.wasm_path));
+        (result.wasm_path, js_path, Some(resolved_path))
+    };
+    component::prepare(&wasm_path)?;
+
+    let js_filename = js_path.as_deref().and_then(|js_path| {
+        Path::new(js_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    });
+    let page = bundle::page(&wasm_path, js_filename.as_deref(), project_path.as_deref())?;
+
+    let mut files = module_files(
+        Path::new(&wasm_path),
+        js_path.as_deref().map(Path::new),
+        project_path.is_some(),
+    );
+    if let Some(project_path) = &project_path {
+        let node_modules = Path::new(project_path).join(bundle::node_modules_dir());
+        for package in bundle::node_packages(&wasm_path, Some(project_path)) {
+            add_tree(
+                &mut files,
+                &node_modules.join(&package),
+                &Path::new(bundle::node_modules_dir()).join(&package),
+            );
+        }
+    }
+    for asset in referenced_assets(&page) {
+        let source = Path::new(ASSETS_DIR).join(&asset);
+        if source.is_file() {
+            files.insert(Path::new(ASSETS_DIR).join(&asset), source);
+        }
+    }
+    if let Some(public_key) = signing.and_then(|signing| signing.public_key.as_deref()) {
+        files.insert(PathBuf::from(PUBLIC_KEY_FILE), PathBuf::from(public_key));
+    }
+
+    let demo = with_demo_page.then(|| {
+        let mut info = ModuleInfo::read(
+            Path::new(&wasm_path),
+            project_path.as_deref().map(Path::new),
+        );
+        if project_path.is_some() {
+            info.build.push(build_kind);
+        }
+        if let Some(level) = optimize {
+            info.build.push(format!("wasm-opt {level}"));
+        }
+        info
+    });
+    let licenses = match &project_path {
+        Some(project_path) => licenses::bundle_report(project_path).map_err(|e| {
+            WasmrunError::add_context("Collecting dependency licenses for the bundle", e)
+        })?,
+        None => None,
+    };
+    let optimize = optimize.map(|level| (Path::new(wasm_path.as_str()), level));
+    write_bundle(
+        Path::new(out),
+        &page,
+        &files,
+        optimize,
+        demo,
+        licenses,
+        signing,
+    )
+}
+
+/// Print what `wasmrun bundle` would do, without building or writing anything
+pub fn handle_bundle_dry_run(
+    path: &Option<String>,
+    positional_path: &Option<String>,
+    out: &str,
+    optimization_level: OptimizationLevel,
+    optimize: Option<OptLevel>,
+    with_demo_page: bool,
+    signing: Option<&Signing>,
+) -> Result<()> {
+    let resolved_path = PathResolver::resolve_input_path(positional_path.clone(), path.clone());
+    let is_wasm_file = Path::new(&resolved_path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("wasm"));
+
+    let mut plan = DryRun::new();
+    let out_dir = Path::new(out);
+    let files = if is_wasm_file {
+        PathResolver::validate_wasm_file(&resolved_path)?;
+        let wasm_dir = Path::new(&resolved_path)
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        check_out_dir(out, &wasm_dir.to_string_lossy())?;
+        let js_path = wasm::glue_for(&resolved_path);
+        Some(module_files(
+            Path::new(&resolved_path),
+            js_path.as_deref().map(Path::new),
+            false,
+        ))
+    } else {
+        PathResolver::validate_directory_exists(&resolved_path)?;
+        check_out_dir(out, &resolved_path)?;
+        let build_dir = Workspace::bundle_dir_for(&resolved_path)?;
+        plan_build(
+            &mut plan,
+            resolved_path.clone(),
+            build_dir.to_string_lossy().to_string(),
+            optimization_level,
+        )?;
+        None
+    };
+
+    if !out_dir.is_dir() {
+        plan.create_dir(out_dir);
+    }
+    plan.write(out_dir.join(site::INDEX_FILE));
+    if with_demo_page {
+        plan.write(out_dir.join(landing::RUNNER_FILE));
+    }
+    if !is_wasm_file && licenses::has_report(&resolved_path) {
+        plan.write(out_dir.join(licenses::BUNDLE_FILE));
+    }
+    match files {
+        Some(files) => {
+            for target in files.keys() {
+                plan.write(out_dir.join(target));
+            }
+            plan.note("copy the /assets/ files the page refers to");
+        }
+        None => plan.note(
+            "copy the build output, the node_modules packages the page imports \
+             and the /assets/ files it refers to",
+        ),
+    }
+    if let Some(level) = optimize {
+        plan.note(format!("optimize the module with wasm-opt {level}"));
+    }
+    plan.write(out_dir.join(CHECKSUMS_FILE));
+    if let Some(signing) = signing {
+        if let Some(public_key) = &signing.public_key {
+            plan.write(out_dir.join(PUBLIC_KEY_FILE));
+            plan.note(format!("check the signatures against {public_key}"));
+        }
+        plan.note(format!(
+            "sign {CHECKSUMS_FILE} and the bundled modules with minisign, using {}",
+            signing.secret_key
+        ));
+        plan.write(out_dir.join(signature_for(Path::new(CHECKSUMS_FILE))));
+    }
+    plan.note(format!(
+        "set the modification times of the bundled files to {}",
+        source_date()
+    ));
+    plan.print("wasmrun bundle");
+    Ok(())
+}
+
+/// Refuse an output directory whose files the bundle could overwrite with
+/// copies of themselves or a generated `index.html`: one holding `source`
+fn check_out_dir(out: &str, source: &str) -> Result<()> {
+    let (Ok(out_dir), Ok(source_dir)) = (fs::canonicalize(out), fs::canonicalize(source)) else {
+        return Ok(());
+    };
+    if source_dir.starts_with(&out_dir) {
+        return Err(WasmrunError::path(format!(
+            "--out {out} contains {source}; choose a directory of its own, such as dist/"
+        )));
+    }
+    Ok(())
+}
+
+/// The files the page loads next to the module at `wasm_path`, by their path
+/// in the bundle. A fresh build directory is taken whole; next to a module
+/// given directly, only the module, its glue, wasm-bindgen snippets and a
+/// component's transpilation are
+fn module_files(
+    wasm_path: &Path,
+    js_path: Option<&Path>,
+    whole_dir: bool,
+) -> BTreeMap<PathBuf, PathBuf> {
+    let mut files = BTreeMap::new();
+    let dir = wasm_path.parent().unwrap_or(Path::new("."));
+    if whole_dir {
+        add_tree(&mut files, dir, Path::new(""));
+        return files;
+    }
+
+    for file in [Some(wasm_path), js_path].into_iter().flatten() {
+        if let Some(name) = file.file_name() {
+            files.insert(PathBuf::from(name), file.to_path_buf());
+        }
+    }
+    add_tree(&mut files, &dir.join("snippets"), Path::new("snippets"));
+    if let Some(transpiled) = component::transpiled() {
+        let script = Path::new(&transpiled);
+        match script
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            Some(subdir) => add_tree(&mut files, &dir.join(subdir), subdir),
+            None => {
+                // jco names the core modules after the script
+                let stem = script.file_stem().unwrap_or_default().to_string_lossy();
+                let mut names = vec![transpiled.clone()];
+                if let Ok(entries) = fs::read_dir(dir) {
+                    names.extend(
+                        entries
+                            .flatten()
+                            .map(|entry| entry.file_name().to_string_lossy().to_string())
+                            .filter(|name| name.starts_with(&format!("{stem}.core"))),
+                    );
+                }
+                for name in names {
+                    files.insert(PathBuf::from(&name), dir.join(&name));
+                }
+            }
+        }
+    }
+    files
+}
+
+/// Add the visible files below `source` to `files`, under `target`
+fn add_tree(files: &mut BTreeMap<PathBuf, PathBuf>, source: &Path, target: &Path) {
+    let mut found = Vec::new();
+    site::collect_files(source, 0, &mut found);
+    for file in found {
+        if let Ok(relative) = file.strip_prefix(source) {
+            files.insert(target.join(relative), file.clone());
+        }
+    }
+}
+
+/// Files under `/assets/` the page refers to, relative to the assets directory
+fn referenced_assets(page: &str) -> Vec<String> {
+    let asset_re = Regex::new(r#"/assets/([A-Za-z0-9._-]+(?:/[A-Za-z0-9._-]+)*)"#).unwrap();
+    let mut assets: Vec<String> = asset_re
+        .captures_iter(page)
+        .map(|caps| caps[1].to_string())
+        .filter(|asset| !asset.split('/').any(|part| part == ".." || part == "."))
+        .collect();
+    assets.sort();
+    assets.dedup();
+    assets
+}
+
+/// Write the page and copy `files` into `out`; the module given with
+/// `optimize` is run through wasm-opt on its way. With `demo`, the page
+/// becomes `run.html` and `index.html` a landing page describing the module.
+/// A license report goes next to them as `THIRD_PARTY_LICENSES.md`, and
+/// with `signing` the checksums and modules get minisign signatures.
+fn write_bundle(
+    out: &Path,
+    page: &str,
+    files: &BTreeMap<PathBuf, PathBuf>,
+    optimize: Option<(&Path, OptLevel)>,
+    demo: Option<ModuleInfo>,
+    licenses: Option<String>,
+    signing: Option<&Signing>,
+) -> Result<()> {
+    let written = |what: &Path, e: std::io::Error| {
+        WasmrunError::add_context(format!("Writing {}", out.join(what).display()), e)
+    };
+    fs::create_dir_all(out).map_err(|e| written(Path::new(""), e))?;
+
+    let module = optimize.and_then(|(module, level)| Some((fs::canonicalize(module).ok()?, level)));
+    let mut sizes = Vec::new();
+    for (target, source) in files {
+        let destination = out.join(target);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).map_err(|e| written(target, e))?;
+        }
+        let size = match &module {
+            Some((module, level)) if fs::canonicalize(source).is_ok_and(|path| &path == module) => {
+                super::optimize_module(source, &destination, *level)?.after
+            }
+            _ => fs::copy(source, &destination).map_err(|e| written(target, e))?,
+        };
+        sizes.push((target.to_string_lossy().to_string(), size));
+    }
+    // The generated pages replace any the build wrote, as on the dev server
+    let mut pages = vec![(site::INDEX_FILE, page.to_string())];
+    if let Some(mut info) = demo {
+        if let Some((_, size)) = sizes.iter().find(|(name, _)| name == &info.file) {
+            info.size = *size;
+        }
+        pages = vec![
+            (site::INDEX_FILE, landing::page(&info)),
+            (landing::RUNNER_FILE, page.to_string()),
+        ];
+    }
+    if let Some(report) = licenses {
+        pages.push((licenses::BUNDLE_FILE, report));
+    }
+    for (name, contents) in pages.iter().rev() {
+        fs::write(out.join(name), contents).map_err(|e| written(Path::new(name), e))?;
+        sizes.retain(|(file, _)| file != name);
+        sizes.insert(0, (name.to_string(), contents.len() as u64));
+    }
+
+    let mut bundled: Vec<PathBuf> = files
+        .keys()
+        .filter(|target| target.as_path() != Path::new(CHECKSUMS_FILE))
+        .cloned()
+        .collect();
+    bundled.extend(pages.iter().map(|(name, _)| PathBuf::from(name)));
+    bundled.sort();
+    bundled.dedup();
+    let checksums = checksums(out, &bundled)?;
+    fs::write(out.join(CHECKSUMS_FILE), &checksums)
+        .map_err(|e| written(Path::new(CHECKSUMS_FILE), e))?;
+    sizes.push((CHECKSUMS_FILE.to_string(), checksums.len() as u64));
+    bundled.push(PathBuf::from(CHECKSUMS_FILE));
+    if let Some(signing) = signing {
+        for signature in sign(out, &bundled, signing)? {
+            let size = fs::metadata(out.join(&signature)).map_or(0, |meta| meta.len());
+            sizes.push((signature.to_string_lossy().to_string(), size));
+            bundled.push(signature);
+        }
+    }
+    stamp(out, &bundled, source_date())?;
+
+    println!("\n📦 Bundled into {}", out.display());
+    let width = sizes.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, size) in &sizes {
+        println!(
+            "   {name:<width$}  {:>10}",
+            CommandExecutor::format_file_size(*size)
+        );
+    }
+    let total: u64 = sizes.iter().map(|(_, size)| size).sum();
+    println!(
+        "   {} files, {}",
+        sizes.len(),
+        CommandExecutor::format_file_size(total)
+    );
+    println!(
+        "\n🚀 Upload {} to any static host, or try it with: wasmrun serve {}",
+        out.display(),
+        out.display()
+    );
+    Ok(())
+}
+
+/// Sign the modules among `bundled` and the checksums with minisign, each
+/// into a `.minisig` next to it, and check the signatures against the
+/// shipped public key. Returns the signatures.
+fn sign(out: &Path, bundled: &[PathBuf], signing: &Signing) -> Result<Vec<PathBuf>> {
+    let secret_key = fs::canonicalize(&signing.secret_key).map_err(|e| {
+        WasmrunError::add_context(format!("Reading the secret key {}", signing.secret_key), e)
+    })?;
+    let signed = signed_files(bundled);
+    println!("🔏 Signing {} files with minisign", signed.len());
+    // minisign asks for the key's password, if it has one, on the terminal
+    minisign(out, &sign_args(&secret_key, &signed, source_date()))?;
+    if signing.public_key.is_some() {
+        for file in &signed {
+            minisign(out, &verify_args(file))?;
+        }
+    }
+    Ok(signed.iter().map(|file| signature_for(file)).collect())
+}
+
+/// The files of a bundle that get signed: the checksums, which cover every
+/// file, and the modules, for checks that look for a signature next to them
+fn signed_files(bundled: &[PathBuf]) -> Vec<PathBuf> {
+    bundled
+        .iter()
+        .filter(|file| {
+            file.as_path() == Path::new(CHECKSUMS_FILE)
+                || file.extension().is_some_and(|ext| ext == "wasm")
+        })
+        .cloned()
+        .collect()
+}
+
+/// Where minisign writes the signature of `file`
+fn signature_for(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_os_string();
+    name.push(format!(".{SIGNATURE_EXTENSION}"));
+    PathBuf::from(name)
+}
+
+/// Arguments that sign `files` with `secret_key`. The trusted comment holds
+/// the bundle's date rather than the time of signing, and Ed25519 signatures
+/// are deterministic, so a signed bundle stays reproducible.
+fn sign_args(secret_key: &Path, files: &[PathBuf], seconds: u64) -> Vec<String> {
+    let mut args = vec![
+        "-S".to_string(),
+        "-s".to_string(),
+        secret_key.to_string_lossy().to_string(),
+        "-t".to_string(),
+        format!("timestamp:{seconds}\twasmrun bundle"),
+        "-m".to_string(),
+    ];
+    args.extend(files.iter().map(|file| file.to_string_lossy().to_string()));
+    args
+}
+
+/// Arguments that check the signature of `file` against the shipped public key
+fn verify_args(file: &Path) -> Vec<String> {
+    vec![
+        "-V".to_string(),
+        "-q".to_string(),
+        "-p".to_string(),
+        PUBLIC_KEY_FILE.to_string(),
+        "-m".to_string(),
+        file.to_string_lossy().to_string(),
+    ]
+}
+
+/// Run minisign in `out`, failing when it does
+fn minisign(out: &Path, args: &[String]) -> Result<()> {
+    let command = CommandExecutor::display_command("minisign", args);
+    let status = std::process::Command::new("minisign")
+        .args(args)
+        .current_dir(out)
+        .status()
+        .map_err(|e| WasmrunError::add_context(format!("Running {command}"), e))?;
+    if !status.success() {
+        return Err(WasmrunError::from(format!("{command} failed ({status})")));
+    }
+    Ok(())
+}
+
+/// `sha256sum` lines for `files` in `out`, with `/` between path components
+fn checksums(out: &Path, files: &[PathBuf]) -> Result<String> {
+    let mut lines = String::new();
+    for file in files {
+        let digest = ArtifactStore::digest_file(&out.join(file))?;
+        let name: Vec<_> = file
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect();
+        lines.push_str(&format!("{digest}  {}\n", name.join("/")));
+    }
+    Ok(lines)
+}
+
+/// The time bundled files are stamped with, in seconds since the epoch
+fn source_date() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse().ok())
+        .unwrap_or(DEFAULT_SOURCE_DATE)
+}
+
+/// Set the modification time of `files` in `out`, the directories holding
+/// them and `out` itself to `seconds`
+fn stamp(out: &Path, files: &[PathBuf], seconds: u64) -> Result<()> {
+    let mut paths = std::collections::BTreeSet::new();
+    for file in files {
+        paths.extend(file.ancestors().map(|path| out.join(path)));
+    }
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+    for path in &paths {
+        set_modified(path, time).map_err(|e| {
+            WasmrunError::add_context(
+                format!("Setting the modification time of {}", path.display()),
+                e,
+            )
+        })?;
+    }
+    Ok(())
+}
+
+fn set_modified(path: &Path, time: SystemTime) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        // FILE_WRITE_ATTRIBUTES, which directories can be opened with as well
+        options.access_mode(0x100);
+    }
+    #[cfg(not(windows))]
+    options.read(true);
+    options
+        .open(path)?
+        .set_times(FileTimes::new().set_accessed(time).set_modified(time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_module_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("snippets/app-1234")).unwrap();
+        fs::write(root.join("snippets/app-1234/inline0.js"), "export {}").unwrap();
+        fs::write(root.join("app_bg.wasm"), "\0asm").unwrap();
+        fs::write(root.join("app.js"), "export default 1").unwrap();
+        fs::write(root.join("notes.txt"), "unrelated").unwrap();
+        fs::write(root.join(".gitignore"), "*").unwrap();
+
+        let wasm = root.join("app_bg.wasm");
+        let js = root.join("app.js");
+        let picked: Vec<PathBuf> = module_files(&wasm, Some(&js), false).into_keys().collect();
+        assert_eq!(
+            picked,
+            vec![
+                PathBuf::from("app.js"),
+                PathBuf::from("app_bg.wasm"),
+                PathBuf::from("snippets/app-1234/inline0.js"),
+            ]
+        );
+
+        let whole = module_files(&wasm, Some(&js), true);
+        assert_eq!(whole.len(), 4);
+        assert_eq!(whole[Path::new("notes.txt")], root.join("notes.txt"));
+    }
+
+    #[test]
+    fn test_referenced_assets_and_out_dir() {
+        let page = r#"<img src="/assets/logo.png"><link href="/base/assets/css/a.css"><a href="/assets/../secret">"#;
+        assert_eq!(referenced_assets(page), vec!["css/a.css", "logo.png"]);
+
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("app");
+        fs::create_dir_all(&project).unwrap();
+        let project = project.to_string_lossy();
+        assert!(check_out_dir(&dir.path().to_string_lossy(), &project).is_err());
+        assert!(check_out_dir(&project, &project).is_err());
+        assert!(check_out_dir(&format!("{project}/dist"), &project).is_ok());
+    }
+
+    #[test]
+    fn test_bundle_carries_the_license_report() {
+        let dir = tempdir().unwrap();
+        let build = dir.path().join("build");
+        fs::create_dir_all(&build).unwrap();
+        fs::write(build.join("app.wasm"), "\0asm").unwrap();
+        let files = BTreeMap::from([(PathBuf::from("app.wasm"), build.join("app.wasm"))]);
+        let report = "# Third-party licenses for app\n".to_string();
+
+        let out = dir.path().join("dist");
+        write_bundle(
+            &out,
+            "<html>",
+            &files,
+            None,
+            None,
+            Some(report.clone()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(out.join(licenses::BUNDLE_FILE)).unwrap(),
+            report
+        );
+        let sums = fs::read_to_string(out.join(CHECKSUMS_FILE)).unwrap();
+        assert!(sums.contains("  THIRD_PARTY_LICENSES.md\n"));
+
+        let plain = dir.path().join("plain");
+        write_bundle(&plain, "<html>", &files, None, None, None, None).unwrap();
+        assert!(!plain.join(licenses::BUNDLE_FILE).exists());
+    }
+
+    #[test]
+    fn test_signing_arguments() {
+        let bundled = vec![
+            PathBuf::from("THIRD_PARTY_LICENSES.md"),
+            PathBuf::from("app_bg.wasm"),
+            PathBuf::from("index.html"),
+            PathBuf::from("nested/core.wasm"),
+            PathBuf::from(CHECKSUMS_FILE),
+        ];
+        let signed = signed_files(&bundled);
+        assert_eq!(
+            signed,
+            [
+                PathBuf::from("app_bg.wasm"),
+                PathBuf::from("nested/core.wasm"),
+                PathBuf::from(CHECKSUMS_FILE),
+            ]
+        );
+        assert_eq!(
+            signature_for(Path::new("nested/core.wasm")),
+            Path::new("nested/core.wasm.minisig")
+        );
+
+        let args = sign_args(Path::new("/keys/minisign.key"), &signed, 315_532_800);
+        assert_eq!(
+            CommandExecutor::display_command("minisign", &args),
+            "minisign -S -s /keys/minisign.key -t 'timestamp:315532800\twasmrun bundle' \
+             -m app_bg.wasm nested/core.wasm SHA256SUMS"
+        );
+        assert_eq!(
+            verify_args(Path::new(CHECKSUMS_FILE)),
+            ["-V", "-q", "-p", "minisign.pub", "-m", "SHA256SUMS"]
+        );
+    }
+
+    #[test]
+    fn test_checksums_and_stamped_times() {
+        let dir = tempdir().unwrap();
+        let out = dir.path();
+        fs::create_dir_all(out.join("snippets")).unwrap();
+        fs::write(out.join("index.html"), "<html>").unwrap();
+        fs::write(out.join("snippets/a.js"), "").unwrap();
+        let files = vec![PathBuf::from("index.html"), PathBuf::from("snippets/a.js")];
+
+        let sums = checksums(out, &files).unwrap();
+        assert_eq!(
+            sums,
+            "b7d082ee12e91b756ea22e8513b8594eebcf5d39fab813da3cb55794dc888ad7  index.html\n\
+             e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  snippets/a.js\n"
+        );
+
+        stamp(out, &files, DEFAULT_SOURCE_DATE).unwrap();
+        for path in [
+            out.join("snippets/a.js"),
+            out.join("snippets"),
+            out.to_path_buf(),
+        ] {
+            let modified = fs::metadata(&path).unwrap().modified().unwrap();
+            let seconds = modified.duration_since(std::time::UNIX_EPOCH).unwrap();
+            assert_eq!(seconds.as_secs(), DEFAULT_SOURCE_DATE);
+        }
+        // A file that cannot be stamped fails the bundle instead of making it irreproducible
+        assert!(stamp(out, &[PathBuf::from("missing.js")], DEFAULT_SOURCE_DATE).is_err());
+    }
+}
@@ -0,0 +1,703 @@
+//! Wasm size and test summaries for CI
+//!
+//! Reports artifact sizes, their change against the base branch, the size
+//! budget from `[budget]` in `wasmrun.toml` and JUnit test results. Output is
+//! GitHub Actions workflow commands or a GitLab Code Quality report, so the
+//! results show up directly on pull and merge requests.
+
+use crate::commands::verify_wasm;
+use crate::config::active_settings;
+use crate::error::{Result, WasmrunError};
+use crate::server::utils::find_wasm_files;
+use crate::utils::CommandExecutor;
+use crate::warn_println;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Directories whose `.wasm` files are intermediate build products rather than artifacts
+const SKIPPED_DIRS: &[&str] = &["node_modules", ".git", "deps", "incremental"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Github,
+    Gitlab,
+    Text,
+}
+
+impl Format {
+    /// Use the requested format, or detect the CI system from its environment
+    fn resolve(format: Option<&str>) -> Self {
+        let env_set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+        match format {
+            Some("github") => Format::Github,
+            Some("gitlab") => Format::Gitlab,
+            Some(_) => Format::Text,
+            None if env_set("GITHUB_ACTIONS") => Format::Github,
+            None if env_set("GITLAB_CI") => Format::Gitlab,
+            None => Format::Text,
+        }
+    }
+}
+
+/// Artifact sizes saved with `--save` and read back with `--base`
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct SizeSnapshot {
+    artifacts: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Artifact {
+    name: String,
+    path: String,
+    size: u64,
+    /// Size of the same artifact on the base branch
+    base: Option<u64>,
+    /// Why the artifact is not a valid wasm module
+    error: Option<String>,
+}
+
+impl Artifact {
+    fn delta(&self) -> Option<i64> {
+        self.base.map(|base| self.size as i64 - base as i64)
+    }
+
+    fn growth_percent(&self) -> Option<f64> {
+        match self.base {
+            Some(base) if base > 0 => Some((self.size as f64 - base as f64) / base as f64 * 100.0),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Budget {
+    max_size: Option<u64>,
+    max_growth_percent: Option<f64>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct TestResults {
+    total: usize,
+    skipped: usize,
+    failures: Vec<TestFailure>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TestFailure {
+    name: String,
+    file: Option<String>,
+    line: Option<u32>,
+    message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Severity {
+    Notice,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Annotation {
+    severity: Severity,
+    title: String,
+    file: Option<String>,
+    line: Option<u32>,
+    message: String,
+}
+
+/// Handle ci-report command
+pub fn handle_ci_report_command(
+    paths: &[String],
+    base: &Option<String>,
+    save: &Option<String>,
+    budget_kb: Option<u64>,
+    junit: &[String],
+    format: &Option<String>,
+) -> Result<()> {
+    let format = Format::resolve(format.as_deref());
+    let settings = active_settings();
+    let budget = Budget {
+        max_size: budget_kb
+            .or(settings.budget.max_size_kb)
+            .map(|kb| kb * 1024),
+        max_growth_percent: settings.budget.max_growth_percent,
+    };
+
+    let paths = if paths.is_empty() {
+        vec!["./".to_string()]
+    } else {
+        paths.to_vec()
+    };
+    let base_sizes = match base {
+        Some(base) => Some(load_base(base)?),
+        None => None,
+    };
+
+    let mut artifacts = Vec::new();
+    for path in collect_artifacts(&paths)? {
+        let size = fs::metadata(&path)
+            .map_err(|e| WasmrunError::add_context(format!("Reading artifact {path}"), e))?
+            .len();
+        let name = file_name(&path);
+        let error = match verify_wasm(&path) {
+            Ok(result) if result.valid_magic => None,
+            Ok(_) => Some("not a WebAssembly module (bad magic bytes)".to_string()),
+            Err(e) => Some(e),
+        };
+        artifacts.push(Artifact {
+            base: base_sizes
+                .as_ref()
+                .and_then(|sizes| sizes.get(&name).copied()),
+            name,
+            path,
+            size,
+            error,
+        });
+    }
+
+    let mut tests = None;
+    for path in junit {
+        let xml = fs::read_to_string(path)
+            .map_err(|e| WasmrunError::add_context(format!("Reading test results {path}"), e))?;
+        let results = tests.get_or_insert_with(TestResults::default);
+        let parsed = parse_junit(&xml);
+        results.total += parsed.total;
+        results.skipped += parsed.skipped;
+        results.failures.extend(parsed.failures);
+    }
+
+    if let Some(save) = save {
+        let snapshot = SizeSnapshot {
+            artifacts: artifacts
+                .iter()
+                .map(|artifact| (artifact.name.clone(), artifact.size))
+                .collect(),
+        };
+        let json = serde_json::to_string_pretty(&snapshot)
+            .map_err(|e| WasmrunError::from(format!("Failed to serialize size snapshot: {e}")))?;
+        fs::write(save, json)
+            .map_err(|e| WasmrunError::add_context(format!("Writing size snapshot {save}"), e))?;
+    }
+
+    let annotations = annotate(&artifacts, &budget, tests.as_ref());
+    let summary = text_summary(&artifacts, &budget, tests.as_ref());
+    match format {
+        Format::Github => {
+            println!("{summary}");
+            print!("{}", github_commands(&annotations));
+            if let Some(path) = std::env::var_os("GITHUB_STEP_SUMMARY") {
+                let markdown = markdown_summary(&artifacts, &budget, tests.as_ref());
+                let written = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .and_then(|mut file| file.write_all(markdown.as_bytes()));
+                if let Err(e) = written {
+                    warn_println!("Could not write the job summary: {e}");
+                }
+            }
+        }
+        Format::Gitlab => {
+            // stdout is the Code Quality report; keep the human summary in the job log
+            eprintln!("{summary}");
+            println!("{}", gitlab_code_quality(&annotations));
+        }
+        Format::Text => {
+            println!("{summary}");
+            for annotation in annotations
+                .iter()
+                .filter(|annotation| annotation.severity > Severity::Notice)
+            {
+                let icon = match annotation.severity {
+                    Severity::Error => "❌",
+                    _ => "⚠️ ",
+                };
+                println!("{icon} {}: {}", annotation.title, annotation.message);
+            }
+        }
+    }
+
+    let failed = annotations
+        .iter()
+        .filter(|annotation| annotation.severity == Severity::Error)
+        .count();
+    if failed > 0 {
+        return Err(WasmrunError::ChecksFailed { count: failed });
+    }
+    Ok(())
+}
+
+/// Expand files and directories into the `.wasm` artifacts they contain
+pub(super) fn collect_artifacts(paths: &[String]) -> Result<Vec<String>> {
+    let mut artifacts = Vec::new();
+    for path in paths {
+        let path_obj = Path::new(path);
+        if path_obj.is_file() {
+            artifacts.push(path.clone());
+        } else if path_obj.is_dir() {
+            artifacts.extend(find_wasm_files(path_obj).into_iter().filter(|file| {
+                !Path::new(file).components().any(|component| {
+                    SKIPPED_DIRS.contains(&component.as_os_str().to_string_lossy().as_ref())
+                })
+            }));
+        } else {
+            return Err(WasmrunError::FileNotFound { path: path.clone() });
+        }
+    }
+    artifacts.sort();
+    artifacts.dedup();
+    Ok(artifacts)
+}
+
+/// Base branch sizes from a `--save` snapshot or from base branch `.wasm` files
+fn load_base(base: &str) -> Result<BTreeMap<String, u64>> {
+    if base.ends_with(".json") {
+        let content = fs::read_to_string(base)
+            .map_err(|e| WasmrunError::add_context(format!("Reading size snapshot {base}"), e))?;
+        let snapshot: SizeSnapshot = serde_json::from_str(&content)
+            .map_err(|e| WasmrunError::from(format!("Invalid size snapshot {base}: {e}")))?;
+        return Ok(snapshot.artifacts);
+    }
+
+    let mut sizes = BTreeMap::new();
+    for path in collect_artifacts(&[base.to_string()])? {
+        if let Ok(metadata) = fs::metadata(&path) {
+            sizes.insert(file_name(&path), metadata.len());
+        }
+    }
+    Ok(sizes)
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Summarize JUnit XML; only the attributes CI tools commonly emit are read
+fn parse_junit(xml: &str) -> TestResults {
+    let testcase = Regex::new(r#"(?s)<testcase\b([^>]*?)(?:/>|>(.*?)</testcase>)"#).unwrap();
+    let failure = Regex::new(r#"<(?:failure|error)\b([^>]*?)/?>"#).unwrap();
+
+    let mut results = TestResults::default();
+    for case in testcase.captures_iter(xml) {
+        results.total += 1;
+        let attributes = &case[1];
+        let body = case.get(2).map_or("", |body| body.as_str());
+
+        if body.contains("<skipped") {
+            results.skipped += 1;
+        } else if let Some(failed) = failure.captures(body) {
+            let name = match (
+                xml_attribute(attributes, "classname"),
+                xml_attribute(attributes, "name"),
+            ) {
+                (Some(class), Some(name)) => format!("{class}::{name}"),
+                (None, Some(name)) => name,
+                _ => "unnamed test".to_string(),
+            };
+            results.failures.push(TestFailure {
+                name,
+                file: xml_attribute(attributes, "file"),
+                line: xml_attribute(attributes, "line").and_then(|line| line.parse().ok()),
+                message: xml_attribute(&failed[1], "message")
+                    .unwrap_or_else(|| "test failed".to_string()),
+            });
+        }
+    }
+    results
+}
+
+fn xml_attribute(attributes: &str, name: &str) -> Option<String> {
+    let pattern = Regex::new(&format!(r#"\b{name}="([^"]*)""#)).ok()?;
+    let value = pattern.captures(attributes)?[1].to_string();
+    Some(
+        value
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&"),
+    )
+}
+
+fn annotate(
+    artifacts: &[Artifact],
+    budget: &Budget,
+    tests: Option<&TestResults>,
+) -> Vec<Annotation> {
+    let mut annotations = Vec::new();
+    let mut add = |severity, title: &str, file: Option<&str>, message: String| {
+        annotations.push(Annotation {
+            severity,
+            title: title.to_string(),
+            file: file.map(str::to_string),
+            line: None,
+            message,
+        });
+    };
+
+    for artifact in artifacts {
+        let file = Some(artifact.path.as_str());
+        if let Some(error) = &artifact.error {
+            add(
+                Severity::Error,
+                "Invalid wasm artifact",
+                file,
+                format!("{}: {error}", artifact.name),
+            );
+            continue;
+        }
+
+        let size = CommandExecutor::format_file_size(artifact.size);
+        let over_budget = budget.max_size.filter(|max| artifact.size > *max);
+        let over_growth = match (artifact.growth_percent(), budget.max_growth_percent) {
+            (Some(growth), Some(max)) if growth > max => Some((growth, max)),
+            _ => None,
+        };
+
+        if let Some(max) = over_budget {
+            add(
+                Severity::Error,
+                "Wasm size budget exceeded",
+                file,
+                format!(
+                    "{} is {size}, {} over the {} budget",
+                    artifact.name,
+                    CommandExecutor::format_file_size(artifact.size - max),
+                    CommandExecutor::format_file_size(max)
+                ),
+            );
+        }
+        if let Some((growth, max)) = over_growth {
+            add(
+                Severity::Error,
+                "Wasm size growth exceeded",
+                file,
+                format!(
+                    "{} grew {growth:.1}% over the base branch (allowed {max:.1}%)",
+                    artifact.name
+                ),
+            );
+        }
+        if over_budget.is_none() && over_growth.is_none() {
+            let mut message = format!("{} is {size}", artifact.name);
+            if let Some(delta) = artifact.delta() {
+                message.push_str(&format!(
+                    " ({} vs base)",
+                    format_delta(delta, artifact.growth_percent())
+                ));
+            }
+            if let Some(max) = budget.max_size {
+                message.push_str(&format!(
+                    ", {:.0}% of the {} budget",
+                    artifact.size as f64 / max as f64 * 100.0,
+                    CommandExecutor::format_file_size(max)
+                ));
+            }
+            add(Severity::Notice, "Wasm size", file, message);
+        }
+    }
+
+    if let Some(tests) = tests {
+        for failure in &tests.failures {
+            annotations.push(Annotation {
+                severity: Severity::Error,
+                title: format!("Test failed: {}", failure.name),
+                file: failure.file.clone(),
+                line: failure.line,
+                message: failure.message.clone(),
+            });
+        }
+        let severity = if tests.failures.is_empty() {
+            Severity::Notice
+        } else {
+            Severity::Warning
+        };
+        annotations.push(Annotation {
+            severity,
+            title: "Tests".to_string(),
+            file: None,
+            line: None,
+            message: tests_line(tests),
+        });
+    }
+
+    annotations
+}
+
+fn format_delta(delta: i64, percent: Option<f64>) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    let mut text = format!(
+        "{sign}{}",
+        CommandExecutor::format_file_size(delta.unsigned_abs())
+    );
+    if let Some(percent) = percent {
+        text.push_str(&format!(" / {percent:+.1}%"));
+    }
+    text
+}
+
+fn tests_line(tests: &TestResults) -> String {
+    let failed = tests.failures.len();
+    format!(
+        "{} passed, {failed} failed, {} skipped",
+        tests.total.saturating_sub(failed + tests.skipped),
+        tests.skipped
+    )
+}
+
+fn budget_status(artifact: &Artifact, budget: &Budget) -> String {
+    if artifact.error.is_some() {
+        return "invalid".to_string();
+    }
+    let mut status = Vec::new();
+    if let Some(max) = budget.max_size {
+        let used = artifact.size as f64 / max as f64 * 100.0;
+        status.push(if artifact.size > max {
+            format!("over ({used:.0}%)")
+        } else {
+            format!("ok ({used:.0}%)")
+        });
+    }
+    if let (Some(growth), Some(max)) = (artifact.growth_percent(), budget.max_growth_percent) {
+        if growth > max {
+            status.push(format!("growth over {max:.1}%"));
+        }
+    }
+    if status.is_empty() {
+        "-".to_string()
+    } else {
+        status.join(", ")
+    }
+}
+
+fn text_summary(artifacts: &[Artifact], budget: &Budget, tests: Option<&TestResults>) -> String {
+    let mut out = String::from("📦 Wasm artifacts\n");
+    if artifacts.is_empty() {
+        out.push_str("   No .wasm artifacts found\n");
+    }
+    for artifact in artifacts {
+        let delta = artifact
+            .delta()
+            .map(|delta| format_delta(delta, artifact.growth_percent()))
+            .unwrap_or_else(|| "-".to_string());
+        out.push_str(&format!(
+            "   {:<28} {:>12}  {:<22} {}\n",
+            artifact.name,
+            CommandExecutor::format_file_size(artifact.size),
+            delta,
+            budget_status(artifact, budget)
+        ));
+    }
+    if let Some(tests) = tests {
+        out.push_str(&format!("🧪 Tests: {}\n", tests_line(tests)));
+    }
+    out
+}
+
+fn markdown_summary(
+    artifacts: &[Artifact],
+    budget: &Budget,
+    tests: Option<&TestResults>,
+) -> String {
+    let mut out = String::from("### 📦 Wasm artifacts\n\n");
+    out.push_str("| Artifact | Size | Δ vs base | Budget |\n|---|---:|---:|---|\n");
+    for artifact in artifacts {
+        let delta = artifact
+            .delta()
+            .map(|delta| format_delta(delta, artifact.growth_percent()))
+            .unwrap_or_else(|| "-".to_string());
+        out.push_str(&format!(
+            "| `{}` | {} | {delta} | {} |\n",
+            artifact.path,
+            CommandExecutor::format_file_size(artifact.size),
+            budget_status(artifact, budget)
+        ));
+    }
+    if let Some(tests) = tests {
+        out.push_str(&format!("\n**Tests:** {}\n", tests_line(tests)));
+        for failure in &tests.failures {
+            out.push_str(&format!("- ❌ `{}`: {}\n", failure.name, failure.message));
+        }
+    }
+    out.push('\n');
+    out
+}
+
+/// GitHub Actions workflow commands, one annotation per line
+fn github_commands(annotations: &[Annotation]) -> String {
+    let escape_data = |value: &str| {
+        value
+            .replace('%', "%25")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A")
+    };
+    let escape_property = |value: &str| escape_data(value).replace(':', "%3A").replace(',', "%2C");
+
+    let mut out = String::new();
+    for annotation in annotations {
+        let command = match annotation.severity {
+            Severity::Notice => "notice",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        let mut properties = Vec::new();
+        if let Some(file) = &annotation.file {
+            properties.push(format!(
+                "file={}",
+                escape_property(file.trim_start_matches("./"))
+            ));
+        }
+        if let Some(line) = annotation.line {
+            properties.push(format!("line={line}"));
+        }
+        properties.push(format!("title={}", escape_property(&annotation.title)));
+        out.push_str(&format!(
+            "::{command} {}::{}\n",
+            properties.join(","),
+            escape_data(&annotation.message)
+        ));
+    }
+    out
+}
+
+/// GitLab Code Quality report (JSON array of findings)
+fn gitlab_code_quality(annotations: &[Annotation]) -> String {
+    let findings: Vec<serde_json::Value> = annotations
+        .iter()
+        .map(|annotation| {
+            let path = annotation
+                .file
+                .as_deref()
+                .unwrap_or("wasmrun.toml")
+                .trim_start_matches("./");
+            let mut hasher = Sha256::new();
+            hasher.update(annotation.title.as_bytes());
+            hasher.update(path.as_bytes());
+            serde_json::json!({
+                "description": format!("{}: {}", annotation.title, annotation.message),
+                "check_name": annotation.title,
+                "fingerprint": format!("{:x}", hasher.finalize()),
+                "severity": match annotation.severity {
+                    Severity::Notice => "info",
+                    Severity::Warning => "minor",
+                    Severity::Error => "major",
+                },
+                "location": {
+                    "path": path,
+                    "lines": { "begin": annotation.line.unwrap_or(1) }
+                }
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&findings).unwrap_or_else(|_| "[]".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact(size: u64, base: Option<u64>) -> Artifact {
+        Artifact {
+            name: "app.wasm".to_string(),
+            path: "./dist/app.wasm".to_string(),
+            size,
+            base,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_budget_and_growth_annotations() {
+        let budget = Budget {
+            max_size: Some(1000),
+            max_growth_percent: Some(5.0),
+        };
+
+        let within = annotate(&[artifact(900, Some(880))], &budget, None);
+        assert_eq!(within.len(), 1);
+        assert_eq!(within[0].severity, Severity::Notice);
+        assert!(within[0].message.contains("vs base"));
+
+        let over = annotate(&[artifact(1200, Some(1000))], &budget, None);
+        let titles: Vec<&str> = over.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            vec!["Wasm size budget exceeded", "Wasm size growth exceeded"]
+        );
+        assert!(over.iter().all(|a| a.severity == Severity::Error));
+    }
+
+    #[test]
+    fn test_parse_junit() {
+        let xml = r#"<?xml version="1.0"?>
+<testsuites>
+  <testsuite name="unit" tests="3">
+    <testcase classname="math" name="adds" time="0.1"/>
+    <testcase classname="math" name="divides" file="src/math.rs" line="42">
+      <failure message="expected 2, got &quot;3&quot;">trace</failure>
+    </testcase>
+    <testcase classname="math" name="later"><skipped/></testcase>
+  </testsuite>
+</testsuites>"#;
+        let results = parse_junit(xml);
+        assert_eq!(results.total, 3);
+        assert_eq!(results.skipped, 1);
+        assert_eq!(
+            results.failures,
+            vec![TestFailure {
+                name: "math::divides".to_string(),
+                file: Some("src/math.rs".to_string()),
+                line: Some(42),
+                message: "expected 2, got \"3\"".to_string(),
+            }]
+        );
+        assert_eq!(tests_line(&results), "1 passed, 1 failed, 1 skipped");
+    }
+
+    #[test]
+    fn test_github_commands_escape_values() {
+        let annotations = vec![Annotation {
+            severity: Severity::Error,
+            title: "Test failed: a::b".to_string(),
+            file: Some("./src/a,b.rs".to_string()),
+            line: Some(3),
+            message: "50% done\nnext".to_string(),
+        }];
+        assert_eq!(
+            github_commands(&annotations),
+            "::error file=src/a%2Cb.rs,line=3,title=Test failed%3A a%3A%3Ab::50%25 done%0Anext\n"
+        );
+    }
+
+    #[test]
+    fn test_gitlab_code_quality_report() {
+        let annotations = annotate(
+            &[artifact(2048, None)],
+            &Budget {
+                max_size: Some(1024),
+                max_growth_percent: None,
+            },
+            None,
+        );
+        let report: serde_json::Value =
+            serde_json::from_str(&gitlab_code_quality(&annotations)).unwrap();
+        assert_eq!(report[0]["severity"], "major");
+        assert_eq!(report[0]["location"]["path"], "dist/app.wasm");
+        assert_eq!(report[0]["fingerprint"].as_str().unwrap().len(), 64);
+    }
+
+    #[test]
+    fn test_format_delta() {
+        assert_eq!(format_delta(-2048, Some(-50.0)), "-2.00 KB / -50.0%");
+        assert_eq!(format_delta(10, None), "+10 bytes");
+    }
+}
@@ -1,14 +1,20 @@
 use crate::compiler;
 use crate::error::Result;
 use crate::ui::print_clean_info;
-use crate::utils::PathResolver;
+use crate::utils::{DryRun, PathResolver};
+use std::path::Path;
 
 /// Handle clean command
 pub fn handle_clean_command(
     path: &Option<String>,
     positional_path: &Option<String>,
     all: bool,
+    dry_run: bool,
 ) -> Result<()> {
+    if dry_run {
+        return plan_clean(path, positional_path, all);
+    }
+
     println!("🧹 Cleaning wasmrun temporary directories...");
     PathResolver::cleanup_all_temp_directories()?;
 
@@ -37,6 +43,53 @@ pub fn handle_clean_command(
     }
 }
 
+/// Print what `wasmrun clean` would remove
+fn plan_clean(path: &Option<String>, positional_path: &Option<String>, all: bool) -> Result<()> {
+    let mut plan = DryRun::new();
+    for stale in PathResolver::stale_temp_paths() {
+        plan.remove(stale);
+    }
+
+    if all {
+        let project_path = PathResolver::resolve_input_path(positional_path.clone(), path.clone());
+        PathResolver::validate_directory_exists(&project_path)?;
+        let artifacts = match compiler::detect_project_language(&project_path) {
+            compiler::ProjectLanguage::C => c_artifacts(&project_path)?,
+            compiler::ProjectLanguage::Asc => asc_artifacts(&project_path),
+            language => {
+                plan.note(format!(
+                    "leave the artifacts of the {language:?} project in place"
+                ));
+                Vec::new()
+            }
+        };
+        for artifact in artifacts {
+            plan.remove(artifact);
+        }
+    }
+
+    plan.print(if all {
+        "wasmrun clean --all"
+    } else {
+        "wasmrun clean"
+    });
+    Ok(())
+}
+
+/// The modules a C build left in the project
+fn c_artifacts(project_path: &str) -> Result<Vec<String>> {
+    PathResolver::find_files_with_extension(project_path, "wasm")
+}
+
+/// The output directories of an AssemblyScript build that exist
+fn asc_artifacts(project_path: &str) -> Vec<String> {
+    ["build", "dist"]
+        .into_iter()
+        .map(|dir| PathResolver::join_paths(project_path, dir))
+        .filter(|dir| Path::new(dir).exists())
+        .collect()
+}
+
 // fn clean_rust_project(project_path: &str) -> Result<()> {
 //     let target_dir = PathResolver::join_paths(project_path, "target");
 //     let pkg_dir = PathResolver::join_paths(project_path, "pkg");
@@ -81,8 +134,7 @@ pub fn handle_clean_command(
 fn clean_c_project(project_path: &str) -> Result<()> {
     let mut cleaned = Vec::new();
 
-    let wasm_files = PathResolver::find_files_with_extension(project_path, "wasm")?;
-    for file in wasm_files {
+    for file in c_artifacts(project_path)? {
         PathResolver::remove_file(&file)?;
         cleaned.push(PathResolver::get_filename(&file)?);
     }
@@ -97,16 +149,11 @@ fn clean_c_project(project_path: &str) -> Result<()> {
 }
 
 fn clean_asc_project(project_path: &str) -> Result<()> {
-    let build_dir = PathResolver::join_paths(project_path, "build");
-    let dist_dir = PathResolver::join_paths(project_path, "dist");
-
     let mut cleaned = Vec::new();
 
-    for dir in [&build_dir, &dist_dir] {
-        if std::path::Path::new(dir).exists() {
-            PathResolver::remove_dir_all(dir)?;
-            cleaned.push(PathResolver::get_filename(dir)?);
-        }
+    for dir in asc_artifacts(project_path) {
+        PathResolver::remove_dir_all(&dir)?;
+        cleaned.push(PathResolver::get_filename(&dir)?);
     }
 
     if cleaned.is_empty() {
@@ -0,0 +1,211 @@
+//! Side-by-side comparison of two wasm builds
+//!
+//! By default both builds are served next to each other. With `--bench` they
+//! are run natively instead, every function instrumented, and the time spent
+//! in each function is compared to find where a build got slower; `--trace`
+//! also writes every timed call as a Chrome trace.
+
+use crate::error::{Result, ServerError, WasmrunError};
+use crate::server::compare::{serve_comparison, CompareSide};
+use crate::server::urls::ServerUrls;
+use crate::server::utils::ServerUtils;
+use crate::utils::wasm_bench::{self, BenchProfile, TimingDelta};
+use crate::utils::{write_atomic, CommandExecutor, PathResolver};
+use crate::warn_println;
+use std::path::Path;
+use std::time::Duration;
+
+/// Functions listed in the timing comparison; the rest are counted
+const MAX_ROWS: usize = 20;
+
+/// Handle compare command
+pub fn handle_compare_command(old: &str, new: &str, port: u16, serve: bool) -> Result<()> {
+    PathResolver::validate_wasm_file(old)?;
+    PathResolver::validate_wasm_file(new)?;
+
+    crate::server::replace_named_instance();
+    let port = ServerUtils::handle_port_conflict(port)?;
+    let old = CompareSide::new("a", "Old", old);
+    let new = CompareSide::new("b", "New", new);
+
+    println!("⚖️  Comparing WebAssembly builds");
+    for side in [&old, &new] {
+        let size = std::fs::metadata(&side.wasm_path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        println!(
+            "   {:<4} {} ({})",
+            side.label,
+            side.wasm_path,
+            CommandExecutor::format_file_size(size)
+        );
+    }
+    println!("🌐 Serving at:");
+    for url in ServerUrls::for_port(port).urls {
+        println!(
+            "   {} {:<9} {}",
+            url.kind.icon(),
+            format!("{}:", url.kind.label()),
+            url.link(&url.display)
+        );
+    }
+
+    serve_comparison(old, new, port, serve)
+        .map_err(|e| WasmrunError::Server(ServerError::startup_failed(port, e)))
+}
+
+/// Handle compare command with `--bench`: time `export` in both builds
+pub fn handle_compare_bench_command(
+    old: &str,
+    new: &str,
+    export: &str,
+    args: &[String],
+    iterations: u32,
+    trace: Option<&str>,
+) -> Result<()> {
+    PathResolver::validate_wasm_file(old)?;
+    PathResolver::validate_wasm_file(new)?;
+
+    println!("⏱️  Benchmarking `{export}` x{iterations} in both builds");
+    let run = |label: &str, path: &str| -> Result<BenchProfile> {
+        let wasm = std::fs::read(path).map_err(|e| WasmrunError::add_context(path, e))?;
+        let profile = wasm_bench::bench(&wasm, export, args, iterations, trace.is_some())
+            .map_err(|reason| WasmrunError::from(format!("{label} build {path}: {reason}")))?;
+        println!(
+            "   {label:<4} {path}: {} per call",
+            format_time(profile.per_iteration(profile.total))
+        );
+        Ok(profile)
+    };
+    let labels = [format!("Old {old}"), format!("New {new}")];
+    let old = run("Old", old)?;
+    let new = run("New", new)?;
+
+    let deltas = wasm_bench::compare(&old, &new);
+    println!();
+    println!(
+        "   {:<32} {:>10} {:>10} {:>10} {:>8}  calls (all runs)",
+        "function", "old", "new", "delta", "%"
+    );
+    for delta in deltas.iter().take(MAX_ROWS) {
+        println!("   {}", format_row(delta));
+    }
+    if deltas.len() > MAX_ROWS {
+        println!("   … {} more functions", deltas.len() - MAX_ROWS);
+    }
+
+    println!();
+    match deltas.first().filter(|delta| delta.change() > 0.0) {
+        Some(slowest) => println!(
+            "🐢 Biggest slowdown: {} ({})",
+            slowest.name,
+            format_change(slowest)
+        ),
+        None => println!("🚀 No function got slower"),
+    }
+    println!("💡 Self time per call to `{export}`, without callees; instrumentation adds to it");
+
+    if let Some(trace) = trace {
+        write_trace(trace, &[(&labels[0], &old), (&labels[1], &new)])?;
+    }
+    Ok(())
+}
+
+/// Write the calls traced in the builds to `path` for chrome://tracing or Perfetto
+fn write_trace(path: &str, profiles: &[(&str, &BenchProfile)]) -> Result<()> {
+    let events = wasm_bench::chrome_trace(profiles);
+    write_atomic(Path::new(path), events.to_string().as_bytes())
+        .map_err(|e| WasmrunError::add_context(format!("Writing {path}"), e))?;
+    let traced: usize = profiles
+        .iter()
+        .map(|(_, profile)| profile.trace.len())
+        .sum();
+    println!("📋 Wrote {traced} calls to {path}; open it in chrome://tracing or ui.perfetto.dev");
+    let untraced: u64 = profiles.iter().map(|(_, profile)| profile.untraced).sum();
+    if untraced > 0 {
+        warn_println!(
+            "Left {untraced} calls out of the trace, past {} per build",
+            wasm_bench::MAX_TRACED_CALLS
+        );
+    }
+    Ok(())
+}
+
+/// A duration at a precision that suits it
+fn format_time(time: Duration) -> String {
+    let micros = time.as_secs_f64() * 1e6;
+    if micros >= 1000.0 {
+        format!("{:.2}ms", micros / 1000.0)
+    } else {
+        format!("{micros:.1}µs")
+    }
+}
+
+/// New minus old time, signed
+fn format_delta(delta: &TimingDelta) -> String {
+    let sign = if delta.change() < 0.0 { "-" } else { "+" };
+    let change = Duration::from_secs_f64(delta.change().abs());
+    format!("{sign}{}", format_time(change))
+}
+
+fn format_change(delta: &TimingDelta) -> String {
+    match delta.percent() {
+        Some(percent) => format!("{}, {percent:+.0}%", format_delta(delta)),
+        None => format_delta(delta),
+    }
+}
+
+fn format_row(delta: &TimingDelta) -> String {
+    let time = |time: Option<Duration>| time.map_or_else(|| "-".to_string(), format_time);
+    let name: String = if delta.name.chars().count() > 32 {
+        let mut name: String = delta.name.chars().take(31).collect();
+        name.push('…');
+        name
+    } else {
+        delta.name.clone()
+    };
+    format!(
+        "{name:<32} {:>10} {:>10} {:>10} {:>8}  {} → {}",
+        time(delta.old),
+        time(delta.new),
+        format_delta(delta),
+        delta
+            .percent()
+            .map(|percent| format!("{percent:+.0}%"))
+            .unwrap_or_else(|| match (delta.old, delta.new) {
+                (None, _) => "new".to_string(),
+                (_, None) => "gone".to_string(),
+                _ => "-".to_string(),
+            }),
+        delta.old_calls,
+        delta.new_calls
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_show_the_change_per_call() {
+        let delta = TimingDelta {
+            name: "render".to_string(),
+            old: Some(Duration::from_micros(800)),
+            new: Some(Duration::from_micros(1200)),
+            old_calls: 10,
+            new_calls: 12,
+        };
+        assert_eq!(format_change(&delta), "+400.0µs, +50%");
+        assert_eq!(
+            format_row(&delta),
+            format!(
+                "render{} {:>10} {:>10} {:>10} {:>8}  10 → 12",
+                " ".repeat(26),
+                "800.0µs",
+                "1.20ms",
+                "+400.0µs",
+                "+50%"
+            )
+        );
+    }
+}
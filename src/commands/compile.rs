@@ -1,37 +1,125 @@
 //! Compilation command implementation
 
-use crate::compiler::builder::{BuildConfig, BuilderFactory, OptimizationLevel, TargetType};
-use crate::compiler::{detect_operating_system, detect_project_language, get_missing_tools};
+use crate::compiler::builder::{
+    BuildConfig, BuildResult, BuilderFactory, OptimizationLevel, TargetType, WasmBuilder,
+};
+use crate::compiler::diagnostics::{self, Diagnostic, Level};
+use crate::compiler::{
+    cargo_features, cargo_target, configured_builder, detect_operating_system,
+    detect_project_language, get_missing_tools,
+};
+use crate::config::active_settings;
 use crate::error::{Result, WasmrunError};
+use crate::plugin::hooks as plugin_hooks;
 use crate::plugin::manager::PluginManager;
-use crate::utils::PathResolver;
+use crate::registry::record_build_result;
+use crate::self_profile::{self, Subsystem};
+use crate::server::build_status;
+use crate::utils::wasm_optimize::OptLevel;
+use crate::utils::{record_build_outputs, typescript, write_atomic, DryRun, PathResolver};
 use std::path::Path;
+use std::time::Instant;
 
 pub fn handle_compile_command(
     project_path: String,
     output_dir: String,
     optimization_level: OptimizationLevel,
     verbose: bool,
+    optimize: Option<OptLevel>,
+    sarif: Option<&str>,
 ) -> Result<()> {
-    run_compile(project_path, output_dir, optimization_level, verbose)
+    let result = match sarif {
+        Some(sarif) => {
+            let report_path = project_path.clone();
+            with_sarif_report(&report_path, sarif, || {
+                run_compile(project_path, output_dir, optimization_level, verbose)
+            })?
+        }
+        None => run_compile(project_path, output_dir, optimization_level, verbose)?,
+    };
+    if let Some(level) = optimize {
+        let wasm_path = Path::new(&result.wasm_path);
+        if wasm_path.is_file() {
+            super::optimize_module(wasm_path, wasm_path, level)?;
+        } else {
+            println!(
+                "⚠️  {} is not a single module; skipping wasm-opt",
+                result.wasm_path
+            );
+        }
+    }
+    Ok(())
 }
 
-pub fn run_compile(
-    project_path: String,
-    output_dir: String,
-    optimization_level: OptimizationLevel,
-    verbose: bool,
-) -> Result<()> {
-    PathResolver::validate_directory_exists(&project_path)?;
-    PathResolver::ensure_output_directory(&output_dir)?;
+/// Run `build` with the compiler's output captured, and write the errors and
+/// warnings in it to `sarif`, failed builds included
+fn with_sarif_report<T>(
+    project_path: &str,
+    sarif: &str,
+    build: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    build_status::begin("compile");
+    let result = build();
+    let mut found = diagnostics::parse(&build_status::output());
+    match &result {
+        Ok(_) => build_status::succeeded(),
+        Err(e) => {
+            build_status::failed(&e.to_string());
+            // A build can fail without a compiler error to point at
+            if !found
+                .iter()
+                .any(|diagnostic| diagnostic.level == Level::Error)
+            {
+                found.push(Diagnostic {
+                    file: None,
+                    line: None,
+                    column: None,
+                    level: Level::Error,
+                    rule: None,
+                    message: e.to_string(),
+                });
+            }
+        }
+    }
 
-    if verbose {
-        println!("🔍 Detecting project type...");
+    let report = diagnostics::to_sarif(&found, Path::new(project_path));
+    let written = write_atomic(Path::new(sarif), format!("{report:#}\n").as_bytes())
+        .map_err(|e| WasmrunError::add_context(format!("Writing {sarif}"), e));
+    if written.is_ok() {
+        let count = |level| {
+            found
+                .iter()
+                .filter(|diagnostic| diagnostic.level == level)
+                .count()
+        };
+        println!(
+            "📋 Wrote {} error(s) and {} warning(s) to {sarif}",
+            count(Level::Error),
+            count(Level::Warning)
+        );
     }
+    // The build's own error matters more than one writing the report
+    let value = result?;
+    written?;
+    Ok(value)
+}
 
-    // Try plugin-based compilation first
-    if let Ok(plugin_manager) = PluginManager::new() {
-        if let Some(plugin) = plugin_manager.find_plugin_for_project(&project_path) {
+/// The builder wasmrun.toml's build.command or build.plugin asks for, else a
+/// plugin that recognises the project, with its name
+fn find_builder(
+    project_path: &str,
+    verbose: bool,
+) -> Result<Option<(String, Box<dyn WasmBuilder>)>> {
+    let selected = configured_builder(&active_settings().build)?;
+    if selected.is_some() {
+        return Ok(selected);
+    }
+    let Ok(plugin_manager) = PluginManager::new() else {
+        return Ok(None);
+    };
+    Ok(plugin_manager
+        .find_plugin_for_project(project_path)
+        .map(|plugin| {
             if verbose {
                 println!(
                     "🔌 Using plugin: {} v{}",
@@ -40,38 +128,155 @@ pub fn run_compile(
                 );
                 println!("📝 Description: {}", plugin.info().description);
             }
+            (plugin.info().name.clone(), plugin.get_builder())
+        }))
+}
 
-            // Check plugin dependencies
-            let builder = plugin.get_builder();
-            let missing_deps = builder.check_dependencies();
-            if !missing_deps.is_empty() {
-                return Err(WasmrunError::from(format!(
-                    "Missing dependencies for {}: {}",
-                    plugin.info().name,
-                    missing_deps.join(", ")
-                )));
-            }
+/// Print what `wasmrun compile` would do, without building or writing anything
+pub fn handle_compile_dry_run(
+    project_path: String,
+    output_dir: String,
+    optimization_level: OptimizationLevel,
+    optimize: Option<OptLevel>,
+    sarif: Option<&str>,
+) -> Result<()> {
+    let mut plan = DryRun::new();
+    plan_build(&mut plan, project_path, output_dir, optimization_level)?;
+    if let Some(level) = optimize {
+        plan.note(format!("optimize the module with wasm-opt {level}"));
+    }
+    if let Some(sarif) = sarif {
+        plan.write(sarif);
+    }
+    plan.print("wasmrun compile");
+    Ok(())
+}
+
+/// Add the steps `run_compile` would take to `plan`: the builder, its
+/// commands and the build hooks
+pub fn plan_build(
+    plan: &mut DryRun,
+    project_path: String,
+    output_dir: String,
+    optimization_level: OptimizationLevel,
+) -> Result<()> {
+    PathResolver::validate_directory_exists(&project_path)?;
+    let (name, builder) = match find_builder(&project_path, false)? {
+        Some(selected) => selected,
+        None => {
+            let language = detect_project_language(&project_path);
+            (
+                format!("{language:?}").to_lowercase(),
+                BuilderFactory::create_builder(&language),
+            )
+        }
+    };
+    // Relative to here rather than to the project, where the commands run
+    let output_dir = std::env::current_dir()
+        .map(|cwd| cwd.join(&output_dir).to_string_lossy().to_string())
+        .unwrap_or(output_dir);
+    let config = BuildConfig {
+        project_path,
+        output_dir,
+        verbose: false,
+        optimization_level,
+        watch: false,
+        target_type: TargetType::Standard,
+        cargo_target: cargo_target::active(),
+        cargo_features: cargo_features::active_features(),
+    };
 
-            let config = BuildConfig {
-                project_path,
-                output_dir,
-                verbose,
-                optimization_level,
-                watch: false,
-                target_type: TargetType::Standard,
-            };
-
-            let result = if verbose {
-                builder
-                    .build_verbose(&config)
-                    .map_err(WasmrunError::Compilation)?
-            } else {
-                builder.build(&config).map_err(WasmrunError::Compilation)?
-            };
-
-            print_compilation_success(&result.wasm_path, &result.js_path, &result.additional_files);
-            return Ok(());
+    plan.note(format!("build with {name} ({})", config.optimization_level));
+    let missing = builder.check_dependencies();
+    if !missing.is_empty() {
+        plan.note(format!("stop: missing {}", missing.join(", ")));
+    }
+    if !Path::new(&config.output_dir).is_dir() {
+        plan.create_dir(&config.output_dir);
+    }
+    let hooks = active_settings().hooks;
+    for hook in hooks.pre_build.iter().flatten() {
+        plan.run(&config.project_path, hook);
+    }
+    let commands = builder.planned_commands(&config);
+    if commands.is_empty() {
+        plan.note(format!(
+            "run the {name} build, whose commands are only known once it runs"
+        ));
+    }
+    for command in commands {
+        plan.run(&config.project_path, command);
+    }
+    for hook in hooks.post_build.iter().flatten() {
+        plan.run(&config.project_path, hook);
+    }
+    Ok(())
+}
+
+pub fn run_compile(
+    project_path: String,
+    output_dir: String,
+    optimization_level: OptimizationLevel,
+    verbose: bool,
+) -> Result<BuildResult> {
+    let _span = self_profile::span(Subsystem::Builds);
+    PathResolver::validate_directory_exists(&project_path)?;
+    PathResolver::ensure_output_directory(&output_dir)?;
+
+    let hooks = active_settings().hooks;
+    hooks
+        .run_pre_build(&project_path)
+        .map_err(WasmrunError::Compilation)?;
+    plugin_hooks::pre_build(&project_path)?;
+
+    if verbose {
+        println!("🔍 Detecting project type...");
+    }
+
+    if let Some((name, builder)) = find_builder(&project_path, verbose)? {
+        // Check plugin dependencies
+        let missing_deps = builder.check_dependencies();
+        if !missing_deps.is_empty() {
+            return Err(WasmrunError::from(format!(
+                "Missing dependencies for {}: {}",
+                name,
+                missing_deps.join(", ")
+            )));
         }
+
+        let config = BuildConfig {
+            project_path,
+            output_dir,
+            verbose,
+            optimization_level,
+            watch: false,
+            target_type: TargetType::Standard,
+            cargo_target: cargo_target::active(),
+            cargo_features: cargo_features::active_features(),
+        };
+
+        let started = Instant::now();
+        let result = if verbose {
+            builder.build_verbose(&config)
+        } else {
+            builder.build(&config)
+        };
+        record_build_result(
+            &config.project_path,
+            builder.language_name(),
+            started,
+            &result,
+        );
+        let mut result = result.map_err(WasmrunError::Compilation)?;
+        typescript::bundle(&mut result);
+
+        hooks
+            .run_post_build(&config.project_path)
+            .map_err(WasmrunError::Compilation)?;
+        plugin_hooks::post_build(&config.project_path, &result.wasm_path)?;
+        record_build_outputs(&result.output_files());
+        print_compilation_success(&result.wasm_path, &result.js_path, &result.additional_files);
+        return Ok(result);
     }
 
     // Fall back to legacy language detection
@@ -101,18 +306,32 @@ pub fn run_compile(
         optimization_level,
         watch: false,
         target_type: TargetType::Standard,
+        cargo_target: cargo_target::active(),
+        cargo_features: cargo_features::active_features(),
     };
 
+    let started = Instant::now();
     let result = if verbose {
-        builder
-            .build_verbose(&config)
-            .map_err(WasmrunError::Compilation)?
+        builder.build_verbose(&config)
     } else {
-        builder.build(&config).map_err(WasmrunError::Compilation)?
+        builder.build(&config)
     };
+    record_build_result(
+        &config.project_path,
+        builder.language_name(),
+        started,
+        &result,
+    );
+    let mut result = result.map_err(WasmrunError::Compilation)?;
+    typescript::bundle(&mut result);
 
+    hooks
+        .run_post_build(&config.project_path)
+        .map_err(WasmrunError::Compilation)?;
+    plugin_hooks::post_build(&config.project_path, &result.wasm_path)?;
+    record_build_outputs(&result.output_files());
     print_compilation_success(&result.wasm_path, &result.js_path, &result.additional_files);
-    Ok(())
+    Ok(result)
 }
 
 fn print_compilation_success(
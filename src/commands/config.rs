@@ -0,0 +1,129 @@
+//! Inspect wasmrun configuration
+
+use crate::cli::ConfigSubcommands;
+use crate::config::effective::{self, Overrides, Source};
+use crate::config::project::{ProjectConfig, PROJECT_CONFIG_FILE};
+use crate::config::WasmrunConfig;
+use crate::error::Result;
+use crate::utils::PathResolver;
+use std::fs;
+use std::path::Path;
+
+/// Handle config subcommands; `overrides` carries the global flags given with the command
+pub fn handle_config_command(
+    subcommand: &ConfigSubcommands,
+    profile: Option<&str>,
+    overrides: Overrides,
+) -> Result<()> {
+    match subcommand {
+        ConfigSubcommands::Show {
+            path,
+            positional_path,
+            resolved,
+            port,
+            base,
+            optimization,
+        } => {
+            let project_path =
+                PathResolver::resolve_input_path(positional_path.clone(), path.clone());
+            if *resolved {
+                let overrides = Overrides {
+                    port: *port,
+                    base: base.clone(),
+                    optimization: optimization.clone(),
+                    ..overrides
+                };
+                show_resolved(&project_path, profile, &overrides)
+            } else {
+                show_files(&project_path)
+            }
+        }
+    }
+}
+
+/// Print the global config and the project's wasmrun.toml as they are on disk
+fn show_files(project_path: &str) -> Result<()> {
+    let global = WasmrunConfig::config_path()?;
+    print_file("Global config", &global);
+    match ProjectConfig::find(project_path) {
+        Some(project) => print_file("Project config", &project),
+        None => println!("\n📄 Project config: no {PROJECT_CONFIG_FILE} for {project_path}"),
+    }
+    println!(
+        "\n💡 Use 'wasmrun config show --resolved' to see the merged values and their sources"
+    );
+    Ok(())
+}
+
+fn print_file(title: &str, path: &Path) {
+    match fs::read_to_string(path) {
+        Ok(content) => {
+            println!("\n📄 {title}: {}\n", path.display());
+            for line in content.trim_end().lines() {
+                println!("   {line}");
+            }
+        }
+        Err(_) => println!("\n📄 {title}: {} (not created yet)", path.display()),
+    }
+}
+
+fn show_resolved(project_path: &str, profile: Option<&str>, overrides: &Overrides) -> Result<()> {
+    let resolved = effective::resolve(project_path, profile, overrides)?;
+
+    println!("⚙️  Effective configuration for {project_path}");
+    println!(
+        "   Global:  {}{}",
+        resolved.global_config.display(),
+        if resolved.global_config_exists {
+            ""
+        } else {
+            " (not created yet)"
+        }
+    );
+    match &resolved.project_config {
+        Some(path) => println!("   Project: {}", path.display()),
+        None => println!("   Project: no {PROJECT_CONFIG_FILE}"),
+    }
+    if let Some(profile) = &resolved.profile {
+        println!("   Profile: {profile}");
+    }
+    println!();
+
+    let rows: Vec<[String; 3]> = resolved
+        .settings
+        .iter()
+        .map(|setting| {
+            [
+                setting.key.clone(),
+                setting
+                    .value
+                    .clone()
+                    .unwrap_or_else(|| "(not set)".to_string()),
+                setting.source.to_string(),
+            ]
+        })
+        .collect();
+    let key_width = rows
+        .iter()
+        .map(|row| row[0].chars().count())
+        .max()
+        .unwrap_or(0);
+    let value_width = rows
+        .iter()
+        .map(|row| row[1].chars().count())
+        .max()
+        .unwrap_or(0);
+    for (row, setting) in rows.iter().zip(&resolved.settings) {
+        let line = format!(
+            "   {:<key_width$}  {:<value_width$}  {}",
+            row[0], row[1], row[2]
+        );
+        // Dim values nobody set, so the ones that were configured stand out
+        if matches!(setting.source, Source::Default | Source::Unset) {
+            println!("\x1b[0;37m{}\x1b[0m", line.trim_end());
+        } else {
+            println!("{}", line.trim_end());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Control a running server over its IPC endpoint
+
+use crate::cli::CtlAction;
+use crate::error::{CommandError, Result, ServerError, WasmrunError};
+use crate::ipc::Endpoint;
+use crate::registry::ServerInstance;
+use crate::server::control::ControlCommand;
+use crate::server::running_instances;
+
+/// Handle ctl command
+pub fn handle_ctl_command(action: &CtlAction, port: Option<u16>) -> Result<()> {
+    let instance = select_instance(running_instances()?, port)?;
+    let command = match action {
+        CtlAction::Reload => ControlCommand::Reload,
+        CtlAction::Rebuild => ControlCommand::Rebuild,
+        CtlAction::Open => ControlCommand::Open,
+        CtlAction::SetHeader { name, value } => ControlCommand::SetHeader {
+            name: name.clone(),
+            value: value.clone(),
+        },
+    };
+
+    let reply: String = Endpoint::for_process(instance.pid)?.request(&command)?;
+    println!("✅ {reply} (port {})", instance.port);
+    Ok(())
+}
+
+/// The server on `port`, or the only running server when no port is given
+fn select_instance(instances: Vec<ServerInstance>, port: Option<u16>) -> Result<ServerInstance> {
+    let mut matching: Vec<ServerInstance> = instances
+        .into_iter()
+        .filter(|instance| port.is_none_or(|port| instance.port == port))
+        .collect();
+
+    match (matching.len(), port) {
+        (0, _) => Err(WasmrunError::Server(ServerError::NotRunning)),
+        (1, _) | (_, Some(_)) => Ok(matching.remove(0)),
+        (_, None) => {
+            let ports: Vec<String> = matching
+                .iter()
+                .map(|instance| instance.port.to_string())
+                .collect();
+            Err(WasmrunError::Command(CommandError::invalid_arguments(
+                format!(
+                    "Several servers are running (ports {}); choose one with --port",
+                    ports.join(", ")
+                ),
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(pid: u32, port: u16) -> ServerInstance {
+        ServerInstance {
+            pid,
+            port,
+            wasm_path: "app.wasm".to_string(),
+            project_path: None,
+            started_at: String::new(),
+            name: None,
+        }
+    }
+
+    #[test]
+    fn test_select_instance() {
+        assert!(select_instance(vec![], None).is_err());
+        assert_eq!(
+            select_instance(vec![instance(1, 8420)], None).unwrap().pid,
+            1
+        );
+
+        let both = vec![instance(1, 8420), instance(2, 3000)];
+        assert!(select_instance(both.clone(), None).is_err());
+        assert_eq!(select_instance(both.clone(), Some(3000)).unwrap().pid, 2);
+        assert!(select_instance(both, Some(9999)).is_err());
+    }
+}
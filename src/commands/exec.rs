@@ -0,0 +1,244 @@
+//! Run WASI modules in the terminal
+//!
+//! `wasmrun exec` runs a WASI (preview 1) command module, or a component
+//! exporting `wasi:cli/run` (WASI preview 2), with an embedded wasmtime
+//! runtime instead of serving it to a browser. The program shares wasmrun's
+//! stdin/stdout/stderr, sees only the directories given with `--dir`, and its
+//! exit code becomes wasmrun's.
+
+use crate::error::{CommandError, Result, WasmError, WasmrunError};
+use crate::utils::PathResolver;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use wasmparser::Parser;
+use wasmtime::component::{Component, ResourceTable};
+use wasmtime::{Engine, Linker, Module, Store};
+use wasmtime_wasi::bindings::sync::Command;
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+use wasmtime_wasi::{DirPerms, FilePerms, I32Exit, IoView, WasiCtx, WasiCtxBuilder, WasiView};
+
+/// Separator between host and guest path in `--dir HOST::GUEST`
+const DIR_SEPARATOR: &str = "::";
+
+/// Handle exec command; returns the module's exit code
+pub fn handle_exec_command(
+    wasm: &str,
+    args: &[String],
+    dirs: &[String],
+    envs: &[String],
+) -> Result<i32> {
+    PathResolver::validate_file_exists(wasm)?;
+    let envs = envs
+        .iter()
+        .map(|env| parse_env(env))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut wasi = WasiCtxBuilder::new();
+    let program = Path::new(wasm).file_name().map_or_else(
+        || wasm.to_string(),
+        |name| name.to_string_lossy().to_string(),
+    );
+    wasi.inherit_stdio().arg(program).args(args).envs(&envs);
+    for dir in dirs {
+        let (host, guest) = parse_dir(dir);
+        PathResolver::validate_directory_exists(host)?;
+        wasi.preopened_dir(host, guest, DirPerms::all(), FilePerms::all())
+            .map_err(|e| WasmrunError::path(format!("Cannot open --dir {host}: {e}")))?;
+    }
+
+    let code = if is_component(wasm) {
+        run_component(wasm, wasi.build())
+    } else {
+        run_module(wasm, wasi.build_p1())
+    };
+    // The module writes through wasmrun's handles; flush before the process exits
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+    code
+}
+
+/// Instantiate the module at `wasm` and call its `_start` export
+fn run_module(wasm: &str, wasi: WasiP1Ctx) -> Result<i32> {
+    let invalid = |reason: String| WasmrunError::Wasm(WasmError::validation_failed(reason));
+
+    let engine = Engine::default();
+    let module = Module::from_file(&engine, wasm).map_err(|e| invalid(format!("{e:#}")))?;
+    let mut linker: Linker<WasiP1Ctx> = Linker::new(&engine);
+    preview1::add_to_linker_sync(&mut linker, |wasi| wasi)
+        .map_err(|e| WasmrunError::from(format!("Failed to set up the WASI runtime: {e:#}")))?;
+
+    let mut store = Store::new(&engine, wasi);
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .map_err(|e| invalid(format!("{e:#}; exec runs WASI preview 1 modules only")))?;
+    let start = instance
+        .get_typed_func::<(), ()>(&mut store, "_start")
+        .map_err(|_| {
+            invalid("module has no _start export; exec runs WASI command modules".to_string())
+        })?;
+
+    match start.call(&mut store, ()) {
+        Ok(()) => Ok(0),
+        Err(e) => match e.downcast_ref::<I32Exit>() {
+            Some(exit) => Ok(exit.0),
+            None => Err(WasmrunError::Wasm(WasmError::Trap {
+                reason: format!("{e:#}"),
+            })),
+        },
+    }
+}
+
+/// Store state for components: the WASI context and the resources it hands out
+struct ComponentState {
+    ctx: WasiCtx,
+    table: ResourceTable,
+}
+
+impl IoView for ComponentState {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+}
+
+impl WasiView for ComponentState {
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.ctx
+    }
+}
+
+/// Whether `wasm` starts with the component header rather than the core module one
+fn is_component(wasm: &str) -> bool {
+    let mut header = [0; 8];
+    File::open(wasm)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok()
+        && Parser::is_component(&header)
+}
+
+/// Instantiate the component at `wasm` and call its `wasi:cli/run` export
+fn run_component(wasm: &str, wasi: WasiCtx) -> Result<i32> {
+    let invalid = |reason: String| WasmrunError::Wasm(WasmError::validation_failed(reason));
+
+    let engine = Engine::default();
+    let component = Component::from_file(&engine, wasm).map_err(|e| invalid(format!("{e:#}")))?;
+    let mut linker = wasmtime::component::Linker::new(&engine);
+    wasmtime_wasi::add_to_linker_sync(&mut linker)
+        .map_err(|e| WasmrunError::from(format!("Failed to set up the WASI runtime: {e:#}")))?;
+
+    let state = ComponentState {
+        ctx: wasi,
+        table: ResourceTable::new(),
+    };
+    let mut store = Store::new(&engine, state);
+    let command = Command::instantiate(&mut store, &component, &linker).map_err(|e| {
+        invalid(format!(
+            "{e:#}; exec runs components that export wasi:cli/run and import only WASI 0.2"
+        ))
+    })?;
+
+    match command.wasi_cli_run().call_run(&mut store) {
+        Ok(Ok(())) => Ok(0),
+        // `wasi:cli/run` only reports failure, without a code
+        Ok(Err(())) => Ok(1),
+        Err(e) => match e.downcast_ref::<I32Exit>() {
+            Some(exit) => Ok(exit.0),
+            None => Err(WasmrunError::Wasm(WasmError::Trap {
+                reason: format!("{e:#}"),
+            })),
+        },
+    }
+}
+
+/// `HOST` is visible to the module under the same path, `HOST::GUEST` under `GUEST`
+fn parse_dir(dir: &str) -> (&str, &str) {
+    dir.split_once(DIR_SEPARATOR).unwrap_or((dir, dir))
+}
+
+fn parse_env(env: &str) -> Result<(String, String)> {
+    match env.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(WasmrunError::Command(CommandError::invalid_arguments(
+            format!("--env '{env}' must be KEY=VALUE"),
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use tempfile::tempdir;
+
+    /// Exits with the length of its first argument, or traps when run without one
+    const ARGS_MODULE: &str = r#"(module
+        (import "wasi_snapshot_preview1" "args_sizes_get" (func $sizes (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $exit (param i32)))
+        (memory (export "memory") 1)
+        (func (export "_start")
+            (drop (call $sizes (i32.const 0) (i32.const 4)))
+            (if (i32.lt_u (i32.load (i32.const 0)) (i32.const 2)) (then unreachable))
+            ;; buffer size minus "args.wat\0", minus the terminating NUL of the argument
+            (call $exit (i32.sub (i32.load (i32.const 4)) (i32.const 10)))))"#;
+
+    fn write_module(dir: &Path) -> String {
+        let path = dir.join("args.wat");
+        std::fs::write(&path, ARGS_MODULE).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_exit_code_and_args_reach_the_module() {
+        let dir = tempdir().unwrap();
+        let wasm = write_module(dir.path());
+        let code = handle_exec_command(&wasm, &["hello".to_string()], &[], &[]).unwrap();
+        assert_eq!(code, 5);
+    }
+
+    #[test]
+    fn test_trap_is_reported() {
+        let dir = tempdir().unwrap();
+        let wasm = write_module(dir.path());
+        let error = handle_exec_command(&wasm, &[], &[], &[]).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::WASM_TRAP);
+    }
+
+    /// A `wasi:cli/run` command that fails when its core function returns 1
+    fn write_command(dir: &Path, status: u8) -> String {
+        let path = dir.join(format!("command-{status}.wasm"));
+        let component = wat::parse_str(format!(
+            r#"(component
+                (core module $m (func (export "run") (result i32) i32.const {status}))
+                (core instance $i (instantiate $m))
+                (func $run (result (result)) (canon lift (core func $i "run")))
+                (instance $cli (export "run" (func $run)))
+                (export "wasi:cli/run@0.2.0" (instance $cli)))"#
+        ))
+        .unwrap();
+        std::fs::write(&path, component).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_component_command() {
+        let dir = tempdir().unwrap();
+        let success = write_command(dir.path(), 0);
+        assert!(is_component(&success));
+        assert_eq!(handle_exec_command(&success, &[], &[], &[]).unwrap(), 0);
+        let failure = write_command(dir.path(), 1);
+        assert_eq!(handle_exec_command(&failure, &[], &[], &[]).unwrap(), 1);
+        assert!(!is_component(&write_module(dir.path())));
+    }
+
+    #[test]
+    fn test_parse_dir_and_env() {
+        assert_eq!(parse_dir("./data"), ("./data", "./data"));
+        assert_eq!(parse_dir("/tmp/out::/out"), ("/tmp/out", "/out"));
+        assert_eq!(
+            parse_env("MODE=fast=1").unwrap(),
+            ("MODE".to_string(), "fast=1".to_string())
+        );
+        assert!(parse_env("=x").is_err());
+        assert!(parse_env("MODE").is_err());
+    }
+}
@@ -0,0 +1,60 @@
+//! Error code catalog
+
+use crate::error::{CommandError, ErrorCode, Result, WasmrunError};
+
+/// Handle explain command: describe one error code, or list the whole catalog
+pub fn handle_explain_command(code: &Option<String>) -> Result<()> {
+    let Some(code) = code else {
+        println!("📖 Wasmrun error codes\n");
+        println!("  {:<7} {:<5} Description", "Code", "Exit");
+        for error_code in ErrorCode::ALL {
+            print_code(error_code);
+        }
+        return Ok(());
+    };
+
+    let error_code = find_code(code).ok_or_else(|| {
+        WasmrunError::Command(CommandError::invalid_arguments(format!(
+            "Unknown error code: {code}"
+        )))
+    })?;
+    println!("  {:<7} {:<5} Description", "Code", "Exit");
+    print_code(error_code);
+    Ok(())
+}
+
+/// Look up a code given as `E0302`, `e0302` or `302`
+fn find_code(code: &str) -> Option<&'static ErrorCode> {
+    let number: u16 = code.trim_start_matches(['E', 'e']).parse().ok()?;
+    ErrorCode::ALL
+        .iter()
+        .find(|error_code| error_code.number == number)
+}
+
+fn print_code(error_code: &ErrorCode) {
+    println!(
+        "  {:<7} {:<5} {}",
+        error_code.to_string(),
+        error_code.exit_code(),
+        error_code.summary
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_code() {
+        assert_eq!(find_code("E0302"), Some(&ErrorCode::BUILD_FAILED));
+        assert_eq!(find_code("e401"), Some(&ErrorCode::PORT_IN_USE));
+        assert_eq!(find_code("E9999"), None);
+        assert_eq!(find_code("build"), None);
+    }
+
+    #[test]
+    fn test_explain_unknown_code_is_usage_error() {
+        let error = handle_explain_command(&Some("E9999".to_string())).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::INVALID_ARGUMENTS);
+    }
+}
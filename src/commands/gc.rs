@@ -0,0 +1,68 @@
+//! Workspace and artifact store garbage collection
+
+use crate::config::WasmrunConfig;
+use crate::error::Result;
+use crate::utils::{collect_garbage, list_workspaces, ArtifactStore, CommandExecutor};
+use std::path::Path;
+
+/// Handle gc command
+pub fn handle_gc_command(max_size: Option<u64>, all: bool) -> Result<()> {
+    let config = WasmrunConfig::load_or_default()?;
+    let cache_dir = WasmrunConfig::cache_dir()?;
+
+    let max_bytes = if all {
+        0
+    } else {
+        max_size.unwrap_or(config.settings.workspace_max_size_mb) * 1024 * 1024
+    };
+
+    let workspaces = list_workspaces(&cache_dir);
+    if workspaces.is_empty() {
+        println!("✨ No cached workspaces in {}", cache_dir.display());
+    } else {
+        collect_workspaces(&cache_dir, max_bytes)?;
+    }
+    // After the workspaces, whose outputs may have been the last links to objects
+    collect_store(&cache_dir, max_bytes)
+}
+
+fn collect_workspaces(cache_dir: &Path, max_bytes: u64) -> Result<()> {
+    println!(
+        "🧹 Collecting workspaces in {} (limit: {})",
+        cache_dir.display(),
+        CommandExecutor::format_file_size(max_bytes)
+    );
+
+    let report = collect_garbage(cache_dir, max_bytes, None)?;
+    for path in &report.removed {
+        println!("🗑️  Removed workspace: {}", path.display());
+    }
+
+    if report.removed.is_empty() {
+        println!(
+            "✅ Workspaces use {}, nothing to remove",
+            CommandExecutor::format_file_size(report.remaining_bytes)
+        );
+    } else {
+        println!(
+            "✅ Freed {} ({} remaining)",
+            CommandExecutor::format_file_size(report.freed_bytes),
+            CommandExecutor::format_file_size(report.remaining_bytes)
+        );
+    }
+
+    Ok(())
+}
+
+fn collect_store(cache_dir: &Path, max_bytes: u64) -> Result<()> {
+    let store = ArtifactStore::open_in(cache_dir)?;
+    let report = store.collect_garbage(max_bytes)?;
+    if report.removed > 0 {
+        println!(
+            "🗑️  Removed {} least recently used stored artifact(s), freed {}",
+            report.removed,
+            CommandExecutor::format_file_size(report.freed_bytes)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,51 @@
+//! Serve the builds of a wasmrun server on another machine as they happen
+
+use crate::config::WasmrunConfig;
+use crate::error::{Result, ServerError, WasmrunError};
+use crate::server::cluster::Mirror;
+use crate::server::urls::ServerUrls;
+use crate::server::utils::ServerUtils;
+use crate::server::wasm;
+use std::fs;
+
+/// Handle join command
+pub fn handle_join_command(url: &str, port: u16, serve: bool) -> Result<()> {
+    crate::offline::require_network(format!("Joining {url}"))?;
+    crate::server::replace_named_instance();
+    let port = ServerUtils::handle_port_conflict(port)?;
+
+    // A fresh copy per port, so two viewers on one machine don't share files
+    let dir = WasmrunConfig::cache_dir()?
+        .join("join")
+        .join(port.to_string());
+    if dir.exists() {
+        fs::remove_dir_all(&dir)
+            .map_err(|e| WasmrunError::add_context(format!("Clearing {}", dir.display()), e))?;
+    }
+    let mirror = Mirror::connect(url, &dir)?;
+    let (wasm_path, wasm_url) = mirror.wasm();
+    let wasm_path = wasm_path.to_string_lossy().to_string();
+    let wasm_url = wasm_url.to_string();
+    let js_path = mirror.js().map(|js| js.to_string_lossy().to_string());
+
+    println!("📡 Following builds of {}", mirror.host());
+    println!("   Module: {wasm_url}");
+    println!("🌐 Serving at:");
+    for url in ServerUrls::for_port(port).urls {
+        println!(
+            "   {} {:<9} {}",
+            url.kind.icon(),
+            format!("{}:", url.kind.label()),
+            url.link(&url.display)
+        );
+    }
+
+    mirror.follow();
+    let served = match &js_path {
+        Some(js_path) => {
+            wasm::serve_wasm_bindgen_files(&wasm_path, js_path, port, &wasm_url, serve)
+        }
+        None => wasm::serve_wasm_file(&wasm_path, port, &wasm_url, serve),
+    };
+    served.map_err(|e| WasmrunError::Server(ServerError::startup_failed(port, e)))
+}
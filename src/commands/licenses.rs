@@ -0,0 +1,471 @@
+//! Third-party license report for wasm builds
+//!
+//! Wasm binaries shipped to browsers contain their dependencies, which often
+//! require attribution. For Rust projects the dependency graph comes from
+//! `cargo metadata` (filtered to the wasm target, without dev and build
+//! dependencies); for Go projects from `go list -m`, with licenses detected
+//! from each module's license file. `wasmrun bundle` puts the Markdown report
+//! of such projects into the bundle as [`BUNDLE_FILE`].
+
+use crate::compiler::{detect_project_language, ProjectLanguage};
+use crate::error::{CompilationError, Result, WasmrunError};
+use crate::utils::PathResolver;
+use crate::warn_println;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Label for dependencies whose license could not be determined
+const UNKNOWN_LICENSE: &str = "Unknown";
+
+/// The Markdown report in a bundle, next to the module it describes
+pub const BUNDLE_FILE: &str = "THIRD_PARTY_LICENSES.md";
+
+/// A third-party package compiled into the wasm binary
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Dependency {
+    pub name: String,
+    pub version: String,
+    pub license: Option<String>,
+    /// Where the package came from: crates.io, a git URL, a local path or a Go module proxy
+    pub source: String,
+    pub repository: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    project: &'a str,
+    language: &'a str,
+    dependencies: &'a [Dependency],
+}
+
+/// Handle licenses command
+pub fn handle_licenses_command(
+    path: &Option<String>,
+    positional_path: &Option<String>,
+    output: &Option<String>,
+    format: &Option<String>,
+) -> Result<()> {
+    let project_path = PathResolver::resolve_input_path(positional_path.clone(), path.clone());
+    PathResolver::validate_directory_exists(&project_path)?;
+
+    let (language, dependencies) = match detect_project_language(&project_path) {
+        ProjectLanguage::Rust => ("Rust", rust_dependencies(&project_path)?),
+        ProjectLanguage::Go => ("Go", go_dependencies(&project_path)?),
+        other => {
+            return Err(WasmrunError::Compilation(
+                CompilationError::UnsupportedLanguage {
+                    language: format!("{other:?} (license reports support Rust and Go projects)"),
+                },
+            ))
+        }
+    };
+
+    let project = project_name(&project_path);
+    let format = format.as_deref().unwrap_or_else(|| match output {
+        Some(output) if output.ends_with(".json") => "json",
+        _ => "markdown",
+    });
+    let report = if format == "json" {
+        let report = Report {
+            project: &project,
+            language,
+            dependencies: &dependencies,
+        };
+        serde_json::to_string_pretty(&report)
+            .map_err(|e| WasmrunError::add_context("Encoding license report", e))?
+    } else {
+        render_markdown(&project, &dependencies)
+    };
+
+    match output {
+        Some(output) => {
+            fs::write(output, report).map_err(|e| {
+                WasmrunError::add_context(format!("Writing license report {output}"), e)
+            })?;
+            println!(
+                "📜 Wrote licenses for {} dependencies to {output}",
+                dependencies.len()
+            );
+        }
+        None => println!("{report}"),
+    }
+
+    let unknown = dependencies
+        .iter()
+        .filter(|dependency| dependency.license.is_none())
+        .count();
+    if unknown > 0 {
+        warn_println!("{unknown} dependencies have no detectable license; review them manually");
+    }
+    Ok(())
+}
+
+/// Whether the project at `project_path` has a dependency graph to report on
+pub fn has_report(project_path: &str) -> bool {
+    matches!(
+        detect_project_language(project_path),
+        ProjectLanguage::Rust | ProjectLanguage::Go
+    )
+}
+
+/// The Markdown report bundles of the project at `project_path` carry, if it
+/// has one
+pub fn bundle_report(project_path: &str) -> Result<Option<String>> {
+    let dependencies = match detect_project_language(project_path) {
+        ProjectLanguage::Rust => rust_dependencies(project_path)?,
+        ProjectLanguage::Go => go_dependencies(project_path)?,
+        _ => return Ok(None),
+    };
+    Ok(Some(render_markdown(
+        &project_name(project_path),
+        &dependencies,
+    )))
+}
+
+/// The project directory's name, for the report title
+fn project_name(project_path: &str) -> String {
+    Path::new(project_path)
+        .canonicalize()
+        .ok()
+        .and_then(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| project_path.to_string())
+}
+
+fn run_tool(tool: &str, language: &str, args: &[&str], dir: &str) -> Result<String> {
+    let output = Command::new(tool)
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|_| {
+            WasmrunError::Compilation(CompilationError::BuildToolNotFound {
+                tool: tool.to_string(),
+                language: language.to_string(),
+            })
+        })?;
+    if !output.status.success() {
+        return Err(WasmrunError::Compilation(
+            CompilationError::ToolExecutionFailed {
+                tool: format!("{tool} {}", args.join(" ")),
+                reason: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            },
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn rust_dependencies(project_path: &str) -> Result<Vec<Dependency>> {
+    let metadata = run_tool(
+        "cargo",
+        "Rust",
+        &[
+            "metadata",
+            "--format-version",
+            "1",
+            "--filter-platform",
+            "wasm32-unknown-unknown",
+        ],
+        project_path,
+    )?;
+    let metadata: Value = serde_json::from_str(&metadata)
+        .map_err(|e| WasmrunError::add_context("Reading cargo metadata", e))?;
+    Ok(parse_cargo_metadata(&metadata))
+}
+
+/// Packages reachable from the workspace members through normal dependencies
+fn parse_cargo_metadata(metadata: &Value) -> Vec<Dependency> {
+    let members: BTreeSet<&str> = metadata["workspace_members"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+
+    let mut graph: HashMap<&str, Vec<&str>> = HashMap::new();
+    for node in metadata["resolve"]["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+    {
+        let Some(id) = node["id"].as_str() else {
+            continue;
+        };
+        let deps = node["deps"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|dep| {
+                dep["dep_kinds"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .any(|kind| kind["kind"].is_null())
+            })
+            .filter_map(|dep| dep["pkg"].as_str())
+            .collect();
+        graph.insert(id, deps);
+    }
+
+    let mut shipped = BTreeSet::new();
+    let mut queue: VecDeque<&str> = members.iter().copied().collect();
+    while let Some(id) = queue.pop_front() {
+        for dep in graph.get(id).into_iter().flatten() {
+            if !members.contains(dep) && shipped.insert(*dep) {
+                queue.push_back(dep);
+            }
+        }
+    }
+
+    let mut dependencies: Vec<Dependency> = metadata["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|package| {
+            package["id"]
+                .as_str()
+                .is_some_and(|id| shipped.contains(id))
+        })
+        .map(|package| {
+            let text = |key: &str| package[key].as_str().map(str::to_string);
+            Dependency {
+                name: text("name").unwrap_or_default(),
+                version: text("version").unwrap_or_default(),
+                license: text("license")
+                    .or_else(|| text("license_file").map(|file| format!("See {file}"))),
+                source: cargo_source(package["source"].as_str()),
+                repository: text("repository"),
+            }
+        })
+        .collect();
+    dependencies.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    dependencies
+}
+
+fn cargo_source(source: Option<&str>) -> String {
+    match source {
+        None => "path".to_string(),
+        Some(source)
+            if source.contains("github.com/rust-lang/crates.io-index")
+                || source.contains("index.crates.io") =>
+        {
+            "crates.io".to_string()
+        }
+        Some(source) => source
+            .split_once('+')
+            .map(|(kind, url)| match kind {
+                "git" => format!("git {url}"),
+                _ => url.to_string(),
+            })
+            .unwrap_or_else(|| source.to_string()),
+    }
+}
+
+fn go_dependencies(project_path: &str) -> Result<Vec<Dependency>> {
+    let modules = run_tool("go", "Go", &["list", "-m", "-json", "all"], project_path)?;
+    let mut dependencies = Vec::new();
+    for module in serde_json::Deserializer::from_str(&modules).into_iter::<Value>() {
+        let module = module.map_err(|e| WasmrunError::add_context("Reading go list output", e))?;
+        if module["Main"].as_bool() == Some(true) {
+            continue;
+        }
+        let effective = if module["Replace"].is_object() {
+            &module["Replace"]
+        } else {
+            &module
+        };
+        let name = module["Path"].as_str().unwrap_or_default().to_string();
+        let license = effective["Dir"]
+            .as_str()
+            .and_then(|dir| find_license_text(Path::new(dir)))
+            .and_then(|text| detect_license(&text).map(str::to_string));
+        let source = match effective["Path"].as_str() {
+            Some(path) if module["Replace"].is_object() => format!("replaced by {path}"),
+            _ => "go module proxy".to_string(),
+        };
+        dependencies.push(Dependency {
+            repository: Some(format!("https://pkg.go.dev/{name}")),
+            name,
+            version: effective["Version"].as_str().unwrap_or("local").to_string(),
+            license,
+            source,
+        });
+    }
+    Ok(dependencies)
+}
+
+fn find_license_text(dir: &Path) -> Option<String> {
+    let mut candidates: Vec<_> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_uppercase())
+                .is_some_and(|name| name.starts_with("LICENSE") || name.starts_with("COPYING"))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+}
+
+/// SPDX identifier of a common license, from its text
+fn detect_license(text: &str) -> Option<&'static str> {
+    let text = text.to_lowercase();
+    let has = |needle: &str| text.contains(needle);
+
+    if has("apache license") && has("version 2.0") {
+        Some("Apache-2.0")
+    } else if has("permission is hereby granted, free of charge") {
+        Some("MIT")
+    } else if has("redistribution and use in source and binary forms") {
+        Some(if has("neither the name") {
+            "BSD-3-Clause"
+        } else {
+            "BSD-2-Clause"
+        })
+    } else if has("mozilla public license") && has("2.0") {
+        Some("MPL-2.0")
+    } else if has("permission to use, copy, modify, and/or distribute") {
+        Some("ISC")
+    } else if has("gnu lesser general public license") {
+        Some("LGPL")
+    } else if has("gnu general public license") {
+        Some("GPL")
+    } else if has("this is free and unencumbered software released into the public domain") {
+        Some("Unlicense")
+    } else {
+        None
+    }
+}
+
+fn render_markdown(project: &str, dependencies: &[Dependency]) -> String {
+    let mut by_license: BTreeMap<&str, Vec<&Dependency>> = BTreeMap::new();
+    for dependency in dependencies {
+        by_license
+            .entry(dependency.license.as_deref().unwrap_or(UNKNOWN_LICENSE))
+            .or_default()
+            .push(dependency);
+    }
+
+    let mut out = format!(
+        "# Third-party licenses for {project}\n\n{} dependencies are compiled into this WebAssembly module.\n",
+        dependencies.len()
+    );
+    for (license, dependencies) in by_license {
+        out.push_str(&format!(
+            "\n## {license}\n\n| Package | Version | Source |\n|---|---|---|\n"
+        ));
+        for dependency in dependencies {
+            let name = match &dependency.repository {
+                Some(repository) => format!("[{}]({repository})", dependency.name),
+                None => dependency.name.clone(),
+            };
+            out.push_str(&format!(
+                "| {name} | {} | {} |\n",
+                dependency.version, dependency.source
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_bundle_report_only_for_rust_and_go() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("index.html"), "<html>").unwrap();
+        let project = dir.path().to_string_lossy();
+        assert!(!has_report(&project));
+        assert_eq!(bundle_report(&project).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_cargo_metadata_skips_dev_and_build_deps() {
+        let metadata = json!({
+            "workspace_members": ["app 0.1.0 (path+file:///app)"],
+            "packages": [
+                {"id": "app 0.1.0 (path+file:///app)", "name": "app", "version": "0.1.0", "license": "MIT", "source": null},
+                {"id": "serde 1.0.0", "name": "serde", "version": "1.0.0", "license": "MIT OR Apache-2.0",
+                 "source": "registry+https://github.com/rust-lang/crates.io-index", "repository": "https://github.com/serde-rs/serde"},
+                {"id": "itoa 1.0.0", "name": "itoa", "version": "1.0.0", "license": null, "license_file": "LICENSE",
+                 "source": "git+https://github.com/dtolnay/itoa#abc"},
+                {"id": "insta 1.0.0", "name": "insta", "version": "1.0.0", "license": "Apache-2.0", "source": "registry+https://github.com/rust-lang/crates.io-index"},
+                {"id": "cc 1.0.0", "name": "cc", "version": "1.0.0", "license": "MIT", "source": "registry+https://github.com/rust-lang/crates.io-index"}
+            ],
+            "resolve": {"nodes": [
+                {"id": "app 0.1.0 (path+file:///app)", "deps": [
+                    {"pkg": "serde 1.0.0", "dep_kinds": [{"kind": null}]},
+                    {"pkg": "insta 1.0.0", "dep_kinds": [{"kind": "dev"}]},
+                    {"pkg": "cc 1.0.0", "dep_kinds": [{"kind": "build"}]}
+                ]},
+                {"id": "serde 1.0.0", "deps": [{"pkg": "itoa 1.0.0", "dep_kinds": [{"kind": null}]}]},
+                {"id": "itoa 1.0.0", "deps": []},
+                {"id": "insta 1.0.0", "deps": []},
+                {"id": "cc 1.0.0", "deps": []}
+            ]}
+        });
+
+        let dependencies = parse_cargo_metadata(&metadata);
+        let names: Vec<&str> = dependencies.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["itoa", "serde"]);
+        assert_eq!(dependencies[0].license.as_deref(), Some("See LICENSE"));
+        assert_eq!(
+            dependencies[0].source,
+            "git https://github.com/dtolnay/itoa#abc"
+        );
+        assert_eq!(dependencies[1].source, "crates.io");
+    }
+
+    #[test]
+    fn test_detect_license() {
+        assert_eq!(
+            detect_license("Apache License\nVersion 2.0, January 2004"),
+            Some("Apache-2.0")
+        );
+        assert_eq!(
+            detect_license(
+                "MIT License\n\nPermission is hereby granted, free of charge, to any person"
+            ),
+            Some("MIT")
+        );
+        assert_eq!(
+            detect_license(
+                "Redistribution and use in source and binary forms ... Neither the name of Google"
+            ),
+            Some("BSD-3-Clause")
+        );
+        assert_eq!(detect_license("All rights reserved."), None);
+    }
+
+    #[test]
+    fn test_render_markdown_groups_by_license() {
+        let dependency = |name: &str, license: Option<&str>| Dependency {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            license: license.map(str::to_string),
+            source: "crates.io".to_string(),
+            repository: None,
+        };
+        let report = render_markdown(
+            "app",
+            &[dependency("a", Some("MIT")), dependency("b", None)],
+        );
+
+        assert!(report.contains("# Third-party licenses for app"));
+        assert!(report.contains("## MIT\n"));
+        assert!(report.contains("## Unknown\n"));
+        assert!(report.contains("| a | 1.0.0 | crates.io |"));
+    }
+}
@@ -1,16 +1,56 @@
+mod audit;
+mod bundle;
+mod ci_report;
 mod clean;
+mod compare;
 mod compile;
+mod config;
+mod ctl;
+mod exec;
+mod explain;
+mod gc;
 mod init;
+mod join;
+mod licenses;
+mod optimize;
 mod os;
 mod plugin;
+mod preview;
+mod project;
+mod ps;
 mod run;
+mod selftest;
+mod serve;
+mod stats;
 mod stop;
+mod types;
 mod verify;
+mod why;
 
+pub use audit::handle_audit_command;
+pub use bundle::{handle_bundle_command, handle_bundle_dry_run, Signing};
+pub use ci_report::handle_ci_report_command;
 pub use clean::handle_clean_command;
-pub use compile::handle_compile_command;
+pub use compare::{handle_compare_bench_command, handle_compare_command};
+pub use compile::{handle_compile_command, handle_compile_dry_run, plan_build, run_compile};
+pub use config::handle_config_command;
+pub use ctl::handle_ctl_command;
+pub use exec::handle_exec_command;
+pub use explain::handle_explain_command;
+pub use gc::handle_gc_command;
+pub use join::handle_join_command;
+pub use licenses::handle_licenses_command;
+pub use optimize::{handle_optimize_command, optimize_module};
 pub use os::handle_os_command;
 pub use plugin::run_plugin_command;
-pub use run::handle_run_command;
+pub use preview::handle_preview_command;
+pub use project::{find_named_project, handle_project_command};
+pub use ps::handle_ps_command;
+pub use run::{handle_run_command, run_zero_config};
+pub use selftest::handle_selftest_command;
+pub use serve::handle_serve_command;
+pub use stats::handle_stats_command;
 pub use stop::handle_stop_command;
+pub use types::handle_types_command;
 pub use verify::{handle_inspect_command, handle_verify_command, verify_wasm, VerificationResult};
+pub use why::handle_why_command;
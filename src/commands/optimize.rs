@@ -0,0 +1,54 @@
+//! Module optimization with wasm-opt (`wasmrun optimize`, `--optimize`)
+
+use crate::error::Result;
+use crate::utils::wasm_optimize::{self, OptLevel, SizeDelta};
+use crate::utils::{CommandExecutor, PathResolver};
+use std::path::Path;
+
+/// Handle optimize command: optimize the module in place, or into `output`
+pub fn handle_optimize_command(
+    path: &Option<String>,
+    positional_path: &Option<String>,
+    level: OptLevel,
+    output: &Option<String>,
+) -> Result<()> {
+    let wasm_path = PathResolver::resolve_input_path(positional_path.clone(), path.clone());
+    PathResolver::validate_wasm_file(&wasm_path)?;
+    let output = output.as_deref().unwrap_or(&wasm_path);
+    optimize_module(Path::new(&wasm_path), Path::new(output), level)?;
+    Ok(())
+}
+
+/// Optimize the module at `input` into `output` and print the size change
+pub fn optimize_module(input: &Path, output: &Path, level: OptLevel) -> Result<SizeDelta> {
+    println!("⚡ Optimizing {} with wasm-opt {level}...", input.display());
+    let delta = wasm_optimize::optimize(input, output, level)?;
+    println!("📉 {}: {}", output.display(), describe(&delta));
+    Ok(delta)
+}
+
+/// `before → after (change)`, e.g. `1.20 MB → 845.31 KB (-31.2%)`
+fn describe(delta: &SizeDelta) -> String {
+    format!(
+        "{} → {} ({:+.1}%)",
+        CommandExecutor::format_file_size(delta.before),
+        CommandExecutor::format_file_size(delta.after),
+        delta.percent()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        let delta = SizeDelta {
+            before: 2048,
+            after: 1536,
+        };
+        let line = describe(&delta);
+        assert!(line.ends_with("(-25.0%)"), "{line}");
+        assert!(line.contains(&CommandExecutor::format_file_size(2048)));
+    }
+}
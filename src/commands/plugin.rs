@@ -1,67 +1,32 @@
 use crate::cli::PluginSubcommands;
 use crate::error::Result;
+use crate::plugin::dylib::with_sha256;
 use crate::plugin::manager::PluginManager;
-
-// TODO: Implement plugin search with proper plugin registry system
-// These functions will be used when we have a proper plugin registry
-// fn get_available_plugins_from_crates_io() -> Vec<String> {
-//     // Try to search crates.io for wasmrun plugins
-//     search_crates_io_for_plugins().unwrap_or_else(|_| {
-//         // Fallback to known working plugins if API call fails
-//         vec!["wasmrust".to_string(), "wasmgo".to_string()]
-//     })
-// }
-
-// fn search_crates_io_for_plugins() -> Result<Vec<String>> {
-//     let output = std::process::Command::new("curl")
-//         .arg("-s")
-//         .arg("https://crates.io/api/v1/crates?q=wasmrun&sort=downloads")
-//         .output()
-//         .map_err(|e| WasmrunError::from(format!("Failed to search crates.io: {e}")))?;
-
-//     if !output.status.success() {
-//         return Err(WasmrunError::from("Failed to query crates.io API".to_string()));
-//     }
-
-//     let response = String::from_utf8_lossy(&output.stdout);
-//     parse_crates_io_response(&response)
-// }
-
-// fn parse_crates_io_response(response: &str) -> Result<Vec<String>> {
-//     use serde_json::Value;
-
-//     let json: Value = serde_json::from_str(response)
-//         .map_err(|e| WasmrunError::from(format!("Failed to parse crates.io response: {e}")))?;
-
-//     let mut plugins = Vec::new();
-
-//     if let Some(crates) = json["crates"].as_array() {
-//         for crate_info in crates.iter().take(10) { // Limit to top 10 results
-//             if let Some(name) = crate_info["name"].as_str() {
-//                 // Filter for likely wasmrun plugins
-//                 if name.contains("wasmrun") || name.contains("wasm-") {
-//                     plugins.push(name.to_string());
-//                 }
-//             }
-//         }
-//     }
-
-//     // Add known plugins if not found in search
-//     let known_plugins = ["wasmrust", "wasmgo"];
-//     for plugin in known_plugins {
-//         if !plugins.contains(&plugin.to_string()) {
-//             plugins.push(plugin.to_string());
-//         }
-//     }
-
-//     Ok(plugins)
-// }
+use crate::plugin::search::{self, Source, PLUGIN_KEYWORD};
+use crate::utils::DryRun;
+use crate::{error_println, info_println, success_println};
 
 pub fn run_plugin_command(subcommand: &PluginSubcommands) -> Result<()> {
     match subcommand {
         PluginSubcommands::List { all: _ } => run_plugin_list(),
-        PluginSubcommands::Install { plugin, version: _ } => run_plugin_install(plugin),
-        PluginSubcommands::Uninstall { plugin } => run_plugin_uninstall(plugin),
+        PluginSubcommands::Install {
+            plugin,
+            sha256,
+            dry_run,
+            ..
+        } => {
+            let plugin = with_sha256(plugin, sha256.as_deref())?;
+            if *dry_run {
+                plan_plugin_install(&plugin)
+            } else {
+                run_plugin_install(&plugin)
+            }
+        }
+        PluginSubcommands::Uninstall {
+            plugin,
+            dry_run: true,
+        } => plan_plugin_uninstall(plugin),
+        PluginSubcommands::Uninstall { plugin, .. } => run_plugin_uninstall(plugin),
         PluginSubcommands::Update { plugin } => run_plugin_update(plugin),
         PluginSubcommands::Enable { plugin, disable } => {
             if *disable {
@@ -71,8 +36,9 @@ pub fn run_plugin_command(subcommand: &PluginSubcommands) -> Result<()> {
             }
         }
         PluginSubcommands::Info { plugin } => run_plugin_info(plugin),
-        // TODO: Implement plugin search with proper plugin registry system
-        // PluginSubcommands::Search { query } => run_plugin_search(query),
+        PluginSubcommands::Search { query, refresh } => {
+            run_plugin_search(query.as_deref(), *refresh)
+        }
     }
 }
 
@@ -131,76 +97,142 @@ pub fn run_plugin_list() -> Result<()> {
     Ok(())
 }
 
-// TODO: Implement plugin search with proper plugin registry system
-// pub fn run_plugin_search(query: &str) -> Result<()> {
-//     println!("🔍 Searching for plugins: {query}");
-
-//     // Get available plugins from crates.io search
-//     let available_plugins = get_available_plugins_from_crates_io();
-//     let matches: Vec<&String> = available_plugins
-//         .iter()
-//         .filter(|plugin| plugin.to_lowercase().contains(&query.to_lowercase()))
-//         .collect();
-
-//     if matches.is_empty() {
-//         println!("❌ No plugins found matching '{query}'");
-//     } else {
-//         println!("\n📦 Found {} plugin(s):", matches.len());
-//         for plugin in matches {
-//             println!("  • {plugin}");
-//         }
-//         println!("\n💡 Use 'wasmrun plugin install <plugin-name>' to install");
-//     }
-
-//     Ok(())
-// }
+pub fn run_plugin_search(query: Option<&str>, refresh: bool) -> Result<()> {
+    match query {
+        Some(query) => info_println!("🔍 Searching crates.io for plugins matching '{query}'..."),
+        None => info_println!("🔍 Searching crates.io for plugins..."),
+    }
+
+    let (plugins, source) = search::search(query, refresh)?;
+    if plugins.is_empty() {
+        match query {
+            Some(query) => info_println!("❌ No plugins found matching '{query}'"),
+            None => info_println!("❌ No crates tagged '{PLUGIN_KEYWORD}' found"),
+        }
+        return Ok(());
+    }
+
+    let source = match source {
+        Source::CratesIo => "",
+        Source::Cache | Source::StaleCache => " (cached)",
+    };
+    println!("\n📦 Found {} plugin(s){source}:\n", plugins.len());
+    let name_width = plugins
+        .iter()
+        .map(|plugin| plugin.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("NAME".len());
+    let version_width = plugins
+        .iter()
+        .map(|plugin| plugin.version.len())
+        .max()
+        .unwrap_or(0)
+        .max("VERSION".len());
+    println!(
+        "  {:<name_width$}  {:<version_width$}  {:>10}  DESCRIPTION",
+        "NAME", "VERSION", "DOWNLOADS"
+    );
+    for plugin in &plugins {
+        println!(
+            "  {:<name_width$}  {:<version_width$}  {:>10}  {}",
+            plugin.name,
+            plugin.version,
+            format_downloads(plugin.downloads),
+            truncate(plugin.description.as_deref().unwrap_or("-").trim(), 60)
+        );
+    }
+    info_println!("\n💡 Use 'wasmrun plugin install <plugin-name>' to install");
+
+    Ok(())
+}
+
+/// 12345 → "12,345"
+fn format_downloads(downloads: u64) -> String {
+    let digits = downloads.to_string();
+    let mut formatted = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= max_chars {
+        text
+    } else {
+        format!("{}…", text.chars().take(max_chars - 1).collect::<String>())
+    }
+}
 
 pub fn run_plugin_install(plugin: &str) -> Result<()> {
     let mut manager = PluginManager::new()?;
-    println!("🔄 Installing plugin: {plugin}");
+    info_println!("🔄 Installing plugin: {plugin}");
 
     manager.install_plugin(plugin)?;
-    println!("✅ Plugin '{plugin}' installed successfully");
+    success_println!("Plugin '{plugin}' installed successfully");
 
     Ok(())
 }
 
 pub fn run_plugin_uninstall(plugin: &str) -> Result<()> {
     let mut manager = PluginManager::new()?;
-    println!("🗑️  Uninstalling plugin: {plugin}");
+    info_println!("🗑️  Uninstalling plugin: {plugin}");
 
     manager.uninstall_plugin(plugin)?;
-    println!("✅ Plugin '{plugin}' uninstalled successfully");
+    success_println!("Plugin '{plugin}' uninstalled successfully");
+
+    Ok(())
+}
+
+/// Print what `plugin install` would do
+pub fn plan_plugin_install(plugin: &str) -> Result<()> {
+    let manager = PluginManager::new()?;
+    let mut plan = DryRun::new();
+    manager.plan_install(plugin, &mut plan)?;
+    plan.print(&format!("wasmrun plugin install {plugin}"));
+    Ok(())
+}
 
+/// Print what `plugin uninstall` would do
+pub fn plan_plugin_uninstall(plugin: &str) -> Result<()> {
+    let manager = PluginManager::new()?;
+    let mut plan = DryRun::new();
+    manager.plan_uninstall(plugin, &mut plan)?;
+    plan.print(&format!("wasmrun plugin uninstall {plugin}"));
     Ok(())
 }
 
 pub fn run_plugin_update(plugin: &str) -> Result<()> {
     let mut manager = PluginManager::new()?;
-    println!("🔄 Updating plugin: {plugin}");
+    info_println!("🔄 Updating plugin: {plugin}");
 
     manager.update_plugin(plugin)?;
-    println!("✅ Plugin '{plugin}' updated successfully");
+    success_println!("Plugin '{plugin}' updated successfully");
 
     Ok(())
 }
 
 pub fn run_plugin_enable(plugin: &str) -> Result<()> {
     let mut manager = PluginManager::new()?;
-    println!("✅ Enabling plugin: {plugin}");
+    info_println!("✅ Enabling plugin: {plugin}");
 
     manager.enable_plugin(plugin)?;
-    println!("✅ Plugin '{plugin}' enabled successfully");
+    success_println!("Plugin '{plugin}' enabled successfully");
 
     Ok(())
 }
 
 pub fn run_plugin_disable(plugin: &str) -> Result<()> {
     let mut manager = PluginManager::new()?;
-    println!("❌ Disabling plugin: {plugin}");
+    info_println!("❌ Disabling plugin: {plugin}");
 
     manager.disable_plugin(plugin)?;
-    println!("✅ Plugin '{plugin}' disabled successfully");
+    success_println!("Plugin '{plugin}' disabled successfully");
 
     Ok(())
 }
@@ -220,7 +252,7 @@ pub fn run_plugin_info(plugin: &str) -> Result<()> {
         println!("Dependencies: {:?}", info.dependencies);
         println!("Capabilities: {:?}", info.capabilities);
     } else {
-        println!("❌ Plugin '{plugin}' not found");
+        error_println!("Plugin '{plugin}' not found");
     }
 
     Ok(())
@@ -230,6 +262,15 @@ pub fn run_plugin_info(plugin: &str) -> Result<()> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_search_output_formatting() {
+        assert_eq!(format_downloads(7), "7");
+        assert_eq!(format_downloads(1234), "1,234");
+        assert_eq!(format_downloads(12345678), "12,345,678");
+        assert_eq!(truncate("short\n text", 60), "short text");
+        assert_eq!(truncate("abcdef", 4), "abc…");
+    }
+
     #[test]
     fn test_run_plugin_command_list() {
         let result = run_plugin_command(&PluginSubcommands::List { all: false });
@@ -286,6 +327,11 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_plan_plugin_uninstall_nonexistent() {
+        assert!(plan_plugin_uninstall("nonexistent_plugin_12345").is_ok());
+    }
+
     #[test]
     fn test_run_plugin_update_nonexistent() {
         let result = run_plugin_update("nonexistent_plugin_12345");
@@ -319,13 +365,18 @@ mod tests {
             PluginSubcommands::Install {
                 plugin: "test".to_string(),
                 version: None,
+                sha256: None,
+                dry_run: false,
             },
             PluginSubcommands::Install {
                 plugin: "test".to_string(),
                 version: Some("1.0.0".to_string()),
+                sha256: None,
+                dry_run: true,
             },
             PluginSubcommands::Uninstall {
                 plugin: "test".to_string(),
+                dry_run: false,
             },
             PluginSubcommands::Update {
                 plugin: "test".to_string(),
@@ -0,0 +1,78 @@
+//! Preview a frozen release build with production-like serving
+
+use crate::commands::compile::run_compile;
+use crate::compiler::builder::OptimizationLevel;
+use crate::config::ServerConfig;
+use crate::error::{Result, WasmrunError};
+use crate::utils::{PathResolver, Workspace};
+use std::fs;
+use std::path::Path;
+
+/// Handle preview command
+pub fn handle_preview_command(
+    path: &Option<String>,
+    positional_path: &Option<String>,
+    port: u16,
+    verbose: bool,
+    serve: bool,
+) -> Result<()> {
+    let resolved_path = PathResolver::resolve_input_path(positional_path.clone(), path.clone());
+    let is_wasm_file = Path::new(&resolved_path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("wasm"));
+
+    let (wasm_path, project_path, snapshot_dir) = if is_wasm_file {
+        PathResolver::validate_wasm_file(&resolved_path)?;
+        let parent = Path::new(&resolved_path)
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_string_lossy()
+            .to_string();
+        let snapshot_dir = Workspace::for_project(&parent)?.fresh_preview_dir()?;
+        (
+            freeze_wasm_file(&resolved_path, &snapshot_dir)?,
+            None,
+            snapshot_dir,
+        )
+    } else {
+        PathResolver::validate_directory_exists(&resolved_path)?;
+        let workspace = Workspace::for_project(&resolved_path)?;
+        workspace.enforce_retention();
+        let snapshot_dir = workspace.fresh_preview_dir()?;
+
+        println!("📦 Building release snapshot of {resolved_path}");
+        let result = run_compile(
+            resolved_path.clone(),
+            snapshot_dir.clone(),
+            OptimizationLevel::Release,
+            verbose,
+        )?;
+        (result.wasm_path, Some(resolved_path), snapshot_dir)
+    };
+
+    crate::server::preview::enable();
+    println!("📸 Previewing frozen snapshot: {snapshot_dir}");
+    println!("   No watching or rebuilds; responses use production caching, CSP and compression");
+
+    crate::config::run_server(ServerConfig {
+        wasm_path,
+        js_path: None,
+        port,
+        watch_mode: false,
+        project_path,
+        output_dir: Some(snapshot_dir),
+        serve,
+    })
+}
+
+/// Copy a wasm file into the snapshot directory so later builds cannot change it
+fn freeze_wasm_file(wasm_path: &str, snapshot_dir: &str) -> Result<String> {
+    let file_name = Path::new(wasm_path)
+        .file_name()
+        .ok_or_else(|| WasmrunError::path(format!("Invalid path: {wasm_path}")))?;
+    let frozen = Path::new(snapshot_dir).join(file_name);
+    fs::copy(wasm_path, &frozen)
+        .map_err(|e| WasmrunError::add_context(format!("Copying {wasm_path} to snapshot"), e))?;
+    Ok(frozen.to_string_lossy().to_string())
+}
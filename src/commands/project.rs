@@ -0,0 +1,191 @@
+//! Named project registry
+//!
+//! `wasmrun project add <name> <path>` records a project in the local registry so
+//! that `wasmrun run <name>` launches it from any directory, on its assigned port.
+
+use crate::cli::ProjectSubcommands;
+use crate::error::{CommandError, Result, WasmrunError};
+use crate::registry::{NamedProject, Registry};
+use crate::utils::CommandExecutor;
+use std::fs;
+use std::path::Path;
+
+/// Handle project subcommands
+pub fn handle_project_command(subcommand: &ProjectSubcommands) -> Result<()> {
+    match subcommand {
+        ProjectSubcommands::Add { name, path, port } => add_project(name, path, *port),
+        ProjectSubcommands::List => list_projects(),
+        ProjectSubcommands::Remove { name } => remove_project(name),
+    }
+}
+
+/// The registered project `target` names, unless `target` is an existing path
+pub fn find_named_project(target: &str) -> Option<NamedProject> {
+    if Path::new(target).exists() || !is_valid_name(target) {
+        return None;
+    }
+    Registry::open()
+        .and_then(|registry| registry.project(target))
+        .ok()
+        .flatten()
+}
+
+fn add_project(name: &str, path: &str, port: Option<u16>) -> Result<()> {
+    if !is_valid_name(name) {
+        return Err(WasmrunError::Command(CommandError::invalid_arguments(
+            format!("Project name '{name}' may only contain letters, digits, '-' and '_'"),
+        )));
+    }
+    let canonical = fs::canonicalize(path)
+        .map_err(|_| WasmrunError::path(format!("Path not found: {path}")))?;
+    if !canonical.is_dir() && canonical.extension().is_none_or(|ext| ext != "wasm") {
+        return Err(WasmrunError::path(format!(
+            "{path} is neither a project directory nor a .wasm file"
+        )));
+    }
+
+    let registry = Registry::open()?;
+    if let Some(port) = port {
+        if let Some(other) = registry
+            .projects()?
+            .into_iter()
+            .find(|project| project.port == Some(port) && project.name != name)
+        {
+            return Err(WasmrunError::Command(CommandError::invalid_arguments(
+                format!(
+                    "Port {port} is already assigned to project '{}'",
+                    other.name
+                ),
+            )));
+        }
+    }
+
+    let replaced = registry.project(name)?.is_some();
+    let project = NamedProject {
+        name: name.to_string(),
+        path: canonical.to_string_lossy().to_string(),
+        port,
+        added_at: chrono::Utc::now().to_rfc3339(),
+    };
+    registry.add_project(&project)?;
+
+    let action = if replaced { "Updated" } else { "Added" };
+    println!("📌 {action} project '{name}' → {}", project.path);
+    if let Some(port) = port {
+        println!("   Port: {port}");
+    }
+    println!("   Launch it with: wasmrun run {name}");
+    Ok(())
+}
+
+fn list_projects() -> Result<()> {
+    let registry = Registry::open()?;
+    let projects = registry.projects()?;
+    if projects.is_empty() {
+        println!("📭 No named projects. Add one with: wasmrun project add <name> <path>");
+        return Ok(());
+    }
+
+    let mut rows = Vec::new();
+    for project in &projects {
+        let port = project
+            .port
+            .map_or_else(|| "default".to_string(), |port| port.to_string());
+        let (last_build, size) = if Path::new(&project.path).is_file() {
+            ("prebuilt".to_string(), file_size(&project.path))
+        } else {
+            match registry.recent_builds(Some(&project.path), 1)?.pop() {
+                Some(build) => (
+                    format!(
+                        "{} {}",
+                        if build.success { "✅" } else { "❌" },
+                        local_time(&build.finished_at)
+                    ),
+                    build
+                        .output_path
+                        .as_deref()
+                        .map_or_else(|| "-".to_string(), file_size),
+                ),
+                None => ("never built".to_string(), "-".to_string()),
+            }
+        };
+        rows.push([
+            project.name.clone(),
+            port,
+            last_build,
+            size,
+            project.path.clone(),
+        ]);
+    }
+
+    let header = ["NAME", "PORT", "LAST BUILD", "SIZE", "PATH"];
+    let mut widths = header.map(|title| title.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    println!("📂 Named projects:");
+    for row in std::iter::once(header.map(String::from)).chain(rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell}{}", " ".repeat(width - cell.chars().count())))
+            .collect();
+        println!("   {}", line.join("  ").trim_end());
+    }
+    Ok(())
+}
+
+fn remove_project(name: &str) -> Result<()> {
+    if Registry::open()?.remove_project(name)? {
+        println!("🗑️  Removed project '{name}'");
+        Ok(())
+    } else {
+        Err(WasmrunError::Command(CommandError::invalid_arguments(
+            format!("No project named '{name}'; see wasmrun project list"),
+        )))
+    }
+}
+
+/// Names may not look like paths, so a directory with the same name always wins
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn file_size(path: &str) -> String {
+    fs::metadata(path).map_or_else(
+        |_| "-".to_string(),
+        |metadata| CommandExecutor::format_file_size(metadata.len()),
+    )
+}
+
+fn local_time(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp).map_or_else(
+        |_| timestamp.to_string(),
+        |time| {
+            time.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_names() {
+        assert!(is_valid_name("demo"));
+        assert!(is_valid_name("game_of-life2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("-demo"));
+        assert!(!is_valid_name("./demo"));
+        assert!(!is_valid_name("demo.wasm"));
+    }
+}
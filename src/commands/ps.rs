@@ -0,0 +1,55 @@
+//! List the running servers
+
+use crate::error::Result;
+use crate::server::running_instances;
+
+/// Handle ps command
+pub fn handle_ps_command() -> Result<()> {
+    let instances = running_instances()?;
+    if instances.is_empty() {
+        println!("📭 No Wasmrun servers are running");
+        return Ok(());
+    }
+
+    let rows: Vec<[String; 5]> = instances
+        .into_iter()
+        .map(|instance| {
+            [
+                instance.name.unwrap_or_else(|| "-".to_string()),
+                instance.pid.to_string(),
+                instance.port.to_string(),
+                local_time(&instance.started_at),
+                instance.project_path.unwrap_or(instance.wasm_path),
+            ]
+        })
+        .collect();
+
+    let header = ["NAME", "PID", "PORT", "STARTED", "SERVING"];
+    let mut widths = header.map(|title| title.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    println!("🖥️  Running servers:");
+    for row in std::iter::once(header.map(String::from)).chain(rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell}{}", " ".repeat(width - cell.chars().count())))
+            .collect();
+        println!("   {}", line.join("  ").trim_end());
+    }
+    Ok(())
+}
+
+fn local_time(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp).map_or_else(
+        |_| timestamp.to_string(),
+        |time| {
+            time.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        },
+    )
+}
@@ -1,11 +1,24 @@
 //! Run command implementation
 
-use crate::compiler::builder::{BuildConfig, OptimizationLevel, TargetType};
-use crate::compiler::{compile_for_execution, detect_project_language};
-use crate::error::{Result, WasmrunError};
+use crate::compiler::builder::{BuildConfig, TargetType};
+use crate::compiler::{
+    cargo_features, cargo_target, compile_for_execution, configured_builder,
+    detect_project_language,
+};
+use crate::config::active_settings;
+use crate::error::{Result, ServerError, WasmrunError};
+use crate::plugin::hooks as plugin_hooks;
 use crate::plugin::manager::PluginManager;
-use crate::utils::PathResolver;
+use crate::registry::{record_build, record_build_result, BuildRecord};
+use crate::self_profile::{self, Subsystem};
+use crate::server::build_status;
+use crate::server::file_cache;
+use crate::server::live_reload;
+use crate::utils::{record_build_outputs, typescript, PathResolver, Workspace};
+use crate::watcher::{ProjectWatcher, WatchOptions};
+use crate::{info_println, warn_println};
 use std::path::Path;
+use std::time::Instant;
 
 pub fn handle_run_command(
     path: &Option<String>,
@@ -29,6 +42,103 @@ pub fn handle_run_command(
     )
 }
 
+/// Bare `wasmrun` in a project directory: detect the project, build it, pick a
+/// free port, watch for changes and open the browser, saying why at each step.
+/// A port given with `--port` is used as is, or not at all.
+pub fn run_zero_config(
+    project_path: &str,
+    port: u16,
+    port_source: &str,
+    port_given: bool,
+) -> Result<()> {
+    let dir = Path::new(project_path)
+        .canonicalize()
+        .unwrap_or_else(|_| Path::new(project_path).to_path_buf());
+    info_println!(
+        "🪄 No arguments given: building and serving {}",
+        dir.display()
+    );
+
+    match describe_project(project_path) {
+        Some(project) => info_println!("🔎 Detected {project}"),
+        None => {
+            let Some(wasm) = single_wasm_file(&dir) else {
+                return Err(WasmrunError::path(format!(
+                    "No project found in {}. Run wasmrun in a Rust, Go, C, AssemblyScript or Python project, or pass a path or .wasm file",
+                    dir.display()
+                )));
+            };
+            info_println!("🔎 No project here, but one module: serving {wasm}");
+            let port = free_port(port, port_source, port_given)?;
+            info_println!("🌐 Opening the browser when the server is ready");
+            return run_wasm_file(&wasm, Some(port), true);
+        }
+    }
+    let port = free_port(port, port_source, port_given)?;
+    info_println!("👀 Watch mode: rebuilding and reloading the page when sources change");
+    info_println!("🌐 Opening the browser when the server is ready");
+
+    run_project(
+        project_path.to_string(),
+        Some(port),
+        true,
+        None,
+        false,
+        true,
+    )
+}
+
+/// What builds the project at `project_path`, for the zero-config log
+fn describe_project(project_path: &str) -> Option<String> {
+    if let Ok(plugin_manager) = PluginManager::new() {
+        if let Some(plugin) = plugin_manager.find_plugin_for_project(project_path) {
+            return Some(format!("a project for the {} plugin", plugin.info().name));
+        }
+    }
+    match detect_project_language(project_path) {
+        crate::compiler::ProjectLanguage::Unknown => None,
+        language => Some(format!("a {language:?} project")),
+    }
+}
+
+/// The only `.wasm` file directly in `dir`, if there is exactly one
+fn single_wasm_file(dir: &Path) -> Option<String> {
+    let mut modules = std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_wasm_file(&path.to_string_lossy()));
+    let module = modules.next()?;
+    modules
+        .next()
+        .is_none()
+        .then(|| module.to_string_lossy().to_string())
+}
+
+/// `port` when it is free, otherwise the next free one above it, unless the
+/// user asked for this port, which only says who holds it
+fn free_port(port: u16, source: &str, given: bool) -> Result<u16> {
+    use crate::server::utils::{is_port_available, ServerUtils};
+    if is_port_available(port) {
+        info_println!("🔌 Port {port} ({source})");
+        return Ok(port);
+    }
+    if given {
+        if ServerUtils::diagnose_port_conflict(port)? {
+            return Ok(port);
+        }
+        return Err(WasmrunError::Server(ServerError::PortInUse { port }));
+    }
+    match (port.saturating_add(1)..port.saturating_add(20)).find(|&p| is_port_available(p)) {
+        Some(free) => {
+            info_println!("🔌 Port {port} ({source}) is in use, so using port {free}");
+            Ok(free)
+        }
+        // Let the server report the conflict and who holds the port
+        None => Ok(port),
+    }
+}
+
 pub fn run_project(
     path: String,
     port: Option<u16>,
@@ -96,11 +206,18 @@ fn run_project_directory(
         println!("🔍 Detecting project type in: {project_path}");
     }
 
+    // wasmrun.toml's build.command or build.plugin, unless --language says otherwise
+    if language.is_none() {
+        if let Some((name, builder)) = configured_builder(&active_settings().build)? {
+            return run_with_builder(builder, name, project_path, port, watch, verbose, serve);
+        }
+    }
+
     // Try plugin-based compilation first
     if let Ok(plugin_manager) = PluginManager::new() {
         if let Some(plugin) = plugin_manager.find_plugin_for_project(project_path) {
-            return run_with_plugin(
-                &plugin_manager,
+            return run_with_builder(
+                plugin.get_builder(),
                 plugin.info().name.clone(),
                 project_path,
                 port,
@@ -131,8 +248,8 @@ fn run_project_directory(
     }
 }
 
-fn run_with_plugin(
-    plugin_manager: &PluginManager,
+fn run_with_builder(
+    builder: Box<dyn crate::compiler::builder::WasmBuilder>,
     plugin_name: String,
     project_path: &str,
     port: Option<u16>,
@@ -144,10 +261,6 @@ fn run_with_plugin(
         println!("🔌 Using plugin: {plugin_name}");
     }
 
-    let builder = plugin_manager
-        .get_builder_for_project(project_path)
-        .ok_or_else(|| WasmrunError::from("Failed to get builder for project"))?;
-
     // Check dependencies
     let missing_deps = builder.check_dependencies();
     if !missing_deps.is_empty() {
@@ -158,9 +271,7 @@ fn run_with_plugin(
         )));
     }
 
-    let temp_dir = std::env::temp_dir().join("wasmrun");
-    std::fs::create_dir_all(&temp_dir)?;
-    let output_dir = temp_dir.to_string_lossy().to_string();
+    let output_dir = prepare_workspace(project_path)?;
 
     if watch {
         run_with_watch(project_path, &output_dir, port, builder, verbose, serve)
@@ -179,8 +290,8 @@ fn run_with_language_override(
 ) -> Result<()> {
     if let Ok(plugin_manager) = PluginManager::new() {
         if let Some(plugin) = plugin_manager.get_plugin_by_language(language) {
-            return run_with_plugin(
-                &plugin_manager,
+            return run_with_builder(
+                plugin.get_builder(),
                 plugin.info().name.clone(),
                 project_path,
                 port,
@@ -205,9 +316,7 @@ fn run_with_detected_language(
     verbose: bool,
     serve: bool,
 ) -> Result<()> {
-    let temp_dir = std::env::temp_dir().join("wasmrun");
-    std::fs::create_dir_all(&temp_dir)?;
-    let output_dir = temp_dir.to_string_lossy().to_string();
+    let output_dir = prepare_workspace(project_path)?;
 
     if watch {
         run_with_watch_legacy(project_path, &output_dir, port, verbose, serve)
@@ -216,6 +325,68 @@ fn run_with_detected_language(
     }
 }
 
+/// Open the project's managed workspace and return its build output directory
+fn prepare_workspace(project_path: &str) -> Result<String> {
+    let workspace = Workspace::for_project(project_path)?;
+    workspace.enforce_retention();
+    workspace.output_dir()
+}
+
+/// Build with a plugin builder, running the project's pre/post build hooks around it
+fn build_with_hooks(
+    builder: &dyn crate::compiler::builder::WasmBuilder,
+    config: &BuildConfig,
+) -> Result<crate::compiler::builder::BuildResult> {
+    let _span = self_profile::span(Subsystem::Builds);
+    let hooks = active_settings().hooks;
+    hooks
+        .run_pre_build(&config.project_path)
+        .map_err(WasmrunError::Compilation)?;
+    plugin_hooks::pre_build(&config.project_path)?;
+    let started = Instant::now();
+    let result = builder.build(config);
+    record_build_result(
+        &config.project_path,
+        builder.language_name(),
+        started,
+        &result,
+    );
+    let mut result = result.map_err(WasmrunError::Compilation)?;
+    typescript::bundle(&mut result);
+    hooks
+        .run_post_build(&config.project_path)
+        .map_err(WasmrunError::Compilation)?;
+    plugin_hooks::post_build(&config.project_path, &result.wasm_path)?;
+
+    record_build_outputs(&result.output_files());
+    Ok(result)
+}
+
+/// Legacy compilation wrapped with the project's pre/post build hooks
+fn compile_with_hooks(project_path: &str, output_dir: &str) -> Result<String> {
+    let _span = self_profile::span(Subsystem::Builds);
+    let hooks = active_settings().hooks;
+    hooks
+        .run_pre_build(project_path)
+        .map_err(WasmrunError::Compilation)?;
+    plugin_hooks::pre_build(project_path)?;
+    let started = Instant::now();
+    let result = compile_for_execution(project_path, output_dir);
+    record_build(BuildRecord::new(
+        project_path,
+        &format!("{:?}", detect_project_language(project_path)),
+        started.elapsed(),
+        result.as_deref().map_err(ToString::to_string),
+    ));
+    let primary_file = result?;
+    hooks
+        .run_post_build(project_path)
+        .map_err(WasmrunError::Compilation)?;
+    plugin_hooks::post_build(project_path, &primary_file)?;
+    record_build_outputs(&[&primary_file]);
+    Ok(primary_file)
+}
+
 fn run_once(
     project_path: &str,
     output_dir: &str,
@@ -231,13 +402,15 @@ fn run_once(
     let config = BuildConfig {
         project_path: project_path.to_string(),
         output_dir: output_dir.to_string(),
-        optimization_level: OptimizationLevel::Release,
+        optimization_level: active_settings().build.optimization_level(),
         verbose,
         watch: false,
         target_type: TargetType::Standard,
+        cargo_target: cargo_target::active(),
+        cargo_features: cargo_features::active_features(),
     };
 
-    let result = builder.build(&config).map_err(WasmrunError::Compilation)?;
+    let result = build_with_hooks(builder.as_ref(), &config)?;
 
     if verbose {
         println!("✅ Build completed");
@@ -265,63 +438,104 @@ fn run_with_watch(
     port: Option<u16>,
     builder: Box<dyn crate::compiler::builder::WasmBuilder>,
     verbose: bool,
-    _serve: bool,
+    serve: bool,
 ) -> Result<()> {
     println!("👀 Watch mode enabled - monitoring for changes...");
 
-    let server_port = port.unwrap_or(8420);
-
     // Initial build
-    let config = BuildConfig {
+    let mut config = BuildConfig {
         project_path: project_path.to_string(),
         output_dir: output_dir.to_string(),
-        optimization_level: OptimizationLevel::Release,
+        optimization_level: active_settings().build.optimization_level(),
         verbose,
         watch: true,
         target_type: TargetType::Standard,
+        cargo_target: cargo_target::active(),
+        cargo_features: cargo_features::active_features(),
     };
 
-    let initial_result = builder.build(&config).map_err(WasmrunError::Compilation)?;
-    let primary_file = initial_result
-        .js_path
-        .as_ref()
-        .unwrap_or(&initial_result.wasm_path);
-
+    let initial_result = build_with_hooks(builder.as_ref(), &config)?;
     println!("✅ Initial build completed");
-    println!("🚀 Server would start on port {server_port} for file: {primary_file}");
-    println!("👀 Watching for changes... (press Ctrl+C to stop)");
 
-    // Set up file watcher
-    let watcher = crate::watcher::ProjectWatcher::new(project_path)
+    let generated = builder.generated_paths(project_path);
+    rebuild_on_changes(project_path, &generated, move || {
+        // Keep up with a feature set chosen on the page since the last build
+        config.cargo_features = cargo_features::active_features();
+        Ok(build_with_hooks(builder.as_ref(), &config)?.wasm_path)
+    })?;
+
+    crate::config::run_server(crate::config::ServerConfig {
+        wasm_path: initial_result.wasm_path,
+        js_path: initial_result.js_path,
+        port: port.unwrap_or(8420),
+        watch_mode: true,
+        project_path: Some(project_path.to_string()),
+        output_dir: Some(output_dir.to_string()),
+        serve,
+    })
+}
+
+/// Watch `project_path` on a background thread, calling `build` when sources
+/// change and reloading open pages once it succeeds; asset changes reload the
+/// pages straight away. Changes below `generated`, which the build writes, are skipped.
+fn rebuild_on_changes(
+    project_path: &str,
+    generated: &[String],
+    mut build: impl FnMut() -> Result<String> + Send + 'static,
+) -> Result<()> {
+    let mut options = WatchOptions::from_settings(&active_settings().watch);
+    options.ignore = options.ignore.with(generated);
+    let watcher = ProjectWatcher::new(project_path, options)
         .map_err(|e| WasmrunError::from(format!("Failed to create file watcher: {e}")))?;
+    println!("👀 Watching for changes... (press Ctrl+C to stop)");
 
-    loop {
-        if let Some(events_result) = watcher.wait_for_change() {
-            match events_result {
-                Ok(events) => {
-                    if watcher.should_recompile(&events) {
-                        println!("📂 Files changed, recompiling...");
-
-                        // Recompile the project
-                        match builder.build(&config) {
-                            Ok(result) => {
-                                let new_primary_file =
-                                    result.js_path.as_ref().unwrap_or(&result.wasm_path);
-                                println!("✅ Recompilation completed: {new_primary_file}");
-                            }
-                            Err(e) => {
-                                eprintln!("❌ Recompilation failed: {e:?}");
-                                println!("👀 Continuing to watch for changes...");
-                            }
-                        }
-                    }
-                }
+    let project_path = project_path.to_string();
+    std::thread::spawn(move || {
+        while let Some(result) = watcher.wait_for_changes() {
+            let mut changes = match result {
+                Ok(changes) => changes,
                 Err(errors) => {
-                    eprintln!("⚠️ File watcher errors: {errors:?}");
+                    warn_println!("File watcher errors: {errors:?}");
+                    continue;
+                }
+            };
+            // Edits saved while the last build ran make one rebuild, not one each
+            changes.merge(watcher.pending_changes());
+            let _span = self_profile::span(Subsystem::Watcher);
+            file_cache::invalidate();
+            let paths: Vec<&Path> = changes
+                .changes
+                .iter()
+                .map(|change| change.path.as_path())
+                .collect();
+            plugin_hooks::file_change(&project_path, &paths);
+            crate::tui::emit(crate::tui::DevEvent::Watch(changes.changes.clone()));
+            if !changes.needs_rebuild() {
+                println!("🔄 {changes} changed, reloading open pages");
+                live_reload::reload_for(&changes);
+                continue;
+            }
+
+            println!("📂 {changes} changed, recompiling...");
+            build_status::begin(&format!("{changes} changed"));
+            match build() {
+                Ok(primary_file) => {
+                    build_status::succeeded();
+                    println!("✅ Recompilation completed: {primary_file}");
+                    // Served files may still be cached from before the build
+                    file_cache::invalidate();
+                    live_reload::reload_after_build(&primary_file);
+                }
+                Err(e) => {
+                    warn_println!("Recompilation failed: {e}");
+                    // Open pages keep the last good build and show the error instead
+                    build_status::failed(&e.to_string());
+                    println!("👀 Continuing to watch for changes...");
                 }
             }
         }
-    }
+    });
+    Ok(())
 }
 
 fn run_once_legacy(
@@ -335,7 +549,7 @@ fn run_once_legacy(
         println!("🔧 Compiling project (legacy mode)...");
     }
 
-    let primary_file = compile_for_execution(project_path, output_dir)?;
+    let primary_file = compile_with_hooks(project_path, output_dir)?;
 
     if verbose {
         println!("✅ Compilation completed");
@@ -362,48 +576,28 @@ fn run_with_watch_legacy(
     output_dir: &str,
     port: Option<u16>,
     _verbose: bool,
-    _serve: bool,
+    serve: bool,
 ) -> Result<()> {
     println!("👀 Watch mode enabled (legacy) - monitoring for changes...");
 
-    let server_port = port.unwrap_or(8420);
-
     // Initial compilation
-    let initial_file = compile_for_execution(project_path, output_dir)?;
-
+    let initial_file = compile_with_hooks(project_path, output_dir)?;
     println!("✅ Initial compilation completed");
-    println!("🚀 Server would start on port {server_port} for file: {initial_file}");
-    println!("👀 Watching for changes... (press Ctrl+C to stop)");
 
-    // Set up file watcher
-    let watcher = crate::watcher::ProjectWatcher::new(project_path)
-        .map_err(|e| WasmrunError::from(format!("Failed to create file watcher: {e}")))?;
+    let (project, output) = (project_path.to_string(), output_dir.to_string());
+    rebuild_on_changes(project_path, &[], move || {
+        compile_with_hooks(&project, &output)
+    })?;
 
-    loop {
-        if let Some(events_result) = watcher.wait_for_change() {
-            match events_result {
-                Ok(events) => {
-                    if watcher.should_recompile(&events) {
-                        println!("📂 Files changed, recompiling...");
-
-                        // Recompile the project
-                        match crate::compiler::compile_for_execution(project_path, output_dir) {
-                            Ok(result_file) => {
-                                println!("✅ Recompilation completed: {result_file}");
-                            }
-                            Err(e) => {
-                                eprintln!("❌ Recompilation failed: {e}");
-                                println!("👀 Continuing to watch for changes...");
-                            }
-                        }
-                    }
-                }
-                Err(errors) => {
-                    eprintln!("⚠️ File watcher errors: {errors:?}");
-                }
-            }
-        }
-    }
+    crate::config::run_server(crate::config::ServerConfig {
+        wasm_path: initial_file,
+        js_path: None,
+        port: port.unwrap_or(8420),
+        watch_mode: true,
+        project_path: Some(project_path.to_string()),
+        output_dir: Some(output_dir.to_string()),
+        serve,
+    })
 }
 
 /// Run a project in OS mode using the multi-language kernel
@@ -459,3 +653,23 @@ pub fn run_project_os_mode(
     // Start the server - this will block until Ctrl+C
     server.start(port)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_single_wasm_file() {
+        let dir = tempdir().unwrap();
+        assert_eq!(single_wasm_file(dir.path()), None);
+
+        std::fs::write(dir.path().join("app.wasm"), b"\0asm").unwrap();
+        std::fs::write(dir.path().join("index.html"), "").unwrap();
+        let module = single_wasm_file(dir.path()).unwrap();
+        assert!(module.ends_with("app.wasm"));
+
+        std::fs::write(dir.path().join("other.wasm"), b"\0asm").unwrap();
+        assert_eq!(single_wasm_file(dir.path()), None);
+    }
+}
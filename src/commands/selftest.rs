@@ -0,0 +1,696 @@
+//! `wasmrun selftest`: build and serve sample projects end to end
+//!
+//! The hello-world projects under `examples/` for Rust, Go, AssemblyScript
+//! and C are built into the binary. Each one is written to a scratch
+//! directory and taken
+//! through what `wasmrun run --watch` does for a real project: language
+//! detection, the toolchain check, the build, the runner page, and a rebuild
+//! after a source edit. The server part runs in a child `wasmrun`, watched
+//! through `/__wasmrun/status`, so every step goes through the same code as
+//! everyday use. A missing toolchain skips its example rather than failing it,
+//! which makes the summary a map of what this machine can build.
+
+use crate::compiler::builder::{BuilderFactory, WasmBuilder};
+use crate::compiler::{configured_builder, detect_project_language, ProjectLanguage};
+use crate::config::project::ProjectConfig;
+use crate::error::{Result, WasmrunError};
+use crate::plugin::manager::PluginManager;
+use crate::server::ansi;
+use crate::server::shutdown;
+use crate::server::status::STATUS_ROUTE;
+use crate::utils::CommandExecutor;
+use std::fs;
+use std::io::Read;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Longest wait for the first build, which may download dependencies
+const BUILD_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Longest wait for the rebuild after the edit
+const REBUILD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How often the child server is polled
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Lines of the server log shown when an example fails
+const LOG_TAIL: usize = 15;
+
+/// Directories not copied from `--examples`
+const SKIPPED_DIRS: [&str; 4] = ["target", "node_modules", "build", "dist"];
+
+// Cargo never packages a directory with its own Cargo.toml, so the Rust
+// example can't be included from examples/rust-hello; this is a trimmed copy
+const RUST_MANIFEST: &str = r#"[package]
+name = "rust-hello"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+crate-type = ["cdylib", "rlib"]
+
+[dependencies]
+wasm-bindgen = "0.2"
+"#;
+
+const RUST_LIB: &str = r#"use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub fn greet(name: &str) -> String {
+    format!("Hello, {name}! This message is from Rust and WebAssembly.")
+}
+
+#[wasm_bindgen]
+pub fn fibonacci(n: u32) -> u32 {
+    if n <= 1 {
+        n
+    } else {
+        fibonacci(n - 1) + fibonacci(n - 2)
+    }
+}
+"#;
+
+/// A sample project compiled into the binary
+struct Example {
+    name: &'static str,
+    language: ProjectLanguage,
+    files: &'static [(&'static str, &'static str)],
+    /// Source file edited to trigger the rebuild
+    edit: &'static str,
+}
+
+const EXAMPLES: &[Example] = &[
+    Example {
+        name: "rust-hello",
+        language: ProjectLanguage::Rust,
+        files: &[("Cargo.toml", RUST_MANIFEST), ("src/lib.rs", RUST_LIB)],
+        edit: "src/lib.rs",
+    },
+    Example {
+        name: "go-hello",
+        language: ProjectLanguage::Go,
+        files: &[
+            ("go.mod", include_str!("../../examples/go-hello/go.mod")),
+            ("main.go", include_str!("../../examples/go-hello/main.go")),
+        ],
+        edit: "main.go",
+    },
+    Example {
+        name: "asc-hello",
+        language: ProjectLanguage::Asc,
+        files: &[
+            (
+                "package.json",
+                include_str!("../../examples/asc-hello/package.json"),
+            ),
+            (
+                "asconfig.json",
+                include_str!("../../examples/asc-hello/asconfig.json"),
+            ),
+            (
+                "assembly/index.ts",
+                include_str!("../../examples/asc-hello/assembly/index.ts"),
+            ),
+        ],
+        edit: "assembly/index.ts",
+    },
+    Example {
+        name: "c-hello",
+        language: ProjectLanguage::C,
+        files: &[
+            ("Makefile", include_str!("../../examples/c-hello/Makefile")),
+            ("main.c", include_str!("../../examples/c-hello/main.c")),
+        ],
+        edit: "main.c",
+    },
+];
+
+/// A project ready to be tested in the scratch directory
+struct Sample {
+    name: String,
+    dir: PathBuf,
+    expected: Option<ProjectLanguage>,
+    edit: Option<PathBuf>,
+}
+
+/// How one step went
+enum Outcome {
+    Pass(String),
+    Fail(String),
+    Skip(String),
+}
+
+/// Handle selftest command
+pub fn handle_selftest_command(
+    examples: Option<&str>,
+    languages: &[String],
+    keep: bool,
+) -> Result<()> {
+    let scratch = std::env::temp_dir().join(format!("wasmrun-selftest-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&scratch);
+    fs::create_dir_all(&scratch)
+        .map_err(|e| WasmrunError::add_context("Creating the self-test directory", e))?;
+
+    let samples = match examples {
+        Some(examples) => copy_examples(Path::new(examples), &scratch)?,
+        None => write_examples(&scratch)?,
+    };
+    let samples: Vec<Sample> = samples
+        .into_iter()
+        .filter(|sample| {
+            languages.is_empty()
+                || languages.contains(&language_key(
+                    &sample
+                        .expected
+                        .clone()
+                        .unwrap_or_else(|| detect_project_language(&sample.dir.to_string_lossy())),
+                ))
+        })
+        .collect();
+    if samples.is_empty() {
+        return Err(WasmrunError::from("No examples to test".to_string()));
+    }
+
+    println!(
+        "🧪 Wasmrun self-test: {} example(s) in {}",
+        samples.len(),
+        scratch.display()
+    );
+    let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+    for sample in &samples {
+        println!();
+        println!("📦 {}", sample.name);
+        let outcomes = test_sample(sample);
+        for (step, outcome) in &outcomes {
+            let (icon, detail) = match outcome {
+                Outcome::Pass(detail) => ("✅", detail),
+                Outcome::Fail(detail) => ("❌", detail),
+                Outcome::Skip(detail) => ("⏭️ ", detail),
+            };
+            let mut lines = detail.lines();
+            println!("   {icon} {step:<9} {}", lines.next().unwrap_or_default());
+            for line in lines {
+                println!("      {line}");
+            }
+        }
+        if outcomes
+            .iter()
+            .any(|(_, outcome)| matches!(outcome, Outcome::Fail(_)))
+        {
+            failed += 1;
+        } else if outcomes
+            .iter()
+            .any(|(_, outcome)| matches!(outcome, Outcome::Skip(_)))
+        {
+            skipped += 1;
+        } else {
+            passed += 1;
+        }
+    }
+
+    println!();
+    println!("📊 {passed} passed, {failed} failed, {skipped} skipped");
+    // The failures point into the server logs, so those stay around
+    if keep || failed > 0 {
+        println!("📁 Kept the examples and logs in {}", scratch.display());
+    } else {
+        let _ = fs::remove_dir_all(&scratch);
+    }
+    if failed > 0 {
+        return Err(WasmrunError::ChecksFailed { count: failed });
+    }
+    Ok(())
+}
+
+/// Run every step for `sample`, stopping at the first that doesn't pass
+fn test_sample(sample: &Sample) -> Vec<(&'static str, Outcome)> {
+    let mut outcomes = Vec::new();
+    let project_path = sample.dir.to_string_lossy().to_string();
+
+    let detected = detect_project_language(&project_path);
+    let plugin = PluginManager::new().ok().and_then(|manager| {
+        manager
+            .find_plugin_for_project(&project_path)
+            .map(|plugin| format!("{} {}", plugin.info().name, plugin.info().version))
+    });
+    let detection = format!(
+        "{detected}{}",
+        plugin
+            .map(|plugin| format!(" (plugin {plugin})"))
+            .unwrap_or_default()
+    );
+    match &sample.expected {
+        Some(expected) if *expected != detected => {
+            outcomes.push((
+                "detect",
+                Outcome::Fail(format!("expected {expected}, found {detection}")),
+            ));
+            return outcomes;
+        }
+        _ => outcomes.push(("detect", Outcome::Pass(detection))),
+    }
+
+    let builder = match builder_for(&project_path, &detected) {
+        Ok(builder) => builder,
+        Err(e) => {
+            outcomes.push(("toolchain", Outcome::Fail(e.to_string())));
+            return outcomes;
+        }
+    };
+    let missing = builder.check_dependencies();
+    if !missing.is_empty() {
+        outcomes.push((
+            "toolchain",
+            Outcome::Skip(format!("missing {}", missing.join(", "))),
+        ));
+        return outcomes;
+    }
+    outcomes.push((
+        "toolchain",
+        Outcome::Pass(format!("{} builder ready", builder.language_name())),
+    ));
+
+    let edit = sample
+        .edit
+        .clone()
+        .or_else(|| source_to_edit(&sample.dir, builder.supported_extensions()));
+    outcomes.extend(exercise_server(&sample.dir, edit.as_deref()));
+    outcomes
+}
+
+/// The builder `wasmrun run` would pick for the project
+fn builder_for(project_path: &str, language: &ProjectLanguage) -> Result<Box<dyn WasmBuilder>> {
+    if let Some(config) = ProjectConfig::find(project_path) {
+        let config = ProjectConfig::load_from_file(&config)?;
+        if let Some((_, builder)) = configured_builder(&config.base.build)? {
+            return Ok(builder);
+        }
+    }
+    Ok(BuilderFactory::create_builder_from_plugin(project_path)
+        .unwrap_or_else(|| BuilderFactory::create_builder(language)))
+}
+
+/// Serve the project from a child `wasmrun run --watch`, then check the
+/// build, the page and the rebuild after editing `edit`
+fn exercise_server(dir: &Path, edit: Option<&Path>) -> Vec<(&'static str, Outcome)> {
+    let mut outcomes = Vec::new();
+    let Some(mut server) = ChildServer::start(dir) else {
+        outcomes.push((
+            "build",
+            Outcome::Fail("cannot start wasmrun for the example".to_string()),
+        ));
+        return outcomes;
+    };
+
+    let first = match server.wait_for_build(None, BUILD_TIMEOUT) {
+        Ok(status) => status,
+        Err(e) => {
+            outcomes.push(("build", Outcome::Fail(server.explain(&e))));
+            server.stop();
+            return outcomes;
+        }
+    };
+    let artifact = first["artifacts"][0]["file"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    match server.get(&format!("/{artifact}")) {
+        Ok(body) if body.starts_with(b"\0asm") => outcomes.push((
+            "build",
+            Outcome::Pass(format!(
+                "{artifact} ({}) in {}",
+                CommandExecutor::format_file_size(body.len() as u64),
+                format_ms(first["build"]["last"]["duration_ms"].as_u64())
+            )),
+        )),
+        Ok(_) => outcomes.push((
+            "build",
+            Outcome::Fail(format!("/{artifact} is not a WebAssembly module")),
+        )),
+        Err(e) => outcomes.push(("build", Outcome::Fail(format!("/{artifact}: {e}")))),
+    }
+
+    match server.get("/") {
+        Ok(page) if String::from_utf8_lossy(&page).contains(&artifact) => outcomes.push((
+            "page",
+            Outcome::Pass(format!("runner page loads {artifact}")),
+        )),
+        Ok(_) => outcomes.push((
+            "page",
+            Outcome::Fail(format!("the page at / doesn't load {artifact}")),
+        )),
+        Err(e) => outcomes.push(("page", Outcome::Fail(format!("/: {e}")))),
+    }
+
+    match edit {
+        Some(edit) => {
+            let since = first["build"]["last"]["finished_at"].clone();
+            let outcome = append_comment(edit)
+                .map_err(|e| format!("cannot edit {}: {e}", edit.display()))
+                .and_then(|_| server.wait_for_build(Some(&since), REBUILD_TIMEOUT))
+                .map(|status| {
+                    Outcome::Pass(format!(
+                        "rebuilt in {} after editing {}",
+                        format_ms(status["build"]["last"]["duration_ms"].as_u64()),
+                        edit.strip_prefix(dir).unwrap_or(edit).display()
+                    ))
+                })
+                .unwrap_or_else(|e| Outcome::Fail(server.explain(&e)));
+            outcomes.push(("reload", outcome));
+        }
+        None => outcomes.push((
+            "reload",
+            Outcome::Skip("no source file to edit".to_string()),
+        )),
+    }
+
+    server.stop();
+    outcomes
+}
+
+/// A `wasmrun run --watch` serving an example on a free port
+struct ChildServer {
+    child: Child,
+    port: u16,
+    token: String,
+    log: PathBuf,
+    agent: ureq::Agent,
+}
+
+impl ChildServer {
+    fn start(dir: &Path) -> Option<Self> {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .ok()?
+            .local_addr()
+            .ok()?
+            .port();
+        let token = format!("selftest-{}-{port}", std::process::id());
+        let log = dir.with_extension("log");
+        let output = fs::File::create(&log).ok()?;
+        let child = Command::new(std::env::current_exe().ok()?)
+            .arg("run")
+            .arg(dir)
+            .args(["--port", &port.to_string(), "--watch"])
+            .env(shutdown::TOKEN_ENV, &token)
+            .stdin(Stdio::null())
+            .stdout(output.try_clone().ok()?)
+            .stderr(output)
+            .spawn()
+            .ok()?;
+        Some(Self {
+            child,
+            port,
+            token,
+            log,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(10))
+                .build(),
+        })
+    }
+
+    /// The status once a build finished after the one at `since`, or the first build
+    fn wait_for_build(
+        &mut self,
+        since: Option<&serde_json::Value>,
+        timeout: Duration,
+    ) -> std::result::Result<serde_json::Value, String> {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if let Ok(Some(exit)) = self.child.try_wait() {
+                return Err(format!("wasmrun exited ({exit})"));
+            }
+            let status = self
+                .get(STATUS_ROUTE)
+                .ok()
+                .and_then(|body| serde_json::from_slice::<serde_json::Value>(&body).ok());
+            if let Some(status) = status {
+                let last = &status["build"]["last"];
+                let finished = !last.is_null() && status["build"]["running"] != true;
+                if finished && since.is_none_or(|since| last["finished_at"] != *since) {
+                    if last["success"] != true {
+                        return Err(format!(
+                            "build failed: {}",
+                            last["error"].as_str().unwrap_or("unknown error")
+                        ));
+                    }
+                    return Ok(status);
+                }
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        Err(format!("no build finished within {}s", timeout.as_secs()))
+    }
+
+    fn get(&self, path: &str) -> std::result::Result<Vec<u8>, String> {
+        let response = self
+            .agent
+            .get(&format!("http://127.0.0.1:{}{path}", self.port))
+            .call()
+            .map_err(|e| e.to_string())?;
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut body)
+            .map_err(|e| e.to_string())?;
+        Ok(body)
+    }
+
+    /// `problem`, followed by the end of the server's log
+    fn explain(&self, problem: &str) -> String {
+        let log = ansi::strip(&fs::read_to_string(&self.log).unwrap_or_default());
+        let lines: Vec<&str> = log.lines().filter(|line| !line.trim().is_empty()).collect();
+        let tail = &lines[lines.len().saturating_sub(LOG_TAIL)..];
+        if tail.is_empty() {
+            return problem.to_string();
+        }
+        format!(
+            "{problem}\n{}\n(full log: {})",
+            tail.join("\n"),
+            self.log.display()
+        )
+    }
+
+    /// Shut the server down, and kill it when it doesn't go
+    fn stop(mut self) {
+        let _ = self
+            .agent
+            .post(&format!(
+                "http://127.0.0.1:{}{}",
+                self.port,
+                shutdown::SHUTDOWN_ROUTE
+            ))
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .call();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Write the built-in examples under `scratch`
+fn write_examples(scratch: &Path) -> Result<Vec<Sample>> {
+    EXAMPLES
+        .iter()
+        .map(|example| {
+            let dir = scratch.join(example.name);
+            for (path, contents) in example.files {
+                let path = dir.join(path);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| {
+                        WasmrunError::add_context(format!("Writing {}", example.name), e)
+                    })?;
+                }
+                fs::write(&path, contents).map_err(|e| {
+                    WasmrunError::add_context(format!("Writing {}", path.display()), e)
+                })?;
+            }
+            Ok(Sample {
+                name: example.name.to_string(),
+                expected: Some(example.language.clone()),
+                edit: Some(dir.join(example.edit)),
+                dir,
+            })
+        })
+        .collect()
+}
+
+/// Copy every project directory in `examples` under `scratch`, so the edits
+/// never touch the originals
+fn copy_examples(examples: &Path, scratch: &Path) -> Result<Vec<Sample>> {
+    let entries = fs::read_dir(examples).map_err(|e| {
+        WasmrunError::add_context(format!("Reading examples in {}", examples.display()), e)
+    })?;
+    let mut projects: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_dir()
+                && !path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    projects.sort();
+    projects
+        .into_iter()
+        .map(|project| {
+            let name = project
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let dir = scratch.join(&name);
+            copy_project(&project, &dir).map_err(|e| {
+                WasmrunError::add_context(format!("Copying {}", project.display()), e)
+            })?;
+            Ok(Sample {
+                name,
+                dir,
+                expected: None,
+                edit: None,
+            })
+        })
+        .collect()
+}
+
+/// Copy a project's sources, leaving out hidden files and build output
+fn copy_project(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            if !SKIPPED_DIRS.contains(&name.as_str()) {
+                copy_project(&path, &to.join(&name))?;
+            }
+        } else if path.is_file() {
+            fs::copy(&path, to.join(&name))?;
+        }
+    }
+    Ok(())
+}
+
+/// The first source file under `dir` with one of `extensions`, or any file
+/// a comment can be appended to
+fn source_to_edit(dir: &Path, extensions: &[&str]) -> Option<PathBuf> {
+    let mut files = Vec::new();
+    crate::server::site::collect_files(dir, 0, &mut files);
+    let extension = |path: &PathBuf| {
+        path.extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    files
+        .iter()
+        .find(|path| extensions.contains(&extension(path).as_str()))
+        .or_else(|| {
+            files
+                .iter()
+                .find(|path| comment_for(&extension(path)).is_some())
+        })
+        .cloned()
+}
+
+/// How a line comment starts in files ending in `.extension`
+fn comment_for(extension: &str) -> Option<&'static str> {
+    match extension {
+        "rs" | "go" | "c" | "h" | "cc" | "cpp" | "ts" | "js" | "zig" => Some("//"),
+        "py" | "toml" => Some("#"),
+        _ => None,
+    }
+}
+
+/// Change `path` without changing what it means
+fn append_comment(path: &Path) -> std::io::Result<()> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let comment = comment_for(&extension).unwrap_or("//");
+    let mut contents = fs::read_to_string(path)?;
+    if !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    contents.push_str(&format!("{comment} edited by wasmrun selftest\n"));
+    fs::write(path, contents)
+}
+
+/// The `--language` value naming `language`
+fn language_key(language: &ProjectLanguage) -> String {
+    language.to_string().to_lowercase()
+}
+
+fn format_ms(ms: Option<u64>) -> String {
+    match ms {
+        Some(ms) if ms >= 1000 => format!("{:.1}s", ms as f64 / 1000.0),
+        Some(ms) => format!("{ms} ms"),
+        None => "?".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_builtin_examples_are_detected() {
+        let dir = tempdir().unwrap();
+        let samples = write_examples(dir.path()).unwrap();
+        assert_eq!(samples.len(), EXAMPLES.len());
+        for sample in &samples {
+            assert_eq!(
+                Some(detect_project_language(&sample.dir.to_string_lossy())),
+                sample.expected,
+                "{}",
+                sample.name
+            );
+            assert!(sample.edit.as_ref().unwrap().is_file(), "{}", sample.name);
+        }
+        let keys: Vec<String> = samples
+            .iter()
+            .map(|sample| language_key(sample.expected.as_ref().unwrap()))
+            .collect();
+        assert_eq!(keys, ["rust", "go", "asc", "c"]);
+    }
+
+    #[test]
+    fn test_copy_and_edit_project() {
+        let dir = tempdir().unwrap();
+        let examples = dir.path().join("examples");
+        fs::create_dir_all(examples.join("app/src")).unwrap();
+        fs::create_dir_all(examples.join("app/target/debug")).unwrap();
+        fs::create_dir_all(examples.join(".git")).unwrap();
+        fs::write(examples.join("app/Cargo.toml"), "[package]").unwrap();
+        fs::write(examples.join("app/src/lib.rs"), "pub fn f() {}").unwrap();
+        fs::write(examples.join("app/target/debug/out"), "").unwrap();
+
+        let scratch = dir.path().join("scratch");
+        let samples = copy_examples(&examples, &scratch).unwrap();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].name, "app");
+        assert!(!scratch.join("app/target").exists());
+
+        let edit = source_to_edit(&samples[0].dir, &["rs"]).unwrap();
+        assert_eq!(edit, scratch.join("app/src/lib.rs"));
+        append_comment(&edit).unwrap();
+        assert_eq!(
+            fs::read_to_string(&edit).unwrap(),
+            "pub fn f() {}\n// edited by wasmrun selftest\n"
+        );
+        assert_eq!(
+            fs::read_to_string(examples.join("app/src/lib.rs")).unwrap(),
+            "pub fn f() {}"
+        );
+    }
+}
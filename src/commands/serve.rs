@@ -0,0 +1,104 @@
+//! Serve a build output directory as it is
+//!
+//! The directory may hold several apps, one per subdirectory, which share the
+//! server (and its shared chunk route, see [`dedup`]). They are served as
+//! prebuilt files: nothing here builds, watches or reloads them, so one app
+//! can't hold up the others. Building is `run` and `watch`, one project each.
+
+use crate::error::{Result, ServerError, WasmrunError};
+use crate::server::dedup;
+use crate::server::gallery;
+use crate::server::listing;
+use crate::server::site::{self, Site};
+use crate::server::urls::ServerUrls;
+use crate::server::utils::ServerUtils;
+use crate::server::wasm;
+use crate::utils::PathResolver;
+use std::path::Path;
+
+/// Handle serve command
+pub fn handle_serve_command(
+    path: &Option<String>,
+    positional_path: &Option<String>,
+    entry: Option<&str>,
+    gallery: bool,
+    listings: bool,
+    port: u16,
+    serve: bool,
+) -> Result<()> {
+    let resolved_path = PathResolver::resolve_input_path(positional_path.clone(), path.clone());
+    PathResolver::validate_directory_exists(&resolved_path)?;
+    let site = if gallery {
+        gallery_site(Path::new(&resolved_path), entry)?
+    } else {
+        Site::discover(Path::new(&resolved_path), entry)?
+    };
+    crate::server::replace_named_instance();
+    let port = ServerUtils::handle_port_conflict(port)?;
+
+    let wasm_url = site.url_path(&site.wasm_path);
+    println!("📁 Serving directory: {}", site.root.display());
+    println!("   Entry:  {wasm_url}");
+    if let Some(js_path) = &site.js_path {
+        println!("   Glue:   {}", site.url_path(js_path));
+    }
+    println!(
+        "   Page:   {}",
+        if gallery {
+            "module gallery"
+        } else if site.has_index {
+            site::INDEX_FILE
+        } else {
+            "wasmrun runner"
+        }
+    );
+    println!("   Files:  {}", site.file_count);
+    if listings {
+        listing::enable();
+        println!("   Lists:  directories without {}", site::INDEX_FILE);
+    }
+    if let Some(summary) = dedup::summary(&site.root) {
+        println!("   Shared: {summary}");
+    }
+    println!("🌐 Serving at:");
+    for url in ServerUrls::for_port(port).urls {
+        println!(
+            "   {} {:<9} {}",
+            url.kind.icon(),
+            format!("{}:", url.kind.label()),
+            url.link(&url.display)
+        );
+    }
+
+    let wasm_path = site.wasm_path.to_string_lossy().to_string();
+    site::set_root(site.root.clone());
+    let served = match &site.js_path {
+        Some(js_path) => wasm::serve_wasm_bindgen_files(
+            &wasm_path,
+            &js_path.to_string_lossy(),
+            port,
+            &wasm_url,
+            serve,
+        ),
+        None => wasm::serve_wasm_file(&wasm_path, port, &wasm_url, serve),
+    };
+    served.map_err(|e| WasmrunError::Server(ServerError::startup_failed(port, e)))
+}
+
+/// The directory with gallery mode on; the entry only matters to requests
+/// outside the gallery, so any module will do when none is given
+fn gallery_site(root: &Path, entry: Option<&str>) -> Result<Site> {
+    let canonical = std::fs::canonicalize(root)
+        .map_err(|_| WasmrunError::path(format!("Directory not found: {}", root.display())))?;
+    let modules = gallery::modules(&canonical);
+    let Some(first) = modules.first() else {
+        return Err(WasmrunError::path(format!(
+            "No WASM files found in directory: {}",
+            root.display()
+        )));
+    };
+    let site = Site::discover(root, Some(entry.unwrap_or(&first.url)))?;
+    gallery::enable();
+    println!("🖼️  Gallery of {} module(s)", modules.len());
+    Ok(site)
+}
@@ -0,0 +1,57 @@
+//! Cache statistics
+
+use crate::config::WasmrunConfig;
+use crate::error::Result;
+use crate::registry::Registry;
+use crate::utils::{list_workspaces, ArtifactStore, CommandExecutor};
+
+/// Number of builds listed from the build history
+const RECENT_BUILDS: usize = 5;
+
+/// Handle stats command
+pub fn handle_stats_command() -> Result<()> {
+    let cache_dir = WasmrunConfig::cache_dir()?;
+    let store = ArtifactStore::open_in(&cache_dir)?;
+    let stats = store.stats();
+    let (objects, store_size) = store.usage();
+
+    println!("📦 Artifact store: {}", store.root().display());
+    println!(
+        "   Objects:      {objects} ({})",
+        CommandExecutor::format_file_size(store_size)
+    );
+    println!("   Hits:         {}", stats.hits);
+    println!("   Misses:       {}", stats.misses);
+    match stats.hit_rate() {
+        Some(rate) => println!("   Hit rate:     {:.1}%", rate * 100.0),
+        None => println!("   Hit rate:     n/a"),
+    }
+    println!(
+        "   Deduplicated: {}",
+        CommandExecutor::format_file_size(stats.bytes_deduplicated)
+    );
+
+    let workspaces = list_workspaces(&cache_dir);
+    let workspace_size: u64 = workspaces.iter().map(|workspace| workspace.size).sum();
+    println!();
+    println!("🗂️  Workspaces: {}", workspaces.len());
+    println!(
+        "   Total size:   {}",
+        CommandExecutor::format_file_size(workspace_size)
+    );
+
+    let builds = Registry::open()?.recent_builds(None, RECENT_BUILDS)?;
+    if !builds.is_empty() {
+        println!();
+        println!("🏗️  Recent builds:");
+        for build in builds {
+            let status = if build.success { "✅" } else { "❌" };
+            println!(
+                "   {status} {} [{}] {} ms — {}",
+                build.finished_at, build.builder, build.duration_ms, build.project_path
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -1,21 +1,34 @@
-use crate::error::Result;
+use crate::error::{Result, ServerError, WasmrunError};
 use crate::server;
 use crate::ui::{print_info, print_status, print_success};
 
-/// Handle stop command
-pub fn handle_stop_command() -> Result<()> {
-    if !server::is_server_running() {
-        print_info("No Wasmrun server is currently running");
-        return Ok(());
-    }
+/// Handle stop command; stops the server named `target` or on port `target`,
+/// or every running server when there is no target
+pub fn handle_stop_command(target: Option<&str>) -> Result<()> {
+    let Some(target) = target else {
+        if !server::is_server_running() {
+            print_info("No Wasmrun server is currently running");
+            return Ok(());
+        }
 
-    print_status("Stopping Wasmrun server...");
+        print_status("Stopping Wasmrun server...");
+        server::stop_existing_server()?;
+        print_success("Wasmrun Server Stopped", "Server terminated successfully");
+        return Ok(());
+    };
 
-    match server::stop_existing_server() {
+    print_status(&format!("Stopping Wasmrun server '{target}'..."));
+    match server::stop_matching(target) {
         Ok(()) => {
             print_success("Wasmrun Server Stopped", "Server terminated successfully");
             Ok(())
         }
+        Err(WasmrunError::Server(ServerError::NotRunning)) => {
+            print_info(&format!(
+                "No running Wasmrun server matches '{target}'; see wasmrun ps"
+            ));
+            Ok(())
+        }
         Err(e) => Err(e),
     }
 }
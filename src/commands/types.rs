@@ -0,0 +1,75 @@
+//! TypeScript definitions of a module's API
+
+use super::ci_report::collect_artifacts;
+use crate::error::{Result, WasmrunError};
+use crate::utils::{typescript, PathResolver, Workspace};
+use std::fs;
+use std::path::Path;
+
+/// Handle types command: print the module's API as TypeScript, or write it to `output`
+pub fn handle_types_command(
+    path: &Option<String>,
+    positional_path: &Option<String>,
+    output: &Option<String>,
+) -> Result<()> {
+    let target = PathResolver::resolve_input_path(positional_path.clone(), path.clone());
+    let module = find_module(&target)?;
+    let types = typescript::for_module(&module).map_err(WasmrunError::from)?;
+
+    match output {
+        Some(output) => {
+            fs::write(output, &types)
+                .map_err(|e| WasmrunError::add_context(format!("Writing {output}"), e))?;
+            eprintln!("✅ Wrote the definitions of {module} to {output}");
+        }
+        None => print!("{types}"),
+    }
+    Ok(())
+}
+
+/// The file given, or the most recently built module of a project, looking in
+/// its build workspace as well as the directory itself
+fn find_module(target: &str) -> Result<String> {
+    let target_path = Path::new(target);
+    if target_path.is_file() {
+        return Ok(target.to_string());
+    }
+    if !target_path.is_dir() {
+        return Err(WasmrunError::FileNotFound {
+            path: target.to_string(),
+        });
+    }
+
+    let mut dirs = vec![target.to_string()];
+    if let Ok(workspace) = Workspace::path_for(target) {
+        if workspace.is_dir() {
+            dirs.push(workspace.to_string_lossy().to_string());
+        }
+    }
+    collect_artifacts(&dirs)?
+        .into_iter()
+        .max_by_key(|artifact| fs::metadata(artifact).and_then(|meta| meta.modified()).ok())
+        .ok_or_else(|| {
+            WasmrunError::path(format!(
+                "No .wasm module found in {target}; build it with `wasmrun compile` first"
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_module_in_directory() {
+        let dir = tempdir().unwrap();
+        let target = dir.path().to_str().unwrap();
+        assert!(find_module(target).is_err());
+
+        let module = dir.path().join("app.wasm");
+        fs::write(&module, b"\0asm\x01\0\0\0").unwrap();
+        assert_eq!(find_module(target).unwrap(), module.to_string_lossy());
+        assert!(find_module(&dir.path().join("missing").to_string_lossy()).is_err());
+    }
+}
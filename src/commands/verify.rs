@@ -1,7 +1,7 @@
 use crate::cli::CommandValidator;
 use crate::config::WASM_MAGIC_BYTES;
 use crate::error::{Result, WasmError, WasmrunError};
-use crate::utils::PathResolver;
+use crate::utils::{ArtifactKind, CommandExecutor, Limits, ModuleInspection, PathResolver};
 use std::fs;
 use std::io::{Cursor, Read};
 use std::path::Path;
@@ -66,19 +66,12 @@ pub fn handle_inspect_command(
     path: &Option<String>,
     positional_path: &Option<String>,
 ) -> Result<()> {
-    let resolved_path = PathResolver::resolve_input_path(positional_path.clone(), path.clone());
-    println!("Resolved path: {resolved_path:?}");
-
-    let wasm_path = CommandValidator::validate_verify_args(path, positional_path)?;
-
-    PathResolver::validate_wasm_file(&wasm_path)?;
-
-    println!("🔍 Inspecting WebAssembly file: {wasm_path}");
+    let wasm_path = resolve_and_validate_wasm_path(path, positional_path)?;
 
-    print_detailed_binary_info(&wasm_path)
+    let inspection = ModuleInspection::from_file(&wasm_path)
         .map_err(|e| WasmrunError::Wasm(WasmError::validation_failed(e)))?;
 
-    println!("Inspection completed successfully.");
+    print_inspection(&wasm_path, &inspection);
     Ok(())
 }
 
@@ -467,11 +460,12 @@ pub fn print_verification_results(path: &str, results: &VerificationResult, deta
     println!("\x1b[1;34m╰\x1b[0m");
 }
 
-pub fn print_detailed_binary_info(path: &str) -> std::result::Result<(), String> {
-    let wasm_bytes = fs::read(path).map_err(|e| format!("Error reading file: {e}"))?;
+/// Print what `wasmrun inspect` found in a binary
+pub fn print_inspection(path: &str, inspection: &ModuleInspection) {
+    let file_size = fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
 
     println!("\n\x1b[1;34m╭\x1b[0m");
-    println!("  🔬 \x1b[1;36mDetailed WASM Binary Analysis\x1b[0m\n");
+    println!("  🔬 \x1b[1;36mWASM Module Inspection\x1b[0m\n");
     println!(
         "  📄 \x1b[1;34mFile:\x1b[0m \x1b[1;33m{}\x1b[0m",
         Path::new(path)
@@ -480,123 +474,147 @@ pub fn print_detailed_binary_info(path: &str) -> std::result::Result<(), String>
             .to_string_lossy()
     );
     println!(
-        "  💾 \x1b[1;34mSize:\x1b[0m \x1b[1;33m{} bytes\x1b[0m",
-        wasm_bytes.len()
+        "  💾 \x1b[1;34mSize:\x1b[0m \x1b[1;33m{}\x1b[0m",
+        CommandExecutor::format_file_size(file_size)
     );
-
-    if wasm_bytes.len() < 8 {
-        println!("  ❌ \x1b[1;31mFile too small to be a valid WASM module\x1b[0m");
-        println!("\x1b[1;34m╰\x1b[0m");
-        return Err("File too small".to_string());
+    println!(
+        "  🏷️  \x1b[1;34mArtifact:\x1b[0m \x1b[1;36m{}\x1b[0m \x1b[0;90m(binary version {})\x1b[0m",
+        inspection.artifact, inspection.version
+    );
+    if inspection.nested_modules > 0 {
+        println!(
+            "  📦 \x1b[1;34mNested core modules:\x1b[0m \x1b[1;33m{}\x1b[0m",
+            inspection.nested_modules
+        );
+    }
+    if inspection.artifact != ArtifactKind::Component {
+        println!(
+            "  🧩 \x1b[1;34mFunctions:\x1b[0m \x1b[1;33m{}\x1b[0m defined, \x1b[1;33m{}\x1b[0m imported",
+            inspection.function_count,
+            inspection.imported_function_count()
+        );
+    }
+    match inspection.start_function {
+        Some(index) => println!("  🚀 \x1b[1;34mStart function:\x1b[0m \x1b[1;33m{index}\x1b[0m"),
+        None => println!("  ℹ️  \x1b[0;90mNo start function\x1b[0m"),
     }
 
-    let magic_bytes = &wasm_bytes[0..4];
     println!(
-        "  🔑 \x1b[1;34mMagic bytes:\x1b[0m \x1b[1;33m{:02X} {:02X} {:02X} {:02X}\x1b[0m",
-        magic_bytes[0], magic_bytes[1], magic_bytes[2], magic_bytes[3]
+        "\n  📥 \x1b[1;34mImports:\x1b[0m \x1b[1;33m{}\x1b[0m",
+        inspection.imports.len()
     );
-
-    if magic_bytes == b"\0asm" {
-        println!("  ✅ \x1b[1;32mValid WebAssembly magic bytes\x1b[0m");
-    } else {
-        println!("  ❌ \x1b[1;31mInvalid magic bytes, not a WASM file\x1b[0m");
-        println!("\x1b[1;34m╰\x1b[0m");
-        return Err("Invalid magic bytes".to_string());
+    for import in &inspection.imports {
+        let name = if import.module.is_empty() {
+            import.name.clone()
+        } else {
+            format!("{}::{}", import.module, import.name)
+        };
+        println!(
+            "     \x1b[1;36m{:<9}\x1b[0m \x1b[1;37m{name}\x1b[0m",
+            import.kind
+        );
     }
 
-    let version = u32::from_le_bytes([wasm_bytes[4], wasm_bytes[5], wasm_bytes[6], wasm_bytes[7]]);
-    println!("  📊 \x1b[1;34mWASM version:\x1b[0m \x1b[1;33m{version}\x1b[0m");
-
-    if version != 1 {
-        println!("  ⚠️ \x1b[1;33mUnexpected WASM version (expected 1)\x1b[0m");
+    println!(
+        "\n  📤 \x1b[1;34mExports:\x1b[0m \x1b[1;33m{}\x1b[0m",
+        inspection.exports.len()
+    );
+    for export in &inspection.exports {
+        let entry = if is_entry_point(&export.name) {
+            " \x1b[1;32m(entry point)\x1b[0m"
+        } else {
+            ""
+        };
+        println!(
+            "     \x1b[1;36m{:<9}\x1b[0m \x1b[1;37m{}\x1b[0m \x1b[0;90m#{}\x1b[0m{entry}",
+            export.kind, export.name, export.index
+        );
     }
 
-    let mut _offset = 8;
-    let _view = std::io::Cursor::new(&wasm_bytes);
-
-    println!("\n  📋 \x1b[1;34mSection analysis:\x1b[0m");
-    let mut section_count = 0;
-
-    // Known section types
-    // TODO: Move to a constant or config and read from there.
-    let section_names = [
-        "Custom",
-        "Type",
-        "Import",
-        "Function",
-        "Table",
-        "Memory",
-        "Global",
-        "Export",
-        "Start",
-        "Element",
-        "Code",
-        "Data",
-        "DataCount",
-    ];
-
-    let mut reader = Cursor::new(wasm_bytes.clone());
-    reader.set_position(8);
-
-    while (reader.position() as usize) < wasm_bytes.len() {
-        section_count += 1;
-
-        let section_start_offset = reader.position() as usize;
-
-        if let Ok(section_id) = read_leb128_u32(&mut reader) {
-            let section_size = read_leb128_u32(&mut reader).unwrap_or(0);
-
-            let section_name = if section_id < section_names.len() as u32 {
-                section_names[section_id as usize]
-            } else {
-                "Unknown"
-            };
-
-            let section_start = reader.position() as usize;
-            let section_end = section_start + section_size as usize;
-
-            println!("  \x1b[1;36m{:2}.\x1b[0m \x1b[1;37m{:10}\x1b[0m ID: {:2}, Size: {:6} bytes, Offset: 0x{:08X}-0x{:08X}",
-                section_count, section_name, section_id, section_size, section_start_offset, section_end - 1);
-
-            if section_start_offset <= 130 && section_end > 126 {
-                println!("     \x1b[1;31m⚠️  WARNING: This section contains offset 128 where errors commonly occur!\x1b[0m");
-
-                if section_id == 5 {
-                    println!("     \x1b[1;33mChecking Memory section details:\x1b[0m");
-                    let current_pos = reader.position();
-                    if section_size >= 1 && (current_pos as usize) < wasm_bytes.len() {
-                        if let Ok(num_memories) = read_leb128_u32(&mut reader) {
-                            println!("     \x1b[1;33mNumber of memories: {num_memories}\x1b[0m");
-                        }
-                    }
-                    reader.set_position(current_pos);
-                }
+    if let Some(world) = &inspection.world {
+        println!(
+            "\n  🌐 \x1b[1;34mWIT world:\x1b[0m \x1b[1;33m{}\x1b[0m",
+            world.name
+        );
+        for (direction, items) in [("import", &world.imports), ("export", &world.exports)] {
+            for item in items {
+                println!("     \x1b[1;36m{direction:<9}\x1b[0m \x1b[1;37m{item}\x1b[0m");
             }
+        }
+    }
 
-            // Skip. Revisit.
-            reader.set_position((section_start + section_size as usize) as u64);
-        } else {
-            break;
+    if !inspection.memories.is_empty() {
+        println!("\n  💾 \x1b[1;34mMemories:\x1b[0m");
+        for (index, memory) in inspection.memories.iter().enumerate() {
+            let maximum = memory
+                .maximum
+                .map_or("unlimited".to_string(), |max| format!("{max} pages"));
+            println!(
+                "     \x1b[1;36m{index}.\x1b[0m \x1b[1;33m{} pages\x1b[0m initial ({}), max \x1b[1;33m{maximum}\x1b[0m{}",
+                memory.initial,
+                CommandExecutor::format_file_size(memory.initial.saturating_mul(64 * 1024)),
+                limit_flags(memory)
+            );
         }
     }
 
-    println!("\n  📊 \x1b[1;34mTotal sections found:\x1b[0m \x1b[1;33m{section_count}\x1b[0m");
+    if !inspection.tables.is_empty() {
+        println!("\n  📊 \x1b[1;34mTables:\x1b[0m");
+        for (index, table) in inspection.tables.iter().enumerate() {
+            let maximum = table
+                .maximum
+                .map_or("unlimited".to_string(), |max| max.to_string());
+            println!(
+                "     \x1b[1;36m{index}.\x1b[0m \x1b[1;37m{}\x1b[0m \x1b[1;33m{}\x1b[0m initial, max \x1b[1;33m{maximum}\x1b[0m{}",
+                table.element_type.as_deref().unwrap_or("?"),
+                table.initial,
+                limit_flags(table)
+            );
+        }
+    }
 
-    if section_count > 0 {
-        println!("  ✅ \x1b[1;32mWASM file structure seems valid\x1b[0m");
-    } else {
-        println!("  ❌ \x1b[1;31mNo sections found in WASM file\x1b[0m");
+    if !inspection.custom_sections.is_empty() {
+        println!("\n  🗂️  \x1b[1;34mCustom sections:\x1b[0m");
+        for section in &inspection.custom_sections {
+            println!(
+                "     \x1b[1;37m{:<24}\x1b[0m \x1b[0;90m({})\x1b[0m",
+                section.name,
+                CommandExecutor::format_file_size(section.size as u64)
+            );
+        }
     }
 
-    // Diagnostic advice.
-    println!("\n  🔍 \x1b[1;34mPossible Issues:\x1b[0m");
-    println!("     \x1b[0;37m• Sections in incorrect order (browsers require specific section ordering)\x1b[0m");
-    println!("     \x1b[0;37m• Memory section with invalid configuration\x1b[0m");
-    println!("     \x1b[0;37m• Binary compiled with incompatible flags or WASM features\x1b[0m");
-    println!("     \x1b[0;37m• File corruption\x1b[0m");
+    println!("\n  📊 \x1b[1;34mHow wasmrun serves it:\x1b[0m");
+    match inspection.artifact {
+        ArtifactKind::WasmBindgen => println!(
+            "     \x1b[0;37mWith its wasm-bindgen JS glue, using the application page\x1b[0m"
+        ),
+        ArtifactKind::Wasi => println!(
+            "     \x1b[0;37mOn the console page with WASI support; `wasmrun exec` runs it natively\x1b[0m"
+        ),
+        ArtifactKind::Component => println!(
+            "     \x1b[1;33mThrough its jco transpilation, if there is one next to it; `wasmrun exec` runs wasi:cli commands natively\x1b[0m"
+        ),
+        ArtifactKind::Core => println!("     \x1b[0;37mOn the console page\x1b[0m"),
+    }
     println!("\x1b[1;34m╰\x1b[0m");
+}
 
-    Ok(())
+/// Trailing notes for a memory's or table's limits, e.g. ` [shared, imported]`
+fn limit_flags(limits: &Limits) -> String {
+    let flags: Vec<&str> = [
+        (limits.is_64, "64-bit"),
+        (limits.shared, "shared"),
+        (limits.imported, "imported"),
+    ]
+    .into_iter()
+    .filter_map(|(set, flag)| set.then_some(flag))
+    .collect();
+    if flags.is_empty() {
+        String::new()
+    } else {
+        format!(" \x1b[0;90m[{}]\x1b[0m", flags.join(", "))
+    }
 }
 
 /// Check if a function name is a known entry point
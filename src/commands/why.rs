@@ -0,0 +1,521 @@
+//! Likely causes of a module failing to load in the browser
+
+use super::audit::csp_sources;
+use super::ci_report::collect_artifacts;
+use crate::config::active_settings;
+use crate::error::{Result, WasmrunError};
+use crate::server::failures::{self, FailureReport};
+use crate::server::wasm::wasm_bindgen_glue;
+use crate::utils::wasm_features::{used_proposals, Proposal};
+use crate::utils::{ArtifactKind, ImportEntry, ModuleInspection, PathResolver};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// WebAssembly page size
+const PAGE_BYTES: u64 = 64 * 1024;
+
+/// Initial memory above which allocation fails on some devices
+const LARGE_MEMORY_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// What is known about the module and the last time it failed
+struct Evidence {
+    inspection: std::result::Result<ModuleInspection, String>,
+    proposals: Vec<Proposal>,
+    has_glue: bool,
+    /// Headers sent with the page, from the report or the current configuration
+    headers: BTreeMap<String, String>,
+    report: Option<FailureReport>,
+}
+
+#[derive(Debug)]
+struct Cause {
+    /// 0–100; how well the evidence supports this cause
+    score: u8,
+    title: String,
+    evidence: Vec<String>,
+    fix: String,
+}
+
+impl Cause {
+    fn new(score: u8, title: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            score,
+            title: title.into(),
+            evidence: Vec::new(),
+            fix: fix.into(),
+        }
+    }
+
+    fn because(mut self, evidence: impl Into<String>) -> Self {
+        self.evidence.push(evidence.into());
+        self
+    }
+}
+
+/// Handle why command: rank the likely causes of a failed instantiation
+pub fn handle_why_command(path: &Option<String>, positional_path: &Option<String>) -> Result<()> {
+    let target = PathResolver::resolve_input_path(positional_path.clone(), path.clone());
+    let reports = failures::load();
+    let module = find_module(&target, &reports)?;
+    let report = reports.iter().rev().find(|report| report.module == module);
+    let failure_count = reports
+        .iter()
+        .filter(|report| report.module == module)
+        .count();
+
+    let bytes =
+        fs::read(&module).map_err(|e| WasmrunError::add_context(format!("Reading {module}"), e))?;
+    let evidence = Evidence {
+        inspection: ModuleInspection::from_bytes(&bytes),
+        proposals: used_proposals(&bytes),
+        has_glue: wasm_bindgen_glue(&module).is_some(),
+        headers: report.map_or_else(configured_headers, |report| report.headers.clone()),
+        report: report.cloned(),
+    };
+
+    println!("🩺 Why {module} fails to load\n");
+    match &evidence.report {
+        Some(report) => {
+            println!("   Last failure: {}: {}", report.error, report.message);
+            println!(
+                "   Browser:      {} ({} failure{} reported, last at {})",
+                report.user_agent,
+                failure_count,
+                if failure_count == 1 { "" } else { "s" },
+                report.reported_at
+            );
+            println!("   Page:         {}", report.page);
+        }
+        None => println!(
+            "   No browser has reported a failure for this module yet; open it with `wasmrun run` to collect one.\n   Checking the module and configuration only."
+        ),
+    }
+    if let Ok(inspection) = &evidence.inspection {
+        println!("   Module:       {}", inspection.artifact);
+    }
+    if !evidence.proposals.is_empty() {
+        let names: Vec<String> = evidence.proposals.iter().map(ToString::to_string).collect();
+        println!("   Proposals:    {}", names.join(", "));
+    }
+
+    let causes = diagnose(&evidence);
+    if causes.is_empty() {
+        println!("\n✅ No likely cause found in the module, headers or browser support");
+        return Ok(());
+    }
+    println!("\n🔎 Likely causes, most likely first:\n");
+    for (rank, cause) in causes.iter().enumerate() {
+        let likelihood = match cause.score {
+            70.. => "high",
+            40..=69 => "medium",
+            _ => "low",
+        };
+        println!("{:>3}. [{likelihood}] {}", rank + 1, cause.title);
+        for line in &cause.evidence {
+            println!("       • {line}");
+        }
+        println!("       💡 {}\n", cause.fix);
+    }
+    Ok(())
+}
+
+/// The module to diagnose: the file given, or in a directory the module that
+/// failed last, or else the most recently built one
+fn find_module(target: &str, reports: &[FailureReport]) -> Result<String> {
+    let canonical = |path: &str| {
+        fs::canonicalize(path)
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|_| path.to_string())
+    };
+    let target_path = Path::new(target);
+    if target_path.is_file() {
+        return Ok(canonical(target));
+    }
+    if !target_path.is_dir() {
+        return Err(WasmrunError::FileNotFound {
+            path: target.to_string(),
+        });
+    }
+
+    let dir = canonical(target);
+    let reported = reports.iter().rev().find(|report| {
+        Path::new(&report.module).starts_with(&dir)
+            || report
+                .project
+                .as_deref()
+                .is_some_and(|project| canonical(project) == dir)
+    });
+    if let Some(report) = reported.filter(|report| Path::new(&report.module).is_file()) {
+        return Ok(report.module.clone());
+    }
+
+    collect_artifacts(std::slice::from_ref(&dir))?
+        .into_iter()
+        .max_by_key(|artifact| fs::metadata(artifact).and_then(|meta| meta.modified()).ok())
+        .map(|artifact| canonical(&artifact))
+        .ok_or_else(|| WasmrunError::path(format!("No .wasm module found in {dir}")))
+}
+
+/// Headers the server would send now, for when no browser has reported
+fn configured_headers() -> BTreeMap<String, String> {
+    let mut headers = active_settings().server.headers;
+    crate::server::isolation::apply(&mut headers);
+    headers
+}
+
+fn header<'a>(headers: &'a BTreeMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Every cause the evidence points to, most likely first
+fn diagnose(evidence: &Evidence) -> Vec<Cause> {
+    let report = evidence.report.as_ref();
+    let message = report.map(|report| report.message.as_str()).unwrap_or("");
+    let error = report.map(|report| report.error.as_str()).unwrap_or("");
+    let mut causes = Vec::new();
+
+    let inspection = match &evidence.inspection {
+        Ok(inspection) => inspection,
+        Err(reason) => {
+            causes.push(
+                Cause::new(
+                    95,
+                    "The file is not a valid WebAssembly module",
+                    "Rebuild it, and check the build writes the .wasm file the page loads",
+                )
+                .because(reason.clone()),
+            );
+            return causes;
+        }
+    };
+
+    if inspection.artifact == ArtifactKind::Component {
+        causes.push(
+            Cause::new(
+                95,
+                "Browsers can't instantiate component-model components",
+                "Transpile it with `jco transpile` next to the component; wasmrun then serves the transpilation",
+            )
+            .because("The file is a component, not a core module"),
+        );
+    }
+
+    if message.contains("magic") || message.contains("BufferSource argument is empty") {
+        causes.push(
+            Cause::new(
+                85,
+                "The page fetched something other than the module",
+                "Check the URL the page loads the module from; a 404 page or an empty file fails this way",
+            )
+            .because(format!("The browser saw no wasm header: {message}")),
+        );
+    }
+
+    if message.contains("MIME") {
+        let mut cause = Cause::new(
+            85,
+            "The module is not served as application/wasm",
+            "Remove the Content-Type override from server.headers or middleware.rhai; streaming compilation requires application/wasm",
+        )
+        .because(format!("The browser rejected the response type: {message}"));
+        if let Some(content_type) = header(&evidence.headers, "Content-Type") {
+            cause = cause.because(format!("server.headers sets Content-Type: {content_type}"));
+        }
+        causes.push(cause);
+    }
+
+    if let Some(policy) = header(&evidence.headers, "Content-Security-Policy") {
+        let scripts =
+            csp_sources(policy, "script-src").or_else(|| csp_sources(policy, "default-src"));
+        let allows_wasm = scripts.is_none_or(|sources| {
+            sources
+                .iter()
+                .any(|source| *source == "'wasm-unsafe-eval'" || *source == "'unsafe-eval'")
+        });
+        if !allows_wasm {
+            let reported =
+                message.contains("Content Security Policy") || message.contains("unsafe-eval");
+            let mut cause = Cause::new(
+                if reported { 95 } else { 60 },
+                "The Content Security Policy forbids compiling WebAssembly",
+                "Add 'wasm-unsafe-eval' to script-src in the Content-Security-Policy header",
+            )
+            .because(format!("Content-Security-Policy: {policy}"));
+            if reported {
+                cause = cause.because(format!("The browser blocked compilation: {message}"));
+            }
+            causes.push(cause);
+        }
+    }
+
+    for proposal in &evidence.proposals {
+        let supported = report.and_then(|report| report.supports.get(proposal.key()).copied());
+        let cause = match supported {
+            Some(true) => continue,
+            Some(false) => Cause::new(
+                90,
+                format!("The browser does not support {proposal}"),
+                format!("Build without {proposal}, or use a browser that supports it"),
+            )
+            .because(format!("The module uses {proposal}"))
+            .because(format!(
+                "The {} probe failed in {}",
+                proposal.key(),
+                report
+                    .map(|report| report.user_agent.as_str())
+                    .unwrap_or("the browser")
+            )),
+            None => Cause::new(
+                if error == "CompileError" { 50 } else { 20 },
+                format!("The module needs {proposal}, which not every browser supports"),
+                format!("Check the browser supports {proposal}, or build without it"),
+            )
+            .because(format!("The module uses {proposal}")),
+        };
+        causes.push(cause);
+    }
+
+    let shared_memory = inspection.memories.iter().any(|memory| memory.shared);
+    if shared_memory {
+        let isolated = match report {
+            Some(report) => report.cross_origin_isolated,
+            None => {
+                header(&evidence.headers, "Cross-Origin-Opener-Policy") == Some("same-origin")
+                    && header(&evidence.headers, "Cross-Origin-Embedder-Policy").is_some()
+            }
+        };
+        if !isolated {
+            let mut cause = Cause::new(
+                if report.is_some() { 90 } else { 65 },
+                "Shared memory needs a cross-origin isolated page",
+                "Serve with --coi (or server.cross_origin_isolation = true) so SharedArrayBuffer is available",
+            )
+            .because("The module uses shared memory");
+            cause = cause.because(match report {
+                Some(_) => "The page was not cross-origin isolated".to_string(),
+                None => "The configured headers don't include COOP and COEP".to_string(),
+            });
+            if report.is_some_and(|report| !report.secure_context) {
+                cause = cause.because(
+                    "The page was not a secure context either; use localhost or serve with --tls",
+                );
+            }
+            causes.push(cause);
+        }
+    }
+
+    if inspection.artifact == ArtifactKind::WasmBindgen {
+        if !evidence.has_glue {
+            causes.push(
+                Cause::new(
+                    85,
+                    "The wasm-bindgen module has no JS glue next to it",
+                    "Run wasm-bindgen (or wasm-pack) and serve the generated .js file with the module",
+                )
+                .because("The module imports from wasm-bindgen's generated JS"),
+            );
+        } else if report.is_some_and(|report| report.page == "console") {
+            causes.push(
+                Cause::new(
+                    80,
+                    "The module was served on the console page instead of through its glue",
+                    "Run the project, or the glue's .js file, so the page loads the module through wasm-bindgen",
+                )
+                .because("The console page instantiates the module without wasm-bindgen's imports"),
+            );
+        }
+    }
+
+    if let Some(import) = missing_import(message, inspection) {
+        let (score, fix) = match import.module.as_str() {
+            "wasi_snapshot_preview1" | "wasi_unstable" => (
+                85,
+                "It is a WASI module: run it with `wasmrun exec`, or give the page a WASI shim",
+            ),
+            "gojs" | "go" => (
+                85,
+                "It is a Go module: load it through the wasm_exec.js that matches your Go version",
+            ),
+            "wbg" | "__wbindgen_placeholder__" | "__wbindgen_externref_xform__" => (
+                85,
+                "Load the module through its wasm-bindgen glue instead of instantiating it directly",
+            ),
+            _ => (
+                75,
+                "Provide the import in the import object, or serve the JS glue the module was built with",
+            ),
+        };
+        causes.push(
+            Cause::new(
+                score,
+                format!(
+                    "The import {}.{} was not provided",
+                    import.module, import.name
+                ),
+                fix,
+            )
+            .because(format!("{error}: {message}")),
+        );
+    } else if error == "LinkError" || message.contains("Import #") {
+        causes.push(
+            Cause::new(
+                70,
+                "An import the module needs was not provided",
+                "Compare the module's imports (`wasmrun inspect`) with the import object the page passes",
+            )
+            .because(format!("{error}: {message}")),
+        );
+    }
+
+    let initial_memory: u64 = inspection
+        .memories
+        .iter()
+        .filter(|memory| !memory.imported)
+        .map(|memory| memory.initial * PAGE_BYTES)
+        .sum();
+    let allocation_failed = error == "RangeError" || message.contains("Out of memory");
+    if allocation_failed || initial_memory >= LARGE_MEMORY_BYTES {
+        let mut cause = Cause::new(
+            if allocation_failed { 80 } else { 30 },
+            "The module's initial memory could not be allocated",
+            "Lower the initial memory, e.g. -C link-arg=--initial-memory=… or -sINITIAL_MEMORY=…",
+        )
+        .because(format!(
+            "The module asks for {} MiB up front",
+            initial_memory / (1024 * 1024)
+        ));
+        if allocation_failed {
+            cause = cause.because(format!("{error}: {message}"));
+        }
+        causes.push(cause);
+    }
+
+    if error == "RuntimeError" && inspection.start_function.is_some() {
+        causes.push(
+            Cause::new(
+                75,
+                "The module's start function trapped during instantiation",
+                "Run it with `wasmrun exec` to see the trap, or move the work out of the start function",
+            )
+            .because(format!("RuntimeError: {message}")),
+        );
+    }
+
+    if report.is_some() && causes.is_empty() {
+        causes.push(
+            Cause::new(
+                10,
+                "The error does not match a known cause",
+                "Check the browser console for the full stack trace",
+            )
+            .because(format!("{error}: {message}")),
+        );
+    }
+
+    causes.sort_by_key(|cause| std::cmp::Reverse(cause.score));
+    causes
+}
+
+/// The import a link error names, by Chrome's `Import #n` index or by quoted name
+fn missing_import<'a>(message: &str, inspection: &'a ModuleInspection) -> Option<&'a ImportEntry> {
+    if let Some(rest) = message.split("Import #").nth(1) {
+        let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+        if let Some(import) = digits
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| inspection.imports.get(index))
+        {
+            return Some(import);
+        }
+    }
+    inspection.imports.iter().find(|import| {
+        [
+            format!("\"{}\"", import.name),
+            format!("'{}'", import.name),
+            format!("{}:{}", import.module, import.name),
+        ]
+        .iter()
+        .any(|needle| message.contains(needle.as_str()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evidence(wat: &str, report: Option<FailureReport>) -> Evidence {
+        let bytes = wat::parse_str(wat).unwrap();
+        Evidence {
+            inspection: ModuleInspection::from_bytes(&bytes),
+            proposals: used_proposals(&bytes),
+            has_glue: false,
+            headers: BTreeMap::new(),
+            report,
+        }
+    }
+
+    fn report(error: &str, message: &str) -> FailureReport {
+        FailureReport {
+            error: error.to_string(),
+            message: message.to_string(),
+            page: "console".to_string(),
+            secure_context: true,
+            ..FailureReport::default()
+        }
+    }
+
+    #[test]
+    fn test_missing_import_ranks_first() {
+        let evidence = evidence(
+            r#"(module (import "env" "log" (func)) (import "env" "now" (func)))"#,
+            Some(report(
+                "LinkError",
+                r#"WebAssembly.instantiate(): Import #1 "env" "now": function import requires a callable"#,
+            )),
+        );
+        let causes = diagnose(&evidence);
+        assert_eq!(causes[0].title, "The import env.now was not provided");
+    }
+
+    #[test]
+    fn test_unsupported_proposal_and_isolation() {
+        let mut failure = report("CompileError", "invalid value type 'Simd128'");
+        failure.supports.insert("simd".to_string(), false);
+        failure.supports.insert("threads".to_string(), true);
+        let evidence = evidence(
+            r#"(module (memory 1 1 shared) (func (result v128) v128.const i32x4 0 0 0 0))"#,
+            Some(failure),
+        );
+        let causes = diagnose(&evidence);
+        let titles: Vec<&str> = causes.iter().map(|cause| cause.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            [
+                "The browser does not support SIMD",
+                "Shared memory needs a cross-origin isolated page"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_csp_without_wasm_unsafe_eval() {
+        let mut evidence = evidence("(module)", None);
+        evidence.headers.insert(
+            "Content-Security-Policy".to_string(),
+            "script-src 'self'".to_string(),
+        );
+        let causes = diagnose(&evidence);
+        assert_eq!(causes.len(), 1);
+        assert!(causes[0].title.contains("Content Security Policy"));
+
+        evidence.headers.insert(
+            "Content-Security-Policy".to_string(),
+            "script-src 'self' 'wasm-unsafe-eval'".to_string(),
+        );
+        assert!(diagnose(&evidence).is_empty());
+    }
+}
@@ -0,0 +1,294 @@
+//! Building with the project's own command (`build.command` in `wasmrun.toml`)
+//!
+//! The command runs through the platform shell in the project directory, with
+//! `WASMRUN_OUTPUT_DIR` and `WASMRUN_OPTIMIZATION` in its environment. The
+//! module it produces is `build.artifact` when set, otherwise the newest
+//! `.wasm` it wrote to the output directory. A module built elsewhere is
+//! copied into the output directory together with its wasm-bindgen glue.
+
+use super::builder::{BuildConfig, BuildResult, WasmBuilder};
+use crate::config::project::BuildSection;
+use crate::error::{CompilationError, CompilationResult, Result};
+use crate::server::build_status;
+use crate::utils::{CommandExecutor, PathResolver};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const LANGUAGE: &str = "build.command";
+
+#[derive(Debug, Clone)]
+pub struct CommandBuilder {
+    command: String,
+    artifact: Option<String>,
+}
+
+impl CommandBuilder {
+    /// A builder for the configured command, if there is one
+    pub fn from_settings(build: &BuildSection) -> Option<Self> {
+        Some(Self {
+            command: build.command.clone()?,
+            artifact: build.artifact.clone(),
+        })
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    fn failed(reason: impl Into<String>) -> CompilationError {
+        CompilationError::BuildFailed {
+            language: LANGUAGE.to_string(),
+            reason: reason.into(),
+        }
+    }
+
+    /// The module the command produced
+    fn find_artifact(&self, config: &BuildConfig) -> CompilationResult<PathBuf> {
+        if let Some(artifact) = &self.artifact {
+            let path = Path::new(&config.project_path).join(artifact);
+            return if path.is_file() {
+                Ok(path)
+            } else {
+                Err(Self::failed(format!(
+                    "build.artifact {artifact} was not produced by `{}`",
+                    self.command
+                )))
+            };
+        }
+        PathResolver::find_files_with_extension(&config.output_dir, "wasm")
+            .unwrap_or_default()
+            .into_iter()
+            .map(PathBuf::from)
+            .max_by_key(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+            .ok_or_else(|| {
+                Self::failed(format!(
+                    "`{}` wrote no .wasm file to $WASMRUN_OUTPUT_DIR; set build.artifact to the module it builds",
+                    self.command
+                ))
+            })
+    }
+}
+
+/// wasm-bindgen's glue and definitions for `wasm`: `app.js`, `app.d.ts` and
+/// `app_bg.wasm.d.ts` for `app_bg.wasm`
+fn glue_files(wasm: &Path) -> Vec<PathBuf> {
+    let Some(name) = wasm
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+    else {
+        return Vec::new();
+    };
+    let stem = name
+        .strip_suffix("_bg.wasm")
+        .or_else(|| name.strip_suffix(".wasm"))
+        .unwrap_or(&name);
+    [
+        format!("{stem}.js"),
+        format!("{stem}.d.ts"),
+        format!("{stem}_bg.wasm.d.ts"),
+    ]
+    .into_iter()
+    .map(|file| wasm.with_file_name(file))
+    .filter(|file| file.is_file())
+    .collect()
+}
+
+impl WasmBuilder for CommandBuilder {
+    fn can_handle_project(&self, _project_path: &str) -> bool {
+        true
+    }
+
+    fn build(&self, config: &BuildConfig) -> CompilationResult<BuildResult> {
+        PathResolver::ensure_output_directory(&config.output_dir)
+            .map_err(|e| Self::failed(e.to_string()))?;
+        let output_dir = fs::canonicalize(&config.output_dir)
+            .unwrap_or_else(|_| PathBuf::from(&config.output_dir));
+        let (shell, flag) = if cfg!(target_os = "windows") {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+
+        println!("🔧 Running build command: {}", self.command);
+        let mut command = Command::new(shell);
+        command
+            .args([flag, &self.command])
+            .current_dir(&config.project_path)
+            .env("WASMRUN_OUTPUT_DIR", &output_dir)
+            .env(
+                "WASMRUN_OPTIMIZATION",
+                config.optimization_level.to_string(),
+            );
+        // A rebuild also shows the output on open pages
+        let status = if build_status::is_building() {
+            CommandExecutor::output_streamed(&mut command).map(|output| output.status)
+        } else {
+            command.status()
+        }
+        .map_err(|e| CompilationError::ToolExecutionFailed {
+            tool: shell.to_string(),
+            reason: e.to_string(),
+        })?;
+        if !status.success() {
+            return Err(Self::failed(format!(
+                "`{}` failed with exit code: {:?}",
+                self.command,
+                status.code()
+            )));
+        }
+
+        let artifact = self.find_artifact(config)?;
+        let mut files = vec![artifact.clone()];
+        files.extend(glue_files(&artifact));
+        let mut copied = Vec::new();
+        for file in files {
+            let target = match file.file_name() {
+                Some(name) if file.parent() != Some(output_dir.as_path()) => {
+                    let target = output_dir.join(name);
+                    fs::copy(&file, &target).map_err(|e| {
+                        Self::failed(format!("Failed to copy {}: {e}", file.display()))
+                    })?;
+                    target
+                }
+                _ => file,
+            };
+            copied.push(target.to_string_lossy().to_string());
+        }
+
+        let wasm_path = copied.remove(0);
+        let js_path = copied
+            .iter()
+            .position(|file| file.ends_with(".js"))
+            .map(|index| copied.remove(index));
+        Ok(BuildResult {
+            is_wasm_bindgen: wasm_path.ends_with("_bg.wasm") && js_path.is_some(),
+            wasm_path,
+            js_path,
+            additional_files: copied,
+        })
+    }
+
+    fn planned_commands(&self, config: &BuildConfig) -> Vec<String> {
+        let (shell, flag) = if cfg!(target_os = "windows") {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+        let output_dir = fs::canonicalize(&config.output_dir)
+            .unwrap_or_else(|_| PathBuf::from(&config.output_dir));
+        vec![format!(
+            "WASMRUN_OUTPUT_DIR={} WASMRUN_OPTIMIZATION={} {}",
+            CommandExecutor::shell_quote(&output_dir.to_string_lossy()),
+            config.optimization_level,
+            CommandExecutor::display_command(shell, &[flag, &self.command])
+        )]
+    }
+
+    fn clean(&self, _project_path: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn clone_box(&self) -> Box<dyn WasmBuilder> {
+        Box::new(self.clone())
+    }
+
+    fn language_name(&self) -> &str {
+        LANGUAGE
+    }
+
+    fn entry_file_candidates(&self) -> &[&str] {
+        &[]
+    }
+
+    fn supported_extensions(&self) -> &[&str] {
+        &[]
+    }
+
+    fn check_dependencies(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn validate_project(&self, _project_path: &str) -> CompilationResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn builder(command: &str, artifact: Option<&str>) -> CommandBuilder {
+        CommandBuilder::from_settings(&BuildSection {
+            command: Some(command.to_string()),
+            artifact: artifact.map(str::to_string),
+            ..BuildSection::default()
+        })
+        .unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_planned_command_is_not_run() {
+        let project = tempdir().unwrap();
+        let output = tempdir().unwrap();
+        let config = BuildConfig::with_defaults(
+            project.path().to_string_lossy().to_string(),
+            output.path().to_string_lossy().to_string(),
+        );
+        let commands = builder("touch built.wasm", None).planned_commands(&config);
+        assert_eq!(commands.len(), 1);
+        assert!(commands[0].starts_with("WASMRUN_OUTPUT_DIR="));
+        assert!(commands[0].ends_with(" WASMRUN_OPTIMIZATION=release sh -c 'touch built.wasm'"));
+        assert!(!project.path().join("built.wasm").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_writes_to_output_dir() {
+        let project = tempdir().unwrap();
+        let output = tempdir().unwrap();
+        let config = BuildConfig::with_defaults(
+            project.path().to_string_lossy().to_string(),
+            output.path().to_string_lossy().to_string(),
+        );
+        let result = builder(r#"printf '\0asm' > "$WASMRUN_OUTPUT_DIR/app.wasm""#, None)
+            .build(&config)
+            .unwrap();
+        assert!(result.wasm_path.ends_with("app.wasm"));
+        assert!(Path::new(&result.wasm_path).starts_with(fs::canonicalize(output.path()).unwrap()));
+
+        assert!(builder("exit 3", None).build(&config).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_artifact_is_copied_with_its_glue() {
+        let project = tempdir().unwrap();
+        let output = tempdir().unwrap();
+        let config = BuildConfig::with_defaults(
+            project.path().to_string_lossy().to_string(),
+            output.path().to_string_lossy().to_string(),
+        );
+        let result = builder(
+            "mkdir -p pkg && printf '\\0asm' > pkg/app_bg.wasm && touch pkg/app.js pkg/app.d.ts",
+            Some("pkg/app_bg.wasm"),
+        )
+        .build(&config)
+        .unwrap();
+        assert!(result.is_wasm_bindgen);
+        assert!(result.js_path.unwrap().ends_with("app.js"));
+        assert!(result.additional_files[0].ends_with("app.d.ts"));
+        assert!(output.path().join("app_bg.wasm").is_file());
+
+        assert!(builder("true", Some("missing.wasm"))
+            .build(&config)
+            .is_err());
+    }
+
+    #[test]
+    fn test_from_settings_needs_a_command() {
+        assert!(CommandBuilder::from_settings(&BuildSection::default()).is_none());
+    }
+}
@@ -1,5 +1,6 @@
 //! Build system abstraction for different languages and compilation targets
 
+use super::cargo_target::CargoTarget;
 use crate::error::{CompilationResult, Result};
 use crate::plugin::manager::PluginManager;
 use serde::{Deserialize, Serialize};
@@ -13,6 +14,11 @@ pub struct BuildConfig {
     pub verbose: bool,
     pub watch: bool,
     pub target_type: TargetType,
+    /// Cargo binary or example to build, for Rust crates with several
+    pub cargo_target: Option<CargoTarget>,
+    /// Cargo features to enable, from the selected `[build.feature_sets]` entry
+    #[serde(default)]
+    pub cargo_features: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,6 +68,18 @@ pub trait WasmBuilder: Send + Sync {
     fn check_dependencies(&self) -> Vec<String>;
     fn validate_project(&self, project_path: &str) -> CompilationResult<()>;
 
+    /// Paths below the project that builds write to, as ignore globs, so
+    /// watch mode doesn't take its own output for changed sources
+    fn generated_paths(&self, _project_path: &str) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// What a build with `config` would do, as equivalent shell commands, for
+    /// `--dry-run`; empty when the steps depend on what earlier ones produce
+    fn planned_commands(&self, _config: &BuildConfig) -> Vec<String> {
+        Vec::new()
+    }
+
     fn build_verbose(&self, config: &BuildConfig) -> CompilationResult<BuildResult> {
         println!("Building {} project...", self.language_name());
         self.build(config)
@@ -98,6 +116,8 @@ impl BuildConfig {
             verbose,
             watch,
             target_type: TargetType::Standard,
+            cargo_target: None,
+            cargo_features: Vec::new(),
         }
     }
 
@@ -109,6 +129,8 @@ impl BuildConfig {
             verbose: false,
             watch: false,
             target_type: TargetType::Standard,
+            cargo_target: None,
+            cargo_features: Vec::new(),
         }
     }
 }
@@ -155,6 +177,13 @@ impl BuildResult {
         self.js_path.as_ref().unwrap_or(&self.wasm_path)
     }
 
+    /// The wasm and JS outputs of the build
+    pub fn output_files(&self) -> Vec<&str> {
+        let mut files = vec![self.wasm_path.as_str()];
+        files.extend(self.js_path.as_deref());
+        files
+    }
+
     #[allow(dead_code)] // TODO: Future web app detection
     pub fn is_web_app(&self) -> bool {
         self.js_path
@@ -180,11 +209,17 @@ impl BuilderFactory {
         use crate::compiler::ProjectLanguage;
 
         match language {
-            ProjectLanguage::Rust => Box::new(UnknownBuilder),
+            ProjectLanguage::Rust => {
+                Box::new(crate::plugin::languages::rust_plugin::RustPlugin::new())
+            }
             ProjectLanguage::C => Box::new(crate::plugin::languages::c_plugin::CPlugin::new()),
-            ProjectLanguage::Asc => Box::new(UnknownBuilder),
-            ProjectLanguage::Go => Box::new(UnknownBuilder),
-            ProjectLanguage::Python => Box::new(UnknownBuilder),
+            ProjectLanguage::Asc => {
+                Box::new(crate::plugin::languages::asc_plugin::AscPlugin::new())
+            }
+            ProjectLanguage::Go => Box::new(crate::plugin::languages::go_plugin::GoPlugin::new()),
+            ProjectLanguage::Python => {
+                Box::new(crate::plugin::languages::python_plugin::PythonPlugin::new())
+            }
             ProjectLanguage::Unknown => Box::new(UnknownBuilder),
         }
     }
@@ -266,6 +301,8 @@ pub fn build_wasm_project(
         optimization_level: OptimizationLevel::Release,
         watch: false,
         target_type: TargetType::Standard,
+        cargo_target: super::cargo_target::active(),
+        cargo_features: super::cargo_features::active_features(),
     };
 
     // Try plugin-based building first
@@ -0,0 +1,97 @@
+//! Switching between Cargo feature sets
+//!
+//! `[build.feature_sets]` in `wasmrun.toml` names groups of Cargo features,
+//! e.g. `simd = ["simd", "fast-math"]`. One of them can be chosen from the
+//! page served in development, so a module can be compared with and without
+//! the features without going back to the terminal. The choice is
+//! process-wide, so rebuilds from the watcher or `wasmrun ctl rebuild` keep
+//! using it, and it reaches the Rust backend through
+//! [`BuildConfig`](super::builder::BuildConfig).
+
+use crate::config::project::active_settings;
+use serde::Serialize;
+use std::sync::RwLock;
+
+static SELECTED: RwLock<Option<FeatureSet>> = RwLock::new(None);
+
+/// A named group of Cargo features from `[build.feature_sets]`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeatureSet {
+    pub name: String,
+    pub features: Vec<String>,
+}
+
+/// The feature sets configured for the active project, by name
+pub fn configured() -> Vec<FeatureSet> {
+    active_settings()
+        .build
+        .feature_sets
+        .into_iter()
+        .map(|(name, features)| FeatureSet { name, features })
+        .collect()
+}
+
+/// Build with the feature set `name` from now on, or with the default features for `None`
+pub fn select(name: Option<&str>) -> Result<Option<FeatureSet>, String> {
+    let selected = match name {
+        Some(name) => {
+            let sets = configured();
+            let set = sets.iter().find(|set| set.name == name).ok_or_else(|| {
+                let names: Vec<&str> = sets.iter().map(|set| set.name.as_str()).collect();
+                format!(
+                    "No feature set named '{name}'; available: {}",
+                    if names.is_empty() {
+                        "(none)".to_string()
+                    } else {
+                        names.join(", ")
+                    }
+                )
+            })?;
+            Some(set.clone())
+        }
+        None => None,
+    };
+    let mut active = SELECTED.write().map_err(|e| e.to_string())?;
+    *active = selected.clone();
+    Ok(selected)
+}
+
+/// The feature set chosen with [`select`], if any
+pub fn active() -> Option<FeatureSet> {
+    SELECTED.read().ok().and_then(|selected| selected.clone())
+}
+
+/// Features to pass to Cargo for the next build; empty for the default features
+pub fn active_features() -> Vec<String> {
+    active().map(|set| set.features).unwrap_or_default()
+}
+
+/// Cargo flag and value enabling `features`, e.g. `["--features", "a,b"]`; empty when there are none
+pub fn cargo_args(features: &[String]) -> Vec<String> {
+    if features.is_empty() {
+        return Vec::new();
+    }
+    vec!["--features".to_string(), features.join(",")]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cargo_args() {
+        assert!(cargo_args(&[]).is_empty());
+        assert_eq!(
+            cargo_args(&["simd".to_string(), "serde/derive".to_string()]),
+            ["--features", "simd,serde/derive"]
+        );
+    }
+
+    #[test]
+    fn test_select_unknown_set() {
+        let error = select(Some("missing")).unwrap_err();
+        assert!(error.contains("No feature set named 'missing'"));
+        assert_eq!(select(None), Ok(None));
+        assert!(active_features().is_empty());
+    }
+}
@@ -0,0 +1,347 @@
+//! Cargo binary and example selection
+//!
+//! A crate with several `[[bin]]` targets, or only examples, builds more than
+//! one module or none of the ones the user wants. `--bin` and `--example`
+//! pick the target explicitly; when they are missing and the choice is
+//! ambiguous, the available targets are offered in a prompt (or listed in the
+//! error when not interactive). The selection is process-wide so watch-mode
+//! rebuilds keep using it, and it reaches the Rust backend through
+//! [`BuildConfig`](super::builder::BuildConfig).
+
+use crate::error::{Result, WasmrunError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::sync::RwLock;
+
+static SELECTED: RwLock<Option<CargoTarget>> = RwLock::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TargetKind {
+    Bin,
+    Example,
+}
+
+/// A binary or example target of a Cargo package
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CargoTarget {
+    pub kind: TargetKind,
+    pub name: String,
+}
+
+impl CargoTarget {
+    /// Cargo flag and value that build this target, e.g. `["--bin", "app"]`
+    pub fn cargo_args(&self) -> [&str; 2] {
+        let flag = match self.kind {
+            TargetKind::Bin => "--bin",
+            TargetKind::Example => "--example",
+        };
+        [flag, &self.name]
+    }
+}
+
+impl fmt::Display for CargoTarget {
+    /// `bin:name` or `example:name`, the form passed to plugins
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            TargetKind::Bin => write!(f, "bin:{}", self.name),
+            TargetKind::Example => write!(f, "example:{}", self.name),
+        }
+    }
+}
+
+/// The targets a package declares or Cargo discovers for it
+#[derive(Debug, Default, PartialEq)]
+pub struct PackageTargets {
+    pub has_lib: bool,
+    pub default_run: Option<String>,
+    pub bins: Vec<String>,
+    pub examples: Vec<String>,
+}
+
+impl PackageTargets {
+    /// Read `Cargo.toml` in `project_path`; `None` when there is no package manifest
+    pub fn load(project_path: &Path) -> Option<Self> {
+        let manifest: toml::Table = fs::read_to_string(project_path.join("Cargo.toml"))
+            .ok()?
+            .parse()
+            .ok()?;
+        let package = manifest.get("package")?.as_table()?;
+        let package_flag = |key: &str| package.get(key).and_then(toml::Value::as_bool);
+
+        let mut targets = Self {
+            has_lib: manifest.contains_key("lib") || project_path.join("src/lib.rs").is_file(),
+            default_run: package
+                .get("default-run")
+                .and_then(toml::Value::as_str)
+                .map(str::to_string),
+            ..Self::default()
+        };
+
+        if project_path.join("src/main.rs").is_file() {
+            if let Some(name) = package.get("name").and_then(toml::Value::as_str) {
+                targets.bins.push(name.to_string());
+            }
+        }
+        if package_flag("autobins") != Some(false) {
+            targets.bins.extend(discover(&project_path.join("src/bin")));
+        }
+        if package_flag("autoexamples") != Some(false) {
+            targets
+                .examples
+                .extend(discover(&project_path.join("examples")));
+        }
+        targets.bins.extend(declared(&manifest, "bin"));
+        targets.examples.extend(declared(&manifest, "example"));
+
+        for names in [&mut targets.bins, &mut targets.examples] {
+            names.sort();
+            names.dedup();
+        }
+        Some(targets)
+    }
+
+    fn find(&self, kind: TargetKind, name: &str) -> Option<CargoTarget> {
+        let names = match kind {
+            TargetKind::Bin => &self.bins,
+            TargetKind::Example => &self.examples,
+        };
+        names
+            .iter()
+            .any(|known| known == name)
+            .then(|| CargoTarget {
+                kind,
+                name: name.to_string(),
+            })
+    }
+
+    /// Targets to choose from when a plain build would be ambiguous; empty when it isn't
+    pub fn ambiguous_choices(&self) -> Vec<CargoTarget> {
+        if self.has_lib || self.default_run.is_some() {
+            return Vec::new();
+        }
+        let (kind, names) = match self.bins.len() {
+            0 => (TargetKind::Example, &self.examples),
+            1 => return Vec::new(),
+            _ => (TargetKind::Bin, &self.bins),
+        };
+        names
+            .iter()
+            .map(|name| CargoTarget {
+                kind,
+                name: name.clone(),
+            })
+            .collect()
+    }
+
+    fn listing(&self) -> String {
+        let mut lines = Vec::new();
+        if !self.bins.is_empty() {
+            lines.push(format!("  binaries: {}", self.bins.join(", ")));
+        }
+        if !self.examples.is_empty() {
+            lines.push(format!("  examples: {}", self.examples.join(", ")));
+        }
+        if lines.is_empty() {
+            lines.push("  (none)".to_string());
+        }
+        lines.join("\n")
+    }
+}
+
+/// Choose the target to build for `project_path` and remember it for later builds
+pub fn select(project_path: &str, bin: Option<&str>, example: Option<&str>) -> Result<()> {
+    let requested = match (bin, example) {
+        (Some(name), _) => Some((TargetKind::Bin, name)),
+        (None, Some(name)) => Some((TargetKind::Example, name)),
+        (None, None) => None,
+    };
+    let Some(targets) = PackageTargets::load(Path::new(project_path)) else {
+        return match requested {
+            Some(_) => Err(invalid(format!(
+                "--bin and --example need a Cargo package, but {project_path} has no Cargo.toml with a [package]"
+            ))),
+            None => Ok(()),
+        };
+    };
+
+    let selected = match requested {
+        Some((kind, name)) => Some(targets.find(kind, name).ok_or_else(|| {
+            let kind = match kind {
+                TargetKind::Bin => "binary",
+                TargetKind::Example => "example",
+            };
+            invalid(format!(
+                "No {kind} named '{name}' in {project_path}; available targets:\n{}",
+                targets.listing()
+            ))
+        })?),
+        None => match targets.ambiguous_choices().as_slice() {
+            [] => None,
+            [only] => Some(only.clone()),
+            choices => Some(prompt(choices).ok_or_else(|| {
+                invalid(format!(
+                    "{project_path} has several Cargo targets; choose one with --bin or --example:\n{}",
+                    targets.listing()
+                ))
+            })?),
+        },
+    };
+
+    if let Some(target) = &selected {
+        println!(
+            "🎯 Building Cargo target: {}",
+            target.cargo_args().join(" ")
+        );
+    }
+    if let Ok(mut active) = SELECTED.write() {
+        *active = selected;
+    }
+    Ok(())
+}
+
+/// The target chosen with [`select`], if any
+pub fn active() -> Option<CargoTarget> {
+    SELECTED.read().ok().and_then(|selected| selected.clone())
+}
+
+/// Ask which target to build; `None` when not interactive or nothing valid was chosen
+fn prompt(choices: &[CargoTarget]) -> Option<CargoTarget> {
+    if !std::io::stdin().is_terminal() || crate::tui::is_active() {
+        return None;
+    }
+
+    println!("❓ Several Cargo targets can be built:");
+    for (number, target) in choices.iter().enumerate() {
+        println!("   {}) {}", number + 1, target.cargo_args().join(" "));
+    }
+    print!("   Build which one? [1-{}] ", choices.len());
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).ok()?;
+    let number: usize = answer.trim().parse().ok()?;
+    choices.get(number.checked_sub(1)?).cloned()
+}
+
+/// Target names Cargo infers from `dir`: `name.rs` files and `name/main.rs` directories
+fn discover(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let is_target = if path.is_dir() {
+                path.join("main.rs").is_file()
+            } else {
+                path.extension().is_some_and(|ext| ext == "rs")
+            };
+            is_target
+                .then(|| path.file_stem()?.to_str().map(str::to_string))
+                .flatten()
+        })
+        .collect()
+}
+
+/// Names of the `[[bin]]` or `[[example]]` tables in a manifest
+fn declared(manifest: &toml::Table, kind: &str) -> Vec<String> {
+    manifest
+        .get(kind)
+        .and_then(toml::Value::as_array)
+        .map(|targets| {
+            targets
+                .iter()
+                .filter_map(|target| target.get("name")?.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn invalid(message: String) -> WasmrunError {
+    WasmrunError::Command(crate::error::CommandError::invalid_arguments(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_load_discovers_and_declares_targets() {
+        let dir = tempdir().unwrap();
+        write(
+            dir.path(),
+            "Cargo.toml",
+            r#"
+[package]
+name = "game"
+
+[[bin]]
+name = "editor"
+path = "tools/editor.rs"
+
+[[example]]
+name = "demo"
+path = "demos/demo.rs"
+"#,
+        );
+        write(dir.path(), "src/main.rs", "fn main() {}");
+        write(dir.path(), "src/bin/server.rs", "fn main() {}");
+        write(dir.path(), "src/bin/level/main.rs", "fn main() {}");
+        write(dir.path(), "src/bin/README.md", "");
+        write(dir.path(), "examples/hello.rs", "fn main() {}");
+
+        let targets = PackageTargets::load(dir.path()).unwrap();
+        assert!(!targets.has_lib);
+        assert_eq!(targets.bins, ["editor", "game", "level", "server"]);
+        assert_eq!(targets.examples, ["demo", "hello"]);
+        assert_eq!(targets.ambiguous_choices().len(), 4);
+        assert_eq!(
+            targets
+                .find(TargetKind::Example, "hello")
+                .unwrap()
+                .to_string(),
+            "example:hello"
+        );
+        assert!(targets.find(TargetKind::Bin, "hello").is_none());
+
+        write(dir.path(), "src/lib.rs", "");
+        assert!(PackageTargets::load(dir.path())
+            .unwrap()
+            .ambiguous_choices()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_ambiguous_choices() {
+        let only_examples = PackageTargets {
+            examples: vec!["a".to_string(), "b".to_string()],
+            ..PackageTargets::default()
+        };
+        let choices = only_examples.ambiguous_choices();
+        assert_eq!(choices[1].cargo_args(), ["--example", "b"]);
+
+        let single_bin = PackageTargets {
+            bins: vec!["app".to_string()],
+            examples: vec!["a".to_string()],
+            ..PackageTargets::default()
+        };
+        assert!(single_bin.ambiguous_choices().is_empty());
+
+        let default_run = PackageTargets {
+            bins: vec!["a".to_string(), "b".to_string()],
+            default_run: Some("a".to_string()),
+            ..PackageTargets::default()
+        };
+        assert!(default_run.ambiguous_choices().is_empty());
+    }
+}
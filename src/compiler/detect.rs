@@ -4,7 +4,7 @@ use std::fs;
 use std::path::Path;
 
 /// Supported project languages
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ProjectLanguage {
     Rust,
     Go,
@@ -65,8 +65,14 @@ pub fn detect_project_language(project_path: &str) -> ProjectLanguage {
         }
     }
 
+    if path.join("asconfig.json").exists() {
+        debug_println!("Found asconfig.json - detected AssemblyScript project");
+        debug_exit!("detect_project_language", ProjectLanguage::Asc);
+        return ProjectLanguage::Asc;
+    }
+
     if let Ok(package_json) = fs::read_to_string(path.join("package.json")) {
-        if package_json.contains("\"asc\"") {
+        if package_json.contains("\"asc\"") || package_json.contains("\"assemblyscript\"") {
             return ProjectLanguage::Asc;
         }
     }
@@ -151,13 +157,14 @@ pub fn get_recommended_tools(language: &ProjectLanguage, os: &OperatingSystem) -
             vec![
                 "rustup".to_string(),
                 "cargo".to_string(),
-                "External Rust plugin (install with: wasmrun plugin install wasmrust)".to_string(),
+                "wasm-bindgen (for wasm-bindgen crates: cargo install wasm-bindgen-cli)"
+                    .to_string(),
             ]
         }
         (ProjectLanguage::Go, _) => {
             vec![
-                "External Go plugin (install with: wasmrun plugin install wasmgo)".to_string(),
-                "tinygo".to_string(),
+                "tinygo (smaller modules)".to_string(),
+                "go (builds with GOOS=js GOARCH=wasm when TinyGo is missing)".to_string(),
             ]
         }
         (ProjectLanguage::C, OperatingSystem::Windows) => {
@@ -178,10 +185,12 @@ pub fn get_recommended_tools(language: &ProjectLanguage, os: &OperatingSystem) -
             vec!["node.js".to_string(), "npm".to_string(), "asc".to_string()]
         }
         (ProjectLanguage::Python, _) => {
-            vec![
-                "External Python plugin (install with: wasmrun plugin install waspy)".to_string(),
-                "python3".to_string(),
-            ]
+            // Pyodide runs in the page; py2wasm is only needed with python.mode = "py2wasm"
+            if crate::config::active_settings().python.mode.as_deref() == Some("py2wasm") {
+                vec!["py2wasm".to_string()]
+            } else {
+                Vec::new()
+            }
         }
         (ProjectLanguage::Unknown, _) => Vec::new(),
     };
@@ -285,6 +294,15 @@ mod tests {
         assert_eq!(result, ProjectLanguage::Asc);
     }
 
+    #[test]
+    fn test_detect_asc_project_with_asconfig() {
+        let temp_dir = tempdir().unwrap();
+        create_test_file(temp_dir.path(), "asconfig.json", r#"{"entries": []}"#);
+
+        let result = detect_project_language(temp_dir.path().to_str().unwrap());
+        assert_eq!(result, ProjectLanguage::Asc);
+    }
+
     #[test]
     fn test_detect_c_project_with_c_files() {
         let temp_dir = tempdir().unwrap();
@@ -0,0 +1,376 @@
+//! Compiler diagnostics as SARIF (`wasmrun compile --sarif`)
+//!
+//! The errors and warnings are read back from the output the compiler printed:
+//! rustc and Cargo, GCC/Clang (and so Emscripten and TinyGo's C parts), Go,
+//! and tsc-style messages from AssemblyScript. Lines in no known format are
+//! left out. The report is SARIF 2.1.0, which code scanning on GitHub and
+//! GitLab and the SARIF viewers of most editors read. Paths inside the project
+//! are written relative to it, against the `%SRCROOT%` base.
+
+use crate::server::ansi;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::path::{Component, Path};
+use std::sync::OnceLock;
+
+/// Base URI id the project-relative paths of a report resolve against
+const SRCROOT: &str = "%SRCROOT%";
+
+/// Lines after a rustc message its `-->` location may follow
+const RUST_LOCATION_LOOKAHEAD: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Note,
+    Warning,
+    Error,
+}
+
+impl Level {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "error" | "fatal error" => Some(Self::Error),
+            "warning" => Some(Self::Warning),
+            "note" => Some(Self::Note),
+            _ => None,
+        }
+    }
+
+    /// The SARIF `level`
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Note => "note",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// An error or warning the compiler reported
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Diagnostic {
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub level: Level,
+    /// Error code or warning flag, e.g. `E0308`, `-Wunused-variable`, `TS2304`
+    pub rule: Option<String>,
+    pub message: String,
+}
+
+struct Patterns {
+    rust_header: Regex,
+    rust_location: Regex,
+    rust_summary: Regex,
+    gcc: Regex,
+    go: Regex,
+    tsc: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        rust_header: Regex::new(r"^(error|warning)(?:\[(E\d{4})\])?: (.+)$").unwrap(),
+        rust_location: Regex::new(r"^\s*--> (.+?):(\d+):(\d+)$").unwrap(),
+        rust_summary: Regex::new(
+            r"^(?:could not compile|aborting due to|build failed|`.+` \(.+\) generated \d+|\d+ warnings? emitted|Some errors have detailed explanations|For more information about)",
+        )
+        .unwrap(),
+        gcc: Regex::new(
+            r"^(.+?):(\d+):(?:(\d+):)? (fatal error|error|warning|note): (.+?)(?: \[(-W[\w=-]+)\])?$",
+        )
+        .unwrap(),
+        go: Regex::new(r"^(\S+\.go):(\d+):(\d+): (.+)$").unwrap(),
+        tsc: Regex::new(r"^(.+?)\((\d+),(\d+)\): (error|warning) (TS\d+|AS\d+): (.+)$").unwrap(),
+    })
+}
+
+/// The diagnostics in `lines` of compiler output, colored or not, in order of
+/// file and position and without duplicates
+pub fn parse(lines: &[String]) -> Vec<Diagnostic> {
+    let patterns = patterns();
+    let lines: Vec<String> = lines.iter().map(|line| ansi::strip(line)).collect();
+    let mut found = BTreeSet::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        let number = |text: Option<regex::Match>| text.and_then(|m| m.as_str().parse().ok());
+
+        if let Some(caps) = patterns.tsc.captures(line) {
+            found.insert(Diagnostic {
+                file: Some(caps[1].trim().to_string()),
+                line: number(caps.get(2)),
+                column: number(caps.get(3)),
+                level: Level::parse(&caps[4]).unwrap_or(Level::Error),
+                rule: Some(caps[5].to_string()),
+                message: caps[6].trim().to_string(),
+            });
+        } else if let Some(caps) = patterns.gcc.captures(line) {
+            found.insert(Diagnostic {
+                file: Some(caps[1].to_string()),
+                line: number(caps.get(2)),
+                column: number(caps.get(3)),
+                level: Level::parse(&caps[4]).unwrap_or(Level::Error),
+                rule: caps.get(6).map(|flag| flag.as_str().to_string()),
+                message: caps[5].trim().to_string(),
+            });
+        } else if let Some(caps) = patterns.rust_header.captures(line) {
+            let message = caps[3].trim();
+            if patterns.rust_summary.is_match(message) {
+                continue;
+            }
+            let location = lines
+                .iter()
+                .skip(index + 1)
+                .take(RUST_LOCATION_LOOKAHEAD)
+                .find_map(|next| patterns.rust_location.captures(next));
+            found.insert(Diagnostic {
+                file: location.as_ref().map(|caps| caps[1].to_string()),
+                line: location.as_ref().and_then(|caps| number(caps.get(2))),
+                column: location.as_ref().and_then(|caps| number(caps.get(3))),
+                level: Level::parse(&caps[1]).unwrap_or(Level::Error),
+                rule: caps.get(2).map(|code| code.as_str().to_string()),
+                message: message.to_string(),
+            });
+        } else if let Some(caps) = patterns.go.captures(line) {
+            // Go reports only errors, without saying so
+            found.insert(Diagnostic {
+                file: Some(caps[1].to_string()),
+                line: number(caps.get(2)),
+                column: number(caps.get(3)),
+                level: Level::Error,
+                rule: None,
+                message: caps[4].trim().to_string(),
+            });
+        }
+    }
+    found.into_iter().collect()
+}
+
+/// A SARIF 2.1.0 log with `diagnostics` as the results of one run of wasmrun
+/// building the project at `project_path`
+pub fn to_sarif(diagnostics: &[Diagnostic], project_path: &Path) -> Value {
+    let root = project_path
+        .canonicalize()
+        .unwrap_or_else(|_| project_path.to_path_buf());
+
+    let rules: BTreeSet<&str> = diagnostics
+        .iter()
+        .filter_map(|diagnostic| diagnostic.rule.as_deref())
+        .collect();
+    let results: Vec<Value> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let mut result = json!({
+                "level": diagnostic.level.as_str(),
+                "message": { "text": diagnostic.message },
+            });
+            if let Some(rule) = &diagnostic.rule {
+                result["ruleId"] = json!(rule);
+            }
+            if let Some(file) = &diagnostic.file {
+                let mut location = artifact_location(file, &root);
+                if let Some(line) = diagnostic.line {
+                    let mut region = json!({ "startLine": line });
+                    if let Some(column) = diagnostic.column {
+                        region["startColumn"] = json!(column);
+                    }
+                    location["region"] = region;
+                }
+                result["locations"] = json!([{ "physicalLocation": location }]);
+            }
+            result
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "wasmrun",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                }
+            },
+            "originalUriBaseIds": {
+                SRCROOT: { "uri": file_uri(&root, true) }
+            },
+            "results": results,
+        }]
+    })
+}
+
+/// Where `file` is, relative to the project `root` when inside it
+fn artifact_location(file: &str, root: &Path) -> Value {
+    let path = Path::new(file);
+    let relative = if path.is_absolute() {
+        path.strip_prefix(root).ok().map(Path::to_path_buf)
+    } else {
+        Some(
+            path.components()
+                .filter(|component| component != &Component::CurDir)
+                .collect(),
+        )
+    };
+    match relative {
+        Some(relative) => json!({
+            "artifactLocation": { "uri": uri_path(&relative), "uriBaseId": SRCROOT }
+        }),
+        None => json!({ "artifactLocation": { "uri": file_uri(path, false) } }),
+    }
+}
+
+/// `path` as a `file://` URI, ending in `/` for a directory
+fn file_uri(path: &Path, directory: bool) -> String {
+    let path = uri_path(path);
+    let slash = if directory && !path.ends_with('/') {
+        "/"
+    } else {
+        ""
+    };
+    if path.starts_with('/') {
+        format!("file://{path}{slash}")
+    } else {
+        // A Windows drive path
+        format!("file:///{path}{slash}")
+    }
+}
+
+/// `path` with `/` separators and the characters URIs reserve escaped
+fn uri_path(path: &Path) -> String {
+    let mut uri = String::new();
+    for c in path.to_string_lossy().replace('\\', "/").chars() {
+        match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '/' | '-' | '.' | '_' | '~' | ':' => uri.push(c),
+            _ => {
+                let mut bytes = [0; 4];
+                for byte in c.encode_utf8(&mut bytes).bytes() {
+                    uri.push_str(&format!("%{byte:02X}"));
+                }
+            }
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_rustc_output() {
+        let output = lines(
+            "\x1b[1m\x1b[33mwarning\x1b[0m\x1b[1m: unused variable: `x`\x1b[0m
+  --> src/lib.rs:3:9
+   |
+3  |     let x = 5;
+warning: `app` (lib) generated 1 warning
+error[E0308]: mismatched types
+ --> src/main.rs:10:5
+error: could not compile `app` (lib) due to 1 previous error",
+        );
+        let diagnostics = parse(&output);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].file.as_deref(), Some("src/lib.rs"));
+        assert_eq!(
+            (diagnostics[0].line, diagnostics[0].column),
+            (Some(3), Some(9))
+        );
+        assert_eq!(diagnostics[0].level, Level::Warning);
+        assert_eq!(diagnostics[0].message, "unused variable: `x`");
+        assert_eq!(diagnostics[1].rule.as_deref(), Some("E0308"));
+        assert_eq!(diagnostics[1].level, Level::Error);
+    }
+
+    #[test]
+    fn test_parse_c_go_and_tsc_output() {
+        let output = lines(
+            "main.c:4:10: fatal error: 'missing.h' file not found
+main.c:7:5: warning: unused variable 'y' [-Wunused-variable]
+./main.go:5:2: undefined: fmt.Prinln
+assembly/index.ts(3,5): error TS2304: Cannot find name 'x'.
+main.c:4:10: fatal error: 'missing.h' file not found",
+        );
+        let diagnostics = parse(&output);
+        assert_eq!(diagnostics.len(), 4);
+        let by_file = |file: &str| {
+            diagnostics
+                .iter()
+                .find(|diagnostic| diagnostic.file.as_deref() == Some(file))
+                .unwrap()
+        };
+        assert_eq!(by_file("./main.go").message, "undefined: fmt.Prinln");
+        assert_eq!(by_file("assembly/index.ts").rule.as_deref(), Some("TS2304"));
+        let warning = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.level == Level::Warning)
+            .unwrap();
+        assert_eq!(warning.rule.as_deref(), Some("-Wunused-variable"));
+        assert_eq!(warning.message, "unused variable 'y'");
+    }
+
+    #[test]
+    fn test_to_sarif() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let diagnostics = vec![
+            Diagnostic {
+                file: Some("./src/my lib.rs".to_string()),
+                line: Some(3),
+                column: Some(9),
+                level: Level::Warning,
+                rule: None,
+                message: "unused variable".to_string(),
+            },
+            Diagnostic {
+                file: Some(root.join("main.c").to_string_lossy().to_string()),
+                line: Some(1),
+                column: None,
+                level: Level::Error,
+                rule: Some("E0308".to_string()),
+                message: "mismatched types".to_string(),
+            },
+            Diagnostic {
+                file: None,
+                line: None,
+                column: None,
+                level: Level::Error,
+                rule: None,
+                message: "build failed".to_string(),
+            },
+        ];
+
+        let sarif = to_sarif(&diagnostics, &root);
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"], json!([{ "id": "E0308" }]));
+        assert!(run["originalUriBaseIds"][SRCROOT]["uri"]
+            .as_str()
+            .unwrap()
+            .ends_with('/'));
+
+        let results = run["results"].as_array().unwrap();
+        let location = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/my%20lib.rs");
+        assert_eq!(location["artifactLocation"]["uriBaseId"], SRCROOT);
+        assert_eq!(
+            location["region"],
+            json!({ "startLine": 3, "startColumn": 9 })
+        );
+        assert_eq!(
+            results[1]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "main.c"
+        );
+        assert_eq!(results[1]["ruleId"], "E0308");
+        assert!(results[2].get("locations").is_none());
+        assert_eq!(results[2]["level"], "error");
+    }
+}
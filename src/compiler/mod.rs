@@ -1,5 +1,9 @@
+pub mod build_command;
 pub mod builder;
+pub mod cargo_features;
+pub mod cargo_target;
 mod detect;
+pub mod diagnostics;
 
 pub use builder::build_wasm_project;
 pub use detect::{
@@ -7,10 +11,34 @@ pub use detect::{
     ProjectLanguage,
 };
 
-use crate::error::{Result, WasmrunError};
+use crate::config::project::BuildSection;
+use crate::error::{ConfigError, Result, WasmrunError};
 use crate::plugin::manager::PluginManager;
 use crate::utils::PathResolver;
 
+/// The builder `wasmrun.toml` pins for the project, with a name to report it
+/// by: `build.command` first, then the plugin named by `build.plugin`
+pub fn configured_builder(
+    build: &BuildSection,
+) -> Result<Option<(String, Box<dyn builder::WasmBuilder>)>> {
+    if let Some(builder) = build_command::CommandBuilder::from_settings(build) {
+        let name = format!("build.command `{}`", builder.command());
+        return Ok(Some((name, Box::new(builder))));
+    }
+    let Some(name) = &build.plugin else {
+        return Ok(None);
+    };
+    let plugin_manager = PluginManager::new()?;
+    let plugin = plugin_manager.find_plugin_by_name(name).ok_or_else(|| {
+        WasmrunError::Config(ConfigError::InvalidValue {
+            message: format!(
+                "build.plugin names {name}, which is not installed; see `wasmrun plugin list`"
+            ),
+        })
+    })?;
+    Ok(Some((name.clone(), plugin.get_builder())))
+}
+
 /// Compile a WASM file from a project directory using plugin system
 #[allow(dead_code)] // TODO: Future project compilation interface
 pub fn create_wasm_from_project(project_path: &str, output_dir: &str) -> Result<String> {
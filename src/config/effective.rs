@@ -0,0 +1,544 @@
+//! Effective configuration with the source of every value
+//!
+//! Settings come from built-in defaults, the global `~/.wasmrun/config.toml`,
+//! the project's `wasmrun.toml`, the selected `[profile.<name>]`, and command
+//! line flags or environment variables, each layer overriding the ones before
+//! it. `wasmrun config show --resolved` prints the merged result with the
+//! layer each value was taken from.
+
+use super::project::{ProjectConfig, ProjectSettings};
+use super::{WasmrunConfig, DEFAULT_PORT};
+use crate::error::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where a resolved value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    GlobalConfig,
+    ProjectConfig,
+    Profile(String),
+    Flag(&'static str),
+    Env(&'static str),
+    /// No layer sets the value and there is no default
+    Unset,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::GlobalConfig => write!(f, "global config"),
+            Source::ProjectConfig => write!(f, "wasmrun.toml"),
+            Source::Profile(name) => write!(f, "wasmrun.toml [profile.{name}]"),
+            Source::Flag(flag) => write!(f, "{flag}"),
+            Source::Env(name) => write!(f, "{name}"),
+            Source::Unset => write!(f, "-"),
+        }
+    }
+}
+
+/// One resolved setting; values are shown in TOML syntax
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setting {
+    pub key: String,
+    pub value: Option<String>,
+    pub source: Source,
+}
+
+/// Command line values that take precedence over the config files
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub port: Option<u16>,
+    pub base: Option<String>,
+    pub optimization: Option<String>,
+    pub coi: bool,
+    pub rate_limit: Option<u32>,
+    pub max_connections: Option<usize>,
+    pub tls: bool,
+    pub offline: bool,
+    pub instantiate: Option<String>,
+    pub load_trigger: Option<String>,
+    pub module_cache: bool,
+}
+
+/// The merged configuration for a project
+#[derive(Debug)]
+pub struct Resolved {
+    pub global_config: PathBuf,
+    pub global_config_exists: bool,
+    pub project_config: Option<PathBuf>,
+    pub profile: Option<String>,
+    pub settings: Vec<Setting>,
+}
+
+/// Resolve every setting for `project_path` with `profile` and `overrides` applied
+pub fn resolve(
+    project_path: &str,
+    profile: Option<&str>,
+    overrides: &Overrides,
+) -> Result<Resolved> {
+    let project_config = ProjectConfig::find(project_path);
+    let config = match &project_config {
+        Some(path) => ProjectConfig::load_from_file(path)?,
+        None => ProjectConfig::default(),
+    };
+    // Fails with the list of available profiles for an unknown name
+    config.resolve(profile)?;
+
+    let base = flatten_settings(&config.base);
+    let profile_layer = profile
+        .and_then(|name| config.profile.get(name))
+        .map(flatten_settings)
+        .unwrap_or_default();
+    let flags = flag_layer(overrides);
+
+    let mut keys: Vec<(String, Option<String>)> = project_defaults()
+        .into_iter()
+        .map(|(key, default)| (key.to_string(), default))
+        .collect();
+    let mut named: Vec<&String> = base
+        .keys()
+        .chain(profile_layer.keys())
+        .filter(|key| {
+            [
+                "server.headers.",
+                "server.proxy.",
+                "build.feature_sets.",
+                "metrics.headers.",
+                "env.",
+                "mime.",
+            ]
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+        })
+        .collect();
+    named.sort();
+    named.dedup();
+    keys.extend(named.into_iter().map(|key| (key.clone(), None)));
+
+    let mut settings: Vec<Setting> = keys
+        .into_iter()
+        .map(|(key, default)| {
+            let (value, source) = if let Some((value, flag)) = flags.get(key.as_str()) {
+                (Some(value.clone()), Source::Flag(flag))
+            } else if let Some(value) = profile_layer.get(&key) {
+                (
+                    Some(value.clone()),
+                    Source::Profile(profile.unwrap_or_default().to_string()),
+                )
+            } else if let Some(value) = base.get(&key) {
+                (Some(value.clone()), Source::ProjectConfig)
+            } else if default.is_some() {
+                (default, Source::Default)
+            } else {
+                (None, Source::Unset)
+            };
+            Setting { key, value, source }
+        })
+        .collect();
+
+    let global_config = WasmrunConfig::config_path()?;
+    let global_config_exists = global_config.is_file();
+    settings.extend(global_settings(&global_config));
+    settings.extend(runtime_settings(overrides));
+
+    Ok(Resolved {
+        global_config,
+        global_config_exists,
+        project_config,
+        profile: profile.map(str::to_string),
+        settings,
+    })
+}
+
+/// Every `wasmrun.toml` key (except individual headers, proxy rules, feature sets and
+/// environment variables) with its built-in default
+fn project_defaults() -> Vec<(&'static str, Option<String>)> {
+    let string = |value: &str| Some(toml::Value::String(value.to_string()).to_string());
+    vec![
+        ("server.port", Some(DEFAULT_PORT.to_string())),
+        (
+            "server.host",
+            string(crate::server::urls::DEFAULT_BIND_HOST),
+        ),
+        ("server.hostname", None),
+        ("server.public_url", None),
+        ("server.base_path", string("/")),
+        ("server.cross_origin_isolation", Some("false".to_string())),
+        ("server.forward_console", Some("true".to_string())),
+        ("server.rate_limit", None),
+        ("server.max_connections", None),
+        (
+            "server.memory_cache_mb",
+            Some(crate::server::file_cache::DEFAULT_CAPACITY_MB.to_string()),
+        ),
+        (
+            "server.write_timeout_secs",
+            Some(
+                crate::server::timeouts::DEFAULT_WRITE_TIMEOUT
+                    .as_secs()
+                    .to_string(),
+            ),
+        ),
+        (
+            "server.response_timeout_secs",
+            Some(
+                crate::server::timeouts::DEFAULT_RESPONSE_TIMEOUT
+                    .as_secs()
+                    .to_string(),
+            ),
+        ),
+        ("build.optimization", string("release")),
+        ("build.command", None),
+        ("build.artifact", None),
+        ("build.plugin", None),
+        ("hooks.pre_build", None),
+        ("hooks.post_build", None),
+        ("budget.max_size_kb", None),
+        ("budget.max_growth_percent", None),
+        ("accessibility.reduced_motion", string("system")),
+        ("accessibility.landmarks", Some("true".to_string())),
+        (
+            "watch.debounce_ms",
+            Some(crate::watcher::DEFAULT_DEBOUNCE.as_millis().to_string()),
+        ),
+        ("watch.paths", None),
+        ("watch.ignore", None),
+        ("loading.instantiate", string("streaming")),
+        ("loading.trigger", string("eager")),
+        ("loading.module_cache", Some("false".to_string())),
+        ("python.mode", string("pyodide")),
+        (
+            "python.pyodide_url",
+            string(crate::plugin::languages::python_plugin::DEFAULT_PYODIDE_URL),
+        ),
+        ("metrics.pushgateway", None),
+        ("metrics.otlp_endpoint", None),
+        (
+            "metrics.interval_secs",
+            Some(
+                crate::server::metrics_export::DEFAULT_INTERVAL
+                    .as_secs()
+                    .to_string(),
+            ),
+        ),
+        (
+            "metrics.job",
+            string(crate::server::metrics_export::DEFAULT_JOB),
+        ),
+    ]
+}
+
+/// Project keys set by command line flags, with the flag that set them
+fn flag_layer(overrides: &Overrides) -> BTreeMap<&'static str, (String, &'static str)> {
+    let mut flags = BTreeMap::new();
+    if let Some(port) = overrides.port {
+        flags.insert("server.port", (port.to_string(), "--port"));
+    }
+    if let Some(base) = &overrides.base {
+        flags.insert(
+            "server.base_path",
+            (toml::Value::String(base.clone()).to_string(), "--base"),
+        );
+    }
+    if overrides.coi {
+        flags.insert(
+            "server.cross_origin_isolation",
+            ("true".to_string(), "--coi"),
+        );
+    }
+    if let Some(limit) = overrides.rate_limit {
+        flags.insert("server.rate_limit", (limit.to_string(), "--rate-limit"));
+    }
+    if let Some(limit) = overrides.max_connections {
+        flags.insert(
+            "server.max_connections",
+            (limit.to_string(), "--max-connections"),
+        );
+    }
+    if let Some(mode) = &overrides.instantiate {
+        flags.insert(
+            "loading.instantiate",
+            (
+                toml::Value::String(mode.clone()).to_string(),
+                "--instantiate",
+            ),
+        );
+    }
+    if let Some(trigger) = &overrides.load_trigger {
+        flags.insert(
+            "loading.trigger",
+            (
+                toml::Value::String(trigger.clone()).to_string(),
+                "--load-trigger",
+            ),
+        );
+    }
+    if overrides.module_cache {
+        flags.insert(
+            "loading.module_cache",
+            ("true".to_string(), "--module-cache"),
+        );
+    }
+    if let Some(optimization) = &overrides.optimization {
+        flags.insert(
+            "build.optimization",
+            (
+                toml::Value::String(optimization.clone()).to_string(),
+                "--optimization",
+            ),
+        );
+    }
+    flags
+}
+
+/// `global.*` settings from `~/.wasmrun/config.toml`, falling back to the built-in defaults
+fn global_settings(config_path: &Path) -> Vec<Setting> {
+    let defaults = toml::Value::try_from(WasmrunConfig::default().settings)
+        .map(|value| flatten(&value))
+        .unwrap_or_default();
+    let configured = fs::read_to_string(config_path)
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .and_then(|mut table| table.remove("settings"))
+        .map(|settings| flatten(&settings))
+        .unwrap_or_default();
+
+    // Paths without a default are left out when serialized
+    let mut keys: Vec<String> = ["cache_dir", "install_dir"].map(String::from).to_vec();
+    keys.extend(defaults.keys().chain(configured.keys()).cloned());
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .map(|key| {
+            let (value, source) = match (configured.get(&key), defaults.get(&key)) {
+                (Some(value), _) => (Some(value.clone()), Source::GlobalConfig),
+                (None, Some(value)) => (Some(value.clone()), Source::Default),
+                (None, None) => (None, Source::Unset),
+            };
+            Setting {
+                key: format!("global.{key}"),
+                value,
+                source,
+            }
+        })
+        .collect()
+}
+
+/// Process-wide switches that only come from flags or the environment
+fn runtime_settings(overrides: &Overrides) -> Vec<Setting> {
+    let offline = if overrides.offline {
+        Source::Flag("--offline")
+    } else if crate::offline::requested_by_env() {
+        Source::Env(crate::offline::OFFLINE_ENV)
+    } else {
+        Source::Default
+    };
+    let tls = if overrides.tls {
+        Source::Flag("--tls")
+    } else {
+        Source::Default
+    };
+    [("offline", offline), ("tls", tls)]
+        .into_iter()
+        .map(|(key, source)| Setting {
+            key: key.to_string(),
+            value: Some((source != Source::Default).to_string()),
+            source,
+        })
+        .collect()
+}
+
+fn flatten_settings(settings: &ProjectSettings) -> BTreeMap<String, String> {
+    toml::Value::try_from(settings)
+        .map(|value| flatten(&value))
+        .unwrap_or_default()
+}
+
+/// Dotted keys of every leaf in a TOML value; arrays are leaves
+fn flatten(value: &toml::Value) -> BTreeMap<String, String> {
+    fn walk(value: &toml::Value, prefix: &str, out: &mut BTreeMap<String, String>) {
+        match value {
+            toml::Value::Table(table) => {
+                for (key, value) in table {
+                    let key = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{prefix}.{key}")
+                    };
+                    walk(value, &key, out);
+                }
+            }
+            leaf => {
+                out.insert(prefix.to_string(), leaf.to_string());
+            }
+        }
+    }
+    let mut out = BTreeMap::new();
+    walk(value, "", &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn setting<'a>(resolved: &'a Resolved, key: &str) -> &'a Setting {
+        resolved
+            .settings
+            .iter()
+            .find(|setting| setting.key == key)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_every_project_key_has_an_entry() {
+        let full = ProjectConfig::from_toml(
+            r#"
+[server]
+port = 1
+host = "h"
+hostname = "h"
+public_url = "https://example.test"
+base_path = "/x/"
+cross_origin_isolation = true
+forward_console = false
+rate_limit = 600
+max_connections = 64
+memory_cache_mb = 64
+write_timeout_secs = 30
+response_timeout_secs = 0
+[build]
+optimization = "size"
+command = "make"
+artifact = "app.wasm"
+plugin = "c"
+[hooks]
+pre_build = ["a"]
+post_build = ["b"]
+[budget]
+max_size_kb = 1
+max_growth_percent = 1.0
+[accessibility]
+reduced_motion = "off"
+landmarks = false
+[watch]
+paths = ["src"]
+debounce_ms = 100
+ignore = ["dist/"]
+[loading]
+instantiate = "buffer"
+trigger = "gesture"
+module_cache = true
+[python]
+mode = "py2wasm"
+pyodide_url = "http://localhost:9000/"
+[metrics]
+pushgateway = "http://pushgateway:9091"
+otlp_endpoint = "http://collector:4318"
+interval_secs = 30
+job = "dev"
+"#,
+        )
+        .unwrap();
+        let known: Vec<&str> = project_defaults().into_iter().map(|(key, _)| key).collect();
+        for key in flatten_settings(&full.base).keys() {
+            assert!(
+                known.contains(&key.as_str()),
+                "{key} missing from project_defaults"
+            );
+        }
+    }
+
+    #[test]
+    fn test_layers_and_sources() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("wasmrun.toml"),
+            r#"
+[server]
+port = 8000
+host = "127.0.0.1"
+[server.headers]
+"X-Base" = "1"
+[profile.demo.server]
+port = 9000
+[profile.demo.server.headers]
+"X-Demo" = "2"
+[profile.demo.build]
+optimization = "size"
+[profile.demo.build.feature_sets]
+simd = ["simd"]
+"#,
+        )
+        .unwrap();
+        let path = dir.path().to_str().unwrap();
+
+        let overrides = Overrides {
+            optimization: Some("debug".to_string()),
+            coi: true,
+            ..Overrides::default()
+        };
+        let resolved = resolve(path, Some("demo"), &overrides).unwrap();
+        let port = setting(&resolved, "server.port");
+        assert_eq!(port.value.as_deref(), Some("9000"));
+        assert_eq!(port.source, Source::Profile("demo".to_string()));
+        assert_eq!(
+            setting(&resolved, "server.host").source,
+            Source::ProjectConfig
+        );
+        assert_eq!(
+            setting(&resolved, "server.host").value.as_deref(),
+            Some("\"127.0.0.1\"")
+        );
+        assert_eq!(
+            setting(&resolved, "server.headers.X-Base").source,
+            Source::ProjectConfig
+        );
+        assert_eq!(
+            setting(&resolved, "server.headers.X-Demo").source,
+            Source::Profile("demo".to_string())
+        );
+        assert_eq!(
+            setting(&resolved, "build.optimization").source,
+            Source::Flag("--optimization")
+        );
+        assert_eq!(
+            setting(&resolved, "build.feature_sets.simd")
+                .value
+                .as_deref(),
+            Some("[\"simd\"]")
+        );
+        assert_eq!(
+            setting(&resolved, "server.cross_origin_isolation")
+                .value
+                .as_deref(),
+            Some("true")
+        );
+        assert_eq!(
+            setting(&resolved, "accessibility.landmarks").source,
+            Source::Default
+        );
+        assert_eq!(
+            setting(&resolved, "budget.max_size_kb").source,
+            Source::Unset
+        );
+        assert!(resolved
+            .settings
+            .iter()
+            .any(|setting| setting.key == "global.default_optimization"));
+
+        let without_profile = resolve(path, None, &Overrides::default()).unwrap();
+        assert_eq!(
+            setting(&without_profile, "server.port").value.as_deref(),
+            Some("8000")
+        );
+        assert!(resolve(path, Some("missing"), &Overrides::default()).is_err());
+    }
+}
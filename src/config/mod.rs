@@ -1,11 +1,15 @@
 //! Configuration module for Wasmrun
 
 pub mod constants;
+pub mod effective;
 pub mod plugin;
+pub mod project;
+pub mod reload;
 pub mod server;
 
 pub use constants::*;
-pub use plugin::{ExternalPluginEntry, WasmrunConfig};
+pub use plugin::{ExternalPluginEntry, GlobalSettings, WasmrunConfig};
+pub use project::{active_settings, ProjectSettings, WatchSection};
 pub use server::{
     compile_project, run_server, setup_project_compilation, FileInfo, PortStatus, ServerConfig,
     ServerInfo,
@@ -24,6 +24,21 @@ pub struct GlobalSettings {
     pub install_dir: Option<PathBuf>,
     pub verbose: bool,
     pub default_optimization: String,
+    #[serde(default = "default_workspace_max_size_mb")]
+    pub workspace_max_size_mb: u64,
+    /// Proxy for http URLs, used when `HTTP_PROXY` isn't set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_proxy: Option<String>,
+    /// Proxy for https URLs, used when `HTTPS_PROXY` isn't set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub https_proxy: Option<String>,
+    /// Hosts to reach directly, used when `NO_PROXY` isn't set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+}
+
+fn default_workspace_max_size_mb() -> u64 {
+    crate::utils::workspace::DEFAULT_WORKSPACE_MAX_MB
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +61,10 @@ impl Default for GlobalSettings {
             install_dir: None,
             verbose: false,
             default_optimization: "size".to_string(),
+            workspace_max_size_mb: default_workspace_max_size_mb(),
+            http_proxy: None,
+            https_proxy: None,
+            no_proxy: None,
         }
     }
 }
@@ -84,6 +103,19 @@ impl WasmrunConfig {
         Ok(home_dir.join(".wasmrun"))
     }
 
+    /// Directory for files that only matter while a process runs, such as IPC
+    /// endpoints: the platform's runtime directory (`$XDG_RUNTIME_DIR` on
+    /// Linux), which only the user can access and which does not outlive a
+    /// reboot, or else the config dir
+    pub fn runtime_dir() -> Result<PathBuf> {
+        if std::env::var_os("WASMRUN_CONFIG_PATH").is_none() {
+            if let Some(runtime_dir) = dirs::runtime_dir() {
+                return Ok(runtime_dir.join("wasmrun"));
+            }
+        }
+        Self::config_dir()
+    }
+
     pub fn plugin_dir() -> Result<PathBuf> {
         let config = Self::load_or_default()?;
 
@@ -94,7 +126,6 @@ impl WasmrunConfig {
         }
     }
 
-    #[allow(dead_code)] // TODO: Future plugin cache directory management
     pub fn cache_dir() -> Result<PathBuf> {
         let config = Self::load_or_default()?;
 
@@ -179,7 +210,8 @@ impl WasmrunConfig {
             })
         })?;
 
-        fs::write(&config_path, config_content).map_err(|e| {
+        // Another wasmrun may be reading the config right now
+        crate::utils::write_atomic(&config_path, config_content.as_bytes()).map_err(|e| {
             WasmrunError::Config(crate::error::ConfigError::ParseError {
                 message: format!("Failed to write config file: {e}"),
             })
@@ -304,9 +336,9 @@ impl WasmrunConfig {
             self.save()?;
             Ok(())
         } else {
-            Err(WasmrunError::from(format!(
-                "External plugin '{name}' not found"
-            )))
+            Err(WasmrunError::PluginNotFound {
+                name: name.to_string(),
+            })
         }
     }
 
@@ -317,9 +349,9 @@ impl WasmrunConfig {
             self.save()?;
             Ok(())
         } else {
-            Err(WasmrunError::from(format!(
-                "External plugin '{name}' not found"
-            )))
+            Err(WasmrunError::PluginNotFound {
+                name: name.to_string(),
+            })
         }
     }
 
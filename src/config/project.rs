@@ -0,0 +1,897 @@
+//! Project-level configuration (`wasmrun.toml`)
+//!
+//! A project may ship a `wasmrun.toml` next to its sources. Top-level sections
+//! apply everywhere, and `[profile.<name>]` tables override them when the
+//! profile is selected with `--profile <name>`:
+//!
+//! ```toml
+//! [server]
+//! port = 8420
+//!
+//! [build]
+//! optimization = "debug"
+//!
+//! [build.feature_sets]
+//! simd = ["simd"]
+//!
+//! [profile.ci.server]
+//! port = 9000
+//!
+//! [profile.demo.build]
+//! optimization = "size"
+//!
+//! [profile.demo.server.headers]
+//! "Cache-Control" = "no-store"
+//!
+//! [profile.demo.hooks]
+//! pre_build = ["npm run assets"]
+//!
+//! [budget]
+//! max_size_kb = 512
+//! max_growth_percent = 5.0
+//!
+//! [accessibility]
+//! reduced_motion = "always"
+//! landmarks = true
+//!
+//! [loading]
+//! instantiate = "buffer"
+//! trigger = "gesture"
+//! module_cache = true
+//!
+//! [python]
+//! mode = "pyodide"
+//!
+//! [metrics]
+//! pushgateway = "http://pushgateway:9091"
+//! otlp_endpoint = "http://collector:4318"
+//! interval_secs = 30
+//! ```
+
+use crate::compiler::builder::OptimizationLevel;
+use crate::error::{CompilationResult, ConfigError, Result, WasmrunError};
+use crate::utils::CommandExecutor;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Name of the project configuration file
+pub const PROJECT_CONFIG_FILE: &str = "wasmrun.toml";
+
+/// Settings resolved for the current invocation, shared with the server and build steps
+static ACTIVE_PROJECT: RwLock<Option<ActiveProject>> = RwLock::new(None);
+
+/// Where the active settings came from, so they can be reloaded later
+#[derive(Debug, Clone)]
+struct ActiveProject {
+    config_path: Option<PathBuf>,
+    profile: Option<String>,
+    settings: ProjectSettings,
+}
+
+/// Contents of a `wasmrun.toml` file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectConfig {
+    #[serde(flatten)]
+    pub base: ProjectSettings,
+    #[serde(default)]
+    pub profile: HashMap<String, ProjectSettings>,
+}
+
+/// A set of project settings, used both for the base config and for each profile
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectSettings {
+    #[serde(default)]
+    pub server: ServerSection,
+    #[serde(default)]
+    pub build: BuildSection,
+    #[serde(default)]
+    pub hooks: HooksSection,
+    #[serde(default)]
+    pub budget: BudgetSection,
+    #[serde(default)]
+    pub accessibility: AccessibilitySection,
+    #[serde(default)]
+    pub watch: WatchSection,
+    #[serde(default)]
+    pub loading: LoadingSection,
+    #[serde(default)]
+    pub python: PythonSection,
+    #[serde(default)]
+    pub metrics: MetricsSection,
+    /// Environment variables for builds, hooks and the server
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Content types of served files by extension, over the built-in ones
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub mime: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerSection {
+    pub port: Option<u16>,
+    /// Address to bind (default: all interfaces)
+    pub host: Option<String>,
+    /// Extra host name the server is reachable under, e.g. `dev.example.test`
+    pub hostname: Option<String>,
+    /// Externally reachable URL, e.g. a tunnel pointing at the dev server
+    pub public_url: Option<String>,
+    /// Path prefix the server is reached under behind a reverse proxy, e.g. `/myapp/`
+    pub base_path: Option<String>,
+    /// Send COOP/COEP headers so pages can use `SharedArrayBuffer` and wasm threads
+    pub cross_origin_isolation: Option<bool>,
+    /// Print the page's console output and uncaught errors in the terminal (default: true)
+    pub forward_console: Option<bool>,
+    /// Requests per minute a single client may make before getting 429 responses
+    pub rate_limit: Option<u32>,
+    /// Requests the server keeps open at once, live-reload polls included
+    pub max_connections: Option<usize>,
+    /// Memory for served files kept between requests, in megabytes (default: 256, 0 turns it off)
+    pub memory_cache_mb: Option<u64>,
+    /// Seconds to wait for a client to take more of a response (default: 60, 0 waits forever)
+    pub write_timeout_secs: Option<u64>,
+    /// Seconds a single response may take in total (default: 600, 0 for no limit)
+    pub response_timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Path prefixes forwarded to a backend, e.g. `"/api" = "http://localhost:8080"`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub proxy: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuildSection {
+    pub optimization: Option<String>,
+    /// Shell command that builds the project instead of a plugin
+    pub command: Option<String>,
+    /// Module the build command produces, relative to the project
+    pub artifact: Option<String>,
+    /// Plugin to build with instead of the detected one, e.g. `wasmrust`
+    pub plugin: Option<String>,
+    /// Named groups of Cargo features that can be switched from the served page
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub feature_sets: BTreeMap<String, Vec<String>>,
+}
+
+impl BuildSection {
+    /// `optimization` as a build level, release when it is not set
+    pub fn optimization_level(&self) -> OptimizationLevel {
+        match self.optimization.as_deref() {
+            Some("debug") => OptimizationLevel::Debug,
+            Some("size") => OptimizationLevel::Size,
+            _ => OptimizationLevel::Release,
+        }
+    }
+}
+
+/// Size limits checked by `wasmrun ci-report`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BudgetSection {
+    /// Largest allowed size of a single `.wasm` artifact, in kilobytes
+    pub max_size_kb: Option<u64>,
+    /// Largest allowed growth over the base branch, in percent
+    pub max_growth_percent: Option<f64>,
+}
+
+/// Accessibility options for the generated runner page
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccessibilitySection {
+    /// `system` (honor prefers-reduced-motion, the default), `always` or `off`
+    pub reduced_motion: Option<String>,
+    /// Add a skip link and a main landmark to the page (default: true)
+    pub landmarks: Option<bool>,
+}
+
+/// How Python projects are turned into something the browser runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PythonSection {
+    /// `pyodide` (the default) to run the sources with Pyodide in the page,
+    /// or `py2wasm` to compile them into a WASI module
+    pub mode: Option<String>,
+    /// Where the page loads Pyodide from, e.g. a self-hosted copy
+    pub pyodide_url: Option<String>,
+}
+
+/// Where the server pushes its metrics, besides serving them itself
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricsSection {
+    /// Prometheus Pushgateway, e.g. `http://pushgateway:9091`
+    pub pushgateway: Option<String>,
+    /// OTLP/HTTP collector, e.g. `http://collector:4318`
+    pub otlp_endpoint: Option<String>,
+    /// Seconds between pushes (default: 15)
+    pub interval_secs: Option<u64>,
+    /// Pushgateway job and OTLP `service.name` (default: `wasmrun`)
+    pub job: Option<String>,
+    /// Headers sent with every push, e.g. `Authorization`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// How the runner page fetches, compiles and instantiates the module
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoadingSection {
+    /// `streaming` (the default) or `buffer` to fetch the whole module first
+    pub instantiate: Option<String>,
+    /// `eager` (the default) or `gesture` to wait for a click or key press
+    pub trigger: Option<String>,
+    /// Keep compiled modules in IndexedDB between loads (default: false)
+    pub module_cache: Option<bool>,
+}
+
+/// How watch mode follows the project's files
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WatchSection {
+    /// Directories or files to watch instead of the whole project
+    pub paths: Option<Vec<String>>,
+    /// How long to wait for a burst of changes to settle, in milliseconds (default: 300)
+    pub debounce_ms: Option<u64>,
+    /// Globs of paths to ignore, on top of `target/`, `node_modules/` and `.git/`
+    pub ignore: Option<Vec<String>>,
+    /// Compile a rebuilt module in open pages before reloading them (default: false)
+    pub prewarm: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HooksSection {
+    pub pre_build: Option<Vec<String>>,
+    pub post_build: Option<Vec<String>>,
+}
+
+impl ProjectConfig {
+    /// Locate `wasmrun.toml` for a project path (a directory or a file inside it)
+    pub fn find(project_path: &str) -> Option<PathBuf> {
+        let path = Path::new(project_path);
+        let dir = if path.is_file() { path.parent()? } else { path };
+        let candidate = dir.join(PROJECT_CONFIG_FILE);
+        candidate.is_file().then_some(candidate)
+    }
+
+    pub fn load_from_file(config_path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(config_path).map_err(|e| {
+            WasmrunError::Config(ConfigError::ParseError {
+                message: format!("Failed to read {}: {e}", config_path.display()),
+            })
+        })?;
+
+        Self::from_toml(&content).map_err(|e| match e {
+            WasmrunError::Config(ConfigError::ParseError { message }) => {
+                WasmrunError::Config(ConfigError::ParseError {
+                    message: format!("{}: {message}", config_path.display()),
+                })
+            }
+            other => other,
+        })
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let config: Self = toml::from_str(content).map_err(|e| {
+            WasmrunError::Config(ConfigError::ParseError {
+                message: format!("Invalid {PROJECT_CONFIG_FILE}: {e}"),
+            })
+        })?;
+        config.base.validate()?;
+        for settings in config.profile.values() {
+            settings.validate()?;
+        }
+        Ok(config)
+    }
+
+    /// Resolve the effective settings, applying the named profile on top of the base
+    pub fn resolve(&self, profile: Option<&str>) -> Result<ProjectSettings> {
+        let Some(name) = profile else {
+            return Ok(self.base.clone());
+        };
+
+        let overrides = self.profile.get(name).ok_or_else(|| {
+            let available = self.profile_names();
+            WasmrunError::Config(ConfigError::InvalidValue {
+                message: if available.is_empty() {
+                    format!("Unknown profile '{name}': no profiles are defined in {PROJECT_CONFIG_FILE}")
+                } else {
+                    format!(
+                        "Unknown profile '{name}'. Available profiles: {}",
+                        available.join(", ")
+                    )
+                },
+            })
+        })?;
+
+        Ok(self.base.merged_with(overrides))
+    }
+
+    /// Profile names defined in this config, sorted
+    pub fn profile_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.profile.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+impl ProjectSettings {
+    /// Return a copy of `self` with every value set in `overrides` replacing the base value
+    pub fn merged_with(&self, overrides: &ProjectSettings) -> ProjectSettings {
+        let mut merged = self.clone();
+
+        if overrides.server.port.is_some() {
+            merged.server.port = overrides.server.port;
+        }
+        if overrides.server.host.is_some() {
+            merged.server.host = overrides.server.host.clone();
+        }
+        if overrides.server.hostname.is_some() {
+            merged.server.hostname = overrides.server.hostname.clone();
+        }
+        if overrides.server.public_url.is_some() {
+            merged.server.public_url = overrides.server.public_url.clone();
+        }
+        if overrides.server.base_path.is_some() {
+            merged.server.base_path = overrides.server.base_path.clone();
+        }
+        if overrides.server.cross_origin_isolation.is_some() {
+            merged.server.cross_origin_isolation = overrides.server.cross_origin_isolation;
+        }
+        if overrides.server.forward_console.is_some() {
+            merged.server.forward_console = overrides.server.forward_console;
+        }
+        if overrides.server.rate_limit.is_some() {
+            merged.server.rate_limit = overrides.server.rate_limit;
+        }
+        if overrides.server.max_connections.is_some() {
+            merged.server.max_connections = overrides.server.max_connections;
+        }
+        if overrides.server.memory_cache_mb.is_some() {
+            merged.server.memory_cache_mb = overrides.server.memory_cache_mb;
+        }
+        if overrides.server.write_timeout_secs.is_some() {
+            merged.server.write_timeout_secs = overrides.server.write_timeout_secs;
+        }
+        if overrides.server.response_timeout_secs.is_some() {
+            merged.server.response_timeout_secs = overrides.server.response_timeout_secs;
+        }
+        for (name, value) in &overrides.server.headers {
+            merged.server.headers.insert(name.clone(), value.clone());
+        }
+        for (prefix, upstream) in &overrides.server.proxy {
+            merged.server.proxy.insert(prefix.clone(), upstream.clone());
+        }
+        if overrides.build.optimization.is_some() {
+            merged.build.optimization = overrides.build.optimization.clone();
+        }
+        if overrides.build.command.is_some() {
+            merged.build.command = overrides.build.command.clone();
+        }
+        if overrides.build.artifact.is_some() {
+            merged.build.artifact = overrides.build.artifact.clone();
+        }
+        if overrides.build.plugin.is_some() {
+            merged.build.plugin = overrides.build.plugin.clone();
+        }
+        for (name, features) in &overrides.build.feature_sets {
+            merged
+                .build
+                .feature_sets
+                .insert(name.clone(), features.clone());
+        }
+        if overrides.hooks.pre_build.is_some() {
+            merged.hooks.pre_build = overrides.hooks.pre_build.clone();
+        }
+        if overrides.hooks.post_build.is_some() {
+            merged.hooks.post_build = overrides.hooks.post_build.clone();
+        }
+        if overrides.budget.max_size_kb.is_some() {
+            merged.budget.max_size_kb = overrides.budget.max_size_kb;
+        }
+        if overrides.budget.max_growth_percent.is_some() {
+            merged.budget.max_growth_percent = overrides.budget.max_growth_percent;
+        }
+        if overrides.accessibility.reduced_motion.is_some() {
+            merged.accessibility.reduced_motion = overrides.accessibility.reduced_motion.clone();
+        }
+        if overrides.accessibility.landmarks.is_some() {
+            merged.accessibility.landmarks = overrides.accessibility.landmarks;
+        }
+        if overrides.loading.instantiate.is_some() {
+            merged.loading.instantiate = overrides.loading.instantiate.clone();
+        }
+        if overrides.loading.trigger.is_some() {
+            merged.loading.trigger = overrides.loading.trigger.clone();
+        }
+        if overrides.loading.module_cache.is_some() {
+            merged.loading.module_cache = overrides.loading.module_cache;
+        }
+        if overrides.python.mode.is_some() {
+            merged.python.mode = overrides.python.mode.clone();
+        }
+        if overrides.python.pyodide_url.is_some() {
+            merged.python.pyodide_url = overrides.python.pyodide_url.clone();
+        }
+        if overrides.metrics.pushgateway.is_some() {
+            merged.metrics.pushgateway = overrides.metrics.pushgateway.clone();
+        }
+        if overrides.metrics.otlp_endpoint.is_some() {
+            merged.metrics.otlp_endpoint = overrides.metrics.otlp_endpoint.clone();
+        }
+        if overrides.metrics.interval_secs.is_some() {
+            merged.metrics.interval_secs = overrides.metrics.interval_secs;
+        }
+        if overrides.metrics.job.is_some() {
+            merged.metrics.job = overrides.metrics.job.clone();
+        }
+        for (name, value) in &overrides.metrics.headers {
+            merged.metrics.headers.insert(name.clone(), value.clone());
+        }
+        if overrides.watch.paths.is_some() {
+            merged.watch.paths = overrides.watch.paths.clone();
+        }
+        if overrides.watch.debounce_ms.is_some() {
+            merged.watch.debounce_ms = overrides.watch.debounce_ms;
+        }
+        if overrides.watch.ignore.is_some() {
+            merged.watch.ignore = overrides.watch.ignore.clone();
+        }
+        if overrides.watch.prewarm.is_some() {
+            merged.watch.prewarm = overrides.watch.prewarm;
+        }
+        for (name, value) in &overrides.env {
+            merged.env.insert(name.clone(), value.clone());
+        }
+        for (extension, content_type) in &overrides.mime {
+            merged.mime.insert(extension.clone(), content_type.clone());
+        }
+
+        merged
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.server.port == Some(0) {
+            return Err(WasmrunError::Config(ConfigError::InvalidValue {
+                message: "server.port must be between 1-65535".to_string(),
+            }));
+        }
+
+        for (key, host) in [
+            ("server.host", &self.server.host),
+            ("server.hostname", &self.server.hostname),
+        ] {
+            if let Some(host) = host {
+                crate::server::urls::host_to_ascii(host).map_err(|reason| {
+                    WasmrunError::Config(ConfigError::InvalidValue {
+                        message: format!("{key} '{host}' is not a valid host: {reason}"),
+                    })
+                })?;
+            }
+        }
+        if let Some(url) = &self.server.public_url {
+            crate::server::urls::normalize_url(url).map_err(|reason| {
+                WasmrunError::Config(ConfigError::InvalidValue {
+                    message: format!("server.public_url '{url}' is not a valid URL: {reason}"),
+                })
+            })?;
+        }
+
+        if let Some(base) = &self.server.base_path {
+            crate::server::base_path::normalize(base).map_err(|reason| {
+                WasmrunError::Config(ConfigError::InvalidValue {
+                    message: format!("server.base_path '{base}' {reason}"),
+                })
+            })?;
+        }
+
+        for (prefix, upstream) in &self.server.proxy {
+            crate::server::proxy::ProxyRule::parse(&format!("{prefix}={upstream}")).map_err(
+                |reason| {
+                    WasmrunError::Config(ConfigError::InvalidValue {
+                        message: format!("server.proxy \"{prefix}\" {reason}"),
+                    })
+                },
+            )?;
+        }
+
+        if let Some(level) = &self.build.optimization {
+            if !["debug", "release", "size"].contains(&level.as_str()) {
+                return Err(WasmrunError::Config(ConfigError::InvalidValue {
+                    message: format!(
+                        "build.optimization must be one of debug, release, size (got '{level}')"
+                    ),
+                }));
+            }
+        }
+
+        for (name, features) in &self.build.feature_sets {
+            let invalid = features.iter().find(|feature| {
+                feature.is_empty()
+                    || feature
+                        .chars()
+                        .any(|c| c.is_whitespace() || c == ',' || c == '"')
+            });
+            if let Some(feature) = invalid {
+                return Err(WasmrunError::Config(ConfigError::InvalidValue {
+                    message: format!(
+                        "build.feature_sets.{name} contains an invalid feature name '{feature}'"
+                    ),
+                }));
+            }
+        }
+
+        if self
+            .build
+            .command
+            .as_deref()
+            .is_some_and(|command| command.trim().is_empty())
+        {
+            return Err(WasmrunError::Config(ConfigError::InvalidValue {
+                message: "build.command must not be empty".to_string(),
+            }));
+        }
+
+        if let Some(name) = self
+            .env
+            .keys()
+            .find(|name| name.is_empty() || name.contains(['=', '\0']))
+        {
+            return Err(WasmrunError::Config(ConfigError::InvalidValue {
+                message: format!("env contains an invalid variable name '{name}'"),
+            }));
+        }
+
+        if let Some((extension, content_type)) =
+            self.mime.iter().find(|(extension, content_type)| {
+                extension.trim_start_matches('.').is_empty()
+                    || !crate::server::mime::is_valid(content_type)
+            })
+        {
+            return Err(WasmrunError::Config(ConfigError::InvalidValue {
+                message: format!(
+                    "mime.{extension} must map an extension to a content type such as \"application/wasm\", not \"{content_type}\""
+                ),
+            }));
+        }
+
+        if self.budget.max_size_kb == Some(0) {
+            return Err(WasmrunError::Config(ConfigError::InvalidValue {
+                message: "budget.max_size_kb must be greater than 0".to_string(),
+            }));
+        }
+        if let Some(percent) = self.budget.max_growth_percent {
+            if !percent.is_finite() || percent < 0.0 {
+                return Err(WasmrunError::Config(ConfigError::InvalidValue {
+                    message: format!(
+                        "budget.max_growth_percent must be zero or positive (got {percent})"
+                    ),
+                }));
+            }
+        }
+
+        if let Some(mode) = &self.accessibility.reduced_motion {
+            if !["system", "always", "off"].contains(&mode.as_str()) {
+                return Err(WasmrunError::Config(ConfigError::InvalidValue {
+                    message: format!(
+                        "accessibility.reduced_motion must be one of system, always, off (got '{mode}')"
+                    ),
+                }));
+            }
+        }
+        if let Some(mode) = &self.loading.instantiate {
+            if !["streaming", "buffer"].contains(&mode.as_str()) {
+                return Err(WasmrunError::Config(ConfigError::InvalidValue {
+                    message: format!(
+                        "loading.instantiate must be one of streaming, buffer (got '{mode}')"
+                    ),
+                }));
+            }
+        }
+        if let Some(trigger) = &self.loading.trigger {
+            if !["eager", "gesture"].contains(&trigger.as_str()) {
+                return Err(WasmrunError::Config(ConfigError::InvalidValue {
+                    message: format!(
+                        "loading.trigger must be one of eager, gesture (got '{trigger}')"
+                    ),
+                }));
+            }
+        }
+        if let Some(mode) = &self.python.mode {
+            if !["pyodide", "py2wasm"].contains(&mode.as_str()) {
+                return Err(WasmrunError::Config(ConfigError::InvalidValue {
+                    message: format!("python.mode must be one of pyodide, py2wasm (got '{mode}')"),
+                }));
+            }
+        }
+        for (key, url) in [
+            ("metrics.pushgateway", &self.metrics.pushgateway),
+            ("metrics.otlp_endpoint", &self.metrics.otlp_endpoint),
+        ] {
+            if let Some(url) = url {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(WasmrunError::Config(ConfigError::InvalidValue {
+                        message: format!("{key} must be an http:// or https:// URL (got '{url}')"),
+                    }));
+                }
+            }
+        }
+        if self.metrics.interval_secs == Some(0) {
+            return Err(WasmrunError::Config(ConfigError::InvalidValue {
+                message: "metrics.interval_secs must be greater than 0".to_string(),
+            }));
+        }
+
+        Ok(())
+    }
+}
+
+impl HooksSection {
+    /// Run the configured `pre_build` hooks in the project directory
+    pub fn run_pre_build(&self, project_path: &str) -> CompilationResult<()> {
+        match &self.pre_build {
+            Some(commands) => CommandExecutor::run_hooks("pre_build", commands, project_path),
+            None => Ok(()),
+        }
+    }
+
+    /// Run the configured `post_build` hooks in the project directory
+    pub fn run_post_build(&self, project_path: &str) -> CompilationResult<()> {
+        match &self.post_build {
+            Some(commands) => CommandExecutor::run_hooks("post_build", commands, project_path),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Load and resolve the project settings for this invocation and make them active
+pub fn activate(project_path: &str, profile: Option<&str>) -> Result<ProjectSettings> {
+    let config_path = ProjectConfig::find(project_path);
+    let config = match &config_path {
+        Some(path) => ProjectConfig::load_from_file(path)?,
+        None => ProjectConfig::default(),
+    };
+    let settings = config.resolve(profile)?;
+
+    if let Ok(mut active) = ACTIVE_PROJECT.write() {
+        *active = Some(ActiveProject {
+            config_path,
+            profile: profile.map(str::to_string),
+            settings: settings.clone(),
+        });
+    }
+
+    Ok(settings)
+}
+
+/// Export `[env]` to this process, so builds, hooks and plugins see it;
+/// variables already set in the environment keep their value
+pub fn export_env(settings: &ProjectSettings) {
+    for (name, value) in &settings.env {
+        if std::env::var_os(name).is_none() {
+            std::env::set_var(name, value);
+        }
+    }
+}
+
+/// The project settings activated for this invocation (defaults if none were loaded)
+pub fn active_settings() -> ProjectSettings {
+    ACTIVE_PROJECT
+        .read()
+        .ok()
+        .and_then(|active| active.as_ref().map(|project| project.settings.clone()))
+        .unwrap_or_default()
+}
+
+/// The config file and profile the active settings were loaded from
+pub fn active_source() -> Option<(PathBuf, Option<String>)> {
+    let active = ACTIVE_PROJECT.read().ok()?;
+    let project = active.as_ref()?;
+    Some((project.config_path.clone()?, project.profile.clone()))
+}
+
+/// Replace the active settings, keeping the recorded config source
+pub fn replace_active_settings(settings: ProjectSettings) {
+    if let Ok(mut active) = ACTIVE_PROJECT.write() {
+        if let Some(project) = active.as_mut() {
+            project.settings = settings;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const SAMPLE: &str = r#"
+[server]
+port = 8000
+
+[server.headers]
+"X-Base" = "1"
+
+[build]
+optimization = "debug"
+
+[build.feature_sets]
+simd = ["simd"]
+
+[hooks]
+pre_build = ["echo base"]
+
+[profile.ci.server]
+port = 9000
+
+[profile.demo.build]
+optimization = "size"
+
+[profile.demo.build.feature_sets]
+fast = ["simd", "fast-math"]
+
+[profile.demo.server.headers]
+"X-Demo" = "yes"
+
+[profile.demo.hooks]
+pre_build = []
+post_build = ["echo done"]
+"#;
+
+    #[test]
+    fn test_resolve_without_profile_uses_base() {
+        let config = ProjectConfig::from_toml(SAMPLE).unwrap();
+        let settings = config.resolve(None).unwrap();
+        assert_eq!(settings.server.port, Some(8000));
+        assert_eq!(settings.build.optimization.as_deref(), Some("debug"));
+        assert_eq!(
+            settings.hooks.pre_build,
+            Some(vec!["echo base".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_resolve_profile_overrides_only_set_values() {
+        let config = ProjectConfig::from_toml(SAMPLE).unwrap();
+
+        let ci = config.resolve(Some("ci")).unwrap();
+        assert_eq!(ci.server.port, Some(9000));
+        assert_eq!(ci.build.optimization.as_deref(), Some("debug"));
+
+        let demo = config.resolve(Some("demo")).unwrap();
+        assert_eq!(demo.server.port, Some(8000));
+        assert_eq!(demo.build.optimization.as_deref(), Some("size"));
+        assert!(matches!(
+            demo.build.optimization_level(),
+            OptimizationLevel::Size
+        ));
+        assert!(matches!(
+            BuildSection::default().optimization_level(),
+            OptimizationLevel::Release
+        ));
+        assert_eq!(
+            demo.server.headers.get("X-Base").map(String::as_str),
+            Some("1")
+        );
+        assert_eq!(
+            demo.server.headers.get("X-Demo").map(String::as_str),
+            Some("yes")
+        );
+        assert_eq!(demo.hooks.pre_build, Some(vec![]));
+        assert_eq!(demo.hooks.post_build, Some(vec!["echo done".to_string()]));
+        assert_eq!(
+            demo.build.feature_sets.keys().collect::<Vec<_>>(),
+            ["fast", "simd"]
+        );
+        assert_eq!(ci.build.feature_sets.len(), 1);
+    }
+
+    #[test]
+    fn test_resolve_unknown_profile() {
+        let config = ProjectConfig::from_toml(SAMPLE).unwrap();
+        let err = config.resolve(Some("staging")).unwrap_err().to_string();
+        assert!(err.contains("staging"));
+        assert!(err.contains("ci, demo"));
+    }
+
+    #[test]
+    fn test_invalid_optimization_rejected() {
+        let result = ProjectConfig::from_toml("[profile.ci.build]\noptimization = \"fast\"\n");
+        assert!(result.is_err());
+        let result = ProjectConfig::from_toml("[accessibility]\nreduced_motion = \"never\"\n");
+        assert!(result.is_err());
+        let result = ProjectConfig::from_toml("[build.feature_sets]\nboth = [\"a,b\"]\n");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_build_proxy_and_env_settings() {
+        let config = ProjectConfig::from_toml(
+            r#"
+env = { RUST_LOG = "info", API_KEY = "dev" }
+
+[server.proxy]
+"/api" = "http://localhost:8080"
+
+[build]
+command = "make wasm"
+artifact = "dist/app.wasm"
+
+[watch]
+paths = ["src", "static"]
+
+[profile.ci]
+env = { RUST_LOG = "warn" }
+
+[profile.ci.build]
+plugin = "c"
+
+[profile.ci.server.proxy]
+"/auth" = "http://localhost:9000"
+"#,
+        )
+        .unwrap();
+        let ci = config.resolve(Some("ci")).unwrap();
+        assert_eq!(ci.build.command.as_deref(), Some("make wasm"));
+        assert_eq!(ci.build.plugin.as_deref(), Some("c"));
+        assert_eq!(ci.env.get("RUST_LOG").map(String::as_str), Some("warn"));
+        assert_eq!(ci.env.get("API_KEY").map(String::as_str), Some("dev"));
+        assert_eq!(ci.server.proxy.len(), 2);
+        assert_eq!(
+            ci.watch.paths,
+            Some(vec!["src".to_string(), "static".to_string()])
+        );
+
+        assert!(ProjectConfig::from_toml("[server.proxy]\n\"api\" = \"http://x\"\n").is_err());
+        assert!(ProjectConfig::from_toml("[server.proxy]\n\"/api\" = \"x:1\"\n").is_err());
+        assert!(ProjectConfig::from_toml("[build]\ncommand = \" \"\n").is_err());
+        assert!(ProjectConfig::from_toml("[env]\n\"A=B\" = \"1\"\n").is_err());
+    }
+
+    #[test]
+    fn test_mime_overrides() {
+        let config = ProjectConfig::from_toml(
+            r#"
+[mime]
+cwasm = "application/wasm"
+
+[profile.ci.mime]
+data = "application/x-emscripten-data"
+"#,
+        )
+        .unwrap();
+        let ci = config.resolve(Some("ci")).unwrap();
+        assert_eq!(ci.mime.len(), 2);
+        assert_eq!(
+            ci.mime.get("data").map(String::as_str),
+            Some("application/x-emscripten-data")
+        );
+
+        assert!(ProjectConfig::from_toml(
+            "[mime]
+cwasm = \"wasm\"\n"
+        )
+        .is_err());
+        assert!(ProjectConfig::from_toml(
+            "[mime]
+\".\" = \"text/plain\"\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_activate_without_config_file_uses_defaults() {
+        let temp_dir = tempdir().unwrap();
+        let project_path = temp_dir.path().to_str().unwrap();
+        assert_eq!(ProjectConfig::find(project_path), None);
+
+        let settings = activate(project_path, None).unwrap();
+        assert_eq!(settings, ProjectSettings::default());
+        assert!(activate(project_path, Some("ci")).is_err());
+    }
+
+    #[test]
+    fn test_find_from_file_inside_project() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(PROJECT_CONFIG_FILE), SAMPLE).unwrap();
+        let wasm_path = temp_dir.path().join("module.wasm");
+        fs::write(&wasm_path, b"\0asm").unwrap();
+
+        let found = ProjectConfig::find(wasm_path.to_str().unwrap());
+        assert_eq!(found, Some(temp_dir.path().join(PROJECT_CONFIG_FILE)));
+    }
+}
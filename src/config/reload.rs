@@ -0,0 +1,221 @@
+//! Live reload of `wasmrun.toml` while the server is running
+//!
+//! Settings that only affect how responses are produced (such as custom
+//! headers) are applied immediately. Settings that are baked in at startup
+//! (the port and the build pipeline) are reported so the user can restart.
+
+use super::project::{self, ProjectConfig, ProjectSettings, PROJECT_CONFIG_FILE};
+use crate::self_profile::{self, Subsystem};
+use crate::warn_println;
+use notify::RecursiveMode;
+use notify_debouncer_mini::new_debouncer;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+
+static WATCHER_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Settings that differ between two versions of the config
+#[derive(Debug, Default, PartialEq)]
+pub struct SettingsChanges {
+    /// Changes that can be applied without restarting
+    pub applied: Vec<&'static str>,
+    /// Changes that only take effect after a restart
+    pub needs_restart: Vec<&'static str>,
+}
+
+impl SettingsChanges {
+    pub fn between(current: &ProjectSettings, updated: &ProjectSettings) -> Self {
+        let mut changes = Self::default();
+
+        if current.server.headers != updated.server.headers {
+            changes.applied.push("server.headers");
+        }
+        if current.server.forward_console != updated.server.forward_console {
+            changes.applied.push("server.forward_console");
+        }
+        if current.mime != updated.mime {
+            changes.applied.push("mime");
+        }
+
+        if current.server.port != updated.server.port {
+            changes.needs_restart.push("server.port");
+        }
+        if current.server.host != updated.server.host {
+            changes.needs_restart.push("server.host");
+        }
+        if current.server.hostname != updated.server.hostname {
+            changes.needs_restart.push("server.hostname");
+        }
+        if current.server.public_url != updated.server.public_url {
+            changes.needs_restart.push("server.public_url");
+        }
+        if current.server.base_path != updated.server.base_path {
+            changes.needs_restart.push("server.base_path");
+        }
+        if current.build.optimization != updated.build.optimization {
+            changes.needs_restart.push("build.optimization");
+        }
+        if current.hooks.pre_build != updated.hooks.pre_build {
+            changes.needs_restart.push("hooks.pre_build");
+        }
+        if current.hooks.post_build != updated.hooks.post_build {
+            changes.needs_restart.push("hooks.post_build");
+        }
+        if current.server.proxy != updated.server.proxy {
+            changes.needs_restart.push("server.proxy");
+        }
+        if current.build.command != updated.build.command
+            || current.build.artifact != updated.build.artifact
+        {
+            changes.needs_restart.push("build.command");
+        }
+        if current.build.plugin != updated.build.plugin {
+            changes.needs_restart.push("build.plugin");
+        }
+        if current.watch != updated.watch {
+            changes.needs_restart.push("watch");
+        }
+        if current.env != updated.env {
+            changes.needs_restart.push("env");
+        }
+        if current.python != updated.python {
+            changes.needs_restart.push("python");
+        }
+        if current.metrics != updated.metrics {
+            changes.needs_restart.push("metrics");
+        }
+
+        changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.needs_restart.is_empty()
+    }
+}
+
+/// Take the hot-reloadable values from `updated`, keeping everything else from `current`
+pub fn apply_safe_changes(current: &ProjectSettings, updated: &ProjectSettings) -> ProjectSettings {
+    let mut next = current.clone();
+    next.server.headers = updated.server.headers.clone();
+    next.server.forward_console = updated.server.forward_console;
+    next.mime = updated.mime.clone();
+    next
+}
+
+/// Start watching the active `wasmrun.toml` in the background (at most once per process)
+pub fn spawn_config_watcher() {
+    let Some((config_path, profile)) = project::active_source() else {
+        return;
+    };
+    if WATCHER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    thread::spawn(move || {
+        let (tx, rx) = channel();
+        let mut debouncer = match new_debouncer(Duration::from_millis(300), None, tx) {
+            Ok(debouncer) => debouncer,
+            Err(e) => {
+                warn_println!("Failed to watch {PROJECT_CONFIG_FILE}: {e}");
+                return;
+            }
+        };
+
+        let watch_dir = config_path.parent().unwrap_or(Path::new("."));
+        if let Err(e) = debouncer
+            .watcher()
+            .watch(watch_dir, RecursiveMode::NonRecursive)
+        {
+            warn_println!("Failed to watch {PROJECT_CONFIG_FILE}: {e}");
+            return;
+        }
+
+        for events in rx.into_iter().flatten() {
+            let _span = self_profile::span(Subsystem::Watcher);
+            let touched = events
+                .iter()
+                .any(|event| event.path.file_name() == config_path.file_name());
+            if touched {
+                reload(&config_path, profile.as_deref());
+            }
+        }
+    });
+}
+
+fn reload(config_path: &Path, profile: Option<&str>) {
+    let updated = match ProjectConfig::load_from_file(config_path)
+        .and_then(|config| config.resolve(profile))
+    {
+        Ok(settings) => settings,
+        Err(e) => {
+            warn_println!("Ignoring {PROJECT_CONFIG_FILE} change: {e}");
+            return;
+        }
+    };
+
+    let current = project::active_settings();
+    let changes = SettingsChanges::between(&current, &updated);
+    if changes.is_empty() {
+        return;
+    }
+
+    if !changes.applied.is_empty() {
+        project::replace_active_settings(apply_safe_changes(&current, &updated));
+        println!(
+            "🔄 Reloaded {PROJECT_CONFIG_FILE}: applied {}",
+            changes.applied.join(", ")
+        );
+    }
+
+    if !changes.needs_restart.is_empty() {
+        println!(
+            "⚠️  {PROJECT_CONFIG_FILE} changed {} — restart wasmrun to apply",
+            changes.needs_restart.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_change_is_hot() {
+        let current = ProjectSettings::default();
+        let mut updated = current.clone();
+        updated
+            .server
+            .headers
+            .insert("X-Frame-Options".to_string(), "DENY".to_string());
+
+        let changes = SettingsChanges::between(&current, &updated);
+        assert_eq!(changes.applied, vec!["server.headers"]);
+        assert!(changes.needs_restart.is_empty());
+    }
+
+    #[test]
+    fn test_port_and_build_changes_need_restart() {
+        let current = ProjectSettings::default();
+        let mut updated = current.clone();
+        updated.server.port = Some(9000);
+        updated.build.optimization = Some("size".to_string());
+        updated
+            .server
+            .headers
+            .insert("X-A".to_string(), "1".to_string());
+
+        let changes = SettingsChanges::between(&current, &updated);
+        assert_eq!(
+            changes.needs_restart,
+            vec!["server.port", "build.optimization"]
+        );
+
+        let next = apply_safe_changes(&current, &updated);
+        assert_eq!(next.server.port, None);
+        assert_eq!(next.build.optimization, None);
+        assert_eq!(next.server.headers, updated.server.headers);
+    }
+}
@@ -1,14 +1,18 @@
 use std::path::Path;
 
 use crate::compiler::builder::{BuildConfig, BuilderFactory, OptimizationLevel, TargetType};
+use crate::compiler::{cargo_features, cargo_target};
 use crate::error::{Result, ServerError, WasmrunError};
 use crate::plugin::manager::PluginManager;
 use crate::utils::PluginUtils;
 use crate::utils::{ProjectAnalysis, WasmAnalysis};
 
-use crate::server::utils::{find_wasm_files, is_port_available};
+use crate::server::component;
+use crate::server::urls::ServerUrls;
+use crate::server::utils::find_wasm_files;
 use crate::server::wasm;
-use crate::server::{is_server_running, stop_existing_server, ServerUtils};
+use crate::server::{asc, emscripten, gojs, pyodide};
+use crate::server::{replace_named_instance, ServerUtils};
 
 #[derive(Debug)]
 #[allow(dead_code)] // TODO: Future server configuration system
@@ -23,7 +27,7 @@ pub struct ServerConfig {
 }
 
 pub struct ServerInfo {
-    pub url: String,
+    pub urls: ServerUrls,
     pub port: u16,
     pub server_pid: u32,
     pub watch_mode: bool,
@@ -42,7 +46,7 @@ impl ServerInfo {
         let analysis = WasmAnalysis::analyze(wasm_path)?;
 
         Ok(Self {
-            url: format!("http://localhost:{port}"),
+            urls: ServerUrls::for_port(port),
             port,
             server_pid: std::process::id(),
             watch_mode,
@@ -56,7 +60,7 @@ impl ServerInfo {
         let content_type = ContentType::Project(analysis);
 
         Ok(Self {
-            url: format!("http://localhost:{port}"),
+            urls: ServerUrls::for_port(port),
             port,
             server_pid: std::process::id(),
             watch_mode,
@@ -148,7 +152,11 @@ impl ServerInfo {
         println!(
             "\x1b[1;34m├─────────────────────────────────────────────────────────────────┤\x1b[0m"
         );
-        println!("\x1b[1;34m│\x1b[0m  🚀 \x1b[1;34mServer URL:\x1b[0m \x1b[4;36m{:<47}\x1b[0m \x1b[1;34m│\x1b[0m", self.url);
+        for url in &self.urls.urls {
+            let label = format!("{}:", url.kind.label());
+            let link = url.link(&format!("{:<47}", url.display));
+            println!("\x1b[1;34m│\x1b[0m  {} \x1b[1;34m{label:<11}\x1b[0m \x1b[4;36m{link}\x1b[0m \x1b[1;34m│\x1b[0m", url.kind.icon());
+        }
         println!("\x1b[1;34m│\x1b[0m  🔌 \x1b[1;34mPort:\x1b[0m \x1b[1;33m{:<55}\x1b[0m \x1b[1;34m│\x1b[0m", self.port);
         println!("\x1b[1;34m│\x1b[0m  ℹ️ \x1b[1;34mProcess ID:\x1b[0m \x1b[1;33m{:<47}\x1b[0m \x1b[1;34m│\x1b[0m", self.server_pid);
 
@@ -192,17 +200,15 @@ pub fn setup_project_compilation(
                 // Map plugin to language type for compatibility with existing code
                 let lang = PluginUtils::map_plugin_to_project_language(plugin, project_path);
 
-                use crate::utils::PathResolver;
-
-                // Clean up temp files from previous runs
-                if let Err(e) = PathResolver::cleanup_temp_directory("wasmrun_temp") {
-                    println!("⚠️  Warning: Failed to cleanup temporary directory: {e}");
-                }
+                use crate::utils::Workspace;
 
-                let temp_output_dir = match PathResolver::create_temp_directory("wasmrun_temp") {
+                // Start from an empty build directory in the project workspace
+                let temp_output_dir = match Workspace::for_project(project_path)
+                    .and_then(|workspace| workspace.fresh_output_dir())
+                {
                     Ok(dir) => dir,
                     Err(e) => {
-                        println!("❌ Failed to create temporary directory: {e}");
+                        println!("❌ Failed to prepare project workspace: {e}");
                         return None;
                     }
                 };
@@ -230,17 +236,15 @@ pub fn setup_project_compilation(
         crate::compiler::detect_project_language(project_path)
     };
 
-    use crate::utils::PathResolver;
+    use crate::utils::Workspace;
 
-    // Clean up any stale files from previous runs
-    if let Err(e) = PathResolver::cleanup_temp_directory("wasmrun_temp") {
-        println!("⚠️  Warning: Failed to cleanup temporary directory: {e}");
-    }
-
-    let temp_output_dir = match PathResolver::create_temp_directory("wasmrun_temp") {
+    // Start from an empty build directory in the project workspace
+    let temp_output_dir = match Workspace::for_project(project_path)
+        .and_then(|workspace| workspace.fresh_output_dir())
+    {
         Ok(dir) => dir,
         Err(e) => {
-            println!("❌ Failed to create temporary directory: {e}");
+            println!("❌ Failed to prepare project workspace: {e}");
             return None;
         }
     };
@@ -262,6 +266,8 @@ pub fn compile_project(
         verbose: false,
         watch: false,
         target_type: TargetType::Standard,
+        cargo_target: cargo_target::active(),
+        cargo_features: cargo_features::active_features(),
     };
 
     // First try plugin-based compilation
@@ -318,18 +324,12 @@ pub fn compile_project(
 }
 
 pub fn run_server(config: ServerConfig) -> Result<()> {
-    if is_server_running() {
-        match stop_existing_server() {
-            Ok(_) => println!("💀 Existing server stopped successfully."),
-            Err(e) => eprintln!("❗ Warning when stopping existing server: {e}"),
-        }
-    }
+    replace_named_instance();
 
-    if !is_port_available(config.port) {
-        return Err(WasmrunError::Server(ServerError::RequestHandlingFailed {
-            reason: format!("Port {} is already in use", config.port),
-        }));
-    }
+    let config = ServerConfig {
+        port: ServerUtils::handle_port_conflict(config.port)?,
+        ..config
+    };
 
     let path_obj = Path::new(&config.wasm_path);
     if !path_obj.exists() {
@@ -375,7 +375,7 @@ pub fn run_server(config: ServerConfig) -> Result<()> {
     }
     if path_obj
         .extension()
-        .map_or(true, |ext| ext.to_string_lossy().to_lowercase() != "wasm")
+        .is_none_or(|ext| ext.to_string_lossy().to_lowercase() != "wasm")
     {
         return Err(WasmrunError::path(format!(
             "Not a WASM file: {}",
@@ -389,13 +389,45 @@ pub fn run_server(config: ServerConfig) -> Result<()> {
         .to_string_lossy()
         .to_string();
 
-    wasm::serve_wasm_file_with_project(
-        &config.wasm_path,
-        config.port,
-        &wasm_filename,
-        config.project_path.as_deref(),
-        config.serve,
-    )
+    component::prepare(&config.wasm_path)?;
+
+    // A wasm-bindgen, Go, Emscripten, AssemblyScript or Python module needs its glue and the
+    // app page, whether or not the caller knew
+    let js_path = config
+        .js_path
+        .clone()
+        .or_else(|| wasm::glue_for(&config.wasm_path));
+    match &js_path {
+        Some(js_path) => {
+            let glue = std::fs::read_to_string(js_path).unwrap_or_default();
+            if gojs::is_wasm_exec(js_path) {
+                println!("🐹 Serving Go module with its runtime: {js_path}");
+            } else if emscripten::is_emscripten_glue(&glue) {
+                println!("🧩 Serving Emscripten module with its runtime: {js_path}");
+            } else if asc::is_assemblyscript_glue(&glue) {
+                println!("📜 Serving AssemblyScript module with its bindings: {js_path}");
+            } else if pyodide::is_pyodide_bootstrap(&glue) {
+                println!("🐍 Serving Python app with Pyodide: {js_path}");
+            } else {
+                println!("🔧 Serving wasm-bindgen module with its JS glue: {js_path}");
+            }
+            wasm::serve_wasm_bindgen_files_with_project(
+                &config.wasm_path,
+                js_path,
+                config.port,
+                &wasm_filename,
+                config.project_path.as_deref(),
+                config.serve,
+            )
+        }
+        None => wasm::serve_wasm_file_with_project(
+            &config.wasm_path,
+            config.port,
+            &wasm_filename,
+            config.project_path.as_deref(),
+            config.serve,
+        ),
+    }
     .map_err(|e| {
         WasmrunError::Server(ServerError::RequestHandlingFailed {
             reason: format!("Server startup failed: {e}"),
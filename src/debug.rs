@@ -1,31 +1,31 @@
 //! Debug logging system for Wasmrun
+//!
+//! The macros below are thin wrappers over [`crate::logging`], which decides
+//! whether and how each message is shown.
 
-use crate::config::DEBUG_ENABLED;
-use std::sync::atomic::Ordering;
+use crate::logging::{self, Level};
 
 /// Enable debug logging
 pub fn enable_debug() {
-    DEBUG_ENABLED.store(true, Ordering::Relaxed);
-    if DEBUG_ENABLED.load(Ordering::Relaxed) {
-        eprintln!("🔍 \x1b[36mDEBUG\x1b[0m [debug.rs:11] Debug mode enabled for Wasmrun session");
+    if logging::level() < Level::Debug {
+        logging::set_level(Level::Debug);
     }
+    crate::debug_println!("Debug mode enabled for Wasmrun session");
 }
 
-/// Check if debug logging is enabled
-pub fn is_debug_enabled() -> bool {
-    DEBUG_ENABLED.load(Ordering::Relaxed)
+/// Log at an explicit level from the calling module
+#[macro_export]
+macro_rules! log_at {
+    ($level:expr, $($arg:tt)*) => {
+        $crate::logging::emit($level, module_path!(), (file!(), line!()), format_args!($($arg)*))
+    };
 }
 
 /// Debug print macro - only prints if debug is enabled
 #[macro_export]
 macro_rules! debug_println {
     ($($arg:tt)*) => {
-        if $crate::debug::is_debug_enabled() {
-            eprintln!("🔍 \x1b[36mDEBUG\x1b[0m [{}:{}] {}",
-                file!().split('/').last().unwrap_or("unknown"),
-                line!(),
-                format_args!($($arg)*));
-        }
+        $crate::log_at!($crate::logging::Level::Debug, $($arg)*)
     };
 }
 
@@ -33,12 +33,7 @@ macro_rules! debug_println {
 #[macro_export]
 macro_rules! trace_println {
     ($($arg:tt)*) => {
-        if $crate::debug::is_debug_enabled() {
-            eprintln!("🔬 \x1b[90mTRACE\x1b[0m [{}:{}] {}",
-                file!().split('/').last().unwrap_or("unknown"),
-                line!(),
-                format_args!($($arg)*));
-        }
+        $crate::log_at!($crate::logging::Level::Trace, $($arg)*)
     };
 }
 
@@ -46,21 +41,15 @@ macro_rules! trace_println {
 #[macro_export]
 macro_rules! debug_enter {
     ($func_name:expr) => {
-        if $crate::debug::is_debug_enabled() {
-            eprintln!("🚪 \x1b[32mENTER\x1b[0m [{}:{}] {}",
-                file!().split('/').last().unwrap_or("unknown"),
-                line!(),
-                $func_name);
-        }
+        $crate::log_at!($crate::logging::Level::Debug, "🚪 ENTER {}", $func_name)
     };
     ($func_name:expr, $($arg:tt)*) => {
-        if $crate::debug::is_debug_enabled() {
-            eprintln!("🚪 \x1b[32mENTER\x1b[0m [{}:{}] {} - {}",
-                file!().split('/').last().unwrap_or("unknown"),
-                line!(),
-                $func_name,
-                format_args!($($arg)*));
-        }
+        $crate::log_at!(
+            $crate::logging::Level::Debug,
+            "🚪 ENTER {} - {}",
+            $func_name,
+            format_args!($($arg)*)
+        )
     };
 }
 
@@ -68,25 +57,15 @@ macro_rules! debug_enter {
 #[macro_export]
 macro_rules! debug_exit {
     ($func_name:expr) => {
-        if $crate::debug::is_debug_enabled() {
-            eprintln!(
-                "🚶 \x1b[33mEXIT\x1b[0m  [{}:{}] {}",
-                file!().split('/').last().unwrap_or("unknown"),
-                line!(),
-                $func_name
-            );
-        }
+        $crate::log_at!($crate::logging::Level::Debug, "🚶 EXIT {}", $func_name)
     };
     ($func_name:expr, $result:expr) => {
-        if $crate::debug::is_debug_enabled() {
-            eprintln!(
-                "🚶 \x1b[33mEXIT\x1b[0m  [{}:{}] {} -> {:?}",
-                file!().split('/').last().unwrap_or("unknown"),
-                line!(),
-                $func_name,
-                $result
-            );
-        }
+        $crate::log_at!(
+            $crate::logging::Level::Debug,
+            "🚶 EXIT {} -> {:?}",
+            $func_name,
+            $result
+        )
     };
 }
 
@@ -96,32 +75,39 @@ macro_rules! debug_time {
     ($name:expr, $block:block) => {{
         let start = std::time::Instant::now();
         let result = $block;
-        if $crate::debug::is_debug_enabled() {
-            eprintln!(
-                "⏱️  \x1b[35mTIME\x1b[0m  [{}:{}] {} took {:?}",
-                file!().split('/').last().unwrap_or("unknown"),
-                line!(),
-                $name,
-                start.elapsed()
-            );
-        }
+        $crate::log_at!(
+            $crate::logging::Level::Debug,
+            "⏱️  {} took {:?}",
+            $name,
+            start.elapsed()
+        );
         result
     }};
 }
 
-/// Info print - always shown to users
+/// Info print - shown unless `-q` is given
 #[macro_export]
 macro_rules! info_println {
     ($($arg:tt)*) => {
-        println!("{}", format_args!($($arg)*));
+        $crate::log_at!($crate::logging::Level::Info, $($arg)*)
+    };
+}
+
+/// Print about a request being served - dropped by `-q` and `--log-requests=off`
+#[macro_export]
+macro_rules! request_println {
+    ($($arg:tt)*) => {
+        if $crate::server::access_log::prints_requests() {
+            $crate::info_println!($($arg)*)
+        }
     };
 }
 
-/// Success print - always shown to users
+/// Success print - shown unless `-q` is given
 #[macro_export]
 macro_rules! success_println {
     ($($arg:tt)*) => {
-        println!("✅ {}", format_args!($($arg)*));
+        $crate::log_at!($crate::logging::Level::Info, "✅ {}", format_args!($($arg)*))
     };
 }
 
@@ -129,7 +115,7 @@ macro_rules! success_println {
 #[macro_export]
 macro_rules! warn_println {
     ($($arg:tt)*) => {
-        eprintln!("⚠️  {}", format_args!($($arg)*));
+        $crate::log_at!($crate::logging::Level::Warn, "⚠️  {}", format_args!($($arg)*))
     };
 }
 
@@ -137,6 +123,6 @@ macro_rules! warn_println {
 #[macro_export]
 macro_rules! error_println {
     ($($arg:tt)*) => {
-        eprintln!("❌ {}", format_args!($($arg)*));
+        $crate::log_at!($crate::logging::Level::Error, "❌ {}", format_args!($($arg)*))
     };
 }
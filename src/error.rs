@@ -43,6 +43,10 @@ pub enum WasmrunError {
     #[error(transparent)]
     Config(#[from] ConfigError),
 
+    /// Local state registry errors
+    #[error("Registry error: {0}")]
+    Registry(#[from] rusqlite::Error),
+
     /// Language detection errors
     #[error("Language detection failed: {message}")]
     #[allow(dead_code)] // TODO: Use for advanced language detection
@@ -52,6 +56,34 @@ pub enum WasmrunError {
     #[error("Missing required tools: {tools:?}")]
     MissingTools { tools: Vec<String> },
 
+    /// Plugin is not installed
+    #[error("Plugin '{name}' not found")]
+    PluginNotFound { name: String },
+
+    /// Network access attempted in offline mode
+    #[error(
+        "{action} needs network access, but offline mode is enabled (--offline or WASMRUN_OFFLINE)"
+    )]
+    NetworkDisabled { action: String },
+
+    /// A request failed for good, after its retries
+    #[error("Request to {url} failed: {reason}")]
+    Network { url: String, reason: String },
+
+    /// A download doesn't match its published SHA-256 digest
+    #[error("Checksum mismatch for {url}: expected SHA-256 {expected}, got {actual}")]
+    ChecksumMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// CI report found budget violations, invalid artifacts or failing tests,
+    /// an accessibility or security audit found violations, or a self-test
+    /// example failed
+    #[error("{count} check(s) failed")]
+    ChecksFailed { count: usize },
+
     /// Generic error with context
     #[error("{context}: {source}")]
     WithContext {
@@ -75,6 +107,14 @@ pub enum WasmError {
     /// wasm-bindgen detection
     #[error("wasm-bindgen module detected but JavaScript file not found")]
     WasmBindgenJsNotFound,
+
+    /// The module trapped while running
+    #[error("WASM module trapped: {reason}")]
+    Trap { reason: String },
+
+    /// A component was served without a browser-loadable transpilation
+    #[error("{path} is a WebAssembly component, which browsers can't instantiate. Transpile it next to the component with `npx @bytecodealliance/jco transpile {path} -o <dir>`, or run a wasi:cli command with `wasmrun exec`")]
+    ComponentNotTranspiled { path: String },
 }
 
 /// Compilation-related errors
@@ -127,6 +167,10 @@ pub enum ServerError {
     #[error("Failed to start server on port {port}: {reason}")]
     StartupFailed { port: u16, reason: String },
 
+    /// Port is taken by another process
+    #[error("Port {port} is already in use")]
+    PortInUse { port: u16 },
+
     /// Request handling failed
     #[error("Failed to handle request: {reason}")]
     RequestHandlingFailed { reason: String },
@@ -168,6 +212,198 @@ pub enum ConfigError {
     FileNotFound { path: String },
 }
 
+/// Failure classes; each maps to a stable process exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    General = 1,
+    Usage = 2,
+    NotFound = 3,
+    InvalidModule = 4,
+    BuildFailed = 5,
+    MissingTools = 6,
+    PortInUse = 7,
+    Server = 8,
+    PluginNotFound = 9,
+    Config = 10,
+    Io = 11,
+    ChecksFailed = 12,
+}
+
+impl ErrorClass {
+    pub fn exit_code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// Stable error code, printed as `E####` next to error messages.
+/// Codes are never renumbered or reused; retired codes stay in [`ErrorCode::ALL`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCode {
+    pub number: u16,
+    pub class: ErrorClass,
+    pub summary: &'static str,
+}
+
+impl ErrorCode {
+    const fn new(number: u16, class: ErrorClass, summary: &'static str) -> Self {
+        Self {
+            number,
+            class,
+            summary,
+        }
+    }
+
+    pub const UNCLASSIFIED: Self = Self::new(1, ErrorClass::General, "Unclassified failure");
+    pub const IO: Self = Self::new(2, ErrorClass::Io, "I/O operation failed");
+    pub const REGISTRY: Self = Self::new(3, ErrorClass::Io, "Local state registry failed");
+    pub const NETWORK_DISABLED: Self = Self::new(
+        4,
+        ErrorClass::Usage,
+        "Network access needed in offline mode",
+    );
+    pub const NETWORK: Self = Self::new(5, ErrorClass::Io, "Network request failed");
+    pub const CHECKSUM_MISMATCH: Self = Self::new(
+        6,
+        ErrorClass::Io,
+        "Download doesn't match its SHA-256 checksum",
+    );
+
+    pub const PATH: Self = Self::new(100, ErrorClass::NotFound, "Invalid or unusable path");
+    pub const FILE_NOT_FOUND: Self = Self::new(101, ErrorClass::NotFound, "File not found");
+    pub const DIRECTORY_NOT_FOUND: Self =
+        Self::new(102, ErrorClass::NotFound, "Directory not found");
+
+    pub const INVALID_FILE_FORMAT: Self =
+        Self::new(200, ErrorClass::InvalidModule, "Unexpected file format");
+    pub const INVALID_MAGIC_BYTES: Self = Self::new(
+        201,
+        ErrorClass::InvalidModule,
+        "File is not a WebAssembly module",
+    );
+    pub const VALIDATION_FAILED: Self =
+        Self::new(202, ErrorClass::InvalidModule, "WASM module is invalid");
+    pub const WASM_BINDGEN_JS_NOT_FOUND: Self = Self::new(
+        203,
+        ErrorClass::InvalidModule,
+        "wasm-bindgen module without its JavaScript glue",
+    );
+    pub const WASM_TRAP: Self = Self::new(204, ErrorClass::General, "WASM module trapped");
+    pub const COMPONENT_NOT_TRANSPILED: Self = Self::new(
+        205,
+        ErrorClass::InvalidModule,
+        "Component served without a jco transpilation",
+    );
+
+    pub const UNSUPPORTED_LANGUAGE: Self =
+        Self::new(300, ErrorClass::BuildFailed, "Language not supported");
+    pub const BUILD_TOOL_NOT_FOUND: Self =
+        Self::new(301, ErrorClass::MissingTools, "Build tool not installed");
+    pub const BUILD_FAILED: Self = Self::new(302, ErrorClass::BuildFailed, "Build failed");
+    pub const TOOL_EXECUTION_FAILED: Self =
+        Self::new(303, ErrorClass::BuildFailed, "Build tool could not be run");
+    pub const INVALID_PROJECT_STRUCTURE: Self =
+        Self::new(304, ErrorClass::BuildFailed, "Invalid project structure");
+    pub const MISSING_ENTRY_FILE: Self =
+        Self::new(305, ErrorClass::BuildFailed, "No entry file found");
+    pub const OUTPUT_DIRECTORY: Self = Self::new(
+        306,
+        ErrorClass::BuildFailed,
+        "Output directory could not be created",
+    );
+    pub const INVALID_OPTIMIZATION_LEVEL: Self =
+        Self::new(307, ErrorClass::Usage, "Invalid optimization level");
+    pub const LANGUAGE_DETECTION: Self = Self::new(
+        308,
+        ErrorClass::BuildFailed,
+        "Project language not detected",
+    );
+    pub const MISSING_TOOLS: Self = Self::new(
+        309,
+        ErrorClass::MissingTools,
+        "Required tools not installed",
+    );
+
+    pub const SERVER_STARTUP: Self = Self::new(400, ErrorClass::Server, "Server failed to start");
+    pub const PORT_IN_USE: Self = Self::new(401, ErrorClass::PortInUse, "Port already in use");
+    pub const REQUEST_HANDLING: Self =
+        Self::new(402, ErrorClass::Server, "Request handling failed");
+    pub const SERVER_NOT_RUNNING: Self = Self::new(403, ErrorClass::Server, "No server is running");
+    pub const SERVER_STOP_FAILED: Self =
+        Self::new(404, ErrorClass::Server, "Server could not be stopped");
+
+    pub const INVALID_ARGUMENTS: Self =
+        Self::new(500, ErrorClass::Usage, "Invalid command arguments");
+
+    pub const CONFIG_INVALID_VALUE: Self =
+        Self::new(600, ErrorClass::Config, "Invalid configuration value");
+    pub const CONFIG_MISSING: Self =
+        Self::new(601, ErrorClass::Config, "Missing required configuration");
+    pub const CONFIG_PARSE: Self =
+        Self::new(602, ErrorClass::Config, "Configuration could not be parsed");
+    pub const CONFIG_NOT_FOUND: Self =
+        Self::new(603, ErrorClass::Config, "Configuration file not found");
+
+    pub const PLUGIN_NOT_FOUND: Self =
+        Self::new(700, ErrorClass::PluginNotFound, "Plugin not installed");
+
+    pub const CHECKS_FAILED: Self = Self::new(
+        800,
+        ErrorClass::ChecksFailed,
+        "Checks failed (size budget, invalid artifact, failing tests, accessibility violations, security audit or self-test)",
+    );
+
+    /// Every error code, in numeric order
+    pub const ALL: &'static [ErrorCode] = &[
+        Self::UNCLASSIFIED,
+        Self::IO,
+        Self::REGISTRY,
+        Self::NETWORK_DISABLED,
+        Self::NETWORK,
+        Self::CHECKSUM_MISMATCH,
+        Self::PATH,
+        Self::FILE_NOT_FOUND,
+        Self::DIRECTORY_NOT_FOUND,
+        Self::INVALID_FILE_FORMAT,
+        Self::INVALID_MAGIC_BYTES,
+        Self::VALIDATION_FAILED,
+        Self::WASM_BINDGEN_JS_NOT_FOUND,
+        Self::WASM_TRAP,
+        Self::COMPONENT_NOT_TRANSPILED,
+        Self::UNSUPPORTED_LANGUAGE,
+        Self::BUILD_TOOL_NOT_FOUND,
+        Self::BUILD_FAILED,
+        Self::TOOL_EXECUTION_FAILED,
+        Self::INVALID_PROJECT_STRUCTURE,
+        Self::MISSING_ENTRY_FILE,
+        Self::OUTPUT_DIRECTORY,
+        Self::INVALID_OPTIMIZATION_LEVEL,
+        Self::LANGUAGE_DETECTION,
+        Self::MISSING_TOOLS,
+        Self::SERVER_STARTUP,
+        Self::PORT_IN_USE,
+        Self::REQUEST_HANDLING,
+        Self::SERVER_NOT_RUNNING,
+        Self::SERVER_STOP_FAILED,
+        Self::INVALID_ARGUMENTS,
+        Self::CONFIG_INVALID_VALUE,
+        Self::CONFIG_MISSING,
+        Self::CONFIG_PARSE,
+        Self::CONFIG_NOT_FOUND,
+        Self::PLUGIN_NOT_FOUND,
+        Self::CHECKS_FAILED,
+    ];
+
+    pub fn exit_code(self) -> i32 {
+        self.class.exit_code()
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "E{:04}", self.number)
+    }
+}
+
 /// Result type alias for Wasmrun operations
 pub type Result<T> = std::result::Result<T, WasmrunError>;
 
@@ -224,6 +460,46 @@ impl WasmrunError {
         }
     }
 
+    /// Stable code identifying this failure
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            WasmrunError::Io(_) => ErrorCode::IO,
+            WasmrunError::Path { .. } => ErrorCode::PATH,
+            WasmrunError::FileNotFound { .. } => ErrorCode::FILE_NOT_FOUND,
+            WasmrunError::DirectoryNotFound { .. } => ErrorCode::DIRECTORY_NOT_FOUND,
+            WasmrunError::InvalidFileFormat { .. } => ErrorCode::INVALID_FILE_FORMAT,
+            WasmrunError::Wasm(error) => error.code(),
+            WasmrunError::Compilation(error) => error.code(),
+            WasmrunError::Server(error) => error.code(),
+            WasmrunError::Command(CommandError::InvalidArguments { .. }) => {
+                ErrorCode::INVALID_ARGUMENTS
+            }
+            WasmrunError::Config(error) => error.code(),
+            WasmrunError::Registry(_) => ErrorCode::REGISTRY,
+            WasmrunError::LanguageDetection { .. } => ErrorCode::LANGUAGE_DETECTION,
+            WasmrunError::MissingTools { .. } => ErrorCode::MISSING_TOOLS,
+            WasmrunError::PluginNotFound { .. } => ErrorCode::PLUGIN_NOT_FOUND,
+            WasmrunError::NetworkDisabled { .. } => ErrorCode::NETWORK_DISABLED,
+            WasmrunError::Network { .. } => ErrorCode::NETWORK,
+            WasmrunError::ChecksumMismatch { .. } => ErrorCode::CHECKSUM_MISMATCH,
+            WasmrunError::ChecksFailed { .. } => ErrorCode::CHECKS_FAILED,
+            WasmrunError::WithContext { source, .. } => {
+                if let Some(error) = source.downcast_ref::<WasmrunError>() {
+                    error.code()
+                } else if source.is::<std::io::Error>() {
+                    ErrorCode::IO
+                } else {
+                    ErrorCode::UNCLASSIFIED
+                }
+            }
+        }
+    }
+
+    /// Process exit code for this failure
+    pub fn exit_code(&self) -> i32 {
+        self.code().exit_code()
+    }
+
     /// Check if this error is recoverable
     #[allow(dead_code)] // TODO: Future error recovery features
     pub fn is_recoverable(&self) -> bool {
@@ -281,6 +557,16 @@ impl WasmrunError {
 }
 
 impl WasmError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            WasmError::InvalidMagicBytes { .. } => ErrorCode::INVALID_MAGIC_BYTES,
+            WasmError::ValidationFailed { .. } => ErrorCode::VALIDATION_FAILED,
+            WasmError::WasmBindgenJsNotFound => ErrorCode::WASM_BINDGEN_JS_NOT_FOUND,
+            WasmError::Trap { .. } => ErrorCode::WASM_TRAP,
+            WasmError::ComponentNotTranspiled { .. } => ErrorCode::COMPONENT_NOT_TRANSPILED,
+        }
+    }
+
     /// new validation failed error
     pub fn validation_failed(reason: impl Into<String>) -> Self {
         Self::ValidationFailed {
@@ -290,6 +576,23 @@ impl WasmError {
 }
 
 impl CompilationError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            CompilationError::UnsupportedLanguage { .. } => ErrorCode::UNSUPPORTED_LANGUAGE,
+            CompilationError::BuildToolNotFound { .. } => ErrorCode::BUILD_TOOL_NOT_FOUND,
+            CompilationError::BuildFailed { .. } => ErrorCode::BUILD_FAILED,
+            CompilationError::ToolExecutionFailed { .. } => ErrorCode::TOOL_EXECUTION_FAILED,
+            CompilationError::InvalidProjectStructure { .. } => {
+                ErrorCode::INVALID_PROJECT_STRUCTURE
+            }
+            CompilationError::MissingEntryFile { .. } => ErrorCode::MISSING_ENTRY_FILE,
+            CompilationError::OutputDirectoryCreationFailed { .. } => ErrorCode::OUTPUT_DIRECTORY,
+            CompilationError::InvalidOptimizationLevel { .. } => {
+                ErrorCode::INVALID_OPTIMIZATION_LEVEL
+            }
+        }
+    }
+
     /// new build failed error
     #[allow(dead_code)] // TODO: Use for compilation error handling
     pub fn build_failed(language: impl Into<String>, reason: impl Into<String>) -> Self {
@@ -301,6 +604,16 @@ impl CompilationError {
 }
 
 impl ServerError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ServerError::StartupFailed { .. } => ErrorCode::SERVER_STARTUP,
+            ServerError::PortInUse { .. } => ErrorCode::PORT_IN_USE,
+            ServerError::RequestHandlingFailed { .. } => ErrorCode::REQUEST_HANDLING,
+            ServerError::NotRunning => ErrorCode::SERVER_NOT_RUNNING,
+            ServerError::StopFailed { .. } => ErrorCode::SERVER_STOP_FAILED,
+        }
+    }
+
     /// new startup failed error
     pub fn startup_failed(port: u16, reason: impl Into<String>) -> Self {
         Self::StartupFailed {
@@ -310,6 +623,17 @@ impl ServerError {
     }
 }
 
+impl ConfigError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ConfigError::InvalidValue { .. } => ErrorCode::CONFIG_INVALID_VALUE,
+            ConfigError::MissingRequired { .. } => ErrorCode::CONFIG_MISSING,
+            ConfigError::ParseError { .. } => ErrorCode::CONFIG_PARSE,
+            ConfigError::FileNotFound { .. } => ErrorCode::CONFIG_NOT_FOUND,
+        }
+    }
+}
+
 impl CommandError {
     /// new invalid arguments error
     pub fn invalid_arguments(message: impl Into<String>) -> Self {
@@ -460,6 +784,58 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_error_codes_are_unique_and_ordered() {
+        for pair in ErrorCode::ALL.windows(2) {
+            assert!(
+                pair[0].number < pair[1].number,
+                "{} must come before {}",
+                pair[0],
+                pair[1]
+            );
+        }
+        assert_eq!(ErrorCode::BUILD_FAILED.to_string(), "E0302");
+    }
+
+    #[test]
+    fn test_error_exit_codes() {
+        let port_in_use = WasmrunError::Server(ServerError::PortInUse { port: 8420 });
+        assert_eq!(port_in_use.code(), ErrorCode::PORT_IN_USE);
+        assert_eq!(port_in_use.exit_code(), 7);
+
+        let build_failed = WasmrunError::Compilation(CompilationError::build_failed("Go", "x"));
+        assert_eq!(
+            build_failed.exit_code(),
+            ErrorClass::BuildFailed.exit_code()
+        );
+
+        let plugin = WasmrunError::PluginNotFound {
+            name: "wasmgo".to_string(),
+        };
+        assert_eq!(plugin.exit_code(), 9);
+
+        let offline = WasmrunError::NetworkDisabled {
+            action: "Installing plugin 'wasmgo'".to_string(),
+        };
+        assert_eq!(offline.code(), ErrorCode::NETWORK_DISABLED);
+        assert_eq!(offline.exit_code(), 2);
+
+        let checks = WasmrunError::ChecksFailed { count: 2 };
+        assert_eq!(checks.exit_code(), 12);
+    }
+
+    #[test]
+    fn test_error_code_through_context() {
+        let wrapped = WasmrunError::add_context(
+            "Running project",
+            WasmrunError::Wasm(WasmError::validation_failed("bad section")),
+        );
+        assert_eq!(wrapped.code(), ErrorCode::VALIDATION_FAILED);
+
+        let io = WasmrunError::add_context("Reading file", std::io::Error::other("boom"));
+        assert_eq!(io.code(), ErrorCode::IO);
+    }
+
     #[test]
     fn test_command_error_invalid_arguments() {
         let error = CommandError::invalid_arguments("missing file path");
@@ -0,0 +1,238 @@
+//! Local IPC between wasmrun processes
+//!
+//! A running server listens on an endpoint named after its process ID in the
+//! `ipc` directory under [`WasmrunConfig::runtime_dir`], a Unix domain socket
+//! on Unix and a loopback TCP port (written to the endpoint file) elsewhere. Messages are single lines of
+//! JSON; every request gets one reply line of the form
+//! `{"ok": true, "data": ...}` or `{"ok": false, "error": "..."}`.
+
+use crate::config::WasmrunConfig;
+use crate::error::{Result, ServerError, WasmrunError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+
+/// Directory under the runtime dir that holds the endpoints
+const IPC_DIR: &str = "ipc";
+
+#[derive(Serialize, Deserialize)]
+struct Reply<T> {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Where a process accepts IPC connections
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    path: PathBuf,
+}
+
+/// Removes the endpoint when the listening process shuts down
+pub struct Listener {
+    path: PathBuf,
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl Endpoint {
+    /// The endpoint of the wasmrun process `pid`
+    pub fn for_process(pid: u32) -> Result<Self> {
+        let dir = WasmrunConfig::runtime_dir()?.join(IPC_DIR);
+        Ok(Self::at(dir.join(format!("{pid}.sock"))))
+    }
+
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Answer requests on a background thread until the returned listener is dropped
+    pub fn listen<Req, Resp, F>(&self, handler: F) -> Result<Listener>
+    where
+        Req: DeserializeOwned,
+        Resp: Serialize,
+        F: Fn(Req) -> std::result::Result<Resp, String> + Send + 'static,
+    {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                WasmrunError::add_context(format!("Creating IPC directory {}", dir.display()), e)
+            })?;
+        }
+        // A previous process with the same ID may have left its endpoint behind
+        let _ = std::fs::remove_file(&self.path);
+
+        platform::listen(&self.path, move |stream| {
+            if let Err(e) = answer(stream, &handler) {
+                crate::debug_println!("IPC connection failed: {e}");
+            }
+        })
+        .map_err(|e| {
+            WasmrunError::add_context(format!("Listening on {}", self.path.display()), e)
+        })?;
+
+        Ok(Listener {
+            path: self.path.clone(),
+        })
+    }
+
+    /// Send one request and wait for the reply
+    pub fn request<Req, Resp>(&self, message: &Req) -> Result<Resp>
+    where
+        Req: Serialize,
+        Resp: DeserializeOwned,
+    {
+        let failed =
+            |reason: String| WasmrunError::Server(ServerError::RequestHandlingFailed { reason });
+
+        let mut stream = platform::connect(&self.path)
+            .map_err(|e| failed(format!("Could not connect to {}: {e}", self.path.display())))?;
+        let mut line = serde_json::to_string(message)
+            .map_err(|e| WasmrunError::add_context("Encoding IPC request", e))?;
+        line.push('\n');
+        stream.write_all(line.as_bytes())?;
+        stream.flush()?;
+
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply)?;
+        if reply.trim().is_empty() {
+            return Err(failed("The server closed the connection".to_string()));
+        }
+        let reply: Reply<Resp> = serde_json::from_str(&reply)
+            .map_err(|e| WasmrunError::add_context("Decoding IPC reply", e))?;
+        match (reply.ok, reply.data) {
+            (true, Some(data)) => Ok(data),
+            _ => Err(failed(
+                reply.error.unwrap_or_else(|| "Empty reply".to_string()),
+            )),
+        }
+    }
+}
+
+fn answer<S, Req, Resp, F>(stream: S, handler: &F) -> std::io::Result<()>
+where
+    S: Read + Write,
+    Req: DeserializeOwned,
+    Resp: Serialize,
+    F: Fn(Req) -> std::result::Result<Resp, String>,
+{
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        let reply = match serde_json::from_str::<Req>(&line) {
+            Ok(request) => match handler(request) {
+                Ok(data) => Reply {
+                    ok: true,
+                    data: Some(data),
+                    error: None,
+                },
+                Err(error) => Reply {
+                    ok: false,
+                    data: None,
+                    error: Some(error),
+                },
+            },
+            Err(e) => Reply {
+                ok: false,
+                data: None,
+                error: Some(format!("Invalid request: {e}")),
+            },
+        };
+        let mut out = serde_json::to_string(&reply).map_err(std::io::Error::other)?;
+        out.push('\n');
+        let stream = reader.get_mut();
+        stream.write_all(out.as_bytes())?;
+        stream.flush()?;
+        line.clear();
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+
+    pub fn listen(
+        path: &Path,
+        on_connection: impl Fn(UnixStream) + Send + 'static,
+    ) -> std::io::Result<()> {
+        let listener = UnixListener::bind(path)?;
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                on_connection(stream);
+            }
+        });
+        Ok(())
+    }
+
+    pub fn connect(path: &Path) -> std::io::Result<UnixStream> {
+        UnixStream::connect(path)
+    }
+}
+
+#[cfg(not(unix))]
+mod platform {
+    use std::net::{Ipv4Addr, TcpListener, TcpStream};
+    use std::path::Path;
+
+    pub fn listen(
+        path: &Path,
+        on_connection: impl Fn(TcpStream) + Send + 'static,
+    ) -> std::io::Result<()> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        crate::utils::write_atomic(path, listener.local_addr()?.port().to_string().as_bytes())?;
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                on_connection(stream);
+            }
+        });
+        Ok(())
+    }
+
+    pub fn connect(path: &Path) -> std::io::Result<TcpStream> {
+        let port: u16 = std::fs::read_to_string(path)?
+            .trim()
+            .parse()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_request_reply_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.sock");
+        let endpoint = Endpoint::at(&path);
+        let listener = endpoint
+            .listen(|n: i64| {
+                if n < 0 {
+                    Err("negative".to_string())
+                } else {
+                    Ok(n * 2)
+                }
+            })
+            .unwrap();
+
+        assert_eq!(endpoint.request::<_, i64>(&21).unwrap(), 42);
+        let error = endpoint.request::<_, i64>(&-1).unwrap_err();
+        assert!(error.to_string().contains("negative"));
+        let error = endpoint.request::<_, i64>(&"text").unwrap_err();
+        assert!(error.to_string().contains("Invalid request"));
+
+        drop(listener);
+        assert!(!path.exists());
+        assert!(endpoint.request::<_, i64>(&1).is_err());
+    }
+}
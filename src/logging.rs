@@ -0,0 +1,255 @@
+//! Central logging for Wasmrun
+//!
+//! Status messages go through [`emit`] with a level and a target, the module
+//! that logged them without the `wasmrun::` prefix (e.g. `server::handler`).
+//! By default info, warnings and errors are shown; `-q` keeps only warnings
+//! and errors, `-v` adds debug and `-vv` trace messages. `WASMRUN_LOG` sets
+//! levels per target, e.g. `WASMRUN_LOG=server=debug,commands::plugin=error`.
+//!
+//! With `--log-format json` every message is written to stderr as one JSON
+//! object per line, so stdout only carries command output such as tables.
+
+use clap::ValueEnum;
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::RwLock;
+
+/// Per-target levels, read at startup
+pub const LOG_ENV: &str = "WASMRUN_LOG";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Level::Error,
+            2 => Level::Warn,
+            3 => Level::Info,
+            4 => Level::Debug,
+            _ => Level::Trace,
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "error" => Some(Level::Error),
+            "warn" | "warning" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+}
+
+/// How log messages are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines, as Wasmrun has always printed them
+    #[default]
+    Text,
+    /// One JSON object per line on stderr
+    Json,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+static JSON: AtomicBool = AtomicBool::new(false);
+static TARGETS: RwLock<Vec<(String, Level)>> = RwLock::new(Vec::new());
+
+/// Configure logging from the command line; `verbosity` counts `-v` flags
+pub fn init(verbosity: u8, quiet: bool, format: LogFormat) {
+    JSON.store(format == LogFormat::Json, Ordering::Relaxed);
+
+    let (default, targets) = match std::env::var(LOG_ENV) {
+        Ok(spec) => parse_spec(&spec),
+        Err(_) => (None, Vec::new()),
+    };
+    let level = match (quiet, verbosity) {
+        (true, _) => Level::Warn,
+        (false, 0) => default.unwrap_or(Level::Info),
+        (false, 1) => Level::Debug,
+        (false, _) => Level::Trace,
+    };
+    set_level(level);
+    if let Ok(mut active) = TARGETS.write() {
+        *active = targets;
+    }
+}
+
+/// Raise or lower the level for every target without an explicit one
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// The level used for targets without an explicit one
+pub fn level() -> Level {
+    Level::from_u8(LEVEL.load(Ordering::Relaxed))
+}
+
+/// Whether messages are written as JSON
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Whether a message at `level` from `module` (a `module_path!()`) would be shown
+pub fn enabled(level: Level, module: &str) -> bool {
+    level <= target_level(target(module))
+}
+
+/// Write a message if its level is enabled for `module`
+pub fn emit(level: Level, module: &str, location: (&str, u32), args: fmt::Arguments) {
+    if !enabled(level, module) {
+        return;
+    }
+    let target = target(module);
+
+    if is_json() {
+        let line = serde_json::json!({
+            "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "level": level.as_str(),
+            "target": target,
+            "message": plain_message(&args.to_string()),
+        });
+        let _ = writeln!(std::io::stderr().lock(), "{line}");
+        return;
+    }
+
+    let file = location.0.rsplit(['/', '\\']).next().unwrap_or("unknown");
+    match level {
+        Level::Info => println!("{args}"),
+        Level::Error | Level::Warn => eprintln!("{args}"),
+        Level::Debug => eprintln!("🔍 \x1b[36mDEBUG\x1b[0m [{file}:{}] {args}", location.1),
+        Level::Trace => eprintln!("🔬 \x1b[90mTRACE\x1b[0m [{file}:{}] {args}", location.1),
+    }
+}
+
+/// Module path relative to the crate, used to select per-target levels
+fn target(module: &str) -> &str {
+    match module {
+        "wasmrun" => "main",
+        _ => module.strip_prefix("wasmrun::").unwrap_or(module),
+    }
+}
+
+/// The most specific configured level for `target`, else the default level
+fn target_level(target: &str) -> Level {
+    let targets = match TARGETS.read() {
+        Ok(targets) => targets,
+        Err(_) => return level(),
+    };
+    targets
+        .iter()
+        .filter(|(prefix, _)| {
+            target == prefix
+                || target
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.starts_with("::"))
+        })
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map_or_else(level, |(_, level)| *level)
+}
+
+/// `level` and `target=level` entries separated by commas; invalid entries are reported and skipped
+fn parse_spec(spec: &str) -> (Option<Level>, Vec<(String, Level)>) {
+    let mut default = None;
+    let mut targets = Vec::new();
+    for entry in spec
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let parsed = match entry.split_once('=') {
+            Some((target, level)) => Level::parse(level).map(|level| {
+                let target = target.trim().trim_start_matches("wasmrun::");
+                targets.push((target.to_string(), level));
+            }),
+            None => Level::parse(entry).map(|level| default = Some(level)),
+        };
+        if parsed.is_none() {
+            eprintln!("⚠️  Ignoring invalid {LOG_ENV} entry '{entry}'");
+        }
+    }
+    (default, targets)
+}
+
+/// The message without colour codes and the decorative symbols that start text lines
+fn plain_message(message: &str) -> String {
+    let mut plain = String::with_capacity(message.len());
+    let mut chars = message.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip a CSI sequence such as \x1b[36m
+            if chars.next_if_eq(&'[').is_some() {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        plain.push(c);
+    }
+    plain
+        .trim_start_matches(|c: char| !c.is_ascii() || c.is_whitespace())
+        .trim_end()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec_and_targets() {
+        let (default, targets) =
+            parse_spec("warn, server=debug,wasmrun::server::handler=trace,plugin=loud");
+        assert_eq!(default, Some(Level::Warn));
+        assert_eq!(
+            targets,
+            vec![
+                ("server".to_string(), Level::Debug),
+                ("server::handler".to_string(), Level::Trace),
+            ]
+        );
+
+        assert_eq!(target("wasmrun::server::handler"), "server::handler");
+        assert_eq!(target("wasmrun"), "main");
+        *TARGETS.write().unwrap() = targets;
+        assert_eq!(target_level("server::handler"), Level::Trace);
+        assert_eq!(target_level("server::runner"), Level::Debug);
+        assert_eq!(target_level("serverless"), level());
+        TARGETS.write().unwrap().clear();
+    }
+
+    #[test]
+    fn test_plain_message() {
+        assert_eq!(
+            plain_message("🔄 Installing plugin: wasmgo"),
+            "Installing plugin: wasmgo"
+        );
+        assert_eq!(
+            plain_message("⚠️  \x1b[1;33mPort 8420\x1b[0m is busy\n"),
+            "Port 8420 is busy"
+        );
+        assert_eq!(plain_message("[E0100] Not found"), "[E0100] Not found");
+    }
+}
@@ -4,10 +4,18 @@ mod compiler;
 mod config;
 mod debug;
 mod error;
+mod ipc;
+mod logging;
+mod network;
+mod offline;
 mod plugin;
+mod process;
+mod registry;
 mod runtime;
+mod self_profile;
 mod server;
 mod template;
+mod tui;
 mod ui;
 mod utils;
 mod watcher;
@@ -15,8 +23,9 @@ mod watcher;
 // Macros are automatically available from crate root
 
 use crate::compiler::builder::OptimizationLevel;
+use crate::config::{ProjectSettings, DEFAULT_PORT};
 use crate::utils::PathResolver;
-use cli::{get_args, Commands, ResolvedArgs};
+use cli::{get_args, Args, CommandArgs, Commands, ResolvedArgs};
 use debug::enable_debug;
 use error::WasmrunError;
 use std::error::Error;
@@ -30,16 +39,77 @@ fn main() {
         eprintln!("\n📋 Include your command, WASM file, and this error message.");
     }));
 
-    let args = get_args();
+    let mut args = get_args();
 
+    logging::init(args.verbosity(), args.quiet, args.log_format);
     if args.debug {
         enable_debug();
     }
+    if args.offline || offline::requested_by_env() {
+        offline::enable();
+    }
+    network::apply_config();
+    if args.self_profile {
+        self_profile::enable();
+    }
 
     debug_enter!("main", "args = {:?}", args);
 
+    apply_named_project(&mut args);
+
+    let settings = match load_project_settings(&args).and_then(|settings| {
+        apply_base_path(&args)?;
+        apply_tls(&args)?;
+        apply_instance_name(&args)?;
+        if args.coi {
+            server::isolation::enable();
+        }
+        if args.no_cache {
+            server::conditional::disable();
+        }
+        if args.copy_url {
+            server::clipboard::request();
+        }
+        apply_access_log(&args)?;
+        server::limits::set_overrides(args.rate_limit, args.max_connections);
+        server::loading::set_overrides(
+            args.instantiate.clone(),
+            args.load_trigger.clone(),
+            args.module_cache,
+        );
+        apply_demo(&args)?;
+        apply_proxy(&args, &settings)?;
+        apply_headers(&args)?;
+        config::project::export_env(&settings);
+        if settings.watch.prewarm == Some(true) {
+            server::live_reload::enable_prewarm();
+        }
+        Ok(settings)
+    }) {
+        Ok(settings) => settings,
+        Err(e) => {
+            error_println!("[{}] {e}", e.code());
+            std::process::exit(e.exit_code());
+        }
+    };
+    debug_println!("Project settings: {:?}", settings);
+
+    let dashboard = if args.tui && matches!(args.command, None | Some(Commands::Run { .. })) {
+        match tui::Tui::start() {
+            Ok(dashboard) => Some(dashboard),
+            Err(e) => {
+                warn_println!("Could not start the terminal UI: {e}");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
     let result = match &args.command {
-        Some(Commands::Stop) => commands::handle_stop_command(),
+        Some(Commands::Stop { target }) => commands::handle_stop_command(target.as_deref()),
+
+        Some(Commands::Ps) => commands::handle_ps_command(),
 
         Some(Commands::Compile {
             path,
@@ -47,6 +117,11 @@ fn main() {
             output,
             verbose,
             optimization,
+            optimize,
+            sarif,
+            bin,
+            example,
+            dry_run,
         }) => {
             debug_println!("Processing compile command");
             let project_path =
@@ -58,14 +133,38 @@ fn main() {
                 output_dir
             );
 
-            let opt_level = match optimization.as_str() {
+            let optimization = optimization
+                .as_deref()
+                .or(settings.build.optimization.as_deref())
+                .unwrap_or("release");
+            let opt_level = match optimization {
                 "debug" => OptimizationLevel::Debug,
                 "size" => OptimizationLevel::Size,
                 _ => OptimizationLevel::Release,
             };
             debug_println!("Optimization level: {:?}", opt_level);
 
-            commands::handle_compile_command(project_path, output_dir, opt_level, *verbose)
+            compiler::cargo_target::select(&project_path, bin.as_deref(), example.as_deref())
+                .and_then(|_| {
+                    if *dry_run {
+                        commands::handle_compile_dry_run(
+                            project_path,
+                            output_dir,
+                            opt_level,
+                            *optimize,
+                            sarif.as_deref(),
+                        )
+                    } else {
+                        commands::handle_compile_command(
+                            project_path,
+                            output_dir,
+                            opt_level,
+                            *verbose > 0,
+                            *optimize,
+                            sarif.as_deref(),
+                        )
+                    }
+                })
         }
         .map_err(|e| match e {
             WasmrunError::Command(_) | WasmrunError::Compilation(_) | WasmrunError::Path { .. } => {
@@ -74,6 +173,61 @@ fn main() {
             _ => e,
         }),
 
+        Some(Commands::Bundle {
+            path,
+            positional_path,
+            out,
+            verbose,
+            optimization,
+            optimize,
+            with_demo_page,
+            sign,
+            public_key,
+            dry_run,
+            ..
+        }) => {
+            let signing = sign.as_ref().map(|secret_key| commands::Signing {
+                secret_key: secret_key.clone(),
+                public_key: public_key.clone(),
+            });
+            let optimization = optimization
+                .as_deref()
+                .or(settings.build.optimization.as_deref())
+                .unwrap_or("release");
+            let opt_level = match optimization {
+                "debug" => OptimizationLevel::Debug,
+                "size" => OptimizationLevel::Size,
+                _ => OptimizationLevel::Release,
+            };
+            debug_println!(
+                "Processing bundle command: out={}, optimization={:?}",
+                out,
+                opt_level
+            );
+            if *dry_run {
+                commands::handle_bundle_dry_run(
+                    path,
+                    positional_path,
+                    out,
+                    opt_level,
+                    *optimize,
+                    *with_demo_page,
+                    signing.as_ref(),
+                )
+            } else {
+                commands::handle_bundle_command(
+                    path,
+                    positional_path,
+                    out,
+                    opt_level,
+                    *optimize,
+                    *with_demo_page,
+                    signing.as_ref(),
+                    *verbose > 0,
+                )
+            }
+        }
+
         Some(Commands::Verify {
             path,
             positional_path,
@@ -100,9 +254,31 @@ fn main() {
             port,
             language,
             watch,
+            prewarm,
             verbose: _verbose,
             serve,
+            a11y_audit,
+            replay,
+            replay_speed,
+            bin,
+            example,
+            instrument,
+            ..
         }) => {
+            let port = port.or(settings.server.port).unwrap_or(DEFAULT_PORT);
+            if *a11y_audit {
+                server::a11y::request_audit();
+            }
+            if *prewarm {
+                server::live_reload::enable_prewarm();
+            }
+            if let Some(functions) = instrument {
+                if server::demo::is_enabled() {
+                    warn_println!("Ignoring --instrument: demo mode serves no profiling overlay");
+                } else {
+                    server::profiler::enable(functions);
+                }
+            }
             debug_println!(
                 "Processing run command: port={}, language={:?}, watch={}, serve={}",
                 port,
@@ -110,19 +286,50 @@ fn main() {
                 watch,
                 serve
             );
-            commands::handle_run_command(
-                path,
-                positional_path,
-                *port,
-                language,
-                *watch,
-                false,
-                *serve,
-            )
-            .map_err(|e| match e {
-                WasmrunError::Command(_) | WasmrunError::Server(_) | WasmrunError::Path { .. } => e,
-                _ => e,
-            })
+            let project_path =
+                PathResolver::resolve_input_path(positional_path.clone(), path.clone());
+            compiler::cargo_target::select(&project_path, bin.as_deref(), example.as_deref())
+                .and_then(|_| {
+                    replay.as_deref().map_or(Ok(()), |name| {
+                        server::replay::set_autoplay(name, replay_speed)
+                    })
+                })
+                .and_then(|_| {
+                    commands::handle_run_command(
+                        path,
+                        positional_path,
+                        port,
+                        language,
+                        *watch,
+                        false,
+                        *serve || replay.is_some(),
+                    )
+                })
+                .map_err(|e| match e {
+                    WasmrunError::Command(_)
+                    | WasmrunError::Server(_)
+                    | WasmrunError::Path { .. } => e,
+                    _ => e,
+                })
+                .and_then(|_| server::a11y::finish_audit())
+        }
+
+        Some(Commands::Preview {
+            path,
+            positional_path,
+            port,
+            verbose,
+            serve,
+            a11y_audit,
+            ..
+        }) => {
+            let port = port.or(settings.server.port).unwrap_or(DEFAULT_PORT);
+            debug_println!("Processing preview command: port={}", port);
+            if *a11y_audit {
+                server::a11y::request_audit();
+            }
+            commands::handle_preview_command(path, positional_path, port, *verbose > 0, *serve)
+                .and_then(|_| server::a11y::finish_audit())
         }
 
         Some(Commands::Os {
@@ -133,6 +340,7 @@ fn main() {
             watch,
             verbose,
         }) => {
+            let port = port.or(settings.server.port).unwrap_or(DEFAULT_PORT);
             debug_println!(
                 "Processing os command: port={}, language={:?}, watch={}, verbose={}",
                 port,
@@ -140,7 +348,7 @@ fn main() {
                 watch,
                 verbose
             );
-            commands::handle_os_command(path, positional_path, *port, language, *watch, *verbose)
+            commands::handle_os_command(path, positional_path, port, language, *watch, *verbose > 0)
                 .map_err(|e| match e {
                     WasmrunError::Command(_)
                     | WasmrunError::Server(_)
@@ -149,6 +357,19 @@ fn main() {
                 })
         }
 
+        Some(Commands::Exec {
+            wasm,
+            args: module_args,
+            dirs,
+            envs,
+        }) => commands::handle_exec_command(wasm, module_args, dirs, envs).map(|code| {
+            if code != 0 {
+                self_profile::report();
+                debug_exit!("main", format!("module exit code: {code}"));
+                std::process::exit(code);
+            }
+        }),
+
         Some(Commands::Plugin(plugin_cmd)) => {
             commands::run_plugin_command(plugin_cmd).map_err(|e| match e {
                 WasmrunError::Command(_) | WasmrunError::Path { .. } => e,
@@ -156,30 +377,173 @@ fn main() {
             })
         }
 
+        Some(Commands::Project(project_cmd)) => commands::handle_project_command(project_cmd),
+
+        Some(Commands::Config(config_cmd)) => commands::handle_config_command(
+            config_cmd,
+            args.profile.as_deref(),
+            config::effective::Overrides {
+                coi: args.coi,
+                rate_limit: args.rate_limit,
+                max_connections: args.max_connections,
+                tls: args.tls || args.cert.is_some(),
+                offline: args.offline,
+                instantiate: args.instantiate.clone(),
+                load_trigger: args.load_trigger.clone(),
+                module_cache: args.module_cache,
+                ..Default::default()
+            },
+        ),
+
         Some(Commands::Clean {
             path,
             positional_path,
             all,
-        }) => commands::handle_clean_command(&path.clone(), &positional_path.clone(), *all),
+            dry_run,
+        }) => {
+            commands::handle_clean_command(&path.clone(), &positional_path.clone(), *all, *dry_run)
+        }
+
+        Some(Commands::Gc { max_size, all }) => commands::handle_gc_command(*max_size, *all),
+
+        Some(Commands::Stats) => commands::handle_stats_command(),
+
+        Some(Commands::Selftest {
+            examples,
+            languages,
+            keep,
+        }) => commands::handle_selftest_command(examples.as_deref(), languages, *keep),
+
+        Some(Commands::Licenses {
+            path,
+            positional_path,
+            output,
+            format,
+        }) => commands::handle_licenses_command(path, positional_path, output, format),
+
+        Some(Commands::Audit {
+            path,
+            positional_path,
+            ..
+        }) => commands::handle_audit_command(path, positional_path),
+
+        Some(Commands::Why {
+            path,
+            positional_path,
+        }) => commands::handle_why_command(path, positional_path),
+
+        Some(Commands::Types {
+            path,
+            positional_path,
+            output,
+        }) => commands::handle_types_command(path, positional_path, output),
+
+        Some(Commands::Optimize {
+            path,
+            positional_path,
+            level,
+            output,
+        }) => commands::handle_optimize_command(path, positional_path, *level, output),
+
+        Some(Commands::Explain { code }) => commands::handle_explain_command(code),
+
+        Some(Commands::Ctl { port, action }) => commands::handle_ctl_command(action, *port),
+
+        Some(Commands::CiReport {
+            paths,
+            base,
+            save,
+            budget,
+            junit,
+            format,
+        }) => commands::handle_ci_report_command(paths, base, save, *budget, junit, format),
+
+        Some(Commands::Serve {
+            path,
+            positional_path,
+            entry,
+            gallery,
+            listings,
+            port,
+            ..
+        }) => {
+            let port = port.or(settings.server.port).unwrap_or(DEFAULT_PORT);
+            commands::handle_serve_command(
+                path,
+                positional_path,
+                entry.as_deref(),
+                *gallery,
+                *listings,
+                port,
+                args.serve,
+            )
+        }
+
+        Some(Commands::Join { url, port }) => {
+            let port = port.or(settings.server.port).unwrap_or(DEFAULT_PORT);
+            commands::handle_join_command(url, port, args.serve)
+        }
+
+        Some(Commands::Compare {
+            old,
+            new,
+            bench: Some(export),
+            bench_args,
+            iterations,
+            trace,
+            ..
+        }) => commands::handle_compare_bench_command(
+            old,
+            new,
+            export,
+            bench_args,
+            *iterations,
+            trace.as_deref(),
+        ),
+
+        Some(Commands::Compare { old, new, port, .. }) => {
+            let port = port.or(settings.server.port).unwrap_or(DEFAULT_PORT);
+            commands::handle_compare_command(old, new, port, args.serve)
+        }
 
         None => {
             debug_println!(
                 "No subcommand provided, running default mode (equivalent to 'run' command)"
             );
+            // Without a path, do everything for the project in the current directory
+            let zero_config = args.positional_path.is_none() && args.path == "./" && !args.wasm;
             let resolved_args = match ResolvedArgs::from_args(args) {
                 Ok(args) => {
                     debug_println!("Resolved args: {:?}", args);
                     args
                 }
                 Err(e) => {
-                    error_println!("{e}");
+                    error_println!("[{}] {e}", e.code());
                     debug_println!("Failed to resolve args: {:?}", e);
-                    std::process::exit(1);
+                    std::process::exit(e.exit_code());
                 }
             };
-            if resolved_args.wasm {
+            let port = resolved_args
+                .port
+                .or(settings.server.port)
+                .unwrap_or(DEFAULT_PORT);
+            if zero_config {
+                let port_source = if resolved_args.port.is_some() {
+                    "from --port"
+                } else if settings.server.port.is_some() {
+                    "from wasmrun.toml"
+                } else {
+                    "default"
+                };
+                commands::run_zero_config(
+                    &resolved_args.path,
+                    port,
+                    port_source,
+                    resolved_args.port.is_some(),
+                )
+            } else if resolved_args.wasm {
                 debug_println!("Running WASM file: {}", resolved_args.path);
-                server::run_wasm_file(&resolved_args.path, resolved_args.port, resolved_args.serve)
+                server::run_wasm_file(&resolved_args.path, port, resolved_args.serve)
             } else {
                 debug_println!(
                     "Running project: {}, language: {:?}, watch: {}",
@@ -191,7 +555,7 @@ fn main() {
                 commands::handle_run_command(
                     &None,
                     &Some(resolved_args.path),
-                    resolved_args.port,
+                    port,
                     &resolved_args.language,
                     resolved_args.watch,
                     false, // verbose mode for default command
@@ -207,20 +571,217 @@ fn main() {
         }
     };
 
+    // Give the terminal back before reporting the outcome
+    drop(dashboard);
+    self_profile::report();
+
     if let Err(e) = result {
         debug_println!("Command execution failed: {:?}", e);
         let mut error_source: &dyn Error = &e;
-        eprintln!("❌ {error_source}");
+        error_println!("[{}] {error_source}", e.code());
 
         while let Some(source) = error_source.source() {
-            eprintln!("   Caused by: {source}");
+            log_at!(logging::Level::Error, "   Caused by: {source}");
             debug_println!("Error chain: {}", source);
             error_source = source;
         }
 
-        debug_exit!("main", "exit code: 1");
-        std::process::exit(1);
+        debug_exit!("main", format!("exit code: {}", e.exit_code()));
+        std::process::exit(e.exit_code());
     }
 
     debug_exit!("main", "exit code: 0");
 }
+
+/// Launch a project registered with `wasmrun project add` when run is given its name
+fn apply_named_project(args: &mut Args) {
+    let (target, port) = match &mut args.command {
+        Some(Commands::Run {
+            path,
+            positional_path,
+            port,
+            ..
+        }) => (positional_path.as_mut().or(path.as_mut()), port),
+        None => (args.positional_path.as_mut(), &mut args.port),
+        _ => return,
+    };
+    let Some(target) = target else {
+        return;
+    };
+    if let Some(project) = commands::find_named_project(target) {
+        println!(
+            "📌 Running project '{}' from {}",
+            project.name, project.path
+        );
+        *target = project.path;
+        *port = port.or(project.port);
+        if args.name.is_none() && server::is_valid_instance_name(&project.name) {
+            args.name = Some(project.name);
+        }
+    }
+}
+
+/// Register the servers this process starts under `--name`
+fn apply_instance_name(args: &Args) -> error::Result<()> {
+    let Some(name) = &args.name else {
+        return Ok(());
+    };
+    if !server::is_valid_instance_name(name) {
+        return Err(WasmrunError::Command(
+            error::CommandError::invalid_arguments(format!(
+                "--name '{name}' must use letters, digits, '-', '_' or '.' and not be a port number"
+            )),
+        ));
+    }
+    server::set_instance_name(name.clone());
+    Ok(())
+}
+
+/// Apply `--base` to the commands that serve pages
+fn apply_base_path(args: &Args) -> error::Result<()> {
+    let base = match &args.command {
+        Some(Commands::Run { base, .. })
+        | Some(Commands::Preview { base, .. })
+        | Some(Commands::Bundle { base, .. })
+        | Some(Commands::Serve { base, .. })
+        | Some(Commands::Compare { base, .. }) => base.as_ref().or(args.base.as_ref()),
+        None => args.base.as_ref(),
+        _ => None,
+    };
+    if let Some(base) = base {
+        let base = server::base_path::normalize(base).map_err(|reason| {
+            WasmrunError::Command(error::CommandError::invalid_arguments(format!(
+                "--base '{base}' {reason}"
+            )))
+        })?;
+        server::base_path::set_override(base);
+    }
+    Ok(())
+}
+
+/// Enable HTTPS for the commands that serve pages when `--tls`, `--cert` or `--key` is given
+fn apply_tls(args: &Args) -> error::Result<()> {
+    let serves = matches!(
+        args.command,
+        None | Some(Commands::Run { .. })
+            | Some(Commands::Preview { .. })
+            | Some(Commands::Serve { .. })
+            | Some(Commands::Join { .. })
+            | Some(Commands::Compare { bench: None, .. })
+    );
+    if serves && (args.tls || args.cert.is_some() || args.key.is_some()) {
+        server::tls::enable(args.cert.as_deref(), args.key.as_deref())?;
+    }
+    Ok(())
+}
+
+/// Parse the `--proxy` rules and forward matching requests to their backends
+fn apply_proxy(args: &Args, settings: &ProjectSettings) -> error::Result<()> {
+    let mut rules: Vec<server::proxy::ProxyRule> = Vec::new();
+    if serves(args) {
+        // Validated when wasmrun.toml was loaded
+        rules.extend(
+            settings
+                .server
+                .proxy
+                .iter()
+                .filter_map(|(prefix, upstream)| {
+                    server::proxy::ProxyRule::parse(&format!("{prefix}={upstream}")).ok()
+                }),
+        );
+    }
+    for rule in &args.proxy {
+        let rule = server::proxy::ProxyRule::parse(rule).map_err(|reason| {
+            WasmrunError::Command(error::CommandError::invalid_arguments(format!(
+                "--proxy '{rule}' {reason}"
+            )))
+        })?;
+        // A flag replaces the wasmrun.toml rule for the same prefix
+        rules.retain(|existing| existing.prefix != rule.prefix);
+        rules.push(rule);
+    }
+    if !rules.is_empty() {
+        server::proxy::enable(rules);
+    }
+    Ok(())
+}
+
+/// Parse the `--header` flags and send them with every response
+fn apply_headers(args: &Args) -> error::Result<()> {
+    if !serves(args) {
+        return Ok(());
+    }
+    let headers = args
+        .headers
+        .iter()
+        .map(|spec| {
+            server::headers::parse(spec).map_err(|reason| {
+                WasmrunError::Command(error::CommandError::invalid_arguments(format!(
+                    "--header '{spec}' {reason}"
+                )))
+            })
+        })
+        .collect::<error::Result<Vec<_>>>()?;
+    server::headers::set_flags(headers);
+    Ok(())
+}
+
+/// Whether the command starts a server
+fn serves(args: &Args) -> bool {
+    matches!(
+        args.command,
+        None | Some(Commands::Run { .. })
+            | Some(Commands::Preview { .. })
+            | Some(Commands::Serve { .. })
+            | Some(Commands::Compare { bench: None, .. })
+    )
+}
+
+/// Open the `--access-log` and honour `--log-requests` for commands that serve
+fn apply_access_log(args: &Args) -> error::Result<()> {
+    if !serves(args) {
+        return Ok(());
+    }
+    if let Some(path) = &args.access_log {
+        server::access_log::enable(path, args.access_log_format)?;
+    }
+    if !args.log_requests {
+        server::access_log::silence_requests();
+    }
+    Ok(())
+}
+
+/// Turn on read-only demo mode for commands that serve, and print how to get in
+fn apply_demo(args: &Args) -> error::Result<()> {
+    if serves(args) && (args.demo || args.demo_token.is_some()) {
+        let token = server::demo::enable(args.demo_token.as_deref())?;
+        println!("🔒 Demo mode: read-only, rate-limited, access by token");
+        println!("   Share the page as <url>/?token={token}");
+    }
+    Ok(())
+}
+
+/// Load `wasmrun.toml` for the targeted project and apply the selected profile
+fn load_project_settings(args: &Args) -> error::Result<ProjectSettings> {
+    let project_path = match &args.command {
+        Some(Commands::Plugin(_))
+        | Some(Commands::Project(_))
+        | Some(Commands::Exec { .. })
+        | Some(Commands::Stop { .. })
+        | Some(Commands::Ps)
+        | Some(Commands::Gc { .. })
+        | Some(Commands::Stats)
+        | Some(Commands::Selftest { .. })
+        | Some(Commands::Explain { .. })
+        | Some(Commands::Ctl { .. }) => return Ok(ProjectSettings::default()),
+        Some(command) => command.resolve_path(),
+        None => args.path.clone(),
+    };
+
+    let settings = config::project::activate(&project_path, args.profile.as_deref())?;
+    if let Some(profile) = &args.profile {
+        println!("🎛️  Using profile: {profile}");
+    }
+
+    Ok(settings)
+}
@@ -0,0 +1,90 @@
+//! Offline mode for air-gapped environments
+//!
+//! Enabled with `--offline` or `WASMRUN_OFFLINE=1`. In offline mode
+//! [`crate::network::agent_builder`] refuses to build agents, so every
+//! outbound request fails fast with [`WasmrunError::NetworkDisabled`]. Commands
+//! that need the network check first with [`require_network`] to say what
+//! they were about to do, cached plugin metadata is used where it exists, and
+//! build toolchains started by wasmrun are told to stay offline as well.
+
+use crate::config::OFFLINE_ENABLED;
+use crate::error::{Result, WasmrunError};
+use std::sync::atomic::Ordering;
+
+/// Environment variable that turns on offline mode
+pub const OFFLINE_ENV: &str = "WASMRUN_OFFLINE";
+
+/// Settings inherited by child toolchains so they resolve dependencies from local caches only
+const TOOLCHAIN_OFFLINE_ENV: &[(&str, &str)] = &[
+    ("CARGO_NET_OFFLINE", "true"),
+    ("GOPROXY", "off"),
+    ("PIP_NO_INDEX", "1"),
+    ("npm_config_offline", "true"),
+];
+
+/// Whether `WASMRUN_OFFLINE` asks for offline mode
+pub fn requested_by_env() -> bool {
+    std::env::var(OFFLINE_ENV)
+        .map(|value| turns_on(&value))
+        .unwrap_or(false)
+}
+
+/// Whether a `WASMRUN_OFFLINE` value means yes
+fn turns_on(value: &str) -> bool {
+    !matches!(value.trim(), "" | "0" | "false" | "no")
+}
+
+/// Enable offline mode for this process and the toolchains it starts.
+/// Must be called before any threads are spawned.
+pub fn enable() {
+    OFFLINE_ENABLED.store(true, Ordering::Relaxed);
+    for (name, value) in TOOLCHAIN_OFFLINE_ENV {
+        if std::env::var_os(name).is_none() {
+            std::env::set_var(name, value);
+        }
+    }
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Fail with a clear error when `action` needs the network in offline mode
+pub fn require_network(action: impl Into<String>) -> Result<()> {
+    require_network_when(is_offline(), action)
+}
+
+/// [`require_network`] with the offline state given rather than read
+pub(crate) fn require_network_when(offline: bool, action: impl Into<String>) -> Result<()> {
+    if offline {
+        return Err(WasmrunError::NetworkDisabled {
+            action: action.into(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_values() {
+        for value in ["1", "true", "yes", " on "] {
+            assert!(turns_on(value), "{value:?}");
+        }
+        for value in ["", " ", "0", "false", "no"] {
+            assert!(!turns_on(value), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn test_require_network() {
+        assert!(require_network_when(false, "Searching crates.io").is_ok());
+        let error = require_network_when(true, "Searching crates.io").unwrap_err();
+        assert!(matches!(error, WasmrunError::NetworkDisabled { .. }));
+        assert!(error
+            .to_string()
+            .starts_with("Searching crates.io needs network access"));
+    }
+}
@@ -1,11 +1,21 @@
 use crate::compiler::builder::{BuildConfig, BuildResult};
 use std::ffi::{c_char, CString};
 
+/// Version of the C plugin interface below, reported by library plugins
+/// through `wasmrun_plugin_abi_version`; bumped on any incompatible change to
+/// these structs or the exported signatures
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
 #[repr(C)]
 pub struct BuildConfigC {
     pub project_path: *const c_char,
     pub output_dir: *const c_char,
     pub watch: bool,
+    /// `bin:<name>` or `example:<name>`, or null to build the default targets.
+    /// Appended last so plugins built against the older layout still read it correctly.
+    pub cargo_target: *const c_char,
+    /// Comma-separated Cargo features to enable, or null for the default features
+    pub cargo_features: *const c_char,
 }
 
 #[repr(C)]
@@ -77,6 +87,10 @@ pub mod symbols {
     use std::ffi::c_void;
     use std::os::raw::{c_char, c_int};
 
+    // Handshake, see [`crate::plugin::dylib`]
+    pub type AbiVersionFn = unsafe extern "C" fn() -> u32;
+    pub type PluginMetadataFn = unsafe extern "C" fn() -> *const c_char;
+
     // Old API (deprecated)
     pub type CreateBuilderFn = unsafe extern "C" fn() -> *mut c_void;
     pub type CanHandleProjectFn = unsafe extern "C" fn(*const c_void, *const c_char) -> bool;
@@ -185,10 +199,31 @@ impl BuildConfigC {
         let project_path = CString::new(config.project_path.clone()).unwrap_or_default();
         let output_dir = CString::new(config.output_dir.clone()).unwrap_or_default();
 
+        let cargo_target = config
+            .cargo_target
+            .as_ref()
+            .map_or(std::ptr::null(), |target| {
+                CString::new(target.to_string())
+                    .unwrap_or_default()
+                    .into_raw()
+                    .cast_const()
+            });
+
+        let cargo_features = if config.cargo_features.is_empty() {
+            std::ptr::null()
+        } else {
+            CString::new(config.cargo_features.join(","))
+                .unwrap_or_default()
+                .into_raw()
+                .cast_const()
+        };
+
         Self {
             project_path: project_path.into_raw(),
             output_dir: output_dir.into_raw(),
             watch: config.watch,
+            cargo_target,
+            cargo_features,
         }
     }
 }
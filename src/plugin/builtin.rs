@@ -2,7 +2,11 @@
 
 use crate::compiler::builder::WasmBuilder;
 use crate::error::Result;
+use crate::plugin::languages::asc_plugin::AscPlugin;
 use crate::plugin::languages::c_plugin::CPlugin;
+use crate::plugin::languages::go_plugin::GoPlugin;
+use crate::plugin::languages::python_plugin::PythonPlugin;
+use crate::plugin::languages::rust_plugin::RustPlugin;
 use crate::plugin::{Plugin, PluginCapabilities, PluginInfo, PluginType};
 use std::sync::Arc;
 
@@ -142,6 +146,14 @@ impl WasmBuilder for BuiltinBuilderWrapper {
         self.builder.validate_project(project_path)
     }
 
+    fn generated_paths(&self, project_path: &str) -> Vec<String> {
+        self.builder.generated_paths(project_path)
+    }
+
+    fn planned_commands(&self, config: &crate::compiler::builder::BuildConfig) -> Vec<String> {
+        self.builder.planned_commands(config)
+    }
+
     fn can_handle_project(&self, project_path: &str) -> bool {
         self.builder.can_handle_project(project_path)
     }
@@ -157,10 +169,26 @@ impl WasmBuilder for BuiltinBuilderWrapper {
 
 /// Load all built-in plugins into a vector
 pub fn load_all_builtin_plugins(plugins: &mut Vec<Box<dyn Plugin>>) -> Result<()> {
+    // Rust plugin, ahead of C so a crate with a Makefile still builds with Cargo
+    let rust_plugin = Arc::new(RustPlugin::new());
+    plugins.push(Box::new(BuiltinPlugin::new(rust_plugin)));
+
+    // Go plugin, also ahead of C for modules with a Makefile
+    let go_plugin = Arc::new(GoPlugin::new());
+    plugins.push(Box::new(BuiltinPlugin::new(go_plugin)));
+
+    // AssemblyScript plugin
+    let asc_plugin = Arc::new(AscPlugin::new());
+    plugins.push(Box::new(BuiltinPlugin::new(asc_plugin)));
+
     // C plugin
     let c_plugin = Arc::new(CPlugin::new());
     plugins.push(Box::new(BuiltinPlugin::new(c_plugin)));
 
+    // Python plugin, last since any directory with a lone script matches
+    let python_plugin = Arc::new(PythonPlugin::new());
+    plugins.push(Box::new(BuiltinPlugin::new(python_plugin)));
+
     Ok(())
 }
 
@@ -173,7 +201,7 @@ pub fn get_builtin_plugin_info() -> Vec<PluginInfo> {
 /// Check if a plugin name is a built-in plugin
 #[allow(dead_code)] // TODO: Future plugin validation
 pub fn is_builtin_plugin(name: &str) -> bool {
-    matches!(name, "c")
+    matches!(name, "asc" | "c" | "go" | "python" | "rust")
 }
 
 /// Get specific built-in plugin info by name
@@ -212,6 +240,8 @@ mod tests {
 
         // Check that we have the expected builtin plugins
         assert!(plugin_names.contains(&"c"));
+        assert!(plugin_names.contains(&"rust"));
+        assert!(plugin_names.contains(&"go"));
     }
 
     #[test]
@@ -317,11 +347,12 @@ mod tests {
     #[test]
     fn test_is_builtin_plugin() {
         assert!(is_builtin_plugin("c"));
+        assert!(is_builtin_plugin("rust"));
+        assert!(is_builtin_plugin("go"));
+        assert!(is_builtin_plugin("asc"));
+        assert!(is_builtin_plugin("python"));
 
-        assert!(!is_builtin_plugin("asc"));
-        assert!(!is_builtin_plugin("rust"));
-        assert!(!is_builtin_plugin("go"));
-        assert!(!is_builtin_plugin("python"));
+        assert!(!is_builtin_plugin("waspy"));
         assert!(!is_builtin_plugin("nonexistent"));
         assert!(!is_builtin_plugin(""));
     }
@@ -0,0 +1,333 @@
+//! Plugins installed as prebuilt dynamic libraries
+//!
+//! `wasmrun plugin install ./libmyplugin.so` copies a cdylib into the plugin
+//! directory instead of building a crate. Such a library speaks the C
+//! interface in [`bridge`](super::bridge) and exports two symbols besides its
+//! builder functions:
+//!
+//! - `wasmrun_plugin_abi_version() -> u32`, the [`PLUGIN_ABI_VERSION`] it was
+//!   built against, checked before anything else in the library is called
+//! - `wasmrun_plugin_metadata() -> *const c_char`, its [`PluginMetadata`] as
+//!   NUL-terminated JSON owned by the library
+//!
+//! Libraries built from source before the version check existed don't export
+//! the first symbol and still load; a library that reports another version
+//! is refused rather than called with structs it would misread.
+//!
+//! A library can also be installed from an http(s) URL. It is checked against
+//! the SHA-256 given with `--sha256` or in a `#sha256=<hex>` fragment of the
+//! URL, or else in a `<url>.sha256` file published next to an https URL.
+//! Without any of those the library is not downloaded.
+
+use super::bridge::{symbols, PLUGIN_ABI_VERSION};
+use super::metadata::PluginMetadata;
+use crate::config::WasmrunConfig;
+use crate::error::{Result, WasmrunError};
+use crate::utils::{download, PluginUtils};
+use libloading::Library;
+use std::ffi::CStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extensions of the libraries wasmrun loads
+pub const LIBRARY_EXTENSIONS: &[&str] = &["so", "dylib"];
+
+/// File holding the metadata of a library plugin in its plugin directory
+pub const METADATA_FILE: &str = "wasm_plugin.json";
+
+/// Whether `plugin`, as given to `wasmrun plugin install`, is a library file
+pub fn is_library_path(plugin: &str) -> bool {
+    let path = Path::new(plugin);
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| LIBRARY_EXTENSIONS.contains(&ext))
+}
+
+/// `plugin` as a library URL and the digest in its `#sha256=` fragment, if
+/// it is an http(s) URL of a library
+pub fn library_url(plugin: &str) -> Option<(&str, Option<&str>)> {
+    let (url, fragment) = plugin.split_once('#').unwrap_or((plugin, ""));
+    let is_http = url.starts_with("https://") || url.starts_with("http://");
+    let is_library = url_file_name(url)
+        .and_then(|name| Path::new(name).extension())
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| LIBRARY_EXTENSIONS.contains(&ext));
+    (is_http && is_library).then(|| (url, fragment.strip_prefix("sha256=")))
+}
+
+/// The last path segment of `url`
+fn url_file_name(url: &str) -> Option<&str> {
+    let path = url.split('?').next()?;
+    path.rsplit('/').next().filter(|name| !name.is_empty())
+}
+
+/// Where the library at `url` is downloaded to before it is installed
+pub fn download_path(url: &str) -> Result<PathBuf> {
+    let name = url_file_name(url)
+        .ok_or_else(|| WasmrunError::from(format!("{url} doesn't name a library file")))?;
+    Ok(WasmrunConfig::config_dir()?.join("downloads").join(name))
+}
+
+/// Download the library at `url`, checking it against `sha256` or the
+/// `.sha256` file next to it; returns its path and digest. Nothing is
+/// downloaded without a digest to check against, and a plain http URL needs
+/// `sha256`, since a `.sha256` file fetched the same way proves nothing.
+pub fn download_library(url: &str, sha256: Option<&str>) -> Result<(PathBuf, String)> {
+    check_source(url, sha256)?;
+    crate::offline::require_network(format!("Downloading {url}"))?;
+    let expected = match sha256 {
+        Some(digest) => digest.to_ascii_lowercase(),
+        None => download::get_string_if_found(&format!("{url}.sha256"))?
+            .map(|text| {
+                download::parse_checksum_file(&text).ok_or_else(|| {
+                    WasmrunError::from(format!("{url}.sha256 doesn't hold a SHA-256 digest"))
+                })
+            })
+            .transpose()?
+            .ok_or_else(|| {
+                WasmrunError::from(format!(
+                    "No checksum for {url}: {url}.sha256 isn't published, so pass --sha256 <digest> to verify the download"
+                ))
+            })?,
+    };
+
+    let path = download_path(url)?;
+    println!("⬇️  Downloading {url}");
+    let digest = download::download_file(url, &path, Some(&expected))?;
+    println!("🔒 SHA-256 verified: {digest}");
+    Ok((path, digest))
+}
+
+/// Refuse to fetch a library over plain http unless its digest is known
+pub fn check_source(url: &str, sha256: Option<&str>) -> Result<()> {
+    if url.starts_with("https://") || sha256.is_some() {
+        return Ok(());
+    }
+    Err(WasmrunError::from(format!(
+        "Refusing to download {url} over plain http without a checksum; use an https URL or pass --sha256 <digest>"
+    )))
+}
+
+/// `plugin` with `sha256` given as its `#sha256=` fragment, for `--sha256`
+pub fn with_sha256(plugin: &str, sha256: Option<&str>) -> Result<String> {
+    let Some(sha256) = sha256 else {
+        return Ok(plugin.to_string());
+    };
+    match library_url(plugin) {
+        Some((url, None)) => Ok(format!("{url}#sha256={sha256}")),
+        Some((_, Some(_))) => Err(WasmrunError::from(
+            "Give the SHA-256 either with --sha256 or in the URL's #sha256= fragment, not both",
+        )),
+        None => Err(WasmrunError::from(
+            "--sha256 only applies to plugin libraries installed from a URL",
+        )),
+    }
+}
+
+/// Refuse a plugin built against another version of the C interface
+pub fn check_abi_version(version: u32) -> Result<()> {
+    if version == PLUGIN_ABI_VERSION {
+        return Ok(());
+    }
+    Err(WasmrunError::from(format!(
+        "Plugin was built for plugin ABI v{version}, but wasmrun {} speaks v{PLUGIN_ABI_VERSION}; rebuild it against a matching wasmrun-plugin version",
+        env!("CARGO_PKG_VERSION")
+    )))
+}
+
+/// The ABI version `library` reports, checked against ours; `None` when it
+/// predates the version check
+pub fn handshake(library: &Library) -> Result<Option<u32>> {
+    let version = unsafe {
+        match library.get::<symbols::AbiVersionFn>(b"wasmrun_plugin_abi_version") {
+            Ok(abi_version) => abi_version(),
+            Err(_) => return Ok(None),
+        }
+    };
+    check_abi_version(version)?;
+    Ok(Some(version))
+}
+
+/// The metadata `library` describes itself with
+fn read_metadata(library: &Library) -> Result<PluginMetadata> {
+    let json = unsafe {
+        let metadata = library
+            .get::<symbols::PluginMetadataFn>(b"wasmrun_plugin_metadata")
+            .map_err(|_| {
+                WasmrunError::from("Plugin library does not export wasmrun_plugin_metadata")
+            })?;
+        let ptr = metadata();
+        if ptr.is_null() {
+            return Err(WasmrunError::from("Plugin library returned no metadata"));
+        }
+        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    };
+    parse_metadata(&json)
+}
+
+fn parse_metadata(json: &str) -> Result<PluginMetadata> {
+    let mut metadata: PluginMetadata = serde_json::from_str(json)
+        .map_err(|e| WasmrunError::from(format!("Invalid plugin metadata: {e}")))?;
+    metadata.validate()?;
+    if metadata.exports.is_none() {
+        metadata.exports = Some(PluginMetadata::create_default_exports(&metadata.name));
+    }
+    Ok(metadata)
+}
+
+/// Check that the library at `path` is a plugin wasmrun can load, and read
+/// its metadata
+pub fn inspect(path: &Path) -> Result<PluginMetadata> {
+    let library = unsafe { Library::new(path) }
+        .map_err(|e| WasmrunError::from(format!("Failed to load {}: {e}", path.display())))?;
+    if handshake(&library)?.is_none() {
+        return Err(WasmrunError::from(
+            "Plugin library does not export wasmrun_plugin_abi_version, so its interface version is unknown",
+        ));
+    }
+    let metadata = read_metadata(&library)?;
+    let has_builder = unsafe {
+        library
+            .get::<symbols::CreateBuilderFn>(b"create_wasm_builder")
+            .is_ok()
+    };
+    if !has_builder {
+        return Err(WasmrunError::from(
+            "Plugin library does not export create_wasm_builder",
+        ));
+    }
+    Ok(metadata)
+}
+
+/// Where [`install`] copies the library at `path` for the plugin `name`
+pub fn install_target(path: &Path, name: &str) -> Result<PathBuf> {
+    let plugin_dir = PluginUtils::get_plugin_directory(name)?;
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("so");
+    Ok(plugin_dir.join(format!("lib{name}.{extension}")))
+}
+
+/// The plugin name the library at `path` is likely to report, from its file
+/// name: `libmyplugin.so` is `myplugin`. Loading the library to ask it runs
+/// its code, which `--dry-run` must not do.
+pub fn name_from_file(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    stem.strip_prefix("lib").unwrap_or(&stem).to_string()
+}
+
+/// Check the library at `path` and copy it into its plugin directory, with
+/// its metadata next to it; returns the metadata
+pub fn install(path: &Path) -> Result<PluginMetadata> {
+    let metadata = inspect(path)?;
+    let plugin_dir = PluginUtils::get_plugin_directory(&metadata.name)?;
+    fs::create_dir_all(&plugin_dir)
+        .map_err(|e| WasmrunError::from(format!("Failed to create plugin directory: {e}")))?;
+    let target = install_target(path, &metadata.name)?;
+    // Copied next to the target and renamed over it, so a loaded older copy
+    // keeps its file rather than being rewritten underneath the process
+    let partial = plugin_dir.join(format!(".lib{}.partial", metadata.name));
+    fs::copy(path, &partial)
+        .and_then(|_| fs::rename(&partial, &target))
+        .map_err(|e| WasmrunError::from(format!("Failed to copy plugin library: {e}")))?;
+    let json = serde_json::to_string_pretty(&metadata)
+        .map_err(|e| WasmrunError::from(format!("Failed to serialize plugin metadata: {e}")))?;
+    fs::write(plugin_dir.join(METADATA_FILE), json)
+        .map_err(|e| WasmrunError::from(format!("Failed to write plugin metadata: {e}")))?;
+    PluginUtils::create_metadata_file(&metadata.name, &plugin_dir, &metadata.version)?;
+
+    println!("📦 Installed {} to {}", path.display(), target.display());
+    Ok(metadata)
+}
+
+/// Metadata stored by [`install`] in `plugin_dir`, if it holds a library plugin
+pub fn installed_metadata(plugin_dir: &Path) -> Option<Result<PluginMetadata>> {
+    let json = fs::read_to_string(plugin_dir.join(METADATA_FILE)).ok()?;
+    Some(parse_metadata(&json))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abi_version_must_match() {
+        assert!(check_abi_version(PLUGIN_ABI_VERSION).is_ok());
+        let error = check_abi_version(PLUGIN_ABI_VERSION + 1).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains(&format!("built for plugin ABI v{}", PLUGIN_ABI_VERSION + 1)));
+    }
+
+    #[test]
+    fn test_parse_metadata_defaults_exports() {
+        let json = r#"{
+            "name": "wasmzig",
+            "version": "0.2.0",
+            "description": "Zig plugin",
+            "author": "someone",
+            "extensions": ["zig"],
+            "entry_files": ["build.zig"],
+            "capabilities": {
+                "compile_wasm": true,
+                "compile_webapp": false,
+                "live_reload": true,
+                "optimization": false,
+                "custom_targets": []
+            },
+            "dependencies": { "tools": ["zig"] }
+        }"#;
+        let metadata = parse_metadata(json).unwrap();
+        assert_eq!(metadata.name, "wasmzig");
+        assert_eq!(metadata.exports.unwrap().build, "wasmzig_build");
+        assert!(parse_metadata(r#"{"name": "broken"}"#).is_err());
+    }
+
+    #[test]
+    fn test_is_library_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join("libwasmzig.so");
+        fs::write(&library, b"").unwrap();
+        assert!(is_library_path(library.to_str().unwrap()));
+        assert!(!is_library_path("wasmzig"));
+        assert!(!is_library_path(dir.path().to_str().unwrap()));
+        assert_eq!(name_from_file(&library), "wasmzig");
+        assert_eq!(name_from_file(Path::new("zig.dylib")), "zig");
+    }
+
+    #[test]
+    fn test_downloads_need_a_checksum_or_https() {
+        assert!(check_source("https://example.com/libzig.so", None).is_ok());
+        assert!(check_source("http://example.com/libzig.so", None).is_err());
+        assert!(check_source("http://example.com/libzig.so", Some("9f86")).is_ok());
+
+        assert_eq!(
+            with_sha256("http://example.com/libzig.so", Some("9f86")).unwrap(),
+            "http://example.com/libzig.so#sha256=9f86"
+        );
+        assert!(with_sha256("http://example.com/libzig.so#sha256=9f86", Some("9f86")).is_err());
+        assert!(with_sha256("wasmzig", Some("9f86")).is_err());
+        assert_eq!(with_sha256("wasmzig", None).unwrap(), "wasmzig");
+    }
+
+    #[test]
+    fn test_library_url() {
+        assert_eq!(
+            library_url("https://example.com/releases/libwasmzig.so"),
+            Some(("https://example.com/releases/libwasmzig.so", None))
+        );
+        assert_eq!(
+            library_url("https://example.com/libwasmzig.dylib?raw=1#sha256=ABC"),
+            Some(("https://example.com/libwasmzig.dylib?raw=1", Some("ABC")))
+        );
+        assert_eq!(library_url("https://example.com/wasmzig.tar.gz"), None);
+        assert_eq!(library_url("./libwasmzig.so"), None);
+        assert_eq!(library_url("wasmzig"), None);
+    }
+}
@@ -0,0 +1,303 @@
+//! Events any plugin can subscribe to (plugin API v3)
+//!
+//! Lifecycle hooks (see [`hooks`](super::hooks)) only reach the plugin that
+//! builds the project, and run inline. The event bus instead tells every
+//! enabled plugin that subscribes what happens during a run: builds starting
+//! and finishing, a new build reaching open pages, pages connecting and the
+//! requests the dev server answers. That is enough for notifiers, metrics
+//! exporters or post-processors of build artifacts that live outside wasmrun.
+//!
+//! Built-in plugins override [`Plugin::subscriptions`] and
+//! [`Plugin::on_event`]. External plugins list the events in their metadata:
+//!
+//! ```toml
+//! [package.metadata.wasm_plugin.hooks]
+//! subscribe = ["build_finished", "request_served"]
+//! ```
+//!
+//! and are run as `<plugin> event <name>` with the [`Event`] as JSON on stdin.
+//!
+//! Events are delivered in order on a thread of their own, so a slow
+//! subscriber never holds up a build or a response. When subscribers fall
+//! [`QUEUE`] events behind, newer events are dropped until they catch up.
+
+use super::Plugin;
+use crate::warn_println;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::OnceLock;
+
+/// Every event a plugin can subscribe to, by the name used in plugin metadata
+pub const EVENTS: &[&str] = &[
+    "build_started",
+    "build_finished",
+    "artifact_swapped",
+    "client_connected",
+    "request_served",
+];
+
+/// Events waiting for delivery before newer ones are dropped
+pub const QUEUE: usize = 256;
+
+static BUS: OnceLock<Option<Bus>> = OnceLock::new();
+
+/// Whether dropping events was reported
+static DROPPED: AtomicBool = AtomicBool::new(false);
+
+/// Something that happened during a run, as sent to external plugins
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    BuildStarted {
+        project_path: String,
+    },
+    BuildFinished {
+        project_path: String,
+        builder: String,
+        success: bool,
+        duration_ms: u64,
+        /// The built module, when the build succeeded
+        output_path: Option<String>,
+        error: Option<String>,
+    },
+    /// Open pages were told to pick up a new build of `wasm_path`
+    ArtifactSwapped {
+        wasm_path: String,
+        /// Whether only the JS glue changed and was swapped without a reload
+        glue_only: bool,
+    },
+    /// A browser loaded the page
+    ClientConnected {
+        client: String,
+        url: String,
+        user_agent: Option<String>,
+    },
+    RequestServed {
+        client: String,
+        method: String,
+        url: String,
+        status: u16,
+        bytes: Option<usize>,
+    },
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::BuildStarted { .. } => "build_started",
+            Event::BuildFinished { .. } => "build_finished",
+            Event::ArtifactSwapped { .. } => "artifact_swapped",
+            Event::ClientConnected { .. } => "client_connected",
+            Event::RequestServed { .. } => "request_served",
+        }
+    }
+}
+
+/// A plugin and the events it asked for
+struct Subscriber {
+    plugin: &'static dyn Plugin,
+    events: Vec<String>,
+}
+
+impl Subscriber {
+    fn wants(&self, name: &str) -> bool {
+        self.events.iter().any(|event| event == name)
+    }
+}
+
+/// The queue to the delivery thread, and which events anyone subscribed to
+struct Bus {
+    sender: SyncSender<Event>,
+    wanted: Vec<String>,
+}
+
+impl Bus {
+    /// Start delivering to `subscribers`; `None` when there are none
+    fn start(subscribers: Vec<Subscriber>) -> Option<Self> {
+        if subscribers.is_empty() {
+            return None;
+        }
+        let mut wanted: Vec<String> = subscribers
+            .iter()
+            .flat_map(|subscriber| subscriber.events.iter().cloned())
+            .collect();
+        wanted.sort_unstable();
+        wanted.dedup();
+
+        let (sender, receiver) = mpsc::sync_channel::<Event>(QUEUE);
+        std::thread::Builder::new()
+            .name("wasmrun-events".to_string())
+            .spawn(move || {
+                for event in receiver {
+                    for subscriber in subscribers.iter().filter(|s| s.wants(event.name())) {
+                        subscriber.plugin.on_event(&event);
+                    }
+                }
+            })
+            .ok()?;
+        Some(Self { sender, wanted })
+    }
+
+    fn send(&self, event: Event) {
+        if !self.wanted.iter().any(|name| name == event.name()) {
+            return;
+        }
+        if let Err(TrySendError::Full(_)) = self.sender.try_send(event) {
+            if !DROPPED.swap(true, Ordering::Relaxed) {
+                warn_println!("Plugins are not keeping up with events, dropping some");
+            }
+        }
+    }
+}
+
+/// The bus to the enabled plugins that subscribe to something, started on
+/// the first event
+fn bus() -> Option<&'static Bus> {
+    BUS.get_or_init(|| {
+        let manager = super::hooks::manager()?;
+        let subscribers = manager
+            .get_builtin_plugins()
+            .iter()
+            .chain(manager.get_external_plugins().values())
+            .filter(|plugin| manager.is_plugin_enabled(&plugin.info().name))
+            .filter_map(|plugin| {
+                let events = plugin.subscriptions();
+                (!events.is_empty()).then(|| Subscriber {
+                    plugin: plugin.as_ref(),
+                    events,
+                })
+            })
+            .collect();
+        Bus::start(subscribers)
+    })
+    .as_ref()
+}
+
+/// Tell the plugins subscribed to `event`
+pub fn publish(event: Event) {
+    if let Some(bus) = bus() {
+        bus.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::builder::WasmBuilder;
+    use crate::plugin::{PluginCapabilities, PluginInfo, PluginType};
+    use std::sync::mpsc::Receiver;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    struct Recorder {
+        info: PluginInfo,
+        events: Vec<String>,
+        seen: Mutex<mpsc::Sender<String>>,
+    }
+
+    impl Plugin for Recorder {
+        fn info(&self) -> &PluginInfo {
+            &self.info
+        }
+
+        fn can_handle_project(&self, _project_path: &str) -> bool {
+            false
+        }
+
+        fn get_builder(&self) -> Box<dyn WasmBuilder> {
+            unreachable!("the recorder builds nothing")
+        }
+
+        fn subscriptions(&self) -> Vec<String> {
+            self.events.clone()
+        }
+
+        fn on_event(&self, event: &Event) {
+            let _ = self.seen.lock().unwrap().send(event.name().to_string());
+        }
+    }
+
+    fn recorder(events: &[&str]) -> (Subscriber, Receiver<String>) {
+        let (sender, receiver) = mpsc::channel();
+        let plugin = Box::leak(Box::new(Recorder {
+            info: PluginInfo {
+                name: "recorder".to_string(),
+                version: "0.1.0".to_string(),
+                description: String::new(),
+                author: String::new(),
+                extensions: vec![],
+                entry_files: vec![],
+                plugin_type: PluginType::Builtin,
+                source: None,
+                dependencies: vec![],
+                capabilities: PluginCapabilities::default(),
+            },
+            events: events.iter().map(|event| event.to_string()).collect(),
+            seen: Mutex::new(sender),
+        }));
+        let subscriber = Subscriber {
+            plugin,
+            events: plugin.subscriptions(),
+        };
+        (subscriber, receiver)
+    }
+
+    #[test]
+    fn test_subscribers_get_only_their_events_in_order() {
+        assert!(Bus::start(vec![]).is_none());
+
+        let (builds, build_events) = recorder(&["build_started", "build_finished"]);
+        let (requests, request_events) = recorder(&["request_served"]);
+        let bus = Bus::start(vec![builds, requests]).unwrap();
+        assert_eq!(
+            bus.wanted,
+            ["build_finished", "build_started", "request_served"]
+        );
+
+        bus.send(Event::ClientConnected {
+            client: "127.0.0.1:5000".to_string(),
+            url: "/".to_string(),
+            user_agent: None,
+        });
+        bus.send(Event::BuildStarted {
+            project_path: "/p".to_string(),
+        });
+        bus.send(Event::RequestServed {
+            client: "127.0.0.1:5000".to_string(),
+            method: "GET".to_string(),
+            url: "/app.wasm".to_string(),
+            status: 200,
+            bytes: Some(4),
+        });
+        bus.send(Event::BuildFinished {
+            project_path: "/p".to_string(),
+            builder: "rust".to_string(),
+            success: true,
+            duration_ms: 12,
+            output_path: Some("/p/app.wasm".to_string()),
+            error: None,
+        });
+
+        let next = |events: &Receiver<String>| events.recv_timeout(Duration::from_secs(5)).ok();
+        assert_eq!(next(&build_events).as_deref(), Some("build_started"));
+        assert_eq!(next(&build_events).as_deref(), Some("build_finished"));
+        assert_eq!(next(&request_events).as_deref(), Some("request_served"));
+        assert!(request_events
+            .recv_timeout(Duration::from_millis(50))
+            .is_err());
+    }
+
+    #[test]
+    fn test_event_json() {
+        let event = Event::ArtifactSwapped {
+            wasm_path: "/p/app.wasm".to_string(),
+            glue_only: false,
+        };
+        assert!(EVENTS.contains(&event.name()));
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"artifact_swapped","wasm_path":"/p/app.wasm","glue_only":false}"#
+        );
+    }
+}
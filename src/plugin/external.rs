@@ -1,10 +1,15 @@
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use std::sync::Arc;
 
 use crate::compiler::builder::{BuildConfig, BuildResult, WasmBuilder};
+use crate::compiler::cargo_features;
 use crate::config::ExternalPluginEntry;
 use crate::error::{CompilationError, CompilationResult, Result, WasmrunError};
-use crate::plugin::metadata::PluginMetadata;
+use crate::plugin::events::Event;
+use crate::plugin::hooks::{HookEvent, RouteRequest, RouteResponse};
+use crate::plugin::metadata::{MetadataHooks, PluginMetadata};
 use crate::plugin::{Plugin, PluginInfo};
 use crate::utils::{PluginUtils, SystemUtils};
 
@@ -67,6 +72,10 @@ impl ExternalPluginWrapper {
                     unsafe {
                         match Library::new(&path) {
                             Ok(library) => {
+                                crate::plugin::dylib::handshake(&library).map_err(|e| {
+                                    WasmrunError::add_context(path.display().to_string(), e)
+                                })?;
+
                                 // Try both old and new API symbols
                                 let has_old_api = library
                                     .get::<symbols::CreateBuilderFn>(b"create_wasm_builder")
@@ -114,6 +123,26 @@ impl ExternalPluginWrapper {
 
         false
     }
+
+    fn hooks(&self) -> MetadataHooks {
+        self.metadata.hooks.clone().unwrap_or_default()
+    }
+
+    /// Send `event` to the plugin when its metadata says it handles it
+    fn run_hook(&self, event: HookEvent) -> Result<()> {
+        if !self.hooks().events.iter().any(|name| name == event.name()) {
+            return Ok(());
+        }
+        run_plugin_command(&self.plugin_name, &["hook", event.name()], &event, false)
+            .map(|_| ())
+            .map_err(|e| {
+                WasmrunError::from(format!(
+                    "Plugin {} {} hook failed: {e}",
+                    self.plugin_name,
+                    event.name()
+                ))
+            })
+    }
 }
 
 impl Plugin for ExternalPluginWrapper {
@@ -158,6 +187,128 @@ impl Plugin for ExternalPluginWrapper {
             self.library.clone(),
         ))
     }
+
+    fn pre_build(&self, project_path: &str) -> Result<()> {
+        self.run_hook(HookEvent::PreBuild {
+            project_path: project_path.to_string(),
+        })
+    }
+
+    fn post_build(&self, project_path: &str, wasm_path: &str) -> Result<()> {
+        self.run_hook(HookEvent::PostBuild {
+            project_path: project_path.to_string(),
+            wasm_path: wasm_path.to_string(),
+        })
+    }
+
+    fn on_serve_start(&self, project_path: &str, url: &str) {
+        let event = HookEvent::OnServeStart {
+            project_path: project_path.to_string(),
+            url: url.to_string(),
+        };
+        if let Err(e) = self.run_hook(event) {
+            eprintln!("⚠️  {e}");
+        }
+    }
+
+    fn on_file_change(&self, project_path: &str, paths: &[&Path]) {
+        let event = HookEvent::OnFileChange {
+            project_path: project_path.to_string(),
+            paths: paths
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect(),
+        };
+        if let Err(e) = self.run_hook(event) {
+            eprintln!("⚠️  {e}");
+        }
+    }
+
+    fn routes(&self) -> Vec<String> {
+        self.hooks().routes
+    }
+
+    fn subscriptions(&self) -> Vec<String> {
+        self.hooks().subscribe
+    }
+
+    fn on_event(&self, event: &Event) {
+        if let Err(e) =
+            run_plugin_command(&self.plugin_name, &["event", event.name()], event, false)
+        {
+            eprintln!(
+                "⚠️  Plugin {} {} event failed: {e}",
+                self.plugin_name,
+                event.name()
+            );
+        }
+    }
+
+    fn handle_route(&self, request: &RouteRequest) -> Option<RouteResponse> {
+        let response = run_plugin_command(&self.plugin_name, &["route"], request, true)
+            .and_then(|stdout| serde_json::from_str(&stdout).map_err(|e| e.to_string()));
+        Some(response.unwrap_or_else(|e| {
+            eprintln!(
+                "⚠️  Plugin {} could not answer {}: {e}",
+                self.plugin_name, request.url
+            );
+            RouteResponse::text(502, format!("Plugin {} failed: {e}", self.plugin_name))
+        }))
+    }
+}
+
+/// The plugin's executable: in ~/.wasmrun/bin when installed there, else
+/// looked up on the system PATH
+fn plugin_binary(plugin_name: &str) -> String {
+    let wasmrun_bin_path = dirs::home_dir()
+        .map(|home| home.join(".wasmrun").join("bin").join(plugin_name))
+        .unwrap_or_else(|| PathBuf::from(plugin_name));
+
+    if wasmrun_bin_path.exists() {
+        wasmrun_bin_path.to_string_lossy().to_string()
+    } else {
+        plugin_name.to_string()
+    }
+}
+
+/// Run the plugin's executable with `args` and `input` as JSON on stdin; its
+/// stdout when it succeeds. Output goes straight to the terminal unless
+/// `capture` is set.
+fn run_plugin_command(
+    plugin_name: &str,
+    args: &[&str],
+    input: &impl serde::Serialize,
+    capture: bool,
+) -> std::result::Result<String, String> {
+    let input = serde_json::to_vec(input).map_err(|e| e.to_string())?;
+    let output = || {
+        if capture {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        }
+    };
+    let mut child = Command::new(plugin_binary(plugin_name))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(output())
+        .stderr(output())
+        .spawn()
+        .map_err(|e| format!("failed to run {plugin_name}: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A plugin that ignores its input may have exited already
+        let _ = stdin.write_all(&input);
+    }
+    let result = child.wait_with_output().map_err(|e| e.to_string())?;
+    if result.status.success() {
+        Ok(String::from_utf8_lossy(&result.stdout).to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        Err(match stderr.trim() {
+            "" => format!("{plugin_name} exited with {}", result.status),
+            stderr => stderr.to_string(),
+        })
+    }
 }
 
 /// Generic WASM builder for all external plugins
@@ -183,20 +334,18 @@ impl ExternalWasmBuilder {
     }
 
     fn build_via_command(&self, config: &BuildConfig) -> CompilationResult<BuildResult> {
-        // Try to find the plugin binary in ~/.wasmrun/bin first, then fallback to system PATH
-        let wasmrun_bin_path = dirs::home_dir()
-            .map(|home| home.join(".wasmrun").join("bin").join(&self.plugin_name))
-            .unwrap_or_else(|| PathBuf::from(&self.plugin_name));
-
-        let plugin_binary = if wasmrun_bin_path.exists() {
-            wasmrun_bin_path.to_string_lossy().to_string()
-        } else {
-            self.plugin_name.clone()
-        };
+        let plugin_binary = plugin_binary(&self.plugin_name);
 
         let output = std::process::Command::new(&plugin_binary)
             .args(["compile", "-p", &config.project_path])
             .args(["-o", &config.output_dir])
+            .args(
+                config
+                    .cargo_target
+                    .iter()
+                    .flat_map(|target| target.cargo_args()),
+            )
+            .args(cargo_features::cargo_args(&config.cargo_features))
             .output();
 
         match output {
@@ -495,6 +644,7 @@ mod tests {
             },
             exports: None,
             frameworks: None,
+            hooks: None,
         }
     }
 
@@ -680,6 +830,8 @@ mod tests {
             verbose: false,
             watch: false,
             target_type: crate::compiler::builder::TargetType::Standard,
+            cargo_target: None,
+            cargo_features: Vec::new(),
         };
 
         let result = builder.build(&config);
@@ -792,6 +944,8 @@ mod tests {
                 verbose: false,
                 watch: false,
                 target_type: crate::compiler::builder::TargetType::Standard,
+                cargo_target: None,
+                cargo_features: Vec::new(),
             },
             BuildConfig {
                 project_path: temp_dir.path().to_str().unwrap().to_string(),
@@ -800,6 +954,8 @@ mod tests {
                 verbose: true,
                 watch: true,
                 target_type: crate::compiler::builder::TargetType::Standard,
+                cargo_target: None,
+                cargo_features: Vec::new(),
             },
         ];
 
@@ -0,0 +1,248 @@
+//! Lifecycle hooks and HTTP routes contributed by plugins (plugin API v2)
+//!
+//! Besides building, the plugin that builds a project can run code before and
+//! after each build, when the dev server starts and when the watcher sees
+//! changes, and can answer requests under URL prefixes of its own. Built-in
+//! plugins override the hook methods of [`Plugin`](super::Plugin). External
+//! plugins list what they implement in their metadata:
+//!
+//! ```toml
+//! [package.metadata.wasm_plugin.hooks]
+//! events = ["pre_build", "post_build", "on_serve_start", "on_file_change"]
+//! routes = ["/__wasmrust/"]
+//! ```
+//!
+//! and are run as `<plugin> hook <event>` with the [`HookEvent`] as JSON on
+//! stdin, or as `<plugin> route` with a [`RouteRequest`] on stdin and a
+//! [`RouteResponse`] expected on stdout.
+
+use super::manager::PluginManager;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+/// Every event a plugin can hook, by the name used in plugin metadata
+pub const EVENTS: &[&str] = &[
+    "pre_build",
+    "post_build",
+    "on_serve_start",
+    "on_file_change",
+];
+
+static MANAGER: OnceLock<Option<PluginManager>> = OnceLock::new();
+
+/// The plugin serving requests under its routes, once the server has started
+static SERVING: RwLock<Option<Serving>> = RwLock::new(None);
+
+#[derive(Debug, Clone)]
+struct Serving {
+    plugin: String,
+    routes: Vec<String>,
+}
+
+/// A lifecycle event, as sent to external plugins
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HookEvent {
+    PreBuild {
+        project_path: String,
+    },
+    PostBuild {
+        project_path: String,
+        wasm_path: String,
+    },
+    OnServeStart {
+        project_path: String,
+        url: String,
+    },
+    OnFileChange {
+        project_path: String,
+        /// Changed paths relative to the project
+        paths: Vec<String>,
+    },
+}
+
+impl HookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::PreBuild { .. } => "pre_build",
+            HookEvent::PostBuild { .. } => "post_build",
+            HookEvent::OnServeStart { .. } => "on_serve_start",
+            HookEvent::OnFileChange { .. } => "on_file_change",
+        }
+    }
+}
+
+/// A request for one of a plugin's routes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteRequest {
+    pub method: String,
+    /// Path and query, below the server's base path
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// A plugin's answer to a [`RouteRequest`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteResponse {
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: String,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+impl RouteResponse {
+    /// A plain text response
+    pub fn text(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            headers: BTreeMap::from([("Content-Type".to_string(), "text/plain".to_string())]),
+            body: body.into(),
+        }
+    }
+}
+
+/// The plugins, loaded once for the hooks and events of this run
+pub(super) fn manager() -> Option<&'static PluginManager> {
+    MANAGER.get_or_init(|| PluginManager::new().ok()).as_ref()
+}
+
+/// Run the `pre_build` hook of the plugin building `project_path`, once the
+/// build is announced on the event bus
+pub fn pre_build(project_path: &str) -> Result<()> {
+    super::events::publish(super::events::Event::BuildStarted {
+        project_path: project_path.to_string(),
+    });
+    match manager() {
+        Some(manager) => manager.dispatch_pre_build(project_path),
+        None => Ok(()),
+    }
+}
+
+/// Run the `post_build` hook of the plugin that built `wasm_path`
+pub fn post_build(project_path: &str, wasm_path: &str) -> Result<()> {
+    match manager() {
+        Some(manager) => manager.dispatch_post_build(project_path, wasm_path),
+        None => Ok(()),
+    }
+}
+
+/// Tell the plugin building `project_path` that its server listens at `url`,
+/// and route requests under its prefixes to it from now on
+pub fn serve_start(project_path: Option<&str>, url: &str) {
+    let (Some(project_path), Some(manager)) = (project_path, manager()) else {
+        return;
+    };
+    let Some(plugin) = manager.plugin_for_build(project_path) else {
+        return;
+    };
+    let routes = plugin.routes();
+    for route in &routes {
+        println!(
+            "🔌 Plugin {} serves {}{}",
+            plugin.info().name,
+            url.trim_end_matches('/'),
+            route
+        );
+    }
+    if let Ok(mut serving) = SERVING.write() {
+        *serving = Some(Serving {
+            plugin: plugin.info().name.clone(),
+            routes,
+        });
+    }
+    manager.dispatch_serve_start(project_path, url);
+}
+
+/// Run the `on_file_change` hook of the plugin building `project_path`
+pub fn file_change(project_path: &str, paths: &[&Path]) {
+    if let Some(manager) = manager() {
+        manager.dispatch_file_change(project_path, paths);
+    }
+}
+
+/// Whether a plugin serves `url`
+pub fn is_routed(url: &str) -> bool {
+    SERVING.read().is_ok_and(|serving| {
+        serving
+            .as_ref()
+            .is_some_and(|serving| matching_route(&serving.routes, url).is_some())
+    })
+}
+
+/// Answer `request` with the plugin serving its URL; `None` when no plugin does
+pub fn handle_route(request: &RouteRequest) -> Option<RouteResponse> {
+    let serving = SERVING.read().ok()?.clone()?;
+    matching_route(&serving.routes, &request.url)?;
+    let plugin = manager()?.find_plugin_by_name(&serving.plugin)?;
+    Some(
+        plugin
+            .handle_route(request)
+            .unwrap_or_else(|| RouteResponse::text(404, "Not found")),
+    )
+}
+
+/// The longest of `routes` that `url` falls under
+fn matching_route<'a>(routes: &'a [String], url: &str) -> Option<&'a str> {
+    let path = url.split('?').next().unwrap_or(url);
+    routes
+        .iter()
+        .filter(|route| {
+            path.strip_prefix(route.trim_end_matches('/'))
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+        .map(String::as_str)
+        .max_by_key(|route| route.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json() {
+        let event = HookEvent::OnFileChange {
+            project_path: "/p".to_string(),
+            paths: vec!["src/lib.rs".to_string()],
+        };
+        assert_eq!(event.name(), "on_file_change");
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"on_file_change","project_path":"/p","paths":["src/lib.rs"]}"#
+        );
+        let pre_build = HookEvent::PreBuild {
+            project_path: "/p".to_string(),
+        };
+        assert!(EVENTS.contains(&pre_build.name()));
+    }
+
+    #[test]
+    fn test_route_response_defaults() {
+        let response: RouteResponse = serde_json::from_str(r#"{"body":"hi"}"#).unwrap();
+        assert_eq!(response.status, 200);
+        assert!(response.headers.is_empty());
+        assert_eq!(response.body, "hi");
+    }
+
+    #[test]
+    fn test_matching_route() {
+        let routes = vec!["/__wasmrust/".to_string(), "/api/go".to_string()];
+        assert_eq!(
+            matching_route(&routes, "/__wasmrust/docs?x=1"),
+            Some("/__wasmrust/")
+        );
+        assert_eq!(matching_route(&routes, "/__wasmrust"), Some("/__wasmrust/"));
+        assert_eq!(matching_route(&routes, "/api/go/status"), Some("/api/go"));
+        assert_eq!(matching_route(&routes, "/api/gopher"), None);
+        assert_eq!(matching_route(&routes, "/index.html"), None);
+    }
+}
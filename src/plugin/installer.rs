@@ -1,6 +1,6 @@
 use crate::error::{Result, WasmrunError};
 use crate::plugin::registry::PluginRegistry;
-use crate::utils::{PluginUtils, SystemUtils};
+use crate::utils::{CommandExecutor, DryRun, PluginUtils, SystemUtils};
 use std::path::{Path, PathBuf};
 
 pub struct PluginInstaller;
@@ -38,6 +38,8 @@ impl PluginInstaller {
     pub fn install_external_plugin(plugin_name: &str) -> Result<InstallationResult> {
         let mut result = InstallationResult::new(plugin_name);
 
+        crate::offline::require_network(format!("Installing plugin '{plugin_name}'"))?;
+
         if !Self::is_supported_plugin(plugin_name) {
             return Err(WasmrunError::from(format!(
                 "Plugin '{plugin_name}' not found or not a valid WebAssembly plugin"
@@ -94,6 +96,37 @@ impl PluginInstaller {
         Ok(result)
     }
 
+    /// Add the steps [`install_external_plugin`](Self::install_external_plugin)
+    /// would take to `plan`
+    pub fn plan_install(plugin_name: &str, plan: &mut DryRun) -> Result<()> {
+        let plugin_dir = PluginUtils::get_plugin_directory(plugin_name)?;
+        plan.note(format!("look up {plugin_name} on crates.io"));
+        if Self::is_plugin_library_installed(plugin_name) {
+            plan.note(format!(
+                "keep the library files already in {}",
+                plugin_dir.display()
+            ));
+            return Ok(());
+        }
+
+        let wasmrun_root = PluginUtils::get_wasmrun_directory()?;
+        plan.create_dir(&plugin_dir);
+        let root = wasmrun_root.to_string_lossy();
+        plan.run(
+            std::env::current_dir().unwrap_or_default(),
+            CommandExecutor::display_command(
+                "cargo",
+                &["install", plugin_name, "--root", &root, "--features", "cli"],
+            ),
+        );
+        plan.write(plugin_dir.join(".wasmrun_metadata"));
+        plan.note(format!(
+            "if {plugin_name} has no binary, build it as a library in {} instead",
+            plugin_dir.display()
+        ));
+        Ok(())
+    }
+
     pub fn update_plugin_metadata(plugin_name: &str, new_version: &str) -> Result<()> {
         if let Ok(plugin_dir) = PluginUtils::get_plugin_directory(plugin_name) {
             PluginUtils::create_metadata_file(plugin_name, &plugin_dir, new_version)?;
@@ -141,6 +174,8 @@ impl PluginInstaller {
 
     #[allow(dead_code)]
     pub fn update_generic_plugin(plugin_name: &str) -> Result<()> {
+        crate::offline::require_network(format!("Updating plugin '{plugin_name}'"))?;
+
         println!("🔄 Updating {plugin_name}...");
 
         let plugin_dir = PluginUtils::get_plugin_directory(plugin_name)?;
@@ -630,7 +665,8 @@ pub extern "C" fn can_handle_project(path: *const std::ffi::c_char) -> bool {{
                 match chars.next() {
                     None => String::new(),
                     Some(first) => {
-                        first.to_uppercase().collect::<String>() + &chars.as_str().to_lowercase()
+                        first.to_uppercase().collect::<String>()
+                            + chars.as_str().to_lowercase().as_str()
                     }
                 }
             })
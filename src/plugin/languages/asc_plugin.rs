@@ -0,0 +1,560 @@
+//! AssemblyScript projects built with `asc`, without an external plugin
+//!
+//! A directory with an `asconfig.json`, or a `package.json` depending on
+//! `assemblyscript`, is compiled with the project's own `asc` from
+//! `node_modules/.bin`, or a global one. The `asconfig.json` target matching
+//! the optimization level is used when it has one, with the output redirected
+//! to the output directory and ESM bindings, which the server loads the module
+//! through. Without `asc` an `asbuild` script in `package.json` is run instead,
+//! and the module it writes is picked up from the target's `outFile`. An
+//! installed `wasmasc` plugin still takes precedence.
+
+use crate::compiler::builder::{BuildConfig, BuildResult, OptimizationLevel, WasmBuilder};
+use crate::error::{CompilationError, CompilationResult};
+use crate::plugin::{Plugin, PluginCapabilities, PluginInfo, PluginType};
+use crate::utils::{CommandExecutor, PathResolver};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const LANGUAGE: &str = "AssemblyScript";
+
+/// The entry `asinit` scaffolds, used when `asconfig.json` names none
+const DEFAULT_ENTRY: &str = "assembly/index.ts";
+
+/// The `package.json` script `asinit` adds for building both targets
+const BUILD_SCRIPT: &str = "asbuild";
+
+/// What the build needs from `asconfig.json`
+#[derive(Debug, Default, Deserialize)]
+struct AsConfig {
+    #[serde(default)]
+    entries: Vec<String>,
+    #[serde(default)]
+    targets: BTreeMap<String, AsTarget>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsTarget {
+    out_file: Option<String>,
+}
+
+impl AsConfig {
+    fn load(project_path: &str) -> Self {
+        fs::read_to_string(Path::new(project_path).join("asconfig.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Where the target for `level` writes its module, relative to the project
+    fn out_file(&self, level: &OptimizationLevel) -> Option<&str> {
+        self.targets.get(target_name(level))?.out_file.as_deref()
+    }
+}
+
+/// What the build needs from `package.json`
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PackageJson {
+    name: Option<String>,
+    #[serde(default)]
+    scripts: BTreeMap<String, String>,
+    #[serde(default)]
+    dependencies: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    dev_dependencies: BTreeMap<String, serde_json::Value>,
+}
+
+impl PackageJson {
+    fn load(project_path: &str) -> Option<Self> {
+        let content = fs::read_to_string(Path::new(project_path).join("package.json")).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn depends_on_assemblyscript(&self) -> bool {
+        self.dependencies.contains_key("assemblyscript")
+            || self.dev_dependencies.contains_key("assemblyscript")
+    }
+
+    /// The package name without its scope, fit for a file name
+    fn module_name(&self) -> Option<String> {
+        let name = self.name.as_deref()?;
+        let name = name.rsplit('/').next()?.trim();
+        (!name.is_empty()).then(|| name.to_string())
+    }
+}
+
+/// Whether `project_path` holds an AssemblyScript project
+pub fn is_assemblyscript_project(project_path: &str) -> bool {
+    Path::new(project_path).join("asconfig.json").is_file()
+        || PackageJson::load(project_path)
+            .is_some_and(|package| package.depends_on_assemblyscript())
+}
+
+/// The `asconfig.json` target for an optimization level
+fn target_name(level: &OptimizationLevel) -> &'static str {
+    match level {
+        OptimizationLevel::Debug => "debug",
+        OptimizationLevel::Release | OptimizationLevel::Size => "release",
+    }
+}
+
+/// The project's own `asc`, else one on the `PATH`
+fn asc_command(project_path: &str) -> Option<String> {
+    let name = if cfg!(target_os = "windows") {
+        "asc.cmd"
+    } else {
+        "asc"
+    };
+    let local = Path::new(project_path)
+        .join("node_modules")
+        .join(".bin")
+        .join(name);
+    if local.is_file() {
+        Some(local.to_string_lossy().to_string())
+    } else {
+        CommandExecutor::is_tool_installed("asc").then(|| "asc".to_string())
+    }
+}
+
+/// Arguments compiling `entries` into `output` with bindings next to it
+fn build_args(
+    config: &BuildConfig,
+    asconfig: &AsConfig,
+    entries: &[String],
+    output: &str,
+) -> Vec<String> {
+    let mut args = entries.to_vec();
+    let target = target_name(&config.optimization_level);
+    if asconfig.targets.contains_key(target) {
+        args.extend(["--target".to_string(), target.to_string()]);
+    }
+    args.extend([
+        "--outFile".to_string(),
+        output.to_string(),
+        "--bindings".to_string(),
+        "esm".to_string(),
+    ]);
+    let optimization: &[&str] = match config.optimization_level {
+        OptimizationLevel::Debug => &["--debug"],
+        OptimizationLevel::Release => &["--optimize"],
+        OptimizationLevel::Size => &["--optimizeLevel", "3", "--shrinkLevel", "2"],
+    };
+    args.extend(optimization.iter().map(|arg| arg.to_string()));
+    args
+}
+
+/// Bindings for a module built without them, in the shape asc writes,
+/// providing only the imports the AssemblyScript runtime itself needs
+const GENERATED_GLUE: &str = r#"// Written by wasmrun for an AssemblyScript module built without --bindings
+let memory;
+function __liftString(pointer) {
+  if (!pointer) return null;
+  const end = (pointer + new Uint32Array(memory.buffer)[(pointer - 4) >>> 2]) >>> 1;
+  return String.fromCharCode(...new Uint16Array(memory.buffer).subarray(pointer >>> 1, end));
+}
+async function instantiate(module, imports = {}) {
+  const adaptedImports = {
+    env: Object.assign(Object.create(globalThis), imports.env || {}, {
+      abort(message, fileName, lineNumber, columnNumber) {
+        throw Error(`${__liftString(message)} in ${__liftString(fileName)}:${lineNumber}:${columnNumber}`);
+      },
+      trace(message, n, ...args) {
+        console.log(`trace: ${__liftString(message)}${n ? " " : ""}${args.slice(0, n).join(", ")}`);
+      },
+      seed() {
+        return Date.now() * Math.random();
+      },
+    }),
+  };
+  const { exports } = await WebAssembly.instantiate(module, adaptedImports);
+  memory = exports.memory;
+  exports._start?.();
+  return exports;
+}
+export default await instantiate(await WebAssembly.compileStreaming(fetch(new URL(__MODULE__, import.meta.url))));
+"#;
+
+/// AssemblyScript WebAssembly plugin
+#[derive(Clone)]
+pub struct AscPlugin {
+    info: PluginInfo,
+}
+
+impl AscPlugin {
+    pub fn new() -> Self {
+        let info = PluginInfo {
+            name: "asc".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            description: "AssemblyScript WebAssembly compiler using asc".to_string(),
+            author: "Wasmrun Team".to_string(),
+            extensions: vec!["ts".to_string()],
+            entry_files: vec!["asconfig.json".to_string(), DEFAULT_ENTRY.to_string()],
+            plugin_type: PluginType::Builtin,
+            source: None,
+            dependencies: vec![],
+            capabilities: PluginCapabilities {
+                compile_wasm: true,
+                compile_webapp: false,
+                live_reload: true,
+                optimization: true,
+                custom_targets: vec!["wasm".to_string()],
+                supported_languages: Some(vec!["assemblyscript".to_string()]),
+            },
+        };
+
+        Self { info }
+    }
+
+    fn failed(reason: impl Into<String>) -> CompilationError {
+        CompilationError::BuildFailed {
+            language: LANGUAGE.to_string(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Entries to pass to `asc`; none when `asconfig.json` lists them, since
+    /// `asc` reads them from there
+    fn entries(project_path: &str, asconfig: &AsConfig) -> CompilationResult<Vec<String>> {
+        if !asconfig.entries.is_empty() {
+            return Ok(Vec::new());
+        }
+        if Path::new(project_path).join(DEFAULT_ENTRY).is_file() {
+            return Ok(vec![DEFAULT_ENTRY.to_string()]);
+        }
+        Err(CompilationError::MissingEntryFile {
+            language: LANGUAGE.to_string(),
+            candidates: vec![DEFAULT_ENTRY.to_string()],
+        })
+    }
+
+    fn build_with_asc(
+        asc: &str,
+        config: &BuildConfig,
+        output_dir: &Path,
+    ) -> CompilationResult<PathBuf> {
+        let asconfig = AsConfig::load(&config.project_path);
+        let entries = Self::entries(&config.project_path, &asconfig)?;
+        let name = PackageJson::load(&config.project_path)
+            .and_then(|package| package.module_name())
+            .unwrap_or_else(|| "module".to_string());
+        let wasm_path = output_dir.join(format!("{name}.wasm"));
+
+        println!("🔨 Building with asc...");
+        let args = build_args(config, &asconfig, &entries, &wasm_path.to_string_lossy());
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let output =
+            CommandExecutor::execute_command(asc, &args, &config.project_path, config.verbose)?;
+        if !output.status.success() {
+            return Err(Self::failed(format!(
+                "asc failed: {}{}",
+                String::from_utf8_lossy(&output.stderr),
+                String::from_utf8_lossy(&output.stdout)
+            )));
+        }
+        Ok(wasm_path)
+    }
+
+    /// Run the project's `asbuild` script and copy the module it wrote
+    fn build_with_script(config: &BuildConfig, output_dir: &Path) -> CompilationResult<PathBuf> {
+        println!("🔨 Building with npm run {BUILD_SCRIPT}...");
+        let output = CommandExecutor::execute_command(
+            "npm",
+            &["run", BUILD_SCRIPT],
+            &config.project_path,
+            config.verbose,
+        )?;
+        if !output.status.success() {
+            return Err(Self::failed(format!(
+                "npm run {BUILD_SCRIPT} failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        let asconfig = AsConfig::load(&config.project_path);
+        let out_file = asconfig
+            .out_file(&config.optimization_level)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("build/{}.wasm", target_name(&config.optimization_level)));
+        let built = Path::new(&config.project_path).join(&out_file);
+        if !built.is_file() {
+            return Err(Self::failed(format!(
+                "npm run {BUILD_SCRIPT} completed but {out_file} was not created"
+            )));
+        }
+        let wasm_path = output_dir.join(built.file_name().unwrap_or_default());
+        fs::copy(&built, &wasm_path)
+            .map_err(|e| Self::failed(format!("copying {out_file}: {e}")))?;
+        let bindings = built.with_extension("js");
+        if bindings.is_file() {
+            fs::copy(&bindings, wasm_path.with_extension("js"))
+                .map_err(|e| Self::failed(format!("copying {}: {e}", bindings.display())))?;
+        }
+        Ok(wasm_path)
+    }
+
+    /// The bindings next to `wasm_path`, written by asc or else by us
+    fn bindings_for(wasm_path: &Path) -> CompilationResult<String> {
+        let bindings = wasm_path.with_extension("js");
+        if !bindings.is_file() {
+            let module =
+                serde_json::to_string(&wasm_path.file_name().unwrap_or_default().to_string_lossy())
+                    .unwrap_or_default();
+            fs::write(&bindings, GENERATED_GLUE.replace("__MODULE__", &module))
+                .map_err(|e| Self::failed(format!("writing {}: {e}", bindings.display())))?;
+        }
+        Ok(bindings.to_string_lossy().to_string())
+    }
+}
+
+impl Plugin for AscPlugin {
+    fn info(&self) -> &PluginInfo {
+        &self.info
+    }
+
+    fn can_handle_project(&self, project_path: &str) -> bool {
+        is_assemblyscript_project(project_path)
+    }
+
+    fn get_builder(&self) -> Box<dyn WasmBuilder> {
+        Box::new(AscPlugin::new())
+    }
+}
+
+impl WasmBuilder for AscPlugin {
+    fn supported_extensions(&self) -> &[&str] {
+        &["ts"]
+    }
+
+    fn entry_file_candidates(&self) -> &[&str] {
+        &["asconfig.json", DEFAULT_ENTRY]
+    }
+
+    fn language_name(&self) -> &str {
+        LANGUAGE
+    }
+
+    fn check_dependencies(&self) -> Vec<String> {
+        let project_path = BuildConfig::default().project_path;
+        if asc_command(&project_path).is_some() || CommandExecutor::is_tool_installed("npm") {
+            return vec![];
+        }
+        vec!["asc (npm install --save-dev assemblyscript)".to_string()]
+    }
+
+    fn validate_project(&self, project_path: &str) -> CompilationResult<()> {
+        PathResolver::validate_directory_exists(project_path).map_err(|e| {
+            CompilationError::InvalidProjectStructure {
+                language: LANGUAGE.to_string(),
+                reason: format!("Project directory validation failed: {e}"),
+            }
+        })?;
+
+        if !is_assemblyscript_project(project_path) {
+            return Err(CompilationError::InvalidProjectStructure {
+                language: LANGUAGE.to_string(),
+                reason: "No asconfig.json or assemblyscript dependency found; set one up with `npx asinit .`"
+                    .to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn generated_paths(&self, project_path: &str) -> Vec<String> {
+        // Where the `asbuild` script writes, bindings and their .d.ts included
+        let asconfig = AsConfig::load(project_path);
+        let mut paths: Vec<String> = asconfig
+            .targets
+            .values()
+            .filter_map(|target| Path::new(target.out_file.as_deref()?).parent())
+            .map(|dir| dir.to_string_lossy().to_string())
+            .filter(|dir| !dir.is_empty())
+            .map(|dir| format!("/{dir}/"))
+            .collect();
+        if paths.is_empty() {
+            paths.push("/build/".to_string());
+        }
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    fn build(&self, config: &BuildConfig) -> CompilationResult<BuildResult> {
+        PathResolver::ensure_output_directory(&config.output_dir).map_err(|_| {
+            CompilationError::OutputDirectoryCreationFailed {
+                path: config.output_dir.clone(),
+            }
+        })?;
+        let output_dir = fs::canonicalize(&config.output_dir)
+            .unwrap_or_else(|_| PathBuf::from(&config.output_dir));
+
+        let has_script = PackageJson::load(&config.project_path)
+            .is_some_and(|package| package.scripts.contains_key(BUILD_SCRIPT));
+        let wasm_path = match asc_command(&config.project_path) {
+            Some(asc) => Self::build_with_asc(&asc, config, &output_dir)?,
+            None if has_script && CommandExecutor::is_tool_installed("npm") => {
+                Self::build_with_script(config, &output_dir)?
+            }
+            None => {
+                return Err(CompilationError::BuildToolNotFound {
+                    tool: "asc (npm install --save-dev assemblyscript)".to_string(),
+                    language: LANGUAGE.to_string(),
+                })
+            }
+        };
+        if !wasm_path.is_file() {
+            return Err(Self::failed(format!(
+                "build completed but {} was not created",
+                wasm_path.display()
+            )));
+        }
+
+        Ok(BuildResult {
+            js_path: Some(Self::bindings_for(&wasm_path)?),
+            wasm_path: wasm_path.to_string_lossy().to_string(),
+            additional_files: vec![],
+            is_wasm_bindgen: false,
+        })
+    }
+
+    fn can_handle_project(&self, project_path: &str) -> bool {
+        is_assemblyscript_project(project_path)
+    }
+
+    fn planned_commands(&self, config: &BuildConfig) -> Vec<String> {
+        let output_dir = fs::canonicalize(&config.output_dir)
+            .unwrap_or_else(|_| PathBuf::from(&config.output_dir));
+        let asconfig = AsConfig::load(&config.project_path);
+        let Some(asc) = asc_command(&config.project_path) else {
+            let out_file = asconfig
+                .out_file(&config.optimization_level)
+                .map(str::to_string)
+                .unwrap_or_else(|| {
+                    format!("build/{}.wasm", target_name(&config.optimization_level))
+                });
+            return vec![
+                CommandExecutor::display_command("npm", &["run", BUILD_SCRIPT]),
+                CommandExecutor::display_command(
+                    "cp",
+                    &[out_file.as_str(), &output_dir.to_string_lossy()],
+                ),
+            ];
+        };
+        let Ok(entries) = Self::entries(&config.project_path, &asconfig) else {
+            return Vec::new();
+        };
+        let name = PackageJson::load(&config.project_path)
+            .and_then(|package| package.module_name())
+            .unwrap_or_else(|| "module".to_string());
+        let wasm_path = output_dir.join(format!("{name}.wasm"));
+        vec![CommandExecutor::display_command(
+            &asc,
+            &build_args(config, &asconfig, &entries, &wasm_path.to_string_lossy()),
+        )]
+    }
+
+    fn clean(&self, _project_path: &str) -> crate::error::Result<()> {
+        Ok(())
+    }
+
+    fn clone_box(&self) -> Box<dyn WasmBuilder> {
+        Box::new(self.clone())
+    }
+}
+
+impl Default for AscPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_projects_are_recognised() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "web", "dependencies": {"lit": "3"}}"#,
+        )
+        .unwrap();
+        assert!(!is_assemblyscript_project(path));
+
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "@me/game", "devDependencies": {"assemblyscript": "^0.27"}}"#,
+        )
+        .unwrap();
+        assert!(is_assemblyscript_project(path));
+        assert_eq!(
+            PackageJson::load(path).unwrap().module_name().as_deref(),
+            Some("game")
+        );
+    }
+
+    #[test]
+    fn test_build_args() {
+        let asconfig: AsConfig = serde_json::from_str(
+            r#"{"targets": {"debug": {"outFile": "build/debug.wasm"}, "release": {"outFile": "build/release.wasm"}}}"#,
+        )
+        .unwrap();
+        let entries = vec![DEFAULT_ENTRY.to_string()];
+        assert_eq!(
+            build_args(
+                &BuildConfig::default(),
+                &asconfig,
+                &entries,
+                "out/game.wasm"
+            )
+            .join(" "),
+            "assembly/index.ts --target release --outFile out/game.wasm --bindings esm --optimize"
+        );
+        let size = BuildConfig {
+            optimization_level: OptimizationLevel::Size,
+            ..BuildConfig::default()
+        };
+        assert_eq!(
+            build_args(&size, &AsConfig::default(), &[], "game.wasm").join(" "),
+            "--outFile game.wasm --bindings esm --optimizeLevel 3 --shrinkLevel 2"
+        );
+        let debug = OptimizationLevel::Debug;
+        assert_eq!(asconfig.out_file(&debug), Some("build/debug.wasm"));
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("asconfig.json"),
+            r#"{"targets": {"release": {"outFile": "dist/release.wasm"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            AscPlugin::new().generated_paths(dir.path().to_str().unwrap()),
+            ["/dist/"]
+        );
+    }
+
+    #[test]
+    fn test_bindings_are_written_when_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let wasm = dir.path().join("game.wasm");
+        fs::write(&wasm, b"\0asm\x01\0\0\0").unwrap();
+        let bindings = AscPlugin::bindings_for(&wasm).unwrap();
+        let source = fs::read_to_string(&bindings).unwrap();
+        assert!(source.contains(r#"new URL("game.wasm", import.meta.url)"#));
+        assert!(crate::server::asc::is_assemblyscript_glue(&source));
+
+        // Bindings asc wrote are kept
+        fs::write(&bindings, "export const add = 1;").unwrap();
+        AscPlugin::bindings_for(&wasm).unwrap();
+        assert_eq!(
+            fs::read_to_string(&bindings).unwrap(),
+            "export const add = 1;"
+        );
+    }
+}
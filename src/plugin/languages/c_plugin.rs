@@ -1,9 +1,70 @@
+//! C and C++ projects built with Emscripten
+//!
+//! A project with a `CMakeLists.txt` is configured with `emcmake cmake` in
+//! `build/emscripten` and built there; one with a Makefile is built with
+//! `emmake make`, so `CC` and `CXX` point at Emscripten. Anything else has its
+//! sources compiled directly with `emcc`, or `em++` when there is C++ among
+//! them. The result is the `.js` + `.wasm` pair Emscripten writes, which the
+//! server runs in its Emscripten shell.
+
 use crate::compiler::builder::{BuildConfig, BuildResult, OptimizationLevel, WasmBuilder};
 use crate::error::{CompilationError, CompilationResult};
 use crate::plugin::{Plugin, PluginCapabilities, PluginInfo, PluginType};
 use crate::utils::{CommandExecutor, PathResolver};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Extensions of C++ sources, which are compiled with `em++`
+const CPP_EXTENSIONS: &[&str] = &["cpp", "cc", "cxx"];
+
+/// Where CMake projects are configured and built, below the project
+const CMAKE_BUILD_DIR: &str = "build/emscripten";
+
+/// A module Emscripten linked, with the files it loads
+#[derive(Debug, PartialEq)]
+struct EmscriptenOutput {
+    wasm: PathBuf,
+    js: PathBuf,
+    /// The preloaded file package, when there is one
+    data: Option<PathBuf>,
+}
+
+/// The newest `.wasm` below `dir` with its Emscripten glue next to it
+fn find_emscripten_output(dir: &Path, depth: usize) -> Option<EmscriptenOutput> {
+    let mut newest: Option<(SystemTime, EmscriptenOutput)> = None;
+    let mut consider = |found: EmscriptenOutput| {
+        let modified = fs::metadata(&found.wasm)
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        if newest.as_ref().is_none_or(|(time, _)| modified > *time) {
+            newest = Some((modified, found));
+        }
+    };
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            // CMake keeps its own files, test programs included, in CMakeFiles
+            let skipped = path.file_name().is_some_and(|name| name == "CMakeFiles");
+            if depth > 0 && !skipped {
+                if let Some(found) = find_emscripten_output(&path, depth - 1) {
+                    consider(found);
+                }
+            }
+        } else if path.extension().is_some_and(|ext| ext == "wasm") {
+            let js = path.with_extension("js");
+            if js.is_file() {
+                let data = Some(path.with_extension("data")).filter(|data| data.is_file());
+                consider(EmscriptenOutput {
+                    wasm: path,
+                    js,
+                    data,
+                });
+            }
+        }
+    }
+    newest.map(|(_, found)| found)
+}
 
 /// C WebAssembly plugin
 #[derive(Clone)]
@@ -38,7 +99,15 @@ impl CPlugin {
 
     /// Find main.c or similar entry point
     fn find_entry_file(&self, project_path: &str) -> CompilationResult<PathBuf> {
-        let common_entry_files = ["main.c", "src/main.c", "app.c", "index.c"];
+        let common_entry_files = [
+            "main.c",
+            "src/main.c",
+            "app.c",
+            "index.c",
+            "main.cpp",
+            "src/main.cpp",
+            "main.cc",
+        ];
 
         for entry_name in common_entry_files.iter() {
             let entry_path = Path::new(project_path).join(entry_name);
@@ -51,7 +120,7 @@ impl CPlugin {
         if let Ok(entries) = fs::read_dir(project_path) {
             for entry in entries.flatten() {
                 if let Some(extension) = entry.path().extension() {
-                    if extension == "c" {
+                    if extension == "c" || CPP_EXTENSIONS.iter().any(|ext| extension == *ext) {
                         return Ok(entry.path());
                     }
                 }
@@ -65,10 +134,107 @@ impl CPlugin {
                 "src/main.c".to_string(),
                 "app.c".to_string(),
                 "index.c".to_string(),
+                "main.cpp".to_string(),
             ],
         })
     }
 
+    /// Check if project is configured with CMake
+    fn has_cmake(&self, project_path: &str) -> bool {
+        Path::new(project_path).join("CMakeLists.txt").is_file()
+    }
+
+    /// Copy the module Emscripten wrote below `dir` and its glue to the output directory
+    fn copy_emscripten_output(
+        &self,
+        dir: &Path,
+        depth: usize,
+        config: &BuildConfig,
+        tool: &str,
+    ) -> CompilationResult<BuildResult> {
+        let EmscriptenOutput { wasm, js, data } =
+            find_emscripten_output(dir, depth).ok_or_else(|| CompilationError::BuildFailed {
+                language: self.language_name().to_string(),
+                reason: format!(
+                    "{tool} finished but left no .wasm with a matching .js in {}; link with emcc, which writes both",
+                    dir.display()
+                ),
+            })?;
+        PathResolver::ensure_output_directory(&config.output_dir).map_err(|_| {
+            CompilationError::OutputDirectoryCreationFailed {
+                path: config.output_dir.clone(),
+            }
+        })?;
+        let copy = |path: &Path| {
+            CommandExecutor::copy_to_output(&path.to_string_lossy(), &config.output_dir, "C")
+        };
+        Ok(BuildResult {
+            wasm_path: copy(&wasm)?,
+            js_path: Some(copy(&js)?),
+            additional_files: data
+                .map(|data| copy(&data))
+                .transpose()?
+                .into_iter()
+                .collect(),
+            is_wasm_bindgen: true,
+        })
+    }
+
+    /// Configure and build a CMake project with Emscripten's toolchain file
+    fn build_with_cmake(&self, config: &BuildConfig) -> CompilationResult<BuildResult> {
+        for tool in ["emcmake", "cmake"] {
+            if !CommandExecutor::is_tool_installed(tool) {
+                return Err(CompilationError::BuildToolNotFound {
+                    tool: tool.to_string(),
+                    language: self.language_name().to_string(),
+                });
+            }
+        }
+        let build_type = match config.optimization_level {
+            OptimizationLevel::Debug => "Debug",
+            OptimizationLevel::Release => "Release",
+            OptimizationLevel::Size => "MinSizeRel",
+        };
+        let build_type = format!("-DCMAKE_BUILD_TYPE={build_type}");
+
+        println!("🔨 Configuring with emcmake cmake...");
+        let configure = CommandExecutor::execute_command(
+            "emcmake",
+            &["cmake", "-S", ".", "-B", CMAKE_BUILD_DIR, &build_type],
+            &config.project_path,
+            config.verbose,
+        )?;
+        if !configure.status.success() {
+            return Err(CompilationError::BuildFailed {
+                language: self.language_name().to_string(),
+                reason: format!(
+                    "CMake configuration failed: {}",
+                    String::from_utf8_lossy(&configure.stderr)
+                ),
+            });
+        }
+
+        println!("🔨 Building with cmake --build...");
+        let build = CommandExecutor::execute_command(
+            "cmake",
+            &["--build", CMAKE_BUILD_DIR],
+            &config.project_path,
+            config.verbose,
+        )?;
+        if !build.status.success() {
+            return Err(CompilationError::BuildFailed {
+                language: self.language_name().to_string(),
+                reason: format!(
+                    "CMake build failed: {}",
+                    String::from_utf8_lossy(&build.stderr)
+                ),
+            });
+        }
+
+        let build_dir = Path::new(&config.project_path).join(CMAKE_BUILD_DIR);
+        self.copy_emscripten_output(&build_dir, 3, config, "CMake")
+    }
+
     /// Check if project uses a Makefile
     fn has_makefile(&self, project_path: &str) -> bool {
         let makefile_variants = ["Makefile", "makefile", "GNUmakefile"];
@@ -93,21 +259,24 @@ impl CPlugin {
             });
         }
 
+        // Through emmake when available, so a Makefile using $(CC) gets emcc
+        let (program, make_args): (&str, &[&str]) = if CommandExecutor::is_tool_installed("emmake")
+        {
+            ("emmake", &["make"])
+        } else {
+            ("make", &[])
+        };
+        let run_make = |target: Option<&str>| {
+            let mut args = make_args.to_vec();
+            args.extend(target);
+            CommandExecutor::execute_command(program, &args, &config.project_path, config.verbose)
+        };
+
         // Execute make
-        let build_output = CommandExecutor::execute_command(
-            "make",
-            &["wasm"],
-            &config.project_path,
-            config.verbose,
-        )?;
+        let build_output = run_make(Some("wasm"))?;
 
         if !build_output.status.success() {
-            let build_output = CommandExecutor::execute_command(
-                "make",
-                &[],
-                &config.project_path,
-                config.verbose,
-            )?;
+            let build_output = run_make(None)?;
 
             if !build_output.status.success() {
                 return Err(CompilationError::BuildFailed {
@@ -120,6 +289,11 @@ impl CPlugin {
             }
         }
 
+        // Prefer the module Emscripten linked, with its glue, wherever the Makefile put it
+        if find_emscripten_output(Path::new(&config.project_path), 2).is_some() {
+            return self.copy_emscripten_output(Path::new(&config.project_path), 2, config, "make");
+        }
+
         let wasm_files = PathResolver::find_files_with_extension(&config.project_path, "wasm")
             .map_err(|e| CompilationError::BuildFailed {
                 language: self.language_name().to_string(),
@@ -177,10 +351,18 @@ impl CPlugin {
         let wasm_output_file = Path::new(&config.output_dir).join(format!("{output_name}.wasm"));
         let js_output_file = Path::new(&config.output_dir).join(format!("{output_name}.js"));
 
-        println!("🔨 Building with Emscripten...");
-
-        // Collect all .c files in the project
+        // Collect all C and C++ sources in the project
         let c_files = self.collect_c_files(&config.project_path)?;
+        let compiler = if c_files.iter().any(|file| {
+            Path::new(file)
+                .extension()
+                .is_some_and(|ext| CPP_EXTENSIONS.iter().any(|cpp| ext == *cpp))
+        }) {
+            "em++"
+        } else {
+            "emcc"
+        };
+        println!("🔨 Building with Emscripten ({compiler})...");
 
         // Build args for emcc
         let mut args = vec![
@@ -211,8 +393,12 @@ impl CPlugin {
         }
 
         // Run emcc
-        let build_output =
-            CommandExecutor::execute_command("emcc", &args, &config.project_path, config.verbose)?;
+        let build_output = CommandExecutor::execute_command(
+            compiler,
+            &args,
+            &config.project_path,
+            config.verbose,
+        )?;
 
         if !build_output.status.success() {
             return Err(CompilationError::BuildFailed {
@@ -239,7 +425,7 @@ impl CPlugin {
         })
     }
 
-    /// Collect all .c files in the project directory
+    /// Collect all C and C++ sources in the project directory
     fn collect_c_files(&self, project_path: &str) -> CompilationResult<Vec<String>> {
         let mut c_files = Vec::new();
 
@@ -251,7 +437,7 @@ impl CPlugin {
         for entry in entries.flatten() {
             let path = entry.path();
             if let Some(extension) = path.extension() {
-                if extension == "c" {
+                if extension == "c" || CPP_EXTENSIONS.iter().any(|ext| extension == *ext) {
                     if let Some(path_str) = path.to_str() {
                         c_files.push(path_str.to_string());
                     }
@@ -262,7 +448,7 @@ impl CPlugin {
         if c_files.is_empty() {
             return Err(CompilationError::BuildFailed {
                 language: self.language_name().to_string(),
-                reason: "No .c files found in project directory".to_string(),
+                reason: "No C or C++ sources found in project directory".to_string(),
             });
         }
 
@@ -276,17 +462,17 @@ impl Plugin for CPlugin {
     }
 
     fn can_handle_project(&self, project_path: &str) -> bool {
-        // Check for Makefile
-        if self.has_makefile(project_path) {
+        // Check for Makefile or CMakeLists.txt
+        if self.has_makefile(project_path) || self.has_cmake(project_path) {
             return true;
         }
 
-        // Look for .c files
+        // Look for C and C++ sources
         if let Ok(entries) = fs::read_dir(project_path) {
             for entry in entries.flatten() {
                 if let Some(extension) = entry.path().extension() {
                     let ext = extension.to_string_lossy().to_lowercase();
-                    if ext == "c" {
+                    if ext == "c" || CPP_EXTENSIONS.contains(&ext.as_str()) {
                         return true;
                     }
                 }
@@ -336,9 +522,23 @@ impl WasmBuilder for CPlugin {
             missing.push("make (build system)".to_string());
         }
 
+        if self.has_cmake(&BuildConfig::default().project_path)
+            && !CommandExecutor::is_tool_installed("cmake")
+        {
+            missing.push("cmake (build system)".to_string());
+        }
+
         missing
     }
 
+    fn generated_paths(&self, project_path: &str) -> Vec<String> {
+        if self.has_cmake(project_path) {
+            vec![format!("/{CMAKE_BUILD_DIR}/")]
+        } else {
+            Vec::new()
+        }
+    }
+
     fn validate_project(&self, project_path: &str) -> CompilationResult<()> {
         PathResolver::validate_directory_exists(project_path).map_err(|e| {
             CompilationError::InvalidProjectStructure {
@@ -347,8 +547,8 @@ impl WasmBuilder for CPlugin {
             }
         })?;
 
-        // Check if we have either a Makefile or can find C files
-        if !self.has_makefile(project_path) {
+        // Check if we have a Makefile, a CMakeLists.txt or can find sources
+        if !self.has_makefile(project_path) && !self.has_cmake(project_path) {
             let _ = self.find_entry_file(project_path)?;
         }
 
@@ -364,7 +564,9 @@ impl WasmBuilder for CPlugin {
             });
         }
 
-        if self.has_makefile(&config.project_path) {
+        if self.has_cmake(&config.project_path) {
+            self.build_with_cmake(config)
+        } else if self.has_makefile(&config.project_path) {
             self.build_with_makefile(config)
         } else {
             self.build_with_emscripten(config)
@@ -418,3 +620,54 @@ impl Default for CPlugin {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emscripten_output_needs_its_glue() {
+        let dir = tempfile::tempdir().unwrap();
+        let build = dir.path().join("bin");
+        fs::create_dir_all(build.join("CMakeFiles")).unwrap();
+        // A bare module and CMake's probes are not what the build produced
+        fs::write(dir.path().join("lib.wasm"), b"").unwrap();
+        fs::write(build.join("CMakeFiles/probe.wasm"), b"").unwrap();
+        fs::write(build.join("CMakeFiles/probe.js"), b"").unwrap();
+        assert!(find_emscripten_output(dir.path(), 3).is_none());
+
+        fs::write(build.join("game.wasm"), b"").unwrap();
+        fs::write(build.join("game.js"), b"").unwrap();
+        fs::write(build.join("game.data"), b"").unwrap();
+        assert_eq!(
+            find_emscripten_output(dir.path(), 3),
+            Some(EmscriptenOutput {
+                wasm: build.join("game.wasm"),
+                js: build.join("game.js"),
+                data: Some(build.join("game.data")),
+            })
+        );
+        assert!(find_emscripten_output(dir.path(), 0).is_none());
+    }
+
+    #[test]
+    fn test_cmake_and_cpp_projects_are_handled() {
+        let plugin = CPlugin::new();
+        let cmake = tempfile::tempdir().unwrap();
+        fs::write(cmake.path().join("CMakeLists.txt"), "project(app)").unwrap();
+        assert!(Plugin::can_handle_project(
+            &plugin,
+            cmake.path().to_str().unwrap()
+        ));
+
+        let cpp = tempfile::tempdir().unwrap();
+        fs::write(cpp.path().join("main.cpp"), "int main() {}").unwrap();
+        let cpp_path = cpp.path().to_str().unwrap();
+        assert!(Plugin::can_handle_project(&plugin, cpp_path));
+        assert_eq!(
+            plugin.find_entry_file(cpp_path).unwrap(),
+            cpp.path().join("main.cpp")
+        );
+        assert_eq!(plugin.collect_c_files(cpp_path).unwrap().len(), 1);
+    }
+}
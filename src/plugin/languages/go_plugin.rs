@@ -0,0 +1,421 @@
+//! Go projects built with TinyGo or the Go toolchain, without an external plugin
+//!
+//! A directory with a `go.mod` is built with TinyGo when it is installed,
+//! which produces far smaller modules, and with `GOOS=js GOARCH=wasm go build`
+//! otherwise. Either way the module needs the `wasm_exec.js` shipped with the
+//! toolchain that built it; it is copied next to the module, and the server
+//! loads the module through it. An installed `wasmgo` plugin still takes
+//! precedence, since external plugins are consulted first.
+
+use crate::compiler::builder::{BuildConfig, BuildResult, OptimizationLevel, WasmBuilder};
+use crate::error::{CompilationError, CompilationResult};
+use crate::plugin::{Plugin, PluginCapabilities, PluginInfo, PluginType};
+use crate::server::gojs::WASM_EXEC;
+use crate::utils::{CommandExecutor, PathResolver};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const LANGUAGE: &str = "Go";
+
+/// Go WebAssembly plugin
+#[derive(Clone)]
+pub struct GoPlugin {
+    info: PluginInfo,
+}
+
+/// The compiler a build runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Toolchain {
+    TinyGo,
+    Go,
+}
+
+impl Toolchain {
+    /// TinyGo when installed, else the Go toolchain when installed
+    fn detect() -> Option<Self> {
+        if CommandExecutor::is_tool_installed("tinygo") {
+            Some(Toolchain::TinyGo)
+        } else if CommandExecutor::is_tool_installed("go") {
+            Some(Toolchain::Go)
+        } else {
+            None
+        }
+    }
+
+    fn command(self) -> &'static str {
+        match self {
+            Toolchain::TinyGo => "tinygo",
+            Toolchain::Go => "go",
+        }
+    }
+
+    /// Arguments building the main package of the current directory into `output`
+    fn build_args(self, config: &BuildConfig, output: &str) -> Vec<String> {
+        let mut args = vec!["build".to_string(), "-o".to_string(), output.to_string()];
+        match self {
+            Toolchain::TinyGo => {
+                args.extend(["-target".to_string(), "wasm".to_string()]);
+                let opt = match config.optimization_level {
+                    OptimizationLevel::Debug => "1",
+                    OptimizationLevel::Release => "2",
+                    OptimizationLevel::Size => "z",
+                };
+                args.extend(["-opt".to_string(), opt.to_string()]);
+                if !matches!(config.optimization_level, OptimizationLevel::Debug) {
+                    args.push("-no-debug".to_string());
+                }
+            }
+            Toolchain::Go => {
+                if !matches!(config.optimization_level, OptimizationLevel::Debug) {
+                    // Drop the symbol table and DWARF, a good part of the module
+                    args.extend(["-trimpath".to_string(), "-ldflags=-s -w".to_string()]);
+                }
+            }
+        }
+        args.push(".".to_string());
+        args
+    }
+
+    /// Variables the build runs with; TinyGo's target implies them
+    fn env(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Toolchain::TinyGo => &[],
+            Toolchain::Go => &[("GOOS", "js"), ("GOARCH", "wasm")],
+        }
+    }
+
+    /// Where `wasm_exec.js` may be below the toolchain's root
+    fn wasm_exec_candidates(self, root: &Path) -> Vec<PathBuf> {
+        match self {
+            Toolchain::TinyGo => vec![root.join("targets").join(WASM_EXEC)],
+            // Go 1.24 moved it from misc/wasm to lib/wasm
+            Toolchain::Go => vec![
+                root.join("lib").join("wasm").join(WASM_EXEC),
+                root.join("misc").join("wasm").join(WASM_EXEC),
+            ],
+        }
+    }
+
+    /// The toolchain's root directory, as it reports it
+    fn root(self, project_path: &str) -> Option<PathBuf> {
+        let variable = match self {
+            Toolchain::TinyGo => "TINYGOROOT",
+            Toolchain::Go => "GOROOT",
+        };
+        let output = CommandExecutor::execute_command(
+            self.command(),
+            &["env", variable],
+            project_path,
+            false,
+        )
+        .ok()
+        .filter(|output| output.status.success())?;
+        let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!root.is_empty()).then(|| PathBuf::from(root))
+    }
+}
+
+/// The name of the module file: the last element of the module path in
+/// `go.mod`, skipping a major version suffix such as the `v2` of `example.com/app/v2`
+fn module_name(go_mod: &str) -> Option<String> {
+    let path = go_mod
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("module "))?
+        .trim()
+        .trim_matches('"');
+    let is_major_version = |part: &str| {
+        part.len() > 1 && part.starts_with('v') && part[1..].chars().all(|c| c.is_ascii_digit())
+    };
+    let mut parts = path.rsplit('/').filter(|part| !part.is_empty());
+    let last = parts.next()?;
+    let name = match parts.next() {
+        Some(parent) if is_major_version(last) => parent,
+        _ => last,
+    };
+    Some(name.to_string())
+}
+
+impl GoPlugin {
+    pub fn new() -> Self {
+        let info = PluginInfo {
+            name: "go".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            description: "Go WebAssembly compiler using TinyGo or GOOS=js GOARCH=wasm".to_string(),
+            author: "Wasmrun Team".to_string(),
+            extensions: vec!["go".to_string()],
+            entry_files: vec!["go.mod".to_string(), "main.go".to_string()],
+            plugin_type: PluginType::Builtin,
+            source: None,
+            dependencies: vec![],
+            capabilities: PluginCapabilities {
+                compile_wasm: true,
+                compile_webapp: false,
+                live_reload: true,
+                optimization: true,
+                custom_targets: vec!["wasm".to_string(), "js/wasm".to_string()],
+                supported_languages: Some(vec!["go".to_string()]),
+            },
+        };
+
+        Self { info }
+    }
+
+    fn failed(reason: impl Into<String>) -> CompilationError {
+        CompilationError::BuildFailed {
+            language: LANGUAGE.to_string(),
+            reason: reason.into(),
+        }
+    }
+
+    fn wasm_exec_source(toolchain: Toolchain, project_path: &str) -> Option<PathBuf> {
+        toolchain.root(project_path).and_then(|root| {
+            toolchain
+                .wasm_exec_candidates(&root)
+                .into_iter()
+                .find(|candidate| candidate.is_file())
+        })
+    }
+
+    /// The module the build writes, named after the Go module
+    fn wasm_path(config: &BuildConfig) -> PathBuf {
+        let output_dir = fs::canonicalize(&config.output_dir)
+            .unwrap_or_else(|_| PathBuf::from(&config.output_dir));
+        let name = fs::read_to_string(Path::new(&config.project_path).join("go.mod"))
+            .ok()
+            .and_then(|go_mod| module_name(&go_mod))
+            .unwrap_or_else(|| "main".to_string());
+        output_dir.join(format!("{name}.wasm"))
+    }
+
+    /// Copy the toolchain's `wasm_exec.js` into the output directory, replacing
+    /// one left by another toolchain or version
+    fn copy_wasm_exec(toolchain: Toolchain, config: &BuildConfig) -> CompilationResult<String> {
+        let source = Self::wasm_exec_source(toolchain, &config.project_path).ok_or_else(|| {
+            Self::failed(format!(
+                "could not find the {WASM_EXEC} of {}; check `{} env`",
+                toolchain.command(),
+                toolchain.command()
+            ))
+        })?;
+        let target = Path::new(&config.output_dir).join(WASM_EXEC);
+        fs::copy(&source, &target)
+            .map_err(|e| Self::failed(format!("copying {}: {e}", source.display())))?;
+        Ok(target.to_string_lossy().to_string())
+    }
+}
+
+impl Plugin for GoPlugin {
+    fn info(&self) -> &PluginInfo {
+        &self.info
+    }
+
+    fn can_handle_project(&self, project_path: &str) -> bool {
+        Path::new(project_path).join("go.mod").is_file()
+    }
+
+    fn get_builder(&self) -> Box<dyn WasmBuilder> {
+        Box::new(GoPlugin::new())
+    }
+}
+
+impl WasmBuilder for GoPlugin {
+    fn supported_extensions(&self) -> &[&str] {
+        &["go"]
+    }
+
+    fn entry_file_candidates(&self) -> &[&str] {
+        &["go.mod", "main.go"]
+    }
+
+    fn language_name(&self) -> &str {
+        LANGUAGE
+    }
+
+    fn check_dependencies(&self) -> Vec<String> {
+        if Toolchain::detect().is_some() {
+            return vec![];
+        }
+        vec!["tinygo (https://tinygo.org) or go (https://go.dev/dl)".to_string()]
+    }
+
+    fn validate_project(&self, project_path: &str) -> CompilationResult<()> {
+        PathResolver::validate_directory_exists(project_path).map_err(|e| {
+            CompilationError::InvalidProjectStructure {
+                language: LANGUAGE.to_string(),
+                reason: format!("Project directory validation failed: {e}"),
+            }
+        })?;
+
+        if !Path::new(project_path).join("go.mod").is_file() {
+            return Err(CompilationError::InvalidProjectStructure {
+                language: LANGUAGE.to_string(),
+                reason: "No go.mod found; create one with `go mod init`".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn build(&self, config: &BuildConfig) -> CompilationResult<BuildResult> {
+        let toolchain = Toolchain::detect().ok_or_else(|| CompilationError::BuildToolNotFound {
+            tool: "tinygo or go".to_string(),
+            language: LANGUAGE.to_string(),
+        })?;
+
+        PathResolver::ensure_output_directory(&config.output_dir).map_err(|_| {
+            CompilationError::OutputDirectoryCreationFailed {
+                path: config.output_dir.clone(),
+            }
+        })?;
+        let wasm_path = Self::wasm_path(config);
+        let wasm_path_str = wasm_path.to_string_lossy().to_string();
+
+        println!("🔨 Building with {}...", toolchain.command());
+        let args = toolchain.build_args(config, &wasm_path_str);
+        if config.verbose {
+            println!("🔧 Executing: {} {}", toolchain.command(), args.join(" "));
+        }
+        let output = Command::new(toolchain.command())
+            .args(&args)
+            .envs(toolchain.env().iter().copied())
+            .current_dir(&config.project_path)
+            .output()
+            .map_err(|e| CompilationError::ToolExecutionFailed {
+                tool: toolchain.command().to_string(),
+                reason: e.to_string(),
+            })?;
+        if !output.status.success() {
+            return Err(Self::failed(format!(
+                "{} build failed: {}",
+                toolchain.command(),
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        if !wasm_path.is_file() {
+            return Err(Self::failed(format!(
+                "{} build completed but {} was not created",
+                toolchain.command(),
+                wasm_path.display()
+            )));
+        }
+
+        let wasm_exec = Self::copy_wasm_exec(toolchain, config)?;
+        Ok(BuildResult {
+            wasm_path: wasm_path_str,
+            js_path: Some(wasm_exec),
+            additional_files: vec![],
+            is_wasm_bindgen: false,
+        })
+    }
+
+    fn planned_commands(&self, config: &BuildConfig) -> Vec<String> {
+        let Some(toolchain) = Toolchain::detect() else {
+            return Vec::new();
+        };
+        let wasm_path = Self::wasm_path(config).to_string_lossy().to_string();
+        let env: String = toolchain
+            .env()
+            .iter()
+            .map(|(name, value)| format!("{name}={value} "))
+            .collect();
+        let mut commands = vec![format!(
+            "{env}{}",
+            CommandExecutor::display_command(
+                toolchain.command(),
+                &toolchain.build_args(config, &wasm_path)
+            )
+        )];
+        if let Some(source) = Self::wasm_exec_source(toolchain, &config.project_path) {
+            let target = Path::new(&config.output_dir).join(WASM_EXEC);
+            commands.push(CommandExecutor::display_command(
+                "cp",
+                &[source.to_string_lossy(), target.to_string_lossy()],
+            ));
+        }
+        commands
+    }
+
+    fn can_handle_project(&self, project_path: &str) -> bool {
+        Path::new(project_path).join("go.mod").is_file()
+    }
+
+    fn clean(&self, project_path: &str) -> crate::error::Result<()> {
+        let _ = CommandExecutor::execute_command("go", &["clean"], project_path, false);
+        Ok(())
+    }
+
+    fn clone_box(&self) -> Box<dyn WasmBuilder> {
+        Box::new(self.clone())
+    }
+}
+
+impl Default for GoPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_name() {
+        assert_eq!(
+            module_name("module example.com/hello\n\ngo 1.22\n").as_deref(),
+            Some("hello")
+        );
+        assert_eq!(
+            module_name("// comment\nmodule github.com/me/app/v2\n").as_deref(),
+            Some("app")
+        );
+        assert_eq!(module_name("module \"demo\"\n").as_deref(), Some("demo"));
+        assert_eq!(module_name("go 1.22\n"), None);
+    }
+
+    #[test]
+    fn test_build_args() {
+        let release = BuildConfig::default();
+        assert_eq!(
+            Toolchain::TinyGo
+                .build_args(&release, "out/app.wasm")
+                .join(" "),
+            "build -o out/app.wasm -target wasm -opt 2 -no-debug ."
+        );
+        assert_eq!(
+            Toolchain::Go.build_args(&release, "out/app.wasm"),
+            [
+                "build",
+                "-o",
+                "out/app.wasm",
+                "-trimpath",
+                "-ldflags=-s -w",
+                "."
+            ]
+        );
+        assert_eq!(Toolchain::Go.env(), [("GOOS", "js"), ("GOARCH", "wasm")]);
+
+        let debug = BuildConfig {
+            optimization_level: OptimizationLevel::Debug,
+            ..BuildConfig::default()
+        };
+        assert_eq!(
+            Toolchain::Go.build_args(&debug, "app.wasm").join(" "),
+            "build -o app.wasm ."
+        );
+    }
+
+    #[test]
+    fn test_wasm_exec_locations() {
+        let candidates = Toolchain::Go.wasm_exec_candidates(Path::new("/usr/local/go"));
+        assert_eq!(
+            candidates[0],
+            Path::new("/usr/local/go/lib/wasm/wasm_exec.js")
+        );
+        assert_eq!(
+            Toolchain::TinyGo.wasm_exec_candidates(Path::new("/opt/tinygo")),
+            [Path::new("/opt/tinygo/targets/wasm_exec.js")]
+        );
+    }
+}
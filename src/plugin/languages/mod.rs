@@ -1,2 +1,6 @@
 // Export built-in language plugins
+pub mod asc_plugin;
 pub mod c_plugin;
+pub mod go_plugin;
+pub mod python_plugin;
+pub mod rust_plugin;
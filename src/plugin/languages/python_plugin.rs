@@ -0,0 +1,536 @@
+//! Python projects, run with Pyodide or compiled with py2wasm
+//!
+//! A directory with a `main.py`, `app.py`, `__main__.py` or `src/main.py`, or
+//! a single `.py` file, is a Python project. By default its sources are
+//! bundled into a module that holds nothing but a [`BUNDLE_SECTION`] custom
+//! section, with the entry file, every `.py` file of the project and the
+//! requirements from `requirements.txt` or `pyproject.toml`. Next to it goes a
+//! bootstrap that loads Pyodide, writes the files into its filesystem,
+//! installs the requirements with micropip and runs the entry. Serving,
+//! caching and live reload then treat the bundle like any other module.
+//!
+//! With `mode = "py2wasm"` under `[python]` in `wasmrun.toml` the entry is
+//! compiled into a WASI module with py2wasm instead, which the console page
+//! runs. An installed `waspy` plugin still takes precedence.
+
+use crate::compiler::builder::{BuildConfig, BuildResult, WasmBuilder};
+use crate::config::active_settings;
+use crate::error::{CompilationError, CompilationResult};
+use crate::plugin::{Plugin, PluginCapabilities, PluginInfo, PluginType};
+use crate::utils::{CommandExecutor, PathResolver};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use wasm_encoder::{CustomSection, Module};
+
+const LANGUAGE: &str = "Python";
+
+/// Custom section of a bundle holding the app as JSON
+pub const BUNDLE_SECTION: &str = "wasmrun.python";
+
+/// Pyodide release the page loads unless `python.pyodide_url` says otherwise
+pub const DEFAULT_PYODIDE_URL: &str = "https://cdn.jsdelivr.net/pyodide/v0.26.4/full/";
+
+/// Entry files, in the order they are looked for
+const ENTRY_FILES: &[&str] = &["main.py", "app.py", "__main__.py", "src/main.py"];
+
+/// Directories never bundled: environments, caches and build outputs
+const SKIPPED_DIRS: &[&str] = &[
+    "__pycache__",
+    "venv",
+    "env",
+    "node_modules",
+    "site-packages",
+    "build",
+    "dist",
+    "target",
+];
+
+/// What a bundle carries, as stored in [`BUNDLE_SECTION`]
+#[derive(Debug, Serialize)]
+struct Bundle {
+    entry: String,
+    /// Sources by path relative to the project, with `/` separators
+    files: BTreeMap<String, String>,
+    /// Requirement specifiers for micropip
+    requirements: Vec<String>,
+}
+
+/// How the project is built, from `python.mode`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Pyodide,
+    Py2wasm,
+}
+
+impl Mode {
+    fn active() -> Self {
+        match active_settings().python.mode.as_deref() {
+            Some("py2wasm") => Mode::Py2wasm,
+            _ => Mode::Pyodide,
+        }
+    }
+}
+
+/// The entry file of `project_path`, relative to it
+pub fn entry_file(project_path: &str) -> Option<String> {
+    let root = Path::new(project_path);
+    if let Some(entry) = ENTRY_FILES.iter().find(|entry| root.join(entry).is_file()) {
+        return Some(entry.to_string());
+    }
+    let mut scripts = fs::read_dir(root)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "py"));
+    match (scripts.next(), scripts.next()) {
+        (Some(script), None) => Some(script.file_name()?.to_string_lossy().to_string()),
+        _ => None,
+    }
+}
+
+/// Every `.py` file under `dir`, relative to `root`
+fn collect_sources(root: &Path, dir: &Path, files: &mut BTreeMap<String, String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if path.is_dir() {
+            if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()) {
+                collect_sources(root, &path, files);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "py") {
+            let (Ok(relative), Ok(source)) = (path.strip_prefix(root), fs::read_to_string(&path))
+            else {
+                continue;
+            };
+            let relative: Vec<String> = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy().to_string())
+                .collect();
+            files.insert(relative.join("/"), source);
+        }
+    }
+}
+
+/// Requirements from `requirements.txt`, else `[project].dependencies` of
+/// `pyproject.toml`; options and includes in `requirements.txt` are skipped
+fn requirements(project_path: &str) -> Vec<String> {
+    let root = Path::new(project_path);
+    if let Ok(content) = fs::read_to_string(root.join("requirements.txt")) {
+        return content
+            .lines()
+            .map(|line| line.split(" #").next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('-'))
+            .map(str::to_string)
+            .collect();
+    }
+    fs::read_to_string(root.join("pyproject.toml"))
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .and_then(|pyproject| {
+            let dependencies = pyproject.get("project")?.get("dependencies")?.as_array()?;
+            Some(
+                dependencies
+                    .iter()
+                    .filter_map(|dependency| dependency.as_str().map(str::to_string))
+                    .collect(),
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Name of the module, from `pyproject.toml` or the project directory
+fn module_name(project_path: &str) -> String {
+    let from_pyproject = fs::read_to_string(Path::new(project_path).join("pyproject.toml"))
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .and_then(|pyproject| Some(pyproject.get("project")?.get("name")?.as_str()?.to_string()));
+    let name = from_pyproject.or_else(|| {
+        fs::canonicalize(project_path)
+            .ok()?
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    });
+    name.map(|name| {
+        name.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>()
+    })
+    .filter(|name| !name.is_empty())
+    .unwrap_or_else(|| "app".to_string())
+}
+
+/// A module carrying `bundle` in its custom section
+/// Where the page loads Pyodide from, ending in `/`. Offline, the page must
+/// not reach for jsDelivr, so a configured copy is required.
+fn pyodide_url(configured: Option<String>, offline: bool) -> Result<String, String> {
+    match configured {
+        Some(url) => Ok(format!("{}/", url.trim_end_matches('/'))),
+        None if offline => Err(format!(
+            "the page would load Pyodide from {DEFAULT_PYODIDE_URL}, but offline mode is enabled; \
+             set python.pyodide_url in wasmrun.toml to a local copy, or mode = \"py2wasm\""
+        )),
+        None => Ok(DEFAULT_PYODIDE_URL.to_string()),
+    }
+}
+
+fn bundle_module(bundle: &Bundle) -> CompilationResult<Vec<u8>> {
+    let data = serde_json::to_vec(bundle).map_err(|e| PythonPlugin::failed(e.to_string()))?;
+    let mut module = Module::new();
+    module.section(&CustomSection {
+        name: BUNDLE_SECTION.into(),
+        data: data.into(),
+    });
+    Ok(module.finish())
+}
+
+/// Runs the bundle of a module with Pyodide, loaded from `__PYODIDE__`
+const BOOTSTRAP: &str = r#"// Written by wasmrun: runs the Python app bundled into a module with Pyodide
+const PYODIDE = __PYODIDE__;
+
+const load = () => new Promise((resolve, reject) => {
+  if (globalThis.loadPyodide) return resolve();
+  const script = document.createElement("script");
+  script.src = PYODIDE + "pyodide.js";
+  script.onload = resolve;
+  script.onerror = () => reject(new Error(`Could not load Pyodide from ${script.src}`));
+  document.head.appendChild(script);
+});
+
+export default async function run(module, { stdout = console.log, stderr = console.error } = {}) {
+  const [section] = WebAssembly.Module.customSections(module, "wasmrun.python");
+  const app = JSON.parse(new TextDecoder().decode(section));
+  await load();
+  const pyodide = await loadPyodide({ indexURL: PYODIDE, stdout, stderr });
+  for (const [path, source] of Object.entries(app.files)) {
+    const parts = path.split("/");
+    for (let i = 1; i < parts.length; i++) {
+      const dir = parts.slice(0, i).join("/");
+      if (!pyodide.FS.analyzePath(dir).exists) pyodide.FS.mkdir(dir);
+    }
+    pyodide.FS.writeFile(path, source);
+  }
+  if (app.requirements.length) {
+    await pyodide.loadPackage("micropip");
+    await pyodide.pyimport("micropip").install(app.requirements);
+  }
+  await pyodide.loadPackagesFromImports(app.files[app.entry]);
+  const entry = app.entry.split("/").slice(0, -1).join("/");
+  pyodide.runPython(`import sys; sys.path[:0] = [${JSON.stringify(entry || ".")}, "."]`);
+  await pyodide.runPythonAsync(app.files[app.entry], { filename: app.entry });
+  return pyodide;
+}
+"#;
+
+/// Python WebAssembly plugin
+#[derive(Clone)]
+pub struct PythonPlugin {
+    info: PluginInfo,
+}
+
+impl PythonPlugin {
+    pub fn new() -> Self {
+        let info = PluginInfo {
+            name: "python".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            description: "Python in the browser with Pyodide, or compiled with py2wasm".to_string(),
+            author: "Wasmrun Team".to_string(),
+            extensions: vec!["py".to_string()],
+            entry_files: ENTRY_FILES.iter().map(|entry| entry.to_string()).collect(),
+            plugin_type: PluginType::Builtin,
+            source: None,
+            dependencies: vec![],
+            capabilities: PluginCapabilities {
+                compile_wasm: true,
+                compile_webapp: false,
+                live_reload: true,
+                optimization: false,
+                custom_targets: vec!["pyodide".to_string(), "wasi".to_string()],
+                supported_languages: Some(vec!["python".to_string()]),
+            },
+        };
+
+        Self { info }
+    }
+
+    fn failed(reason: impl Into<String>) -> CompilationError {
+        CompilationError::BuildFailed {
+            language: LANGUAGE.to_string(),
+            reason: reason.into(),
+        }
+    }
+
+    fn entry(project_path: &str) -> CompilationResult<String> {
+        entry_file(project_path).ok_or_else(|| CompilationError::MissingEntryFile {
+            language: LANGUAGE.to_string(),
+            candidates: ENTRY_FILES.iter().map(|entry| entry.to_string()).collect(),
+        })
+    }
+
+    /// Bundle the sources and write the bootstrap next to the bundle
+    fn build_bundle(config: &BuildConfig, output_dir: &Path) -> CompilationResult<BuildResult> {
+        let entry = Self::entry(&config.project_path)?;
+        let url = pyodide_url(
+            active_settings().python.pyodide_url,
+            crate::offline::is_offline(),
+        )
+        .map_err(Self::failed)?;
+        let root = Path::new(&config.project_path);
+        let mut files = BTreeMap::new();
+        collect_sources(root, root, &mut files);
+        let bundle = Bundle {
+            entry,
+            files,
+            requirements: requirements(&config.project_path),
+        };
+        println!(
+            "📦 Bundling {} Python files for Pyodide...",
+            bundle.files.len()
+        );
+
+        let name = module_name(&config.project_path);
+        let wasm_path = output_dir.join(format!("{name}.wasm"));
+        fs::write(&wasm_path, bundle_module(&bundle)?)
+            .map_err(|e| Self::failed(format!("writing {}: {e}", wasm_path.display())))?;
+
+        let js_path = wasm_path.with_extension("js");
+        let url = serde_json::to_string(&url).unwrap_or_default();
+        fs::write(&js_path, BOOTSTRAP.replace("__PYODIDE__", &url))
+            .map_err(|e| Self::failed(format!("writing {}: {e}", js_path.display())))?;
+
+        Ok(BuildResult {
+            wasm_path: wasm_path.to_string_lossy().to_string(),
+            js_path: Some(js_path.to_string_lossy().to_string()),
+            additional_files: vec![],
+            is_wasm_bindgen: false,
+        })
+    }
+
+    /// Compile the entry into a WASI module with py2wasm
+    fn build_with_py2wasm(
+        config: &BuildConfig,
+        output_dir: &Path,
+    ) -> CompilationResult<BuildResult> {
+        if !CommandExecutor::is_tool_installed("py2wasm") {
+            return Err(CompilationError::BuildToolNotFound {
+                tool: "py2wasm (pip install py2wasm)".to_string(),
+                language: LANGUAGE.to_string(),
+            });
+        }
+        let entry = Self::entry(&config.project_path)?;
+        let wasm_path = output_dir.join(format!("{}.wasm", module_name(&config.project_path)));
+
+        println!("🔨 Compiling {entry} with py2wasm...");
+        let wasm = wasm_path.to_string_lossy();
+        let output = CommandExecutor::execute_command(
+            "py2wasm",
+            &[&entry, "-o", &wasm],
+            &config.project_path,
+            config.verbose,
+        )?;
+        if !output.status.success() {
+            return Err(Self::failed(format!(
+                "py2wasm failed: {}{}",
+                String::from_utf8_lossy(&output.stderr),
+                String::from_utf8_lossy(&output.stdout)
+            )));
+        }
+        if !wasm_path.is_file() {
+            return Err(Self::failed(format!(
+                "py2wasm completed but {} was not created",
+                wasm_path.display()
+            )));
+        }
+        // A bootstrap from an earlier Pyodide build would make this look like a bundle
+        let _ = fs::remove_file(wasm_path.with_extension("js"));
+
+        Ok(BuildResult {
+            wasm_path: wasm_path.to_string_lossy().to_string(),
+            js_path: None,
+            additional_files: vec![],
+            is_wasm_bindgen: false,
+        })
+    }
+}
+
+impl Plugin for PythonPlugin {
+    fn info(&self) -> &PluginInfo {
+        &self.info
+    }
+
+    fn can_handle_project(&self, project_path: &str) -> bool {
+        entry_file(project_path).is_some()
+    }
+
+    fn get_builder(&self) -> Box<dyn WasmBuilder> {
+        Box::new(PythonPlugin::new())
+    }
+}
+
+impl WasmBuilder for PythonPlugin {
+    fn supported_extensions(&self) -> &[&str] {
+        &["py"]
+    }
+
+    fn entry_file_candidates(&self) -> &[&str] {
+        ENTRY_FILES
+    }
+
+    fn language_name(&self) -> &str {
+        LANGUAGE
+    }
+
+    fn check_dependencies(&self) -> Vec<String> {
+        match Mode::active() {
+            Mode::Py2wasm if !CommandExecutor::is_tool_installed("py2wasm") => {
+                vec!["py2wasm (pip install py2wasm)".to_string()]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn validate_project(&self, project_path: &str) -> CompilationResult<()> {
+        PathResolver::validate_directory_exists(project_path).map_err(|e| {
+            CompilationError::InvalidProjectStructure {
+                language: LANGUAGE.to_string(),
+                reason: format!("Project directory validation failed: {e}"),
+            }
+        })?;
+        Self::entry(project_path).map(|_| ())
+    }
+
+    fn build(&self, config: &BuildConfig) -> CompilationResult<BuildResult> {
+        PathResolver::ensure_output_directory(&config.output_dir).map_err(|_| {
+            CompilationError::OutputDirectoryCreationFailed {
+                path: config.output_dir.clone(),
+            }
+        })?;
+        let output_dir = fs::canonicalize(&config.output_dir)
+            .unwrap_or_else(|_| PathBuf::from(&config.output_dir));
+
+        match Mode::active() {
+            Mode::Pyodide => Self::build_bundle(config, &output_dir),
+            Mode::Py2wasm => Self::build_with_py2wasm(config, &output_dir),
+        }
+    }
+
+    fn can_handle_project(&self, project_path: &str) -> bool {
+        entry_file(project_path).is_some()
+    }
+
+    fn clean(&self, _project_path: &str) -> crate::error::Result<()> {
+        Ok(())
+    }
+
+    fn clone_box(&self) -> Box<dyn WasmBuilder> {
+        Box::new(self.clone())
+    }
+}
+
+impl Default for PythonPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_and_requirements() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        assert_eq!(entry_file(path), None);
+
+        fs::write(dir.path().join("game.py"), "print('hi')").unwrap();
+        assert_eq!(entry_file(path).as_deref(), Some("game.py"));
+        fs::write(dir.path().join("util.py"), "").unwrap();
+        assert_eq!(entry_file(path), None);
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/main.py"), "").unwrap();
+        assert_eq!(entry_file(path).as_deref(), Some("src/main.py"));
+
+        fs::write(
+            dir.path().join("pyproject.toml"),
+            "[project]\nname = \"snake.game\"\ndependencies = [\"numpy>=1.26\"]\n",
+        )
+        .unwrap();
+        assert_eq!(requirements(path), ["numpy>=1.26"]);
+        assert_eq!(module_name(path), "snake_game");
+        fs::write(
+            dir.path().join("requirements.txt"),
+            "# pinned\nrequests==2.31  # http\n-r dev.txt\n\nattrs\n",
+        )
+        .unwrap();
+        assert_eq!(requirements(path), ["requests==2.31", "attrs"]);
+    }
+
+    #[test]
+    fn test_pyodide_url() {
+        assert_eq!(pyodide_url(None, false).unwrap(), DEFAULT_PYODIDE_URL);
+        let local = Some("http://localhost:9000/pyodide".to_string());
+        assert_eq!(
+            pyodide_url(local.clone(), false).unwrap(),
+            "http://localhost:9000/pyodide/"
+        );
+        // Offline, only a configured copy will do
+        assert!(pyodide_url(None, true)
+            .unwrap_err()
+            .contains("python.pyodide_url"));
+        assert_eq!(
+            pyodide_url(local, true).unwrap(),
+            "http://localhost:9000/pyodide/"
+        );
+    }
+
+    #[test]
+    fn test_bundle_carries_the_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.py"), "import game.board").unwrap();
+        fs::create_dir_all(dir.path().join("game")).unwrap();
+        fs::write(dir.path().join("game/board.py"), "SIZE = 8").unwrap();
+        fs::create_dir_all(dir.path().join(".venv/lib")).unwrap();
+        fs::write(dir.path().join(".venv/lib/six.py"), "").unwrap();
+        fs::create_dir_all(dir.path().join("__pycache__")).unwrap();
+        fs::write(dir.path().join("__pycache__/main.py"), "").unwrap();
+        let out = tempfile::tempdir().unwrap();
+
+        let config = BuildConfig {
+            project_path: dir.path().to_string_lossy().to_string(),
+            output_dir: out.path().to_string_lossy().to_string(),
+            ..BuildConfig::default()
+        };
+        let result = PythonPlugin::new().build(&config).unwrap();
+        let glue = fs::read_to_string(result.js_path.unwrap()).unwrap();
+        assert!(glue.contains(&format!("const PYODIDE = \"{DEFAULT_PYODIDE_URL}\";")));
+        assert!(crate::server::pyodide::is_pyodide_bootstrap(&glue));
+
+        let wasm = fs::read(&result.wasm_path).unwrap();
+        wasmparser::validate(&wasm).unwrap();
+        let section = wasmparser::Parser::new(0)
+            .parse_all(&wasm)
+            .find_map(|payload| match payload.unwrap() {
+                wasmparser::Payload::CustomSection(reader) if reader.name() == BUNDLE_SECTION => {
+                    Some(reader.data().to_vec())
+                }
+                _ => None,
+            })
+            .unwrap();
+        let bundle: serde_json::Value = serde_json::from_slice(&section).unwrap();
+        assert_eq!(bundle["entry"], "main.py");
+        let files: Vec<&String> = bundle["files"].as_object().unwrap().keys().collect();
+        assert_eq!(files, ["game/board.py", "main.py"]);
+        assert_eq!(bundle["requirements"], serde_json::json!([]));
+    }
+}
@@ -0,0 +1,629 @@
+//! Rust projects built with Cargo, without an external plugin
+//!
+//! A crate that depends on `wasm-bindgen` is built for
+//! `wasm32-unknown-unknown` and run through the `wasm-bindgen` CLI, which
+//! writes the module and its JS glue to the output directory. Other crates
+//! build their library for `wasm32-unknown-unknown` when it is a `cdylib`,
+//! and their binary for `wasm32-wasip1` otherwise. Release builds go through
+//! the wasm-opt built into wasmrun. An installed `wasmrust` plugin still takes
+//! precedence, since external plugins are consulted first.
+
+use crate::compiler::builder::{BuildConfig, BuildResult, OptimizationLevel, WasmBuilder};
+use crate::compiler::cargo_target::{CargoTarget, PackageTargets, TargetKind};
+use crate::error::{CompilationError, CompilationResult};
+use crate::plugin::{Plugin, PluginCapabilities, PluginInfo, PluginType};
+use crate::utils::wasm_optimize::{self, OptLevel};
+use crate::utils::{CommandExecutor, PathResolver};
+use crate::warn_println;
+use clap::ValueEnum;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const LANGUAGE: &str = "Rust";
+
+/// Target for libraries and wasm-bindgen crates
+pub const WASM32_UNKNOWN: &str = "wasm32-unknown-unknown";
+/// Target for binaries with a `main`, which need WASI
+pub const WASM32_WASI: &str = "wasm32-wasip1";
+
+/// Rust WebAssembly plugin
+#[derive(Clone)]
+pub struct RustPlugin {
+    info: PluginInfo,
+}
+
+/// The parts of `Cargo.toml` that decide how a crate is built
+#[derive(Debug, Default, PartialEq)]
+struct CrateManifest {
+    /// Name of the library's `.wasm`, with hyphens turned into underscores
+    lib_name: String,
+    cdylib: bool,
+    uses_wasm_bindgen: bool,
+}
+
+impl CrateManifest {
+    fn load(project_path: &Path) -> Option<Self> {
+        let manifest: toml::Table = fs::read_to_string(project_path.join("Cargo.toml"))
+            .ok()?
+            .parse()
+            .ok()?;
+        Self::parse(&manifest)
+    }
+
+    fn parse(manifest: &toml::Table) -> Option<Self> {
+        let package = manifest.get("package")?.as_table()?;
+        let lib = manifest.get("lib").and_then(toml::Value::as_table);
+        let lib_name = lib
+            .and_then(|lib| lib.get("name"))
+            .or_else(|| package.get("name"))
+            .and_then(toml::Value::as_str)?
+            .replace('-', "_");
+        let cdylib = lib
+            .and_then(|lib| lib.get("crate-type"))
+            .and_then(toml::Value::as_array)
+            .is_some_and(|types| types.iter().any(|kind| kind.as_str() == Some("cdylib")));
+
+        // Dependencies may also be declared per target, e.g. for wasm32 only
+        let has_dependency = |table: &toml::Table| {
+            table
+                .get("dependencies")
+                .and_then(toml::Value::as_table)
+                .is_some_and(|deps| deps.contains_key("wasm-bindgen"))
+        };
+        let uses_wasm_bindgen = has_dependency(manifest)
+            || manifest
+                .get("target")
+                .and_then(toml::Value::as_table)
+                .is_some_and(|targets| {
+                    targets
+                        .values()
+                        .filter_map(toml::Value::as_table)
+                        .any(has_dependency)
+                });
+
+        Some(Self {
+            lib_name,
+            cdylib,
+            uses_wasm_bindgen,
+        })
+    }
+}
+
+/// How one build of a crate runs
+#[derive(Debug, PartialEq)]
+struct Plan {
+    target: &'static str,
+    /// `--lib`, `--bin <name>` or `--example <name>`
+    selection: Vec<String>,
+    /// The module, relative to `target/<triple>/<profile>/`
+    artifact: String,
+    wasm_bindgen: bool,
+}
+
+impl Plan {
+    fn new(
+        manifest: &CrateManifest,
+        targets: &PackageTargets,
+        selected: Option<&CargoTarget>,
+    ) -> Result<Self, String> {
+        let target = if manifest.uses_wasm_bindgen {
+            WASM32_UNKNOWN
+        } else {
+            WASM32_WASI
+        };
+        let binary = |binary: &CargoTarget| {
+            let artifact = match binary.kind {
+                TargetKind::Bin => format!("{}.wasm", binary.name),
+                TargetKind::Example => format!("examples/{}.wasm", binary.name),
+            };
+            Self {
+                target,
+                selection: binary
+                    .cargo_args()
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect(),
+                artifact,
+                wasm_bindgen: manifest.uses_wasm_bindgen,
+            }
+        };
+
+        if let Some(selected) = selected {
+            return Ok(binary(selected));
+        }
+        if targets.has_lib && (manifest.cdylib || manifest.uses_wasm_bindgen) {
+            return Ok(Self {
+                target: WASM32_UNKNOWN,
+                selection: vec!["--lib".to_string()],
+                artifact: format!("{}.wasm", manifest.lib_name),
+                wasm_bindgen: manifest.uses_wasm_bindgen,
+            });
+        }
+        let bin = targets
+            .default_run
+            .clone()
+            .or_else(|| match targets.bins.as_slice() {
+                [only] => Some(only.clone()),
+                _ => None,
+            });
+        match bin {
+            Some(name) => Ok(binary(&CargoTarget {
+                kind: TargetKind::Bin,
+                name,
+            })),
+            None if targets.has_lib => Err(
+                "the library is not a cdylib; add `crate-type = [\"cdylib\"]` under [lib] in Cargo.toml, or pick a binary with --bin"
+                    .to_string(),
+            ),
+            None if targets.bins.is_empty() => {
+                Err("the crate has no library or binary to build".to_string())
+            }
+            None => Err(format!(
+                "the crate has several binaries ({}); pick one with --bin",
+                targets.bins.join(", ")
+            )),
+        }
+    }
+
+    fn cargo_args(&self, config: &BuildConfig) -> Vec<String> {
+        let mut args = vec![
+            "build".to_string(),
+            "--target".to_string(),
+            self.target.to_string(),
+        ];
+        if !matches!(config.optimization_level, OptimizationLevel::Debug) {
+            args.push("--release".to_string());
+        }
+        args.extend(self.selection.iter().cloned());
+        if !config.cargo_features.is_empty() {
+            args.push("--features".to_string());
+            args.push(config.cargo_features.join(","));
+        }
+        args
+    }
+
+    /// Where Cargo leaves the module, given its target directory
+    fn artifact_path(&self, target_dir: &Path, config: &BuildConfig) -> PathBuf {
+        let profile = match config.optimization_level {
+            OptimizationLevel::Debug => "debug",
+            OptimizationLevel::Release | OptimizationLevel::Size => "release",
+        };
+        target_dir
+            .join(self.target)
+            .join(profile)
+            .join(&self.artifact)
+    }
+}
+
+impl RustPlugin {
+    pub fn new() -> Self {
+        let info = PluginInfo {
+            name: "rust".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            description: "Rust WebAssembly compiler using Cargo".to_string(),
+            author: "Wasmrun Team".to_string(),
+            extensions: vec!["rs".to_string()],
+            entry_files: vec!["Cargo.toml".to_string()],
+            plugin_type: PluginType::Builtin,
+            source: None,
+            dependencies: vec![],
+            capabilities: PluginCapabilities {
+                compile_wasm: true,
+                compile_webapp: false,
+                live_reload: true,
+                optimization: true,
+                custom_targets: vec![WASM32_UNKNOWN.to_string(), WASM32_WASI.to_string()],
+                supported_languages: Some(vec!["rust".to_string()]),
+            },
+        };
+
+        Self { info }
+    }
+
+    fn failed(reason: impl Into<String>) -> CompilationError {
+        CompilationError::BuildFailed {
+            language: LANGUAGE.to_string(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Cargo's target directory for the crate, which is shared in a workspace
+    fn target_dir(project_path: &str) -> PathBuf {
+        CommandExecutor::execute_command(
+            "cargo",
+            &["metadata", "--format-version", "1", "--no-deps"],
+            project_path,
+            false,
+        )
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| serde_json::from_slice::<serde_json::Value>(&output.stdout).ok())
+        .and_then(|metadata| metadata["target_directory"].as_str().map(PathBuf::from))
+        .unwrap_or_else(|| Path::new(project_path).join("target"))
+    }
+
+    /// Fail early, with the fix, when rustup manages the toolchain and lacks `target`
+    fn check_target_installed(target: &str, project_path: &str) -> CompilationResult<()> {
+        if !CommandExecutor::is_tool_installed("rustup") {
+            return Ok(());
+        }
+        let Ok(output) = CommandExecutor::execute_command(
+            "rustup",
+            &["target", "list", "--installed"],
+            project_path,
+            false,
+        ) else {
+            return Ok(());
+        };
+        let installed = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() || installed.lines().any(|line| line.trim() == target) {
+            return Ok(());
+        }
+        Err(Self::failed(format!(
+            "the {target} target is not installed; add it with `rustup target add {target}`"
+        )))
+    }
+
+    fn wasm_bindgen_args(artifact: &Path, config: &BuildConfig) -> Vec<String> {
+        vec![
+            "--target".to_string(),
+            "web".to_string(),
+            "--no-typescript".to_string(),
+            "--out-dir".to_string(),
+            config.output_dir.clone(),
+            artifact.to_string_lossy().to_string(),
+        ]
+    }
+
+    /// Write the module and its JS glue to the output directory
+    fn run_wasm_bindgen(artifact: &Path, config: &BuildConfig) -> CompilationResult<BuildResult> {
+        if !CommandExecutor::is_tool_installed("wasm-bindgen") {
+            return Err(CompilationError::BuildToolNotFound {
+                tool: "wasm-bindgen (cargo install wasm-bindgen-cli)".to_string(),
+                language: LANGUAGE.to_string(),
+            });
+        }
+        let args = Self::wasm_bindgen_args(artifact, config);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let output = CommandExecutor::execute_command(
+            "wasm-bindgen",
+            &args,
+            &config.project_path,
+            config.verbose,
+        )?;
+        if !output.status.success() {
+            return Err(Self::failed(format!(
+                "wasm-bindgen failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        let stem = artifact
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let output_dir = Path::new(&config.output_dir);
+        Ok(BuildResult {
+            wasm_path: output_dir
+                .join(format!("{stem}_bg.wasm"))
+                .to_string_lossy()
+                .to_string(),
+            js_path: Some(
+                output_dir
+                    .join(format!("{stem}.js"))
+                    .to_string_lossy()
+                    .to_string(),
+            ),
+            additional_files: vec![],
+            is_wasm_bindgen: true,
+        })
+    }
+
+    /// The wasm-opt level for the build, if it is optimized at all
+    fn wasm_opt_level(config: &BuildConfig) -> Option<OptLevel> {
+        match config.optimization_level {
+            OptimizationLevel::Debug => None,
+            OptimizationLevel::Release => Some(OptLevel::O2),
+            OptimizationLevel::Size => Some(OptLevel::Oz),
+        }
+    }
+
+    /// Optimize the module in place; a module wasm-opt can't handle is kept as built
+    fn run_wasm_opt(wasm_path: &str, config: &BuildConfig) {
+        let Some(level) = Self::wasm_opt_level(config) else {
+            return;
+        };
+        println!("⚡ Optimizing with wasm-opt {level}...");
+        let path = Path::new(wasm_path);
+        if let Err(e) = wasm_optimize::optimize(path, path, level) {
+            warn_println!("wasm-opt failed, serving the unoptimized module: {e}");
+        }
+    }
+}
+
+impl Plugin for RustPlugin {
+    fn info(&self) -> &PluginInfo {
+        &self.info
+    }
+
+    fn can_handle_project(&self, project_path: &str) -> bool {
+        Path::new(project_path).join("Cargo.toml").is_file()
+    }
+
+    fn get_builder(&self) -> Box<dyn WasmBuilder> {
+        Box::new(RustPlugin::new())
+    }
+}
+
+impl WasmBuilder for RustPlugin {
+    fn supported_extensions(&self) -> &[&str] {
+        &["rs"]
+    }
+
+    fn entry_file_candidates(&self) -> &[&str] {
+        &["Cargo.toml"]
+    }
+
+    fn language_name(&self) -> &str {
+        LANGUAGE
+    }
+
+    fn check_dependencies(&self) -> Vec<String> {
+        let mut missing = Vec::new();
+
+        if !CommandExecutor::is_tool_installed("cargo") {
+            missing.push("cargo (Rust toolchain - install from https://rustup.rs)".to_string());
+        }
+
+        missing
+    }
+
+    fn validate_project(&self, project_path: &str) -> CompilationResult<()> {
+        PathResolver::validate_directory_exists(project_path).map_err(|e| {
+            CompilationError::InvalidProjectStructure {
+                language: LANGUAGE.to_string(),
+                reason: format!("Project directory validation failed: {e}"),
+            }
+        })?;
+
+        if CrateManifest::load(Path::new(project_path)).is_none() {
+            return Err(CompilationError::InvalidProjectStructure {
+                language: LANGUAGE.to_string(),
+                reason:
+                    "Cargo.toml has no [package]; run wasmrun on a member crate of the workspace"
+                        .to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn build(&self, config: &BuildConfig) -> CompilationResult<BuildResult> {
+        if !CommandExecutor::is_tool_installed("cargo") {
+            return Err(CompilationError::BuildToolNotFound {
+                tool: "cargo".to_string(),
+                language: LANGUAGE.to_string(),
+            });
+        }
+
+        let project = Path::new(&config.project_path);
+        let (Some(manifest), Some(targets)) =
+            (CrateManifest::load(project), PackageTargets::load(project))
+        else {
+            return Err(Self::failed("Cargo.toml has no [package]"));
+        };
+        let plan =
+            Plan::new(&manifest, &targets, config.cargo_target.as_ref()).map_err(Self::failed)?;
+        Self::check_target_installed(plan.target, &config.project_path)?;
+
+        PathResolver::ensure_output_directory(&config.output_dir).map_err(|_| {
+            CompilationError::OutputDirectoryCreationFailed {
+                path: config.output_dir.clone(),
+            }
+        })?;
+
+        println!("🔨 Building with Cargo for {}...", plan.target);
+        let args = plan.cargo_args(config);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let build_output =
+            CommandExecutor::execute_command("cargo", &args, &config.project_path, config.verbose)?;
+        if !build_output.status.success() {
+            return Err(Self::failed(format!(
+                "Cargo build failed: {}",
+                String::from_utf8_lossy(&build_output.stderr)
+            )));
+        }
+
+        let artifact = plan.artifact_path(&Self::target_dir(&config.project_path), config);
+        if !artifact.is_file() {
+            return Err(Self::failed(format!(
+                "Cargo build completed but {} was not created",
+                artifact.display()
+            )));
+        }
+
+        let result = if plan.wasm_bindgen {
+            Self::run_wasm_bindgen(&artifact, config)?
+        } else {
+            let wasm_path = CommandExecutor::copy_to_output(
+                &artifact.to_string_lossy(),
+                &config.output_dir,
+                LANGUAGE,
+            )?;
+            BuildResult::new(wasm_path)
+        };
+        Self::run_wasm_opt(&result.wasm_path, config);
+        Ok(result)
+    }
+
+    fn can_handle_project(&self, project_path: &str) -> bool {
+        Path::new(project_path).join("Cargo.toml").is_file()
+    }
+
+    fn planned_commands(&self, config: &BuildConfig) -> Vec<String> {
+        let project = Path::new(&config.project_path);
+        let (Some(manifest), Some(targets)) =
+            (CrateManifest::load(project), PackageTargets::load(project))
+        else {
+            return Vec::new();
+        };
+        let Ok(plan) = Plan::new(&manifest, &targets, config.cargo_target.as_ref()) else {
+            return Vec::new();
+        };
+
+        let mut commands = vec![CommandExecutor::display_command(
+            "cargo",
+            &plan.cargo_args(config),
+        )];
+        let artifact = plan.artifact_path(&Self::target_dir(&config.project_path), config);
+        let output_dir = Path::new(&config.output_dir);
+        let wasm_path = if plan.wasm_bindgen {
+            commands.push(CommandExecutor::display_command(
+                "wasm-bindgen",
+                &Self::wasm_bindgen_args(&artifact, config),
+            ));
+            let stem = artifact.file_stem().unwrap_or_default().to_string_lossy();
+            output_dir.join(format!("{stem}_bg.wasm"))
+        } else {
+            commands.push(CommandExecutor::display_command(
+                "cp",
+                &[
+                    artifact.to_string_lossy().as_ref(),
+                    config.output_dir.as_str(),
+                ],
+            ));
+            output_dir.join(artifact.file_name().unwrap_or_default())
+        };
+        if let Some(level) =
+            Self::wasm_opt_level(config).and_then(|level| level.to_possible_value())
+        {
+            let wasm_path = wasm_path.to_string_lossy();
+            commands.push(CommandExecutor::display_command(
+                "wasmrun",
+                &["optimize", &wasm_path, "--level", level.get_name()],
+            ));
+        }
+        commands
+    }
+
+    fn clean(&self, project_path: &str) -> crate::error::Result<()> {
+        // Only the wasm builds, leaving host builds of the crate alone
+        for target in [WASM32_UNKNOWN, WASM32_WASI] {
+            let _ = CommandExecutor::execute_command(
+                "cargo",
+                &["clean", "--target", target],
+                project_path,
+                false,
+            );
+        }
+        Ok(())
+    }
+
+    fn clone_box(&self) -> Box<dyn WasmBuilder> {
+        Box::new(self.clone())
+    }
+}
+
+impl Default for RustPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(toml: &str) -> CrateManifest {
+        CrateManifest::parse(&toml.parse().unwrap()).unwrap()
+    }
+
+    fn targets(has_lib: bool, bins: &[&str]) -> PackageTargets {
+        PackageTargets {
+            has_lib,
+            bins: bins.iter().map(|name| name.to_string()).collect(),
+            ..PackageTargets::default()
+        }
+    }
+
+    #[test]
+    fn test_manifest_detects_wasm_bindgen_and_cdylib() {
+        let crate_manifest = manifest(
+            r#"
+            [package]
+            name = "my-app"
+            [lib]
+            crate-type = ["cdylib", "rlib"]
+            [target.'cfg(target_arch = "wasm32")'.dependencies]
+            wasm-bindgen = "0.2"
+            "#,
+        );
+        assert_eq!(
+            crate_manifest,
+            CrateManifest {
+                lib_name: "my_app".to_string(),
+                cdylib: true,
+                uses_wasm_bindgen: true,
+            }
+        );
+        assert!(CrateManifest::parse(&"[workspace]".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_wasm_bindgen_library_plan() {
+        let crate_manifest =
+            manifest("[package]\nname = \"my-app\"\n[dependencies]\nwasm-bindgen = \"0.2\"\n");
+        let plan = Plan::new(&crate_manifest, &targets(true, &[]), None).unwrap();
+        assert_eq!(plan.target, WASM32_UNKNOWN);
+        assert_eq!(plan.artifact, "my_app.wasm");
+        assert!(plan.wasm_bindgen);
+
+        let config = BuildConfig {
+            cargo_features: vec!["simd".to_string(), "fast".to_string()],
+            ..BuildConfig::default()
+        };
+        assert_eq!(
+            plan.cargo_args(&config).join(" "),
+            "build --target wasm32-unknown-unknown --release --lib --features simd,fast"
+        );
+        assert_eq!(
+            plan.artifact_path(Path::new("/p/target"), &config),
+            Path::new("/p/target/wasm32-unknown-unknown/release/my_app.wasm")
+        );
+    }
+
+    #[test]
+    fn test_binary_plan_targets_wasi() {
+        let crate_manifest = manifest("[package]\nname = \"hello\"\n");
+        let plan = Plan::new(&crate_manifest, &targets(false, &["hello"]), None).unwrap();
+        assert_eq!(plan.target, WASM32_WASI);
+        assert_eq!(plan.selection, ["--bin", "hello"]);
+        assert_eq!(plan.artifact, "hello.wasm");
+        assert!(!plan.wasm_bindgen);
+
+        let example = CargoTarget {
+            kind: TargetKind::Example,
+            name: "demo".to_string(),
+        };
+        let plan = Plan::new(&crate_manifest, &targets(false, &["hello"]), Some(&example)).unwrap();
+        assert_eq!(plan.artifact, "examples/demo.wasm");
+
+        let config = BuildConfig {
+            optimization_level: OptimizationLevel::Debug,
+            ..BuildConfig::default()
+        };
+        assert_eq!(
+            plan.cargo_args(&config).join(" "),
+            "build --target wasm32-wasip1 --example demo"
+        );
+    }
+
+    #[test]
+    fn test_plan_needs_something_to_build() {
+        let crate_manifest = manifest("[package]\nname = \"tools\"\n");
+        let error = Plan::new(&crate_manifest, &targets(true, &[]), None).unwrap_err();
+        assert!(error.contains("cdylib"));
+        let error = Plan::new(&crate_manifest, &targets(false, &["a", "b"]), None).unwrap_err();
+        assert!(error.contains("a, b"));
+    }
+}
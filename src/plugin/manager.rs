@@ -1,16 +1,18 @@
 //! Plugin management and registry
 
 use crate::compiler::builder::WasmBuilder;
-use crate::config::{ExternalPluginEntry, WasmrunConfig};
+use crate::config::{active_settings, ExternalPluginEntry, WasmrunConfig};
 use crate::error::{Result, WasmrunError};
 use crate::plugin::builtin::load_all_builtin_plugins;
 use crate::plugin::external::ExternalPluginLoader;
 use crate::plugin::installer::PluginInstaller;
 use crate::plugin::registry::PluginRegistry;
 use crate::plugin::{Plugin, PluginCapabilities, PluginInfo, PluginSource};
-use crate::utils::PluginUtils;
+use crate::registry::Registry;
+use crate::utils::{DryRun, PluginUtils};
 use crate::{debug_enter, debug_exit, debug_println};
 use std::collections::HashMap;
+use std::path::Path;
 use std::process::Command;
 
 #[derive(Debug, Clone)]
@@ -53,6 +55,8 @@ impl PluginManager {
             },
         };
 
+        manager.apply_registry_state();
+
         debug_println!("Loading all plugins");
         manager.load_all_plugins()?;
         manager.update_stats();
@@ -66,6 +70,38 @@ impl PluginManager {
         Ok(manager)
     }
 
+    /// Apply enable/disable state from the registry, seeding it from the config
+    /// for plugins the registry has not seen yet
+    fn apply_registry_state(&mut self) {
+        let registry = match Registry::open() {
+            Ok(registry) => registry,
+            Err(e) => {
+                debug_println!("Registry unavailable, using plugin state from config: {e}");
+                return;
+            }
+        };
+
+        for (name, entry) in self.config.external_plugins.iter_mut() {
+            match registry.plugin_enabled(name) {
+                Ok(Some(enabled)) => entry.enabled = enabled,
+                Ok(None) => {
+                    if let Err(e) = registry.set_plugin_enabled(name, entry.enabled) {
+                        debug_println!("Failed to record state for plugin {name}: {e}");
+                    }
+                }
+                Err(e) => debug_println!("Failed to read state for plugin {name}: {e}"),
+            }
+        }
+    }
+
+    fn save_plugin_state(plugin_name: &str, enabled: Option<bool>) -> Result<()> {
+        let registry = Registry::open()?;
+        match enabled {
+            Some(enabled) => registry.set_plugin_enabled(plugin_name, enabled),
+            None => registry.remove_plugin(plugin_name),
+        }
+    }
+
     fn load_all_plugins(&mut self) -> Result<()> {
         load_all_builtin_plugins(&mut self.builtin_plugins)?;
 
@@ -123,6 +159,44 @@ impl PluginManager {
         None
     }
 
+    /// The plugin building `project_path`: `build.plugin` from wasmrun.toml,
+    /// else the first plugin that recognises the project; none when
+    /// `build.command` builds it
+    pub fn plugin_for_build(&self, project_path: &str) -> Option<&dyn Plugin> {
+        let build = active_settings().build;
+        match (&build.plugin, &build.command) {
+            (Some(name), _) => self.find_plugin_by_name(name),
+            (None, Some(_)) => None,
+            (None, None) => self.find_plugin_for_project(project_path),
+        }
+    }
+
+    pub fn dispatch_pre_build(&self, project_path: &str) -> Result<()> {
+        match self.plugin_for_build(project_path) {
+            Some(plugin) => plugin.pre_build(project_path),
+            None => Ok(()),
+        }
+    }
+
+    pub fn dispatch_post_build(&self, project_path: &str, wasm_path: &str) -> Result<()> {
+        match self.plugin_for_build(project_path) {
+            Some(plugin) => plugin.post_build(project_path, wasm_path),
+            None => Ok(()),
+        }
+    }
+
+    pub fn dispatch_serve_start(&self, project_path: &str, url: &str) {
+        if let Some(plugin) = self.plugin_for_build(project_path) {
+            plugin.on_serve_start(project_path, url);
+        }
+    }
+
+    pub fn dispatch_file_change(&self, project_path: &str, paths: &[&Path]) {
+        if let Some(plugin) = self.plugin_for_build(project_path) {
+            plugin.on_file_change(project_path, paths);
+        }
+    }
+
     pub fn find_plugin_for_language(&self, language: &str) -> Option<&dyn Plugin> {
         // Check external plugins first using the common utility
         for plugin in self.external_plugins.values() {
@@ -249,6 +323,12 @@ impl PluginManager {
     }
 
     pub fn update_plugin(&mut self, plugin_name: &str) -> Result<()> {
+        crate::offline::require_network(if plugin_name == "all" {
+            "Updating plugins".to_string()
+        } else {
+            format!("Updating plugin '{plugin_name}'")
+        })?;
+
         if plugin_name == "all" {
             self.update_all_external_plugins()
         } else {
@@ -339,6 +419,10 @@ impl PluginManager {
     }
 
     fn get_latest_crates_io_version(&self, crate_name: &str) -> Result<String> {
+        crate::offline::require_network(format!(
+            "Checking crates.io for the latest version of '{crate_name}'"
+        ))?;
+
         // Use cargo search to find the latest version
         let output = std::process::Command::new("cargo")
             .args(["search", crate_name, "--limit", "1"])
@@ -447,11 +531,12 @@ impl PluginManager {
         if let Some(entry) = self.config.external_plugins.get_mut(plugin_name) {
             entry.enabled = true;
             self.config.save()?;
+            Self::save_plugin_state(plugin_name, Some(true))?;
             self.reload_single_plugin(plugin_name)?;
         } else {
-            return Err(WasmrunError::from(format!(
-                "Plugin '{plugin_name}' not found"
-            )));
+            return Err(WasmrunError::PluginNotFound {
+                name: plugin_name.to_string(),
+            });
         }
         Ok(())
     }
@@ -460,12 +545,13 @@ impl PluginManager {
         if let Some(entry) = self.config.external_plugins.get_mut(plugin_name) {
             entry.enabled = false;
             self.config.save()?;
+            Self::save_plugin_state(plugin_name, Some(false))?;
             self.external_plugins.remove(plugin_name);
             self.update_stats();
         } else {
-            return Err(WasmrunError::from(format!(
-                "Plugin '{plugin_name}' not found"
-            )));
+            return Err(WasmrunError::PluginNotFound {
+                name: plugin_name.to_string(),
+            });
         }
         Ok(())
     }
@@ -478,12 +564,25 @@ impl PluginManager {
         if self.config.external_plugins.contains_key(plugin_name) {
             self.config.external_plugins.remove(plugin_name);
             self.config.save()?;
+            Self::save_plugin_state(plugin_name, None)?;
         }
 
         self.update_stats();
         Ok(())
     }
 
+    /// Add the steps [`uninstall_plugin`](Self::uninstall_plugin) would take to `plan`
+    pub fn plan_uninstall(&self, plugin_name: &str, plan: &mut DryRun) -> Result<()> {
+        if self.config.external_plugins.contains_key(plugin_name) {
+            plan.write(WasmrunConfig::config_path()?);
+            plan.note(format!(
+                "forget plugin '{plugin_name}' in the registry; its files in {} stay",
+                self.get_plugin_directory(plugin_name)?.display()
+            ));
+        }
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub fn get_plugin_capabilities(&self, plugin_name: &str) -> Option<&PluginCapabilities> {
         if let Some(info) = self.get_plugin_info(plugin_name) {
@@ -518,6 +617,7 @@ impl PluginManager {
                     }
                 }
                 PluginSource::Local { path } => Some(format!("Local: {}", path.display())),
+                PluginSource::Url { url, .. } => Some(format!("URL: {url}")),
             }
         } else {
             None
@@ -910,6 +1010,26 @@ impl PluginManager {
 
     /// Install plugin
     pub fn install_plugin(&mut self, plugin_name: &str) -> Result<()> {
+        #[cfg(not(target_os = "windows"))]
+        if crate::plugin::dylib::is_library_path(plugin_name) {
+            let path = Path::new(plugin_name);
+            let source = PluginSource::Local {
+                path: path.canonicalize().unwrap_or_else(|_| path.to_path_buf()),
+            };
+            return self.install_library_plugin(path, source);
+        }
+        #[cfg(not(target_os = "windows"))]
+        if let Some((url, sha256)) = crate::plugin::dylib::library_url(plugin_name) {
+            let (path, sha256) = crate::plugin::dylib::download_library(url, sha256)?;
+            let source = PluginSource::Url {
+                url: url.to_string(),
+                sha256,
+            };
+            let installed = self.install_library_plugin(&path, source);
+            let _ = std::fs::remove_file(&path);
+            return installed;
+        }
+
         // Check if already installed
         if self.is_plugin_installed(plugin_name) {
             return Err(WasmrunError::from(format!(
@@ -930,6 +1050,84 @@ impl PluginManager {
 
         Ok(())
     }
+
+    /// Add the steps [`install_plugin`](Self::install_plugin) would take to `plan`
+    pub fn plan_install(&self, plugin_name: &str, plan: &mut DryRun) -> Result<()> {
+        #[cfg(not(target_os = "windows"))]
+        if crate::plugin::dylib::is_library_path(plugin_name) {
+            // Verifying the library means loading it and running its code
+            let path = Path::new(plugin_name);
+            let name = crate::plugin::dylib::name_from_file(path);
+            plan.note(format!(
+                "load {} to check its ABI version and read its metadata (skipped in a dry run)",
+                path.display()
+            ));
+            let target = crate::plugin::dylib::install_target(path, &name)?;
+            if let Some(plugin_dir) = target.parent() {
+                plan.create_dir(plugin_dir);
+                plan.write(&target);
+                plan.write(plugin_dir.join(crate::plugin::dylib::METADATA_FILE));
+                plan.write(plugin_dir.join(".wasmrun_metadata"));
+            }
+            plan.write(WasmrunConfig::config_path()?);
+            plan.note(format!(
+                "register the plugin, presumably named '{name}' after the file"
+            ));
+            return Ok(());
+        }
+        #[cfg(not(target_os = "windows"))]
+        if let Some((url, sha256)) = crate::plugin::dylib::library_url(plugin_name) {
+            crate::plugin::dylib::check_source(url, sha256)?;
+            let path = crate::plugin::dylib::download_path(url)?;
+            if sha256.is_none() {
+                plan.note(format!(
+                    "fetch {url}.sha256, and stop if it isn't published"
+                ));
+            }
+            plan.note(format!("download {url}"));
+            plan.write(crate::utils::download::part_path(&path));
+            plan.note(match sha256 {
+                Some(sha256) => format!("check the download against SHA-256 {sha256}"),
+                None => format!("check the download against {url}.sha256"),
+            });
+            plan.write(&path);
+            plan.note("install the downloaded library as a local one, then remove it");
+            plan.write(WasmrunConfig::config_path()?);
+            return Ok(());
+        }
+
+        if self.is_plugin_installed(plugin_name) {
+            return Err(WasmrunError::from(format!(
+                "Plugin '{plugin_name}' is already installed"
+            )));
+        }
+        PluginInstaller::plan_install(plugin_name, plan)?;
+        plan.write(WasmrunConfig::config_path()?);
+        plan.note(format!("register plugin '{plugin_name}'"));
+        Ok(())
+    }
+
+    /// Install a prebuilt plugin library from `path`, recording where it came from
+    #[cfg(not(target_os = "windows"))]
+    fn install_library_plugin(&mut self, path: &Path, source: PluginSource) -> Result<()> {
+        let metadata = crate::plugin::dylib::install(path)?;
+        let plugin_name = metadata.name.clone();
+        if self.external_plugins.contains_key(&plugin_name) {
+            println!("🔄 Replacing the loaded '{plugin_name}' plugin");
+            self.external_plugins.remove(&plugin_name);
+        }
+
+        self.register_installed_plugin(&plugin_name)?;
+        if let Some(entry) = self.config.external_plugins.get_mut(&plugin_name) {
+            entry.source = source;
+            self.config.save()?;
+        }
+        println!(
+            "🔌 Plugin '{}' installation completed (v{})",
+            plugin_name, metadata.version
+        );
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -16,6 +16,8 @@ pub struct PluginMetadata {
     pub dependencies: MetadataDependencies,
     pub exports: Option<MetadataExports>,
     pub frameworks: Option<MetadataFrameworks>,
+    /// Lifecycle hooks and routes the plugin implements (plugin API v2)
+    pub hooks: Option<MetadataHooks>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +47,20 @@ pub struct MetadataExports {
     pub plugin_create: String,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetadataHooks {
+    /// Events from [`hooks::EVENTS`](crate::plugin::hooks::EVENTS)
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// URL prefixes the plugin answers on the dev server
+    #[serde(default)]
+    pub routes: Vec<String>,
+    /// Events from [`events::EVENTS`](crate::plugin::events::EVENTS) sent to
+    /// the plugin whether or not it builds the project
+    #[serde(default)]
+    pub subscribe: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetadataFrameworks {
     pub supported: Vec<String>,
@@ -72,6 +88,11 @@ struct CargoMetadata {
 
 impl PluginMetadata {
     pub fn from_installed_plugin(plugin_dir: &Path) -> Result<Self> {
+        #[cfg(not(target_os = "windows"))]
+        if let Some(metadata) = crate::plugin::dylib::installed_metadata(plugin_dir) {
+            return metadata;
+        }
+
         let cargo_toml_path = plugin_dir.join("Cargo.toml");
         if !cargo_toml_path.exists() {
             return Err(WasmrunError::from(
@@ -116,6 +137,10 @@ impl PluginMetadata {
             return Ok(metadata);
         }
 
+        crate::offline::require_network(format!(
+            "Fetching metadata for '{crate_name}' from crates.io"
+        ))?;
+
         // Try to download Cargo.toml from crates.io API (future implementation)
         if let Ok(metadata) = Self::from_crates_io_api(crate_name) {
             return Ok(metadata);
@@ -242,6 +267,7 @@ impl PluginMetadata {
             },
             exports: Some(Self::create_default_exports(&name)),
             frameworks: None,
+            hooks: None,
         }
     }
 
@@ -275,7 +301,7 @@ impl PluginMetadata {
         }
     }
 
-    fn create_default_exports(plugin_name: &str) -> MetadataExports {
+    pub(crate) fn create_default_exports(plugin_name: &str) -> MetadataExports {
         let prefix = plugin_name.replace('-', "_");
         MetadataExports {
             create_wasm_builder: "create_wasm_builder".to_string(),
@@ -331,27 +357,46 @@ impl PluginMetadata {
             ));
         }
 
+        if let Some(hooks) = &self.hooks {
+            if let Some(event) = hooks
+                .events
+                .iter()
+                .find(|event| !crate::plugin::hooks::EVENTS.contains(&event.as_str()))
+            {
+                return Err(WasmrunError::from(format!(
+                    "Unknown plugin hook '{event}' (expected one of: {})",
+                    crate::plugin::hooks::EVENTS.join(", ")
+                )));
+            }
+            if let Some(event) = hooks
+                .subscribe
+                .iter()
+                .find(|event| !crate::plugin::events::EVENTS.contains(&event.as_str()))
+            {
+                return Err(WasmrunError::from(format!(
+                    "Unknown plugin event '{event}' (expected one of: {})",
+                    crate::plugin::events::EVENTS.join(", ")
+                )));
+            }
+            if let Some(route) = hooks.routes.iter().find(|route| !route.starts_with('/')) {
+                return Err(WasmrunError::from(format!(
+                    "Plugin route '{route}' must start with '/'"
+                )));
+            }
+        }
+
         Ok(())
     }
 }
 
 /// Download crate metadata from crates.io API
 fn download_crate_metadata_from_api(crate_name: &str) -> Result<PluginMetadata> {
-    let output = std::process::Command::new("curl")
-        .arg("-s")
-        .arg(format!("https://crates.io/api/v1/crates/{crate_name}"))
-        .output()
-        .map_err(|e| {
-            WasmrunError::from(format!("Failed to download metadata from crates.io: {e}"))
-        })?;
-
-    if !output.status.success() {
-        return Err(WasmrunError::from(format!(
-            "Failed to query crates.io API for {crate_name}"
-        )));
-    }
-
-    let response = String::from_utf8_lossy(&output.stdout);
+    crate::offline::require_network(format!(
+        "Fetching metadata for '{crate_name}' from crates.io"
+    ))?;
+    let response = crate::utils::download::get_string(&format!(
+        "https://crates.io/api/v1/crates/{crate_name}"
+    ))?;
     parse_crates_io_metadata_response(crate_name, &response)
 }
 
@@ -409,6 +454,7 @@ fn parse_crates_io_metadata_response(crate_name: &str, response: &str) -> Result
         },
         exports: None,
         frameworks: None,
+        hooks: None,
     })
 }
 
@@ -1,29 +1,88 @@
 //! Plugin system for Wasmrun
 
 use crate::compiler::builder::WasmBuilder;
+use crate::error::Result;
+use events::Event;
+use hooks::{RouteRequest, RouteResponse};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub mod bridge;
 pub mod builtin;
+#[cfg(not(target_os = "windows"))]
+pub mod dylib;
+pub mod events;
 pub mod external;
+pub mod hooks;
 pub mod installer;
 pub mod languages;
 pub mod manager;
 pub mod metadata;
 pub mod registry;
+pub mod search;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PluginSource {
-    CratesIo { name: String, version: String },
-    Git { url: String, branch: Option<String> },
-    Local { path: PathBuf },
+    CratesIo {
+        name: String,
+        version: String,
+    },
+    Git {
+        url: String,
+        branch: Option<String>,
+    },
+    Local {
+        path: PathBuf,
+    },
+    /// A prebuilt library downloaded from `url`, with the digest it matched
+    Url {
+        url: String,
+        sha256: String,
+    },
 }
 
 pub trait Plugin: Send + Sync {
     fn info(&self) -> &PluginInfo;
     fn can_handle_project(&self, project_path: &str) -> bool;
     fn get_builder(&self) -> Box<dyn WasmBuilder>;
+
+    // Lifecycle hooks for projects this plugin builds; see [`hooks`]
+
+    /// Runs before each build; an error stops the build
+    fn pre_build(&self, _project_path: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Runs after each successful build of `wasm_path`; an error fails the build
+    fn post_build(&self, _project_path: &str, _wasm_path: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Runs once the dev server listens at `url`
+    fn on_serve_start(&self, _project_path: &str, _url: &str) {}
+
+    /// Runs when the watcher sees `paths` change, relative to the project
+    fn on_file_change(&self, _project_path: &str, _paths: &[&Path]) {}
+
+    /// URL prefixes this plugin answers on the dev server, e.g. `/__wasmrust/`
+    fn routes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Answer a request under one of [`routes`](Plugin::routes); `None` is a 404
+    fn handle_route(&self, _request: &RouteRequest) -> Option<RouteResponse> {
+        None
+    }
+
+    // Events of the whole run, for any enabled plugin; see [`events`]
+
+    /// Names of the [`events::EVENTS`] this plugin wants to receive
+    fn subscriptions(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Receives each event it subscribed to, on the event thread
+    fn on_event(&self, _event: &Event) {}
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
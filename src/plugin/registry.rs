@@ -76,9 +76,9 @@ impl PluginRegistry {
             self.entries.insert(name.to_string(), entry);
             Ok(())
         } else {
-            Err(crate::error::WasmrunError::from(format!(
-                "Plugin '{name}' not found in registry"
-            )))
+            Err(crate::error::WasmrunError::PluginNotFound {
+                name: name.to_string(),
+            })
         }
     }
 
@@ -93,6 +93,8 @@ impl PluginRegistry {
             return Ok(false);
         }
 
+        crate::offline::require_network(format!("Looking up '{plugin_name}' on crates.io"))?;
+
         // Check crates.io for the plugin
         let output = std::process::Command::new("cargo")
             .args(["search", plugin_name, "--limit", "1"])
@@ -132,21 +134,13 @@ impl PluginRegistry {
 fn fetch_plugin_metadata_from_crates_io(
     plugin_name: &str,
 ) -> Result<crate::plugin::metadata::PluginMetadata> {
-    use crate::error::WasmrunError;
-
-    let output = std::process::Command::new("curl")
-        .arg("-s")
-        .arg(format!("https://crates.io/api/v1/crates/{plugin_name}"))
-        .output()
-        .map_err(|e| WasmrunError::from(format!("Failed to fetch metadata from crates.io: {e}")))?;
-
-    if !output.status.success() {
-        return Err(WasmrunError::from(format!(
-            "Failed to query crates.io for {plugin_name}"
-        )));
-    }
+    crate::offline::require_network(format!(
+        "Fetching metadata for '{plugin_name}' from crates.io"
+    ))?;
 
-    let response = String::from_utf8_lossy(&output.stdout);
+    let response = crate::utils::download::get_string(&format!(
+        "https://crates.io/api/v1/crates/{plugin_name}"
+    ))?;
     parse_crate_metadata(&response, plugin_name)
 }
 
@@ -195,27 +189,18 @@ fn parse_crate_metadata(
         },
         exports: None,
         frameworks: None,
+        hooks: None,
     })
 }
 
 fn fetch_plugin_dependencies_from_crates_io(plugin_name: &str) -> Result<Vec<String>> {
-    use crate::error::WasmrunError;
-
-    let output = std::process::Command::new("curl")
-        .arg("-s")
-        .arg(format!(
-            "https://crates.io/api/v1/crates/{plugin_name}/dependencies"
-        ))
-        .output()
-        .map_err(|e| {
-            WasmrunError::from(format!("Failed to fetch dependencies from crates.io: {e}"))
-        })?;
-
-    if !output.status.success() {
-        return Ok(vec![]);
-    }
+    crate::offline::require_network(format!(
+        "Fetching dependencies of '{plugin_name}' from crates.io"
+    ))?;
 
-    let response = String::from_utf8_lossy(&output.stdout);
+    let response = crate::utils::download::get_string(&format!(
+        "https://crates.io/api/v1/crates/{plugin_name}/dependencies"
+    ))?;
     parse_crate_dependencies(&response)
 }
 
@@ -0,0 +1,184 @@
+//! Plugin search on crates.io
+//!
+//! Plugins are published to crates.io with the `wasmrun-plugin` keyword.
+//! `wasmrun plugin search` lists those crates, caching the listing in the
+//! wasmrun cache directory so repeated searches don't hit the API. In offline
+//! mode, or when crates.io can't be reached, the cached listing is used
+//! whatever its age.
+
+use crate::config::WasmrunConfig;
+use crate::error::{Result, WasmrunError};
+use crate::utils::download;
+use crate::warn_println;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Keyword that marks a crate as a wasmrun plugin
+pub const PLUGIN_KEYWORD: &str = "wasmrun-plugin";
+
+const CRATES_API: &str = "https://crates.io/api/v1/crates";
+const PER_PAGE: usize = 100;
+/// Stop paging after this many pages, far more than the plugin ecosystem needs
+const MAX_PAGES: usize = 10;
+
+const CACHE_FILE: &str = "plugin-search.json";
+/// How long a cached listing is used before crates.io is queried again
+const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// A plugin crate as listed by crates.io
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginCrate {
+    pub name: String,
+    #[serde(rename = "max_version")]
+    pub version: String,
+    pub downloads: u64,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl PluginCrate {
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.name.to_lowercase().contains(&query)
+            || self
+                .description
+                .as_deref()
+                .is_some_and(|description| description.to_lowercase().contains(&query))
+    }
+}
+
+#[derive(Deserialize)]
+struct CratesResponse {
+    crates: Vec<PluginCrate>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SearchCache {
+    /// Seconds since the Unix epoch
+    fetched_at: u64,
+    crates: Vec<PluginCrate>,
+}
+
+/// Where a search result came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    CratesIo,
+    Cache,
+    /// Cached listing used because crates.io could not be queried
+    StaleCache,
+}
+
+/// Plugins matching `query` (all plugins without one), most downloaded first
+pub fn search(query: Option<&str>, refresh: bool) -> Result<(Vec<PluginCrate>, Source)> {
+    let cache_path = WasmrunConfig::cache_dir()?.join(CACHE_FILE);
+    let cached = read_cache(&cache_path);
+
+    let (mut crates, source) = match cached {
+        Some((crates, age)) if !refresh && age < CACHE_TTL => (crates, Source::Cache),
+        cached => match fetch_plugins() {
+            Ok(crates) => {
+                write_cache(&cache_path, &crates);
+                (crates, Source::CratesIo)
+            }
+            Err(e) => match cached {
+                Some((crates, _)) => {
+                    warn_println!("{e}; showing cached results");
+                    (crates, Source::StaleCache)
+                }
+                None => return Err(e),
+            },
+        },
+    };
+
+    if let Some(query) = query {
+        crates.retain(|plugin| plugin.matches(query));
+    }
+    crates.sort_by(|a, b| b.downloads.cmp(&a.downloads).then(a.name.cmp(&b.name)));
+    Ok((crates, source))
+}
+
+/// Every crate with the plugin keyword, across result pages
+fn fetch_plugins() -> Result<Vec<PluginCrate>> {
+    crate::offline::require_network("Searching crates.io for plugins")?;
+
+    let mut crates = Vec::new();
+    for page in 1..=MAX_PAGES {
+        let url = format!(
+            "{CRATES_API}?keyword={PLUGIN_KEYWORD}&sort=downloads&per_page={PER_PAGE}&page={page}"
+        );
+        let response: CratesResponse = serde_json::from_str(&download::get_string(&url)?)
+            .map_err(|e| WasmrunError::from(format!("Failed to parse crates.io response: {e}")))?;
+        let last_page = response.crates.len() < PER_PAGE;
+        crates.extend(response.crates);
+        if last_page {
+            break;
+        }
+    }
+    Ok(crates)
+}
+
+/// The cached listing and its age
+fn read_cache(path: &Path) -> Option<(Vec<PluginCrate>, Duration)> {
+    let cache: SearchCache = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    let age = unix_time().saturating_sub(cache.fetched_at);
+    Some((cache.crates, Duration::from_secs(age)))
+}
+
+/// Cache failures only cost a later request, so they are not reported
+fn write_cache(path: &Path, crates: &[PluginCrate]) {
+    let cache = SearchCache {
+        fetched_at: unix_time(),
+        crates: crates.to_vec(),
+    };
+    if let (Some(dir), Ok(json)) = (path.parent(), serde_json::to_string(&cache)) {
+        let _ = fs::create_dir_all(dir).and_then(|_| fs::write(path, json));
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const RESPONSE: &str = r#"{
+        "crates": [
+            {"name": "wasmrust", "max_version": "0.4.1", "downloads": 5120, "description": "Rust WebAssembly plugin for wasmrun", "keywords": null},
+            {"name": "wasmgo", "max_version": "0.2.0", "downloads": 830, "description": null}
+        ],
+        "meta": {"total": 2}
+    }"#;
+
+    #[test]
+    fn test_parse_and_match() {
+        let response: CratesResponse = serde_json::from_str(RESPONSE).unwrap();
+        let [rust, go] = &response.crates[..] else {
+            panic!("expected two crates");
+        };
+        assert_eq!(rust.version, "0.4.1");
+        assert!(rust.matches("RUST"));
+        assert!(rust.matches("webassembly"));
+        assert!(go.matches("go"));
+        assert!(!go.matches("webassembly"));
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("cache").join(CACHE_FILE);
+        assert!(read_cache(&path).is_none());
+
+        let response: CratesResponse = serde_json::from_str(RESPONSE).unwrap();
+        write_cache(&path, &response.crates);
+        let (crates, age) = read_cache(&path).unwrap();
+        assert_eq!(crates, response.crates);
+        assert!(age < CACHE_TTL);
+    }
+}
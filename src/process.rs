@@ -0,0 +1,226 @@
+//! Process control through the platform's APIs
+//!
+//! Signals on Unix, process handles on Windows. Nothing here shells out, so it
+//! works the same wherever wasmrun runs and cannot be confused by the output
+//! of `ps` or `kill` on an unusual system.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+/// How often [`wait_for_exit`] checks on the process
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Whether a process with this ID exists
+#[cfg(unix)]
+pub fn is_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 only checks that the process exists and may be signalled
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Ask a process to exit, letting it clean up first
+#[cfg(unix)]
+pub fn terminate(pid: u32) -> io::Result<()> {
+    signal(pid, libc::SIGTERM)
+}
+
+/// End a process immediately
+#[cfg(unix)]
+pub fn kill(pid: u32) -> io::Result<()> {
+    signal(pid, libc::SIGKILL)
+}
+
+/// The name of the program a process runs, e.g. `wasmrun`; `None` when it
+/// cannot be inspected
+#[cfg(target_os = "linux")]
+pub fn name(pid: u32) -> Option<String> {
+    let name = std::fs::read_to_string(format!("/proc/{pid}/comm")).ok()?;
+    Some(name.trim_end().to_string())
+}
+
+/// The name of the program a process runs, e.g. `wasmrun`; `None` when it
+/// cannot be inspected
+#[cfg(target_os = "macos")]
+pub fn name(pid: u32) -> Option<String> {
+    let pid = libc::c_int::try_from(pid).ok()?;
+    let mut buffer = [0u8; 256];
+    let length = unsafe {
+        libc::proc_name(
+            pid,
+            buffer.as_mut_ptr() as *mut libc::c_void,
+            buffer.len() as u32,
+        )
+    };
+    let length = usize::try_from(length).ok().filter(|length| *length > 0)?;
+    Some(String::from_utf8_lossy(&buffer[..length]).to_string())
+}
+
+/// The name of the program a process runs; not available on this platform
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "macos"))))]
+pub fn name(_pid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(unix)]
+fn signal(pid: u32, signal: libc::c_int) -> io::Result<()> {
+    let pid = libc::pid_t::try_from(pid)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "process ID out of range"))?;
+    if unsafe { libc::kill(pid, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::io;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, STILL_ACTIVE};
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, QueryFullProcessImageNameW, TerminateProcess,
+        PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_TERMINATE,
+    };
+
+    /// A process handle, closed when dropped
+    pub struct Process(HANDLE);
+
+    impl Process {
+        pub fn open(pid: u32, access: u32) -> io::Result<Self> {
+            let handle = unsafe { OpenProcess(access, 0, pid) };
+            if handle.is_null() {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(Self(handle))
+            }
+        }
+
+        pub fn is_running(&self) -> bool {
+            let mut code = 0u32;
+            unsafe { GetExitCodeProcess(self.0, &mut code) != 0 && code == STILL_ACTIVE as u32 }
+        }
+
+        /// Path of the process's executable
+        pub fn image_path(&self) -> Option<String> {
+            let mut buffer = [0u16; 1024];
+            let mut length = buffer.len() as u32;
+            let found = unsafe {
+                QueryFullProcessImageNameW(
+                    self.0,
+                    PROCESS_NAME_WIN32,
+                    buffer.as_mut_ptr(),
+                    &mut length,
+                )
+            };
+            (found != 0).then(|| String::from_utf16_lossy(&buffer[..length as usize]))
+        }
+
+        pub fn terminate(&self) -> io::Result<()> {
+            if unsafe { TerminateProcess(self.0, 1) } != 0 {
+                Ok(())
+            } else {
+                Err(io::Error::last_os_error())
+            }
+        }
+    }
+
+    impl Drop for Process {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    pub const QUERY: u32 = PROCESS_QUERY_LIMITED_INFORMATION;
+    pub const TERMINATE: u32 = PROCESS_TERMINATE;
+}
+
+/// Whether a process with this ID exists
+#[cfg(windows)]
+pub fn is_alive(pid: u32) -> bool {
+    windows::Process::open(pid, windows::QUERY).is_ok_and(|process| process.is_running())
+}
+
+/// The name of the program a process runs, e.g. `wasmrun`; `None` when it
+/// cannot be inspected
+#[cfg(windows)]
+pub fn name(pid: u32) -> Option<String> {
+    let path = windows::Process::open(pid, windows::QUERY)
+        .ok()?
+        .image_path()?;
+    let name = std::path::Path::new(&path).file_stem()?;
+    Some(name.to_string_lossy().to_string())
+}
+
+/// Ask a process to exit. Windows has no termination request a console
+/// process can be sent from outside its console, so this ends it like [`kill`]
+#[cfg(windows)]
+pub fn terminate(pid: u32) -> io::Result<()> {
+    kill(pid)
+}
+
+/// End a process immediately
+#[cfg(windows)]
+pub fn kill(pid: u32) -> io::Result<()> {
+    windows::Process::open(pid, windows::TERMINATE)?.terminate()
+}
+
+/// Whether `pid` runs the same program as this process. A process ID recorded
+/// by a wasmrun that never cleaned up, e.g. across a reboot, may belong to an
+/// unrelated program by now, which must not be mistaken for wasmrun
+pub fn runs_wasmrun(pid: u32) -> bool {
+    match (name(pid), name(std::process::id())) {
+        (Some(name), Some(own)) => name == own,
+        // Without a way to tell, trust the record
+        _ => true,
+    }
+}
+
+/// Wait up to `timeout` for a process to exit; true when it has
+pub fn wait_for_exit(pid: u32, timeout: Duration) -> bool {
+    let started = Instant::now();
+    while is_alive(pid) {
+        if started.elapsed() >= timeout {
+            return false;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_is_alive() {
+        assert!(is_alive(std::process::id()));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos", windows))]
+    #[test]
+    fn test_name() {
+        let own = name(std::process::id()).unwrap();
+        // The test binary is named after the crate
+        assert!(own.starts_with("wasmrun"), "{own}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_and_wait() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id();
+        assert!(is_alive(pid));
+        assert!(!wait_for_exit(pid, Duration::from_millis(100)));
+
+        terminate(pid).unwrap();
+        // Reap it so it does not linger as a zombie
+        child.wait().unwrap();
+        assert!(wait_for_exit(pid, Duration::from_secs(5)));
+        assert!(kill(pid).is_err());
+    }
+}
@@ -0,0 +1,499 @@
+//! Local state registry
+//!
+//! A small SQLite database under the config dir (`~/.wasmrun/registry.db`) that
+//! records running server instances, plugin enable/disable state, build
+//! history and named projects. The schema is versioned with `PRAGMA user_version` and upgraded by
+//! applying the pending entries of [`MIGRATIONS`] in order.
+
+use crate::compiler::builder::BuildResult;
+use crate::config::WasmrunConfig;
+use crate::debug_println;
+use crate::error::{CompilationResult, Result};
+use crate::plugin::events::Event;
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// File name of the registry database inside the config dir
+pub const REGISTRY_FILE: &str = "registry.db";
+
+/// Schema migrations; entry `i` upgrades the database from version `i` to `i + 1`
+const MIGRATIONS: &[&str] = &[INITIAL_SCHEMA, NAMED_PROJECTS, INSTANCE_NAMES];
+
+const INITIAL_SCHEMA: &str = "
+CREATE TABLE server_instances (
+    pid INTEGER PRIMARY KEY,
+    port INTEGER NOT NULL,
+    wasm_path TEXT NOT NULL,
+    project_path TEXT,
+    started_at TEXT NOT NULL
+);
+CREATE TABLE plugin_state (
+    name TEXT PRIMARY KEY,
+    enabled INTEGER NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE TABLE build_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_path TEXT NOT NULL,
+    builder TEXT NOT NULL,
+    success INTEGER NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    output_path TEXT,
+    error TEXT,
+    finished_at TEXT NOT NULL
+);
+CREATE INDEX build_history_project ON build_history (project_path, id);
+";
+
+const NAMED_PROJECTS: &str = "
+CREATE TABLE projects (
+    name TEXT PRIMARY KEY,
+    path TEXT NOT NULL,
+    port INTEGER,
+    added_at TEXT NOT NULL
+);
+";
+
+const INSTANCE_NAMES: &str = "
+ALTER TABLE server_instances ADD COLUMN name TEXT;
+";
+
+/// A running wasmrun server
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerInstance {
+    pub pid: u32,
+    pub port: u16,
+    pub wasm_path: String,
+    pub project_path: Option<String>,
+    pub started_at: String,
+    /// Name given with `--name`
+    pub name: Option<String>,
+}
+
+/// One finished build
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildRecord {
+    pub project_path: String,
+    pub builder: String,
+    pub success: bool,
+    pub duration_ms: u64,
+    pub output_path: Option<String>,
+    pub error: Option<String>,
+    pub finished_at: String,
+}
+
+impl BuildRecord {
+    pub fn new(
+        project_path: &str,
+        builder: &str,
+        duration: Duration,
+        outcome: std::result::Result<&str, String>,
+    ) -> Self {
+        let (success, output_path, error) = match outcome {
+            Ok(output) => (true, Some(output.to_string()), None),
+            Err(error) => (false, None, Some(error)),
+        };
+        Self {
+            project_path: project_path.to_string(),
+            builder: builder.to_string(),
+            success,
+            duration_ms: duration.as_millis() as u64,
+            output_path,
+            error,
+            finished_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// A project registered with `wasmrun project add`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedProject {
+    pub name: String,
+    pub path: String,
+    pub port: Option<u16>,
+    pub added_at: String,
+}
+
+pub struct Registry {
+    conn: Connection,
+}
+
+impl Registry {
+    /// Open the registry in the wasmrun config dir, creating and migrating it as needed
+    pub fn open() -> Result<Self> {
+        let config_dir = WasmrunConfig::config_dir()?;
+        std::fs::create_dir_all(&config_dir)?;
+        Self::open_at(&config_dir.join(REGISTRY_FILE))
+    }
+
+    pub fn open_at(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(Duration::from_secs(2))?;
+        Self::with_connection(conn)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        let registry = Self { conn };
+        registry.migrate()?;
+        Ok(registry)
+    }
+
+    fn migrate(&self) -> Result<()> {
+        if self.schema_version()? >= MIGRATIONS.len() {
+            return Ok(());
+        }
+
+        // Take the write lock before reading the version again, so two processes
+        // opening a new registry at once can't both apply the same migration
+        let transaction = Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)?;
+        let version = self.schema_version()?;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            debug_println!("Applying registry migration {}", index + 1);
+            transaction.execute_batch(&format!(
+                "{migration}; PRAGMA user_version = {};",
+                index + 1
+            ))?;
+        }
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    pub fn schema_version(&self) -> Result<usize> {
+        Ok(self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?)
+    }
+
+    pub fn register_instance(&self, instance: &ServerInstance) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO server_instances (pid, port, wasm_path, project_path, started_at, name)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                instance.pid,
+                instance.port,
+                instance.wasm_path,
+                instance.project_path,
+                instance.started_at,
+                instance.name
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_instance(&self, pid: u32) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM server_instances WHERE pid = ?1", params![pid])?;
+        Ok(())
+    }
+
+    /// Remove the record of `instance` only, leaving a newer server that got the same process ID
+    pub fn remove_stale_instance(&self, instance: &ServerInstance) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM server_instances WHERE pid = ?1 AND started_at = ?2",
+            params![instance.pid, instance.started_at],
+        )?;
+        Ok(())
+    }
+
+    pub fn instances(&self) -> Result<Vec<ServerInstance>> {
+        let mut statement = self.conn.prepare(
+            "SELECT pid, port, wasm_path, project_path, started_at, name
+             FROM server_instances ORDER BY started_at",
+        )?;
+        let instances = statement
+            .query_map([], |row| {
+                Ok(ServerInstance {
+                    pid: row.get(0)?,
+                    port: row.get(1)?,
+                    wasm_path: row.get(2)?,
+                    project_path: row.get(3)?,
+                    started_at: row.get(4)?,
+                    name: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(instances)
+    }
+
+    pub fn set_plugin_enabled(&self, name: &str, enabled: bool) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO plugin_state (name, enabled, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET enabled = excluded.enabled, updated_at = excluded.updated_at",
+            params![name, enabled, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Recorded enabled state of a plugin, or `None` if the registry has no entry for it
+    pub fn plugin_enabled(&self, name: &str) -> Result<Option<bool>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT enabled FROM plugin_state WHERE name = ?1",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    pub fn remove_plugin(&self, name: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM plugin_state WHERE name = ?1", params![name])?;
+        Ok(())
+    }
+
+    pub fn record_build(&self, record: &BuildRecord) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO build_history
+                (project_path, builder, success, duration_ms, output_path, error, finished_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                record.project_path,
+                record.builder,
+                record.success,
+                record.duration_ms,
+                record.output_path,
+                record.error,
+                record.finished_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Most recent builds first, optionally limited to one project
+    pub fn recent_builds(
+        &self,
+        project_path: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<BuildRecord>> {
+        let mut statement = self.conn.prepare(
+            "SELECT project_path, builder, success, duration_ms, output_path, error, finished_at
+             FROM build_history
+             WHERE ?1 IS NULL OR project_path = ?1
+             ORDER BY id DESC LIMIT ?2",
+        )?;
+        let builds = statement
+            .query_map(params![project_path, limit as i64], |row| {
+                Ok(BuildRecord {
+                    project_path: row.get(0)?,
+                    builder: row.get(1)?,
+                    success: row.get(2)?,
+                    duration_ms: row.get(3)?,
+                    output_path: row.get(4)?,
+                    error: row.get(5)?,
+                    finished_at: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(builds)
+    }
+
+    /// Register a project, replacing any earlier entry with the same name
+    pub fn add_project(&self, project: &NamedProject) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO projects (name, path, port, added_at) VALUES (?1, ?2, ?3, ?4)",
+            params![project.name, project.path, project.port, project.added_at],
+        )?;
+        Ok(())
+    }
+
+    /// Remove a named project; returns whether it was registered
+    pub fn remove_project(&self, name: &str) -> Result<bool> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM projects WHERE name = ?1", params![name])?;
+        Ok(removed > 0)
+    }
+
+    pub fn project(&self, name: &str) -> Result<Option<NamedProject>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT name, path, port, added_at FROM projects WHERE name = ?1",
+                params![name],
+                named_project,
+            )
+            .optional()?)
+    }
+
+    pub fn projects(&self) -> Result<Vec<NamedProject>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT name, path, port, added_at FROM projects ORDER BY name")?;
+        let projects = statement
+            .query_map([], named_project)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(projects)
+    }
+}
+
+fn named_project(row: &rusqlite::Row) -> rusqlite::Result<NamedProject> {
+    Ok(NamedProject {
+        name: row.get(0)?,
+        path: row.get(1)?,
+        port: row.get(2)?,
+        added_at: row.get(3)?,
+    })
+}
+
+/// Append a build to the history; failures only produce a debug message
+pub fn record_build(record: BuildRecord) {
+    if let Err(e) = Registry::open().and_then(|registry| registry.record_build(&record)) {
+        debug_println!("Failed to record build history: {e}");
+    }
+    crate::server::metrics::record_build(record.success, Duration::from_millis(record.duration_ms));
+    crate::server::status::record_build(&record);
+    crate::plugin::events::publish(Event::BuildFinished {
+        project_path: record.project_path.clone(),
+        builder: record.builder.clone(),
+        success: record.success,
+        duration_ms: record.duration_ms,
+        output_path: record.output_path.clone(),
+        error: record.error.clone(),
+    });
+    crate::tui::emit(crate::tui::DevEvent::Build(record));
+}
+
+/// Append the outcome of a builder run that started at `started` to the history
+pub fn record_build_result(
+    project_path: &str,
+    builder: &str,
+    started: Instant,
+    result: &CompilationResult<BuildResult>,
+) {
+    record_build(BuildRecord::new(
+        project_path,
+        builder,
+        started.elapsed(),
+        result
+            .as_ref()
+            .map(|build| build.wasm_path.as_str())
+            .map_err(ToString::to_string),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_migrations_apply_once() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join(REGISTRY_FILE);
+
+        let registry = Registry::open_at(&path).unwrap();
+        assert_eq!(registry.schema_version().unwrap(), MIGRATIONS.len());
+        drop(registry);
+
+        let reopened = Registry::open_at(&path).unwrap();
+        assert_eq!(reopened.schema_version().unwrap(), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn test_concurrent_open_migrates_once() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join(REGISTRY_FILE);
+
+        let openers: Vec<_> = (0..4)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || Registry::open_at(&path).map(|_| ()))
+            })
+            .collect();
+        for opener in openers {
+            opener.join().unwrap().unwrap();
+        }
+        let registry = Registry::open_at(&path).unwrap();
+        assert_eq!(registry.schema_version().unwrap(), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn test_server_instances() {
+        let registry = Registry::open_in_memory().unwrap();
+        let instance = ServerInstance {
+            pid: 4242,
+            port: 8420,
+            wasm_path: "/tmp/app.wasm".to_string(),
+            project_path: Some("/tmp/app".to_string()),
+            started_at: chrono::Utc::now().to_rfc3339(),
+            name: Some("docs".to_string()),
+        };
+
+        registry.register_instance(&instance).unwrap();
+        assert_eq!(registry.instances().unwrap(), vec![instance.clone()]);
+
+        let older = ServerInstance {
+            started_at: "2020-01-01T00:00:00+00:00".to_string(),
+            ..instance.clone()
+        };
+        registry.remove_stale_instance(&older).unwrap();
+        assert_eq!(registry.instances().unwrap().len(), 1);
+
+        registry.remove_instance(4242).unwrap();
+        assert!(registry.instances().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_plugin_state() {
+        let registry = Registry::open_in_memory().unwrap();
+        assert_eq!(registry.plugin_enabled("wasmrust").unwrap(), None);
+
+        registry.set_plugin_enabled("wasmrust", false).unwrap();
+        assert_eq!(registry.plugin_enabled("wasmrust").unwrap(), Some(false));
+        registry.set_plugin_enabled("wasmrust", true).unwrap();
+        assert_eq!(registry.plugin_enabled("wasmrust").unwrap(), Some(true));
+
+        registry.remove_plugin("wasmrust").unwrap();
+        assert_eq!(registry.plugin_enabled("wasmrust").unwrap(), None);
+    }
+
+    #[test]
+    fn test_build_history_most_recent_first() {
+        let registry = Registry::open_in_memory().unwrap();
+        let first = BuildRecord::new("/a", "rust", Duration::from_millis(120), Ok("/a/out.wasm"));
+        let second = BuildRecord::new("/b", "go", Duration::from_millis(80), Err("boom".into()));
+        registry.record_build(&first).unwrap();
+        registry.record_build(&second).unwrap();
+
+        let all = registry.recent_builds(None, 10).unwrap();
+        assert_eq!(all, vec![second.clone(), first.clone()]);
+        assert!(!all[0].success);
+        assert_eq!(all[0].error.as_deref(), Some("boom"));
+
+        assert_eq!(registry.recent_builds(Some("/a"), 10).unwrap(), vec![first]);
+        assert_eq!(registry.recent_builds(None, 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_named_projects() {
+        let registry = Registry::open_in_memory().unwrap();
+        let demo = NamedProject {
+            name: "demo".to_string(),
+            path: "/tmp/demo".to_string(),
+            port: Some(3001),
+            added_at: chrono::Utc::now().to_rfc3339(),
+        };
+        registry.add_project(&demo).unwrap();
+        assert_eq!(registry.project("demo").unwrap(), Some(demo.clone()));
+
+        let moved = NamedProject {
+            path: "/tmp/demo-v2".to_string(),
+            port: None,
+            ..demo
+        };
+        registry.add_project(&moved).unwrap();
+        assert_eq!(registry.projects().unwrap(), vec![moved]);
+
+        assert!(registry.remove_project("demo").unwrap());
+        assert!(!registry.remove_project("demo").unwrap());
+        assert_eq!(registry.project("demo").unwrap(), None);
+    }
+}
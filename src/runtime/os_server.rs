@@ -606,14 +606,7 @@ impl OsServer {
         let content = fs::read(&full_path)
             .map_err(|e| WasmrunError::from(format!("Failed to read asset: {e}")))?;
 
-        let content_type = match full_path.extension().and_then(|ext| ext.to_str()) {
-            Some("png") => "image/png",
-            Some("jpg") | Some("jpeg") => "image/jpeg",
-            Some("svg") => "image/svg+xml",
-            Some("css") => "text/css",
-            Some("js") => "application/javascript",
-            _ => "application/octet-stream",
-        };
+        let content_type = crate::server::mime::for_path(&full_path);
 
         let response = Response::from_data(content).with_header(
             Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()).unwrap(),
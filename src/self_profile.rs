@@ -0,0 +1,500 @@
+//! Profiling of wasmrun itself (`--self-profile`)
+//!
+//! Long dev sessions keep rebuilt artifacts in memory, so reports of the dev
+//! server growing over time need numbers. With `--self-profile` wasmrun tags
+//! the work it does as server, watcher or build work and keeps per-subsystem
+//! CPU time and heap allocation counters. A summary with the resident set size
+//! and the sizes of the in-memory caches is printed when wasmrun exits,
+//! including on Ctrl+C.
+
+use crate::utils::CommandExecutor;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Part of wasmrun that work is attributed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subsystem {
+    /// Startup, command handling and threads without a more specific tag
+    Other = 0,
+    /// Serving requests
+    Server = 1,
+    /// Handling file change events
+    Watcher = 2,
+    /// Compiling projects, excluding the compiler processes themselves
+    Builds = 3,
+}
+
+const SUBSYSTEMS: [Subsystem; 4] = [
+    Subsystem::Server,
+    Subsystem::Watcher,
+    Subsystem::Builds,
+    Subsystem::Other,
+];
+
+impl Subsystem {
+    fn name(self) -> &'static str {
+        match self {
+            Subsystem::Other => "other",
+            Subsystem::Server => "server",
+            Subsystem::Watcher => "watcher",
+            Subsystem::Builds => "builds",
+        }
+    }
+
+    fn from_index(index: u8) -> Self {
+        match index {
+            1 => Subsystem::Server,
+            2 => Subsystem::Watcher,
+            3 => Subsystem::Builds,
+            _ => Subsystem::Other,
+        }
+    }
+}
+
+/// Counters of one subsystem
+struct Counters {
+    cpu_ns: AtomicU64,
+    busy_ns: AtomicU64,
+    spans: AtomicU64,
+    allocated: AtomicU64,
+    allocations: AtomicU64,
+    child_cpu_ns: AtomicU64,
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Self {
+            cpu_ns: AtomicU64::new(0),
+            busy_ns: AtomicU64::new(0),
+            spans: AtomicU64::new(0),
+            allocated: AtomicU64::new(0),
+            allocations: AtomicU64::new(0),
+            child_cpu_ns: AtomicU64::new(0),
+        }
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STARTED: OnceLock<Instant> = OnceLock::new();
+static COUNTERS: [Counters; 4] = [
+    Counters::new(),
+    Counters::new(),
+    Counters::new(),
+    Counters::new(),
+];
+
+/// Heap bytes allocated and not yet freed since profiling started, and its high-water mark
+static LIVE_HEAP: AtomicI64 = AtomicI64::new(0);
+static PEAK_HEAP: AtomicI64 = AtomicI64::new(0);
+
+thread_local! {
+    static CURRENT: Cell<u8> = const { Cell::new(Subsystem::Other as u8) };
+    /// Thread CPU time already attributed to a subsystem
+    static ATTRIBUTED_CPU: Cell<u64> = const { Cell::new(0) };
+}
+
+/// The system allocator, counting allocations while `--self-profile` is on
+struct ProfilingAllocator;
+
+#[global_allocator]
+static ALLOCATOR: ProfilingAllocator = ProfilingAllocator;
+
+unsafe impl GlobalAlloc for ProfilingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        record_free(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record_free(layout.size());
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+#[inline]
+fn record_alloc(size: usize) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let counters = &COUNTERS[current() as usize];
+    counters.allocated.fetch_add(size as u64, Ordering::Relaxed);
+    counters.allocations.fetch_add(1, Ordering::Relaxed);
+    let live = LIVE_HEAP.fetch_add(size as i64, Ordering::Relaxed) + size as i64;
+    PEAK_HEAP.fetch_max(live, Ordering::Relaxed);
+}
+
+#[inline]
+fn record_free(size: usize) {
+    if ENABLED.load(Ordering::Relaxed) {
+        LIVE_HEAP.fetch_sub(size as i64, Ordering::Relaxed);
+    }
+}
+
+/// The current thread's subsystem; `Other` while the thread is being torn down
+fn current() -> Subsystem {
+    CURRENT
+        .try_with(|current| Subsystem::from_index(current.get()))
+        .unwrap_or(Subsystem::Other)
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Start profiling and print the report when the process is interrupted.
+/// Must be called before any threads are spawned.
+pub fn enable() {
+    STARTED.get_or_init(Instant::now);
+    ENABLED.store(true, Ordering::Relaxed);
+    #[cfg(unix)]
+    report_on_signal();
+}
+
+/// Attributes the current thread's work to a subsystem until dropped
+pub struct Span {
+    subsystem: Subsystem,
+    previous: Subsystem,
+    started: Instant,
+    children_cpu: Duration,
+}
+
+/// Tag the work the current thread does until the returned span is dropped;
+/// `None` when profiling is off
+pub fn span(subsystem: Subsystem) -> Option<Span> {
+    if !is_enabled() {
+        return None;
+    }
+    let previous = switch_to(subsystem);
+    Some(Span {
+        subsystem,
+        previous,
+        started: Instant::now(),
+        children_cpu: children_cpu_time(),
+    })
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        switch_to(self.previous);
+        let counters = &COUNTERS[self.subsystem as usize];
+        counters.spans.fetch_add(1, Ordering::Relaxed);
+        counters
+            .busy_ns
+            .fetch_add(nanos(self.started.elapsed()), Ordering::Relaxed);
+        // Compilers run as child processes; their CPU time shows up once they are reaped
+        let children = children_cpu_time().saturating_sub(self.children_cpu);
+        counters
+            .child_cpu_ns
+            .fetch_add(nanos(children), Ordering::Relaxed);
+    }
+}
+
+/// Charge the thread's CPU time since the last switch to its current subsystem, then switch
+fn switch_to(subsystem: Subsystem) -> Subsystem {
+    let now = nanos(thread_cpu_time());
+    let spent = ATTRIBUTED_CPU.with(|attributed| now.saturating_sub(attributed.replace(now)));
+    let previous = current();
+    COUNTERS[previous as usize]
+        .cpu_ns
+        .fetch_add(spent, Ordering::Relaxed);
+    CURRENT.with(|current| current.set(subsystem as u8));
+    previous
+}
+
+/// Print the report if profiling is on
+pub fn report() {
+    if !is_enabled() {
+        return;
+    }
+    // Charge the reporting thread's own work before reading the counters
+    switch_to(current());
+    for line in report_lines() {
+        eprintln!("{line}");
+    }
+}
+
+fn report_lines() -> Vec<String> {
+    let uptime = STARTED.get().map(Instant::elapsed).unwrap_or_default();
+    let attributed: u64 = [Subsystem::Server, Subsystem::Watcher, Subsystem::Builds]
+        .iter()
+        .map(|subsystem| COUNTERS[*subsystem as usize].cpu_ns.load(Ordering::Relaxed))
+        .sum();
+    // Threads that never open a span only show up in the process total
+    let other_cpu = nanos(process_cpu_time()).saturating_sub(attributed).max(
+        COUNTERS[Subsystem::Other as usize]
+            .cpu_ns
+            .load(Ordering::Relaxed),
+    );
+
+    let header = ["SUBSYSTEM", "CPU", "BUSY", "EVENTS", "ALLOCATED", "ALLOCS"];
+    let mut rows = vec![header.map(String::from)];
+    for subsystem in SUBSYSTEMS {
+        let counters = &COUNTERS[subsystem as usize];
+        let cpu = match subsystem {
+            Subsystem::Other => other_cpu,
+            _ => counters.cpu_ns.load(Ordering::Relaxed),
+        };
+        let mut cpu = format_duration(Duration::from_nanos(cpu));
+        let child_cpu = counters.child_cpu_ns.load(Ordering::Relaxed);
+        if child_cpu > 0 {
+            cpu.push_str(&format!(
+                " (+{} compilers)",
+                format_duration(Duration::from_nanos(child_cpu))
+            ));
+        }
+        let spans = counters.spans.load(Ordering::Relaxed);
+        let (busy, events) = if subsystem == Subsystem::Other {
+            ("-".to_string(), "-".to_string())
+        } else {
+            (
+                format_duration(Duration::from_nanos(
+                    counters.busy_ns.load(Ordering::Relaxed),
+                )),
+                spans.to_string(),
+            )
+        };
+        rows.push([
+            subsystem.name().to_string(),
+            cpu,
+            busy,
+            events,
+            CommandExecutor::format_file_size(counters.allocated.load(Ordering::Relaxed)),
+            counters.allocations.load(Ordering::Relaxed).to_string(),
+        ]);
+    }
+
+    let mut widths = [0; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut lines = vec![format!(
+        "\n📊 wasmrun self-profile ({} uptime):",
+        format_duration(uptime)
+    )];
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell}{}", " ".repeat(width - cell.chars().count())))
+            .collect();
+        lines.push(format!("   {}", cells.join("  ").trim_end()));
+    }
+
+    let size = |bytes: i64| CommandExecutor::format_file_size(bytes.max(0) as u64);
+    let mut memory = format!(
+        "   Heap: {} live, {} peak",
+        size(LIVE_HEAP.load(Ordering::Relaxed)),
+        size(PEAK_HEAP.load(Ordering::Relaxed))
+    );
+    if let Some((rss, peak_rss)) = resident_set_size() {
+        memory.push_str(&format!(" · RSS: {}, {} peak", size(rss), size(peak_rss)));
+    }
+    lines.push(memory);
+
+    let (builds, bytes) = crate::server::delta::memory_usage();
+    lines.push(format!(
+        "   Cached: {builds} wasm build(s) for delta updates, {}",
+        size(bytes as i64)
+    ));
+    lines
+}
+
+/// Report and exit on SIGINT/SIGTERM; the handler only writes the signal to a pipe
+/// that a reporter thread waits on, since printing is not safe inside a handler.
+/// A server replaces these handlers with its graceful shutdown once it starts,
+/// after which the report is printed when `main` returns
+#[cfg(unix)]
+fn report_on_signal() {
+    use std::sync::atomic::AtomicI32;
+
+    static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+    extern "C" fn on_signal(signal: libc::c_int) {
+        let byte = signal as u8;
+        unsafe {
+            libc::write(
+                SIGNAL_PIPE.load(Ordering::Relaxed),
+                &byte as *const u8 as *const libc::c_void,
+                1,
+            );
+        }
+    }
+
+    let mut pipe = [0; 2];
+    if unsafe { libc::pipe(pipe.as_mut_ptr()) } != 0 {
+        return;
+    }
+    SIGNAL_PIPE.store(pipe[1], Ordering::Relaxed);
+
+    std::thread::spawn(move || {
+        let mut signal = 0u8;
+        let read = unsafe { libc::read(pipe[0], &mut signal as *mut u8 as *mut libc::c_void, 1) };
+        if read == 1 {
+            report();
+            std::process::exit(128 + i32::from(signal));
+        }
+    });
+
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+#[cfg(unix)]
+fn thread_cpu_time() -> Duration {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut time) } != 0 {
+        return Duration::ZERO;
+    }
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
+
+#[cfg(unix)]
+fn rusage(who: libc::c_int) -> Option<libc::rusage> {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    (unsafe { libc::getrusage(who, &mut usage) } == 0).then_some(usage)
+}
+
+#[cfg(unix)]
+fn cpu_time(usage: &libc::rusage) -> Duration {
+    let timeval = |time: libc::timeval| {
+        Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64)
+    };
+    timeval(usage.ru_utime) + timeval(usage.ru_stime)
+}
+
+#[cfg(unix)]
+fn process_cpu_time() -> Duration {
+    rusage(libc::RUSAGE_SELF).map_or(Duration::ZERO, |usage| cpu_time(&usage))
+}
+
+#[cfg(unix)]
+fn children_cpu_time() -> Duration {
+    rusage(libc::RUSAGE_CHILDREN).map_or(Duration::ZERO, |usage| cpu_time(&usage))
+}
+
+#[cfg(not(unix))]
+fn thread_cpu_time() -> Duration {
+    Duration::ZERO
+}
+
+#[cfg(not(unix))]
+fn process_cpu_time() -> Duration {
+    Duration::ZERO
+}
+
+#[cfg(not(unix))]
+fn children_cpu_time() -> Duration {
+    Duration::ZERO
+}
+
+/// Current and peak resident set size in bytes
+#[cfg(target_os = "linux")]
+fn resident_set_size() -> Option<(i64, i64)> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let field = |name: &str| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|value| {
+                value
+                    .trim()
+                    .trim_end_matches("kB")
+                    .trim()
+                    .parse::<i64>()
+                    .ok()
+            })
+            .map(|kb| kb * 1024)
+    };
+    Some((field("VmRSS:")?, field("VmHWM:")?))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_set_size() -> Option<(i64, i64)> {
+    None
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h {}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else if secs >= 1 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spans_attribute_allocations() {
+        ENABLED.store(true, Ordering::Relaxed);
+        let before = COUNTERS[Subsystem::Watcher as usize]
+            .allocated
+            .load(Ordering::Relaxed);
+        {
+            let _span = span(Subsystem::Watcher);
+            assert_eq!(current(), Subsystem::Watcher);
+            {
+                let _build = span(Subsystem::Builds);
+                assert_eq!(current(), Subsystem::Builds);
+            }
+            assert_eq!(current(), Subsystem::Watcher);
+            std::hint::black_box(vec![0u8; 4096]);
+        }
+        assert_eq!(current(), Subsystem::Other);
+        let after = COUNTERS[Subsystem::Watcher as usize]
+            .allocated
+            .load(Ordering::Relaxed);
+        assert!(after - before >= 4096);
+        assert!(report_lines().iter().any(|line| line.contains("watcher")));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(250)), "250ms");
+        assert_eq!(format_duration(Duration::from_millis(1500)), "1.5s");
+        assert_eq!(format_duration(Duration::from_secs(125)), "2m 5s");
+        assert_eq!(format_duration(Duration::from_secs(7260)), "2h 1m");
+    }
+}
@@ -0,0 +1,571 @@
+//! Accessibility options for the runner page and `--a11y-audit`
+//!
+//! The generated page honors `prefers-reduced-motion` and gets a skip link and
+//! a main landmark, as configured in the `[accessibility]` section of
+//! `wasmrun.toml`. With `--a11y-audit` the served page also loads axe-core,
+//! a headless Chrome or Chromium opens it, and the violations axe reports are
+//! posted back to the server and printed before it shuts down.
+
+use super::utils::{content_type_header, respond};
+use crate::config::{active_settings, WasmrunConfig};
+use crate::error::{Result, WasmrunError};
+use crate::error_println;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tiny_http::{Method, Request, Response, Server};
+
+/// axe-core release downloaded when the project does not provide one
+pub const AXE_VERSION: &str = "4.10.2";
+
+/// Where the page loads axe-core from during an audit
+pub const AXE_SCRIPT_ROUTE: &str = "/__wasmrun/axe.min.js";
+
+/// Where the page posts the audit results
+pub const REPORT_ROUTE: &str = "/__wasmrun/a11y-report";
+
+/// How long the module gets to render before axe runs
+const SETTLE_TIME_MS: u64 = 1500;
+
+/// How long to wait for the headless browser to report
+const AUDIT_TIMEOUT: Duration = Duration::from_secs(90);
+
+const SKIP_LINK_CSS: &str = ".wasmrun-skip-link{position:absolute;left:-10000px;top:0;z-index:10000;padding:8px 16px;background:#000;color:#fff}.wasmrun-skip-link:focus{left:8px;top:8px}";
+
+const REDUCED_MOTION_CSS: &str = "*,*::before,*::after{animation-duration:0.01ms!important;animation-iteration-count:1!important;transition-duration:0.01ms!important;scroll-behavior:auto!important}";
+
+static AUDIT_REQUESTED: AtomicBool = AtomicBool::new(false);
+static AXE_SCRIPT: Mutex<Option<PathBuf>> = Mutex::new(None);
+static REPORT: Mutex<Option<String>> = Mutex::new(None);
+static REPORTED: Condvar = Condvar::new();
+static OUTCOME: Mutex<Option<Result<usize>>> = Mutex::new(None);
+
+/// How the page treats animations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReducedMotion {
+    /// Reduce motion when the OS asks for it
+    System,
+    /// Always reduce motion, to test the reduced experience
+    Always,
+    Off,
+}
+
+/// Accessibility options applied to the generated page
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageOptions {
+    pub reduced_motion: ReducedMotion,
+    pub landmarks: bool,
+}
+
+impl PageOptions {
+    /// Options from the `[accessibility]` section of the active `wasmrun.toml`
+    pub fn from_settings() -> Self {
+        let accessibility = active_settings().accessibility;
+        Self {
+            reduced_motion: match accessibility.reduced_motion.as_deref() {
+                Some("always") => ReducedMotion::Always,
+                Some("off") => ReducedMotion::Off,
+                _ => ReducedMotion::System,
+            },
+            landmarks: accessibility.landmarks.unwrap_or(true),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AxeReport {
+    #[serde(default)]
+    violations: Vec<Violation>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Violation {
+    id: String,
+    impact: Option<String>,
+    help: String,
+    help_url: Option<String>,
+    #[serde(default)]
+    nodes: Vec<ViolationNode>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ViolationNode {
+    #[serde(default)]
+    target: Vec<serde_json::Value>,
+}
+
+/// Run an accessibility audit once the server is up (set by `--a11y-audit`)
+pub fn request_audit() {
+    AUDIT_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+pub fn audit_requested() -> bool {
+    AUDIT_REQUESTED.load(Ordering::Relaxed)
+}
+
+/// Apply the configured accessibility options to a generated page
+pub fn inject(html: &str) -> String {
+    apply(html, &PageOptions::from_settings(), audit_requested())
+}
+
+fn apply(html: &str, options: &PageOptions, audit: bool) -> String {
+    let mut head = String::new();
+    match options.reduced_motion {
+        ReducedMotion::System => head.push_str(&format!(
+            "<style>@media (prefers-reduced-motion: reduce){{{REDUCED_MOTION_CSS}}}</style>\n"
+        )),
+        ReducedMotion::Always => {
+            head.push_str(&format!("<style>{REDUCED_MOTION_CSS}</style>\n"));
+            // Let scripts that check the media query see the reduced setting too
+            head.push_str(
+                r#"<script>
+(function (matchMedia) {
+  window.matchMedia = (query) => /prefers-reduced-motion\s*:\s*reduce/.test(query)
+    ? Object.assign(matchMedia.call(window, "all"), { media: query })
+    : matchMedia.call(window, query);
+})(window.matchMedia);
+</script>
+"#,
+            );
+        }
+        ReducedMotion::Off => {}
+    }
+    if options.landmarks {
+        head.push_str(&format!("<style>{SKIP_LINK_CSS}</style>\n"));
+    }
+
+    let mut html = match html.find("</head>") {
+        Some(index) => format!("{}{head}{}", &html[..index], &html[index..]),
+        None => format!("{head}{html}"),
+    };
+    if options.landmarks {
+        html = add_landmarks(&html);
+    }
+    if audit {
+        let script = audit_script();
+        html = match html.rfind("</body>") {
+            Some(index) => format!("{}{script}\n{}", &html[..index], &html[index..]),
+            None => format!("{html}\n{script}"),
+        };
+    }
+    html
+}
+
+/// Add a page language, a skip link and a `main` landmark for the content
+fn add_landmarks(html: &str) -> String {
+    let mut html = html.to_string();
+    if let Some(index) = html.find("<html") {
+        let end = html[index..]
+            .find('>')
+            .map_or(html.len(), |end| index + end);
+        if !html[index..end].contains("lang=") {
+            html.insert_str(index + "<html".len(), r#" lang="en""#);
+        }
+    }
+
+    let Some(body_start) = html
+        .find("<body")
+        .and_then(|index| html[index..].find('>').map(|end| index + end + 1))
+    else {
+        return html;
+    };
+    let body_end = html.rfind("</body>").unwrap_or(html.len());
+    let skip_link = r##"
+<a class="wasmrun-skip-link" href="#wasmrun-main">Skip to content</a>"##;
+
+    if let Some(index) = html[body_start..body_end].find("<main") {
+        let at = body_start + index + "<main".len();
+        let end = html[at..].find('>').map_or(html.len(), |end| at + end);
+        let target = match html[at..end].find("id=\"") {
+            Some(id) => {
+                let id_start = at + id + "id=\"".len();
+                let id_len = html[id_start..].find('"').unwrap_or(0);
+                html[id_start..id_start + id_len].to_string()
+            }
+            None => {
+                html.insert_str(at, r#" id="wasmrun-main" tabindex="-1""#);
+                "wasmrun-main".to_string()
+            }
+        };
+        html.insert_str(
+            body_start,
+            &skip_link.replace("#wasmrun-main", &format!("#{target}")),
+        );
+        return html;
+    }
+
+    // Leave a top-level header and footer outside `main` so they keep their landmark roles
+    let content_start = html[body_start..body_end]
+        .find("</header>")
+        .map_or(body_start, |index| body_start + index + "</header>".len());
+    let content_end = html[content_start..body_end]
+        .rfind("<footer")
+        .map_or(body_end, |index| content_start + index);
+    html.insert_str(content_end, "</main>\n");
+    html.insert_str(
+        content_start,
+        "\n<main id=\"wasmrun-main\" tabindex=\"-1\">",
+    );
+    html.insert_str(body_start, skip_link);
+    html
+}
+
+fn audit_script() -> String {
+    format!(
+        r#"<script>
+window.addEventListener("load", () => setTimeout(() => {{
+  const send = (report) => fetch("{REPORT_ROUTE}", {{
+    method: "POST",
+    headers: {{ "Content-Type": "application/json" }},
+    body: JSON.stringify(report),
+  }});
+  const axeScript = document.createElement("script");
+  axeScript.src = (window.__WASMRUN_BASE__ || "/") + "{axe}";
+  axeScript.onload = () => axe.run(document, {{ resultTypes: ["violations"] }})
+    .then((results) => send({{ violations: results.violations }}))
+    .catch((error) => send({{ error: String(error) }}));
+  axeScript.onerror = () => send({{ error: "Could not load axe-core" }});
+  document.head.appendChild(axeScript);
+}}, {SETTLE_TIME_MS}));
+</script>"#,
+        axe = AXE_SCRIPT_ROUTE.trim_start_matches('/'),
+    )
+}
+
+/// Serve the axe-core script to an audited page
+pub fn serve_axe(request: Request) {
+    let script = AXE_SCRIPT.lock().ok().and_then(|script| script.clone());
+    match script {
+        Some(script) => {
+            super::api::serve_file(request, &script.to_string_lossy(), "application/javascript")
+        }
+        None => {
+            let response = Response::from_string("404 Not Found")
+                .with_status_code(404)
+                .with_header(content_type_header("text/plain"));
+            if let Err(e) = respond(request, response) {
+                error_println!("Error sending 404 response: {e}");
+            }
+        }
+    }
+}
+
+/// Accept the results posted by an audited page
+pub fn receive_report(mut request: Request) {
+    if *request.method() != Method::Post {
+        let response = Response::from_string("405 Method Not Allowed").with_status_code(405);
+        if let Err(e) = respond(request, response) {
+            error_println!("Error sending audit response: {e}");
+        }
+        return;
+    }
+
+    let mut body = String::new();
+    let status = match request.as_reader().read_to_string(&mut body) {
+        Ok(_) => {
+            if let Ok(mut report) = REPORT.lock() {
+                *report = Some(body);
+                REPORTED.notify_all();
+            }
+            204
+        }
+        Err(_) => 400,
+    };
+    if let Err(e) = respond(request, Response::empty(status)) {
+        error_println!("Error sending audit response: {e}");
+    }
+}
+
+/// Open the served page in a headless browser and stop the server once axe has reported
+pub fn start_audit(port: u16, project_dir: &Path, server: &Arc<Server>) {
+    if !audit_requested() {
+        return;
+    }
+
+    let prepared = axe_script(project_dir).and_then(|script| {
+        let browser = find_browser().ok_or_else(|| {
+            WasmrunError::from(
+                "No Chrome, Chromium or Edge found for --a11y-audit; set WASMRUN_BROWSER to its path"
+                    .to_string(),
+            )
+        })?;
+        Ok((script, browser))
+    });
+    let (script, browser) = match prepared {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            finish(Err(e), server);
+            return;
+        }
+    };
+    if let Ok(mut axe) = AXE_SCRIPT.lock() {
+        *axe = Some(script);
+    }
+
+    let probe = super::urls::probe_address(port);
+    let url = format!(
+        "{}://{probe}{}",
+        super::tls::scheme(),
+        super::base_path::active()
+    );
+    let server = Arc::clone(server);
+    std::thread::spawn(move || {
+        let outcome = run_audit(&browser, &probe, &url)
+            .map_err(|reason| WasmrunError::from(format!("Accessibility audit failed: {reason}")));
+        finish(outcome, &server);
+    });
+}
+
+fn finish(outcome: Result<usize>, server: &Server) {
+    if let Ok(mut result) = OUTCOME.lock() {
+        *result = Some(outcome);
+    }
+    server.unblock();
+}
+
+fn run_audit(browser: &Path, probe: &str, url: &str) -> std::result::Result<usize, String> {
+    let started = Instant::now();
+    while std::net::TcpStream::connect(probe).is_err() {
+        if started.elapsed() > Duration::from_secs(30) {
+            return Err("The server did not start".to_string());
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    println!("♿ Auditing {url} with axe-core {AXE_VERSION}...");
+    let profile = std::env::temp_dir().join(format!("wasmrun-a11y-{}", std::process::id()));
+    let mut child = Command::new(browser)
+        .args([
+            "--headless=new",
+            "--disable-gpu",
+            "--no-first-run",
+            "--no-default-browser-check",
+            // The page may be served with a self-signed --tls certificate
+            "--ignore-certificate-errors",
+        ])
+        .arg(format!("--user-data-dir={}", profile.display()))
+        .arg(url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {e}", browser.display()))?;
+
+    let report = wait_for_report(AUDIT_TIMEOUT);
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = std::fs::remove_dir_all(&profile);
+
+    let report = report.ok_or_else(|| {
+        format!(
+            "The page did not report audit results within {}s",
+            AUDIT_TIMEOUT.as_secs()
+        )
+    })?;
+    let report: AxeReport =
+        serde_json::from_str(&report).map_err(|e| format!("Invalid audit report: {e}"))?;
+    if let Some(error) = report.error {
+        return Err(format!("axe-core failed: {error}"));
+    }
+    print_violations(&report.violations);
+    Ok(report.violations.len())
+}
+
+fn wait_for_report(timeout: Duration) -> Option<String> {
+    let deadline = Instant::now() + timeout;
+    let mut report = REPORT.lock().ok()?;
+    while report.is_none() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return None;
+        }
+        report = REPORTED.wait_timeout(report, remaining).ok()?.0;
+    }
+    report.take()
+}
+
+fn print_violations(violations: &[Violation]) {
+    for violation in violations {
+        println!(
+            "❌ [{}] {}: {} ({} element(s))",
+            violation.impact.as_deref().unwrap_or("unknown"),
+            violation.id,
+            violation.help,
+            violation.nodes.len()
+        );
+        for node in violation.nodes.iter().take(5) {
+            let target: Vec<String> = node
+                .target
+                .iter()
+                .map(|selector| match selector.as_str() {
+                    Some(selector) => selector.to_string(),
+                    None => selector.to_string(),
+                })
+                .collect();
+            println!("   ↳ {}", target.join(" "));
+        }
+        if let Some(help_url) = &violation.help_url {
+            println!("   📖 {help_url}");
+        }
+    }
+}
+
+/// Result of the audit requested with `--a11y-audit`, once the server has stopped
+pub fn finish_audit() -> Result<()> {
+    if !audit_requested() {
+        return Ok(());
+    }
+    let outcome = OUTCOME.lock().ok().and_then(|mut outcome| outcome.take());
+    match outcome {
+        Some(Ok(0)) => {
+            println!("✅ No accessibility violations found");
+            Ok(())
+        }
+        Some(Ok(count)) => Err(WasmrunError::ChecksFailed { count }),
+        Some(Err(e)) => Err(e),
+        None => Err(WasmrunError::from(
+            "Accessibility audit did not complete before the server stopped".to_string(),
+        )),
+    }
+}
+
+/// axe-core from the project's `node_modules`, or a cached download
+fn axe_script(project_dir: &Path) -> Result<PathBuf> {
+    let script = project_dir.join("node_modules/axe-core/axe.min.js");
+    if script.is_file() {
+        return Ok(script);
+    }
+
+    let cached = WasmrunConfig::cache_dir()?
+        .join("axe-core")
+        .join(AXE_VERSION)
+        .join("axe.min.js");
+    if cached.is_file() {
+        return Ok(cached);
+    }
+
+    crate::offline::require_network(format!("Downloading axe-core {AXE_VERSION}"))?;
+    if let Some(dir) = cached.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| WasmrunError::add_context(format!("Creating {}", dir.display()), e))?;
+    }
+    println!("📥 Downloading axe-core {AXE_VERSION}...");
+    let partial = cached.with_extension("part");
+    let status = Command::new("curl")
+        .args(["-fsSL", "-o"])
+        .arg(&partial)
+        .arg(format!(
+            "https://cdn.jsdelivr.net/npm/axe-core@{AXE_VERSION}/axe.min.js"
+        ))
+        .status()
+        .map_err(|e| WasmrunError::from(format!("Failed to run curl to fetch axe-core: {e}")))?;
+    if !status.success() {
+        let _ = std::fs::remove_file(&partial);
+        return Err(WasmrunError::from(format!(
+            "Failed to download axe-core {AXE_VERSION}"
+        )));
+    }
+    std::fs::rename(&partial, &cached)
+        .map_err(|e| WasmrunError::add_context(format!("Saving {}", cached.display()), e))?;
+    Ok(cached)
+}
+
+/// A Chromium-based browser: `WASMRUN_BROWSER`, then common names on `PATH` and install locations
+fn find_browser() -> Option<PathBuf> {
+    if let Some(browser) = std::env::var_os("WASMRUN_BROWSER") {
+        return Some(PathBuf::from(browser));
+    }
+
+    const NAMES: &[&str] = &[
+        "google-chrome",
+        "google-chrome-stable",
+        "chromium",
+        "chromium-browser",
+        "chrome",
+        "microsoft-edge",
+        "msedge",
+    ];
+    let on_path = std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths).find_map(|dir| {
+            NAMES
+                .iter()
+                .map(|name| dir.join(format!("{name}{}", std::env::consts::EXE_SUFFIX)))
+                .find(|path| path.is_file())
+        })
+    });
+    if on_path.is_some() {
+        return on_path;
+    }
+
+    [
+        "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+        "/Applications/Chromium.app/Contents/MacOS/Chromium",
+        "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+        r"C:\Program Files\Google\Chrome\Application\chrome.exe",
+        r"C:\Program Files (x86)\Google\Chrome\Application\chrome.exe",
+        r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "<html><head><title>t</title></head><body><header>h</header><div id=\"app\"></div><footer>f</footer></body></html>";
+
+    #[test]
+    fn test_landmarks_wrap_content_between_header_and_footer() {
+        let options = PageOptions {
+            reduced_motion: ReducedMotion::Off,
+            landmarks: true,
+        };
+        let html = apply(PAGE, &options, false);
+
+        assert!(html.contains(r#"<html lang="en">"#));
+        assert!(html.contains(r##"<a class="wasmrun-skip-link" href="#wasmrun-main">"##));
+        let main = html.find("<main id=\"wasmrun-main\"").unwrap();
+        assert!(html.find("</header>").unwrap() < main);
+        assert!(html.find("</main>").unwrap() < html.find("<footer").unwrap());
+        assert!(!html.contains("prefers-reduced-motion"));
+    }
+
+    #[test]
+    fn test_existing_main_keeps_its_id() {
+        let options = PageOptions {
+            reduced_motion: ReducedMotion::System,
+            landmarks: true,
+        };
+        let html = apply(
+            "<html lang=\"de\"><head></head><body><main id=\"content\">x</main></body></html>",
+            &options,
+            false,
+        );
+
+        assert!(html.contains(r#"<html lang="de">"#));
+        assert!(html.contains(r##"href="#content""##));
+        assert_eq!(html.matches("<main").count(), 1);
+        assert!(html.contains("@media (prefers-reduced-motion: reduce)"));
+    }
+
+    #[test]
+    fn test_audit_and_forced_reduced_motion() {
+        let options = PageOptions {
+            reduced_motion: ReducedMotion::Always,
+            landmarks: false,
+        };
+        let html = apply(PAGE, &options, true);
+
+        assert!(html.contains("window.matchMedia"));
+        assert!(!html.contains("@media (prefers-reduced-motion"));
+        assert!(!html.contains("<main"));
+        assert!(html.contains(REPORT_ROUTE));
+        assert!(html.find("axe.run").unwrap() < html.find("</body>").unwrap());
+    }
+}
@@ -0,0 +1,223 @@
+//! HTTP access log
+//!
+//! `--access-log [PATH]` writes a line for every response in Apache's combined
+//! format, followed by the time taken in milliseconds, to PATH or to stdout
+//! when no path is given. `--access-log-format common` writes the shorter
+//! common format instead. `--log-requests=off` drops the lines wasmrun prints
+//! for each request it serves, so scripts and benchmarks see only the log.
+
+use crate::error::{Result, WasmrunError};
+use clap::ValueEnum;
+use std::cell::Cell;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+use tiny_http::Request;
+
+/// Layout of access log lines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum AccessLogFormat {
+    /// Client, time, request line, status and size
+    Common,
+    /// Common, plus referer, user agent and milliseconds taken
+    #[default]
+    Combined,
+}
+
+struct AccessLog {
+    format: AccessLogFormat,
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+static LOG: OnceLock<AccessLog> = OnceLock::new();
+
+/// Cleared by `--log-requests=off`
+static PRINT_REQUESTS: AtomicBool = AtomicBool::new(true);
+
+thread_local! {
+    /// When the worker thread picked up the request it is handling
+    static STARTED: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// Write access log lines to `path`, appending, or to stdout for `-`
+pub fn enable(path: &str, format: AccessLogFormat) -> Result<()> {
+    let out: Box<dyn Write + Send> = if path == "-" {
+        Box::new(std::io::stdout())
+    } else {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| WasmrunError::add_context(format!("Opening access log {path}"), e))?;
+        Box::new(file)
+    };
+    let _ = LOG.set(AccessLog {
+        format,
+        out: Mutex::new(out),
+    });
+    Ok(())
+}
+
+/// Stop printing a line for each request served
+pub fn silence_requests() {
+    PRINT_REQUESTS.store(false, Ordering::Relaxed);
+}
+
+/// Whether to print a line for each request served
+pub fn prints_requests() -> bool {
+    PRINT_REQUESTS.load(Ordering::Relaxed)
+}
+
+/// Start timing the request the current thread is about to handle
+pub fn begin() {
+    STARTED.with(|started| started.set(Some(Instant::now())));
+}
+
+/// A response on its way out, logged once it has been sent
+pub struct Pending {
+    line: String,
+    started: Instant,
+}
+
+impl Pending {
+    /// Describe the response to `request`; `None` when there is no access log
+    pub fn new(request: &Request, status: u16, bytes: Option<usize>) -> Option<Self> {
+        let log = LOG.get()?;
+        let started = STARTED.with(Cell::take).unwrap_or_else(Instant::now);
+        let header = |name: &'static str| {
+            request
+                .headers()
+                .iter()
+                .find(|header| header.field.equiv(name))
+                .map(|header| header.value.as_str().to_string())
+        };
+        let entry = Entry {
+            client: request
+                .remote_addr()
+                .map(|addr| addr.ip().to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            time: chrono::Local::now()
+                .format("%d/%b/%Y:%H:%M:%S %z")
+                .to_string(),
+            request_line: format!(
+                "{} {} HTTP/{}",
+                request.method(),
+                request.url(),
+                request.http_version()
+            ),
+            status,
+            bytes,
+            referer: header("Referer"),
+            user_agent: header("User-Agent"),
+        };
+        Some(Self {
+            line: entry.format(log.format),
+            started,
+        })
+    }
+
+    /// Write the line, with the time taken when the format has it
+    pub fn finish(self) {
+        let Some(log) = LOG.get() else {
+            return;
+        };
+        let mut line = self.line;
+        if log.format == AccessLogFormat::Combined {
+            line.push_str(&format!(" {}", self.started.elapsed().as_millis()));
+        }
+        line.push('\n');
+        if let Ok(mut out) = log.out.lock() {
+            let _ = out.write_all(line.as_bytes()).and_then(|_| out.flush());
+        }
+    }
+}
+
+struct Entry {
+    client: String,
+    time: String,
+    request_line: String,
+    status: u16,
+    bytes: Option<usize>,
+    referer: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl Entry {
+    /// The line without the time taken, which is only known once the body is sent
+    fn format(&self, format: AccessLogFormat) -> String {
+        let bytes = match self.bytes {
+            Some(bytes) if bytes > 0 => bytes.to_string(),
+            _ => "-".to_string(),
+        };
+        let mut line = format!(
+            "{} - - [{}] \"{}\" {} {bytes}",
+            self.client,
+            self.time,
+            escape(&self.request_line),
+            self.status
+        );
+        if format == AccessLogFormat::Combined {
+            let quoted = |value: &Option<String>| match value {
+                Some(value) => format!("\"{}\"", escape(value)),
+                None => "\"-\"".to_string(),
+            };
+            line.push_str(&format!(
+                " {} {}",
+                quoted(&self.referer),
+                quoted(&self.user_agent)
+            ));
+        }
+        line
+    }
+}
+
+/// Escape quotes, backslashes and control characters the way Apache does
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> Entry {
+        Entry {
+            client: "127.0.0.1".to_string(),
+            time: "16/Oct/2026:10:00:00 +0200".to_string(),
+            request_line: "GET /app.wasm?v=\"1\" HTTP/1.1".to_string(),
+            status: 200,
+            bytes: Some(5120),
+            referer: None,
+            user_agent: Some("curl/8.5.0".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_common_and_combined_lines() {
+        assert_eq!(
+            entry().format(AccessLogFormat::Common),
+            r#"127.0.0.1 - - [16/Oct/2026:10:00:00 +0200] "GET /app.wasm?v=\"1\" HTTP/1.1" 200 5120"#
+        );
+        let not_modified = Entry {
+            status: 304,
+            bytes: Some(0),
+            ..entry()
+        };
+        assert_eq!(
+            not_modified.format(AccessLogFormat::Combined),
+            r#"127.0.0.1 - - [16/Oct/2026:10:00:00 +0200] "GET /app.wasm?v=\"1\" HTTP/1.1" 304 - "-" "curl/8.5.0""#
+        );
+        assert_eq!(escape("a\\b\tc"), "a\\\\b\\x09c");
+    }
+}
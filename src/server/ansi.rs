@@ -0,0 +1,234 @@
+//! Compiler output with ANSI colors, as HTML for the error overlay
+//!
+//! Only SGR sequences (`ESC [ … m`) are kept, as `<span>`s with inline
+//! styles; cursor movement and other escapes are dropped.
+
+use super::compare::escape_html;
+
+/// The 16 basic colors, normal then bright, close to common terminal themes
+const PALETTE: [&str; 16] = [
+    "#3b3b3b", "#ef5350", "#8bc34a", "#fbc02d", "#42a5f5", "#ba68c8", "#26c6da", "#e0e0e0",
+    "#757575", "#ff8a80", "#ccff90", "#ffff8d", "#82b1ff", "#ea80fc", "#84ffff", "#ffffff",
+];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Style {
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    fg: Option<String>,
+    bg: Option<String>,
+}
+
+impl Style {
+    fn css(&self) -> String {
+        let mut css = String::new();
+        if self.bold {
+            css.push_str("font-weight:bold;");
+        }
+        if self.dim {
+            css.push_str("opacity:.7;");
+        }
+        if self.italic {
+            css.push_str("font-style:italic;");
+        }
+        if self.underline {
+            css.push_str("text-decoration:underline;");
+        }
+        if let Some(fg) = &self.fg {
+            css.push_str(&format!("color:{fg};"));
+        }
+        if let Some(bg) = &self.bg {
+            css.push_str(&format!("background:{bg};"));
+        }
+        css
+    }
+
+    /// Apply the parameters of one SGR sequence
+    fn apply(&mut self, params: &[u32]) {
+        if params.is_empty() {
+            *self = Style::default();
+            return;
+        }
+        let mut params = params.iter().copied();
+        while let Some(code) = params.next() {
+            match code {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                30..=37 => self.fg = Some(PALETTE[(code - 30) as usize].to_string()),
+                90..=97 => self.fg = Some(PALETTE[(code - 90 + 8) as usize].to_string()),
+                40..=47 => self.bg = Some(PALETTE[(code - 40) as usize].to_string()),
+                100..=107 => self.bg = Some(PALETTE[(code - 100 + 8) as usize].to_string()),
+                39 => self.fg = None,
+                49 => self.bg = None,
+                38 | 48 => {
+                    let color = extended_color(&mut params);
+                    if code == 38 {
+                        self.fg = color;
+                    } else {
+                        self.bg = color;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The color of a `38;5;n` or `38;2;r;g;b` sequence, after the 38 or 48
+fn extended_color(params: &mut impl Iterator<Item = u32>) -> Option<String> {
+    match params.next()? {
+        5 => {
+            let index = params.next()?;
+            Some(match index {
+                0..=15 => PALETTE[index as usize].to_string(),
+                16..=231 => {
+                    let index = index - 16;
+                    let level = |n: u32| if n == 0 { 0 } else { 55 + n * 40 };
+                    format!(
+                        "rgb({},{},{})",
+                        level(index / 36),
+                        level(index / 6 % 6),
+                        level(index % 6)
+                    )
+                }
+                _ => {
+                    let grey = 8 + (index.min(255) - 232) * 10;
+                    format!("rgb({grey},{grey},{grey})")
+                }
+            })
+        }
+        2 => {
+            let (r, g, b) = (params.next()?, params.next()?, params.next()?);
+            Some(format!("rgb({},{},{})", r.min(255), g.min(255), b.min(255)))
+        }
+        _ => None,
+    }
+}
+
+/// Split the escape sequence off `rest`, which follows an ESC: the
+/// parameters of an SGR sequence, and what comes after the sequence
+fn split_escape(rest: &str) -> (Option<&str>, &str) {
+    if let Some(sequence) = rest.strip_prefix('[') {
+        let end = sequence
+            .find(|c: char| ('@'..='~').contains(&c))
+            .unwrap_or(sequence.len());
+        let params = sequence[end..].starts_with('m').then(|| &sequence[..end]);
+        return (params, sequence.get(end + 1..).unwrap_or(""));
+    }
+    // Others, such as `ESC ( B`, end at the first byte past their intermediates
+    let end = rest
+        .char_indices()
+        .find(|(_, c)| !(' '..='/').contains(c))
+        .map_or(rest.len(), |(index, c)| index + c.len_utf8());
+    (None, &rest[end..])
+}
+
+/// The HTML being written, with a span open for the current style
+#[derive(Default)]
+struct Writer {
+    html: String,
+    /// The style of the open span, if one is
+    open: Option<String>,
+}
+
+impl Writer {
+    /// Write `text` in the style `css`, opening a span only for text
+    fn text(&mut self, text: &str, css: String) {
+        if text.is_empty() {
+            return;
+        }
+        if self.open.as_ref() != Some(&css) {
+            self.close();
+            if !css.is_empty() {
+                self.html.push_str(&format!("<span style=\"{css}\">"));
+                self.open = Some(css);
+            }
+        }
+        self.html.push_str(&escape_html(text));
+    }
+
+    fn close(&mut self) {
+        if self.open.take().is_some() {
+            self.html.push_str("</span>");
+        }
+    }
+}
+
+/// `text` as HTML, with its colors as styled spans
+pub fn to_html(text: &str) -> String {
+    let mut writer = Writer::default();
+    let mut style = Style::default();
+    let mut rest = text;
+    while let Some(start) = rest.find('\x1b') {
+        writer.text(&rest[..start], style.css());
+        let (params, after) = split_escape(&rest[start + 1..]);
+        if let Some(params) = params {
+            let params: Vec<u32> = params
+                .split(';')
+                .filter(|param| !param.is_empty())
+                .map(|param| param.parse().unwrap_or(0))
+                .collect();
+            style.apply(&params);
+        }
+        rest = after;
+    }
+    writer.text(rest, style.css());
+    writer.close();
+    writer.html
+}
+
+/// `text` without its escape sequences
+pub fn strip(text: &str) -> String {
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('\x1b') {
+        plain.push_str(&rest[..start]);
+        rest = split_escape(&rest[start + 1..]).1;
+    }
+    plain.push_str(rest);
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colors_become_spans() {
+        let cargo = "\x1b[0m\x1b[1m\x1b[38;5;9merror[E0425]\x1b[0m\x1b[0m\x1b[1m: cannot find value `x` in <scope>\x1b[0m";
+        assert_eq!(
+            to_html(cargo),
+            "<span style=\"font-weight:bold;color:#ff8a80;\">error[E0425]</span>\
+             <span style=\"font-weight:bold;\">: cannot find value `x` in &lt;scope&gt;</span>"
+        );
+        assert_eq!(
+            strip(cargo),
+            "error[E0425]: cannot find value `x` in <scope>"
+        );
+
+        assert_eq!(
+            to_html("\x1b[32mok\x1b[39m \x1b[48;2;1;2;300mbg\x1b[m"),
+            "<span style=\"color:#8bc34a;\">ok</span> \
+             <span style=\"background:rgb(1,2,255);\">bg</span>"
+        );
+    }
+
+    #[test]
+    fn test_other_escapes_are_dropped() {
+        assert_eq!(to_html("\x1b[2Kdone\x1b[1A"), "done");
+        assert_eq!(to_html("a\x1b(Bb"), "ab");
+        assert_eq!(to_html("cut \x1b[31"), "cut ");
+        assert_eq!(strip("\x1b[2K\x1b[31mred\x1b[0m"), "red");
+    }
+}
@@ -1,9 +1,18 @@
-use std::fs;
-use tiny_http::{Request, Response};
+use std::fs::{self, File};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use tiny_http::{Header, Request, Response, StatusCode};
 
-use super::utils::{check_assets_directory, content_type_header};
+use super::compression;
+use super::conditional::{self, Validators};
+use super::file_cache;
+use super::profiler;
+use super::range::{self, ByteRange};
+use super::toggles::{Throttled, Toggles};
+use super::utils::{check_assets_directory, content_type_header, respond};
 use crate::commands::verify_wasm;
 use crate::plugin::manager::PluginManager;
+use crate::{error_println, request_println, warn_println};
 
 /// Serve WASM module information as JSON
 pub fn serve_module_info(request: Request, wasm_path: &str, project_path: Option<&str>) {
@@ -12,12 +21,12 @@ pub fn serve_module_info(request: Request, wasm_path: &str, project_path: Option
             // Get plugin information for the project
             let plugin_info = if let Ok(plugin_manager) = PluginManager::new() {
                 if let Some(project_path) = project_path {
-                    println!("🔍 Looking for plugin for project: {project_path}");
+                    request_println!("🔍 Looking for plugin for project: {project_path}");
 
                     // Find the plugin used for this project
                     if let Some(plugin) = plugin_manager.find_plugin_for_project(project_path) {
                         let info = plugin.info();
-                        println!("✅ Found plugin: {} v{}", info.name, info.version);
+                        request_println!("🔌 Found plugin: {} v{}", info.name, info.version);
                         Some(serde_json::json!({
                             "name": info.name,
                             "version": info.version,
@@ -40,6 +49,12 @@ pub fn serve_module_info(request: Request, wasm_path: &str, project_path: Option
                                         "type": "git",
                                         "url": url,
                                         "branch": branch
+                                    }),
+                                crate::plugin::PluginSource::Url { url, sha256 } =>
+                                    serde_json::json!({
+                                        "type": "url",
+                                        "url": url,
+                                        "sha256": sha256
                                     })
                             }),
                             "capabilities": {
@@ -51,15 +66,15 @@ pub fn serve_module_info(request: Request, wasm_path: &str, project_path: Option
                             }
                         }))
                     } else {
-                        println!("❌ No plugin found for project: {project_path}");
+                        request_println!("🔌 No plugin found for project: {project_path}");
                         None
                     }
                 } else {
-                    println!("❌ No project path provided, unable to detect plugin");
+                    request_println!("🔌 No project path provided, unable to detect plugin");
                     None
                 }
             } else {
-                println!("❌ Failed to create plugin manager");
+                warn_println!("Failed to create plugin manager");
                 None
             };
 
@@ -88,7 +103,7 @@ pub fn serve_module_info(request: Request, wasm_path: &str, project_path: Option
                 json_response["plugin"] = plugin;
             }
 
-            println!("📊 Serving module info for: {wasm_path}");
+            request_println!("📊 Serving module info for: {wasm_path}");
 
             let response = Response::from_string(json_response.to_string())
                 .with_header(content_type_header("application/json"))
@@ -97,12 +112,12 @@ pub fn serve_module_info(request: Request, wasm_path: &str, project_path: Option
                         .unwrap(),
                 );
 
-            if let Err(e) = request.respond(response) {
-                eprintln!("❗ Error sending module info response: {e}");
+            if let Err(e) = respond(request, response) {
+                error_println!("Error sending module info response: {e}");
             }
         }
         Err(error) => {
-            eprintln!("❗ Error analyzing WASM module {wasm_path}: {error}");
+            error_println!("Error analyzing WASM module {wasm_path}: {error}");
 
             let error_response = serde_json::json!({
                 "error": error,
@@ -117,8 +132,8 @@ pub fn serve_module_info(request: Request, wasm_path: &str, project_path: Option
                         .unwrap(),
                 );
 
-            if let Err(e) = request.respond(response) {
-                eprintln!("❗ Error sending error response: {e}");
+            if let Err(e) = respond(request, response) {
+                error_println!("Error sending error response: {e}");
             }
         }
     }
@@ -134,7 +149,7 @@ pub fn serve_version_info(request: Request) {
         "version": version
     });
 
-    println!("📊 Serving version info: {name} v{version}");
+    request_println!("📊 Serving version info: {name} v{version}");
 
     let response = Response::from_string(version_response.to_string())
         .with_header(content_type_header("application/json"))
@@ -142,36 +157,138 @@ pub fn serve_version_info(request: Request) {
             tiny_http::Header::from_bytes(&b"Access-Control-Allow-Origin"[..], b"*").unwrap(),
         );
 
-    if let Err(e) = request.respond(response) {
-        eprintln!("❗ Error sending version info response: {e}");
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending version info response: {e}");
     }
 }
 
+/// A response body read from memory or straight from disk
+trait Body: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> Body for T {}
+
 /// Serve a file
 pub fn serve_file(request: Request, file_path: &str, content_type: &str) {
-    match fs::read(file_path) {
-        Ok(file_bytes) => {
-            println!(
-                "🔄 Serving file: {} ({} bytes, content-type: {})",
-                file_path,
-                file_bytes.len(),
-                content_type
-            );
-            let response =
-                Response::from_data(file_bytes).with_header(content_type_header(content_type));
-            if let Err(e) = request.respond(response) {
-                eprintln!("❗ Error sending file response: {e}");
+    let toggles = Toggles::from_request(&request);
+    // With `--instrument` or `?profile=1`, modules are served rewritten rather than as stored
+    if content_type == "application/wasm" {
+        if let Some(bytes) = profiler::instrumented(file_path, toggles.profile) {
+            request_println!("🔄 Serving instrumented module: {file_path}");
+            let response = Response::from_data(bytes)
+                .with_header(content_type_header(content_type))
+                .with_header(Header::from_bytes("Cache-Control", "no-store").unwrap());
+            if let Err(e) = respond(request, response) {
+                error_println!("Error sending file response: {e}");
             }
+            return;
         }
+    }
+
+    // Prefer a pre-compressed sibling such as app.wasm.br over compressing on every request
+    let precompressed = compression::precompressed(&request, file_path);
+    let file_path = match &precompressed {
+        Some((sibling, _)) => sibling.to_str().unwrap_or(file_path),
+        None => file_path,
+    };
+    let opened = File::open(file_path).and_then(|file| Ok((file.metadata()?, file)));
+    let (metadata, file) = match opened {
+        Ok(opened) => opened,
         Err(e) => {
-            eprintln!("❗ Error reading file {file_path}: {e}");
+            error_println!("Error reading file {file_path}: {e}");
             let response = Response::from_string(format!("Error: {e}"))
                 .with_status_code(500)
                 .with_header(content_type_header("text/plain"));
-            if let Err(e) = request.respond(response) {
-                eprintln!("❗ Error sending error response: {e}");
+            if let Err(e) = respond(request, response) {
+                error_println!("Error sending error response: {e}");
+            }
+            return;
+        }
+    };
+
+    // Repeated requests are answered from memory while the file is unchanged
+    let cached = (!toggles.nocache)
+        .then(|| file_cache::read(Path::new(file_path), &metadata, content_type))
+        .flatten();
+    let from_memory = if cached.is_some() { " from memory" } else { "" };
+    let mut body: Box<dyn Body> = match cached {
+        Some(bytes) => Box::new(Cursor::new(bytes)),
+        None => Box::new(file),
+    };
+    if toggles.throttle {
+        body = Box::new(Throttled::new(body));
+    }
+
+    let length = metadata.len();
+    let validators = (conditional::is_enabled() && !toggles.nocache)
+        .then(|| Validators::from_metadata(&metadata));
+    let mut headers = vec![content_type_header(content_type)];
+    match &validators {
+        Some(validators) => headers.extend([
+            Header::from_bytes("ETag", validators.etag.as_str()).unwrap(),
+            Header::from_bytes("Last-Modified", validators.last_modified()).unwrap(),
+        ]),
+        None => headers.push(Header::from_bytes("Cache-Control", "no-store").unwrap()),
+    }
+    if let Some((_, encoding)) = precompressed {
+        headers.extend([
+            Header::from_bytes("Content-Encoding", encoding.as_str()).unwrap(),
+            Header::from_bytes("Vary", "Accept-Encoding").unwrap(),
+        ]);
+    }
+
+    if validators
+        .as_ref()
+        .is_some_and(|validators| validators.not_modified(&request))
+    {
+        request_println!("🔄 Not modified: {file_path}");
+        let response = Response::new(StatusCode(304), headers, std::io::empty(), None, None);
+        if let Err(e) = respond(request, response) {
+            error_println!("Error sending file response: {e}");
+        }
+        return;
+    }
+
+    // Stream from disk rather than reading the file, which may be hundreds of MB.
+    // Large bodies would otherwise be sent chunked, without a Content-Length.
+    let range = range::requested(&request, length, validators.as_ref());
+    headers.push(Header::from_bytes("Accept-Ranges", "bytes").unwrap());
+    if let Some(content_range) = range.content_range(length) {
+        headers.push(Header::from_bytes("Content-Range", content_range).unwrap());
+    }
+    let response = match range {
+        ByteRange::Full => {
+            request_println!("🔄 Serving file{from_memory}: {file_path} ({length} bytes, content-type: {content_type})");
+            Response::new(StatusCode(200), headers, body, Some(length as usize), None)
+                .with_chunked_threshold(usize::MAX)
+                .boxed()
+        }
+        ByteRange::Partial { start, end } => {
+            let part = end - start + 1;
+            request_println!(
+                "🔄 Serving file{from_memory}: {file_path} (bytes {start}-{end} of {length})"
+            );
+            if let Err(e) = body.seek(SeekFrom::Start(start)) {
+                error_println!("Error reading file {file_path}: {e}");
+                Response::empty(500).boxed()
+            } else {
+                Response::new(
+                    StatusCode(206),
+                    headers,
+                    body.take(part),
+                    Some(part as usize),
+                    None,
+                )
+                .with_chunked_threshold(usize::MAX)
+                .boxed()
             }
         }
+        ByteRange::Unsatisfiable => {
+            warn_println!("Range outside {file_path} ({length} bytes) requested");
+            Response::new(StatusCode(416), headers, std::io::empty(), Some(0), None).boxed()
+        }
+    };
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending file response: {e}");
     }
 }
 
@@ -180,45 +297,31 @@ pub fn serve_asset(request: Request, url: &str) {
     let asset_filename = url.strip_prefix("/assets/").unwrap_or("");
     let asset_path = format!("./assets/{asset_filename}");
 
-    let content_type = if url.ends_with(".png") {
-        "image/png"
-    } else if url.ends_with(".jpg") || url.ends_with(".jpeg") {
-        "image/jpeg"
-    } else if url.ends_with(".svg") {
-        "image/svg+xml"
-    } else if url.ends_with(".gif") {
-        "image/gif"
-    } else if url.ends_with(".css") {
-        "text/css"
-    } else if url.ends_with(".js") {
-        "application/javascript"
-    } else {
-        "application/octet-stream"
-    };
+    let content_type = super::mime::for_path(Path::new(&asset_path));
 
     match fs::read(&asset_path) {
         Ok(asset_bytes) => {
-            println!(
+            request_println!(
                 "🖼️ Successfully serving asset: {} ({} bytes)",
                 asset_path,
                 asset_bytes.len()
             );
             let response =
-                Response::from_data(asset_bytes).with_header(content_type_header(content_type));
-            if let Err(e) = request.respond(response) {
-                eprintln!("‼️ Error sending asset response: {e}");
+                Response::from_data(asset_bytes).with_header(content_type_header(&content_type));
+            if let Err(e) = respond(request, response) {
+                error_println!("Error sending asset response: {e}");
             }
         }
         Err(e) => {
-            eprintln!("‼️ Error reading asset file {asset_path}: {e} (does the file exist?)");
+            error_println!("Error reading asset file {asset_path}: {e} (does the file exist?)");
 
             check_assets_directory();
 
             let response = Response::from_string(format!("Asset not found: {e}"))
                 .with_status_code(404)
                 .with_header(content_type_header("text/plain"));
-            if let Err(e) = request.respond(response) {
-                eprintln!("‼️ Error sending asset error response: {e}");
+            if let Err(e) = respond(request, response) {
+                error_println!("Error sending asset error response: {e}");
             }
         }
     }
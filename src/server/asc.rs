@@ -0,0 +1,115 @@
+//! Running modules built by AssemblyScript
+//!
+//! `asc --bindings esm` writes an ES module next to the module that compiles
+//! it, instantiates it with the imports the runtime needs (`abort`, `trace`,
+//! `seed`) and exports its functions with strings and arrays converted. When
+//! such bindings sit next to the module they are served with it, and the page
+//! imports them through the `window.init` hook. The instantiated exports are
+//! left on `window.asc` for the browser console.
+
+use std::fs;
+use std::path::Path;
+
+/// Whether `source` is AssemblyScript bindings, generated by asc or by the
+/// built-in plugin for a module built without them
+pub fn is_assemblyscript_glue(source: &str) -> bool {
+    source.contains("async function instantiate(module, imports = {})")
+}
+
+/// The bindings to serve the module at `wasm_path` with, when asc built it
+pub fn glue_for(wasm_path: &str) -> Option<String> {
+    let glue = Path::new(wasm_path).with_extension("js");
+    let source = fs::read_to_string(&glue).ok()?;
+    is_assemblyscript_glue(&source).then(|| glue.to_string_lossy().to_string())
+}
+
+/// Add the loader to a page serving the module at `wasm_path` as
+/// `wasm_filename`, with `js_filename` next to it
+pub fn inject(
+    html: &str,
+    wasm_path: &str,
+    wasm_filename: &str,
+    js_filename: Option<&str>,
+) -> String {
+    let Some(js_filename) = js_filename else {
+        return html.to_string();
+    };
+    let is_glue = Path::new(wasm_path)
+        .parent()
+        .and_then(|dir| fs::read_to_string(dir.join(js_filename)).ok())
+        .is_some_and(|source| is_assemblyscript_glue(&source));
+    if !is_glue {
+        return html.to_string();
+    }
+
+    let module = serde_json::to_string(wasm_filename)
+        .unwrap_or_default()
+        .replace('<', "\\u003c");
+    let href = js_filename.replace('&', "&amp;").replace('"', "&quot;");
+    // A preload link carries the versioned URL of the bindings to the loader
+    let script = format!(
+        "<link rel=\"modulepreload\" href=\"{href}\" data-wasmrun-asc>\n<script>\n{}</script>",
+        LOADER_SCRIPT.replace("__MODULE__", &module)
+    );
+    let head = html
+        .find("<head")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+    match head {
+        Some(index) => format!("{}\n{script}{}", &html[..index], &html[index..]),
+        None => format!("{script}\n{html}"),
+    }
+}
+
+const LOADER_SCRIPT: &str = r#"window.init = async () => {
+  const bindings = document.querySelector("link[data-wasmrun-asc]").href;
+  // The bindings compile the module themselves; keep it for the page
+  const instantiate = WebAssembly.instantiate;
+  let compiled;
+  WebAssembly.instantiate = function (source, ...rest) {
+    if (source instanceof WebAssembly.Module) compiled = source;
+    return instantiate.call(this, source, ...rest);
+  };
+  try {
+    const exports = await import(bindings);
+    window.asc = exports.default || exports;
+    console.info("wasmrun: AssemblyScript exports available as window.asc");
+  } finally {
+    WebAssembly.instantiate = instantiate;
+  }
+  return compiled || WebAssembly.compileStreaming(fetch(__MODULE__));
+};
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loader_only_for_assemblyscript_bindings() {
+        let dir = tempfile::tempdir().unwrap();
+        let wasm = dir.path().join("release.wasm");
+        let wasm_path = wasm.to_str().unwrap();
+        fs::write(&wasm, b"\0asm\x01\0\0\0").unwrap();
+        assert_eq!(glue_for(wasm_path), None);
+        fs::write(dir.path().join("app.js"), "export default 1;").unwrap();
+        fs::write(
+            dir.path().join("release.js"),
+            "async function instantiate(module, imports = {}) {\n  return {};\n}",
+        )
+        .unwrap();
+        assert!(glue_for(wasm_path).unwrap().ends_with("release.js"));
+
+        let page = "<html><head><title>x</title></head><body></body></html>";
+        assert_eq!(inject(page, wasm_path, "release.wasm", None), page);
+        assert_eq!(
+            inject(page, wasm_path, "release.wasm", Some("app.js")),
+            page
+        );
+        let injected = inject(page, wasm_path, "release.wasm", Some("release.js"));
+        assert!(injected.starts_with(
+            "<html><head>\n<link rel=\"modulepreload\" href=\"release.js\" data-wasmrun-asc>"
+        ));
+        assert!(injected.contains(r#"fetch("release.wasm")"#));
+        assert!(injected.find("window.init").unwrap() < injected.find("<title>").unwrap());
+    }
+}
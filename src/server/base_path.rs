@@ -0,0 +1,187 @@
+//! Serving under a path prefix behind a reverse proxy (`--base /myapp/`)
+//!
+//! Requests are routed the same whether the proxy forwards the full path
+//! (`/myapp/assets/x.css`) or strips the prefix (`/assets/x.css`). Absolute
+//! URLs in generated pages are prefixed so the browser keeps going through
+//! the proxy.
+
+use super::utils::respond;
+use crate::config::active_settings;
+use crate::error_println;
+use regex::{Captures, Regex};
+use std::sync::RwLock;
+use tiny_http::{Header, Request, Response};
+
+/// Base path given on the command line; takes precedence over `server.base_path`
+static OVERRIDE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Server routes referenced by absolute URLs in the page scripts
+const ROUTE_PREFIXES: &str = r"api/|assets/|reload\b|node_modules/|__wasmrun/";
+
+/// How to handle a request URL under the base path
+#[derive(Debug, PartialEq)]
+pub enum Route {
+    /// Serve this URL, relative to the server root
+    Path(String),
+    /// Send the browser here first (the base path without its trailing slash)
+    Redirect(String),
+}
+
+/// Validate a base path and bring it to the `/prefix/` form; `/` means no prefix
+pub fn normalize(base: &str) -> std::result::Result<String, String> {
+    let trimmed = base.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        return Ok("/".to_string());
+    }
+    if trimmed.contains(['?', '#', '\\']) || trimmed.contains("//") {
+        return Err("must be a plain path such as /myapp/".to_string());
+    }
+    if trimmed
+        .split('/')
+        .any(|segment| segment == "." || segment == "..")
+    {
+        return Err("must not contain '.' or '..' segments".to_string());
+    }
+    Ok(format!("/{trimmed}/"))
+}
+
+/// Use `base` instead of the configured `server.base_path`
+pub fn set_override(base: String) {
+    if let Ok(mut current) = OVERRIDE.write() {
+        *current = Some(base);
+    }
+}
+
+/// The active base path, always starting and ending with `/`
+pub fn active() -> String {
+    let configured = OVERRIDE
+        .read()
+        .ok()
+        .and_then(|current| current.clone())
+        .or(active_settings().server.base_path);
+    configured
+        .and_then(|base| normalize(&base).ok())
+        .unwrap_or_else(|| "/".to_string())
+}
+
+/// Map a request URL onto the server's routes
+pub fn route(url: &str, base: &str) -> Route {
+    if base == "/" {
+        return Route::Path(url.to_string());
+    }
+
+    let (path, query) = match url.find('?') {
+        Some(index) => url.split_at(index),
+        None => (url, ""),
+    };
+    if path == base.trim_end_matches('/') {
+        return Route::Redirect(format!("{base}{query}"));
+    }
+    match path.strip_prefix(base) {
+        Some(rest) => Route::Path(format!("/{rest}{query}")),
+        None => Route::Path(url.to_string()),
+    }
+}
+
+/// Redirect the browser to `location`
+pub fn redirect(request: Request, location: &str) {
+    let mut response = Response::empty(302);
+    if let Ok(header) = Header::from_bytes("Location", location) {
+        response = response.with_header(header);
+    }
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending redirect: {e}");
+    }
+}
+
+/// Prefix the absolute URLs in a generated page with `base`
+pub fn rewrite_html(html: &str, base: &str) -> String {
+    if base == "/" {
+        return html.to_string();
+    }
+
+    let attributes = Regex::new(r#"(\b(?:src|href|action|poster)=["'])/([^/])"#).unwrap();
+    let html = attributes.replace_all(html, |caps: &Captures| {
+        format!("{}{base}{}", &caps[1], &caps[2])
+    });
+
+    let literals = Regex::new(&format!(r#"(["'`])/({ROUTE_PREFIXES})"#)).unwrap();
+    let html = literals.replace_all(&html, |caps: &Captures| {
+        format!("{}{base}{}", &caps[1], &caps[2])
+    });
+
+    // Catch URLs built at runtime that the rewrite above cannot see
+    let script = format!(
+        r#"<script>
+window.__WASMRUN_BASE__ = {base:?};
+(function (base) {{
+  const fix = (url) => typeof url === "string" && url.startsWith("/") && !url.startsWith("//") && !url.startsWith(base) ? base + url.slice(1) : url;
+  const fetch = window.fetch;
+  window.fetch = (input, init) => fetch(fix(input), init);
+  const open = XMLHttpRequest.prototype.open;
+  XMLHttpRequest.prototype.open = function (method, url, ...rest) {{ return open.call(this, method, fix(url), ...rest); }};
+}})(window.__WASMRUN_BASE__);
+</script>"#
+    );
+
+    match html.find("<head>") {
+        Some(index) => {
+            let at = index + "<head>".len();
+            format!("{}\n{script}{}", &html[..at], &html[at..])
+        }
+        None => format!("{script}\n{html}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("myapp").unwrap(), "/myapp/");
+        assert_eq!(normalize("/team/app/").unwrap(), "/team/app/");
+        assert_eq!(normalize("/").unwrap(), "/");
+        assert!(normalize("/a/../b").is_err());
+        assert!(normalize("/a?x=1").is_err());
+    }
+
+    #[test]
+    fn test_route_with_and_without_prefix() {
+        let base = "/myapp/";
+        assert_eq!(route("/myapp/", base), Route::Path("/".to_string()));
+        assert_eq!(
+            route("/myapp/assets/logo.png", base),
+            Route::Path("/assets/logo.png".to_string())
+        );
+        assert_eq!(
+            route("/api/version", base),
+            Route::Path("/api/version".to_string())
+        );
+        assert_eq!(
+            route("/myapp?x=1", base),
+            Route::Redirect("/myapp/?x=1".to_string())
+        );
+        assert_eq!(route("/a/b", "/"), Route::Path("/a/b".to_string()));
+    }
+
+    #[test]
+    fn test_rewrite_html() {
+        let html = r#"<html><head><link rel="icon" href="/assets/logo.png"></head>
+<body><a href="/">home</a><img src="//cdn.example/x.png">
+<script>fetch('/api/module-info'); const r = "/reload"; const u = `/api/fs/list${p}`;</script>
+<script type="importmap">{"imports":{"pkg":"/node_modules/pkg/index.js"}}</script></body></html>"#;
+        let rewritten = rewrite_html(html, "/myapp/");
+
+        assert!(rewritten.contains(r#"href="/myapp/assets/logo.png""#));
+        assert!(rewritten.contains(r#"<a href="/myapp/">"#));
+        assert!(rewritten.contains(r#"src="//cdn.example/x.png""#));
+        assert!(rewritten.contains("fetch('/myapp/api/module-info')"));
+        assert!(rewritten.contains(r#""/myapp/reload""#));
+        assert!(rewritten.contains("`/myapp/api/fs/list${p}`"));
+        assert!(rewritten.contains(r#""/myapp/node_modules/pkg/index.js""#));
+        assert!(rewritten.contains(r#"window.__WASMRUN_BASE__ = "/myapp/";"#));
+
+        assert_eq!(rewrite_html(html, "/"), html);
+    }
+}
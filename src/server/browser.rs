@@ -0,0 +1,220 @@
+//! Opening served pages in a browser
+//!
+//! How a page is opened depends on where wasmrun runs. Under WSL the Windows
+//! browser is started through `wslview`, or `cmd.exe` when wslu isn't
+//! installed. In an SSH session, on a Linux machine without a display, or in
+//! CI no browser is started; the URL is printed instead, with the port
+//! forward to set up for SSH. `BROWSER` always wins: when it is set, the
+//! command it names is used wherever wasmrun runs.
+
+use super::urls::{ServerUrls, UrlKind};
+use crate::utils::SystemUtils;
+use std::process::{Command, Stdio};
+
+/// What to do about the page of the server on a port
+pub enum Plan {
+    /// Open this URL with the opener
+    Open(Opener, String),
+    /// Tell the user how to open the page themselves
+    Explain(Vec<String>),
+}
+
+/// How to show the page served on `port`
+pub fn plan(port: u16) -> Plan {
+    let urls = ServerUrls::for_port(port);
+    let url_of = |url: Option<&super::urls::ServerUrl>| {
+        url.map(|url| url.url.clone())
+            .unwrap_or_else(|| format!("{}://localhost:{port}", super::tls::scheme()))
+    };
+    match Opener::detect() {
+        // The forwarded port is reached on the user's own localhost
+        Opener::Skip(skip @ Skip::Ssh { .. }) => {
+            let local = urls.urls.iter().find(|url| url.kind == UrlKind::Local);
+            Plan::Explain(skip.instructions(&url_of(local), port))
+        }
+        Opener::Skip(skip) => Plan::Explain(skip.instructions(&url_of(urls.preferred()), port)),
+        opener => Plan::Open(opener, url_of(urls.preferred())),
+    }
+}
+
+/// How to open a page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Opener {
+    /// The desktop's default browser, or `BROWSER`
+    System,
+    /// The Windows browser, from inside WSL
+    Wsl,
+    /// Don't open anything
+    Skip(Skip),
+}
+
+/// Why no browser is opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Skip {
+    /// Connected over SSH; the address the client connected to, if known
+    Ssh { server: Option<String> },
+    /// A CI job
+    Ci,
+    /// No X11 or Wayland display
+    NoDisplay,
+}
+
+impl Opener {
+    /// The opener for this machine and session
+    pub fn detect() -> Self {
+        Self::detect_from(
+            |name| std::env::var(name).ok(),
+            is_wsl(),
+            cfg!(target_os = "linux"),
+        )
+    }
+
+    /// The opener given the variables `env` returns; `linux` says whether a
+    /// missing display means there is no desktop
+    fn detect_from(env: impl Fn(&str) -> Option<String>, wsl: bool, linux: bool) -> Self {
+        let set = |name: &str| env(name).is_some_and(|value| !value.trim().is_empty());
+        if set("BROWSER") {
+            return Opener::System;
+        }
+        if set("CI") {
+            return Opener::Skip(Skip::Ci);
+        }
+        if wsl {
+            return Opener::Wsl;
+        }
+        if set("SSH_CONNECTION") || set("SSH_CLIENT") || set("SSH_TTY") {
+            // SSH_CONNECTION is "client-ip client-port server-ip server-port"
+            let server = env("SSH_CONNECTION")
+                .and_then(|connection| connection.split_whitespace().nth(2).map(str::to_string));
+            return Opener::Skip(Skip::Ssh { server });
+        }
+        if linux && !set("DISPLAY") && !set("WAYLAND_DISPLAY") {
+            return Opener::Skip(Skip::NoDisplay);
+        }
+        Opener::System
+    }
+
+    /// Open `url`
+    pub fn open(&self, url: &str) -> Result<(), String> {
+        match self {
+            Opener::System => webbrowser::open(url).map_err(|e| e.to_string()),
+            Opener::Wsl => open_from_wsl(url),
+            Opener::Skip(_) => Ok(()),
+        }
+    }
+}
+
+impl Skip {
+    /// What to tell the user instead of opening `url`, served on `port`
+    fn instructions(&self, url: &str, port: u16) -> Vec<String> {
+        match self {
+            Skip::Ssh { server } => {
+                let user = std::env::var("USER").map(|user| format!("{user}@"));
+                let host = server.as_deref().unwrap_or("<this-host>");
+                vec![
+                    "🔗 Running over SSH, so no browser is opened here. Forward the port from your machine:".to_string(),
+                    format!(
+                        "   ssh -L {port}:localhost:{port} {}{host}",
+                        user.unwrap_or_default()
+                    ),
+                    format!("   then open {url}"),
+                ]
+            }
+            Skip::Ci => vec![format!(
+                "🔗 Running in CI, not opening a browser. Serving {url}"
+            )],
+            Skip::NoDisplay => vec![format!(
+                "🔗 No display found, not opening a browser. Open {url} (set BROWSER to pick one)"
+            )],
+        }
+    }
+}
+
+/// Whether this is Linux running under WSL
+fn is_wsl() -> bool {
+    if !cfg!(target_os = "linux") {
+        return false;
+    }
+    std::env::var_os("WSL_DISTRO_NAME").is_some()
+        || std::env::var_os("WSL_INTEROP").is_some()
+        || std::fs::read_to_string("/proc/sys/kernel/osrelease")
+            .is_ok_and(|release| release.to_ascii_lowercase().contains("microsoft"))
+}
+
+/// Start the Windows browser on `url`
+fn open_from_wsl(url: &str) -> Result<(), String> {
+    let mut command = if SystemUtils::is_tool_available("wslview") {
+        let mut command = Command::new("wslview");
+        command.arg(url);
+        command
+    } else {
+        // cmd.exe would split the URL at `&` unless it is escaped
+        let mut command = Command::new("cmd.exe");
+        command.args(["/c", "start", &url.replace('&', "^&")]);
+        command
+    };
+    let status = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("Cannot start the Windows browser: {e}"))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "The Windows browser could not be started ({status})"
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn detect(vars: &[(&str, &str)], wsl: bool, linux: bool) -> Opener {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Opener::detect_from(|name| vars.get(name).cloned(), wsl, linux)
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(&[("DISPLAY", ":0")], false, true), Opener::System);
+        assert_eq!(detect(&[], false, false), Opener::System);
+        assert_eq!(detect(&[], false, true), Opener::Skip(Skip::NoDisplay));
+        assert_eq!(detect(&[], true, true), Opener::Wsl);
+        assert_eq!(
+            detect(
+                &[
+                    ("SSH_CONNECTION", "10.0.0.5 51000 10.0.0.9 22"),
+                    ("DISPLAY", "localhost:10.0")
+                ],
+                false,
+                true
+            ),
+            Opener::Skip(Skip::Ssh {
+                server: Some("10.0.0.9".to_string())
+            })
+        );
+        assert_eq!(
+            detect(&[("SSH_TTY", "/dev/pts/1")], false, false),
+            Opener::Skip(Skip::Ssh { server: None })
+        );
+        assert_eq!(
+            detect(&[("CI", "true"), ("DISPLAY", ":0")], true, true),
+            Opener::Skip(Skip::Ci)
+        );
+        assert_eq!(
+            detect(
+                &[("BROWSER", "firefox"), ("SSH_TTY", "/dev/pts/1")],
+                false,
+                true
+            ),
+            Opener::System
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! The state of the latest rebuild, for the page's build overlay
+//!
+//! While watch mode or `wasmrun ctl rebuild` rebuilds the project, every
+//! line the compiler prints is echoed to the terminal and kept here. Open
+//! pages are told a build started through the live reload poll, and show the
+//! output as it arrives in an overlay. A successful build reloads them as
+//! before; a failed one leaves the page running the last good module with
+//! the error on top, until a later build succeeds. Pages rendered from a
+//! template show the compiler's diagnostics from [`ERRORS_ROUTE`] over the
+//! whole page instead, colored as in the terminal.
+
+use super::utils::{content_type_header, respond};
+use super::{ansi, live_reload};
+use crate::error_println;
+use std::sync::Mutex;
+use std::time::Instant;
+use tiny_http::{Request, Response};
+
+/// Where pages fetch the build state
+pub const BUILD_ROUTE: &str = "/__wasmrun/build";
+
+/// Where the error overlay fetches the diagnostics of a failed build
+pub const ERRORS_ROUTE: &str = "/__wasmrun/errors";
+
+/// Lines of output kept per build; older ones are dropped first
+const MAX_LINES: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    Idle,
+    Building,
+    Failed,
+}
+
+#[derive(Debug)]
+struct Status {
+    /// Counts builds, so pages can tell a new one from more output
+    build: u64,
+    phase: Phase,
+    /// What started the build, e.g. the files that changed
+    reason: String,
+    started: Option<Instant>,
+    /// Output lines, and how many were dropped from the front
+    log: Vec<String>,
+    dropped: usize,
+    error: Option<String>,
+}
+
+static STATUS: Mutex<Status> = Mutex::new(Status {
+    build: 0,
+    phase: Phase::Idle,
+    reason: String::new(),
+    started: None,
+    log: Vec::new(),
+    dropped: 0,
+    error: None,
+});
+
+/// Note that a build started because of `reason`
+pub fn begin(reason: &str) {
+    if let Ok(mut status) = STATUS.lock() {
+        status.build += 1;
+        status.phase = Phase::Building;
+        status.reason = reason.to_string();
+        status.started = Some(Instant::now());
+        status.log.clear();
+        status.dropped = 0;
+        status.error = None;
+    }
+    crate::tui::emit(crate::tui::DevEvent::BuildStarted {
+        reason: reason.to_string(),
+    });
+    live_reload::announce_build();
+}
+
+/// Whether a build started with [`begin`] is running, and wants its output
+pub fn is_building() -> bool {
+    STATUS
+        .lock()
+        .is_ok_and(|status| status.phase == Phase::Building)
+}
+
+/// Keep a line of compiler output
+pub fn append(line: &str) {
+    if let Ok(mut status) = STATUS.lock() {
+        if status.phase != Phase::Building {
+            return;
+        }
+        if status.log.len() == MAX_LINES {
+            status.log.remove(0);
+            status.dropped += 1;
+        }
+        status.log.push(line.to_string());
+    }
+}
+
+/// The output lines kept of the latest build, oldest first
+pub fn output() -> Vec<String> {
+    STATUS
+        .lock()
+        .map(|status| status.log.clone())
+        .unwrap_or_default()
+}
+
+/// Note that the build succeeded; reloading the pages is up to the caller
+pub fn succeeded() {
+    if let Ok(mut status) = STATUS.lock() {
+        status.phase = Phase::Idle;
+    }
+    crate::tui::emit(crate::tui::DevEvent::BuildFinished { success: true });
+}
+
+/// Note that the build failed with `error`, and show it on open pages
+pub fn failed(error: &str) {
+    if let Ok(mut status) = STATUS.lock() {
+        status.phase = Phase::Failed;
+        status.error = Some(error.to_string());
+    }
+    crate::tui::emit(crate::tui::DevEvent::BuildFinished { success: false });
+    live_reload::announce_build();
+}
+
+/// The state as JSON, with output lines from line `from` of the build on
+fn snapshot(from: usize) -> serde_json::Value {
+    let Ok(status) = STATUS.lock() else {
+        return serde_json::json!({ "phase": "idle" });
+    };
+    let phase = match status.phase {
+        Phase::Idle => "idle",
+        Phase::Building => "building",
+        Phase::Failed => "failed",
+    };
+    let skip = from.saturating_sub(status.dropped).min(status.log.len());
+    serde_json::json!({
+        "build": status.build,
+        "phase": phase,
+        "reason": status.reason,
+        "elapsed_ms": status.started.map(|started| started.elapsed().as_millis() as u64),
+        "from": status.dropped + skip,
+        "log": &status.log[skip..],
+        "next": status.dropped + status.log.len(),
+        "error": status.error,
+    })
+}
+
+/// The diagnostics of the latest build as JSON, colored as HTML and plain;
+/// `failed` is false once a later build starts
+fn errors() -> serde_json::Value {
+    let Ok(status) = STATUS.lock() else {
+        return serde_json::json!({ "failed": false });
+    };
+    let error = status.error.clone().unwrap_or_default();
+    // The error usually repeats the tool's output; the log has all of it
+    let diagnostics = if status.log.is_empty() {
+        error.clone()
+    } else {
+        status.log.join("\n")
+    };
+    let title = ansi::strip(error.lines().next().unwrap_or("Build failed"));
+    serde_json::json!({
+        "build": status.build,
+        "failed": status.phase == Phase::Failed,
+        "reason": status.reason,
+        "title": title,
+        "dropped": status.dropped,
+        "html": ansi::to_html(&diagnostics),
+        "text": ansi::strip(&diagnostics),
+    })
+}
+
+fn respond_json(request: Request, body: serde_json::Value) {
+    let response = Response::from_string(body.to_string())
+        .with_header(content_type_header("application/json"))
+        .with_header(tiny_http::Header::from_bytes("Cache-Control", "no-store").unwrap());
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending build status: {e}");
+    }
+}
+
+/// Answer `GET /__wasmrun/errors`
+pub fn serve_errors(request: Request) {
+    respond_json(request, errors());
+}
+
+/// Answer `GET /__wasmrun/build?from=<line>`
+pub fn serve(request: Request) {
+    let from = request
+        .url()
+        .split_once('?')
+        .and_then(|(_, query)| query.split('&').find_map(|pair| pair.strip_prefix("from=")))
+        .and_then(|from| from.parse().ok())
+        .unwrap_or(0);
+    respond_json(request, snapshot(from));
+}
+
+/// Add the build overlay to a served page
+pub fn inject(html: &str) -> String {
+    let script = format!(
+        "<script>\n{}</script>",
+        OVERLAY_SCRIPT.replace("__ROUTE__", &format!("{BUILD_ROUTE:?}"))
+    );
+    match html.rfind("</body>") {
+        Some(index) => format!("{}{script}\n{}", &html[..index], &html[index..]),
+        None => format!("{html}\n{script}"),
+    }
+}
+
+/// The script showing the diagnostics of a failed build over the whole page,
+/// which pages get from their template
+pub fn error_overlay_script() -> String {
+    format!(
+        "<script>\n{}</script>",
+        ERROR_OVERLAY_SCRIPT.replace("__ROUTE__", &format!("{ERRORS_ROUTE:?}"))
+    )
+}
+
+const ERROR_OVERLAY_SCRIPT: &str = r##"(() => {
+  // The build panel leaves failures to this overlay
+  window.__wasmrunErrorOverlay = true;
+  let box = null;
+  let dismissed = -1;
+  const hide = () => { if (box) box.remove(); };
+  const show = (errors) => {
+    if (!box) {
+      box = document.createElement("div");
+      box.id = "wasmrun-error-overlay";
+      box.setAttribute("role", "alertdialog");
+      box.style.cssText = "position:fixed;inset:0;overflow:auto;z-index:2147483647;background:rgba(18,19,23,.96);" +
+        "color:#e6e6e6;font:13px/1.5 ui-monospace,SFMono-Regular,Menlo,monospace;padding:32px 40px;box-sizing:border-box";
+      box.innerHTML = '<button type="button" aria-label="Dismiss" style="float:right;background:none;border:0;color:inherit;font-size:22px;cursor:pointer">×</button>' +
+        '<h2 style="margin:0 0 4px;color:#ff8a80;font-size:18px"></h2><div class="reason" style="opacity:.7"></div>' +
+        '<pre style="margin:20px 0;padding:16px;background:#0d0e11;border-radius:6px;white-space:pre-wrap;overflow-wrap:anywhere"></pre>' +
+        '<div style="opacity:.7">Fix the error and save; the page reloads after the next successful build. Esc dismisses this.</div>';
+      box.querySelector("button").onclick = () => { dismissed = box.dataset.build; hide(); };
+    }
+    box.dataset.build = errors.build;
+    box.querySelector("h2").textContent = "❌ " + errors.title;
+    box.querySelector(".reason").textContent = errors.reason ? `Rebuilt because ${errors.reason}` : "";
+    box.querySelector("pre").innerHTML = (errors.dropped ? `… ${errors.dropped} earlier lines\n` : "") + errors.html;
+    if (!box.isConnected) document.body.appendChild(box);
+  };
+  const check = async () => {
+    const errors = await fetch(__ROUTE__).then((r) => r.json()).catch(() => null);
+    if (errors && errors.failed && String(errors.build) !== String(dismissed)) show(errors);
+    else hide();
+  };
+  document.addEventListener("keydown", (event) => {
+    if (event.key === "Escape" && box && box.isConnected) box.querySelector("button").click();
+  });
+  window.addEventListener("wasmrun:build", check);
+  if (document.readyState === "loading") document.addEventListener("DOMContentLoaded", check);
+  else check();
+})();
+"##;
+
+const OVERLAY_SCRIPT: &str = r##"(() => {
+  let build = 0;
+  let from = 0;
+  let box = null;
+  let polling = false;
+  const overlay = () => {
+    if (!box) {
+      box = document.createElement("div");
+      box.id = "wasmrun-build-overlay";
+      box.setAttribute("role", "status");
+      box.style.cssText = "position:fixed;left:0;right:0;bottom:0;max-height:60vh;overflow:auto;z-index:2147483646;" +
+        "background:#16181d;color:#e6e6e6;font:12px/1.45 ui-monospace,SFMono-Regular,Menlo,monospace;" +
+        "padding:10px 16px 12px;box-shadow:0 -4px 18px rgba(0,0,0,.45)";
+      box.innerHTML = '<button type="button" aria-label="Dismiss" style="float:right;background:none;border:0;color:inherit;font-size:16px;cursor:pointer">×</button>' +
+        '<strong></strong><pre class="log" style="margin:8px 0 0;white-space:pre-wrap"></pre>' +
+        '<pre class="error" style="margin:8px 0 0;white-space:pre-wrap;color:#ff8a80"></pre>';
+      box.querySelector("button").onclick = () => box.remove();
+    }
+    if (!box.isConnected) document.body.appendChild(box);
+    return box;
+  };
+  const update = async () => {
+    const status = await fetch(__ROUTE__ + "?from=" + from).then((r) => r.json()).catch(() => null);
+    if (!status) return false;
+    if (status.phase === "idle" || (status.phase === "failed" && window.__wasmrunErrorOverlay)) {
+      if (box) box.remove();
+      return false;
+    }
+    const panel = overlay();
+    if (status.build !== build) {
+      build = status.build;
+      panel.querySelector(".log").textContent = "";
+      // The offset was into an earlier build's output; start over
+      if (from > 0) {
+        from = 0;
+        return true;
+      }
+    }
+    from = status.next;
+    const log = panel.querySelector(".log");
+    if (status.log.length) log.textContent += status.log.join("\n") + "\n";
+    const building = status.phase === "building";
+    panel.style.borderTop = "3px solid " + (building ? "#3b82f6" : "#ef4444");
+    panel.querySelector("strong").textContent = building
+      ? `🔨 Rebuilding${status.reason ? ` (${status.reason})` : ""}…`
+      : "❌ Build failed — the page keeps running the last successful build";
+    panel.querySelector(".error").textContent = building ? "" : status.error || "";
+    if (building) panel.scrollTop = panel.scrollHeight;
+    return building;
+  };
+  const follow = async () => {
+    if (polling) return;
+    polling = true;
+    while (await update()) await new Promise((resolve) => setTimeout(resolve, 300));
+    polling = false;
+  };
+  window.addEventListener("wasmrun:build", follow);
+  if (document.readyState === "loading") document.addEventListener("DOMContentLoaded", follow);
+  else follow();
+})();
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_is_kept_while_building() {
+        append("before any build");
+        begin("src/lib.rs changed");
+        let build = snapshot(0)["build"].as_u64().unwrap();
+        assert!(is_building());
+        append("Compiling app v0.1.0");
+        append("error[E0425]: cannot find value `x`");
+        failed("cargo build failed");
+
+        // Other tests may start builds too; only check this one
+        let status = snapshot(1);
+        if status["build"].as_u64() == Some(build) {
+            assert_eq!(status["phase"], "failed");
+            assert_eq!(status["from"], 1);
+            assert_eq!(
+                status["log"],
+                serde_json::json!(["error[E0425]: cannot find value `x`"])
+            );
+            assert_eq!(status["next"], 2);
+            assert_eq!(status["error"], "cargo build failed");
+            let errors = errors();
+            if errors["build"].as_u64() == Some(build) {
+                assert_eq!(errors["failed"], true);
+                assert_eq!(errors["title"], "cargo build failed");
+                assert_eq!(
+                    errors["text"],
+                    "Compiling app v0.1.0\nerror[E0425]: cannot find value `x`"
+                );
+            }
+            append("after the build");
+            assert_eq!(snapshot(0)["next"], 2);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Static export of the runner page (`wasmrun bundle`)
+//!
+//! Renders the page the dev server answers `/` with as a file, through the
+//! same glue loaders, import map, cache busting, loading strategy,
+//! accessibility options and base path. Left out is everything that needs
+//! the dev server behind it: live reload, the build error overlay, delta
+//! updates and the development panels. Files next to the module keep their
+//! `?v=<content hash>` URLs, so caches pick up a new upload right away.
+
+use super::cache_bust::Versions;
+use super::import_map::{ImportMap, NODE_MODULES_PREFIX};
+use super::toggles::Toggles;
+use super::{a11y, asc, base_path, component, emscripten, gojs, loading, pyodide};
+use crate::error::{Result, WasmrunError};
+use crate::template::{TemplateManager, TemplateType};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// The page for the module at `wasm_path`, served next to it as `index.html`
+/// with `js_filename` as its glue
+pub fn page(
+    wasm_path: &str,
+    js_filename: Option<&str>,
+    project_path: Option<&str>,
+) -> Result<String> {
+    let wasm_filename = Path::new(wasm_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| WasmrunError::path(format!("Invalid path: {wasm_path}")))?;
+
+    let html = match component::page() {
+        Some(page) => page,
+        None => {
+            let template_type = if js_filename.is_some() {
+                TemplateType::App
+            } else {
+                TemplateType::Console
+            };
+            TemplateManager::new()?.generate_static_html(&template_type, &wasm_filename)?
+        }
+    };
+
+    let versions = Versions::for_output(wasm_path);
+    let mut import_map = ImportMap::for_project(wasm_path, project_path);
+    import_map.imports.extend(versions.import_entries());
+    let html = emscripten::inject(
+        &gojs::inject(&html, &wasm_filename, js_filename),
+        wasm_path,
+        &wasm_filename,
+        js_filename,
+    );
+    let html = pyodide::inject(
+        &asc::inject(&html, wasm_path, &wasm_filename, js_filename),
+        wasm_path,
+        &wasm_filename,
+        js_filename,
+    );
+    let page = a11y::inject(&import_map.inject(&versions.inject(&html)));
+    let page = loading::inject(&page, &versions, &Toggles::default());
+    Ok(base_path::rewrite_html(&page, &base_path::active()))
+}
+
+/// The packages under the project's `node_modules` the page imports, which
+/// the dev server serves from the project and a bundle has to carry along
+pub fn node_packages(wasm_path: &str, project_path: Option<&str>) -> BTreeSet<String> {
+    ImportMap::for_project(wasm_path, project_path)
+        .imports
+        .keys()
+        .filter(|specifier| !specifier.ends_with('/'))
+        .cloned()
+        .collect()
+}
+
+/// URL directory the packages of [`node_packages`] are loaded from
+pub fn node_modules_dir() -> &'static str {
+    NODE_MODULES_PREFIX.trim_matches('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_node_packages() {
+        let dir = tempdir().unwrap();
+        let project = dir.path();
+        fs::write(
+            project.join("package.json"),
+            r#"{"dependencies": {"left-pad": "1.0.0", "missing": "1.0.0"}}"#,
+        )
+        .unwrap();
+        let package = project.join("node_modules/left-pad");
+        fs::create_dir_all(&package).unwrap();
+        fs::write(package.join("package.json"), r#"{"main": "index.js"}"#).unwrap();
+        fs::write(package.join("index.js"), "export default 1;").unwrap();
+        let wasm = project.join("app.wasm");
+
+        let packages = node_packages(&wasm.to_string_lossy(), Some(&project.to_string_lossy()));
+        assert_eq!(packages.into_iter().collect::<Vec<_>>(), vec!["left-pad"]);
+        assert!(node_packages(&wasm.to_string_lossy(), None).is_empty());
+        assert_eq!(node_modules_dir(), "node_modules");
+    }
+}
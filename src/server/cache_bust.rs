@@ -0,0 +1,264 @@
+//! Content-hash cache busting for the dev page
+//!
+//! After a rebuild the browser may still use a cached copy of the old wasm or
+//! JS glue. The served page therefore requests the files next to the wasm
+//! module with a `?v=<content hash>` query: `src`/`href` attributes are
+//! rewritten, ES module imports go through import map entries, and a small
+//! script adds the parameter to `fetch` calls. The parameter is stripped again
+//! before routing.
+
+use regex::{Captures, Regex};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Query parameter carrying the content hash
+const VERSION_PARAM: &str = "v";
+
+/// Hex digits of the SHA-256 digest used as the version
+const HASH_LENGTH: usize = 12;
+
+/// Hashes by file, reused while the file's size and modification time are unchanged
+type HashCache = HashMap<PathBuf, (u64, SystemTime, String)>;
+static HASHES: OnceLock<Mutex<HashCache>> = OnceLock::new();
+
+/// Content hashes of the wasm and JS files served next to the module, by file name
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Versions {
+    files: BTreeMap<String, String>,
+    /// URL directory of the files relative to the page, empty when they are served next to it
+    dir: String,
+}
+
+impl Versions {
+    /// Hash the `.wasm`, `.js` and `.mjs` files in the directory of `wasm_path`
+    pub fn for_output(wasm_path: &str) -> Self {
+        let mut versions = Self::default();
+        let Some(dir) = Path::new(wasm_path).parent() else {
+            return versions;
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let Ok(entries) = fs::read_dir(dir) else {
+            return versions;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let versioned = path
+                .extension()
+                .is_some_and(|ext| ext == "wasm" || ext == "js" || ext == "mjs");
+            if !versioned || !path.is_file() {
+                continue;
+            }
+            if let (Some(name), Some(hash)) = (path.file_name(), content_hash(&path)) {
+                versions
+                    .files
+                    .insert(name.to_string_lossy().to_string(), hash);
+            }
+        }
+        versions
+    }
+
+    /// The files are served from `dir` (e.g. `pkg`) below the page rather than next to it
+    pub fn under(mut self, dir: &str) -> Self {
+        self.dir = dir.trim_matches('/').to_string();
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Content hashes of the wasm files alone
+    pub fn wasm_hashes(&self) -> BTreeMap<String, String> {
+        self.files
+            .iter()
+            .filter(|(name, _)| name.ends_with(".wasm"))
+            .map(|(name, hash)| (name.clone(), hash.clone()))
+            .collect()
+    }
+
+    /// Import map entries that load the versioned URL of each JS module
+    pub fn import_entries(&self) -> BTreeMap<String, String> {
+        self.files
+            .iter()
+            .filter(|(name, _)| !name.ends_with(".wasm"))
+            .map(|(name, hash)| {
+                let url = if self.dir.is_empty() {
+                    format!("./{name}")
+                } else {
+                    format!("./{}/{name}", self.dir)
+                };
+                let versioned = versioned(&url, hash);
+                (url, versioned)
+            })
+            .collect()
+    }
+
+    /// Version the `src`/`href` attributes that point at hashed files and add the fetch hook
+    pub fn inject(&self, html: &str) -> String {
+        if self.is_empty() {
+            return html.to_string();
+        }
+
+        static ATTRIBUTE_RE: OnceLock<Regex> = OnceLock::new();
+        let attribute_re = ATTRIBUTE_RE.get_or_init(|| {
+            Regex::new(r#"(\b(?:src|href)=["'])((?:\./|/)?([^"'?#/]+))(["'])"#).unwrap()
+        });
+        let html =
+            attribute_re.replace_all(html, |caps: &Captures| match self.files.get(&caps[3]) {
+                Some(hash) => format!("{}{}{}", &caps[1], versioned(&caps[2], hash), &caps[4]),
+                None => caps[0].to_string(),
+            });
+
+        let files = serde_json::to_string(&self.files).unwrap_or_else(|_| "{}".to_string());
+        let script = format!(
+            r#"<script>
+(function (versions) {{
+  const version = (input) => {{
+    if (typeof input !== "string" && !(input instanceof URL)) return input;
+    const url = new URL(input, document.baseURI);
+    const hash = versions[url.pathname.split("/").pop()];
+    if (url.origin !== location.origin || !hash || url.searchParams.has("{VERSION_PARAM}")) return input;
+    url.searchParams.set("{VERSION_PARAM}", hash);
+    return url.href;
+  }};
+  const fetch = window.fetch;
+  window.fetch = (input, init) => fetch(version(input), init);
+}})({files});
+</script>"#
+        );
+
+        match html
+            .find("<head")
+            .and_then(|start| html[start..].find('>').map(|end| start + end + 1))
+        {
+            Some(at) => format!("{}\n{script}{}", &html[..at], &html[at..]),
+            None => format!("{script}\n{html}"),
+        }
+    }
+}
+
+/// Remove the version parameter from a request URL before it is routed
+pub fn strip(url: String) -> String {
+    let Some((path, query)) = url.split_once('?') else {
+        return url;
+    };
+    let prefix = format!("{VERSION_PARAM}=");
+    let rest: Vec<&str> = query
+        .split('&')
+        .filter(|pair| !pair.starts_with(&prefix))
+        .collect();
+    if rest.is_empty() {
+        path.to_string()
+    } else {
+        format!("{path}?{}", rest.join("&"))
+    }
+}
+
+/// The file name of `path` with its version, e.g. `app.wasm?v=0123456789ab`
+pub(super) fn versioned_name(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    Some(versioned(&name, &content_hash(path)?))
+}
+
+fn versioned(url: &str, hash: &str) -> String {
+    format!("{url}?{VERSION_PARAM}={hash}")
+}
+
+/// The version of `bytes`: the start of their SHA-256 digest in hex
+pub(super) fn hash_bytes(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>()[..HASH_LENGTH]
+        .to_string()
+}
+
+pub(super) fn content_hash(path: &Path) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    let (size, modified) = (metadata.len(), metadata.modified().ok()?);
+    let cache = HASHES.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some((cached_size, cached_modified, hash)) = cache.lock().ok()?.get(path) {
+        if *cached_size == size && *cached_modified == modified {
+            return Some(hash.clone());
+        }
+    }
+
+    let bytes = fs::read(path).ok()?;
+    let hash = hash_bytes(&bytes);
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(path.to_path_buf(), (size, modified, hash.clone()));
+    }
+    if let (Some(name), true) = (
+        path.file_name(),
+        path.extension().is_some_and(|ext| ext == "wasm"),
+    ) {
+        super::delta::remember(&name.to_string_lossy(), &hash, bytes);
+    }
+    Some(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_versions_change_with_content() {
+        let dir = tempdir().unwrap();
+        let wasm = dir.path().join("app.wasm");
+        fs::write(&wasm, b"\0asm one").unwrap();
+        fs::write(dir.path().join("app.js"), "export default 1").unwrap();
+        fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let before = Versions::for_output(wasm.to_str().unwrap());
+        assert_eq!(before.files.len(), 2);
+        assert_eq!(before.files["app.wasm"].len(), HASH_LENGTH);
+        assert_eq!(
+            before.import_entries(),
+            BTreeMap::from([(
+                "./app.js".to_string(),
+                format!("./app.js?v={}", before.files["app.js"])
+            )])
+        );
+
+        assert_eq!(
+            before.clone().under("/pkg/").import_entries()["./pkg/app.js"],
+            format!("./pkg/app.js?v={}", before.files["app.js"])
+        );
+
+        fs::write(&wasm, b"\0asm two, rebuilt").unwrap();
+        let after = Versions::for_output(wasm.to_str().unwrap());
+        assert_ne!(before.files["app.wasm"], after.files["app.wasm"]);
+        assert_eq!(before.files["app.js"], after.files["app.js"]);
+    }
+
+    #[test]
+    fn test_inject_versions_attributes() {
+        let versions = Versions {
+            files: BTreeMap::from([("app.js".to_string(), "abc".to_string())]),
+            ..Versions::default()
+        };
+        let html = versions.inject(
+            r#"<html><head></head><body><script src="./app.js"></script><script src="other.js"></script><a href="/app.js">x</a></body></html>"#,
+        );
+        assert!(html.contains(r#"src="./app.js?v=abc""#));
+        assert!(html.contains(r#"src="other.js""#));
+        assert!(html.contains(r#"href="/app.js?v=abc""#));
+        assert!(html.find("window.fetch").unwrap() < html.find("<body>").unwrap());
+    }
+
+    #[test]
+    fn test_strip() {
+        assert_eq!(strip("/app.wasm?v=abc".to_string()), "/app.wasm");
+        assert_eq!(strip("/reload?since=5".to_string()), "/reload?since=5");
+        assert_eq!(strip("/x.js?a=1&v=abc".to_string()), "/x.js?a=1");
+        assert_eq!(strip("/".to_string()), "/");
+    }
+}
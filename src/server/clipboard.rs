@@ -0,0 +1,133 @@
+//! `--copy-url`: put the server's URL on the clipboard at startup
+//!
+//! The URL copied is the one to share: a public URL, host name or LAN address
+//! before localhost. It goes through the platform's clipboard tool (`pbcopy`,
+//! `clip.exe`, `wl-copy`, `xclip` or `xsel`), or, when none of them works,
+//! through an OSC 52 escape code, which most terminals accept even over SSH.
+
+use super::urls::ServerUrls;
+use crate::utils::SystemUtils;
+use crate::{info_println, warn_println};
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by `--copy-url`
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Copy the URL once the server is listening
+pub fn request() {
+    REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Copy the shareable URL of the server on `port`, if `--copy-url` was given
+pub fn copy_url(port: u16) {
+    if !REQUESTED.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    let Some(url) = ServerUrls::for_port(port)
+        .shareable()
+        .map(|url| url.url.clone())
+    else {
+        return;
+    };
+    match copy(&url) {
+        Ok(via) => info_println!("📋 Copied {url} to the clipboard ({via})"),
+        Err(e) => warn_println!("Could not copy {url} to the clipboard: {e}"),
+    }
+}
+
+/// Put `text` on the clipboard, returning what it went through
+fn copy(text: &str) -> Result<&'static str, String> {
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    for (tool, args) in tools(wayland) {
+        if SystemUtils::is_tool_available(tool) && pipe_to(tool, args, text).is_ok() {
+            return Ok(tool);
+        }
+    }
+    let mut stdout = std::io::stdout();
+    if !stdout.is_terminal() || crate::tui::is_active() {
+        return Err("no clipboard tool found (install xclip, xsel or wl-clipboard)".to_string());
+    }
+    write!(stdout, "{}", osc52(text))
+        .and_then(|_| stdout.flush())
+        .map_err(|e| e.to_string())?;
+    Ok("terminal")
+}
+
+/// Clipboard tools to try, in order
+fn tools(wayland: bool) -> Vec<(&'static str, &'static [&'static str])> {
+    if cfg!(target_os = "macos") {
+        return vec![("pbcopy", &[])];
+    }
+    if cfg!(target_os = "windows") {
+        return vec![("clip", &[])];
+    }
+    let mut tools: Vec<(&'static str, &'static [&'static str])> = vec![
+        ("xclip", &["-selection", "clipboard"]),
+        ("xsel", &["--clipboard", "--input"]),
+        // WSL reaches the Windows clipboard through clip.exe
+        ("clip.exe", &[]),
+    ];
+    if wayland {
+        tools.insert(0, ("wl-copy", &[]));
+    }
+    tools
+}
+
+fn pipe_to(tool: &str, args: &[&str], text: &str) -> std::io::Result<()> {
+    let mut child = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "{tool} exited with {status}"
+        )))
+    }
+}
+
+/// The escape code asking the terminal to set its clipboard to `text`
+fn osc52(text: &str) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in text.as_bytes().chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let group = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    format!("\x1b]52;c;{encoded}\x07")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52() {
+        assert_eq!(
+            osc52("http://localhost:8420"),
+            "\x1b]52;c;aHR0cDovL2xvY2FsaG9zdDo4NDIw\x07"
+        );
+        assert_eq!(osc52("a"), "\x1b]52;c;YQ==\x07");
+        assert_eq!(osc52("ab"), "\x1b]52;c;YWI=\x07");
+    }
+}
@@ -0,0 +1,351 @@
+//! Fanning one build out to viewers on other machines (`wasmrun join`)
+//!
+//! Every server describes what it serves at [`MANIFEST_ROUTE`]: the module
+//! and its JS glue, with their content hashes, and the generation of its live
+//! reload state. A viewer started with `wasmrun join <url>` downloads those
+//! files, serves them with its own runner page and long-polls the host's
+//! `/reload` route. When the host reloads its pages after a build, the viewer
+//! downloads the files whose hash changed and reloads its own pages the way
+//! the host would, so a device lab follows a single build machine.
+
+use super::live_reload;
+use super::utils::{content_type_header, respond};
+use crate::error::{Result, WasmrunError};
+use crate::{error_println, info_println, warn_println};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tiny_http::{Request, Response};
+
+/// Where a server describes its artifacts for viewers
+pub const MANIFEST_ROUTE: &str = "/__wasmrun/cluster";
+
+/// Addresses of the viewers that have fetched the manifest
+static VIEWERS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// A served file, by the path it is requested at
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub url: String,
+    pub hash: String,
+}
+
+/// What a host serves, as of one live reload generation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub generation: u64,
+    pub wasm: Artifact,
+    pub js: Option<Artifact>,
+}
+
+impl Manifest {
+    /// The manifest for the module at `wasm_path`, served as `wasm_filename`,
+    /// with the glue next to it served as `js_filename`
+    fn for_served(wasm_filename: &str, wasm_path: &str, js_filename: Option<&str>) -> Option<Self> {
+        let wasm_path = Path::new(wasm_path);
+        let artifact = |url: &str, path: &Path| {
+            Some(Artifact {
+                url: url.to_string(),
+                hash: super::cache_bust::content_hash(path)?,
+            })
+        };
+        Some(Self {
+            generation: live_reload::generation(),
+            wasm: artifact(wasm_filename, wasm_path)?,
+            js: js_filename.and_then(|js| artifact(js, &wasm_path.parent()?.join(js))),
+        })
+    }
+
+    /// The artifacts that differ from those in `previous`
+    fn changed_since<'a>(&'a self, previous: &Manifest) -> Vec<&'a Artifact> {
+        [
+            (Some(&self.wasm), Some(&previous.wasm)),
+            (self.js.as_ref(), previous.js.as_ref()),
+        ]
+        .into_iter()
+        .filter_map(|(now, before)| now.filter(|now| before != Some(*now)))
+        .collect()
+    }
+}
+
+/// Answer a viewer asking what this server serves
+pub fn serve_manifest(
+    request: Request,
+    wasm_filename: &str,
+    wasm_path: &str,
+    js_filename: Option<&str>,
+) {
+    let viewer = request
+        .remote_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_default();
+    if VIEWERS
+        .lock()
+        .is_ok_and(|mut viewers| viewers.insert(viewer.clone()))
+    {
+        info_println!("📡 Viewer joined from {viewer}");
+    }
+
+    let response = match Manifest::for_served(wasm_filename, wasm_path, js_filename) {
+        Some(manifest) => {
+            Response::from_string(serde_json::to_string(&manifest).unwrap_or_default())
+                .with_header(content_type_header("application/json"))
+        }
+        None => Response::from_string("Module not built yet")
+            .with_status_code(503)
+            .with_header(content_type_header("text/plain")),
+    };
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending cluster manifest: {e}");
+    }
+}
+
+/// A viewer's copy of what a host serves
+pub struct Mirror {
+    /// The host's URL, ending in `/`
+    host: String,
+    dir: PathBuf,
+    agent: ureq::Agent,
+    manifest: Manifest,
+}
+
+impl Mirror {
+    /// Download what the host at `url` serves into `dir`
+    pub fn connect(url: &str, dir: &Path) -> Result<Self> {
+        let host = host_url(url)?;
+        let proxy = crate::network::proxy_for(&host);
+        let agent = crate::network::agent_builder_via(&host, proxy.as_ref())?
+            .timeout_connect(Duration::from_secs(5))
+            // Longer than the host holds a reload poll open
+            .timeout_read(live_reload::POLL_TIMEOUT + Duration::from_secs(10))
+            .build();
+        // A build machine on the LAN behind a corporate proxy usually belongs in NO_PROXY
+        let via = proxy
+            .map(|proxy| format!(" through the proxy in {}", proxy.describe()))
+            .unwrap_or_default();
+        let joining = |e: String| {
+            WasmrunError::add_context(format!("Joining {host}{via}"), std::io::Error::other(e))
+        };
+        let mirror = Self {
+            manifest: fetch_manifest(&agent, &host).map_err(joining)?,
+            host: host.clone(),
+            dir: dir.to_path_buf(),
+            agent,
+        };
+        for artifact in std::iter::once(&mirror.manifest.wasm).chain(&mirror.manifest.js) {
+            mirror.download(artifact).map_err(joining)?;
+        }
+        Ok(mirror)
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// The local copy of the module, and the path it is served at
+    pub fn wasm(&self) -> (PathBuf, &str) {
+        (
+            self.dir.join(&self.manifest.wasm.url),
+            &self.manifest.wasm.url,
+        )
+    }
+
+    /// The local copy of the glue, if the host serves one
+    pub fn js(&self) -> Option<PathBuf> {
+        Some(self.local_path(self.manifest.js.as_ref()?))
+    }
+
+    /// Where the copy of `artifact` goes; the glue is served from the module's directory
+    fn local_path(&self, artifact: &Artifact) -> PathBuf {
+        let (wasm_path, wasm_url) = self.wasm();
+        match wasm_path.parent() {
+            Some(dir) if artifact.url != wasm_url => dir.join(&artifact.url),
+            _ => wasm_path,
+        }
+    }
+
+    /// Download `artifact`, replacing the old copy only once it is complete
+    fn download(&self, artifact: &Artifact) -> std::result::Result<(), String> {
+        let target = self.local_path(artifact);
+        let mut bytes = Vec::new();
+        self.agent
+            .get(&format!("{}{}", self.host, artifact.url))
+            .call()
+            .map_err(|e| format!("downloading {}: {e}", artifact.url))?
+            .into_reader()
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("downloading {}: {e}", artifact.url))?;
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let partial = target.with_extension("partial");
+        fs::write(&partial, bytes)
+            .and_then(|_| fs::rename(&partial, &target))
+            .map_err(|e| format!("writing {}: {e}", target.display()))
+    }
+
+    /// Catch up with `manifest`; true when a file changed
+    fn sync(&mut self, manifest: Manifest) -> std::result::Result<bool, String> {
+        if manifest.wasm.url != self.manifest.wasm.url {
+            return Err(format!(
+                "the host now serves {} instead of {}; run wasmrun join again",
+                manifest.wasm.url, self.manifest.wasm.url
+            ));
+        }
+        let previous = std::mem::replace(&mut self.manifest, manifest.clone());
+        let changed = manifest.changed_since(&previous);
+        for artifact in &changed {
+            if let Err(e) = self.download(artifact) {
+                // Try again on the next change instead of keeping a half-updated copy
+                self.manifest = previous;
+                return Err(e);
+            }
+        }
+        Ok(!changed.is_empty())
+    }
+
+    /// Follow the host's reloads on a thread of its own
+    pub fn follow(mut self) {
+        std::thread::spawn(move || {
+            let mut reachable = true;
+            loop {
+                let url = format!("{}reload?since={}", self.host, self.manifest.generation);
+                let reply = self
+                    .agent
+                    .get(&url)
+                    .call()
+                    .map_err(|e| e.to_string())
+                    .and_then(|response| response.into_string().map_err(|e| e.to_string()));
+                let reply = match reply {
+                    Ok(reply) => reply,
+                    Err(e) => {
+                        if reachable {
+                            warn_println!("Lost the build host {}: {e}", self.host);
+                            reachable = false;
+                        }
+                        std::thread::sleep(Duration::from_secs(2));
+                        continue;
+                    }
+                };
+                if !reachable {
+                    info_println!("📡 Build host {} is back", self.host);
+                    reachable = true;
+                }
+                if matches!(reply.as_str(), "no-reload" | "not-watching") {
+                    continue;
+                }
+
+                let result = fetch_manifest(&self.agent, &self.host)
+                    .and_then(|manifest| self.sync(manifest));
+                match result {
+                    Ok(true) => {
+                        info_println!("📡 New build from {}", self.host);
+                        live_reload::reload_after_build(&self.wasm().0.to_string_lossy());
+                    }
+                    // Stylesheets of the host's project don't reach the viewer's pages
+                    Ok(false) if reply.starts_with("styles ") => {}
+                    Ok(false) => live_reload::request_reload(),
+                    Err(e) => {
+                        warn_println!("Could not follow {}: {e}", self.host);
+                        std::thread::sleep(Duration::from_secs(2));
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// `url` with a scheme and a trailing `/`, so routes can be appended to it
+fn host_url(url: &str) -> Result<String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Err(WasmrunError::from(
+            "wasmrun join needs the URL of a running server",
+        ));
+    }
+    let url = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("http://{url}")
+    };
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(WasmrunError::from(format!(
+            "Cannot join {url}: only http:// and https:// servers can be joined"
+        )));
+    }
+    Ok(format!("{}/", url.trim_end_matches('/')))
+}
+
+fn fetch_manifest(agent: &ureq::Agent, host: &str) -> std::result::Result<Manifest, String> {
+    let url = format!("{host}{}", MANIFEST_ROUTE.trim_start_matches('/'));
+    agent
+        .get(&url)
+        .call()
+        .map_err(|e| format!("{url}: {e}"))?
+        .into_json()
+        .map_err(|e| format!("{url} did not answer with a wasmrun manifest: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact(url: &str, hash: &str) -> Artifact {
+        Artifact {
+            url: url.to_string(),
+            hash: hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_host_url() {
+        assert_eq!(
+            host_url("192.168.1.5:8420").unwrap(),
+            "http://192.168.1.5:8420/"
+        );
+        assert_eq!(
+            host_url("https://build.local/myapp/").unwrap(),
+            "https://build.local/myapp/"
+        );
+        assert!(host_url("ftp://build.local").is_err());
+        assert!(host_url(" ").is_err());
+    }
+
+    #[test]
+    fn test_changed_artifacts() {
+        let before = Manifest {
+            generation: 3,
+            wasm: artifact("app_bg.wasm", "aaaa"),
+            js: Some(artifact("app.js", "1111")),
+        };
+        let glue_only = Manifest {
+            generation: 4,
+            js: Some(artifact("app.js", "2222")),
+            ..before.clone()
+        };
+        assert_eq!(
+            glue_only.changed_since(&before),
+            vec![&artifact("app.js", "2222")]
+        );
+        assert!(before.changed_since(&before).is_empty());
+    }
+
+    #[test]
+    fn test_manifest_for_served_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let wasm = dir.path().join("app_bg.wasm");
+        fs::write(&wasm, b"\0asm\x01\0\0\0").unwrap();
+        fs::write(dir.path().join("app.js"), "export default 1;").unwrap();
+
+        let manifest =
+            Manifest::for_served("app_bg.wasm", wasm.to_str().unwrap(), Some("app.js")).unwrap();
+        assert_eq!(manifest.wasm.url, "app_bg.wasm");
+        assert_eq!(manifest.js.unwrap().url, "app.js");
+        assert!(Manifest::for_served("gone.wasm", "/nonexistent/gone.wasm", None).is_none());
+    }
+}
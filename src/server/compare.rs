@@ -0,0 +1,397 @@
+//! Side-by-side comparison of two wasm builds
+//!
+//! `wasmrun compare old.wasm new.wasm` serves a shell page with one frame per
+//! build. Each frame is the regular console UI, mounted under `/a/` or `/b/`.
+//! The shell can mirror user input from one frame into the other and record a
+//! sequence of inputs to replay against both builds.
+
+use std::path::Path;
+use std::sync::Arc;
+use tiny_http::{Request, Response};
+
+use super::api::{serve_asset, serve_file, serve_module_info, serve_version_info};
+use super::base_path::{self, Route};
+use super::utils::{content_type_header, respond};
+use super::InstanceGuard;
+use crate::error_println;
+use crate::self_profile::{self, Subsystem};
+use crate::template::{TemplateManager, TemplateType};
+
+/// One of the two builds being compared
+#[derive(Debug, Clone)]
+pub struct CompareSide {
+    /// URL prefix the side is mounted under (`a` or `b`)
+    pub mount: &'static str,
+    pub label: &'static str,
+    pub wasm_path: String,
+    pub wasm_filename: String,
+}
+
+impl CompareSide {
+    pub fn new(mount: &'static str, label: &'static str, wasm_path: &str) -> Self {
+        let wasm_filename = Path::new(wasm_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| wasm_path.to_string());
+        Self {
+            mount,
+            label,
+            wasm_path: wasm_path.to_string(),
+            wasm_filename,
+        }
+    }
+
+    fn size(&self) -> u64 {
+        std::fs::metadata(&self.wasm_path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0)
+    }
+}
+
+/// Serve two wasm files side by side until the process is stopped
+pub fn serve_comparison(
+    old: CompareSide,
+    new: CompareSide,
+    port: u16,
+    serve: bool,
+) -> Result<(), String> {
+    let server = Arc::new(super::tls::bind(super::urls::bind_address(port))?);
+    let _instance = InstanceGuard::register(port, &old.wasm_path, None);
+    super::shutdown::stop_on_request(&server);
+    super::clipboard::copy_url(port);
+
+    if serve {
+        crate::server::utils::open_browser_when_ready(port);
+    }
+
+    let template_manager = TemplateManager::default();
+    let sides = [old, new];
+
+    super::pool::serve(&server, super::pool::workers(), |request| {
+        handle_compare_request(request, &sides, &template_manager);
+    });
+
+    Ok(())
+}
+
+fn handle_compare_request(request: Request, sides: &[CompareSide; 2], templates: &TemplateManager) {
+    let _span = self_profile::span(Subsystem::Server);
+    let Some((request, _slot)) = super::limits::admit(request) else {
+        return;
+    };
+    let Some(request) = super::demo::admit(request) else {
+        return;
+    };
+    let url = match base_path::route(request.url(), &base_path::active()) {
+        Route::Path(url) => url.split('?').next().unwrap_or("/").to_string(),
+        Route::Redirect(location) => {
+            base_path::redirect(request, &location);
+            return;
+        }
+    };
+
+    if url == "/" {
+        send_html(request, compare_page(sides));
+        return;
+    }
+    if url == "/api/version" {
+        serve_version_info(request);
+        return;
+    }
+    if url == "/api/module-info" {
+        let side = side_from_referer(&request, sides);
+        serve_module_info(request, &side.wasm_path, None);
+        return;
+    }
+    if url.starts_with("/assets/") {
+        serve_asset(request, &url);
+        return;
+    }
+
+    for side in sides {
+        let mount = format!("/{}/", side.mount);
+        let Some(rest) = url.strip_prefix(&mount) else {
+            continue;
+        };
+
+        if rest.is_empty() {
+            match templates.generate_html(&TemplateType::Console, &side.wasm_filename) {
+                Ok(html) => send_html(request, html),
+                Err(e) => send_html(
+                    request,
+                    format!("<html><body><h1>Error</h1><p>Failed to generate HTML: {e}</p></body></html>"),
+                ),
+            }
+        } else if rest == side.wasm_filename {
+            serve_file(request, &side.wasm_path, "application/wasm");
+        } else if rest.starts_with("assets/") {
+            serve_asset(request, &format!("/{rest}"));
+        } else {
+            not_found(request);
+        }
+        return;
+    }
+
+    not_found(request);
+}
+
+/// The compared side a request came from, based on the frame that issued it
+fn side_from_referer<'a>(request: &Request, sides: &'a [CompareSide; 2]) -> &'a CompareSide {
+    let referer = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Referer"))
+        .map(|header| header.value.as_str().to_string())
+        .unwrap_or_default();
+
+    sides
+        .iter()
+        .find(|side| referer.contains(&format!("/{}/", side.mount)))
+        .unwrap_or(&sides[0])
+}
+
+fn send_html(request: Request, html: String) {
+    let html = base_path::rewrite_html(&html, &base_path::active());
+    let response = Response::from_string(html).with_header(content_type_header("text/html"));
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending HTML response: {e}");
+    }
+}
+
+fn not_found(request: Request) {
+    let response = Response::from_string("404 Not Found")
+        .with_status_code(404)
+        .with_header(content_type_header("text/plain"));
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending 404 response: {e}");
+    }
+}
+
+pub(super) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Shell page with both frames, layout controls and input mirroring/recording
+pub fn compare_page(sides: &[CompareSide; 2]) -> String {
+    let panes: String = sides
+        .iter()
+        .map(|side| {
+            format!(
+                r#"<section class="pane" data-side="{mount}">
+  <header><strong>{label}</strong> {name} <span class="meta">{size}</span> <span class="meta" id="load-{mount}"></span></header>
+  <iframe id="frame-{mount}" src="/{mount}/" title="{label}"></iframe>
+</section>"#,
+                mount = side.mount,
+                label = side.label,
+                name = escape_html(&side.wasm_path),
+                size = crate::utils::CommandExecutor::format_file_size(side.size()),
+            )
+        })
+        .collect();
+
+    COMPARE_PAGE.replace("<!-- @panes -->", &panes)
+}
+
+const COMPARE_PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Wasmrun - Compare</title>
+<style>
+  * { box-sizing: border-box; }
+  body { margin: 0; font-family: system-ui, sans-serif; background: #0f172a; color: #e2e8f0; height: 100vh; display: flex; flex-direction: column; }
+  nav { display: flex; gap: 0.5rem; align-items: center; padding: 0.5rem 1rem; background: #1e293b; }
+  nav button, nav label { font: inherit; color: inherit; background: #334155; border: 0; border-radius: 4px; padding: 0.3rem 0.7rem; cursor: pointer; }
+  nav button.active { background: #6366f1; }
+  nav .status { margin-left: auto; opacity: 0.8; }
+  main { flex: 1; display: flex; min-height: 0; }
+  .pane { flex: 1; display: flex; flex-direction: column; min-width: 0; border-left: 1px solid #334155; }
+  .pane header { padding: 0.3rem 0.8rem; font-size: 0.85rem; background: #1e293b; white-space: nowrap; overflow: hidden; text-overflow: ellipsis; }
+  .pane .meta { opacity: 0.7; margin-left: 0.5rem; }
+  .pane iframe { flex: 1; border: 0; background: #fff; }
+  main[data-layout="a"] .pane[data-side="b"], main[data-layout="b"] .pane[data-side="a"] { display: none; }
+</style>
+</head>
+<body>
+<nav>
+  <strong>⚖️ Wasmrun Compare</strong>
+  <button data-layout="split" class="active">Split</button>
+  <button data-layout="a">Old</button>
+  <button data-layout="b">New</button>
+  <button id="toggle" title="Flip between the two builds (T)">Toggle</button>
+  <label><input type="checkbox" id="mirror" checked> Mirror input</label>
+  <button id="record">⏺ Record</button>
+  <button id="replay" disabled>▶ Replay</button>
+  <span class="status" id="status"></span>
+</nav>
+<main id="panes" data-layout="split">
+<!-- @panes -->
+</main>
+<script>
+(() => {
+  const panes = document.getElementById('panes');
+  const status = document.getElementById('status');
+  const mirror = document.getElementById('mirror');
+  const recordButton = document.getElementById('record');
+  const replayButton = document.getElementById('replay');
+  const frames = { a: document.getElementById('frame-a'), b: document.getElementById('frame-b') };
+  const EVENTS = ['click', 'input', 'change', 'keydown', 'keyup', 'submit'];
+  let recording = null;
+  let recorded = [];
+  let replaying = false;
+
+  function setLayout(layout) {
+    panes.dataset.layout = layout;
+    document.querySelectorAll('nav button[data-layout]').forEach((button) => {
+      button.classList.toggle('active', button.dataset.layout === layout);
+    });
+  }
+  document.querySelectorAll('nav button[data-layout]').forEach((button) => {
+    button.addEventListener('click', () => setLayout(button.dataset.layout));
+  });
+  const toggle = () => setLayout(panes.dataset.layout === 'a' ? 'b' : 'a');
+  document.getElementById('toggle').addEventListener('click', toggle);
+  document.addEventListener('keydown', (event) => {
+    if (event.key === 't' && event.target === document.body) toggle();
+  });
+
+  // Identify an element by its child-index path from <body> so it can be found in the other frame
+  function pathOf(element) {
+    const path = [];
+    while (element && element.parentElement && element.tagName !== 'BODY') {
+      path.unshift(Array.prototype.indexOf.call(element.parentElement.children, element));
+      element = element.parentElement;
+    }
+    return path;
+  }
+  function find(doc, path) {
+    let element = doc.body;
+    for (const index of path) {
+      if (!element) return null;
+      element = element.children[index];
+    }
+    return element;
+  }
+
+  function capture(event) {
+    const target = event.target;
+    const input = { type: event.type, path: pathOf(target) };
+    if ('value' in target && typeof target.value === 'string') input.value = target.value;
+    if ('checked' in target) input.checked = target.checked;
+    if (event.key !== undefined) input.key = event.key;
+    return input;
+  }
+
+  function apply(side, input) {
+    const doc = frames[side].contentDocument;
+    const target = doc && find(doc, input.path);
+    if (!target) return;
+    if (input.value !== undefined && 'value' in target) {
+      // Use the native setter so framework-controlled inputs notice the change
+      const setter = Object.getOwnPropertyDescriptor(Object.getPrototypeOf(target), 'value');
+      if (setter && setter.set) setter.set.call(target, input.value); else target.value = input.value;
+    }
+    if (input.checked !== undefined && 'checked' in target) target.checked = input.checked;
+    if (input.type === 'click') {
+      target.click();
+    } else if (input.type === 'keydown' || input.type === 'keyup') {
+      target.dispatchEvent(new KeyboardEvent(input.type, { key: input.key, bubbles: true }));
+    } else {
+      target.dispatchEvent(new Event(input.type, { bubbles: true }));
+    }
+  }
+
+  function attach(side) {
+    const frame = frames[side];
+    const other = side === 'a' ? 'b' : 'a';
+    const started = performance.now();
+    frame.addEventListener('load', () => {
+      const loaded = document.getElementById('load-' + side);
+      if (loaded) loaded.textContent = 'loaded in ' + Math.round(performance.now() - started) + ' ms';
+      EVENTS.forEach((type) => {
+        frame.contentDocument.addEventListener(type, (event) => {
+          if (!event.isTrusted || replaying) return;
+          const input = capture(event);
+          if (recording) recording.push({ at: performance.now() - recording.start, input });
+          if (mirror.checked) apply(other, input);
+        }, true);
+      });
+    });
+  }
+  attach('a');
+  attach('b');
+
+  recordButton.addEventListener('click', () => {
+    if (recording) {
+      recorded = recording;
+      recording = null;
+      recordButton.textContent = '⏺ Record';
+      replayButton.disabled = recorded.length === 0;
+      status.textContent = 'Recorded ' + recorded.length + ' input(s)';
+    } else {
+      recording = [];
+      recording.start = performance.now();
+      recordButton.textContent = '⏹ Stop';
+      status.textContent = 'Recording…';
+    }
+  });
+
+  replayButton.addEventListener('click', () => {
+    let pending = 2;
+    status.textContent = 'Reloading both builds…';
+    Object.entries(frames).forEach(([, frame]) => {
+      frame.addEventListener('load', function ready() {
+        frame.removeEventListener('load', ready);
+        if (--pending === 0) replay();
+      });
+      frame.contentWindow.location.reload();
+    });
+  });
+
+  function replay() {
+    replaying = true;
+    status.textContent = 'Replaying ' + recorded.length + ' input(s)…';
+    recorded.forEach(({ at, input }, index) => {
+      setTimeout(() => {
+        apply('a', input);
+        apply('b', input);
+        if (index === recorded.length - 1) {
+          replaying = false;
+          status.textContent = 'Replay finished';
+        }
+      }, at);
+    });
+  }
+})();
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_side_mounts_by_filename() {
+        let side = CompareSide::new("a", "Old", "/tmp/builds/app.wasm");
+        assert_eq!(side.wasm_filename, "app.wasm");
+        assert_eq!(side.mount, "a");
+    }
+
+    #[test]
+    fn test_compare_page_contains_both_frames() {
+        let sides = [
+            CompareSide::new("a", "Old", "old/app.wasm"),
+            CompareSide::new("b", "New", "new/<app>.wasm"),
+        ];
+        let html = compare_page(&sides);
+        assert!(html.contains(r#"<iframe id="frame-a" src="/a/""#));
+        assert!(html.contains(r#"<iframe id="frame-b" src="/b/""#));
+        assert!(html.contains("new/&lt;app&gt;.wasm"));
+        assert!(!html.contains("<!-- @panes -->"));
+    }
+}
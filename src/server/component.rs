@@ -0,0 +1,187 @@
+//! Serving component-model components
+//!
+//! Browsers only instantiate core modules. `jco transpile` turns a component
+//! into an ES module plus the core modules it is made of; when that output
+//! sits next to the component, in the same directory or one below it, the page
+//! at `/` imports it and lists the component's exports. Without it the server
+//! refuses to start and names the jco command to run.
+
+use super::compare::escape_html;
+use crate::error::{Result, WasmError, WasmrunError};
+use crate::utils::{ArtifactKind, ModuleInspection};
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+
+/// The transpiled ES module, relative to the component's directory
+static TRANSPILED: RwLock<Option<String>> = RwLock::new(None);
+
+/// When `wasm_path` is a component, serve its jco transpilation in its place,
+/// or fail when there is none
+pub fn prepare(wasm_path: &str) -> Result<()> {
+    let is_component = ModuleInspection::from_file(wasm_path)
+        .is_ok_and(|inspection| inspection.artifact == ArtifactKind::Component);
+    if !is_component {
+        return Ok(());
+    }
+    let transpiled = find_transpiled(wasm_path).ok_or_else(|| {
+        WasmrunError::Wasm(WasmError::ComponentNotTranspiled {
+            path: wasm_path.to_string(),
+        })
+    })?;
+    println!("🧩 Serving the component through its jco transpilation: {transpiled}");
+    enable(&transpiled);
+    Ok(())
+}
+
+/// The ES module `jco transpile` produced for the component at `wasm_path`,
+/// relative to the component's directory
+fn find_transpiled(wasm_path: &str) -> Option<String> {
+    let path = Path::new(wasm_path);
+    let dir = path.parent()?;
+    let stem = path.file_stem()?.to_string_lossy();
+    let script = format!("{stem}.js");
+
+    let mut candidates = vec![script.clone()];
+    if let Ok(entries) = fs::read_dir(dir) {
+        let mut subdirs: Vec<String> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name != "node_modules" && !name.starts_with('.'))
+            .collect();
+        subdirs.sort();
+        candidates.extend(
+            subdirs
+                .into_iter()
+                .map(|subdir| format!("{subdir}/{script}")),
+        );
+    }
+
+    candidates
+        .into_iter()
+        .find(|candidate| is_jco_output(&dir.join(candidate), &stem))
+}
+
+/// jco writes the core modules as `<name>.core.wasm` next to the script, unless
+/// they are small enough to be inlined; the script always exports `$init`
+fn is_jco_output(script: &Path, stem: &str) -> bool {
+    let Ok(source) = fs::read_to_string(script) else {
+        return false;
+    };
+    script.with_file_name(format!("{stem}.core.wasm")).is_file() || source.contains("$init")
+}
+
+/// Serve the page for a transpiled component instead of the runner templates
+fn enable(transpiled: &str) {
+    if let Ok(mut active) = TRANSPILED.write() {
+        *active = Some(transpiled.to_string());
+    }
+}
+
+/// The transpiled ES module being served, relative to the component's directory
+pub fn transpiled() -> Option<String> {
+    TRANSPILED.read().ok()?.clone()
+}
+
+/// The page importing the transpiled component, when one is being served
+pub fn page() -> Option<String> {
+    Some(component_page(&transpiled()?))
+}
+
+fn component_page(transpiled: &str) -> String {
+    let title = escape_html(
+        Path::new(transpiled)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .as_ref(),
+    );
+    let url = serde_json::to_string(&format!("./{transpiled}")).unwrap_or_default();
+    format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+  body {{ margin: 0; font: 14px system-ui, sans-serif; color: #e2e8f0; background: #0f172a; }}
+  main {{ max-width: 720px; margin: 0 auto; padding: 24px; }}
+  table {{ border-collapse: collapse; width: 100%; }}
+  th, td {{ text-align: left; padding: 4px 8px; border-bottom: 1px solid #334155; }}
+  code {{ color: #fbbf24; }}
+</style>
+</head>
+<body>
+<main>
+  <h1>{title}</h1>
+  <p id="status" role="status">Loading component…</p>
+  <table id="exports" hidden>
+    <thead><tr><th>Export</th><th>Kind</th></tr></thead>
+    <tbody></tbody>
+  </table>
+</main>
+<script type="module">
+const status = document.getElementById("status");
+try {{
+  const component = await import({url});
+  if (component.$init) await component.$init;
+  window.component = component;
+  const rows = document.querySelector("#exports tbody");
+  for (const [name, value] of Object.entries(component)) {{
+    if (name === "$init") continue;
+    const row = rows.insertRow();
+    row.insertCell().textContent = name;
+    row.insertCell().textContent = typeof value === "function" ? "function" : "interface";
+  }}
+  document.getElementById("exports").hidden = false;
+  status.innerHTML = "Component loaded. Its exports are available as <code>window.component</code> in the console.";
+}} catch (error) {{
+  console.error(error);
+  status.textContent = `Failed to load the transpiled component: ${{error.message}}`;
+}}
+</script>
+</body>
+</html>
+"##
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_transpiled() {
+        let dir = tempdir().unwrap();
+        let wasm = dir.path().join("app.wasm");
+        std::fs::write(&wasm, b"\0asm\x0d\0\x01\0").unwrap();
+        let wasm = wasm.to_string_lossy().to_string();
+        assert_eq!(find_transpiled(&wasm), None);
+
+        // Unrelated scripts with the same name are not jco output
+        std::fs::write(dir.path().join("app.js"), "console.log(1)").unwrap();
+        assert_eq!(find_transpiled(&wasm), None);
+
+        std::fs::create_dir(dir.path().join("web")).unwrap();
+        std::fs::write(dir.path().join("web/app.js"), "export { x }").unwrap();
+        std::fs::write(dir.path().join("web/app.core.wasm"), b"\0asm").unwrap();
+        assert_eq!(find_transpiled(&wasm).as_deref(), Some("web/app.js"));
+
+        std::fs::write(
+            dir.path().join("app.js"),
+            "export const $init = (async () => {})();",
+        )
+        .unwrap();
+        assert_eq!(find_transpiled(&wasm).as_deref(), Some("app.js"));
+    }
+
+    #[test]
+    fn test_component_page_imports_the_transpilation() {
+        let page = component_page("web/app.js");
+        assert!(page.contains(r#"await import("./web/app.js")"#));
+        assert!(page.contains("<title>app</title>"));
+    }
+}
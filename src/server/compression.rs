@@ -0,0 +1,478 @@
+//! Response compression
+//!
+//! Wasm and JS compress three to five times, so text, JSON and wasm responses
+//! are encoded with brotli or gzip, whichever the client's `Accept-Encoding`
+//! prefers. A build that already ships `app.wasm.br` or `app.wasm.gz` next
+//! to the module has that file served as is, which avoids compressing on every
+//! request and usually compresses better.
+//!
+//! Compressed bodies of files are kept by their `ETag`, within the memory
+//! `server.memory_cache_mb` allows, so a reload does not compress the module
+//! again. Responses larger than [`MAX_COMPRESS_BYTES`] are streamed as they are
+//! rather than read into memory to be compressed.
+
+use crate::{error_println, warn_println};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tiny_http::{Header, Request, Response, ResponseBox};
+
+/// Responses smaller than this are not worth compressing
+const MIN_COMPRESS_BYTES: usize = 1024;
+
+/// Responses larger than this are streamed uncompressed; ship an `.br` or
+/// `.gz` sibling to have them served compressed
+pub const MAX_COMPRESS_BYTES: usize = 16 * 1024 * 1024;
+
+/// Brotli quality for on-the-fly compression; the maximum of 11 is far too slow per request
+const BROTLI_QUALITY: u32 = 5;
+
+/// Brotli window size, as a power of two
+const BROTLI_WINDOW: u32 = 22;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    /// `Content-Encoding` token
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    /// File extension of a pre-compressed sibling
+    fn extension(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gz",
+        }
+    }
+
+    fn encode(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoding::Brotli => {
+                let mut encoder =
+                    brotli::CompressorWriter::new(Vec::new(), 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+                encoder.write_all(data)?;
+                encoder.flush()?;
+                Ok(encoder.into_inner())
+            }
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// The encoding to use for `request`, from its `Accept-Encoding` header
+pub fn negotiate(request: &Request) -> Option<Encoding> {
+    let accepted = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Accept-Encoding"))?;
+    preferred(accepted.value.as_str())
+}
+
+/// The encoding a client prefers; brotli wins ties, and `q=0` refuses an encoding
+fn preferred(accept_encoding: &str) -> Option<Encoding> {
+    let (brotli, gzip) = qualities(accept_encoding);
+    if brotli > 0.0 && brotli >= gzip {
+        Some(Encoding::Brotli)
+    } else if gzip > 0.0 {
+        Some(Encoding::Gzip)
+    } else {
+        None
+    }
+}
+
+/// Whether `accept_encoding` allows `encoding` at all
+fn accepts(accept_encoding: &str, encoding: Encoding) -> bool {
+    let (brotli, gzip) = qualities(accept_encoding);
+    match encoding {
+        Encoding::Brotli => brotli > 0.0,
+        Encoding::Gzip => gzip > 0.0,
+    }
+}
+
+/// Quality values given to brotli and gzip, directly or through `*`
+fn qualities(accept_encoding: &str) -> (f32, f32) {
+    let mut brotli = None;
+    let mut gzip = None;
+    let mut any = None;
+    for entry in accept_encoding.split(',') {
+        let mut parts = entry.split(';');
+        let name = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+        let quality = parts
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())
+            .unwrap_or(0.0);
+        match name.as_str() {
+            "br" => brotli = Some(quality),
+            "gzip" | "x-gzip" => gzip = Some(quality),
+            "*" => any = Some(quality),
+            _ => {}
+        }
+    }
+    (brotli.or(any).unwrap_or(0.0), gzip.or(any).unwrap_or(0.0))
+}
+
+pub fn is_compressible(content_type: &str) -> bool {
+    content_type.starts_with("text/")
+        || [
+            "application/javascript",
+            "application/json",
+            "application/manifest+json",
+            "application/xml",
+            "application/wasm",
+            "image/svg+xml",
+        ]
+        .iter()
+        .any(|kind| content_type.starts_with(kind))
+}
+
+/// A pre-compressed sibling of `path` the client accepts, e.g. `app.wasm.br`.
+/// Siblings older than the file are left over from an earlier build and are ignored.
+pub fn precompressed(request: &Request, path: &str) -> Option<(PathBuf, Encoding)> {
+    let accepted = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Accept-Encoding"))?;
+    let modified = Path::new(path).metadata().and_then(|meta| meta.modified());
+
+    [Encoding::Brotli, Encoding::Gzip]
+        .into_iter()
+        .filter(|encoding| accepts(accepted.value.as_str(), *encoding))
+        .map(|encoding| {
+            (
+                PathBuf::from(format!("{path}.{}", encoding.extension())),
+                encoding,
+            )
+        })
+        .find(|(sibling, _)| {
+            let sibling_modified = sibling.metadata().and_then(|meta| meta.modified());
+            match (&modified, sibling_modified) {
+                (Ok(modified), Ok(sibling_modified)) => sibling_modified >= *modified,
+                _ => false,
+            }
+        })
+}
+
+fn header_value<'a>(headers: &'a [Header], name: &'static str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+/// What a compressed body was made from: the file's tag, content type and
+/// length, which change whenever its contents do
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    etag: String,
+    content_type: String,
+    length: usize,
+    encoding: Encoding,
+}
+
+static COMPRESSED: Mutex<Option<Compressed>> = Mutex::new(None);
+
+/// Compressed bodies, the least recently used dropped first
+#[derive(Debug, Default)]
+struct Compressed {
+    entries: HashMap<Key, (Arc<[u8]>, u64)>,
+    uses: u64,
+}
+
+impl Compressed {
+    fn get(&mut self, key: &Key) -> Option<Arc<[u8]>> {
+        self.uses += 1;
+        let (bytes, last_used) = self.entries.get_mut(key)?;
+        *last_used = self.uses;
+        Some(Arc::clone(bytes))
+    }
+
+    fn insert(&mut self, key: Key, bytes: Arc<[u8]>, capacity: u64) {
+        if bytes.len() as u64 > capacity {
+            return;
+        }
+        self.entries.insert(key, (bytes, self.uses));
+        while self.memory() > capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => self.entries.remove(&oldest),
+                None => break,
+            };
+        }
+    }
+
+    fn memory(&self) -> u64 {
+        self.entries
+            .values()
+            .map(|(bytes, _)| bytes.len() as u64)
+            .sum()
+    }
+}
+
+/// Forget every compressed body, e.g. after the watcher saw changes
+pub fn invalidate() {
+    if let Ok(mut compressed) = COMPRESSED.lock() {
+        *compressed = None;
+    }
+}
+
+/// Compress `response` when it is worth it and the client accepts an encoding
+pub fn apply<R>(request: &Request, response: Response<R>) -> ResponseBox
+where
+    R: Read + Send + 'static,
+{
+    let content_type = header_value(response.headers(), "Content-Type").unwrap_or_default();
+    let eligible = response.status_code().0 == 200
+        && is_compressible(content_type)
+        && header_value(response.headers(), "Content-Encoding").is_none()
+        && response
+            .data_length()
+            .is_some_and(|length| (MIN_COMPRESS_BYTES..=MAX_COMPRESS_BYTES).contains(&length));
+    let Some(encoding) = eligible.then(|| negotiate(request)).flatten() else {
+        return response.boxed();
+    };
+    // Only a strong tag names the exact bytes of the body
+    let key = header_value(response.headers(), "ETag")
+        .filter(|etag| !etag.starts_with("W/"))
+        .map(|etag| Key {
+            etag: etag.to_string(),
+            content_type: content_type.to_string(),
+            length: response.data_length().unwrap_or_default(),
+            encoding,
+        });
+
+    let status = response.status_code();
+    // Byte ranges of the compressed body can't be served, so stop advertising them,
+    // and the compressed body is only equivalent to the file, so its tag becomes weak
+    let mut headers: Vec<Header> = response
+        .headers()
+        .iter()
+        .filter(|header| !header.field.equiv("Accept-Ranges"))
+        .map(|header| {
+            if header.field.equiv("ETag") && !header.value.as_str().starts_with("W/") {
+                Header::from_bytes("ETag", format!("W/{}", header.value))
+                    .unwrap_or_else(|_| header.clone())
+            } else {
+                header.clone()
+            }
+        })
+        .collect();
+    let encoded = |headers: &mut Vec<Header>| {
+        headers.extend(
+            [
+                ("Content-Encoding", encoding.as_str()),
+                ("Vary", "Accept-Encoding"),
+            ]
+            .into_iter()
+            .filter_map(|(name, value)| Header::from_bytes(name, value).ok()),
+        );
+    };
+
+    let cached = key.as_ref().and_then(|key| {
+        COMPRESSED
+            .lock()
+            .ok()?
+            .get_or_insert_with(Compressed::default)
+            .get(key)
+    });
+    let body: Arc<[u8]> = match cached {
+        Some(compressed) => {
+            encoded(&mut headers);
+            compressed
+        }
+        None => {
+            let mut body = Vec::new();
+            if let Err(e) = response.into_reader().read_to_end(&mut body) {
+                error_println!("Error reading response for compression: {e}");
+                return Response::empty(500).boxed();
+            }
+            match encoding.encode(&body) {
+                Ok(compressed) => {
+                    encoded(&mut headers);
+                    let compressed = Arc::from(compressed);
+                    if let (Some(key), Ok(mut cache)) = (key, COMPRESSED.lock()) {
+                        cache.get_or_insert_with(Compressed::default).insert(
+                            key,
+                            Arc::clone(&compressed),
+                            super::file_cache::capacity(),
+                        );
+                    }
+                    compressed
+                }
+                Err(e) => {
+                    warn_println!("Serving uncompressed response: {e}");
+                    Arc::from(body)
+                }
+            }
+        }
+    };
+    let length = body.len();
+    Response::new(status, headers, Cursor::new(body), Some(length), None)
+        .with_chunked_threshold(usize::MAX)
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_preferred_encoding() {
+        assert_eq!(preferred("gzip, deflate, br"), Some(Encoding::Brotli));
+        assert_eq!(preferred("gzip"), Some(Encoding::Gzip));
+        assert_eq!(preferred("br;q=0.5, gzip;q=0.8"), Some(Encoding::Gzip));
+        assert_eq!(preferred("br;q=0, *"), Some(Encoding::Gzip));
+        assert_eq!(preferred("*;q=0"), None);
+        assert_eq!(preferred("identity"), None);
+        assert!(accepts("br, gzip", Encoding::Gzip));
+        assert!(!accepts("br", Encoding::Gzip));
+
+        assert!(is_compressible("application/wasm"));
+        assert!(is_compressible("text/css"));
+        assert!(!is_compressible("image/png"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data = "(module)".repeat(500);
+
+        let gzipped = Encoding::Gzip.encode(data.as_bytes()).unwrap();
+        assert!(gzipped.len() < data.len());
+        let mut decoded = String::new();
+        GzDecoder::new(gzipped.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, data);
+
+        let brotli = Encoding::Brotli.encode(data.as_bytes()).unwrap();
+        assert!(brotli.len() < data.len());
+        let mut decoded = String::new();
+        brotli::Decompressor::new(brotli.as_slice(), 4096)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_precompressed_sibling() {
+        let dir = tempdir().unwrap();
+        let wasm = dir.path().join("app.wasm");
+        std::fs::write(&wasm, b"\0asm").unwrap();
+        std::fs::write(dir.path().join("app.wasm.gz"), b"gz").unwrap();
+        let path = wasm.to_str().unwrap();
+        let request = |accept: &str| -> Request {
+            tiny_http::TestRequest::new()
+                .with_header(Header::from_bytes("Accept-Encoding", accept).unwrap())
+                .into()
+        };
+
+        assert_eq!(
+            precompressed(&request("br, gzip"), path),
+            Some((dir.path().join("app.wasm.gz"), Encoding::Gzip))
+        );
+        assert_eq!(precompressed(&request("br"), path), None);
+
+        std::fs::write(dir.path().join("app.wasm.br"), b"br").unwrap();
+        assert_eq!(
+            precompressed(&request("gzip, br"), path).map(|(_, encoding)| encoding),
+            Some(Encoding::Brotli)
+        );
+    }
+
+    /// A body that fails when read, to tell a cached response from a fresh one
+    struct Unreadable;
+
+    impl Read for Unreadable {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("read again"))
+        }
+    }
+
+    fn headers(etag: &str) -> Vec<Header> {
+        vec![
+            Header::from_bytes("Content-Type", "application/wasm").unwrap(),
+            Header::from_bytes("ETag", etag).unwrap(),
+        ]
+    }
+
+    fn decoded(response: ResponseBox) -> String {
+        let mut decoded = String::new();
+        brotli::Decompressor::new(response.into_reader(), 4096)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        decoded
+    }
+
+    #[test]
+    fn test_compressed_bodies_are_kept_by_etag() {
+        let request = || -> Request {
+            tiny_http::TestRequest::new()
+                .with_header(Header::from_bytes("Accept-Encoding", "br").unwrap())
+                .into()
+        };
+        let data = "(module)".repeat(500);
+        let status = tiny_http::StatusCode(200);
+        let etag = "\"compression-test-1\"";
+
+        let fresh = Response::new(
+            status,
+            headers(etag),
+            Cursor::new(data.clone()),
+            Some(data.len()),
+            None,
+        );
+        assert_eq!(decoded(apply(&request(), fresh)), data);
+
+        // The same file again is not read nor compressed a second time
+        let again = Response::new(status, headers(etag), Unreadable, Some(data.len()), None);
+        assert_eq!(decoded(apply(&request(), again)), data);
+
+        // A file that changed has another tag
+        let changed = Response::new(
+            status,
+            headers("\"compression-test-2\""),
+            Unreadable,
+            Some(data.len()),
+            None,
+        );
+        assert_eq!(apply(&request(), changed).status_code().0, 500);
+    }
+
+    #[test]
+    fn test_large_responses_stream_uncompressed() {
+        let request: Request = tiny_http::TestRequest::new()
+            .with_header(Header::from_bytes("Accept-Encoding", "br").unwrap())
+            .into();
+        let large = Response::new(
+            tiny_http::StatusCode(200),
+            headers("\"compression-test-large\""),
+            Unreadable,
+            Some(MAX_COMPRESS_BYTES + 1),
+            None,
+        );
+        let response = apply(&request, large);
+        assert_eq!(response.status_code().0, 200);
+        assert!(header_value(response.headers(), "Content-Encoding").is_none());
+        assert_eq!(response.data_length(), Some(MAX_COMPRESS_BYTES + 1));
+    }
+}
@@ -0,0 +1,152 @@
+//! Conditional requests for served files
+//!
+//! Files are sent with an `ETag` built from their modification time and size,
+//! and a `Last-Modified` date. A browser revalidating with `If-None-Match` or
+//! `If-Modified-Since` gets `304 Not Modified` while the file is unchanged, so
+//! reloads don't transfer large modules again. A rebuild changes the mtime and
+//! with it the tag. `--no-cache` turns this off and sends `no-store` instead.
+
+use chrono::{DateTime, Utc};
+use std::fs::Metadata;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tiny_http::Request;
+
+/// Set by `--no-cache`
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Stop sending validators and ask browsers not to cache anything
+pub fn disable() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    !DISABLED.load(Ordering::Relaxed)
+}
+
+/// What identifies one version of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validators {
+    pub etag: String,
+    modified: DateTime<Utc>,
+}
+
+impl Validators {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+        Self::new(modified, metadata.len())
+    }
+
+    fn new(modified: SystemTime, length: u64) -> Self {
+        // Nanoseconds, so two builds within the same second still get different tags
+        let nanos = modified
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_nanos());
+        Self {
+            etag: format!("\"{nanos:x}-{length:x}\""),
+            modified: modified.into(),
+        }
+    }
+
+    /// `Last-Modified` value, an HTTP date
+    pub fn last_modified(&self) -> String {
+        self.modified
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string()
+    }
+
+    /// Whether the client's cached copy is this version
+    pub fn not_modified(&self, request: &Request) -> bool {
+        // If-None-Match takes precedence; If-Modified-Since is only a fallback
+        if let Some(tags) = header(request, "If-None-Match") {
+            return tags.trim() == "*"
+                || tags
+                    .split(',')
+                    .any(|tag| weak_match(tag.trim(), &self.etag));
+        }
+        header(request, "If-Modified-Since")
+            .and_then(|since| DateTime::parse_from_rfc2822(since.trim()).ok())
+            .is_some_and(|since| self.modified.timestamp() <= since.timestamp())
+    }
+
+    /// Whether an `If-Range` condition holds, so the requested range may be sent
+    pub fn if_range_matches(&self, condition: &str) -> bool {
+        let condition = condition.trim();
+        if condition.starts_with('"') {
+            // Ranges need a strong match
+            return condition == self.etag;
+        }
+        DateTime::parse_from_rfc2822(condition)
+            .is_ok_and(|date| date.timestamp() == self.modified.timestamp())
+    }
+}
+
+/// Weak comparison: tags match when equal after dropping any `W/` prefix
+fn weak_match(tag: &str, etag: &str) -> bool {
+    tag.trim_start_matches("W/") == etag.trim_start_matches("W/")
+}
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn validators() -> Validators {
+        Validators::new(UNIX_EPOCH + Duration::new(784_111_777, 5), 1024)
+    }
+
+    #[test]
+    fn test_validators_format() {
+        let validators = validators();
+        assert_eq!(validators.etag, "\"ae1b981bc490a05-400\"");
+        assert_eq!(validators.last_modified(), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_ne!(
+            Validators::new(UNIX_EPOCH + Duration::new(784_111_777, 6), 1024).etag,
+            validators.etag
+        );
+    }
+
+    #[test]
+    fn test_not_modified() {
+        let validators = validators();
+        let request = |name: &str, value: &str| -> Request {
+            tiny_http::TestRequest::new()
+                .with_header(tiny_http::Header::from_bytes(name, value).unwrap())
+                .into()
+        };
+        assert!(validators.not_modified(&request(
+            "If-None-Match",
+            "\"x\", W/\"ae1b981bc490a05-400\""
+        )));
+        assert!(validators.not_modified(&request("If-None-Match", "*")));
+        assert!(!validators.not_modified(&request("If-None-Match", "\"x\"")));
+        assert!(validators.not_modified(&request(
+            "If-Modified-Since",
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        )));
+        assert!(!validators.not_modified(&request(
+            "If-Modified-Since",
+            "Sun, 06 Nov 1994 08:49:36 GMT"
+        )));
+        assert!(!validators.not_modified(&request("Accept", "*/*")));
+    }
+
+    #[test]
+    fn test_if_range() {
+        let validators = validators();
+        assert!(validators.if_range_matches("\"ae1b981bc490a05-400\""));
+        assert!(!validators.if_range_matches("W/\"ae1b981bc490a05-400\""));
+        assert!(!validators.if_range_matches("\"other\""));
+        assert!(validators.if_range_matches("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert!(!validators.if_range_matches("Sun, 06 Nov 1994 08:49:38 GMT"));
+        assert!(weak_match("W/\"a\"", "\"a\""));
+    }
+}
@@ -0,0 +1,220 @@
+//! The served page's console, printed in the terminal
+//!
+//! Pages get a script that passes what they write with `console.log`,
+//! `info`, `warn`, `error` and `debug`, and errors nothing caught, on to
+//! [`LOG_ROUTE`] in batches. Panic messages of Rust modules, which
+//! `console_error_panic_hook` writes with `console.error`, so show up next to
+//! the build output. Traps are left to [`traps`](super::traps), which names
+//! their frames. `server.forward_console = false` turns this off.
+
+use super::utils::respond;
+use crate::config::active_settings;
+use crate::logging::Level;
+use crate::{error_println, log_at};
+use std::io::Read;
+use tiny_http::{Method, Request, Response};
+
+pub const LOG_ROUTE: &str = "/__wasmrun/log";
+
+/// Largest batch accepted, in bytes; the page sends far less
+const MAX_BODY: u64 = 256 * 1024;
+
+#[derive(Debug, serde::Deserialize)]
+struct Batch {
+    entries: Vec<Entry>,
+    /// Entries the page dropped because it wrote faster than it could send
+    #[serde(default)]
+    dropped: usize,
+}
+
+#[derive(Debug, PartialEq, serde::Deserialize)]
+struct Entry {
+    /// `log`, `info`, `warn`, `error`, `debug`, or `uncaught` for an error
+    /// nothing caught
+    level: String,
+    text: String,
+}
+
+/// Whether pages forward their console
+pub fn is_enabled() -> bool {
+    active_settings().server.forward_console != Some(false)
+}
+
+/// The terminal line for `entry`, with its continuation lines indented
+fn format_entry(entry: &Entry) -> String {
+    let prefix = match entry.level.as_str() {
+        "error" => "🌐❌ console.error:",
+        "uncaught" => "🌐💥 Uncaught",
+        "warn" => "🌐⚠️  console.warn:",
+        "info" => "🌐 console.info:",
+        "debug" => "🌐 console.debug:",
+        _ => "🌐 console.log:",
+    };
+    let text = entry.text.trim_end().replace('\n', "\n   ");
+    format!("{prefix} {text}")
+}
+
+/// Print a batch of console output posted by a served page
+pub fn receive(mut request: Request) {
+    if *request.method() != Method::Post {
+        let response = Response::from_string("405 Method Not Allowed").with_status_code(405);
+        if let Err(e) = respond(request, response) {
+            error_println!("Error sending console log response: {e}");
+        }
+        return;
+    }
+
+    let mut body = String::new();
+    let batch = request
+        .as_reader()
+        .take(MAX_BODY)
+        .read_to_string(&mut body)
+        .ok()
+        .and_then(|_| serde_json::from_str::<Batch>(&body).ok());
+    let status = match batch {
+        Some(batch) => {
+            for entry in &batch.entries {
+                let level = match entry.level.as_str() {
+                    "error" | "uncaught" => Level::Error,
+                    "warn" => Level::Warn,
+                    _ => Level::Info,
+                };
+                log_at!(level, "{}", format_entry(entry));
+            }
+            if batch.dropped > 0 {
+                log_at!(
+                    Level::Warn,
+                    "🌐 … {} console messages dropped",
+                    batch.dropped
+                );
+            }
+            204
+        }
+        None => 400,
+    };
+    if let Err(e) = respond(request, Response::empty(status)) {
+        error_println!("Error sending console log response: {e}");
+    }
+}
+
+/// Add the forwarder to a served page, when forwarding is on
+pub fn inject(html: &str) -> String {
+    if !is_enabled() {
+        return html.to_string();
+    }
+    let script = format!(
+        "<script>\n{}</script>",
+        FORWARDER_SCRIPT.replace("__ROUTE__", LOG_ROUTE)
+    );
+    // First in the head, to see what the other scripts log while loading
+    let head = html
+        .find("<head")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+    match head {
+        Some(index) => format!("{}\n{script}{}", &html[..index], &html[index..]),
+        None => format!("{script}\n{html}"),
+    }
+}
+
+const FORWARDER_SCRIPT: &str = r#"(() => {
+  const ROUTE = "__ROUTE__";
+  const MAX_TEXT = 4000;
+  const MAX_QUEUED = 200;
+  let queue = [];
+  let dropped = 0;
+  let timer = null;
+
+  const describe = (value) => {
+    if (typeof value === "string") return value;
+    if (value instanceof Error) return value.stack && value.stack.includes(value.message) ? value.stack : `${value}\n${value.stack || ""}`;
+    if (typeof value === "function" || typeof value === "symbol" || value === undefined) return String(value);
+    try {
+      const seen = new WeakSet();
+      return JSON.stringify(value, (key, item) => {
+        if (typeof item === "bigint") return `${item}n`;
+        if (typeof item === "object" && item !== null) {
+          if (seen.has(item)) return "[Circular]";
+          seen.add(item);
+        }
+        return item;
+      });
+    } catch {
+      return String(value);
+    }
+  };
+
+  const flush = () => {
+    timer = null;
+    if (!queue.length) return;
+    const body = JSON.stringify({ entries: queue, dropped });
+    queue = [];
+    dropped = 0;
+    fetch(ROUTE, { method: "POST", headers: { "Content-Type": "application/json" }, body, keepalive: true }).catch(() => {});
+  };
+
+  const send = (level, args) => {
+    if (queue.length >= MAX_QUEUED) {
+      dropped++;
+      return;
+    }
+    let text = args.map(describe).join(" ");
+    if (text.length > MAX_TEXT) text = text.slice(0, MAX_TEXT) + "…";
+    queue.push({ level, text });
+    if (!timer) timer = setTimeout(flush, 100);
+  };
+
+  for (const level of ["log", "info", "warn", "error", "debug"]) {
+    const original = console[level];
+    console[level] = function (...args) {
+      try {
+        send(level, args);
+      } catch {}
+      return original.apply(this, args);
+    };
+  }
+
+  // Traps are reported, with their frames, by the trap agent
+  const uncaught = (error) => {
+    if (typeof WebAssembly !== "undefined" && error instanceof WebAssembly.RuntimeError) return;
+    send("uncaught", [error]);
+  };
+  addEventListener("error", (event) => uncaught(event.error || event.message));
+  addEventListener("unhandledrejection", (event) => uncaught(event.reason));
+  addEventListener("pagehide", flush);
+})();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forwarder_is_first_in_the_head() {
+        let page = "<html><head><title>t</title></head><body></body></html>";
+        let page = inject(page);
+        assert!(page.find("<script>").unwrap() < page.find("<title>").unwrap());
+        assert!(page.contains(r#"const ROUTE = "/__wasmrun/log";"#));
+    }
+
+    #[test]
+    fn test_entries_print_with_their_level() {
+        let batch: Batch = serde_json::from_str(
+            r#"{"entries":[{"level":"error","text":"panicked at src/lib.rs:4:5:\nboom\n"},{"level":"log","text":"ready"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(batch.dropped, 0);
+        assert_eq!(
+            format_entry(&batch.entries[0]),
+            "🌐❌ console.error: panicked at src/lib.rs:4:5:\n   boom"
+        );
+        assert_eq!(format_entry(&batch.entries[1]), "🌐 console.log: ready");
+        let uncaught = Entry {
+            level: "uncaught".to_string(),
+            text: "TypeError: x is undefined".to_string(),
+        };
+        assert_eq!(
+            format_entry(&uncaught),
+            "🌐💥 Uncaught TypeError: x is undefined"
+        );
+    }
+}
@@ -0,0 +1,292 @@
+//! Control interface for a running server (`wasmrun ctl`)
+//!
+//! Each server answers [`ControlCommand`]s on its IPC endpoint, so scripts and
+//! editor tasks can reload pages, rebuild the project, open the browser or
+//! change response headers without restarting it. The debug panel's command
+//! palette sends the commands that concern the page to [`CONTROL_ROUTE`]
+//! instead; that route only takes JSON bodies, which browsers won't send
+//! across origins without a preflight the server never approves.
+
+use super::build_status;
+use super::file_cache;
+use super::live_reload;
+use crate::config::active_settings;
+use crate::ipc::{Endpoint, Listener};
+use crate::{error_println, info_println, warn_println};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::RwLock;
+use tiny_http::{Method, Request, Response};
+
+/// Where pages send control commands
+pub const CONTROL_ROUTE: &str = "/__wasmrun/ctl";
+
+/// Headers set with `wasmrun ctl set-header`; they win over `server.headers` and `--header`
+static HEADERS: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+/// What this process serves, once [`start`] has been called
+static CONTEXT: RwLock<Option<ControlContext>> = RwLock::new(None);
+
+/// A request sent to a running server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum ControlCommand {
+    /// Reload every open page
+    Reload,
+    /// Rebuild the project, then reload open pages
+    Rebuild,
+    /// Open the page in the browser
+    Open,
+    /// Add a response header, or remove it when `value` is `None`
+    SetHeader { name: String, value: Option<String> },
+    /// Stop serving and exit, cleaning up like Ctrl+C does
+    Shutdown,
+}
+
+/// What the server is serving, needed to carry out commands
+#[derive(Debug, Clone)]
+pub struct ControlContext {
+    pub port: u16,
+    pub wasm_path: String,
+    pub project_path: Option<String>,
+}
+
+/// Headers added at runtime, applied to every response
+pub fn runtime_headers() -> BTreeMap<String, String> {
+    HEADERS
+        .read()
+        .map(|headers| headers.clone())
+        .unwrap_or_default()
+}
+
+/// Accept control commands for this server until the returned listener is dropped;
+/// demo servers accept none
+pub fn start(context: ControlContext) -> Option<Listener> {
+    if super::demo::is_enabled() {
+        // Nothing may change a demo from outside, not even on the same machine
+        return None;
+    }
+    if let Ok(mut current) = CONTEXT.write() {
+        *current = Some(context.clone());
+    }
+    let listener = Endpoint::for_process(std::process::id())
+        .and_then(|endpoint| endpoint.listen(move |command| handle(command, &context)));
+    match listener {
+        Ok(listener) => Some(listener),
+        Err(e) => {
+            warn_println!("Control interface unavailable: {e}");
+            None
+        }
+    }
+}
+
+fn handle(command: ControlCommand, context: &ControlContext) -> Result<String, String> {
+    info_println!("🎛️  Control command: {command:?}");
+    match command {
+        ControlCommand::Reload => {
+            live_reload::request_reload();
+            Ok("Reloading open pages".to_string())
+        }
+        ControlCommand::Rebuild => rebuild(context),
+        ControlCommand::Open => {
+            super::utils::open_browser_when_ready(context.port);
+            Ok(format!("Opening the browser on port {}", context.port))
+        }
+        ControlCommand::SetHeader { name, value } => set_header(name, value),
+        ControlCommand::Shutdown => {
+            super::shutdown::request("Stop requested");
+            Ok("Shutting down".to_string())
+        }
+    }
+}
+
+/// Answer a page under [`CONTROL_ROUTE`]: `GET` describes the server, `POST`
+/// runs a command given as JSON
+pub fn serve(request: Request) {
+    let context = CONTEXT.read().ok().and_then(|context| context.clone());
+    let Some(context) = context.filter(|_| !super::demo::is_enabled()) else {
+        send(
+            request,
+            404,
+            "Control commands are not accepted".to_string(),
+        );
+        return;
+    };
+    match request.method() {
+        Method::Get => {
+            let urls = super::urls::ServerUrls::for_port(context.port);
+            let info = serde_json::json!({
+                "project": context.project_path.is_some(),
+                "share_url": urls.shareable().map(|url| url.url.clone()),
+            });
+            send(request, 200, info.to_string());
+        }
+        Method::Post => run_posted(request, context),
+        _ => send(request, 405, "Use GET or POST".to_string()),
+    }
+}
+
+fn run_posted(mut request: Request, context: ControlContext) {
+    let is_json = request.headers().iter().any(|header| {
+        header.field.equiv("Content-Type") && header.value.as_str().starts_with("application/json")
+    });
+    if !is_json {
+        send(
+            request,
+            415,
+            "Send the command as application/json".to_string(),
+        );
+        return;
+    }
+    let mut body = String::new();
+    let command = request
+        .as_reader()
+        .take(64 * 1024)
+        .read_to_string(&mut body)
+        .map_err(|e| e.to_string())
+        .and_then(|_| serde_json::from_str::<ControlCommand>(&body).map_err(|e| e.to_string()));
+    let command = match command {
+        Ok(command) if allowed_from_page(&command) => command,
+        Ok(command) => {
+            send(
+                request,
+                403,
+                format!("{command:?} is only accepted from wasmrun ctl"),
+            );
+            return;
+        }
+        Err(e) => {
+            send(request, 400, format!("Invalid control command: {e}"));
+            return;
+        }
+    };
+    // A rebuild takes a while; keep serving other requests meanwhile
+    std::thread::spawn(move || match handle(command, &context) {
+        Ok(message) => send(request, 200, message),
+        Err(message) => send(request, 500, message),
+    });
+}
+
+/// Pages may only ask for what a file change would do, a rebuild or a
+/// reload; headers, windows and shutdown stay with `wasmrun ctl`
+fn allowed_from_page(command: &ControlCommand) -> bool {
+    matches!(command, ControlCommand::Reload | ControlCommand::Rebuild)
+}
+
+fn send(request: Request, status: u16, body: String) {
+    let content_type = if body.starts_with('{') {
+        "application/json"
+    } else {
+        "text/plain"
+    };
+    let response = Response::from_string(body)
+        .with_status_code(status)
+        .with_header(super::utils::content_type_header(content_type));
+    if let Err(e) = super::utils::respond(request, response) {
+        error_println!("Error sending control response: {e}");
+    }
+}
+
+fn rebuild(context: &ControlContext) -> Result<String, String> {
+    rebuild_project(context.project_path.as_deref(), &context.wasm_path)
+}
+
+/// Rebuild the project into the directory `wasm_path` is served from, then reload open pages
+pub fn rebuild_project(project_path: Option<&str>, wasm_path: &str) -> Result<String, String> {
+    let project_path = project_path
+        .map(str::to_string)
+        .ok_or("This server is serving a WASM file, not a project")?;
+    let output_dir = Path::new(wasm_path)
+        .parent()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .ok_or("Cannot determine the build output directory")?;
+
+    // Build the way the server's first build did, `--profile` included
+    let optimization_level = active_settings().build.optimization_level();
+    build_status::begin("rebuild requested");
+    let result = crate::commands::run_compile(project_path, output_dir, optimization_level, false)
+        .map_err(|e| {
+            build_status::failed(&e.to_string());
+            format!("Rebuild failed: {e}")
+        })?;
+    build_status::succeeded();
+    file_cache::invalidate();
+    live_reload::reload_after_build(&result.wasm_path);
+    Ok(format!("Rebuilt {}", result.wasm_path))
+}
+
+fn set_header(name: String, value: Option<String>) -> Result<String, String> {
+    let mut headers = HEADERS.write().map_err(|e| e.to_string())?;
+    match value {
+        Some(value) => {
+            if !super::headers::is_valid(&name, &value) {
+                return Err(format!("Invalid header: {name}: {value}"));
+            }
+            let message = format!("Set header {name}: {value}");
+            headers.insert(name, value);
+            Ok(message)
+        }
+        None => match headers.remove(&name) {
+            Some(_) => Ok(format!("Removed header {name}")),
+            None => Err(format!("Header {name} was not set with set-header")),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_wire_format() {
+        let command = ControlCommand::SetHeader {
+            name: "X-Env".to_string(),
+            value: Some("dev".to_string()),
+        };
+        let json = serde_json::to_string(&command).unwrap();
+        assert_eq!(
+            json,
+            r#"{"command":"set-header","name":"X-Env","value":"dev"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<ControlCommand>(r#"{"command":"reload"}"#).unwrap(),
+            ControlCommand::Reload
+        );
+    }
+
+    #[test]
+    fn test_set_and_remove_header() {
+        let name = "X-Control-Test".to_string();
+        set_header(name.clone(), Some("1".to_string())).unwrap();
+        assert_eq!(runtime_headers().get(&name).map(String::as_str), Some("1"));
+
+        set_header(name.clone(), None).unwrap();
+        assert!(!runtime_headers().contains_key(&name));
+        assert!(set_header(name, None).is_err());
+        assert!(set_header("Bad Header".to_string(), Some("x".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_rebuild_needs_a_project() {
+        let context = ControlContext {
+            port: 8420,
+            wasm_path: "app.wasm".to_string(),
+            project_path: None,
+        };
+        assert!(handle(ControlCommand::Rebuild, &context).is_err());
+    }
+
+    #[test]
+    fn test_pages_cannot_stop_the_server() {
+        assert!(allowed_from_page(&ControlCommand::Reload));
+        assert!(allowed_from_page(&ControlCommand::Rebuild));
+        assert!(!allowed_from_page(&ControlCommand::SetHeader {
+            name: "Content-Security-Policy".to_string(),
+            value: None,
+        }));
+        assert!(!allowed_from_page(&ControlCommand::Shutdown));
+        assert!(!allowed_from_page(&ControlCommand::Open));
+    }
+}
@@ -0,0 +1,264 @@
+//! Content-addressed serving of shared wasm chunks
+//!
+//! Prebuilt apps served side by side from one directory (`wasmrun serve`),
+//! one per subdirectory, often ship byte-identical dependency chunks from a
+//! split build, each under its own path. A request for such a chunk is redirected to
+//! `/__wasmrun/wasm/<hash>.wasm`, which the browser may cache for good, so it
+//! downloads one copy however many apps load it. Every time a client gets a
+//! chunk from a path it has not loaded before without downloading it again,
+//! the chunk's size is reported to the dashboard as saved.
+
+use super::base_path;
+use super::cache_bust;
+use super::conditional;
+use super::site;
+use super::toggles::Toggles;
+use super::utils::{content_type_header, respond};
+use crate::utils::CommandExecutor;
+use crate::{error_println, request_println};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tiny_http::{Header, Request, Response};
+
+/// Route serving shared chunks by content hash
+pub const DEDUP_ROUTE: &str = "/__wasmrun/wasm/";
+
+static VISITS: OnceLock<Mutex<Visits>> = OnceLock::new();
+
+/// What each client has loaded, to tell which redirects saved a download
+#[derive(Debug, Default)]
+struct Visits {
+    /// (client, hash) pairs whose chunk the client downloaded
+    downloaded: HashSet<(String, String)>,
+    /// (client, path) pairs a client was redirected from
+    redirected: HashSet<(String, PathBuf)>,
+}
+
+impl Visits {
+    /// Record a redirect; true when the client already has the chunk from
+    /// another path, so without the redirect it would download it again
+    fn redirect(&mut self, client: &str, path: &Path, hash: &str) -> bool {
+        let first_visit = self
+            .redirected
+            .insert((client.to_string(), path.to_path_buf()));
+        first_visit
+            && self
+                .downloaded
+                .contains(&(client.to_string(), hash.to_string()))
+    }
+
+    fn download(&mut self, client: &str, hash: &str) {
+        self.downloaded
+            .insert((client.to_string(), hash.to_string()));
+    }
+}
+
+fn visits() -> &'static Mutex<Visits> {
+    VISITS.get_or_init(|| Mutex::new(Visits::default()))
+}
+
+/// Wasm files under `root` with the same content, by content hash; only
+/// hashes shared by two or more files
+pub fn shared_chunks(root: &Path) -> BTreeMap<String, Vec<PathBuf>> {
+    let mut files = Vec::new();
+    site::collect_files(root, 0, &mut files);
+    let mut chunks: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for path in files
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+    {
+        if let Some(hash) = cache_bust::content_hash(&path) {
+            chunks.entry(hash).or_default().push(path);
+        }
+    }
+    chunks.retain(|_, paths| paths.len() > 1);
+    chunks
+}
+
+/// One line on the shared chunks under `root`, for the startup banner
+pub fn summary(root: &Path) -> Option<String> {
+    let chunks = shared_chunks(root);
+    if chunks.is_empty() || !conditional::is_enabled() {
+        return None;
+    }
+    let copies: usize = chunks.values().map(|paths| paths.len()).sum();
+    let saved: u64 = chunks
+        .values()
+        .map(|paths| (paths.len() as u64 - 1) * file_size(&paths[0]))
+        .sum();
+    let groups = match chunks.len() {
+        1 => "1 group".to_string(),
+        count => format!("{count} groups"),
+    };
+    Some(format!(
+        "{copies} identical wasm files in {groups}, downloaded once ({} saved per visitor)",
+        CommandExecutor::format_file_size(saved)
+    ))
+}
+
+/// Redirect a request for `file` to its content-addressed URL when other
+/// files under `root` have the same content; gives the request back otherwise,
+/// including to browsers bypassing the cache or profiling
+pub fn redirect_shared(request: Request, root: &Path, file: &Path, base: &str) -> Option<Request> {
+    let toggles = Toggles::from_request(&request);
+    if !conditional::is_enabled()
+        || toggles.nocache
+        || toggles.profile
+        || file.extension().is_none_or(|ext| ext != "wasm")
+    {
+        return Some(request);
+    }
+    let Some(hash) = shared_chunks(root)
+        .into_iter()
+        .find_map(|(hash, paths)| paths.iter().any(|path| path == file).then_some(hash))
+    else {
+        return Some(request);
+    };
+
+    let saved = visits()
+        .lock()
+        .is_ok_and(|mut visits| visits.redirect(&client(&request), file, &hash));
+    if saved {
+        crate::tui::emit(crate::tui::DevEvent::Deduplicated {
+            bytes: file_size(file),
+        });
+    }
+
+    let location = format!("{}{DEDUP_ROUTE}{hash}.wasm", base.trim_end_matches('/'));
+    base_path::redirect(request, &location);
+    None
+}
+
+/// Answer a request for `name` (`<hash>.wasm`) under [`DEDUP_ROUTE`]
+pub fn serve(request: Request, name: &str) {
+    let file = name.strip_suffix(".wasm").and_then(|hash| {
+        let root = site::root()?;
+        let paths = shared_chunks(&root).remove(hash)?;
+        Some((hash.to_string(), paths.into_iter().next()?))
+    });
+    let Some((hash, file)) = file else {
+        let response = Response::from_string("Not found")
+            .with_status_code(404)
+            .with_header(content_type_header("text/plain"));
+        if let Err(e) = respond(request, response) {
+            error_println!("Error sending response: {e}");
+        }
+        return;
+    };
+
+    let etag = format!("\"{hash}\"");
+    let headers = [
+        content_type_header("application/wasm"),
+        Header::from_bytes("ETag", etag.as_str()).unwrap(),
+        // The URL names the content, so it never changes
+        Header::from_bytes("Cache-Control", "public, max-age=31536000, immutable").unwrap(),
+    ];
+    let cached = request.headers().iter().any(|header| {
+        header.field.equiv("If-None-Match")
+            && header
+                .value
+                .as_str()
+                .split(',')
+                .any(|tag| tag.trim().trim_start_matches("W/") == etag)
+    });
+    if cached {
+        let mut response = Response::empty(304);
+        for header in headers {
+            response.add_header(header);
+        }
+        if let Err(e) = respond(request, response) {
+            error_println!("Error sending response: {e}");
+        }
+        return;
+    }
+
+    let mut response = match File::open(&file) {
+        // Sent with a Content-Length, like other files, rather than chunked
+        Ok(opened) => Response::from_file(opened).with_chunked_threshold(usize::MAX),
+        Err(e) => {
+            error_println!("Error reading file {}: {e}", file.display());
+            let response = Response::from_string(format!("Error: {e}"))
+                .with_status_code(500)
+                .with_header(content_type_header("text/plain"));
+            if let Err(e) = respond(request, response) {
+                error_println!("Error sending error response: {e}");
+            }
+            return;
+        }
+    };
+    for header in headers {
+        response.add_header(header);
+    }
+    if let Ok(mut visits) = visits().lock() {
+        visits.download(&client(&request), &hash);
+    }
+    request_println!("🔄 Serving shared chunk: {}", file.display());
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending file response: {e}");
+    }
+}
+
+/// Clients are told apart by address; their ports change between connections
+fn client(request: &Request) -> String {
+    request
+        .remote_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_default()
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_shared_chunks() {
+        let dir = tempdir().unwrap();
+        for (path, contents) in [
+            ("shop/app.wasm", "\0asm shop"),
+            ("shop/vendor.wasm", "\0asm vendor"),
+            ("blog/app.wasm", "\0asm blog"),
+            ("blog/vendor.wasm", "\0asm vendor"),
+        ] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        let root = fs::canonicalize(dir.path()).unwrap();
+
+        let chunks = shared_chunks(&root);
+        assert_eq!(chunks.len(), 1);
+        let paths = chunks.values().next().unwrap();
+        assert_eq!(
+            paths,
+            &[root.join("blog/vendor.wasm"), root.join("shop/vendor.wasm")]
+        );
+        assert!(summary(&root)
+            .unwrap()
+            .starts_with("2 identical wasm files in 1 group,"));
+    }
+
+    #[test]
+    fn test_redirect_saves_once_downloaded() {
+        let mut visits = Visits::default();
+        let (shop, blog) = (
+            Path::new("/shop/vendor.wasm"),
+            Path::new("/blog/vendor.wasm"),
+        );
+        assert!(!visits.redirect("10.0.0.1", shop, "abc"));
+        visits.download("10.0.0.1", "abc");
+        assert!(visits.redirect("10.0.0.1", blog, "abc"));
+        // Reloading an app saves nothing on top of ordinary caching
+        assert!(!visits.redirect("10.0.0.1", blog, "abc"));
+        // Another client has nothing yet
+        assert!(!visits.redirect("10.0.0.2", blog, "abc"));
+    }
+}
@@ -0,0 +1,412 @@
+//! Delta updates for large wasm modules
+//!
+//! Rebuilding a big debug module usually changes a small part of it, yet the
+//! page downloads the whole file again. The server keeps the last few builds
+//! of every wasm file of at least [`MIN_SIZE`] bytes in memory. The served page
+//! stores the module it loaded in IndexedDB, and when the content hash in the
+//! URL changes it asks [`DELTA_ROUTE`] for a patch from its copy to the new
+//! build and applies that before instantiation. Anything unexpected falls
+//! back to downloading the full module.
+//!
+//! The delta is a list of operations that rebuild the new file: copy a range
+//! of the old file, or insert literal bytes. Matching ranges are found with a
+//! rolling hash over fixed-size blocks of the old file.
+
+use super::utils::{content_type_header, respond};
+use crate::error_println;
+use crate::utils::CommandExecutor;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex, OnceLock};
+use tiny_http::{Header, Request, Response};
+
+/// Route serving deltas: `?file=<name>&from=<hash>&to=<hash>`
+pub const DELTA_ROUTE: &str = "/__wasmrun/delta";
+
+/// Smaller modules are always downloaded in full
+pub const MIN_SIZE: usize = 1024 * 1024;
+
+/// Builds kept per file; clients further behind download the full module
+const MAX_VERSIONS: usize = 3;
+
+/// Bytes hashed per block when matching old and new content
+const BLOCK: usize = 64;
+
+/// Multiplier of the rolling hash
+const PRIME: u32 = 16_777_619;
+
+const MAGIC: &[u8; 4] = b"WRD1";
+const OP_COPY: u8 = 0;
+const OP_INSERT: u8 = 1;
+
+/// A delta is only worth sending when it is clearly smaller than the module
+const MAX_DELTA_RATIO: f64 = 0.8;
+
+type History = HashMap<String, Vec<(String, Arc<Vec<u8>>)>>;
+static HISTORY: OnceLock<Mutex<History>> = OnceLock::new();
+
+/// The most recently computed delta, reused for other clients on the same version
+type LastDelta = Option<((String, String, String), Arc<Vec<u8>>)>;
+static LAST_DELTA: Mutex<LastDelta> = Mutex::new(None);
+
+fn history() -> &'static Mutex<History> {
+    HISTORY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Keep a build of the wasm file `name` with content hash `hash` for future deltas
+pub fn remember(name: &str, hash: &str, bytes: Vec<u8>) {
+    if bytes.len() < MIN_SIZE || super::preview::is_enabled() {
+        return;
+    }
+    let Ok(mut history) = history().lock() else {
+        return;
+    };
+    let versions = history.entry(name.to_string()).or_default();
+    if versions.iter().any(|(known, _)| known == hash) {
+        return;
+    }
+    versions.push((hash.to_string(), Arc::new(bytes)));
+    if versions.len() > MAX_VERSIONS {
+        versions.remove(0);
+    }
+}
+
+/// Builds kept for deltas and the bytes they and the cached last delta hold
+pub fn memory_usage() -> (usize, u64) {
+    let (mut builds, mut bytes) = (0, 0);
+    if let Ok(history) = history().lock() {
+        for (_, data) in history.values().flatten() {
+            builds += 1;
+            bytes += data.len() as u64;
+        }
+    }
+    if let Ok(last) = LAST_DELTA.lock() {
+        bytes += last.as_ref().map_or(0, |(_, delta)| delta.len() as u64);
+    }
+    (builds, bytes)
+}
+
+/// Add the script that loads tracked modules through deltas to a served page
+pub fn inject(html: &str) -> String {
+    let files: Vec<String> = match history().lock() {
+        Ok(history) => history.keys().cloned().collect(),
+        Err(_) => return html.to_string(),
+    };
+    if files.is_empty() {
+        return html.to_string();
+    }
+
+    let files = serde_json::to_string(&files).unwrap_or_else(|_| "[]".to_string());
+    let script = format!("<script>\n(function (files) {{{AGENT_SCRIPT}}})({files});\n</script>");
+    match html
+        .find("<head")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1))
+    {
+        Some(at) => format!("{}\n{script}{}", &html[..at], &html[at..]),
+        None => format!("{script}\n{html}"),
+    }
+}
+
+/// Answer a delta request, or 404 when no useful delta exists so the page loads the full file
+pub fn serve(request: Request, query: &str) {
+    let param = |key: &str| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix(key)?.strip_prefix('='))
+            .unwrap_or_default()
+            .to_string()
+    };
+    let (file, from, to) = (param("file"), param("from"), param("to"));
+
+    match delta_between(&file, &from, &to) {
+        Some((delta, full_size)) => {
+            println!(
+                "📦 Sending delta for {file}: {} instead of {}",
+                CommandExecutor::format_file_size(delta.len() as u64),
+                CommandExecutor::format_file_size(full_size as u64)
+            );
+            let mut response = Response::from_data(delta.as_slice())
+                .with_header(content_type_header("application/octet-stream"));
+            if let Ok(header) = Header::from_bytes(&b"Content-Encoding"[..], &b"gzip"[..]) {
+                response.add_header(header);
+            }
+            if let Err(e) = respond(request, response) {
+                error_println!("Error sending delta: {e}");
+            }
+        }
+        None => {
+            let response = Response::from_string("no delta")
+                .with_status_code(404)
+                .with_header(content_type_header("text/plain"));
+            if let Err(e) = respond(request, response) {
+                error_println!("Error sending delta response: {e}");
+            }
+        }
+    }
+}
+
+/// The gzip-compressed delta from build `from` to build `to` of `file`, and the full size
+fn delta_between(file: &str, from: &str, to: &str) -> Option<(Arc<Vec<u8>>, usize)> {
+    let key = (file.to_string(), from.to_string(), to.to_string());
+    let (old, new) = {
+        let history = history().lock().ok()?;
+        let versions = history.get(file)?;
+        let find = |hash: &str| {
+            versions
+                .iter()
+                .find(|(known, _)| known == hash)
+                .map(|(_, bytes)| Arc::clone(bytes))
+        };
+        (find(from)?, find(to)?)
+    };
+
+    if let Ok(last) = LAST_DELTA.lock() {
+        if let Some((cached_key, delta)) = last.as_ref() {
+            if *cached_key == key {
+                return Some((Arc::clone(delta), new.len()));
+            }
+        }
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(&diff(&old, &new)).ok()?;
+    let delta = Arc::new(encoder.finish().ok()?);
+    if delta.len() as f64 > new.len() as f64 * MAX_DELTA_RATIO {
+        return None;
+    }
+    if let Ok(mut last) = LAST_DELTA.lock() {
+        *last = Some((key, Arc::clone(&delta)));
+    }
+    Some((delta, new.len()))
+}
+
+/// Operations that rebuild `new` from `old`
+fn diff(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::with_capacity(new.len() / 8);
+    delta.extend_from_slice(MAGIC);
+    delta.extend_from_slice(&(new.len() as u32).to_le_bytes());
+
+    let mut index: HashMap<u32, usize> = HashMap::with_capacity(old.len() / BLOCK);
+    for offset in (0..old.len().saturating_sub(BLOCK - 1)).step_by(BLOCK) {
+        index
+            .entry(block_hash(&old[offset..offset + BLOCK]))
+            .or_insert(offset);
+    }
+    // Weight of the byte leaving the window when the hash rolls forward
+    let outgoing = (1..BLOCK).fold(1u32, |power, _| power.wrapping_mul(PRIME));
+
+    let mut literal_start = 0;
+    let mut position = 0;
+    let mut hash = (new.len() >= BLOCK).then(|| block_hash(&new[..BLOCK]));
+    while let Some(current) = hash {
+        let matched = index
+            .get(&current)
+            .copied()
+            .filter(|&offset| old[offset..offset + BLOCK] == new[position..position + BLOCK]);
+        if let Some(offset) = matched {
+            let (mut start_new, mut start_old) = (position, offset);
+            while start_new > literal_start
+                && start_old > 0
+                && new[start_new - 1] == old[start_old - 1]
+            {
+                start_new -= 1;
+                start_old -= 1;
+            }
+            let mut length = position + BLOCK - start_new;
+            while start_new + length < new.len()
+                && start_old + length < old.len()
+                && new[start_new + length] == old[start_old + length]
+            {
+                length += 1;
+            }
+
+            push_insert(&mut delta, &new[literal_start..start_new]);
+            delta.push(OP_COPY);
+            delta.extend_from_slice(&(start_old as u32).to_le_bytes());
+            delta.extend_from_slice(&(length as u32).to_le_bytes());
+
+            position = start_new + length;
+            literal_start = position;
+            hash = (position + BLOCK <= new.len())
+                .then(|| block_hash(&new[position..position + BLOCK]));
+        } else if position + BLOCK < new.len() {
+            hash = Some(
+                current
+                    .wrapping_sub(u32::from(new[position]).wrapping_mul(outgoing))
+                    .wrapping_mul(PRIME)
+                    .wrapping_add(u32::from(new[position + BLOCK])),
+            );
+            position += 1;
+        } else {
+            hash = None;
+        }
+    }
+    push_insert(&mut delta, &new[literal_start..]);
+    delta
+}
+
+fn push_insert(delta: &mut Vec<u8>, bytes: &[u8]) {
+    if bytes.is_empty() {
+        return;
+    }
+    delta.push(OP_INSERT);
+    delta.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    delta.extend_from_slice(bytes);
+}
+
+fn block_hash(block: &[u8]) -> u32 {
+    block.iter().fold(0u32, |hash, &byte| {
+        hash.wrapping_mul(PRIME).wrapping_add(u32::from(byte))
+    })
+}
+
+/// Page script: `files` lists the wasm file names the server keeps builds of
+const AGENT_SCRIPT: &str = r#"
+  if (!window.indexedDB) return;
+  const route = "/__wasmrun/delta";
+  const db = () => new Promise((resolve, reject) => {
+    const open = indexedDB.open("wasmrun-delta", 1);
+    open.onupgradeneeded = () => open.result.createObjectStore("modules");
+    open.onsuccess = () => resolve(open.result);
+    open.onerror = () => reject(open.error);
+  });
+  const store = (mode, action) => db().then((conn) => new Promise((resolve, reject) => {
+    const tx = conn.transaction("modules", mode);
+    const req = action(tx.objectStore("modules"));
+    tx.oncomplete = () => resolve(req.result);
+    tx.onerror = () => reject(tx.error);
+  }));
+  const load = (key) => store("readonly", (s) => s.get(key)).catch(() => undefined);
+  const save = (key, hash, bytes) => store("readwrite", (s) => s.put({ hash, bytes }, key)).catch(() => {});
+  const apply = (old, delta) => {
+    const view = new DataView(delta.buffer, delta.byteOffset, delta.byteLength);
+    if (String.fromCharCode(...delta.subarray(0, 4)) !== "WRD1") throw new Error("not a delta");
+    const out = new Uint8Array(view.getUint32(4, true));
+    let pos = 8, at = 0;
+    while (pos < delta.length) {
+      const op = delta[pos];
+      if (op === 0) {
+        const offset = view.getUint32(pos + 1, true), length = view.getUint32(pos + 5, true);
+        out.set(old.subarray(offset, offset + length), at);
+        pos += 9; at += length;
+      } else if (op === 1) {
+        const length = view.getUint32(pos + 1, true);
+        out.set(delta.subarray(pos + 5, pos + 5 + length), at);
+        pos += 5 + length; at += length;
+      } else throw new Error("bad delta operation " + op);
+    }
+    if (at !== out.length) throw new Error("delta has the wrong length");
+    return out;
+  };
+  const fetch = window.fetch;
+  window.fetch = async (input, init) => {
+    const url = typeof input === "string" || input instanceof URL ? new URL(input, document.baseURI) : null;
+    const name = url && url.pathname.split("/").pop();
+    const hash = url && url.searchParams.get("v");
+    if (!hash || url.origin !== location.origin || !files.includes(name) || (init && init.method && init.method !== "GET")) {
+      return fetch(input, init);
+    }
+    const cached = await load(url.pathname);
+    let bytes = cached && cached.hash === hash ? new Uint8Array(cached.bytes) : null;
+    if (!bytes && cached) {
+      try {
+        const reply = await fetch(`${route}?file=${encodeURIComponent(name)}&from=${cached.hash}&to=${hash}`);
+        if (reply.ok) {
+          const delta = new Uint8Array(await reply.arrayBuffer());
+          bytes = apply(new Uint8Array(cached.bytes), delta);
+          console.info(`wasmrun: updated ${name} with a ${delta.length}-byte delta`);
+        }
+      } catch (error) {
+        console.warn("wasmrun: delta update failed, loading the full module", error);
+      }
+    }
+    if (!bytes) {
+      const response = await fetch(input, init);
+      if (!response.ok) return response;
+      bytes = new Uint8Array(await response.arrayBuffer());
+    }
+    save(url.pathname, hash, bytes.buffer);
+    return new Response(bytes, { status: 200, headers: { "Content-Type": "application/wasm" } });
+  };
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rebuild the new file from a delta, as the page script does
+    fn apply(old: &[u8], delta: &[u8]) -> Vec<u8> {
+        assert_eq!(&delta[..4], MAGIC);
+        let read = |at: usize| u32::from_le_bytes(delta[at..at + 4].try_into().unwrap()) as usize;
+        let mut out = Vec::with_capacity(read(4));
+        let mut pos = 8;
+        while pos < delta.len() {
+            if delta[pos] == OP_COPY {
+                let (offset, length) = (read(pos + 1), read(pos + 5));
+                out.extend_from_slice(&old[offset..offset + length]);
+                pos += 9;
+            } else {
+                let length = read(pos + 1);
+                out.extend_from_slice(&delta[pos + 5..pos + 5 + length]);
+                pos += 5 + length;
+            }
+        }
+        assert_eq!(out.len(), read(4));
+        out
+    }
+
+    fn pseudo_random(length: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed;
+        (0..length)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_round_trip_is_small_for_local_changes() {
+        let old = pseudo_random(200_000, 7);
+        let mut new = old.clone();
+        new[1_000..1_010].copy_from_slice(b"0123456789");
+        new.splice(50_000..50_000, pseudo_random(300, 9));
+        new.drain(150_000..150_500);
+
+        let delta = diff(&old, &new);
+        assert_eq!(apply(&old, &delta), new);
+        assert!(delta.len() < 2_000, "delta is {} bytes", delta.len());
+    }
+
+    #[test]
+    fn test_diff_handles_unrelated_and_tiny_inputs() {
+        for (old, new) in [
+            (pseudo_random(5_000, 1), pseudo_random(4_000, 2)),
+            (Vec::new(), b"abc".to_vec()),
+            (b"abc".to_vec(), Vec::new()),
+        ] {
+            assert_eq!(apply(&old, &diff(&old, &new)), new);
+        }
+    }
+
+    #[test]
+    fn test_history_serves_deltas_between_kept_builds() {
+        let old = pseudo_random(MIN_SIZE, 3);
+        let mut new = old.clone();
+        new[10] ^= 0xff;
+        remember("history-test.wasm", "old", old);
+        remember("history-test.wasm", "new", new);
+        for hash in ["a", "b", "c"] {
+            remember("history-test.wasm", hash, pseudo_random(MIN_SIZE, 4));
+        }
+        remember("small-test.wasm", "x", vec![0; 16]);
+
+        assert!(delta_between("history-test.wasm", "old", "new").is_none());
+        let (delta, size) = delta_between("history-test.wasm", "a", "b").unwrap();
+        assert_eq!(size, MIN_SIZE);
+        assert!(delta.len() < MIN_SIZE / 10);
+        assert!(!history().lock().unwrap().contains_key("small-test.wasm"));
+    }
+}
@@ -0,0 +1,203 @@
+//! Read-only demo mode (`--demo`)
+//!
+//! For leaving a server running on a shared machine for others to look at.
+//! Every request needs the access token: opening a page with `?token=<token>`
+//! stores it in a cookie, and scripts can send it as a bearer token. Only GET
+//! and HEAD are answered, clients are rate-limited even when no limit is
+//! configured (see [`limits`](super::limits)), the control interface stays
+//! off, and pages are served without the development panels (feature sets,
+//! replay, profiler).
+
+use super::utils::{content_type_header, respond};
+use crate::error::{CommandError, Result, WasmrunError};
+use crate::error_println;
+use std::sync::RwLock;
+use tiny_http::{Header, Method, Request, Response};
+
+/// Cookie holding the access token once a page was opened with it
+const TOKEN_COOKIE: &str = "wasmrun_demo";
+
+/// Query parameter that grants access
+const TOKEN_PARAM: &str = "token";
+
+/// Requests per minute a single client may make unless `server.rate_limit` says
+/// otherwise; a page load takes a handful
+pub const REQUESTS_PER_MINUTE: u32 = 300;
+
+/// Access token, set while demo mode is on
+static TOKEN: RwLock<Option<String>> = RwLock::new(None);
+
+/// Turn on demo mode with `token`, or a random one; returns the token in use
+pub fn enable(token: Option<&str>) -> Result<String> {
+    let token = match token.map(str::trim) {
+        Some("") => {
+            return Err(WasmrunError::Command(CommandError::invalid_arguments(
+                "--demo-token must not be empty",
+            )))
+        }
+        Some(token) => token.to_string(),
+        None => random_token()?,
+    };
+    if let Ok(mut active) = TOKEN.write() {
+        *active = Some(token.clone());
+    }
+    Ok(token)
+}
+
+pub fn is_enabled() -> bool {
+    TOKEN.read().is_ok_and(|token| token.is_some())
+}
+
+fn random_token() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes)
+        .map_err(|e| WasmrunError::add_context("Generating a demo access token", e))?;
+    Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Let `request` through, or answer it here when demo mode turns it away
+pub fn admit(request: Request) -> Option<Request> {
+    let token = TOKEN.read().ok()?.clone();
+    let Some(token) = token else {
+        return Some(request);
+    };
+
+    if !matches!(request.method(), Method::Get | Method::Head) {
+        reject(request, 405, "This is a read-only demo");
+        return None;
+    }
+
+    let (path, query_token) = split_token(request.url());
+    if let Some(presented) = query_token {
+        if same_token(&presented, &token) {
+            // Keep the token in a cookie and out of the address bar and history
+            let response = Response::empty(303)
+                .with_header(header("Location", &path))
+                .with_header(header(
+                    "Set-Cookie",
+                    &format!("{TOKEN_COOKIE}={token}; Path=/; HttpOnly; SameSite=Strict"),
+                ));
+            if let Err(e) = respond(request, response) {
+                error_println!("Error sending demo redirect: {e}");
+            }
+            return None;
+        }
+    } else if presented_token(&request).is_some_and(|presented| same_token(presented, &token)) {
+        return Some(request);
+    }
+
+    reject(
+        request,
+        401,
+        "This demo needs an access token: open it with ?token=<token>",
+    );
+    None
+}
+
+/// The token from the `Cookie` or `Authorization` header
+fn presented_token(request: &Request) -> Option<&str> {
+    request.headers().iter().find_map(|header| {
+        let value = header.value.as_str();
+        if header.field.equiv("Authorization") {
+            value.strip_prefix("Bearer ").map(str::trim)
+        } else if header.field.equiv("Cookie") {
+            value.split(';').find_map(|cookie| {
+                cookie
+                    .trim()
+                    .strip_prefix(TOKEN_COOKIE)
+                    .and_then(|rest| rest.strip_prefix('='))
+            })
+        } else {
+            None
+        }
+    })
+}
+
+/// The URL without its `token` query parameter, and that parameter's value
+fn split_token(url: &str) -> (String, Option<String>) {
+    let Some((path, query)) = url.split_once('?') else {
+        return (url.to_string(), None);
+    };
+    let mut token = None;
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|pair| {
+            match pair
+                .strip_prefix(TOKEN_PARAM)
+                .and_then(|rest| rest.strip_prefix('='))
+            {
+                Some(value) => {
+                    token = Some(value.to_string());
+                    false
+                }
+                None => true,
+            }
+        })
+        .collect();
+    if kept.is_empty() {
+        (path.to_string(), token)
+    } else {
+        (format!("{path}?{}", kept.join("&")), token)
+    }
+}
+
+/// Compare tokens without returning early at the first differing byte
+pub(super) fn same_token(presented: &str, token: &str) -> bool {
+    presented.len() == token.len()
+        && presented
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).unwrap_or_else(|_| content_type_header("text/plain"))
+}
+
+fn reject(request: Request, status: u16, message: &str) {
+    let response = Response::from_string(message)
+        .with_status_code(status)
+        .with_header(content_type_header("text/plain"));
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending demo response: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_token() {
+        assert_eq!(split_token("/"), ("/".to_string(), None));
+        assert_eq!(
+            split_token("/?token=abc"),
+            ("/".to_string(), Some("abc".to_string()))
+        );
+        assert_eq!(
+            split_token("/app/?a=1&token=abc&b=2"),
+            ("/app/?a=1&b=2".to_string(), Some("abc".to_string()))
+        );
+        assert_eq!(split_token("/?tokens=1"), ("/?tokens=1".to_string(), None));
+    }
+
+    #[test]
+    fn test_same_token() {
+        assert!(same_token("abc", "abc"));
+        assert!(!same_token("abd", "abc"));
+        assert!(!same_token("ab", "abc"));
+    }
+
+    #[test]
+    fn test_presented_token() {
+        let request: Request = tiny_http::TestRequest::new()
+            .with_header(Header::from_bytes("Cookie", "theme=dark; wasmrun_demo=abc").unwrap())
+            .into();
+        assert_eq!(presented_token(&request), Some("abc"));
+        let request: Request = tiny_http::TestRequest::new()
+            .with_header(Header::from_bytes("Authorization", "Bearer xyz").unwrap())
+            .into();
+        assert_eq!(presented_token(&request), Some("xyz"));
+    }
+}
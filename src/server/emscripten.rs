@@ -0,0 +1,178 @@
+//! Running modules built by Emscripten
+//!
+//! Emscripten writes a module together with a `.js` of the same name that
+//! sets up its runtime: the filesystem, `printf`, the main loop and, for
+//! graphical programs, the canvas. When such glue sits next to the module it
+//! is served with it, and the page gets the shell Emscripten programs expect
+//! from `emcc --shell-file`: a canvas and an output area that `stdout` and
+//! `stderr` are printed to. The glue is loaded as a classic script through
+//! the app page's `window.init` hook, with a `Module` configuration that
+//! instantiates the module from the page so it shows up in the inspector.
+
+use std::fs;
+use std::path::Path;
+
+/// Whether `source` is the runtime Emscripten generates for a module
+pub fn is_emscripten_glue(source: &str) -> bool {
+    source.contains("emscripten") || source.contains("var Module = typeof Module")
+}
+
+/// The glue to serve the module at `wasm_path` with, when Emscripten built it
+pub fn glue_for(wasm_path: &str) -> Option<String> {
+    let glue = Path::new(wasm_path).with_extension("js");
+    let source = fs::read_to_string(&glue).ok()?;
+    is_emscripten_glue(&source).then(|| glue.to_string_lossy().to_string())
+}
+
+/// Whether the program draws, going by what its glue pulls in
+fn draws_to_canvas(source: &str) -> bool {
+    ["GLctx", "SDL.", "_emscripten_set_main_loop", "getContext("]
+        .iter()
+        .any(|marker| source.contains(marker))
+}
+
+/// Add the shell and the loader to a page serving the module at `wasm_path`
+/// as `wasm_filename`, with `js_filename` next to it
+pub fn inject(
+    html: &str,
+    wasm_path: &str,
+    wasm_filename: &str,
+    js_filename: Option<&str>,
+) -> String {
+    let Some(js_filename) = js_filename else {
+        return html.to_string();
+    };
+    let source = Path::new(wasm_path)
+        .parent()
+        .and_then(|dir| fs::read_to_string(dir.join(js_filename)).ok());
+    let Some(source) = source.filter(|source| is_emscripten_glue(source)) else {
+        return html.to_string();
+    };
+
+    let module = serde_json::to_string(wasm_filename)
+        .unwrap_or_default()
+        .replace('<', "\\u003c");
+    let href = js_filename.replace('&', "&amp;").replace('"', "&quot;");
+    // A link rather than a script, so the glue is versioned with the page but runs on `init`
+    let script = format!(
+        "<link rel=\"preload\" href=\"{href}\" as=\"script\" data-wasmrun-emscripten>\n<script>\n{}</script>",
+        LOADER_SCRIPT
+            .replace("__MODULE__", &module)
+            .replace("__CANVAS__", &draws_to_canvas(&source).to_string())
+    );
+    let head = html
+        .find("<head")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+    match head {
+        Some(index) => format!("{}\n{script}{}", &html[..index], &html[index..]),
+        None => format!("{script}\n{html}"),
+    }
+}
+
+const LOADER_SCRIPT: &str = r#"(() => {
+  const shell = (drawing) => {
+    const root = document.getElementById("wasm-app") || document.body;
+    const canvas = document.createElement("canvas");
+    canvas.id = "canvas";
+    canvas.tabIndex = -1;
+    canvas.hidden = !drawing;
+    canvas.style.cssText = "display:block;margin:0 auto;background:#000;max-width:100%";
+    canvas.addEventListener("webglcontextlost", (event) => event.preventDefault());
+    const output = document.createElement("pre");
+    output.id = "output";
+    output.style.cssText = "margin:0.5rem 0;padding:0.5rem;max-height:16rem;overflow:auto;background:#111;color:#ddd;font:12px/1.4 monospace;white-space:pre-wrap";
+    root.append(canvas, output);
+    return { canvas, output };
+  };
+
+  window.init = () => new Promise((resolve, reject) => {
+    const { canvas, output } = shell(__CANVAS__);
+    const print = (level) => (...args) => {
+      const text = args.join(" ");
+      console[level](text);
+      output.textContent += text + "\n";
+      output.scrollTop = output.scrollHeight;
+    };
+    let compiled;
+    const config = {
+      canvas,
+      print: print("log"),
+      printErr: print("error"),
+      setStatus: (status) => status && console.info("wasmrun:", status),
+      // Instantiate here rather than in the glue, to hand the module to the page
+      instantiateWasm(imports, receive) {
+        const response = fetch(__MODULE__);
+        const instantiated = WebAssembly.instantiateStreaming
+          ? WebAssembly.instantiateStreaming(response, imports)
+          : response.then((r) => r.arrayBuffer()).then((bytes) => WebAssembly.instantiate(bytes, imports));
+        instantiated.then(({ instance, module }) => {
+          compiled = module;
+          receive(instance, module);
+        }, reject);
+        return {};
+      },
+      onRuntimeInitialized: () => resolve(compiled),
+      onAbort: (what) => reject(new Error(`Emscripten program aborted: ${what}`)),
+    };
+    window.Module = config;
+
+    const script = document.createElement("script");
+    script.src = document.querySelector("link[data-wasmrun-emscripten]").href;
+    script.onerror = () => reject(new Error(`Could not load ${script.src}`));
+    // Built with -s MODULARIZE, the glue defines a factory instead of running
+    script.onload = () => {
+      if (typeof window.Module === "function") window.Module(config).catch(reject);
+    };
+    document.head.appendChild(script);
+  });
+})();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLUE: &str =
+        "var Module = typeof Module != 'undefined' ? Module : {};\n// emscripten runtime";
+
+    #[test]
+    fn test_glue_is_recognised_next_to_the_module() {
+        let dir = tempfile::tempdir().unwrap();
+        let wasm = dir.path().join("hello.wasm");
+        fs::write(&wasm, b"\0asm\x01\0\0\0").unwrap();
+        assert_eq!(glue_for(wasm.to_str().unwrap()), None);
+
+        fs::write(
+            dir.path().join("hello.js"),
+            "export default function init() {}",
+        )
+        .unwrap();
+        assert_eq!(glue_for(wasm.to_str().unwrap()), None);
+
+        fs::write(dir.path().join("hello.js"), GLUE).unwrap();
+        assert!(glue_for(wasm.to_str().unwrap())
+            .unwrap()
+            .ends_with("hello.js"));
+    }
+
+    #[test]
+    fn test_shell_only_for_emscripten_glue() {
+        let dir = tempfile::tempdir().unwrap();
+        let wasm = dir.path().join("game.wasm");
+        let wasm_path = wasm.to_str().unwrap();
+        fs::write(dir.path().join("app.js"), "export default 1;").unwrap();
+        fs::write(dir.path().join("game.js"), format!("{GLUE}\nvar GLctx;")).unwrap();
+
+        let page = "<html><head><title>x</title></head><body></body></html>";
+        assert_eq!(inject(page, wasm_path, "game.wasm", None), page);
+        assert_eq!(inject(page, wasm_path, "game.wasm", Some("app.js")), page);
+
+        let injected = inject(page, wasm_path, "game.wasm", Some("game.js"));
+        assert!(injected.starts_with(
+            "<html><head>\n<link rel=\"preload\" href=\"game.js\" as=\"script\" data-wasmrun-emscripten>"
+        ));
+        assert!(injected.contains(r#"fetch("game.wasm")"#));
+        assert!(injected.contains("shell(true)"));
+        assert!(injected.find("window.init").unwrap() < injected.find("<title>").unwrap());
+    }
+}
@@ -0,0 +1,282 @@
+//! Reports of modules that failed to compile or instantiate in the browser
+//!
+//! Served pages get a small script that watches every `WebAssembly`
+//! compilation and instantiation. When one fails, it probes which proposals
+//! the browser supports and posts the error to [`REPORT_ROUTE`]. The server
+//! adds what only it knows, the page variant and the headers it sends, and
+//! keeps the latest reports in `~/.wasmrun/failures.json` for `wasmrun why`.
+
+use super::utils::respond;
+use crate::config::{active_settings, WasmrunConfig};
+use crate::error::Result;
+use crate::utils::wasm_features::Proposal;
+use crate::{error_println, info_println, warn_println};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tiny_http::{Method, Request, Response};
+
+pub const REPORT_ROUTE: &str = "/__wasmrun/instantiate-failure";
+
+const FAILURES_FILE: &str = "failures.json";
+
+/// Reports kept on disk, newest last
+const MAX_REPORTS: usize = 20;
+
+/// Failures this server has seen, per module
+static SEEN: Mutex<Vec<(String, usize)>> = Mutex::new(Vec::new());
+
+/// One failed compilation or instantiation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FailureReport {
+    /// Absolute path of the served module
+    pub module: String,
+    pub project: Option<String>,
+    /// Page the module was served with, e.g. `app` or `console`
+    pub page: String,
+    /// Headers the server added to every response
+    pub headers: BTreeMap<String, String>,
+    /// JS error name, e.g. `LinkError`
+    pub error: String,
+    pub message: String,
+    pub user_agent: String,
+    pub secure_context: bool,
+    pub cross_origin_isolated: bool,
+    /// Probe results by [`Proposal::key`]
+    pub supports: BTreeMap<String, bool>,
+    pub reported_at: String,
+}
+
+/// What the page posts; the server fills in the rest
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BrowserReport {
+    error: String,
+    message: String,
+    user_agent: String,
+    secure_context: bool,
+    cross_origin_isolated: bool,
+    #[serde(default)]
+    supports: BTreeMap<String, bool>,
+}
+
+fn failures_path() -> Result<PathBuf> {
+    Ok(WasmrunConfig::config_dir()?.join(FAILURES_FILE))
+}
+
+/// Stored reports, oldest first
+pub fn load() -> Vec<FailureReport> {
+    failures_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn store(report: FailureReport) -> Result<()> {
+    let mut reports = load();
+    reports.push(report);
+    let excess = reports.len().saturating_sub(MAX_REPORTS);
+    reports.drain(..excess);
+    let path = failures_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let json = serde_json::to_string_pretty(&reports).map_err(std::io::Error::from)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// The headers [`respond`] adds to every response right now
+fn served_headers() -> BTreeMap<String, String> {
+    let mut headers = active_settings().server.headers;
+    headers.extend(super::control::runtime_headers());
+    super::isolation::apply(&mut headers);
+    headers
+}
+
+/// Accept a failure posted by a served page
+pub fn receive(mut request: Request, wasm_path: &str, project_path: Option<&str>, page: &str) {
+    if *request.method() != Method::Post {
+        let response = Response::from_string("405 Method Not Allowed").with_status_code(405);
+        if let Err(e) = respond(request, response) {
+            error_println!("Error sending failure report response: {e}");
+        }
+        return;
+    }
+
+    let mut body = String::new();
+    let parsed = request
+        .as_reader()
+        .read_to_string(&mut body)
+        .ok()
+        .and_then(|_| serde_json::from_str::<BrowserReport>(&body).ok());
+    let status = match parsed {
+        Some(browser) => {
+            record(browser, wasm_path, project_path, page);
+            204
+        }
+        None => 400,
+    };
+    if let Err(e) = respond(request, Response::empty(status)) {
+        error_println!("Error sending failure report response: {e}");
+    }
+}
+
+fn record(browser: BrowserReport, wasm_path: &str, project_path: Option<&str>, page: &str) {
+    let module = fs::canonicalize(wasm_path)
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|_| wasm_path.to_string());
+    let name = Path::new(wasm_path)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    error_println!(
+        "The browser could not load {name}: {}: {}",
+        browser.error,
+        browser.message
+    );
+
+    let failures = SEEN.lock().map_or(1, |mut seen| {
+        match seen
+            .iter_mut()
+            .find(|(seen_module, _)| *seen_module == module)
+        {
+            Some((_, count)) => {
+                *count += 1;
+                *count
+            }
+            None => {
+                seen.push((module.clone(), 1));
+                1
+            }
+        }
+    });
+    if failures == 2 {
+        info_println!("💡 {name} keeps failing; `wasmrun why {module}` ranks the likely causes");
+    }
+
+    let report = FailureReport {
+        module,
+        project: project_path.map(str::to_string),
+        page: page.to_string(),
+        headers: served_headers(),
+        error: browser.error,
+        message: browser.message,
+        user_agent: browser.user_agent,
+        secure_context: browser.secure_context,
+        cross_origin_isolated: browser.cross_origin_isolated,
+        supports: browser.supports,
+        reported_at: chrono::Utc::now().to_rfc3339(),
+    };
+    if let Err(e) = store(report) {
+        warn_println!("Could not save the failure report: {e}");
+    }
+}
+
+/// Add the failure reporter to a served page
+pub fn inject(html: &str) -> String {
+    let probes: BTreeMap<&str, Vec<u8>> = Proposal::ALL
+        .into_iter()
+        .map(|proposal| (proposal.key(), proposal.probe()))
+        .collect();
+    let script = format!(
+        "<script>\n{}</script>",
+        REPORTER_SCRIPT.replace("__ROUTE__", REPORT_ROUTE).replace(
+            "__PROBES__",
+            &serde_json::to_string(&probes).unwrap_or_else(|_| "{}".to_string())
+        )
+    );
+    // The wrappers must be in place before any script on the page compiles a module
+    let head = html
+        .find("<head")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+    match head {
+        Some(index) => format!("{}\n{script}{}", &html[..index], &html[index..]),
+        None => format!("{script}\n{html}"),
+    }
+}
+
+const REPORTER_SCRIPT: &str = r#"(() => {
+  const ROUTE = "__ROUTE__";
+  const PROBES = __PROBES__;
+  const reported = new Set();
+
+  function report(error) {
+    const message = String((error && error.message) || error);
+    if (reported.has(message)) return;
+    reported.add(message);
+    const supports = {};
+    for (const [name, bytes] of Object.entries(PROBES)) {
+      try { supports[name] = WebAssembly.validate(new Uint8Array(bytes)); } catch { supports[name] = false; }
+    }
+    fetch(ROUTE, {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({
+        error: (error && error.name) || "Error",
+        message,
+        userAgent: navigator.userAgent,
+        secureContext: window.isSecureContext,
+        crossOriginIsolated: window.crossOriginIsolated === true,
+        supports,
+      }),
+    }).catch(() => {});
+  }
+
+  const watch = (name) => {
+    const original = WebAssembly[name];
+    if (!original) return;
+    WebAssembly[name] = function (...args) {
+      return original.apply(this, args).catch((error) => {
+        report(error);
+        throw error;
+      });
+    };
+  };
+  ["compile", "compileStreaming", "instantiate", "instantiateStreaming"].forEach(watch);
+
+  for (const name of ["Module", "Instance"]) {
+    WebAssembly[name] = new Proxy(WebAssembly[name], {
+      construct(target, args) {
+        try {
+          return new target(...args);
+        } catch (error) {
+          report(error);
+          throw error;
+        }
+      },
+    });
+  }
+})();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inject_into_head() {
+        let page = inject("<html><head><title>t</title></head><body></body></html>");
+        let script = page.find("<script>").unwrap();
+        assert!(page.find("<head>").unwrap() < script);
+        assert!(script < page.find("<title>").unwrap());
+        assert!(page.contains(r#"const ROUTE = "/__wasmrun/instantiate-failure";"#));
+        assert!(page.contains(r#""simd":[0,97,115,109"#));
+    }
+
+    #[test]
+    fn test_browser_report_parses() {
+        let report: BrowserReport = serde_json::from_str(
+            r#"{"error":"LinkError","message":"Import #0 \"env\" \"f\": function import requires a callable",
+                "userAgent":"Test","secureContext":true,"crossOriginIsolated":false,
+                "supports":{"simd":true}}"#,
+        )
+        .unwrap();
+        assert_eq!(report.error, "LinkError");
+        assert_eq!(report.supports.get("simd"), Some(&true));
+    }
+}
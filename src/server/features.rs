@@ -0,0 +1,190 @@
+//! Switching Cargo feature sets from the runner page
+//!
+//! When `wasmrun.toml` configures `[build.feature_sets]`, the served page gets
+//! a small panel listing them. Choosing one selects it for every later build
+//! and rebuilds the project; open pages reload once the build is done, so a
+//! module can be compared with and without the features side by side.
+
+use super::control;
+use super::utils::{content_type_header, respond};
+use crate::compiler::cargo_features;
+use crate::{error_println, info_println, success_println};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tiny_http::{Method, Request, Response};
+
+/// Routes for listing feature sets and switching between them
+pub const FEATURES_ROUTE: &str = "/__wasmrun/features";
+
+/// Set while a rebuild started from the page is running
+static REBUILDING: AtomicBool = AtomicBool::new(false);
+
+/// Answer a request under [`FEATURES_ROUTE`]; `rest` is the URL after the route
+pub fn handle(request: Request, rest: &str, wasm_path: &str, project_path: Option<&str>) {
+    let name = rest.split('?').next().unwrap_or("").trim_start_matches('/');
+    match (request.method().clone(), name) {
+        (Method::Get, "") => send(request, 200, state()),
+        (Method::Post | Method::Put, name) if !name.is_empty() => {
+            match super::site::percent_decode(name) {
+                Some(name) => switch(request, Some(name), wasm_path, project_path),
+                None => send(request, 400, "Invalid feature set name".to_string()),
+            }
+        }
+        (Method::Delete, "") => switch(request, None, wasm_path, project_path),
+        _ => send(request, 400, "Unsupported feature set request".to_string()),
+    }
+}
+
+/// The configured sets, the active one, and whether a rebuild is running, as JSON
+fn state() -> String {
+    serde_json::json!({
+        "sets": cargo_features::configured(),
+        "active": cargo_features::active().map(|set| set.name),
+        "rebuilding": REBUILDING.load(Ordering::Relaxed),
+    })
+    .to_string()
+}
+
+/// Select a feature set and rebuild with it, answering once the build has finished
+fn switch(request: Request, name: Option<String>, wasm_path: &str, project_path: Option<&str>) {
+    let Some(project_path) = project_path.map(str::to_string) else {
+        send(
+            request,
+            400,
+            "This server is serving a WASM file, not a project".to_string(),
+        );
+        return;
+    };
+    if REBUILDING.swap(true, Ordering::SeqCst) {
+        send(request, 409, "A rebuild is already running".to_string());
+        return;
+    }
+    let previous = cargo_features::active().map(|set| set.name);
+    if let Err(error) = cargo_features::select(name.as_deref()) {
+        REBUILDING.store(false, Ordering::SeqCst);
+        send(request, 400, error);
+        return;
+    }
+
+    let features = cargo_features::active_features();
+    info_println!(
+        "🧩 Switching to {}, rebuilding...",
+        match &name {
+            Some(name) => format!("feature set '{name}' ({})", features.join(", ")),
+            None => "the default features".to_string(),
+        }
+    );
+    // Build on its own thread so pages and assets are still served meanwhile
+    let wasm_path = wasm_path.to_string();
+    std::thread::spawn(move || {
+        let result = control::rebuild_project(Some(&project_path), &wasm_path);
+        if result.is_err() {
+            // Keep building what is actually being served
+            let _ = cargo_features::select(previous.as_deref());
+        }
+        REBUILDING.store(false, Ordering::SeqCst);
+        match result {
+            Ok(message) => {
+                success_println!("{message}");
+                send(request, 200, state());
+            }
+            Err(error) => {
+                error_println!("{error}");
+                send(request, 500, error);
+            }
+        }
+    });
+}
+
+fn send(request: Request, status: u16, body: String) {
+    let content_type = if status == 200 {
+        "application/json"
+    } else {
+        "text/plain"
+    };
+    let response = Response::from_string(body)
+        .with_status_code(status)
+        .with_header(content_type_header(content_type));
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending feature set response: {e}");
+    }
+}
+
+/// Add the feature set panel to a served page when the project configures any
+pub fn inject(html: &str, project_path: Option<&str>) -> String {
+    if project_path.is_none() || cargo_features::configured().is_empty() {
+        return html.to_string();
+    }
+    let script = format!("<script>\n{PANEL_SCRIPT}</script>");
+    match html.rfind("</body>") {
+        Some(index) => format!("{}{script}\n{}", &html[..index], &html[index..]),
+        None => format!("{html}\n{script}"),
+    }
+}
+
+const PANEL_SCRIPT: &str = r#"(() => {
+  const ROUTE = "/__wasmrun/features";
+  const host = document.createElement("div");
+  host.setAttribute("data-wasmrun-features", "");
+  const root = host.attachShadow({ mode: "open" });
+  root.innerHTML = `<style>
+    .bar { position: fixed; left: 12px; bottom: 12px; z-index: 2147483647; display: flex; gap: 4px; align-items: center;
+      font: 12px system-ui, sans-serif; color: #e2e8f0; background: #1e293bee; padding: 6px 8px; border-radius: 6px; }
+    select { font: inherit; color: inherit; background: #334155; border: 0; border-radius: 4px; padding: 3px 6px; cursor: pointer; }
+    select:disabled { opacity: 0.5; cursor: default; }
+    .status { opacity: 0.8; }
+  </style>
+  <div class="bar" role="toolbar" aria-label="Cargo features">
+    <label for="set">Features</label>
+    <select id="set"></select>
+    <span class="status" id="status" role="status"></span>
+  </div>`;
+  const select = root.getElementById("set");
+  const status = (text) => { root.getElementById("status").textContent = text; };
+
+  function show(state) {
+    select.replaceChildren(new Option("default", ""));
+    state.sets.forEach((set) => {
+      const option = new Option(set.name, set.name, false, set.name === state.active);
+      option.title = set.features.join(", ");
+      select.add(option);
+    });
+    select.disabled = state.rebuilding;
+    status(state.rebuilding ? "rebuilding…" : "");
+  }
+
+  select.addEventListener("change", async () => {
+    const name = select.value;
+    select.disabled = true;
+    status("rebuilding…");
+    const response = await fetch(name ? ROUTE + "/" + encodeURIComponent(name) : ROUTE, { method: name ? "POST" : "DELETE" })
+      .catch((error) => ({ ok: false, text: async () => String(error) }));
+    if (response.ok) {
+      // The page reloads with the new build; show the state until it does
+      show(await response.json());
+      status("rebuilt");
+    } else {
+      status(await response.text());
+      fetch(ROUTE).then((response) => response.json()).then(show).catch(() => {});
+    }
+  });
+
+  fetch(ROUTE).then((response) => response.json()).then((state) => {
+    show(state);
+    document.body.appendChild(host);
+  }).catch(() => {});
+})();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inject_needs_a_project_with_feature_sets() {
+        let html = "<html><body></body></html>";
+        assert_eq!(inject(html, None), html);
+        // No wasmrun.toml is active in tests, so nothing is configured
+        assert_eq!(inject(html, Some(".")), html);
+        assert!(state().contains(r#""sets":[]"#));
+    }
+}
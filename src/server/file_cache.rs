@@ -0,0 +1,241 @@
+//! Served files kept in memory
+//!
+//! A reload fetches the page's module and assets again, and a large module
+//! would otherwise be read from disk every time. Files are kept by path with
+//! their content type and content hash, up to `server.memory_cache_mb`
+//! (256 MB by default, 0 turns the cache off), and the least recently used
+//! are dropped when it fills. The watcher empties the cache when files
+//! change; a file whose size or modification time no longer matches is read
+//! again anyway, so edits made while nothing watches are picked up too.
+//! Files with the same content, such as a chunk shared by several apps, are
+//! held once.
+
+use super::cache_bust;
+use crate::config::active_settings;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Memory the cache may use when `server.memory_cache_mb` is not set
+pub const DEFAULT_CAPACITY_MB: u64 = 256;
+
+/// A file taking more than this share of the capacity is streamed from disk
+const LARGEST_FILE_SHARE: u64 = 4;
+
+static CACHE: Mutex<Option<Cache>> = Mutex::new(None);
+
+#[derive(Debug)]
+struct Entry {
+    bytes: Arc<[u8]>,
+    content_type: String,
+    hash: String,
+    /// Size and modification time of the file the bytes were read from
+    size: u64,
+    modified: SystemTime,
+    /// When the entry was last served, as a count of cache reads
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Cache {
+    entries: HashMap<PathBuf, Entry>,
+    reads: u64,
+}
+
+impl Cache {
+    /// The cached bytes of `path`, when still current
+    fn get(&mut self, path: &Path, metadata: &Metadata, content_type: &str) -> Option<Arc<[u8]>> {
+        self.reads += 1;
+        let modified = metadata.modified().ok()?;
+        let entry = self.entries.get_mut(path)?;
+        if entry.size != metadata.len()
+            || entry.modified != modified
+            || entry.content_type != content_type
+        {
+            self.entries.remove(path);
+            return None;
+        }
+        entry.last_used = self.reads;
+        Some(Arc::clone(&entry.bytes))
+    }
+
+    /// Keep `bytes` for `path`, dropping the least recently used entries to
+    /// stay within `capacity` bytes
+    fn insert(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        content_type: &str,
+        bytes: Vec<u8>,
+        capacity: u64,
+    ) -> Arc<[u8]> {
+        let hash = cache_bust::hash_bytes(&bytes);
+        // Another path with the same content shares its bytes
+        let bytes = self
+            .entries
+            .values()
+            .find(|entry| entry.hash == hash)
+            .map(|entry| Arc::clone(&entry.bytes))
+            .unwrap_or_else(|| Arc::from(bytes));
+        let entry = Entry {
+            bytes: Arc::clone(&bytes),
+            content_type: content_type.to_string(),
+            hash,
+            size: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            last_used: self.reads,
+        };
+        self.entries.insert(path.to_path_buf(), entry);
+
+        while self.memory() > capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(path, _)| path.clone());
+            match oldest {
+                Some(oldest) => self.entries.remove(&oldest),
+                None => break,
+            };
+        }
+        bytes
+    }
+
+    /// Bytes held, counting shared content once
+    fn memory(&self) -> u64 {
+        let mut seen = HashSet::new();
+        self.entries
+            .values()
+            .filter(|entry| seen.insert(entry.hash.as_str()))
+            .map(|entry| entry.bytes.len() as u64)
+            .sum()
+    }
+}
+
+/// Memory the cache may use, in bytes
+pub fn capacity() -> u64 {
+    active_settings()
+        .server
+        .memory_cache_mb
+        .unwrap_or(DEFAULT_CAPACITY_MB)
+        .saturating_mul(1024 * 1024)
+}
+
+/// The contents of the file at `path`, served as `content_type`, from memory
+/// or read and kept; `None` when the file should be streamed from disk
+/// instead, because it is too large to keep or the cache is off
+pub fn read(path: &Path, metadata: &Metadata, content_type: &str) -> Option<Arc<[u8]>> {
+    let capacity = capacity();
+    if metadata.len() > capacity / LARGEST_FILE_SHARE {
+        return None;
+    }
+    if let Some(bytes) =
+        CACHE
+            .lock()
+            .ok()?
+            .get_or_insert_with(Cache::default)
+            .get(path, metadata, content_type)
+    {
+        return Some(bytes);
+    }
+
+    // Read without holding the lock, so other files are served meanwhile
+    let bytes = fs::read(path).ok()?;
+    if bytes.len() as u64 != metadata.len() {
+        // Changed while being read; serve what is there without keeping it
+        return Some(Arc::from(bytes));
+    }
+    let mut cache = CACHE.lock().ok()?;
+    Some(cache.get_or_insert_with(Cache::default).insert(
+        path,
+        metadata,
+        content_type,
+        bytes,
+        capacity,
+    ))
+}
+
+/// Forget every cached file and its compressed forms, e.g. after the
+/// watcher saw changes
+pub fn invalidate() {
+    if let Ok(mut cache) = CACHE.lock() {
+        *cache = None;
+    }
+    super::compression::invalidate();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(dir: &Path, name: &str, contents: &str) -> (PathBuf, Metadata) {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+        (path, metadata)
+    }
+
+    #[test]
+    fn test_least_recently_used_are_evicted() {
+        let dir = tempdir().unwrap();
+        let mut cache = Cache::default();
+        let (a, a_meta) = write(dir.path(), "a.wasm", "aaaa");
+        let (b, b_meta) = write(dir.path(), "b.wasm", "bbbb");
+        let (c, c_meta) = write(dir.path(), "c.wasm", "cccc");
+
+        cache.insert(&a, &a_meta, "application/wasm", b"aaaa".to_vec(), 8);
+        cache.insert(&b, &b_meta, "application/wasm", b"bbbb".to_vec(), 8);
+        assert!(cache.get(&a, &a_meta, "application/wasm").is_some());
+        // b was used least recently, so it makes room for c
+        cache.insert(&c, &c_meta, "application/wasm", b"cccc".to_vec(), 8);
+        assert!(cache.get(&b, &b_meta, "application/wasm").is_none());
+        assert_eq!(
+            &*cache.get(&a, &a_meta, "application/wasm").unwrap(),
+            b"aaaa"
+        );
+        assert!(cache.get(&c, &c_meta, "application/wasm").is_some());
+        assert_eq!(cache.memory(), 8);
+    }
+
+    #[test]
+    fn test_changed_file_is_read_again() {
+        let dir = tempdir().unwrap();
+        let mut cache = Cache::default();
+        let (path, metadata) = write(dir.path(), "app.wasm", "old");
+        cache.insert(&path, &metadata, "application/wasm", b"old".to_vec(), 64);
+        assert!(cache.get(&path, &metadata, "application/wasm").is_some());
+        assert!(cache.get(&path, &metadata, "text/plain").is_none());
+
+        cache.insert(&path, &metadata, "application/wasm", b"old".to_vec(), 64);
+        let (_, changed) = write(dir.path(), "app.wasm", "newer");
+        assert!(cache.get(&path, &changed, "application/wasm").is_none());
+        assert!(cache.entries.is_empty());
+    }
+
+    #[test]
+    fn test_identical_files_are_held_once() {
+        let dir = tempdir().unwrap();
+        let mut cache = Cache::default();
+        let (shop, shop_meta) = write(dir.path(), "shop.wasm", "vendor");
+        let (blog, blog_meta) = write(dir.path(), "blog.wasm", "vendor");
+        let first = cache.insert(
+            &shop,
+            &shop_meta,
+            "application/wasm",
+            b"vendor".to_vec(),
+            64,
+        );
+        let second = cache.insert(
+            &blog,
+            &blog_meta,
+            "application/wasm",
+            b"vendor".to_vec(),
+            64,
+        );
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.memory(), 6);
+    }
+}
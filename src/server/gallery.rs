@@ -0,0 +1,293 @@
+//! Gallery mode (`wasmrun serve <dir> --gallery`)
+//!
+//! Instead of running one entry, `/` lists every wasm module in the directory
+//! as a card, and each card links to the runner page for its module at
+//! `/?module=<path>`. Cards take their title, description and screenshot from
+//! a `module.toml` next to the module, or from `<name>.module.toml` when a
+//! directory holds several modules. The directory is scanned on every visit,
+//! so demos added while the server runs show up on reload.
+
+use super::compare::escape_html;
+use super::site;
+use crate::utils::CommandExecutor;
+use crate::warn_println;
+use serde::Deserialize;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Metadata file shared by the modules in a directory
+pub const METADATA_FILE: &str = "module.toml";
+
+/// Query parameter naming the module a run page is for
+const MODULE_PARAM: &str = "module";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// What a `module.toml` says about a module
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModuleMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Image relative to the metadata file
+    pub screenshot: Option<String>,
+}
+
+/// A module listed in the gallery
+#[derive(Debug, Clone, PartialEq)]
+pub struct GalleryModule {
+    pub wasm_path: PathBuf,
+    /// URL path relative to the served root, without the leading slash
+    pub url: String,
+    /// wasm-bindgen JS glue next to the module
+    pub js_path: Option<PathBuf>,
+    pub metadata: ModuleMetadata,
+    /// URL path of the screenshot, when it exists under the root
+    pub screenshot_url: Option<String>,
+}
+
+impl GalleryModule {
+    fn new(root: &Path, wasm_path: PathBuf) -> Self {
+        let (metadata, metadata_dir) = metadata_for(&wasm_path);
+        let screenshot_url = metadata
+            .screenshot
+            .as_deref()
+            .map(|screenshot| metadata_dir.join(screenshot))
+            .filter(|screenshot| screenshot.is_file())
+            .and_then(|screenshot| fs::canonicalize(screenshot).ok())
+            .filter(|screenshot| screenshot.starts_with(root))
+            .map(|screenshot| url_path(root, &screenshot));
+        Self {
+            url: url_path(root, &wasm_path),
+            js_path: site::glue_for(&wasm_path),
+            wasm_path,
+            metadata,
+            screenshot_url,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        self.metadata.title.clone().unwrap_or_else(|| {
+            let stem = self
+                .wasm_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            stem.strip_suffix("_bg").unwrap_or(&stem).to_string()
+        })
+    }
+
+    /// Where the card links to
+    pub fn run_url(&self) -> String {
+        format!("/?{MODULE_PARAM}={}", percent_encode(&self.url))
+    }
+}
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Every wasm module under `root`, in path order
+pub fn modules(root: &Path) -> Vec<GalleryModule> {
+    let mut files = Vec::new();
+    site::collect_files(root, 0, &mut files);
+    files
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+        .map(|path| GalleryModule::new(root, path))
+        .collect()
+}
+
+/// The module a gallery run page URL (`/?module=<path>`) is for
+pub fn module_for(url: &str) -> Option<GalleryModule> {
+    if !is_enabled() {
+        return None;
+    }
+    let query = url.strip_prefix("/?")?;
+    let path = query.split('&').find_map(|pair| {
+        pair.strip_prefix(MODULE_PARAM)
+            .and_then(|rest| rest.strip_prefix('='))
+    })?;
+    let root = site::root()?;
+    let wasm_path = site::resolve(&root, &format!("/{path}"))?;
+    wasm_path
+        .extension()
+        .is_some_and(|ext| ext == "wasm")
+        .then(|| GalleryModule::new(&root, wasm_path))
+}
+
+/// The metadata for `wasm_path` and the directory its paths are relative to
+pub(super) fn metadata_for(wasm_path: &Path) -> (ModuleMetadata, PathBuf) {
+    let dir = wasm_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    let stem = wasm_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let candidates = [
+        dir.join(format!("{stem}.{METADATA_FILE}")),
+        dir.join(METADATA_FILE),
+    ];
+    let Some(file) = candidates.iter().find(|file| file.is_file()) else {
+        return (ModuleMetadata::default(), dir);
+    };
+    let metadata = fs::read_to_string(file)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| toml::from_str(&contents).map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| {
+            warn_println!("Ignoring {}: {e}", file.display());
+            ModuleMetadata::default()
+        });
+    (metadata, dir)
+}
+
+fn url_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Escape everything in a URL path but unreserved characters and `/`
+pub(super) fn percent_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+/// The card index for the modules under `root`
+pub fn index_page(root: &Path) -> String {
+    let modules = modules(root);
+    let name = root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| root.display().to_string());
+
+    let mut cards = String::new();
+    for module in &modules {
+        let screenshot = match &module.screenshot_url {
+            Some(url) => format!(
+                r#"<img src="/{}" alt="" loading="lazy">"#,
+                escape_html(&percent_encode(url))
+            ),
+            None => r#"<div class="placeholder">.wasm</div>"#.to_string(),
+        };
+        let description = module
+            .metadata
+            .description
+            .as_deref()
+            .map(|description| format!("<p>{}</p>", escape_html(description)))
+            .unwrap_or_default();
+        let size = fs::metadata(&module.wasm_path)
+            .map(|metadata| CommandExecutor::format_file_size(metadata.len()))
+            .unwrap_or_default();
+        let _ = writeln!(
+            cards,
+            r#"<a class="card" href="{href}">{screenshot}<div class="body"><h2>{title}</h2>{description}<span class="meta">{path} · {size}</span></div></a>"#,
+            href = escape_html(&module.run_url()),
+            title = escape_html(&module.title()),
+            path = escape_html(&module.url),
+        );
+    }
+    if modules.is_empty() {
+        cards.push_str(r#"<p class="empty">No .wasm modules found in this directory.</p>"#);
+    }
+
+    GALLERY_PAGE
+        .replace("$TITLE$", &escape_html(&name))
+        .replace("$COUNT$", &modules.len().to_string())
+        .replace("<!-- @cards -->", &cards)
+}
+
+const GALLERY_PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Wasmrun - $TITLE$</title>
+<style>
+  body { margin: 0; font-family: system-ui, sans-serif; background: #0f172a; color: #e2e8f0; }
+  header { padding: 2rem 2rem 1rem; }
+  header h1 { margin: 0; font-size: 1.6rem; }
+  header p { margin: 0.3rem 0 0; color: #94a3b8; }
+  main { display: grid; grid-template-columns: repeat(auto-fill, minmax(260px, 1fr)); gap: 1.25rem; padding: 1rem 2rem 2rem; }
+  .card { display: flex; flex-direction: column; border-radius: 10px; overflow: hidden; background: #1e293b; color: inherit; text-decoration: none; border: 1px solid #334155; }
+  .card:hover, .card:focus { border-color: #38bdf8; }
+  .card img, .placeholder { width: 100%; aspect-ratio: 16 / 9; object-fit: cover; background: #0b1220; }
+  .placeholder { display: flex; align-items: center; justify-content: center; color: #475569; font-family: ui-monospace, monospace; font-size: 1.4rem; }
+  .body { padding: 0.9rem 1rem 1rem; }
+  .body h2 { margin: 0 0 0.4rem; font-size: 1.1rem; }
+  .body p { margin: 0 0 0.6rem; color: #cbd5e1; font-size: 0.95rem; }
+  .meta { color: #64748b; font-size: 0.8rem; font-family: ui-monospace, monospace; }
+  .empty { color: #94a3b8; }
+</style>
+</head>
+<body>
+<header><h1>$TITLE$</h1><p>$COUNT$ WebAssembly modules</p></header>
+<main>
+<!-- @cards -->
+</main>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_modules_with_metadata() {
+        let dir = tempdir().unwrap();
+        write(dir.path(), "clock/clock.wasm", "\0asm");
+        write(
+            dir.path(),
+            "clock/module.toml",
+            "title = \"Clock\"\ndescription = \"Ticks <fast>\"\nscreenshot = \"shot.png\"\n",
+        );
+        write(dir.path(), "clock/shot.png", "png");
+        write(dir.path(), "tools/a b.wasm", "\0asm");
+        write(dir.path(), "tools/hash.wasm", "\0asm");
+        write(dir.path(), "tools/hash.module.toml", "title = \"Hasher\"\n");
+        let root = fs::canonicalize(dir.path()).unwrap();
+
+        let modules = modules(&root);
+        let titles: Vec<String> = modules.iter().map(GalleryModule::title).collect();
+        assert_eq!(titles, ["Clock", "a b", "Hasher"]);
+        assert_eq!(modules[0].screenshot_url.as_deref(), Some("clock/shot.png"));
+        assert_eq!(modules[1].run_url(), "/?module=tools/a%20b.wasm");
+
+        let page = index_page(&root);
+        assert!(page.contains("<p>Ticks &lt;fast&gt;</p>"));
+        assert!(page.contains(r#"<img src="/clock/shot.png""#));
+        assert!(page.contains("3 WebAssembly modules"));
+    }
+
+    #[test]
+    fn test_invalid_metadata_is_ignored() {
+        let dir = tempdir().unwrap();
+        write(dir.path(), "app.wasm", "\0asm");
+        write(dir.path(), "module.toml", "title = 3\n");
+        let root = fs::canonicalize(dir.path()).unwrap();
+        assert_eq!(modules(&root)[0].title(), "app");
+    }
+}
@@ -0,0 +1,117 @@
+//! Running modules built by Go or TinyGo
+//!
+//! Such a module imports its runtime from the `gojs` namespace (`go` before
+//! Go 1.12), which only the `wasm_exec.js` of the toolchain that built it
+//! provides. When that file sits next to the module, it is served as the
+//! module's glue and the page gets a loader that creates a `Go` instance,
+//! instantiates the module with its imports and starts `main`. The app page
+//! picks the loader up through the same `window.init` hook wasm-bindgen
+//! glue uses.
+
+use crate::utils::ModuleInspection;
+use std::path::Path;
+
+/// The runtime shipped with Go and TinyGo
+pub const WASM_EXEC: &str = "wasm_exec.js";
+
+/// Whether the module at `wasm_path` needs a Go runtime
+pub fn is_go_module(wasm_path: &Path) -> bool {
+    ModuleInspection::from_file(wasm_path).is_ok_and(|inspection| {
+        inspection
+            .imports
+            .iter()
+            .any(|import| matches!(import.module.as_str(), "gojs" | "go"))
+    })
+}
+
+/// The runtime to serve the module at `wasm_path` with, when it is a Go module
+pub fn wasm_exec_for(wasm_path: &str) -> Option<String> {
+    let path = Path::new(wasm_path);
+    let wasm_exec = path.parent()?.join(WASM_EXEC);
+    (wasm_exec.is_file() && is_go_module(path)).then(|| wasm_exec.to_string_lossy().to_string())
+}
+
+/// Whether `js_path` is a Go runtime rather than wasm-bindgen glue
+pub fn is_wasm_exec(js_path: &str) -> bool {
+    Path::new(js_path)
+        .file_name()
+        .is_some_and(|name| name == WASM_EXEC)
+}
+
+/// Add the runtime and the loader to a page serving `wasm_filename` with `js_filename`
+pub fn inject(html: &str, wasm_filename: &str, js_filename: Option<&str>) -> String {
+    if js_filename != Some(WASM_EXEC) {
+        return html.to_string();
+    }
+    let module = serde_json::to_string(wasm_filename)
+        .unwrap_or_default()
+        .replace('<', "\\u003c");
+    let script = format!(
+        "<script src=\"{WASM_EXEC}\"></script>\n<script>\n{}</script>",
+        LOADER_SCRIPT.replace("__MODULE__", &module)
+    );
+    // Ahead of the page's own scripts, which call `window.init` once loaded
+    let head = html
+        .find("<head")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+    match head {
+        Some(index) => format!("{}\n{script}{}", &html[..index], &html[index..]),
+        None => format!("{script}\n{html}"),
+    }
+}
+
+const LOADER_SCRIPT: &str = r#"window.init = async () => {
+  const go = new Go();
+  const response = fetch(__MODULE__);
+  const { instance, module } = WebAssembly.instantiateStreaming
+    ? await WebAssembly.instantiateStreaming(response, go.importObject)
+    : await WebAssembly.instantiate(await (await response).arrayBuffer(), go.importObject);
+  // `main` often never returns, e.g. when it blocks to keep callbacks alive
+  go.run(instance).then(
+    () => console.info("wasmrun: Go program exited"),
+    (error) => console.error("wasmrun: Go program failed:", error),
+  );
+  return module;
+};
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loader_only_for_wasm_exec() {
+        let page = "<html><head><title>x</title></head><body></body></html>";
+        assert_eq!(inject(page, "app_bg.wasm", Some("app.js")), page);
+        assert_eq!(inject(page, "main.wasm", None), page);
+
+        let injected = inject(page, "main.wasm", Some(WASM_EXEC));
+        assert!(injected.starts_with("<html><head>\n<script src=\"wasm_exec.js\"></script>"));
+        assert!(injected.contains(r#"fetch("main.wasm")"#));
+        assert!(injected.find("window.init").unwrap() < injected.find("<title>").unwrap());
+        assert!(is_wasm_exec("/out/wasm_exec.js"));
+        assert!(!is_wasm_exec("/out/app.js"));
+    }
+
+    #[test]
+    fn test_go_modules_are_recognised_by_their_imports() {
+        let dir = tempfile::tempdir().unwrap();
+        let go = wat::parse_str(
+            r#"(module (import "gojs" "runtime.wasmExit" (func (param i32))) (memory (export "mem") 1))"#,
+        )
+        .unwrap();
+        let wasm = dir.path().join("main.wasm");
+        std::fs::write(&wasm, &go).unwrap();
+        assert!(is_go_module(&wasm));
+        assert_eq!(wasm_exec_for(wasm.to_str().unwrap()), None);
+
+        std::fs::write(dir.path().join(WASM_EXEC), "// runtime").unwrap();
+        assert!(wasm_exec_for(wasm.to_str().unwrap())
+            .unwrap()
+            .ends_with(WASM_EXEC));
+
+        let plain = dir.path().join("plain.wasm");
+        std::fs::write(&plain, wat::parse_str("(module)").unwrap()).unwrap();
+        assert!(!is_go_module(&plain));
+    }
+}
@@ -1,10 +1,51 @@
 use std::fs;
 use std::path::Path;
+use std::sync::Mutex;
 use tiny_http::{Request, Response};
 
+use super::a11y;
 use super::api::{serve_asset, serve_file, serve_module_info, serve_version_info};
-use super::utils::{content_type_header, determine_content_type};
+use super::asc;
+use super::base_path::{self, Route};
+use super::build_status;
+use super::cache_bust::{self, Versions};
+use super::cluster;
+use super::component;
+use super::console;
+use super::control;
+use super::dedup;
+use super::delta;
+use super::demo;
+use super::emscripten;
+use super::failures;
+use super::features;
+use super::gallery;
+use super::gojs;
+use super::import_map::{resolve_node_module, ImportMap, NODE_MODULES_PREFIX};
+use super::limits;
+use super::listing;
+use super::live_reload;
+use super::loading;
+use super::metrics;
+use super::middleware::{self, RequestAction};
+use super::mime;
+use super::plugin_routes;
+use super::preview;
+use super::profiler;
+use super::proxy;
+use super::pyodide;
+use super::replay;
+use super::shutdown;
+use super::site;
+use super::status;
+use super::toggles::Toggles;
+use super::traps;
+use super::utils::{content_type_header, determine_content_type, respond};
+use crate::plugin::events::{self, Event};
+use crate::self_profile::{self, Subsystem};
 use crate::template::{TemplateManager, TemplateType};
+use crate::utils::typescript;
+use crate::{error_println, request_println};
 
 /// Handle an incoming HTTP request
 #[allow(clippy::too_many_arguments)]
@@ -15,74 +56,298 @@ pub fn handle_request(
     wasm_path: &str,
     project_path: Option<&str>,
     watch_mode: bool,
-    clients_to_reload: &mut Vec<String>,
+    clients_to_reload: &Mutex<Vec<String>>,
     template_manager: &TemplateManager,
     template_type: &TemplateType,
 ) {
-    let url = request.url().to_string();
+    let _span = self_profile::span(Subsystem::Server);
+    let Some((request, slot)) = limits::admit(request) else {
+        return;
+    };
+    let Some(request) = demo::admit(request) else {
+        return;
+    };
+    let base = base_path::active();
+    let url = match base_path::route(request.url(), &base) {
+        Route::Path(url) => cache_bust::strip(url),
+        Route::Redirect(location) => {
+            base_path::redirect(request, &location);
+            return;
+        }
+    };
+    if url == shutdown::SHUTDOWN_ROUTE {
+        shutdown::handle(request);
+        return;
+    }
+    let url = match middleware::on_request(&request, url) {
+        RequestAction::Continue(url) => url,
+        RequestAction::Respond(mock) => {
+            request_println!("🧩 Middleware answered request for: {}", request.url());
+            if let Err(e) = respond(request, mock.into_response()) {
+                error_println!("Error sending middleware response: {e}");
+            }
+            return;
+        }
+    };
+    let Some(request) = proxy::forward(request, &url) else {
+        return;
+    };
+    let Some(request) = plugin_routes::forward(request, &url) else {
+        return;
+    };
     let client_addr = match request.remote_addr() {
         Some(addr) => addr.to_string(),
         None => "unknown".to_string(),
     };
 
-    println!("📝 Received request for: {url}");
+    request_println!("📝 Received request for: {url}");
+
+    let gallery_module = gallery::module_for(&url);
+    // The page may carry toggles such as `?debug=1` in its query
+    let page_requested = url.split('?').next() == Some("/");
+    if page_requested && gallery::is_enabled() && gallery_module.is_none() {
+        let root = site::root().unwrap_or_default();
+        let html = base_path::rewrite_html(&gallery::index_page(&root), &base);
+        let response = Response::from_string(html).with_header(content_type_header("text/html"));
+        if let Err(e) = respond(request, response) {
+            error_println!("Error sending gallery page: {e}");
+        }
+    } else if page_requested || gallery_module.is_some() {
+        let (toggles, toggles_changed) = Toggles::for_page(&request, &url);
+        // A gallery card's run page is the runner page of its module
+        let gallery_page = gallery_module.as_ref().map(|module| {
+            let template_type = if module.js_path.is_some() {
+                TemplateType::App
+            } else {
+                TemplateType::Console
+            };
+            (
+                module.url.as_str(),
+                module.wasm_path.to_string_lossy(),
+                template_type,
+            )
+        });
+        let (wasm_filename, wasm_path, template_type) = match &gallery_page {
+            Some((url, path, template_type)) => (*url, path.as_ref(), template_type),
+            None => (wasm_filename, wasm_path, template_type),
+        };
 
-    if url == "/" {
         // Serve the main HTML page
-        let html = if watch_mode {
+        let html = if let Some(index) = site::index_html().filter(|_| gallery_page.is_none()) {
+            Ok(index)
+        } else if let Some(page) = component::page() {
+            Ok(page)
+        } else if watch_mode {
             template_manager.generate_html_with_watch_mode(template_type, wasm_filename, true)
         } else {
             template_manager.generate_html(template_type, wasm_filename)
         };
 
         let html = match html {
-            Ok(html) => html,
+            Ok(html) => {
+                let mut versions = Versions::for_output(wasm_path);
+                if let Some(dir) = wasm_filename.rsplit_once('/').map(|(dir, _)| dir) {
+                    // Directory mode serves nested entries at their path below the page
+                    versions = versions.under(dir);
+                }
+                let mut import_map = ImportMap::for_project(wasm_path, project_path);
+                import_map.imports.extend(versions.import_entries());
+                // Gallery cards bring their own glue, if any
+                let glue = js_filename.filter(|_| gallery_page.is_none());
+                let html = emscripten::inject(
+                    &gojs::inject(&html, wasm_filename, glue),
+                    wasm_path,
+                    wasm_filename,
+                    glue,
+                );
+                let html = pyodide::inject(
+                    &asc::inject(&html, wasm_path, wasm_filename, glue),
+                    wasm_path,
+                    wasm_filename,
+                    glue,
+                );
+                let page =
+                    a11y::inject(&import_map.inject(&delta::inject(&versions.inject(&html))));
+                let page = if demo::is_enabled() {
+                    // Visitors of a demo get the app without the development panels
+                    live_reload::inject(&page)
+                } else {
+                    let page = failures::inject(&profiler::inject(
+                        &live_reload::inject(&build_status::inject(&features::inject(
+                            &replay::inject(&page, wasm_filename),
+                            project_path,
+                        ))),
+                        toggles.profile,
+                    ));
+                    let page = console::inject(&traps::inject(&page, toggles.break_on_trap));
+                    toggles.inject(&page, wasm_filename)
+                };
+                // Last, so the loader runs before the other scripts wrap WebAssembly
+                base_path::rewrite_html(&loading::inject(&page, &versions, &toggles), &base)
+            }
             Err(e) => {
-                eprintln!("❗ Error generating HTML: {e}");
+                error_println!("Error generating HTML: {e}");
                 format!(
                     "<html><body><h1>Error</h1><p>Failed to generate HTML: {e}</p></body></html>"
                 )
             }
         };
 
-        let response = Response::from_string(html).with_header(content_type_header("text/html"));
-        if let Err(e) = request.respond(response) {
-            eprintln!("❗ Error sending HTML response: {e}");
+        let mut response =
+            Response::from_string(html).with_header(content_type_header("text/html"));
+        if !preview::is_enabled() {
+            // Always fetch the page, so it picks up the versions of a fresh build
+            response.add_header(
+                tiny_http::Header::from_bytes(&b"Cache-Control"[..], &b"no-store"[..]).unwrap(),
+            );
+        }
+        if toggles_changed {
+            response.add_header(toggles.cookie_header(&base));
+        }
+        events::publish(Event::ClientConnected {
+            client: client_addr.clone(),
+            url: url.clone(),
+            user_agent: request
+                .headers()
+                .iter()
+                .find(|header| header.field.equiv("User-Agent"))
+                .map(|header| header.value.to_string()),
+        });
+        if let Err(e) = respond(request, response) {
+            error_println!("Error sending HTML response: {e}");
         }
 
-        if watch_mode && !clients_to_reload.contains(&client_addr) {
-            clients_to_reload.push(client_addr);
+        if let Some(mut clients) = watch_mode.then(|| clients_to_reload.lock().ok()).flatten() {
+            if !clients.contains(&client_addr) {
+                clients.push(client_addr);
+            }
         }
     } else if url == format!("/{wasm_filename}") {
         serve_file(request, wasm_path, "application/wasm");
-    } else if let Some(js_file) = js_filename {
-        if url == format!("/{js_file}") {
-            let js_path = Path::new(wasm_path).parent().unwrap().join(js_file);
-            serve_file(request, js_path.to_str().unwrap(), "application/javascript");
-        }
+    } else if let Some(js_file) = js_filename.filter(|js_file| url == format!("/{js_file}")) {
+        let js_path = Path::new(wasm_path).parent().unwrap().join(js_file);
+        serve_file(request, js_path.to_str().unwrap(), "application/javascript");
+    } else if let Some(module_path) = url
+        .starts_with(NODE_MODULES_PREFIX)
+        .then_some(project_path)
+        .flatten()
+        .and_then(|project_path| resolve_node_module(project_path, &url))
+    {
+        let content_type = determine_content_type(&module_path);
+        serve_file(request, module_path.to_str().unwrap(), &content_type);
+    } else if let Some(since) = live_reload::parse_since(&url) {
+        // Hold the poll open on its own thread so other requests are still served
+        std::thread::spawn(move || {
+            // The poll counts as open until it is answered
+            let _slot = slot;
+            let reply = if live_reload::wait_for_change(since, live_reload::POLL_TIMEOUT) {
+                live_reload::reply(since)
+            } else {
+                "no-reload".to_string()
+            };
+            let response =
+                Response::from_string(reply).with_header(content_type_header("text/plain"));
+            if let Err(e) = respond(request, response) {
+                error_println!("Error sending reload response: {e}");
+            }
+        });
     } else if url == "/reload" {
         if watch_mode {
             // TODO: check if there was an actual file change
-            println!("🔄 Handling reload request in watch mode");
+            request_println!("🔄 Handling reload request in watch mode");
 
             let response =
                 Response::from_string("no-reload").with_header(content_type_header("text/plain"));
 
-            if let Err(e) = request.respond(response) {
-                eprintln!("❗ Error sending reload response: {e}");
+            if let Err(e) = respond(request, response) {
+                error_println!("Error sending reload response: {e}");
             }
         } else {
             let response = Response::from_string("not-watching")
                 .with_header(content_type_header("text/plain"));
 
-            if let Err(e) = request.respond(response) {
-                eprintln!("❗ Error sending reload response: {e}");
+            if let Err(e) = respond(request, response) {
+                error_println!("Error sending reload response: {e}");
+            }
+        }
+    } else if let Some(query) = url
+        .strip_prefix(delta::DELTA_ROUTE)
+        .and_then(|rest| rest.strip_prefix('?'))
+    {
+        delta::serve(request, query);
+    } else if url == a11y::AXE_SCRIPT_ROUTE {
+        a11y::serve_axe(request);
+    } else if url == a11y::REPORT_ROUTE {
+        a11y::receive_report(request);
+    } else if url == failures::REPORT_ROUTE {
+        failures::receive(
+            request,
+            wasm_path,
+            project_path,
+            &page_variant(template_type),
+        );
+    } else if url == traps::TRAP_ROUTE {
+        traps::receive(request, wasm_path);
+    } else if url == console::LOG_ROUTE {
+        console::receive(request);
+    } else if let Some(rest) = url.strip_prefix(replay::RECORDINGS_ROUTE) {
+        replay::handle(request, rest);
+    } else if let Some(rest) = url.strip_prefix(features::FEATURES_ROUTE) {
+        features::handle(request, rest, wasm_path, project_path);
+    } else if url == typescript::TYPES_ROUTE {
+        match typescript::for_module(wasm_path) {
+            Ok(types) => {
+                let response = Response::from_string(types)
+                    .with_header(content_type_header("application/typescript"));
+                if let Err(e) = respond(request, response) {
+                    error_println!("Error sending type definitions: {e}");
+                }
+            }
+            Err(e) => {
+                error_println!("Cannot describe the module's types: {e}");
+                not_found(request);
             }
         }
     } else if url == "/api/module-info" {
         serve_module_info(request, wasm_path, project_path);
     } else if url == "/api/version" {
         serve_version_info(request);
+    } else if url == control::CONTROL_ROUTE {
+        control::serve(request);
+    } else if url.split('?').next() == Some(build_status::BUILD_ROUTE) {
+        build_status::serve(request);
+    } else if url.split('?').next() == Some(build_status::ERRORS_ROUTE) {
+        build_status::serve_errors(request);
+    } else if url.split('?').next() == Some(metrics::METRICS_ROUTE) {
+        metrics::serve(request);
+    } else if url.split('?').next() == Some(status::STATUS_ROUTE) {
+        status::serve(request, wasm_path, js_filename, project_path);
+    } else if url == cluster::MANIFEST_ROUTE {
+        cluster::serve_manifest(request, wasm_filename, wasm_path, js_filename);
+    } else if let Some(name) = url.strip_prefix(dedup::DEDUP_ROUTE) {
+        dedup::serve(request, name);
+    } else if let Some(root) = site::root() {
+        // Directory mode serves exactly the tree on disk, without guessing at file names
+        match site::resolve(&root, &url) {
+            Some(file) => {
+                let Some(request) = dedup::redirect_shared(request, &root, &file, &base) else {
+                    return;
+                };
+                let content_type = determine_content_type(&file);
+                serve_file(request, file.to_str().unwrap(), &content_type);
+            }
+            None => match listing::directory(&root, &url) {
+                Some(dir) => {
+                    let html = base_path::rewrite_html(&listing::page(&root, &dir), &base);
+                    let response =
+                        Response::from_string(html).with_header(content_type_header("text/html"));
+                    if let Err(e) = respond(request, response) {
+                        error_println!("Error sending directory listing: {e}");
+                    }
+                }
+                None => not_found(request),
+            },
+        }
     } else if url.starts_with("/assets/") {
         serve_asset(request, &url);
     } else {
@@ -91,7 +356,7 @@ pub fn handle_request(
 
         if requested_file.exists() && requested_file.is_file() {
             let content_type = determine_content_type(&requested_file);
-            serve_file(request, requested_file.to_str().unwrap(), content_type);
+            serve_file(request, requested_file.to_str().unwrap(), &content_type);
         } else {
             if url.ends_with("_bg.wasm") {
                 if let Ok(entries) = fs::read_dir(base_dir) {
@@ -123,14 +388,8 @@ pub fn handle_request(
                                 .file_name()
                                 .is_some_and(|name| name.to_string_lossy() == filename)
                             {
-                                let content_type = match *ext {
-                                    "js" => "application/javascript",
-                                    "css" => "text/css",
-                                    "json" => "application/json",
-                                    "wasm" => "application/wasm",
-                                    _ => "application/octet-stream",
-                                };
-                                serve_file(request, entry_path.to_str().unwrap(), content_type);
+                                let content_type = mime::for_extension(ext);
+                                serve_file(request, entry_path.to_str().unwrap(), &content_type);
                                 return;
                             }
                         }
@@ -138,13 +397,27 @@ pub fn handle_request(
                 }
             }
 
-            // 404 for all other requests
-            let response = Response::from_string("404 Not Found")
-                .with_status_code(404)
-                .with_header(content_type_header("text/plain"));
-            if let Err(e) = request.respond(response) {
-                eprintln!("❗ Error sending 404 response: {e}");
-            }
+            not_found(request);
         }
     }
 }
+
+fn not_found(request: Request) {
+    let response = Response::from_string("404 Not Found")
+        .with_status_code(404)
+        .with_header(content_type_header("text/plain"));
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending 404 response: {e}");
+    }
+}
+
+/// The page served at `/`, as recorded in failure reports
+fn page_variant(template_type: &TemplateType) -> String {
+    if site::index_html().is_some() {
+        "index.html".to_string()
+    } else if component::page().is_some() {
+        "component".to_string()
+    } else {
+        template_type.as_str().to_string()
+    }
+}
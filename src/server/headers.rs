@@ -0,0 +1,74 @@
+//! Extra response headers
+//!
+//! Every response carries the headers from `[server.headers]` in
+//! `wasmrun.toml`, then those given with `--header "Name: Value"`, then those
+//! set on the running server with `wasmrun ctl set-header`. A later source
+//! replaces a header of the same name, whatever its case.
+
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// Set by `--header`
+static FLAGS: RwLock<Vec<(String, String)>> = RwLock::new(Vec::new());
+
+/// Whether `name: value` can be sent as a header
+pub fn is_valid(name: &str, value: &str) -> bool {
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c));
+    valid_name && tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).is_ok()
+}
+
+/// Parse a `--header` argument, `Name: Value`
+pub fn parse(spec: &str) -> Result<(String, String), String> {
+    let (name, value) = spec
+        .split_once(':')
+        .ok_or("is not of the form 'Name: Value'")?;
+    let (name, value) = (name.trim(), value.trim());
+    if !is_valid(name, value) {
+        return Err("is not a valid header".to_string());
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Send `headers` with every response, over `server.headers`
+pub fn set_flags(headers: Vec<(String, String)>) {
+    if let Ok(mut flags) = FLAGS.write() {
+        *flags = headers;
+    }
+}
+
+/// The headers to add to every response right now
+pub fn configured() -> BTreeMap<String, String> {
+    let mut headers = crate::config::active_settings().server.headers;
+    let flags = FLAGS.read().map(|flags| flags.clone()).unwrap_or_default();
+    for (name, value) in flags.into_iter().chain(super::control::runtime_headers()) {
+        headers.retain(|existing, _| !existing.eq_ignore_ascii_case(&name));
+        headers.insert(name, value);
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("Content-Security-Policy: default-src 'self'"),
+            Ok((
+                "Content-Security-Policy".to_string(),
+                "default-src 'self'".to_string()
+            ))
+        );
+        assert_eq!(
+            parse("X-Empty:"),
+            Ok(("X-Empty".to_string(), String::new()))
+        );
+        assert!(parse("Cache-Control no-store").is_err());
+        assert!(parse("Bad Name: 1").is_err());
+        assert!(parse(": value").is_err());
+    }
+}
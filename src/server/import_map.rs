@@ -0,0 +1,290 @@
+//! Import map generation for JS dependencies
+//!
+//! wasm-bindgen snippet modules and projects with a `package.json` may import
+//! bare specifiers such as `import { x } from "some-pkg"`. Browsers cannot
+//! resolve those without a bundler, so the served HTML gets an import map that
+//! points each package at its copy under `/node_modules/`.
+
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+/// URL prefix under which the project's `node_modules` directory is served
+pub const NODE_MODULES_PREFIX: &str = "/node_modules/";
+
+/// Mapping from bare specifiers to served URLs
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ImportMap {
+    pub imports: BTreeMap<String, String>,
+}
+
+impl ImportMap {
+    /// Build the import map for a served module and its (optional) project directory
+    pub fn for_project(wasm_path: &str, project_path: Option<&str>) -> Self {
+        let mut import_map = Self::default();
+        let Some(project_dir) = project_path.map(Path::new) else {
+            return import_map;
+        };
+
+        let mut packages: BTreeSet<String> = declared_dependencies(project_dir);
+        if let Some(output_dir) = Path::new(wasm_path).parent() {
+            packages.extend(
+                snippet_specifiers(&output_dir.join("snippets"))
+                    .iter()
+                    .map(|specifier| package_name(specifier).to_string()),
+            );
+        }
+
+        let node_modules = project_dir.join("node_modules");
+        for package in packages {
+            let package_dir = node_modules.join(&package);
+            let Some(entry) = package_entry(&package_dir) else {
+                continue;
+            };
+            import_map.imports.insert(
+                package.clone(),
+                format!("{NODE_MODULES_PREFIX}{package}/{entry}"),
+            );
+            import_map.imports.insert(
+                format!("{package}/"),
+                format!("{NODE_MODULES_PREFIX}{package}/"),
+            );
+        }
+
+        import_map
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.imports.is_empty()
+    }
+
+    /// The `<script type="importmap">` tag for this map
+    pub fn to_script_tag(&self) -> String {
+        let json = serde_json::json!({ "imports": self.imports });
+        format!(r#"<script type="importmap">{json}</script>"#)
+    }
+
+    /// Insert the import map into an HTML document, ahead of any module scripts
+    pub fn inject(&self, html: &str) -> String {
+        if self.is_empty() {
+            return html.to_string();
+        }
+
+        let tag = self.to_script_tag();
+        let insert_at = html
+            .find("<head")
+            .and_then(|start| html[start..].find('>').map(|end| start + end + 1))
+            .or_else(|| html.find("<script"))
+            .unwrap_or(0);
+
+        let mut injected = String::with_capacity(html.len() + tag.len() + 1);
+        injected.push_str(&html[..insert_at]);
+        injected.push('\n');
+        injected.push_str(&tag);
+        injected.push_str(&html[insert_at..]);
+        injected
+    }
+}
+
+/// Resolve a `/node_modules/...` URL to a file in the project, rejecting path traversal
+pub fn resolve_node_module(project_path: &str, url: &str) -> Option<PathBuf> {
+    let relative = Path::new(url.strip_prefix(NODE_MODULES_PREFIX)?);
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let file = Path::new(project_path).join("node_modules").join(relative);
+    file.is_file().then_some(file)
+}
+
+/// Package names from `dependencies` in the project's `package.json`
+fn declared_dependencies(project_dir: &Path) -> BTreeSet<String> {
+    let Ok(content) = fs::read_to_string(project_dir.join("package.json")) else {
+        return BTreeSet::new();
+    };
+    let Ok(manifest) = serde_json::from_str::<Value>(&content) else {
+        return BTreeSet::new();
+    };
+
+    manifest
+        .get("dependencies")
+        .and_then(Value::as_object)
+        .map(|dependencies| dependencies.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Bare specifiers imported by the wasm-bindgen snippet modules
+fn snippet_specifiers(snippets_dir: &Path) -> BTreeSet<String> {
+    static IMPORT_RE: OnceLock<Regex> = OnceLock::new();
+    let import_re = IMPORT_RE
+        .get_or_init(|| Regex::new(r#"(?:\bfrom\s*|\bimport\s*\(?\s*)["']([^"']+)["']"#).unwrap());
+
+    let mut specifiers = BTreeSet::new();
+    let mut pending = vec![snippets_dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.is_dir() {
+                pending.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext == "js" || ext == "mjs")
+            {
+                let Ok(source) = fs::read_to_string(&path) else {
+                    continue;
+                };
+                specifiers.extend(
+                    import_re
+                        .captures_iter(&source)
+                        .map(|captures| captures[1].to_string())
+                        .filter(|specifier| is_bare_specifier(specifier)),
+                );
+            }
+        }
+    }
+
+    specifiers
+}
+
+fn is_bare_specifier(specifier: &str) -> bool {
+    !(specifier.starts_with('.') || specifier.starts_with('/') || specifier.contains("://"))
+}
+
+/// Package part of a specifier: `pkg/sub` -> `pkg`, `@scope/pkg/sub` -> `@scope/pkg`
+fn package_name(specifier: &str) -> &str {
+    let segments = if specifier.starts_with('@') { 2 } else { 1 };
+    match specifier.match_indices('/').nth(segments - 1) {
+        Some((index, _)) => &specifier[..index],
+        None => specifier,
+    }
+}
+
+/// Entry module of an installed package, relative to its directory
+fn package_entry(package_dir: &Path) -> Option<String> {
+    let manifest = fs::read_to_string(package_dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok());
+
+    let declared = manifest.as_ref().and_then(|manifest| {
+        manifest
+            .get("exports")
+            .and_then(export_entry)
+            .or_else(|| manifest.get("module")?.as_str().map(str::to_string))
+            .or_else(|| manifest.get("main")?.as_str().map(str::to_string))
+    });
+
+    let entry = declared.unwrap_or_else(|| "index.js".to_string());
+    let entry = entry.trim_start_matches("./").to_string();
+    package_dir.join(&entry).is_file().then_some(entry)
+}
+
+/// Browser-facing entry from an `exports` field (string, `"."` entry or condition object)
+fn export_entry(exports: &Value) -> Option<String> {
+    match exports {
+        Value::String(path) => Some(path.clone()),
+        Value::Object(map) => {
+            if let Some(root) = map.get(".") {
+                return export_entry(root);
+            }
+            ["browser", "import", "module", "default"]
+                .iter()
+                .find_map(|condition| export_entry(map.get(*condition)?))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_import_map_from_dependencies_and_snippets() {
+        let project = tempdir().unwrap();
+        let root = project.path();
+        write(
+            &root.join("package.json"),
+            r#"{ "dependencies": { "lit": "^3.0.0", "missing": "1.0.0" } }"#,
+        );
+        write(
+            &root.join("node_modules/lit/package.json"),
+            r#"{ "exports": { ".": { "import": "./index.js" } } }"#,
+        );
+        write(&root.join("node_modules/lit/index.js"), "export {}");
+        write(
+            &root.join("node_modules/@scope/util/package.json"),
+            r#"{ "module": "dist/util.mjs" }"#,
+        );
+        write(&root.join("node_modules/@scope/util/dist/util.mjs"), "");
+        write(
+            &root.join("pkg/snippets/app-123/inline0.js"),
+            r#"import { helper } from "@scope/util/helpers"; import "./local.js";"#,
+        );
+        let wasm_path = root.join("pkg/app_bg.wasm");
+
+        let import_map = ImportMap::for_project(wasm_path.to_str().unwrap(), root.to_str());
+
+        assert_eq!(
+            import_map.imports.get("lit").map(String::as_str),
+            Some("/node_modules/lit/index.js")
+        );
+        assert_eq!(
+            import_map.imports.get("@scope/util").map(String::as_str),
+            Some("/node_modules/@scope/util/dist/util.mjs")
+        );
+        assert!(import_map.imports.contains_key("@scope/util/"));
+        assert!(!import_map.imports.contains_key("missing"));
+    }
+
+    #[test]
+    fn test_inject_places_map_at_start_of_head() {
+        let mut import_map = ImportMap::default();
+        import_map
+            .imports
+            .insert("lit".to_string(), "/node_modules/lit/index.js".to_string());
+
+        let html = import_map
+            .inject(r#"<html><head lang="en"><script type="module"></script></head></html>"#);
+        let map_at = html.find("importmap").unwrap();
+        assert!(map_at < html.find(r#"type="module""#).unwrap());
+        assert!(map_at > html.find("<head").unwrap());
+
+        assert_eq!(
+            ImportMap::default().inject("<head></head>"),
+            "<head></head>"
+        );
+    }
+
+    #[test]
+    fn test_resolve_node_module_rejects_traversal() {
+        let project = tempdir().unwrap();
+        write(&project.path().join("node_modules/lit/index.js"), "");
+        let project_path = project.path().to_str().unwrap();
+
+        assert!(resolve_node_module(project_path, "/node_modules/lit/index.js").is_some());
+        assert!(resolve_node_module(project_path, "/node_modules/../package.json").is_none());
+        assert!(resolve_node_module(project_path, "/node_modules/lit/missing.js").is_none());
+    }
+
+    #[test]
+    fn test_package_name() {
+        assert_eq!(package_name("lit"), "lit");
+        assert_eq!(package_name("lit/decorators.js"), "lit");
+        assert_eq!(package_name("@scope/util"), "@scope/util");
+        assert_eq!(package_name("@scope/util/helpers"), "@scope/util");
+    }
+}
@@ -0,0 +1,65 @@
+//! Cross-origin isolation (`--coi`)
+//!
+//! `SharedArrayBuffer`, and with it wasm threads (wasm-bindgen-rayon and
+//! similar), is only available to cross-origin isolated pages. With `--coi` or
+//! `server.cross_origin_isolation = true` every response carries the
+//! COOP/COEP pair that opts the page in.
+
+use crate::config::active_settings;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Headers that make a page cross-origin isolated
+pub const HEADERS: [(&str, &str); 2] = [
+    ("Cross-Origin-Opener-Policy", "same-origin"),
+    ("Cross-Origin-Embedder-Policy", "require-corp"),
+];
+
+/// Set by `--coi`, which isolates pages even when the config does not ask for it
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed) || active_settings().server.cross_origin_isolation == Some(true)
+}
+
+/// Add the isolation headers to `headers` unless they are already set there
+pub fn apply(headers: &mut BTreeMap<String, String>) {
+    if !is_enabled() {
+        return;
+    }
+    for (name, value) in HEADERS {
+        if !headers.keys().any(|key| key.eq_ignore_ascii_case(name)) {
+            headers.insert(name.to_string(), value.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_keeps_configured_values() {
+        enable();
+        let mut headers = BTreeMap::from([(
+            "cross-origin-embedder-policy".to_string(),
+            "credentialless".to_string(),
+        )]);
+        apply(&mut headers);
+        assert_eq!(
+            headers
+                .get("Cross-Origin-Opener-Policy")
+                .map(String::as_str),
+            Some("same-origin")
+        );
+        assert_eq!(headers.len(), 2);
+        assert_eq!(
+            headers["cross-origin-embedder-policy"].as_str(),
+            "credentialless"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Landing page for bundles (`wasmrun bundle --with-demo-page`)
+//!
+//! Describes the module above a link to its runner page, for galleries of
+//! published examples: its name, version and description from `module.toml`,
+//! `Cargo.toml` or `package.json`, its exports and size, and how it was
+//! built. Nothing on the page depends on when it was generated, so a
+//! reproducible bundle stays reproducible with it.
+
+use super::compare::escape_html;
+use super::gallery;
+use crate::commands::verify_wasm;
+use crate::utils::CommandExecutor;
+use serde_json::Value;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Where the runner page goes when the landing page is `index.html`
+pub const RUNNER_FILE: &str = "run.html";
+
+/// What the landing page says about a module
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleInfo {
+    pub name: String,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    /// Name of the module file in the bundle, linked for download
+    pub file: String,
+    pub exports: Vec<String>,
+    pub size: u64,
+    /// How the module was built, one fact each, e.g. `wasm-opt -Oz`
+    pub build: Vec<String>,
+}
+
+impl ModuleInfo {
+    /// Read what is known about the module at `wasm_path`, built from
+    /// `project_path` when it is given
+    pub fn read(wasm_path: &Path, project_path: Option<&Path>) -> Self {
+        let file = wasm_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let stem = wasm_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let manifest = project_path.map(Manifest::read).unwrap_or_default();
+        let (metadata, _) = gallery::metadata_for(wasm_path);
+
+        Self {
+            name: metadata
+                .title
+                .or(manifest.name)
+                .unwrap_or_else(|| stem.strip_suffix("_bg").unwrap_or(&stem).to_string()),
+            version: manifest.version,
+            description: metadata.description.or(manifest.description),
+            file,
+            exports: verify_wasm(&wasm_path.to_string_lossy())
+                .map(|result| result.export_names)
+                .unwrap_or_default(),
+            size: fs::metadata(wasm_path)
+                .map(|metadata| metadata.len())
+                .unwrap_or(0),
+            build: vec![format!("wasmrun {}", env!("CARGO_PKG_VERSION"))],
+        }
+    }
+}
+
+/// Name, version and description from a project's package manifest
+#[derive(Debug, Default, PartialEq)]
+struct Manifest {
+    name: Option<String>,
+    version: Option<String>,
+    description: Option<String>,
+}
+
+impl Manifest {
+    /// `Cargo.toml`'s `[package]`, else `package.json`
+    fn read(project_path: &Path) -> Self {
+        let cargo = fs::read_to_string(project_path.join("Cargo.toml"))
+            .ok()
+            .and_then(|contents| contents.parse::<toml::Table>().ok());
+        if let Some(package) = cargo
+            .as_ref()
+            .and_then(|manifest| manifest.get("package"))
+            .and_then(toml::Value::as_table)
+        {
+            let field = |key: &str| package.get(key).and_then(toml::Value::as_str);
+            return Self {
+                name: field("name").map(str::to_string),
+                version: field("version").map(str::to_string),
+                description: field("description").map(str::to_string),
+            };
+        }
+
+        let package = fs::read_to_string(project_path.join("package.json"))
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+            .unwrap_or_default();
+        let field = |key: &str| package[key].as_str().map(str::to_string);
+        Self {
+            name: field("name"),
+            version: field("version"),
+            description: field("description"),
+        }
+    }
+}
+
+/// The landing page for `info`, linking to the runner page next to it
+pub fn page(info: &ModuleInfo) -> String {
+    let mut details = String::new();
+    let mut row = |label: &str, value: String| {
+        let _ = writeln!(details, "<dt>{label}</dt><dd>{value}</dd>");
+    };
+    if let Some(version) = &info.version {
+        row("Version", escape_html(version));
+    }
+    row(
+        "Module",
+        format!(
+            r#"<a href="{file}" download>{file}</a>, {size}"#,
+            file = escape_html(&info.file),
+            size = CommandExecutor::format_file_size(info.size)
+        ),
+    );
+    if !info.build.is_empty() {
+        row("Built with", escape_html(&info.build.join(", ")));
+    }
+
+    let exports = if info.exports.is_empty() {
+        r#"<p class="empty">The module exports nothing.</p>"#.to_string()
+    } else {
+        let items: String = info
+            .exports
+            .iter()
+            .map(|name| format!("<li><code>{}</code></li>", escape_html(name)))
+            .collect();
+        format!("<ul>{items}</ul>")
+    };
+    let description = info
+        .description
+        .as_deref()
+        .map(|description| format!("<p>{}</p>", escape_html(description)))
+        .unwrap_or_default();
+
+    LANDING_PAGE
+        .replace("$TITLE$", &escape_html(&info.name))
+        .replace("$RUNNER$", RUNNER_FILE)
+        .replace("$COUNT$", &info.exports.len().to_string())
+        .replace("<!-- @description -->", &description)
+        .replace("<!-- @details -->", &details)
+        .replace("<!-- @exports -->", &exports)
+}
+
+const LANDING_PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>$TITLE$</title>
+<style>
+  body { margin: 0; font-family: system-ui, sans-serif; background: #0f172a; color: #e2e8f0; }
+  main { max-width: 46rem; margin: 0 auto; padding: 2.5rem 1.5rem; }
+  h1 { margin: 0; font-size: 1.8rem; }
+  h2 { margin: 2rem 0 0.6rem; font-size: 1.1rem; color: #94a3b8; }
+  p { color: #cbd5e1; }
+  a { color: #38bdf8; }
+  .run { display: inline-block; margin: 1rem 0; padding: 0.6rem 1.2rem; border-radius: 8px; background: #38bdf8; color: #0f172a; font-weight: 600; text-decoration: none; }
+  .run:hover, .run:focus { background: #7dd3fc; }
+  dl { display: grid; grid-template-columns: max-content 1fr; gap: 0.4rem 1.2rem; margin: 0; }
+  dt { color: #94a3b8; }
+  dd { margin: 0; }
+  ul { margin: 0; padding: 0; list-style: none; display: flex; flex-wrap: wrap; gap: 0.4rem; }
+  code { padding: 0.15rem 0.45rem; border-radius: 5px; background: #1e293b; border: 1px solid #334155; font-family: ui-monospace, monospace; font-size: 0.9rem; }
+  .empty { color: #64748b; }
+</style>
+</head>
+<body>
+<main>
+<h1>$TITLE$</h1>
+<!-- @description -->
+<a class="run" href="$RUNNER$">Run it</a>
+<dl>
+<!-- @details -->
+</dl>
+<h2>Exports ($COUNT$)</h2>
+<!-- @exports -->
+</main>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_module_info_from_manifests() {
+        let dir = tempdir().unwrap();
+        let project = dir.path();
+        let wasm = project.join("demo_bg.wasm");
+        let module =
+            wat::parse_str(r#"(module (func (export "add")) (memory (export "memory") 1))"#)
+                .unwrap();
+        fs::write(&wasm, &module).unwrap();
+
+        let info = ModuleInfo::read(&wasm, None);
+        assert_eq!(info.name, "demo");
+        assert_eq!(info.file, "demo_bg.wasm");
+        assert_eq!(info.exports, ["add", "memory"]);
+        assert_eq!(info.size, module.len() as u64);
+
+        fs::write(
+            project.join("package.json"),
+            r#"{"name": "js-demo", "version": "0.1.0", "description": "From npm"}"#,
+        )
+        .unwrap();
+        let info = ModuleInfo::read(&wasm, Some(project));
+        assert_eq!(info.name, "js-demo");
+        assert_eq!(info.description.as_deref(), Some("From npm"));
+
+        fs::write(
+            project.join("Cargo.toml"),
+            "[package]\nname = \"rs-demo\"\nversion = \"1.2.3\"\n",
+        )
+        .unwrap();
+        fs::write(project.join("module.toml"), "title = \"Demo <1>\"\n").unwrap();
+        let info = ModuleInfo::read(&wasm, Some(project));
+        assert_eq!(info.name, "Demo <1>");
+        assert_eq!(info.version.as_deref(), Some("1.2.3"));
+        assert_eq!(info.description, None);
+
+        let page = page(&info);
+        assert!(page.contains("<h1>Demo &lt;1&gt;</h1>"));
+        assert!(page.contains(r#"<a class="run" href="run.html">"#));
+        assert!(page.contains("<li><code>add</code></li>"));
+        assert!(page.contains("<dt>Version</dt><dd>1.2.3</dd>"));
+    }
+}
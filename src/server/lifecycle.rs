@@ -1,93 +1,293 @@
-use crate::config::PID_FILE;
+use super::control::ControlCommand;
+use crate::config::WasmrunConfig;
 use crate::error::{Result, ServerError, WasmrunError};
+use crate::ipc::Endpoint;
+use crate::process;
+use crate::registry::{Registry, ServerInstance};
+use crate::utils::LockFile;
+use crate::{info_println, warn_println};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 
-/// Check if a wasmrun server is currently running
-pub fn is_server_running() -> bool {
-    if !std::path::Path::new(PID_FILE).exists() {
-        return false;
+/// How long a server gets to exit after each way of asking it to
+const GRACE_PERIOD: Duration = Duration::from_secs(3);
+
+/// Lock file in the config dir held by a wasmrun that is starting a server
+const STARTUP_LOCK: &str = "instances.lock";
+
+/// Longest wait for another wasmrun to finish starting its server
+const STARTUP_WAIT: Duration = Duration::from_secs(10);
+
+/// The startup lock, while this process holds it
+static STARTUP: Mutex<Option<LockFile>> = Mutex::new(None);
+
+/// Name this process's server registers under (`--name`)
+static INSTANCE_NAME: OnceLock<String> = OnceLock::new();
+
+/// Register the servers this process starts under `name`
+pub fn set_instance_name(name: String) {
+    let _ = INSTANCE_NAME.set(name);
+}
+
+pub fn instance_name() -> Option<&'static str> {
+    INSTANCE_NAME.get().map(String::as_str)
+}
+
+/// Whether `name` may name a server; names that are all digits would be read as ports
+pub fn is_valid_instance_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && !name.chars().all(|c| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Whether `target` names `instance` or is the port it listens on
+fn matches_target(instance: &ServerInstance, target: &str) -> bool {
+    instance.name.as_deref() == Some(target) || target.parse() == Ok(instance.port)
+}
+
+/// Registers the current process as a running server for as long as it is alive
+pub struct InstanceGuard {
+    pid: u32,
+}
+
+impl InstanceGuard {
+    pub fn register(port: u16, wasm_path: &str, project_path: Option<&str>) -> Self {
+        let instance = ServerInstance {
+            pid: std::process::id(),
+            port,
+            wasm_path: wasm_path.to_string(),
+            project_path: project_path.map(str::to_string),
+            started_at: chrono::Utc::now().to_rfc3339(),
+            name: instance_name().map(str::to_string),
+        };
+
+        if let Err(e) = Registry::open().and_then(|registry| registry.register_instance(&instance))
+        {
+            warn_println!("Failed to register server instance: {e}");
+        }
+        end_startup();
+
+        Self { pid: instance.pid }
+    }
+}
+
+impl Drop for InstanceGuard {
+    fn drop(&mut self) {
+        if let Ok(registry) = Registry::open() {
+            let _ = registry.remove_instance(self.pid);
+        }
     }
+}
 
-    if let Ok(pid_str) = std::fs::read_to_string(PID_FILE) {
-        if let Ok(pid) = pid_str.trim().parse::<u32>() {
-            let ps_command = std::process::Command::new("ps")
-                .arg("-p")
-                .arg(pid.to_string())
-                .output();
+/// Take turns with other wasmrun processes starting servers: wait until the one
+/// starting now has registered, then hold the others off until this one has,
+/// so two servers never both find the same port free or replace the same name.
+/// Does nothing when this process already holds the lock
+pub fn begin_startup() {
+    let Ok(mut held) = STARTUP.lock() else {
+        return;
+    };
+    if held.is_some() {
+        return;
+    }
+    let Ok(config_dir) = WasmrunConfig::config_dir() else {
+        return;
+    };
+    let _ = std::fs::create_dir_all(&config_dir);
+    let path = config_dir.join(STARTUP_LOCK);
 
-            if let Ok(output) = ps_command {
-                return output.status.success()
-                    && String::from_utf8_lossy(&output.stdout).lines().count() > 1;
-            }
+    if let Some(pid) =
+        LockFile::holder(&path).filter(|&pid| pid != std::process::id() && process::is_alive(pid))
+    {
+        info_println!("⏳ Waiting for another wasmrun (PID {pid}) to finish starting...");
+    }
+    match LockFile::acquire(&path, STARTUP_WAIT) {
+        Ok(lock) => *held = Some(lock),
+        Err(e) => warn_println!("Starting without waiting any longer: {e}"),
+    }
+}
+
+/// Let other wasmrun processes start their servers
+fn end_startup() {
+    if let Ok(mut held) = STARTUP.lock() {
+        held.take();
+    }
+}
+
+/// Server instances from the registry that are still alive, pruning stale records
+fn live_instances(registry: &Registry) -> Result<Vec<ServerInstance>> {
+    let current_pid = std::process::id();
+    let mut live = Vec::new();
+
+    for instance in registry.instances()? {
+        if instance.pid == current_pid {
+            continue;
+        }
+        if process::is_alive(instance.pid) && process::runs_wasmrun(instance.pid) {
+            live.push(instance);
+        } else {
+            registry.remove_stale_instance(&instance)?;
         }
     }
 
-    false
+    Ok(live)
 }
 
-/// Stop an existing wasmrun server if one is running
+/// Check if a wasmrun server is currently running
+pub fn is_server_running() -> bool {
+    Registry::open()
+        .and_then(|registry| live_instances(&registry))
+        .map(|instances| !instances.is_empty())
+        .unwrap_or(false)
+}
+
+/// Wasmrun servers that are currently running
+pub fn running_instances() -> Result<Vec<ServerInstance>> {
+    live_instances(&Registry::open()?)
+}
+
+/// Stop existing wasmrun servers if any are running
 pub fn stop_existing_server() -> Result<()> {
-    if !is_server_running() {
-        if std::path::Path::new(PID_FILE).exists() {
-            std::fs::remove_file(PID_FILE).map_err(|e| {
-                WasmrunError::Server(ServerError::StopFailed {
-                    pid: 0,
-                    reason: format!("Failed to remove stale PID file: {e}"),
-                })
-            })?;
-        }
+    let registry = Registry::open()?;
+    let instances = live_instances(&registry)?;
+    if instances.is_empty() {
         return Err(WasmrunError::Server(ServerError::NotRunning));
     }
 
-    let pid_str = std::fs::read_to_string(PID_FILE).map_err(|e| {
-        WasmrunError::Server(ServerError::StopFailed {
-            pid: 0,
-            reason: format!("Failed to read PID file: {e}"),
-        })
-    })?;
-
-    let pid = pid_str.trim().parse::<u32>().map_err(|e| {
-        WasmrunError::Server(ServerError::StopFailed {
-            pid: 0,
-            reason: format!("Failed to parse PID '{}': {}", pid_str.trim(), e),
-        })
-    })?;
-
-    let kill_command = std::process::Command::new("kill")
-        .arg("-9")
-        .arg(pid.to_string())
-        .output()
-        .map_err(|e| {
+    for instance in instances {
+        stop_instance(&registry, &instance)?;
+    }
+
+    Ok(())
+}
+
+/// Stop the running servers named `target`, or listening on port `target`
+pub fn stop_matching(target: &str) -> Result<()> {
+    let registry = Registry::open()?;
+    let instances: Vec<ServerInstance> = live_instances(&registry)?
+        .into_iter()
+        .filter(|instance| matches_target(instance, target))
+        .collect();
+    if instances.is_empty() {
+        return Err(WasmrunError::Server(ServerError::NotRunning));
+    }
+
+    for instance in instances {
+        stop_instance(&registry, &instance)?;
+    }
+
+    Ok(())
+}
+
+/// Stop the server already running under this process's `--name`, so the one
+/// about to start takes its place. Servers with other names keep running
+pub fn replace_named_instance() {
+    begin_startup();
+    let Some(name) = instance_name() else {
+        return;
+    };
+    match stop_matching(name) {
+        Ok(()) | Err(WasmrunError::Server(ServerError::NotRunning)) => {}
+        Err(e) => warn_println!("Could not finish stopping server '{name}': {e}"),
+    }
+}
+
+/// The running wasmrun server listening on `port`, if any
+pub fn instance_on_port(port: u16) -> Option<ServerInstance> {
+    Registry::open()
+        .and_then(|registry| live_instances(&registry))
+        .ok()?
+        .into_iter()
+        .find(|instance| instance.port == port)
+}
+
+/// Stop the wasmrun server with process ID `pid`
+pub fn stop_server(pid: u32) -> Result<()> {
+    let registry = Registry::open()?;
+    match live_instances(&registry)?
+        .into_iter()
+        .find(|instance| instance.pid == pid)
+    {
+        Some(instance) => stop_instance(&registry, &instance),
+        None => Err(WasmrunError::Server(ServerError::NotRunning)),
+    }
+}
+
+/// Stop a server the way Ctrl+C would, through its control endpoint or else a
+/// termination signal, and kill it only when it does not exit in time
+fn stop_instance(registry: &Registry, instance: &ServerInstance) -> Result<()> {
+    let pid = instance.pid;
+    let shut_down = Endpoint::for_process(pid)
+        .and_then(|endpoint| endpoint.request::<_, String>(&ControlCommand::Shutdown))
+        .is_ok();
+    let mut stopped = shut_down && process::wait_for_exit(pid, GRACE_PERIOD);
+    if !stopped && process::terminate(pid).is_ok() {
+        stopped = process::wait_for_exit(pid, GRACE_PERIOD);
+    }
+    if !stopped {
+        process::kill(pid).map_err(|e| {
             WasmrunError::Server(ServerError::StopFailed {
                 pid,
                 reason: format!("Failed to kill server process: {e}"),
             })
         })?;
-
-    if kill_command.status.success() {
-        std::fs::remove_file(PID_FILE).map_err(|e| {
-            WasmrunError::Server(ServerError::StopFailed {
+        if !process::wait_for_exit(pid, GRACE_PERIOD) {
+            return Err(WasmrunError::Server(ServerError::StopFailed {
                 pid,
-                reason: format!("Failed to remove PID file: {e}"),
-            })
-        })?;
-        println!("💀 Existing Wasmrun server terminated successfully.");
-        Ok(())
-    } else {
-        let error_msg = String::from_utf8_lossy(&kill_command.stderr);
-        Err(WasmrunError::Server(ServerError::StopFailed {
-            pid,
-            reason: error_msg.to_string(),
-        }))
+                reason: "The process is still running".to_string(),
+            }));
+        }
     }
+
+    registry.remove_instance(pid)?;
+    let name = instance
+        .name
+        .as_deref()
+        .map(|name| format!("'{name}' "))
+        .unwrap_or_default();
+    println!(
+        "💀 Wasmrun server {name}(PID {pid}, port {}) terminated successfully.",
+        instance.port
+    );
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_matches_target() {
+        let instance = ServerInstance {
+            pid: 1,
+            port: 3000,
+            wasm_path: "app.wasm".to_string(),
+            project_path: None,
+            started_at: String::new(),
+            name: Some("proj-a".to_string()),
+        };
+        assert!(matches_target(&instance, "proj-a"));
+        assert!(matches_target(&instance, "3000"));
+        assert!(!matches_target(&instance, "proj-b"));
+        assert!(!matches_target(&instance, "8420"));
+    }
+
+    #[test]
+    fn test_is_valid_instance_name() {
+        assert!(is_valid_instance_name("proj-a"));
+        assert!(is_valid_instance_name("docs_v2.1"));
+        assert!(!is_valid_instance_name("8420"));
+        assert!(!is_valid_instance_name(""));
+        assert!(!is_valid_instance_name("-x"));
+        assert!(!is_valid_instance_name("my app"));
+    }
+
     #[test]
     fn test_is_server_running_no_pid_file() {
-        // This test ensures is_server_running doesn't crash when there's no PID file
+        // This test ensures is_server_running doesn't crash when no instance is registered
         let _result = is_server_running();
         // Result depends on whether server is actually running, but shouldn't crash
         // Just verify the function returns without panicking
@@ -0,0 +1,167 @@
+//! Per-client rate limits and a cap on open requests
+//!
+//! A server reachable through a tunnel or from the LAN can be hammered by a
+//! client stuck in a reload loop. `server.rate_limit` (or `--rate-limit`)
+//! bounds the requests one IP address may make per minute, and
+//! `server.max_connections` (or `--max-connections`) bounds how many requests
+//! are open at once, pages waiting on live reload included. Requests over
+//! either limit get `429 Too Many Requests` with a `Retry-After` header.
+
+use super::utils::{content_type_header, respond};
+use crate::config::active_settings;
+use crate::{error_println, warn_println};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Request, Response};
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Limits given on the command line; they take precedence over `[server]`
+static RATE_LIMIT: RwLock<Option<u32>> = RwLock::new(None);
+static MAX_CONNECTIONS: RwLock<Option<usize>> = RwLock::new(None);
+
+/// Requests per client in the current window, with the window's start
+static CLIENTS: Mutex<Vec<(IpAddr, Instant, u32)>> = Mutex::new(Vec::new());
+
+/// Requests currently being answered or held open
+static OPEN: AtomicUsize = AtomicUsize::new(0);
+
+/// Use these limits instead of the configured ones
+pub fn set_overrides(rate_limit: Option<u32>, max_connections: Option<usize>) {
+    if let Ok(mut limit) = RATE_LIMIT.write() {
+        *limit = rate_limit;
+    }
+    if let Ok(mut limit) = MAX_CONNECTIONS.write() {
+        *limit = max_connections;
+    }
+}
+
+/// Requests per minute a single client may make; demo servers are always limited
+fn rate_limit() -> Option<u32> {
+    RATE_LIMIT
+        .read()
+        .ok()
+        .and_then(|limit| *limit)
+        .or(active_settings().server.rate_limit)
+        .or(super::demo::is_enabled().then_some(super::demo::REQUESTS_PER_MINUTE))
+        .filter(|limit| *limit > 0)
+}
+
+fn max_connections() -> Option<usize> {
+    MAX_CONNECTIONS
+        .read()
+        .ok()
+        .and_then(|limit| *limit)
+        .or(active_settings().server.max_connections)
+        .filter(|limit| *limit > 0)
+}
+
+/// Counts a request as open until dropped
+#[derive(Debug)]
+pub struct Slot(());
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        OPEN.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Take a slot for an open request, unless `max` are open already
+fn acquire(max: Option<usize>) -> Option<Slot> {
+    let open = OPEN.fetch_add(1, Ordering::Relaxed);
+    let slot = Slot(());
+    match max {
+        Some(max) if open >= max => None,
+        _ => Some(slot),
+    }
+}
+
+/// Let `request` through with the slot it occupies, or answer it with 429
+/// when it is over a limit
+pub fn admit(request: Request) -> Option<(Request, Slot)> {
+    if let Some(limit) = rate_limit() {
+        let ip = request.remote_addr().map(|addr| addr.ip());
+        if let Some(retry_after) = ip.and_then(|ip| rate_limited(ip, limit, Instant::now())) {
+            too_many(request, "Too many requests, try again shortly", retry_after);
+            return None;
+        }
+    }
+    match acquire(max_connections()) {
+        Some(slot) => Some((request, slot)),
+        None => {
+            too_many(
+                request,
+                "The server has too many open requests, try again shortly",
+                Duration::from_secs(1),
+            );
+            None
+        }
+    }
+}
+
+/// Count a request from `ip`; the time until the window resets once it is over `limit`
+fn rate_limited(ip: IpAddr, limit: u32, now: Instant) -> Option<Duration> {
+    let mut clients = CLIENTS.lock().ok()?;
+    clients.retain(|(_, start, _)| now.duration_since(*start) < RATE_WINDOW);
+    match clients.iter_mut().find(|(client, _, _)| *client == ip) {
+        Some((_, start, count)) => {
+            *count += 1;
+            if *count == limit + 1 {
+                warn_println!("Rate limit of {limit} requests per minute reached by {ip}");
+            }
+            (*count > limit).then(|| RATE_WINDOW - now.duration_since(*start))
+        }
+        None => {
+            clients.push((ip, now, 1));
+            None
+        }
+    }
+}
+
+fn too_many(request: Request, message: &str, retry_after: Duration) {
+    let mut response = Response::from_string(message)
+        .with_status_code(429)
+        .with_header(content_type_header("text/plain"));
+    if let Ok(header) = Header::from_bytes(
+        "Retry-After",
+        retry_after.as_secs().max(1).to_string().as_bytes(),
+    ) {
+        response.add_header(header);
+    }
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending rate limit response: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_window() {
+        let ip: IpAddr = "192.0.2.7".parse().unwrap();
+        let start = Instant::now();
+        for _ in 0..5 {
+            assert_eq!(rate_limited(ip, 5, start), None);
+        }
+        let retry_after = rate_limited(ip, 5, start + Duration::from_secs(10)).unwrap();
+        assert_eq!(retry_after, Duration::from_secs(50));
+        // Other clients have their own budget
+        assert_eq!(rate_limited("192.0.2.8".parse().unwrap(), 5, start), None);
+        assert_eq!(rate_limited(ip, 5, start + RATE_WINDOW), None);
+    }
+
+    #[test]
+    fn test_connection_cap() {
+        // Other tests may hold slots of their own, so cap relative to what is open
+        let open = OPEN.load(Ordering::Relaxed);
+        let first = acquire(Some(open + 1));
+        assert!(first.is_some());
+        assert!(acquire(Some(open + 1)).is_none());
+        drop(first);
+        assert!(acquire(Some(open + 1)).is_some());
+        assert!(acquire(None).is_some());
+    }
+}
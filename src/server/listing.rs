@@ -0,0 +1,224 @@
+//! Directory listings (`wasmrun serve <dir> --listings`)
+//!
+//! A request for a directory without an `index.html` answers with a page
+//! listing its entries instead of a 404: directories first, then files by
+//! name, each with its size and modification time. WebAssembly modules are
+//! highlighted. Hidden entries are left out, as they are everywhere else in
+//! directory mode. The root keeps serving the runner page.
+
+use super::compare::escape_html;
+use super::gallery::percent_encode;
+use super::site;
+use crate::utils::CommandExecutor;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// One row of a listing
+#[derive(Debug, PartialEq)]
+struct Entry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// The directory under `root` a request URL names, when it has no `index.html`
+/// and listings are on. Paths that would leave `root` name nothing.
+pub fn directory(root: &Path, url: &str) -> Option<PathBuf> {
+    if !is_enabled() {
+        return None;
+    }
+    directory_in(root, url)
+}
+
+fn directory_in(root: &Path, url: &str) -> Option<PathBuf> {
+    let dir = site::locate(root, url)?;
+    (dir.is_dir() && !dir.join(site::INDEX_FILE).is_file()).then_some(dir)
+}
+
+/// The listing page of `dir`, a directory under `root`
+pub fn page(root: &Path, dir: &Path) -> String {
+    let relative = dir
+        .strip_prefix(root)
+        .unwrap_or(dir)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    let url_of = |parts: &[String]| {
+        let path = parts.join("/");
+        if path.is_empty() {
+            "/".to_string()
+        } else {
+            format!("/{}/", percent_encode(&path))
+        }
+    };
+    let title = format!("/{}", relative.join("/"));
+
+    let mut rows = String::new();
+    if let Some((_, parent)) = relative.split_last() {
+        let _ = writeln!(
+            rows,
+            r#"<tr><td><a href="{}">../</a></td><td></td><td></td></tr>"#,
+            escape_html(&url_of(parent))
+        );
+    }
+    let here = url_of(&relative);
+    let entries = entries(dir);
+    for entry in &entries {
+        let (href, name) = if entry.is_dir {
+            (
+                format!("{here}{}/", percent_encode(&entry.name)),
+                format!("{}/", entry.name),
+            )
+        } else {
+            (
+                format!("{here}{}", percent_encode(&entry.name)),
+                entry.name.clone(),
+            )
+        };
+        let class = if entry.is_dir {
+            "dir"
+        } else if entry.name.ends_with(".wasm") {
+            "wasm"
+        } else {
+            "file"
+        };
+        let size = if entry.is_dir {
+            "-".to_string()
+        } else {
+            CommandExecutor::format_file_size(entry.size)
+        };
+        let modified = entry
+            .modified
+            .map(|modified| {
+                chrono::DateTime::<chrono::Local>::from(modified)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let _ = writeln!(
+            rows,
+            r#"<tr class="{class}"><td><a href="{href}">{name}</a></td><td class="size">{size}</td><td>{modified}</td></tr>"#,
+            href = escape_html(&href),
+            name = escape_html(&name),
+        );
+    }
+    if entries.is_empty() {
+        rows.push_str(r#"<tr><td colspan="3" class="empty">This directory is empty.</td></tr>"#);
+    }
+
+    LISTING_PAGE
+        .replace("$TITLE$", &escape_html(&title))
+        .replace("<!-- @rows -->", &rows)
+}
+
+/// The visible entries of `dir`, directories first, then by name
+fn entries(dir: &Path) -> Vec<Entry> {
+    let Ok(read) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<Entry> = read
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                return None;
+            }
+            // Follow symlinks so a linked directory lists as one
+            let metadata = fs::metadata(entry.path()).ok()?;
+            Some(Entry {
+                name,
+                is_dir: metadata.is_dir(),
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    entries
+}
+
+const LISTING_PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Index of $TITLE$</title>
+<style>
+  body { margin: 0; padding: 2rem; font-family: system-ui, sans-serif; background: #0f172a; color: #e2e8f0; }
+  h1 { margin: 0 0 1rem; font-size: 1.4rem; font-family: ui-monospace, monospace; }
+  table { border-collapse: collapse; width: 100%; max-width: 960px; font-family: ui-monospace, monospace; font-size: 0.9rem; }
+  th { text-align: left; color: #94a3b8; font-weight: normal; border-bottom: 1px solid #334155; }
+  th, td { padding: 0.35rem 1rem 0.35rem 0; }
+  a { color: #e2e8f0; text-decoration: none; }
+  a:hover, a:focus { text-decoration: underline; }
+  .dir a { color: #38bdf8; }
+  .wasm a { color: #a78bfa; font-weight: bold; }
+  .wasm td:first-child::after { content: "wasm"; margin-left: 0.6rem; padding: 0 0.35rem; border-radius: 4px; background: #4c1d95; color: #ede9fe; font-size: 0.7rem; }
+  .size { text-align: right; color: #94a3b8; }
+  td:last-child { color: #64748b; }
+  .empty { color: #94a3b8; }
+</style>
+</head>
+<body>
+<h1>Index of $TITLE$</h1>
+<table>
+<thead><tr><th>Name</th><th class="size">Size</th><th>Modified</th></tr></thead>
+<tbody>
+<!-- @rows -->
+</tbody>
+</table>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_directory_and_page() {
+        let dir = tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join("pkg/nested")).unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("docs/index.html"), "<html></html>").unwrap();
+        fs::write(root.join("pkg/app_bg.wasm"), [0u8; 2048]).unwrap();
+        fs::write(root.join("pkg/app.js"), "export {}").unwrap();
+        fs::write(root.join("pkg/.hidden"), "").unwrap();
+        fs::write(root.join("pkg/a <b>.txt"), "").unwrap();
+
+        assert_eq!(directory_in(&root, "/pkg/"), Some(root.join("pkg")));
+        assert_eq!(directory_in(&root, "/pkg"), Some(root.join("pkg")));
+        assert_eq!(directory_in(&root, "/docs/"), None);
+        assert_eq!(directory_in(&root, "/pkg/app.js"), None);
+        assert_eq!(directory_in(&root, "/pkg/%2e%2e/%2e%2e/"), None);
+
+        let html = page(&root, &root.join("pkg"));
+        assert!(html.contains("Index of /pkg"));
+        assert!(html.contains(r#"<a href="/">../</a>"#));
+        assert!(html.contains(r#"<a href="/pkg/nested/">nested/</a>"#));
+        assert!(html.contains(r#"<tr class="wasm"><td><a href="/pkg/app_bg.wasm">app_bg.wasm</a>"#));
+        assert!(html.contains(r#"href="/pkg/a%20%3Cb%3E.txt">a &lt;b&gt;.txt</a>"#));
+        assert!(!html.contains(".hidden"));
+        let order: Vec<usize> = ["nested/", "a &lt;b&gt;.txt", "app.js", "app_bg.wasm"]
+            .iter()
+            .map(|name| html.find(name).unwrap())
+            .collect();
+        assert!(order.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
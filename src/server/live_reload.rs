@@ -0,0 +1,461 @@
+//! Reloading open pages on request
+//!
+//! Served pages long-poll `/reload?since=<generation>`. Bumping the
+//! generation, e.g. from `wasmrun ctl reload` or after `wasmrun ctl rebuild`,
+//! answers every waiting page with `reload`. When watch mode sees only
+//! stylesheets change, pages get `styles <generation>` instead and swap their
+//! stylesheets without losing the module's state.
+//!
+//! With prewarming on (`--prewarm`), a reload after a rebuild is announced as
+//! `prewarm <url>`: the page downloads and compiles the new module in the
+//! background while it stays usable, and reloads once that is done. The
+//! reloaded page then finds the module in the browser's HTTP and compiled
+//! code caches instead of waiting for a large module to compile.
+//!
+//! When a rebuild of a wasm-bindgen project changes the JS glue but leaves
+//! the wasm byte for byte the same, pages get `glue <generation> <old> <new>`.
+//! They import the new glue and initialize it with the module they already
+//! compiled, then point globals holding the old glue or its exports at the new
+//! ones. A page can do its own rebinding by handling the cancelable
+//! `wasmrun:glue` event; when nothing was rebound it reloads after all.
+//!
+//! A rebuild starting or failing is announced as `build <generation>`, which
+//! pages pass on as a `wasmrun:build` event to the
+//! [build overlay](super::build_status).
+
+use crate::plugin::events::{publish, Event};
+use crate::watcher::ChangeSet;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a poll is held open before the page asks again
+pub const POLL_TIMEOUT: Duration = Duration::from_secs(25);
+
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    generation: u64,
+    /// Last generation that needs a full reload
+    last_full: u64,
+    /// Module to compile before that reload, relative to the page
+    module: Option<String>,
+    /// Last generation that only changed JS glue
+    last_glue: u64,
+    /// Versioned names of the glue before and after that change
+    glue: Option<(String, String)>,
+    /// Last generation that started or failed a build
+    last_build: u64,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    generation: 0,
+    last_full: 0,
+    module: None,
+    last_glue: 0,
+    glue: None,
+    last_build: 0,
+});
+
+/// Content hashes of each built wasm file and of its glue, to tell which changed
+static BUILDS: Mutex<Option<HashMap<PathBuf, Build>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Build {
+    wasm: String,
+    /// Versioned name of the JS glue, for wasm-bindgen modules
+    glue: Option<String>,
+}
+
+impl Build {
+    fn of(wasm_path: &str) -> Option<Self> {
+        Some(Self {
+            wasm: super::cache_bust::content_hash(Path::new(wasm_path))?,
+            glue: super::wasm::wasm_bindgen_glue(wasm_path)
+                .and_then(|glue| super::cache_bust::versioned_name(Path::new(&glue))),
+        })
+    }
+
+    /// The glue names before and after `next`, when only the glue changed
+    fn glue_change(&self, next: &Build) -> Option<(String, String)> {
+        match (&self.glue, &next.glue) {
+            (Some(old), Some(new)) if self.wasm == next.wasm && old != new => {
+                Some((old.clone(), new.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Note the build being served, so the next rebuild can be compared with it
+pub fn record_build(wasm_path: &str) {
+    if let (Some(build), Ok(mut builds)) = (Build::of(wasm_path), BUILDS.lock()) {
+        builds
+            .get_or_insert_with(HashMap::new)
+            .insert(PathBuf::from(wasm_path), build);
+    }
+}
+static CHANGED: Condvar = Condvar::new();
+
+static PREWARM: AtomicBool = AtomicBool::new(false);
+
+/// Have pages compile a rebuilt module before reloading
+pub fn enable_prewarm() {
+    PREWARM.store(true, Ordering::Relaxed);
+}
+
+pub fn is_prewarm_enabled() -> bool {
+    PREWARM.load(Ordering::Relaxed)
+}
+
+pub fn generation() -> u64 {
+    STATE.lock().map(|state| state.generation).unwrap_or(0)
+}
+
+/// Ask every open page to reload
+pub fn request_reload() {
+    bump(true, None);
+}
+
+/// Bring open pages up to date with files watch mode saw change
+pub fn reload_for(changes: &ChangeSet) {
+    bump(!changes.styles_only(), None);
+}
+
+/// Reload open pages after a build produced `wasm_path`, prewarming the
+/// module first when enabled
+pub fn reload_after_build(wasm_path: &str) {
+    let build = Build::of(wasm_path);
+    let previous = BUILDS.lock().ok().and_then(|mut builds| {
+        let builds = builds.get_or_insert_with(HashMap::new);
+        match &build {
+            Some(build) => builds.insert(PathBuf::from(wasm_path), build.clone()),
+            None => builds.remove(Path::new(wasm_path)),
+        }
+    });
+    let glue_change = previous
+        .zip(build)
+        .and_then(|(previous, build)| previous.glue_change(&build));
+    if let Some((old, new)) = glue_change {
+        println!("🧩 Only the JS glue changed, swapping it in open pages");
+        publish(Event::ArtifactSwapped {
+            wasm_path: wasm_path.to_string(),
+            glue_only: true,
+        });
+        if let Ok(mut state) = STATE.lock() {
+            state.generation += 1;
+            state.last_glue = state.generation;
+            state.glue = Some((old, new));
+            CHANGED.notify_all();
+        }
+        return;
+    }
+
+    let module = is_prewarm_enabled()
+        .then(|| super::cache_bust::versioned_name(Path::new(wasm_path)))
+        .flatten();
+    bump(true, module);
+    publish(Event::ArtifactSwapped {
+        wasm_path: wasm_path.to_string(),
+        glue_only: false,
+    });
+}
+
+/// Tell open pages the build state changed
+pub fn announce_build() {
+    if let Ok(mut state) = STATE.lock() {
+        state.generation += 1;
+        state.last_build = state.generation;
+        CHANGED.notify_all();
+    }
+}
+
+fn bump(full: bool, module: Option<String>) {
+    if let Ok(mut state) = STATE.lock() {
+        state.generation += 1;
+        if full {
+            state.last_full = state.generation;
+            state.module = module;
+        }
+        CHANGED.notify_all();
+    }
+}
+
+/// What to tell a page that has seen generation `since`
+pub fn reply(since: u64) -> String {
+    STATE
+        .lock()
+        .map_or_else(|_| "reload".to_string(), |state| reply_for(&state, since))
+}
+
+fn reply_for(state: &State, since: u64) -> String {
+    if state.last_full > since {
+        return match &state.module {
+            Some(module) => format!("prewarm {module}"),
+            None => "reload".to_string(),
+        };
+    }
+    match &state.glue {
+        Some((old, new)) if state.last_glue > since => {
+            format!("glue {} {old} {new}", state.generation)
+        }
+        _ if state.last_build > since => format!("build {}", state.generation),
+        _ => format!("styles {}", state.generation),
+    }
+}
+
+/// Wait until the generation moves past `since`; `false` on timeout
+pub fn wait_for_change(since: u64, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    let Ok(mut state) = STATE.lock() else {
+        return false;
+    };
+    while state.generation <= since {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return false;
+        }
+        state = match CHANGED.wait_timeout(state, remaining) {
+            Ok((state, _)) => state,
+            Err(_) => return false,
+        };
+    }
+    true
+}
+
+/// The generation a polling page has seen, from `/reload?since=<n>`
+pub fn parse_since(url: &str) -> Option<u64> {
+    url.strip_prefix("/reload?")?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("since="))?
+        .parse()
+        .ok()
+}
+
+/// Add the polling script to a served page
+pub fn inject(html: &str) -> String {
+    let script = format!(
+        r#"<script>
+(function (since) {{
+  const swapStyles = () => document.querySelectorAll('link[rel="stylesheet"]').forEach((link) => {{
+    const url = new URL(link.href);
+    url.searchParams.set("wasmrun-reload", Date.now());
+    link.href = url.href;
+  }});
+  const prewarm = (url) => {{
+    const started = performance.now();
+    const response = fetch(new URL(url, document.baseURI));
+    const compiled = WebAssembly.compileStreaming
+      ? WebAssembly.compileStreaming(response)
+      : response.then((response) => response.arrayBuffer()).then((bytes) => WebAssembly.compile(bytes));
+    return compiled
+      .then(() => console.log(`[wasmrun] Compiled ${{url}} in ${{Math.round(performance.now() - started)}} ms, reloading`))
+      .catch(() => {{}});
+  }};
+  const swapGlue = async (old, next) => {{
+    const module = window.__wasmrunLastModule;
+    if (!module) return false;
+    const started = performance.now();
+    const previous = await import(new URL(old, document.baseURI).href);
+    const glue = await import(new URL(next, document.baseURI).href);
+    try {{
+      await glue.default({{ module_or_path: module }});
+    }} catch (error) {{
+      // Glue from wasm-bindgen before 0.2.93 takes the module itself
+      await glue.default(module);
+    }}
+    const event = new CustomEvent("wasmrun:glue", {{ cancelable: true, detail: {{ previous, next: glue }} }});
+    let rebound = !window.dispatchEvent(event);
+    if (!rebound) {{
+      for (const key of Object.keys(window)) {{
+        const value = window[key];
+        if (value === previous) {{
+          window[key] = glue;
+          rebound = true;
+        }} else if (typeof value === "function") {{
+          const name = Object.keys(previous).find((name) => previous[name] === value);
+          if (name && name in glue) {{
+            window[key] = glue[name];
+            rebound = true;
+          }}
+        }}
+      }}
+    }}
+    if (rebound) {{
+      console.log(`[wasmrun] Swapped in ${{next}} in ${{Math.round(performance.now() - started)}} ms`);
+    }}
+    return rebound;
+  }};
+  const poll = () => fetch('/reload?since=' + since)
+    .then((response) => response.text())
+    .then((text) => {{
+      if (text === "reload") return location.reload();
+      if (text.startsWith("prewarm ")) return prewarm(text.slice(8)).then(() => location.reload());
+      if (text.startsWith("glue ")) {{
+        const [generation, old, next] = text.slice(5).split(" ");
+        since = Number(generation);
+        return swapGlue(old, next)
+          .catch((error) => {{
+            console.warn("[wasmrun] Could not swap the JS glue:", error);
+            return false;
+          }})
+          .then((swapped) => (swapped ? poll() : location.reload()));
+      }}
+      if (text.startsWith("build ")) {{
+        since = Number(text.slice(6));
+        window.dispatchEvent(new Event("wasmrun:build"));
+      }}
+      if (text.startsWith("styles ")) {{
+        since = Number(text.slice(7));
+        swapStyles();
+      }}
+      poll();
+    }})
+    .catch(() => setTimeout(poll, 2000));
+  poll();
+}})({});
+</script>"#,
+        generation()
+    );
+
+    let html = match html.rfind("</body>") {
+        Some(index) => format!("{}{script}\n{}", &html[..index], &html[index..]),
+        None => format!("{html}\n{script}"),
+    };
+
+    // The module must be caught before any script on the page compiles it
+    let head = html
+        .find("<head")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+    match head {
+        Some(index) => format!("{}\n{MODULE_SCRIPT}{}", &html[..index], &html[index..]),
+        None => format!("{MODULE_SCRIPT}\n{html}"),
+    }
+}
+
+/// Keeps the last module the page compiled, for initializing swapped-in glue
+const MODULE_SCRIPT: &str = r#"<script>
+(() => {
+  const keep = (module) => {
+    if (module instanceof WebAssembly.Module) {
+      Object.defineProperty(window, "__wasmrunLastModule", { value: module, configurable: true });
+    }
+    return module;
+  };
+  for (const name of ["compile", "compileStreaming"]) {
+    const original = WebAssembly[name];
+    if (original) WebAssembly[name] = (...args) => original.apply(WebAssembly, args).then(keep);
+  }
+  for (const name of ["instantiate", "instantiateStreaming"]) {
+    const original = WebAssembly[name];
+    if (!original) continue;
+    WebAssembly[name] = (source, ...rest) => {
+      keep(source);
+      return original.call(WebAssembly, source, ...rest).then((result) => {
+        if (result && result.module) keep(result.module);
+        return result;
+      });
+    };
+  }
+})();
+</script>"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("/reload?since=3"), Some(3));
+        assert_eq!(parse_since("/reload?x=1&since=12"), Some(12));
+        assert_eq!(parse_since("/reload"), None);
+        assert_eq!(parse_since("/reload?since=abc"), None);
+    }
+
+    #[test]
+    fn test_reload_wakes_waiting_pages() {
+        let since = generation();
+        assert!(!wait_for_change(since, Duration::from_millis(10)));
+
+        let waiter = std::thread::spawn(move || wait_for_change(since, Duration::from_secs(5)));
+        std::thread::sleep(Duration::from_millis(20));
+        request_reload();
+        assert!(waiter.join().unwrap());
+        assert!(wait_for_change(since, Duration::from_millis(10)));
+    }
+
+    fn state(generation: u64, last_full: u64, module: Option<&str>) -> State {
+        State {
+            generation,
+            last_full,
+            module: module.map(str::to_string),
+            last_glue: 0,
+            glue: None,
+            last_build: 0,
+        }
+    }
+
+    #[test]
+    fn test_reply_after_stylesheet_changes() {
+        // Only stylesheets changed since generation 3
+        assert_eq!(reply_for(&state(5, 3, None), 3), "styles 5");
+        assert_eq!(reply_for(&state(5, 3, None), 4), "styles 5");
+        // A full reload happened since generation 3
+        assert_eq!(reply_for(&state(5, 4, None), 3), "reload");
+    }
+
+    #[test]
+    fn test_reply_prewarms_rebuilt_module() {
+        let rebuilt = state(5, 4, Some("app.wasm?v=0123456789ab"));
+        assert_eq!(reply_for(&rebuilt, 3), "prewarm app.wasm?v=0123456789ab");
+        // Pages that already reloaded for it only swap stylesheets
+        assert_eq!(reply_for(&rebuilt, 4), "styles 5");
+    }
+
+    #[test]
+    fn test_reply_swaps_changed_glue() {
+        let mut swapped = state(6, 4, None);
+        swapped.last_glue = 6;
+        swapped.glue = Some((
+            "pkg/app.js?v=aaaa".to_string(),
+            "pkg/app.js?v=bbbb".to_string(),
+        ));
+        assert_eq!(
+            reply_for(&swapped, 5),
+            "glue 6 pkg/app.js?v=aaaa pkg/app.js?v=bbbb"
+        );
+        // A full reload since still wins
+        assert_eq!(reply_for(&swapped, 3), "reload");
+        assert_eq!(reply_for(&swapped, 6), "styles 6");
+    }
+
+    #[test]
+    fn test_reply_announces_builds() {
+        let mut failed = state(7, 4, None);
+        failed.last_build = 7;
+        assert_eq!(reply_for(&failed, 5), "build 7");
+        assert_eq!(reply_for(&failed, 3), "reload");
+        assert_eq!(reply_for(&failed, 7), "styles 7");
+    }
+
+    #[test]
+    fn test_glue_change_needs_the_same_wasm() {
+        let build = |wasm: &str, glue: &str| Build {
+            wasm: wasm.to_string(),
+            glue: Some(glue.to_string()),
+        };
+        assert_eq!(
+            build("w1", "app.js?v=a").glue_change(&build("w1", "app.js?v=b")),
+            Some(("app.js?v=a".to_string(), "app.js?v=b".to_string()))
+        );
+        assert_eq!(
+            build("w1", "app.js?v=a").glue_change(&build("w2", "app.js?v=b")),
+            None
+        );
+        assert_eq!(
+            build("w1", "app.js?v=a").glue_change(&build("w1", "app.js?v=a")),
+            None
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//! How the runner page loads the module
+//!
+//! Browsers differ in how well they stream-compile, some glue code trips over
+//! `instantiateStreaming`, and an app may want to compare load strategies.
+//! The `[loading]` section of `wasmrun.toml` (or `--instantiate`,
+//! `--load-trigger` and `--module-cache`) picks:
+//!
+//! - `instantiate`: `streaming`, the default, leaves the page's calls alone;
+//!   `buffer` turns `instantiateStreaming`/`compileStreaming` into a fetch of
+//!   the whole module followed by `instantiate`/`compile`
+//! - `trigger`: `eager`, the default, or `gesture` to hold compilation until
+//!   the first click, tap or key press, as autoplay-style policies may require
+//! - `module_cache`: keep compiled modules in IndexedDB and skip compiling on
+//!   later loads, so iterating on JS or CSS around a large unchanged module
+//!   starts it right away. Entries are keyed by the same content hash that
+//!   versions served files, which the page is told for the module next to
+//!   it, so a hit does not even read the response body; only the newest build
+//!   of each file is kept. `?nocache=1` bypasses it like the other caches.
+//!   Browsers that refuse to store a `WebAssembly.Module` say so once in the
+//!   console and compile as usual.
+//!
+//! A script at the very top of the page wraps the `WebAssembly` functions
+//! before any glue code runs, and logs how long the module took to become
+//! ready, so strategies can be compared from the console.
+
+use super::cache_bust::Versions;
+use super::toggles::Toggles;
+use crate::config::active_settings;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// Strategies given on the command line; they take precedence over `[loading]`
+static OVERRIDES: RwLock<Option<Overrides>> = RwLock::new(None);
+
+#[derive(Debug, Clone, Default)]
+struct Overrides {
+    instantiate: Option<String>,
+    trigger: Option<String>,
+    module_cache: bool,
+}
+
+/// Use these strategies instead of the configured ones
+pub fn set_overrides(instantiate: Option<String>, trigger: Option<String>, module_cache: bool) {
+    if let Ok(mut overrides) = OVERRIDES.write() {
+        *overrides = Some(Overrides {
+            instantiate,
+            trigger,
+            module_cache,
+        });
+    }
+}
+
+/// The load strategy for the runner page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Strategy {
+    /// Fetch whole modules before compiling them, instead of streaming
+    pub buffer: bool,
+    /// Wait for a user gesture before compiling
+    pub on_gesture: bool,
+    pub module_cache: bool,
+}
+
+impl Strategy {
+    /// The strategy from the command line and the active `wasmrun.toml`
+    pub fn current() -> Self {
+        let loading = active_settings().loading;
+        let overrides = OVERRIDES
+            .read()
+            .ok()
+            .and_then(|overrides| overrides.clone())
+            .unwrap_or_default();
+        let instantiate = overrides.instantiate.or(loading.instantiate);
+        let trigger = overrides.trigger.or(loading.trigger);
+        Self {
+            buffer: instantiate.as_deref() == Some("buffer"),
+            on_gesture: trigger.as_deref() == Some("gesture"),
+            module_cache: overrides.module_cache || loading.module_cache == Some(true),
+        }
+    }
+
+    /// Whether the page's own calls are left as they are
+    pub fn is_default(&self) -> bool {
+        !self.buffer && !self.on_gesture && !self.module_cache
+    }
+
+    /// Add the loader script to a served page, first thing in its head;
+    /// `hashes` are the content hashes of the wasm files by name
+    pub fn inject(&self, html: &str, hashes: &BTreeMap<String, String>) -> String {
+        if self.is_default() {
+            return html.to_string();
+        }
+        let config = serde_json::json!({
+            "instantiate": if self.buffer { "buffer" } else { "streaming" },
+            "trigger": if self.on_gesture { "gesture" } else { "eager" },
+            "cache": self.module_cache,
+            "hashes": hashes,
+        });
+        let script = format!(
+            "<script>\n{}</script>",
+            LOADER_SCRIPT.replace("__CONFIG__", &config.to_string())
+        );
+        let head = html
+            .find("<head")
+            .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+        match head {
+            Some(index) => format!("{}\n{script}{}", &html[..index], &html[index..]),
+            None => format!("{script}\n{html}"),
+        }
+    }
+}
+
+/// Apply the current strategy to a served page whose module files have
+/// `versions`, for a browser with `toggles`
+pub fn inject(html: &str, versions: &Versions, toggles: &Toggles) -> String {
+    let mut strategy = Strategy::current();
+    strategy.module_cache &= !toggles.nocache;
+    strategy.inject(html, &versions.wasm_hashes())
+}
+
+const LOADER_SCRIPT: &str = r#"(() => {
+  const CONFIG = __CONFIG__;
+  const native = {
+    compile: WebAssembly.compile,
+    compileStreaming: WebAssembly.compileStreaming,
+    instantiate: WebAssembly.instantiate,
+    instantiateStreaming: WebAssembly.instantiateStreaming,
+  };
+  const strategy = `${CONFIG.instantiate}, ${CONFIG.trigger}${CONFIG.cache ? ", module cache" : ""}`;
+
+  // Hold compilation until the first click, tap or key press
+  let gesture = Promise.resolve();
+  if (CONFIG.trigger === "gesture") {
+    gesture = new Promise((resolve) => {
+      const events = ["pointerdown", "keydown", "touchstart"];
+      let done = false;
+      let hint = null;
+      const start = () => {
+        done = true;
+        events.forEach((name) => removeEventListener(name, start, true));
+        if (hint) hint.remove();
+        resolve();
+      };
+      events.forEach((name) => addEventListener(name, start, true));
+      const show = () => {
+        if (done) return;
+        hint = document.createElement("div");
+        hint.textContent = "Click or press a key to load the WebAssembly module";
+        hint.style.cssText = "position:fixed;top:12px;left:50%;transform:translateX(-50%);z-index:2147483647;padding:8px 14px;border-radius:6px;background:#1f2937;color:#fff;font:13px system-ui,sans-serif";
+        document.body.appendChild(hint);
+      };
+      if (document.body) show();
+      else addEventListener("DOMContentLoaded", show);
+    });
+  }
+
+  // Compiled modules in IndexedDB, by content hash: the 12 hex digits of
+  // SHA-256 the server versions files with, so a module it announced is found
+  // without reading or hashing its bytes
+  let cacheWorks = CONFIG.cache && typeof indexedDB !== "undefined" && !!(self.crypto && crypto.subtle);
+  if (CONFIG.cache && !cacheWorks) {
+    console.info("wasmrun: module cache needs IndexedDB and crypto.subtle (a secure context); compiling each load");
+  }
+  function store(mode, run) {
+    return new Promise((resolve, reject) => {
+      const open = indexedDB.open("wasmrun-modules", 1);
+      open.onupgradeneeded = () => open.result.createObjectStore("modules");
+      open.onerror = () => reject(open.error);
+      open.onsuccess = () => {
+        const db = open.result;
+        const transaction = db.transaction("modules", mode);
+        let request;
+        try {
+          // Browsers that can't clone a WebAssembly.Module throw here
+          request = run(transaction.objectStore("modules"));
+        } catch (e) {
+          db.close();
+          reject(e);
+          return;
+        }
+        transaction.oncomplete = () => {
+          db.close();
+          resolve(request.result);
+        };
+        transaction.onerror = transaction.onabort = () => {
+          db.close();
+          reject(transaction.error);
+        };
+      };
+    });
+  }
+  function lookup(hash) {
+    return store("readonly", (modules) => modules.get(hash)).then((entry) => {
+      if (!entry || !(entry.module instanceof WebAssembly.Module)) return null;
+      console.info(`wasmrun: compiled module ${entry.name || hash} (${hash}) taken from IndexedDB`);
+      return entry.module;
+    }, () => null);
+  }
+  // Keep the new module and drop older builds of the same file
+  function keep(hash, name, module) {
+    store("readwrite", (modules) => {
+      const request = modules.put({ module, name, stored: Date.now() }, hash);
+      if (name) {
+        modules.openCursor().onsuccess = (event) => {
+          const cursor = event.target.result;
+          if (!cursor) return;
+          if (cursor.key !== hash && cursor.value && cursor.value.name === name) cursor.delete();
+          cursor.continue();
+        };
+      }
+      return request;
+    }).catch((e) => {
+      cacheWorks = false;
+      console.info(`wasmrun: this browser can't keep compiled modules in IndexedDB (${e && e.name}); compiling each load`);
+    });
+  }
+  async function hashOf(bytes) {
+    const digest = new Uint8Array(await crypto.subtle.digest("SHA-256", bytes));
+    return Array.from(digest, (byte) => byte.toString(16).padStart(2, "0")).join("").slice(0, 12);
+  }
+  // The announced hash of a response's file, from its `?v=` or its name
+  function announced(response) {
+    try {
+      const url = new URL(response.url);
+      const name = url.pathname.split("/").pop();
+      return { name, hash: url.searchParams.get("v") || CONFIG.hashes[name] || null };
+    } catch (e) {
+      return { name: "", hash: null };
+    }
+  }
+  async function compiled(bytes, name, hash) {
+    if (!cacheWorks) return native.compile.call(WebAssembly, bytes);
+    hash = hash || await hashOf(bytes);
+    const cached = await lookup(hash);
+    if (cached) return cached;
+    const module = await native.compile.call(WebAssembly, bytes);
+    keep(hash, name, module);
+    return module;
+  }
+  async function compiledResponse(source) {
+    const response = await source;
+    const { name, hash } = announced(response);
+    const cached = cacheWorks && hash ? await lookup(hash) : null;
+    return cached || compiled(await response.arrayBuffer(), name, hash);
+  }
+
+  function ready(result) {
+    console.info(`wasmrun: module ready ${Math.round(performance.now())} ms after navigation (${strategy})`);
+    return result;
+  }
+  function instantiateModule(module, imports) {
+    return native.instantiate.call(WebAssembly, module, imports).then((instance) => ({ module, instance }));
+  }
+  const whole = (source) => Promise.resolve(source).then((response) => response.arrayBuffer());
+
+  WebAssembly.compile = function (bytes) {
+    return gesture.then(() => compiled(bytes));
+  };
+  WebAssembly.instantiate = function (source, imports) {
+    return gesture.then(() => {
+      if (source instanceof WebAssembly.Module) return native.instantiate.call(WebAssembly, source, imports);
+      if (!cacheWorks) return native.instantiate.call(WebAssembly, source, imports);
+      return compiled(source).then((module) => instantiateModule(module, imports));
+    }).then(ready);
+  };
+  if (native.compileStreaming) {
+    WebAssembly.compileStreaming = function (source) {
+      return gesture.then(() => {
+        if (cacheWorks) return compiledResponse(source);
+        if (CONFIG.instantiate === "buffer") return whole(source).then((bytes) => native.compile.call(WebAssembly, bytes));
+        return native.compileStreaming.call(WebAssembly, source);
+      });
+    };
+  }
+  if (native.instantiateStreaming) {
+    WebAssembly.instantiateStreaming = function (source, imports) {
+      return gesture.then(() => {
+        if (cacheWorks) return compiledResponse(source).then((module) => instantiateModule(module, imports));
+        if (CONFIG.instantiate === "buffer") return whole(source).then((bytes) => native.instantiate.call(WebAssembly, bytes, imports));
+        return native.instantiateStreaming.call(WebAssembly, source, imports);
+      }).then(ready);
+    };
+  }
+})();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "<html><head><title>app</title></head><body></body></html>";
+
+    #[test]
+    fn test_default_strategy_leaves_page_alone() {
+        let strategy = Strategy {
+            buffer: false,
+            on_gesture: false,
+            module_cache: false,
+        };
+        assert!(strategy.is_default());
+        assert_eq!(strategy.inject(PAGE, &BTreeMap::new()), PAGE);
+    }
+
+    #[test]
+    fn test_loader_runs_first_with_its_config() {
+        let strategy = Strategy {
+            buffer: true,
+            on_gesture: true,
+            module_cache: false,
+        };
+        let page = strategy.inject(PAGE, &BTreeMap::new());
+        let script = page.find("<script>").unwrap();
+        assert!(script < page.find("<title>").unwrap());
+        assert!(page.contains(r#""instantiate":"buffer""#));
+        assert!(page.contains(r#""trigger":"gesture""#));
+        assert!(page.contains(r#""cache":false"#));
+    }
+
+    #[test]
+    fn test_module_cache_knows_served_hashes() {
+        let strategy = Strategy {
+            buffer: false,
+            on_gesture: false,
+            module_cache: true,
+        };
+        let hashes = BTreeMap::from([("app.wasm".to_string(), "0123456789ab".to_string())]);
+        let page = strategy.inject(PAGE, &hashes);
+        assert!(page.contains(r#""cache":true"#));
+        assert!(page.contains(r#""hashes":{"app.wasm":"0123456789ab"}"#));
+
+        let nocache = Toggles {
+            nocache: true,
+            ..Toggles::default()
+        };
+        let page = inject(PAGE, &Versions::default(), &nocache);
+        assert!(!page.contains(r#""cache":true"#));
+    }
+}
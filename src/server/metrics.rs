@@ -0,0 +1,273 @@
+//! Counters of what a server has answered and built (`/__wasmrun/metrics`)
+//!
+//! Every response sent through [`respond`](super::utils::respond) is counted,
+//! with its status class and body size, and every build with its outcome and
+//! duration. The totals, the uptime and the live reload generation are served
+//! as JSON, so a look at a long-running server doesn't need the terminal UI.
+//! Prometheus scrapers, and `?format=prometheus`, get the text exposition
+//! format instead; [`metrics_export`](super::metrics_export) pushes the same
+//! [`samples`] elsewhere.
+
+use super::utils::{content_type_header, respond};
+use crate::error_println;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tiny_http::{Request, Response};
+
+/// Where the counters are served
+pub const METRICS_ROUTE: &str = "/__wasmrun/metrics";
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+static REQUESTS: AtomicU64 = AtomicU64::new(0);
+static CLIENT_ERRORS: AtomicU64 = AtomicU64::new(0);
+static SERVER_ERRORS: AtomicU64 = AtomicU64::new(0);
+static BYTES_SENT: AtomicU64 = AtomicU64::new(0);
+static BUILDS: AtomicU64 = AtomicU64::new(0);
+static BUILD_FAILURES: AtomicU64 = AtomicU64::new(0);
+static BUILD_MILLIS: AtomicU64 = AtomicU64::new(0);
+static LAST_BUILD_MILLIS: AtomicU64 = AtomicU64::new(0);
+
+/// Whether a [`Sample`] only goes up, or can go either way
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Counter,
+    Gauge,
+}
+
+/// The current value of one metric
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: Kind,
+    pub value: f64,
+}
+
+/// Count a response with `status` and a body of `bytes`, when known
+pub fn record(status: u16, bytes: Option<usize>) {
+    STARTED.get_or_init(Instant::now);
+    REQUESTS.fetch_add(1, Ordering::Relaxed);
+    match status {
+        400..=499 => CLIENT_ERRORS.fetch_add(1, Ordering::Relaxed),
+        500..=599 => SERVER_ERRORS.fetch_add(1, Ordering::Relaxed),
+        _ => 0,
+    };
+    BYTES_SENT.fetch_add(bytes.unwrap_or(0) as u64, Ordering::Relaxed);
+}
+
+/// Count a build that took `duration`
+pub fn record_build(success: bool, duration: Duration) {
+    let millis = duration.as_millis() as u64;
+    BUILDS.fetch_add(1, Ordering::Relaxed);
+    if !success {
+        BUILD_FAILURES.fetch_add(1, Ordering::Relaxed);
+    }
+    BUILD_MILLIS.fetch_add(millis, Ordering::Relaxed);
+    LAST_BUILD_MILLIS.store(millis, Ordering::Relaxed);
+}
+
+/// The counters as JSON
+fn snapshot() -> serde_json::Value {
+    serde_json::json!({
+        "uptime_seconds": STARTED.get_or_init(Instant::now).elapsed().as_secs(),
+        "requests": REQUESTS.load(Ordering::Relaxed),
+        "client_errors": CLIENT_ERRORS.load(Ordering::Relaxed),
+        "server_errors": SERVER_ERRORS.load(Ordering::Relaxed),
+        "bytes_sent": BYTES_SENT.load(Ordering::Relaxed),
+        "reload_generation": super::live_reload::generation(),
+        "builds": BUILDS.load(Ordering::Relaxed),
+        "build_failures": BUILD_FAILURES.load(Ordering::Relaxed),
+        "build_time_ms": BUILD_MILLIS.load(Ordering::Relaxed),
+        "last_build_ms": LAST_BUILD_MILLIS.load(Ordering::Relaxed),
+    })
+}
+
+/// Every metric with its current value
+pub fn samples() -> Vec<Sample> {
+    let count = |counter: &AtomicU64| counter.load(Ordering::Relaxed) as f64;
+    let seconds = |millis: &AtomicU64| count(millis) / 1000.0;
+    let sample = |name, help, kind, value| Sample {
+        name,
+        help,
+        kind,
+        value,
+    };
+    vec![
+        sample(
+            "wasmrun_uptime_seconds",
+            "Seconds since the server answered its first request",
+            Kind::Gauge,
+            STARTED.get_or_init(Instant::now).elapsed().as_secs_f64(),
+        ),
+        sample(
+            "wasmrun_http_requests_total",
+            "Responses sent",
+            Kind::Counter,
+            count(&REQUESTS),
+        ),
+        sample(
+            "wasmrun_http_client_errors_total",
+            "Responses sent with a 4xx status",
+            Kind::Counter,
+            count(&CLIENT_ERRORS),
+        ),
+        sample(
+            "wasmrun_http_server_errors_total",
+            "Responses sent with a 5xx status",
+            Kind::Counter,
+            count(&SERVER_ERRORS),
+        ),
+        sample(
+            "wasmrun_http_response_bytes_total",
+            "Bytes of response bodies of known size",
+            Kind::Counter,
+            count(&BYTES_SENT),
+        ),
+        sample(
+            "wasmrun_reloads_total",
+            "Times open pages were told to reload",
+            Kind::Counter,
+            super::live_reload::generation() as f64,
+        ),
+        sample(
+            "wasmrun_builds_total",
+            "Builds run",
+            Kind::Counter,
+            count(&BUILDS),
+        ),
+        sample(
+            "wasmrun_build_failures_total",
+            "Builds that failed",
+            Kind::Counter,
+            count(&BUILD_FAILURES),
+        ),
+        sample(
+            "wasmrun_build_duration_seconds_total",
+            "Time spent building",
+            Kind::Counter,
+            seconds(&BUILD_MILLIS),
+        ),
+        sample(
+            "wasmrun_last_build_duration_seconds",
+            "Duration of the latest build",
+            Kind::Gauge,
+            seconds(&LAST_BUILD_MILLIS),
+        ),
+    ]
+}
+
+/// `samples` in the Prometheus text exposition format, each labelled with `labels`
+pub fn prometheus_text(samples: &[Sample], labels: &[(&str, &str)]) -> String {
+    let labels = if labels.is_empty() {
+        String::new()
+    } else {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(name, value)| {
+                let value = value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n");
+                format!("{name}=\"{value}\"")
+            })
+            .collect();
+        format!("{{{}}}", labels.join(","))
+    };
+    let mut text = String::new();
+    for sample in samples {
+        let kind = match sample.kind {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+        };
+        let _ = writeln!(text, "# HELP {} {}", sample.name, sample.help);
+        let _ = writeln!(text, "# TYPE {} {kind}", sample.name);
+        let _ = writeln!(text, "{}{labels} {}", sample.name, sample.value);
+    }
+    text
+}
+
+/// Whether `request` asks for the Prometheus format, by query or as a scraper would
+fn wants_prometheus(request: &Request) -> bool {
+    let query = request.url().split_once('?').map(|(_, query)| query);
+    if query.is_some_and(|query| query.split('&').any(|pair| pair == "format=prometheus")) {
+        return true;
+    }
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Accept"))
+        .is_some_and(|accept| {
+            let accept = accept.value.as_str();
+            accept.contains("openmetrics-text") || accept.contains("version=0.0.4")
+        })
+}
+
+pub fn serve(request: Request) {
+    let (body, content_type) = if wants_prometheus(&request) {
+        (
+            prometheus_text(&samples(), &[]),
+            "text/plain; version=0.0.4; charset=utf-8",
+        )
+    } else {
+        (
+            serde_json::to_string_pretty(&snapshot()).unwrap_or_default(),
+            "application/json",
+        )
+    };
+    let response = Response::from_string(body)
+        .with_header(content_type_header(content_type))
+        .with_header(tiny_http::Header::from_bytes("Cache-Control", "no-store").unwrap());
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending metrics: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_responses_are_counted_by_status() {
+        let before = snapshot();
+        record(200, Some(1024));
+        record(404, None);
+        record(503, Some(10));
+        let after = snapshot();
+        let grew = |key: &str| after[key].as_u64().unwrap() - before[key].as_u64().unwrap();
+        // Other tests respond concurrently, so only lower bounds hold
+        assert!(grew("requests") >= 3);
+        assert!(grew("client_errors") >= 1);
+        assert!(grew("server_errors") >= 1);
+        assert!(grew("bytes_sent") >= 1034);
+    }
+
+    #[test]
+    fn test_prometheus_text() {
+        let samples = [
+            Sample {
+                name: "wasmrun_builds_total",
+                help: "Builds run",
+                kind: Kind::Counter,
+                value: 3.0,
+            },
+            Sample {
+                name: "wasmrun_last_build_duration_seconds",
+                help: "Duration of the latest build",
+                kind: Kind::Gauge,
+                value: 0.25,
+            },
+        ];
+        assert_eq!(
+            prometheus_text(&samples, &[("instance", "ci \"7\"")]),
+            "# HELP wasmrun_builds_total Builds run\n\
+             # TYPE wasmrun_builds_total counter\n\
+             wasmrun_builds_total{instance=\"ci \\\"7\\\"\"} 3\n\
+             # HELP wasmrun_last_build_duration_seconds Duration of the latest build\n\
+             # TYPE wasmrun_last_build_duration_seconds gauge\n\
+             wasmrun_last_build_duration_seconds{instance=\"ci \\\"7\\\"\"} 0.25\n"
+        );
+        assert!(prometheus_text(&samples[..1], &[]).ends_with("wasmrun_builds_total 3\n"));
+    }
+}
@@ -0,0 +1,399 @@
+//! Pushing server and build metrics to a Pushgateway or an OTLP collector
+//!
+//! `/__wasmrun/metrics` only helps while someone can reach the server. With
+//! `[metrics]` in `wasmrun.toml` the server also pushes its [`samples`] on an
+//! interval, and once more when it stops, so a platform team can collect the
+//! health of every developer's dev server in one place:
+//!
+//! - to a Prometheus Pushgateway, as `PUT /metrics/job/<job>/instance/<instance>`
+//!   in the text exposition format
+//! - to an OpenTelemetry collector, as OTLP/HTTP JSON posted to `/v1/metrics`
+//!
+//! The instance is the server's `--name`, or the host name and port. A push
+//! that fails is reported once, and again only after a push succeeded. Pushes
+//! go through the proxy [`network`](crate::network) picks for their URL, and
+//! the server doesn't start when that proxy is unusable or wasmrun is offline.
+
+use super::metrics::{self, Kind, Sample};
+use crate::config::active_settings;
+use crate::config::project::MetricsSection;
+use crate::error::{Result, WasmrunError};
+use crate::{info_println, warn_println};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Time between pushes unless `metrics.interval_secs` says otherwise
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Pushgateway job and OTLP service name unless `metrics.job` says otherwise
+pub const DEFAULT_JOB: &str = "wasmrun";
+
+/// Where metrics are pushed
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    Pushgateway(String),
+    Otlp(String),
+}
+
+impl Target {
+    /// The URL pushes go to
+    fn url(&self, job: &str, instance: &str) -> String {
+        match self {
+            Target::Pushgateway(base) => format!(
+                "{}/metrics/job/{}/instance/{}",
+                base.trim_end_matches('/'),
+                path_segment(job),
+                path_segment(instance)
+            ),
+            Target::Otlp(base) if base.trim_end_matches('/').ends_with("/v1/metrics") => {
+                base.clone()
+            }
+            Target::Otlp(base) => format!("{}/v1/metrics", base.trim_end_matches('/')),
+        }
+    }
+}
+
+/// What to push where, from `[metrics]`
+#[derive(Debug, Clone, PartialEq)]
+struct Export {
+    targets: Vec<Target>,
+    interval: Duration,
+    job: String,
+    instance: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Export {
+    /// `None` when `section` names nowhere to push to
+    fn from_section(section: MetricsSection, instance: String) -> Option<Self> {
+        let targets: Vec<Target> = section
+            .pushgateway
+            .map(Target::Pushgateway)
+            .into_iter()
+            .chain(section.otlp_endpoint.map(Target::Otlp))
+            .collect();
+        if targets.is_empty() {
+            return None;
+        }
+        Some(Self {
+            targets,
+            interval: section
+                .interval_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_INTERVAL),
+            job: section.job.unwrap_or_else(|| DEFAULT_JOB.to_string()),
+            instance,
+            headers: section.headers.into_iter().collect(),
+        })
+    }
+
+    /// The body of a push to `target`
+    fn body(&self, target: &Target, samples: &[Sample], started: SystemTime) -> String {
+        match target {
+            Target::Pushgateway(_) => metrics::prometheus_text(samples, &[]),
+            Target::Otlp(_) => otlp_json(samples, &self.job, &self.instance, started).to_string(),
+        }
+    }
+
+    fn push(
+        &self,
+        agent: &ureq::Agent,
+        target: &Target,
+        body: &str,
+    ) -> std::result::Result<(), String> {
+        let (method, content_type) = match target {
+            Target::Pushgateway(_) => ("PUT", "text/plain; version=0.0.4"),
+            Target::Otlp(_) => ("POST", "application/json"),
+        };
+        let mut request = agent
+            .request(method, &target.url(&self.job, &self.instance))
+            .set("Content-Type", content_type);
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        request
+            .send_string(body)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Pushes metrics until dropped, then pushes them one last time
+pub struct Exporter {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Start pushing the metrics of the server on `port`, when `[metrics]` says where to.
+/// Fails in offline mode, or when a target's proxy can't be used.
+pub fn start(port: u16) -> Result<Option<Exporter>> {
+    let instance = match super::lifecycle::instance_name() {
+        Some(name) => name.to_string(),
+        None => format!("{}:{port}", host_name()),
+    };
+    let Some(export) = Export::from_section(active_settings().metrics, instance) else {
+        return Ok(None);
+    };
+    crate::offline::require_network("Pushing metrics")?;
+    for target in &export.targets {
+        info_println!(
+            "📈 Pushing metrics every {}s to {}",
+            export.interval.as_secs(),
+            target.url(&export.job, &export.instance)
+        );
+    }
+
+    let started = SystemTime::now();
+    // One agent per target, as each may go through a different proxy
+    let agents = export
+        .targets
+        .iter()
+        .map(|target| {
+            let url = target.url(&export.job, &export.instance);
+            Ok(crate::network::agent_builder(&url)?
+                .timeout(Duration::from_secs(5))
+                .build())
+        })
+        .collect::<Result<Vec<ureq::Agent>>>()?;
+    let (stop, stopped) = mpsc::channel::<()>();
+    let thread = std::thread::Builder::new()
+        .name("wasmrun-metrics".to_string())
+        .spawn(move || {
+            let mut failing = vec![false; export.targets.len()];
+            loop {
+                // A message or a dropped sender both mean the server is stopping
+                let last = !matches!(
+                    stopped.recv_timeout(export.interval),
+                    Err(RecvTimeoutError::Timeout)
+                );
+                let samples = metrics::samples();
+                let targets = export.targets.iter().zip(&agents);
+                for ((target, agent), failing) in targets.zip(failing.iter_mut()) {
+                    let body = export.body(target, &samples, started);
+                    match export.push(agent, target, &body) {
+                        Ok(()) => *failing = false,
+                        Err(e) if !*failing => {
+                            *failing = true;
+                            warn_println!("Pushing metrics failed: {e}");
+                        }
+                        Err(_) => {}
+                    }
+                }
+                if last {
+                    break;
+                }
+            }
+        })
+        .map_err(|e| WasmrunError::add_context("Starting the metrics exporter", e))?;
+    Ok(Some(Exporter {
+        stop: Some(stop),
+        thread: Some(thread),
+    }))
+}
+
+/// The name of this machine, for the instance label
+fn host_name() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// `value` percent-encoded for a URL path segment
+fn path_segment(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b':' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Nanoseconds since the epoch, as OTLP JSON wants them: a string
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+/// `samples` as an OTLP `ExportMetricsServiceRequest` in JSON. Counters become
+/// cumulative sums counted from `started`, named without Prometheus' `_total`
+fn otlp_json(
+    samples: &[Sample],
+    job: &str,
+    instance: &str,
+    started: SystemTime,
+) -> serde_json::Value {
+    let now = unix_nanos(SystemTime::now());
+    let start = unix_nanos(started);
+    let attribute = |key: &str, value: &str| serde_json::json!({ "key": key, "value": { "stringValue": value } });
+    let metrics: Vec<serde_json::Value> = samples
+        .iter()
+        .map(|sample| {
+            let unit = if sample.name.contains("seconds") {
+                "s"
+            } else if sample.name.contains("bytes") {
+                "By"
+            } else {
+                "1"
+            };
+            match sample.kind {
+                Kind::Counter => serde_json::json!({
+                    "name": sample.name.trim_end_matches("_total"),
+                    "description": sample.help,
+                    "unit": unit,
+                    "sum": {
+                        "aggregationTemporality": 2,
+                        "isMonotonic": true,
+                        "dataPoints": [{
+                            "startTimeUnixNano": start,
+                            "timeUnixNano": now,
+                            "asDouble": sample.value,
+                        }],
+                    },
+                }),
+                Kind::Gauge => serde_json::json!({
+                    "name": sample.name,
+                    "description": sample.help,
+                    "unit": unit,
+                    "gauge": {
+                        "dataPoints": [{ "timeUnixNano": now, "asDouble": sample.value }],
+                    },
+                }),
+            }
+        })
+        .collect();
+    serde_json::json!({
+        "resourceMetrics": [{
+            "resource": {
+                "attributes": [
+                    attribute("service.name", job),
+                    attribute("service.instance.id", instance),
+                    attribute("service.version", env!("CARGO_PKG_VERSION")),
+                ],
+            },
+            "scopeMetrics": [{
+                "scope": { "name": "wasmrun", "version": env!("CARGO_PKG_VERSION") },
+                "metrics": metrics,
+            }],
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(pushgateway: Option<&str>, otlp_endpoint: Option<&str>) -> Option<Export> {
+        Export::from_section(
+            MetricsSection {
+                pushgateway: pushgateway.map(str::to_string),
+                otlp_endpoint: otlp_endpoint.map(str::to_string),
+                headers: [("Authorization".to_string(), "Bearer t".to_string())].into(),
+                ..MetricsSection::default()
+            },
+            "dev box:8420".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_targets_and_urls() {
+        assert_eq!(export(None, None), None);
+        let export = export(Some("http://pg:9091/"), Some("http://otel:4318")).unwrap();
+        assert_eq!(export.interval, DEFAULT_INTERVAL);
+        let urls: Vec<String> = export
+            .targets
+            .iter()
+            .map(|target| target.url(&export.job, &export.instance))
+            .collect();
+        assert_eq!(
+            urls,
+            [
+                "http://pg:9091/metrics/job/wasmrun/instance/dev%20box:8420",
+                "http://otel:4318/v1/metrics"
+            ]
+        );
+        let otlp = Target::Otlp("https://otel.example/v1/metrics".to_string());
+        assert_eq!(otlp.url("j", "i"), "https://otel.example/v1/metrics");
+    }
+
+    #[test]
+    fn test_otlp_json() {
+        let samples = [
+            Sample {
+                name: "wasmrun_builds_total",
+                help: "Builds run",
+                kind: Kind::Counter,
+                value: 2.0,
+            },
+            Sample {
+                name: "wasmrun_uptime_seconds",
+                help: "Uptime",
+                kind: Kind::Gauge,
+                value: 9.5,
+            },
+        ];
+        let json = otlp_json(&samples, "ci", "runner-4", UNIX_EPOCH);
+        let resource = &json["resourceMetrics"][0];
+        assert_eq!(
+            resource["resource"]["attributes"][1]["value"]["stringValue"],
+            "runner-4"
+        );
+        let metrics = &resource["scopeMetrics"][0]["metrics"];
+        assert_eq!(metrics[0]["name"], "wasmrun_builds");
+        assert_eq!(metrics[0]["sum"]["isMonotonic"], true);
+        assert_eq!(metrics[0]["sum"]["dataPoints"][0]["startTimeUnixNano"], "0");
+        assert_eq!(metrics[0]["sum"]["dataPoints"][0]["asDouble"], 2.0);
+        assert_eq!(metrics[1]["unit"], "s");
+        assert_eq!(metrics[1]["gauge"]["dataPoints"][0]["asDouble"], 9.5);
+    }
+
+    #[test]
+    fn test_push_reaches_the_pushgateway() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let export = export(Some(&format!("http://127.0.0.1:{port}")), None).unwrap();
+        let received = std::thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let authorization = request
+                .headers()
+                .iter()
+                .find(|header| header.field.equiv("Authorization"))
+                .map(|header| header.value.to_string());
+            let summary = (request.method().to_string(), request.url().to_string());
+            request.respond(tiny_http::Response::empty(200)).unwrap();
+            (summary, authorization, body)
+        });
+
+        let target = &export.targets[0];
+        let samples = metrics::samples();
+        let body = export.body(target, &samples, SystemTime::now());
+        let agent = ureq::AgentBuilder::new().build();
+        export.push(&agent, target, &body).unwrap();
+
+        let ((method, url), authorization, body) = received.join().unwrap();
+        assert_eq!(method, "PUT");
+        assert_eq!(url, "/metrics/job/wasmrun/instance/dev%20box:8420");
+        assert_eq!(authorization.as_deref(), Some("Bearer t"));
+        assert!(body.contains("# TYPE wasmrun_builds_total counter"));
+    }
+}
@@ -0,0 +1,501 @@
+//! Project middleware scripts (`middleware.rhai`)
+//!
+//! A project can customize the dev server without forking wasmrun by putting
+//! a [Rhai](https://rhai.rs) script named `middleware.rhai` in its root. Both
+//! hooks are optional:
+//!
+//! ```rhai
+//! // Called before routing. Return nothing to continue, the (modified)
+//! // request to rewrite its path or query, or a response map to answer
+//! // the request directly.
+//! fn on_request(req) {
+//!     if req.path == "/api/user" {
+//!         return #{ status: 200, headers: #{ "Content-Type": "application/json" }, body: `{"name":"dev"}` };
+//!     }
+//!     if req.path.starts_with("/legacy/") {
+//!         req.path = req.path.sub_string(7);
+//!         return req;
+//!     }
+//! }
+//!
+//! // Called before any response is sent. Return nothing to keep it, or the
+//! // (modified) response to change its status, headers or body.
+//! fn on_response(req, res) {
+//!     res.headers["X-Served-By"] = "wasmrun";
+//!     res
+//! }
+//! ```
+//!
+//! The script is recompiled when it changes on disk. A script that fails to
+//! compile or run is reported and the request is served as if there was no
+//! middleware.
+
+use crate::{error_println, info_println, warn_println};
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tiny_http::{Header, Request, Response, ResponseBox, StatusCode};
+
+/// Script looked up in the project directory
+pub const MIDDLEWARE_FILE: &str = "middleware.rhai";
+
+/// Upper bound on the work a single hook call may do, so a runaway loop
+/// cannot hang the server
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+static ACTIVE: Mutex<Option<Middleware>> = Mutex::new(None);
+
+/// The parts of a request exposed to scripts as `req`
+#[derive(Debug, Clone)]
+pub struct RequestInfo {
+    pub method: String,
+    pub url: String,
+    pub client: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl RequestInfo {
+    /// Describe `request`, using `url` in place of its raw URL
+    pub fn new(request: &Request, url: &str) -> Self {
+        Self {
+            method: request.method().to_string(),
+            url: url.to_string(),
+            client: request
+                .remote_addr()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| "unknown".to_string()),
+            headers: header_pairs(request.headers()),
+        }
+    }
+
+    fn to_map(&self) -> Map {
+        let (path, query) = match self.url.split_once('?') {
+            Some((path, query)) => (path, query),
+            None => (self.url.as_str(), ""),
+        };
+        let mut map = Map::new();
+        map.insert("method".into(), self.method.clone().into());
+        map.insert("path".into(), path.to_string().into());
+        map.insert("query".into(), query.to_string().into());
+        map.insert("client".into(), self.client.clone().into());
+        map.insert("headers".into(), headers_to_map(&self.headers).into());
+        map
+    }
+}
+
+/// A response produced or modified by a script
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// `None` keeps the original body
+    pub body: Option<Vec<u8>>,
+}
+
+impl ScriptResponse {
+    fn tiny_headers(&self) -> Vec<Header> {
+        self.headers
+            .iter()
+            .filter_map(|(name, value)| {
+                Header::from_bytes(name.as_bytes(), value.as_bytes())
+                    .map_err(|_| warn_println!("Middleware: ignoring invalid header: {name}"))
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// Build the response for a request answered directly by the script
+    pub fn into_response(self) -> Response<Cursor<Vec<u8>>> {
+        let headers = self.tiny_headers();
+        let body = self.body.unwrap_or_default();
+        let length = body.len();
+        Response::new(
+            StatusCode(self.status),
+            headers,
+            Cursor::new(body),
+            Some(length),
+            None,
+        )
+    }
+}
+
+/// What `on_request` decided
+#[derive(Debug, PartialEq)]
+pub enum RequestAction {
+    /// Serve this URL
+    Continue(String),
+    /// Answer with this response without routing the request
+    Respond(ScriptResponse),
+}
+
+struct Middleware {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    engine: Engine,
+    ast: Option<AST>,
+}
+
+impl Middleware {
+    fn load(path: PathBuf) -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let mut middleware = Self {
+            path,
+            modified: None,
+            engine,
+            ast: None,
+        };
+        middleware.compile();
+        middleware
+    }
+
+    fn compile(&mut self) {
+        self.modified = modified_time(&self.path);
+        self.ast = match std::fs::read_to_string(&self.path) {
+            Ok(source) => match self.compile_source(&source) {
+                Ok(ast) => Some(ast),
+                Err(e) => {
+                    error_println!("Middleware {}: {e}", self.path.display());
+                    None
+                }
+            },
+            Err(e) => {
+                error_println!("Failed to read middleware {}: {e}", self.path.display());
+                None
+            }
+        };
+    }
+
+    fn compile_source(&self, source: &str) -> Result<AST, String> {
+        self.engine.compile(source).map_err(|e| e.to_string())
+    }
+
+    /// Recompile the script if it changed since it was loaded
+    fn refresh(&mut self) {
+        if modified_time(&self.path) != self.modified {
+            info_println!("🔄 Reloading middleware: {}", self.path.display());
+            self.compile();
+        }
+    }
+
+    fn call(&self, name: &str, args: Vec<Dynamic>) -> Result<Option<Map>, String> {
+        let Some(ast) = &self.ast else {
+            return Ok(None);
+        };
+        if !ast.iter_functions().any(|f| f.name == name) {
+            return Ok(None);
+        }
+
+        let options = CallFnOptions::new().eval_ast(false);
+        let result: Dynamic = self
+            .engine
+            .call_fn_with_options(options, &mut Scope::new(), ast, name, args)
+            .map_err(|e| e.to_string())?;
+
+        if result.is_unit() {
+            return Ok(None);
+        }
+        let type_name = result.type_name();
+        result
+            .try_cast::<Map>()
+            .map(Some)
+            .ok_or_else(|| format!("{name} must return a map or nothing, got {type_name}"))
+    }
+
+    fn on_request(&self, info: &RequestInfo) -> Result<RequestAction, String> {
+        let Some(map) = self.call("on_request", vec![info.to_map().into()])? else {
+            return Ok(RequestAction::Continue(info.url.clone()));
+        };
+
+        if map.contains_key("status") || map.contains_key("body") {
+            return Ok(RequestAction::Respond(response_from_map(&map, 200, &[])?));
+        }
+
+        let path = match map.get("path") {
+            Some(path) => path
+                .clone()
+                .into_string()
+                .map_err(|_| "req.path must be a string".to_string())?,
+            None => info.url.split('?').next().unwrap_or_default().to_string(),
+        };
+        if !path.starts_with('/') {
+            return Err(format!("req.path must start with '/', got {path:?}"));
+        }
+        let query = match map.get("query") {
+            Some(query) => query
+                .clone()
+                .into_string()
+                .map_err(|_| "req.query must be a string".to_string())?,
+            None => String::new(),
+        };
+
+        Ok(RequestAction::Continue(if query.is_empty() {
+            path
+        } else {
+            format!("{path}?{query}")
+        }))
+    }
+
+    fn on_response(
+        &self,
+        info: &RequestInfo,
+        status: u16,
+        headers: &[(String, String)],
+    ) -> Result<Option<ScriptResponse>, String> {
+        let mut res = Map::new();
+        res.insert("status".into(), (status as i64).into());
+        res.insert("headers".into(), headers_to_map(headers).into());
+
+        match self.call("on_response", vec![info.to_map().into(), res.into()])? {
+            Some(map) => response_from_map(&map, status, headers).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Use the middleware script in `dir`, if there is one
+pub fn activate(dir: &Path) {
+    let path = dir.join(MIDDLEWARE_FILE);
+    if !path.is_file() {
+        return;
+    }
+
+    info_println!("🧩 Using middleware: {}", path.display());
+    if let Ok(mut active) = ACTIVE.lock() {
+        *active = Some(Middleware::load(path));
+    }
+}
+
+fn with_active<T>(f: impl FnOnce(&Middleware) -> T) -> Option<T> {
+    let mut active = ACTIVE.lock().ok()?;
+    let middleware = active.as_mut()?;
+    middleware.refresh();
+    Some(f(middleware))
+}
+
+/// Run `on_request` for `request`, whose URL has been routed to `url`
+pub fn on_request(request: &Request, url: String) -> RequestAction {
+    let info = RequestInfo::new(request, &url);
+    match with_active(|middleware| middleware.on_request(&info)) {
+        Some(Ok(action)) => action,
+        Some(Err(e)) => {
+            error_println!("Middleware on_request failed: {e}");
+            RequestAction::Continue(url)
+        }
+        None => RequestAction::Continue(url),
+    }
+}
+
+/// Run `on_response` and apply its changes to `response`
+pub fn on_response<R>(request: &Request, response: Response<R>) -> ResponseBox
+where
+    R: Read + Send + 'static,
+{
+    let info = RequestInfo::new(request, request.url());
+    let headers = header_pairs(response.headers());
+    let changes = match with_active(|middleware| {
+        middleware.on_response(&info, response.status_code().0, &headers)
+    }) {
+        Some(Ok(Some(changes))) => changes,
+        Some(Err(e)) => {
+            error_println!("Middleware on_response failed: {e}");
+            return response.boxed();
+        }
+        _ => return response.boxed(),
+    };
+
+    let status = StatusCode(changes.status);
+    let tiny_headers = changes.tiny_headers();
+    match changes.body {
+        Some(body) => {
+            let length = body.len();
+            Response::new(status, tiny_headers, Cursor::new(body), Some(length), None).boxed()
+        }
+        None => {
+            let length = response.data_length();
+            let chunked_threshold = response.chunked_threshold();
+            Response::new(status, tiny_headers, response.into_reader(), length, None)
+                .with_chunked_threshold(chunked_threshold)
+                .boxed()
+        }
+    }
+}
+
+fn response_from_map(
+    map: &Map,
+    default_status: u16,
+    default_headers: &[(String, String)],
+) -> Result<ScriptResponse, String> {
+    let status = match map.get("status") {
+        Some(status) => status
+            .as_int()
+            .ok()
+            .and_then(|status| u16::try_from(status).ok())
+            .filter(|status| (100..=599).contains(status))
+            .ok_or_else(|| format!("status must be an HTTP status code, got {status}"))?,
+        None => default_status,
+    };
+
+    let mut headers = match map.get("headers") {
+        Some(headers) => {
+            let headers = headers
+                .clone()
+                .try_cast::<Map>()
+                .ok_or_else(|| "headers must be a map".to_string())?;
+            headers
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect()
+        }
+        None => default_headers.to_vec(),
+    };
+
+    let body = match map.get("body") {
+        None => None,
+        Some(body) if body.is_blob() => Some(body.clone().cast::<rhai::Blob>()),
+        Some(body) if body.is_map() => {
+            if !headers.iter().any(|(name, _)| is_content_type(name)) {
+                headers.push(("Content-Type".to_string(), "application/json".to_string()));
+            }
+            Some(rhai::format_map_as_json(&body.clone().cast::<Map>()).into_bytes())
+        }
+        Some(body) => Some(body.to_string().into_bytes()),
+    };
+    if body.is_some() && !headers.iter().any(|(name, _)| is_content_type(name)) {
+        headers.push(("Content-Type".to_string(), "text/plain".to_string()));
+    }
+
+    Ok(ScriptResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+fn is_content_type(name: &str) -> bool {
+    name.eq_ignore_ascii_case("content-type")
+}
+
+fn header_pairs(headers: &[Header]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|header| (header.field.to_string(), header.value.to_string()))
+        .collect()
+}
+
+fn headers_to_map(headers: &[(String, String)]) -> Map {
+    headers
+        .iter()
+        .map(|(name, value)| (name.as_str().into(), value.clone().into()))
+        .collect()
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(source: &str) -> Middleware {
+        let mut middleware = Middleware::load(PathBuf::from("missing-middleware.rhai"));
+        middleware.ast = Some(middleware.compile_source(source).unwrap());
+        middleware
+    }
+
+    fn request(url: &str) -> RequestInfo {
+        RequestInfo {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            client: "127.0.0.1:5000".to_string(),
+            headers: vec![("Accept".to_string(), "text/html".to_string())],
+        }
+    }
+
+    #[test]
+    fn test_on_request_rewrites_and_mocks() {
+        let middleware = script(
+            r#"
+            fn on_request(req) {
+                if req.path == "/api/user" {
+                    return #{ status: 201, body: #{ name: "dev" } };
+                }
+                if req.path.starts_with("/legacy/") {
+                    req.path = req.path.sub_string(7);
+                    return req;
+                }
+            }
+            "#,
+        );
+
+        assert_eq!(
+            middleware
+                .on_request(&request("/legacy/app.js?v=2"))
+                .unwrap(),
+            RequestAction::Continue("/app.js?v=2".to_string())
+        );
+        assert_eq!(
+            middleware.on_request(&request("/index.html")).unwrap(),
+            RequestAction::Continue("/index.html".to_string())
+        );
+
+        let RequestAction::Respond(mock) = middleware.on_request(&request("/api/user")).unwrap()
+        else {
+            panic!("expected a mock response");
+        };
+        assert_eq!(mock.status, 201);
+        assert_eq!(mock.body.as_deref(), Some(br#"{"name":"dev"}"#.as_slice()));
+        assert!(mock
+            .headers
+            .contains(&("Content-Type".to_string(), "application/json".to_string())));
+    }
+
+    #[test]
+    fn test_on_response_edits_headers() {
+        let middleware = script(
+            r#"
+            fn on_response(req, res) {
+                if req.headers["Accept"] == "text/html" {
+                    res.headers["X-Env"] = "dev";
+                    res.headers.remove("Server");
+                    return res;
+                }
+            }
+            "#,
+        );
+        let headers = vec![("Server".to_string(), "tiny-http".to_string())];
+
+        let changes = middleware
+            .on_response(&request("/"), 200, &headers)
+            .unwrap()
+            .unwrap();
+        assert_eq!(changes.status, 200);
+        assert_eq!(
+            changes.headers,
+            vec![("X-Env".to_string(), "dev".to_string())]
+        );
+        assert_eq!(changes.body, None);
+
+        let mut json = request("/");
+        json.headers.clear();
+        assert_eq!(middleware.on_response(&json, 200, &headers).unwrap(), None);
+    }
+
+    #[test]
+    fn test_script_errors_are_reported() {
+        let middleware = script(
+            r#"
+            fn on_request(req) { 42 }
+            fn on_response(req, res) { #{ status: 1000 } }
+            "#,
+        );
+        assert!(middleware.on_request(&request("/")).is_err());
+        assert!(middleware.on_response(&request("/"), 200, &[]).is_err());
+
+        let endless = script("fn on_request(req) { loop {} }");
+        assert!(endless.on_request(&request("/")).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+//! Content types of served files
+//!
+//! Every file the servers send gets its `Content-Type` from the extension,
+//! looked up first in the `[mime]` table of `wasmrun.toml` and then in the
+//! built-in list below. Unknown extensions are sent as
+//! `application/octet-stream`.
+//!
+//! ```toml
+//! [mime]
+//! cwasm = "application/wasm"
+//! glsl = "text/plain"
+//! ```
+
+use crate::config::active_settings;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// For extensions neither table knows
+pub const DEFAULT: &str = "application/octet-stream";
+
+/// Built-in types, by lowercase extension
+const BUILTIN: &[(&str, &str)] = &[
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("js", "application/javascript"),
+    ("mjs", "application/javascript"),
+    ("cjs", "application/javascript"),
+    ("ts", "application/typescript"),
+    ("json", "application/json"),
+    ("map", "application/json"),
+    ("webmanifest", "application/manifest+json"),
+    ("wasm", "application/wasm"),
+    ("wat", "text/plain"),
+    ("data", "application/octet-stream"),
+    ("txt", "text/plain"),
+    ("md", "text/markdown"),
+    ("csv", "text/csv"),
+    ("xml", "application/xml"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("svg", "image/svg+xml"),
+    ("ico", "image/x-icon"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+    ("mp3", "audio/mpeg"),
+    ("wav", "audio/wav"),
+    ("ogg", "audio/ogg"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+    ("glb", "model/gltf-binary"),
+    ("gltf", "model/gltf+json"),
+];
+
+/// The content type for `path`, from its extension
+pub fn for_path(path: &Path) -> String {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(extension) => for_extension(extension),
+        None => DEFAULT.to_string(),
+    }
+}
+
+/// The content type for files ending in `.extension`
+pub fn for_extension(extension: &str) -> String {
+    lookup(&active_settings().mime, extension)
+}
+
+/// `extension` in the overrides, then the built-in list; case doesn't matter
+fn lookup(overrides: &BTreeMap<String, String>, extension: &str) -> String {
+    let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+    overrides
+        .iter()
+        .find(|(key, _)| key.trim_start_matches('.').eq_ignore_ascii_case(&extension))
+        .map(|(_, content_type)| content_type.as_str())
+        .or_else(|| {
+            BUILTIN
+                .iter()
+                .find(|(key, _)| *key == extension)
+                .map(|(_, content_type)| *content_type)
+        })
+        .unwrap_or(DEFAULT)
+        .to_string()
+}
+
+/// Whether `content_type` looks like `type/subtype`, optionally with parameters
+pub fn is_valid(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    let token = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+    };
+    matches!(essence.split_once('/'), Some((kind, subtype)) if token(kind) && token(subtype))
+        && !content_type.contains(['\r', '\n'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let overrides = BTreeMap::from([
+            ("cwasm".to_string(), "application/wasm".to_string()),
+            (".JS".to_string(), "text/javascript".to_string()),
+        ]);
+        assert_eq!(lookup(&overrides, "cwasm"), "application/wasm");
+        assert_eq!(lookup(&overrides, "js"), "text/javascript");
+        assert_eq!(lookup(&overrides, "WOFF2"), "font/woff2");
+        assert_eq!(
+            lookup(&overrides, "webmanifest"),
+            "application/manifest+json"
+        );
+        assert_eq!(lookup(&BTreeMap::new(), "js"), "application/javascript");
+        assert_eq!(lookup(&BTreeMap::new(), "unknown"), DEFAULT);
+    }
+
+    #[test]
+    fn test_is_valid() {
+        assert!(is_valid("application/wasm"));
+        assert!(is_valid("text/plain; charset=utf-8"));
+        assert!(!is_valid("wasm"));
+        assert!(!is_valid("text/"));
+        assert!(!is_valid("text/plain\r\nX-Injected: 1"));
+    }
+}
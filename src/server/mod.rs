@@ -1,10 +1,68 @@
+pub mod a11y;
+pub mod access_log;
+pub mod ansi;
 mod api;
+pub mod asc;
+pub mod base_path;
+pub mod browser;
+pub mod build_status;
+pub mod bundle;
+mod cache_bust;
+pub mod clipboard;
+pub mod cluster;
+pub mod compare;
+pub mod component;
+mod compression;
+pub mod conditional;
+pub mod console;
+pub mod control;
+pub mod dedup;
+pub mod delta;
+pub mod demo;
+pub mod emscripten;
+pub mod failures;
+mod features;
+pub mod file_cache;
+pub mod gallery;
+pub mod gojs;
 mod handler;
+pub mod headers;
+mod import_map;
+pub mod isolation;
+pub mod landing;
 mod lifecycle;
+pub mod limits;
+pub mod listing;
+pub mod live_reload;
+pub mod loading;
+pub mod metrics;
+pub mod metrics_export;
+pub mod middleware;
+pub mod mime;
+mod plugin_routes;
+mod pool;
+mod port;
+pub mod preview;
+pub mod profiler;
+pub mod proxy;
+pub mod pyodide;
+mod range;
+pub mod replay;
 mod runner;
+pub mod shutdown;
+pub mod site;
+pub mod status;
+pub mod timeouts;
+pub mod tls;
+pub mod toggles;
+pub mod traps;
+pub mod urls;
 pub mod utils;
 pub mod wasm;
 
-pub use lifecycle::{is_server_running, stop_existing_server};
+pub use lifecycle::{
+    is_server_running, is_valid_instance_name, replace_named_instance, running_instances,
+    set_instance_name, stop_existing_server, stop_matching, InstanceGuard,
+};
 pub use runner::run_wasm_file;
 pub use utils::ServerUtils;
@@ -0,0 +1,58 @@
+//! Requests answered by the plugin that builds the project
+//!
+//! A plugin lists URL prefixes in its metadata (see [`crate::plugin::hooks`]),
+//! and requests under them are handed to it with their method, headers and
+//! body, ahead of wasmrun's own routes.
+
+use super::utils::respond;
+use crate::plugin::hooks::{self, RouteRequest};
+use crate::{error_println, info_println};
+use std::io::Cursor;
+use tiny_http::{Header, Request, Response, StatusCode};
+
+/// Answer `request` with a plugin when its path, `url`, is one of a plugin's
+/// routes, and hand it back otherwise
+pub fn forward(mut request: Request, url: &str) -> Option<Request> {
+    if !hooks::is_routed(url) {
+        return Some(request);
+    }
+    info_println!("🔌 Plugin route: {} {url}", request.method());
+
+    let mut body = Vec::new();
+    if let Err(e) = request.as_reader().read_to_end(&mut body) {
+        error_println!("Error reading request body for plugin: {e}");
+        return None;
+    }
+    let route_request = RouteRequest {
+        method: request.method().to_string(),
+        url: url.to_string(),
+        headers: request
+            .headers()
+            .iter()
+            .map(|header| (header.field.to_string(), header.value.to_string()))
+            .collect(),
+        body: String::from_utf8_lossy(&body).to_string(),
+    };
+    let Some(answer) = hooks::handle_route(&route_request) else {
+        // The plugin went away since the server started
+        return Some(request);
+    };
+
+    let headers = answer
+        .headers
+        .iter()
+        .filter_map(|(name, value)| Header::from_bytes(name.as_bytes(), value.as_bytes()).ok())
+        .collect();
+    let length = answer.body.len();
+    let response = Response::new(
+        StatusCode(answer.status),
+        headers,
+        Cursor::new(answer.body.into_bytes()),
+        Some(length),
+        None,
+    );
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending plugin response: {e}");
+    }
+    None
+}
@@ -0,0 +1,152 @@
+//! Concurrent request handling
+//!
+//! tiny_http hands out requests one at a time. The accepting loop passes each
+//! one on to a fixed set of worker threads, so a large wasm download no longer
+//! holds up the reload polls and asset fetches queued behind it. The loop ends
+//! when the server is unblocked on shutdown; the workers then finish the
+//! requests they already have and exit.
+//!
+//! A handler that panics only loses its own request: the worker catches the
+//! panic and carries on, and tiny_http answers the dropped request with a 500.
+
+use crate::error_println;
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use tiny_http::{Request, Server};
+
+/// Fewest workers, so a few slow downloads cannot stall a small machine
+const MIN_WORKERS: usize = 8;
+
+/// Most workers, however many cores there are
+const MAX_WORKERS: usize = 32;
+
+/// Number of worker threads handling requests
+pub fn workers() -> usize {
+    std::thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+        .saturating_mul(2)
+        .clamp(MIN_WORKERS, MAX_WORKERS)
+}
+
+/// Accept requests on `server` until it is unblocked, handling each with
+/// `handle` on one of `workers` threads
+pub fn serve<F>(server: &Server, workers: usize, handle: F)
+where
+    F: Fn(Request) + Sync,
+{
+    let (sender, receiver) = mpsc::channel::<Request>();
+    let receiver = Mutex::new(receiver);
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers.max(1) {
+            scope.spawn(|| loop {
+                // Hold the lock only while taking a request, not while handling it
+                let request = match receiver.lock() {
+                    Ok(receiver) => receiver.recv(),
+                    Err(_) => return,
+                };
+                match request {
+                    Ok(request) => {
+                        super::access_log::begin();
+                        let url = request.url().to_string();
+                        if panic::catch_unwind(AssertUnwindSafe(|| handle(request))).is_err() {
+                            error_println!("Handling {url} panicked; answered with 500");
+                        }
+                    }
+                    Err(_) => return,
+                }
+            });
+        }
+
+        for request in server.incoming_requests() {
+            if sender.send(request).is_err() {
+                break;
+            }
+        }
+        // Let the workers drain the queue and stop
+        drop(sender);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::mpsc::RecvTimeoutError;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tiny_http::Response;
+
+    fn get(port: u16, path: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(
+            stream,
+            "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_slow_request_does_not_block_others() {
+        let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+        let port = server.server_addr().to_ip().unwrap().port();
+        let (release, released) = mpsc::channel::<()>();
+        let released = Mutex::new(released);
+
+        let serving = Arc::clone(&server);
+        let handle = std::thread::spawn(move || {
+            serve(&serving, 2, |request| {
+                if request.url() == "/slow" {
+                    // Stays busy until the fast request has been answered
+                    let waited = released
+                        .lock()
+                        .unwrap()
+                        .recv_timeout(Duration::from_secs(10));
+                    assert_ne!(waited, Err(RecvTimeoutError::Timeout));
+                }
+                let body = request.url().to_string();
+                request.respond(Response::from_string(body)).unwrap();
+            });
+        });
+
+        let slow = std::thread::spawn(move || get(port, "/slow"));
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(get(port, "/fast").ends_with("/fast"));
+        release.send(()).unwrap();
+        assert!(slow.join().unwrap().ends_with("/slow"));
+
+        server.unblock();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_panicking_handler_answers_500_and_keeps_serving() {
+        let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+        let port = server.server_addr().to_ip().unwrap().port();
+
+        let serving = Arc::clone(&server);
+        let handle = std::thread::spawn(move || {
+            serve(&serving, 1, |request| {
+                if request.url() == "/panic" {
+                    panic!("handler failed");
+                }
+                let body = request.url().to_string();
+                request.respond(Response::from_string(body)).unwrap();
+            });
+        });
+
+        assert!(get(port, "/panic").starts_with("HTTP/1.1 500"));
+        // The only worker survived the panic
+        assert!(get(port, "/after").ends_with("/after"));
+
+        server.unblock();
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,242 @@
+//! Finding out which process holds a busy port
+//!
+//! Wasmrun servers are looked up in the instance registry first. Other
+//! processes are found through `/proc` on Linux, `lsof` on macOS and the BSDs,
+//! and `netstat` on Windows, where the process is then named through its handle.
+
+use super::lifecycle::instance_on_port;
+use crate::registry::ServerInstance;
+use std::io::{IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+/// A process listening on a port
+#[derive(Debug)]
+pub struct PortOwner {
+    pub pid: u32,
+    pub name: Option<String>,
+    /// Set when the owner is a wasmrun server
+    pub instance: Option<ServerInstance>,
+}
+
+impl PortOwner {
+    pub fn describe(&self) -> String {
+        match (&self.instance, &self.name) {
+            (Some(instance), _) => {
+                let serving = instance
+                    .project_path
+                    .as_deref()
+                    .unwrap_or(&instance.wasm_path);
+                let name = instance
+                    .name
+                    .as_deref()
+                    .map(|name| format!("'{name}' "))
+                    .unwrap_or_default();
+                format!(
+                    "another wasmrun server {name}(PID {}) serving {serving}",
+                    self.pid
+                )
+            }
+            (None, Some(name)) => format!("{name} (PID {})", self.pid),
+            (None, None) => format!("PID {}", self.pid),
+        }
+    }
+}
+
+/// Identify the process listening on `port`
+pub fn find_owner(port: u16) -> Option<PortOwner> {
+    if let Some(instance) = instance_on_port(port) {
+        return Some(PortOwner {
+            pid: instance.pid,
+            name: Some("wasmrun".to_string()),
+            instance: Some(instance),
+        });
+    }
+
+    let (pid, name) = platform::find_listener(port)?;
+    Some(PortOwner {
+        pid,
+        name,
+        instance: None,
+    })
+}
+
+/// Ask a yes/no question on the terminal; `false` when not interactive
+pub fn confirm(question: &str) -> bool {
+    if !std::io::stdin().is_terminal() || crate::tui::is_active() {
+        return false;
+    }
+
+    print!("❓ {question} [Y/n] ");
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "" | "y" | "yes")
+}
+
+/// Wait briefly for a stopped process to release `port`
+pub fn wait_until_free(port: u16) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(3);
+    while Instant::now() < deadline {
+        if super::utils::is_port_available(port) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+/// Socket inode of a listener on `port` in a `/proc/net/tcp` line
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_proc_net_line(line: &str, port: u16) -> Option<u64> {
+    const LISTEN: &str = "0A";
+
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let local_port = fields.get(1)?.rsplit(':').next()?;
+    if u16::from_str_radix(local_port, 16).ok()? != port || *fields.get(3)? != LISTEN {
+        return None;
+    }
+    fields.get(9)?.parse().ok()
+}
+
+/// Process ID and command from `lsof -F pc` output
+#[cfg_attr(any(target_os = "linux", windows), allow(dead_code))]
+fn parse_lsof(output: &str) -> Option<(u32, Option<String>)> {
+    let pid = output.lines().find_map(|line| line.strip_prefix('p'))?;
+    let name = output
+        .lines()
+        .find_map(|line| line.strip_prefix('c'))
+        .map(str::to_string);
+    Some((pid.parse().ok()?, name))
+}
+
+/// Process ID listening on `port` in `netstat -ano` output
+#[cfg_attr(not(windows), allow(dead_code))]
+fn parse_netstat(output: &str, port: u16) -> Option<u32> {
+    output.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [proto, local, _, state, pid]
+                if proto.eq_ignore_ascii_case("tcp")
+                    && state.eq_ignore_ascii_case("listening")
+                    && local.rsplit(':').next()? == port.to_string() =>
+            {
+                pid.parse().ok()
+            }
+            _ => None,
+        }
+    })
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+
+    pub fn find_listener(port: u16) -> Option<(u32, Option<String>)> {
+        let inodes: Vec<u64> = ["/proc/net/tcp", "/proc/net/tcp6"]
+            .iter()
+            .filter_map(|table| fs::read_to_string(table).ok())
+            .flat_map(|table| {
+                table
+                    .lines()
+                    .skip(1)
+                    .filter_map(|line| super::parse_proc_net_line(line, port))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        if inodes.is_empty() {
+            return None;
+        }
+
+        // Processes of other users cannot be inspected without privileges
+        for entry in fs::read_dir("/proc").ok()?.flatten() {
+            let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse().ok()) else {
+                continue;
+            };
+            let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+                continue;
+            };
+            let holds_socket = fds.flatten().any(|fd| {
+                fs::read_link(fd.path()).is_ok_and(|target| {
+                    inodes
+                        .iter()
+                        .any(|inode| target.to_string_lossy() == format!("socket:[{inode}]"))
+                })
+            });
+            if holds_socket {
+                let name = fs::read_to_string(entry.path().join("comm"))
+                    .ok()
+                    .map(|name| name.trim().to_string());
+                return Some((pid, name));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::process::Command;
+
+    pub fn find_listener(port: u16) -> Option<(u32, Option<String>)> {
+        let output = Command::new("netstat")
+            .args(["-ano", "-p", "TCP"])
+            .output()
+            .ok()?;
+        let pid = super::parse_netstat(&String::from_utf8_lossy(&output.stdout), port)?;
+        Some((pid, crate::process::name(pid)))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use std::process::Command;
+
+    pub fn find_listener(port: u16) -> Option<(u32, Option<String>)> {
+        let output = Command::new("lsof")
+            .args(["-nP", &format!("-iTCP:{port}"), "-sTCP:LISTEN", "-Fpc"])
+            .output()
+            .ok()?;
+        super::parse_lsof(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_listener_tables() {
+        let proc_line = "   1: 00000000:20E4 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 4242 1 0000000000000000 100 0 0 10 0";
+        assert_eq!(parse_proc_net_line(proc_line, 8420), Some(4242));
+        assert_eq!(parse_proc_net_line(proc_line, 8421), None);
+        let established = proc_line.replace(" 0A ", " 01 ");
+        assert_eq!(parse_proc_net_line(&established, 8420), None);
+
+        assert_eq!(
+            parse_lsof("p4321\ncnode\nf23\n"),
+            Some((4321, Some("node".to_string())))
+        );
+        assert_eq!(parse_lsof(""), None);
+
+        let netstat = "
+  Proto  Local Address          Foreign Address        State           PID
+  TCP    0.0.0.0:135            0.0.0.0:0              LISTENING       1000
+  TCP    0.0.0.0:8420           0.0.0.0:0              LISTENING       5150
+  TCP    [::]:8420              [::]:0                 LISTENING       5150";
+        assert_eq!(parse_netstat(netstat, 8420), Some(5150));
+        assert_eq!(parse_netstat(netstat, 9000), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_find_owner_of_own_listener() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let owner = find_owner(port).expect("listener should be found");
+        assert_eq!(owner.pid, std::process::id());
+        assert!(owner.instance.is_none());
+    }
+}
@@ -0,0 +1,79 @@
+//! Production-like responses for `wasmrun preview`
+//!
+//! The preview server adds the headers a typical static host would send:
+//! caching and a Content Security Policy. Compression applies to every server
+//! (see [`compression`](super::compression)). Headers set in `server.headers`
+//! in wasmrun.toml take precedence.
+
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tiny_http::{Header, Response, ResponseBox};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Default policy; allows the inline scripts of the served page and wasm compilation
+pub const CONTENT_SECURITY_POLICY: &str = "default-src 'self'; script-src 'self' 'unsafe-inline' 'wasm-unsafe-eval'; style-src 'self' 'unsafe-inline'; img-src 'self' data: blob:; connect-src 'self'; worker-src 'self' blob:; object-src 'none'; base-uri 'self'";
+
+/// Serve every response with production headers
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Cache policy by content type: pages revalidate, assets are cached for a day
+fn cache_control(content_type: &str) -> &'static str {
+    if content_type.starts_with("text/html") || content_type.starts_with("application/json") {
+        "no-cache"
+    } else {
+        "public, max-age=86400"
+    }
+}
+
+fn header_value<'a>(headers: &'a [Header], name: &'static str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+/// Add production headers to `response`
+pub fn apply<R>(mut response: Response<R>) -> ResponseBox
+where
+    R: Read + Send + 'static,
+{
+    if !is_enabled() {
+        return response.boxed();
+    }
+
+    let content_type = header_value(response.headers(), "Content-Type")
+        .unwrap_or_default()
+        .to_string();
+    let defaults = [
+        ("Cache-Control", cache_control(&content_type)),
+        ("Content-Security-Policy", CONTENT_SECURITY_POLICY),
+        ("X-Content-Type-Options", "nosniff"),
+    ];
+    for (name, value) in defaults {
+        if header_value(response.headers(), name).is_none() {
+            if let Ok(header) = Header::from_bytes(name, value) {
+                response.add_header(header);
+            }
+        }
+    }
+
+    response.boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_policy() {
+        assert_eq!(cache_control("text/html; charset=utf-8"), "no-cache");
+        assert_eq!(cache_control("application/wasm"), "public, max-age=86400");
+    }
+}
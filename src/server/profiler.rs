@@ -0,0 +1,341 @@
+//! Profiling overlay for instrumented modules
+//!
+//! With `wasmrun run --instrument <FUNCS>`, modules are served with the chosen
+//! functions wrapped in entry and exit hooks (see
+//! [`wasm_instrument`](crate::utils::wasm_instrument)). The page gets a script
+//! that supplies those hooks to every `WebAssembly` instantiation, whatever
+//! glue does it, and an overlay with call counts and inclusive time per
+//! function. The module is rewritten once per build and kept in memory.
+//! The overlay's Trace button downloads the calls it recorded in the Chrome
+//! `trace_event` format, for chrome://tracing or Perfetto.
+//! A browser that opened the page with `?profile=1` gets the same with every
+//! function instrumented, even without `--instrument`.
+
+use crate::utils::wasm_instrument::{self, PROFILE_IMPORT_MODULE, PROFILE_SECTION};
+use crate::{info_println, warn_println};
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use std::time::SystemTime;
+
+/// Function patterns from `--instrument`
+static PATTERNS: RwLock<Option<Vec<String>>> = RwLock::new(None);
+
+/// Instrumented modules, with the modification time of the file they were made
+/// from and the patterns they were instrumented for
+type Cached = (PathBuf, SystemTime, Vec<String>, Vec<u8>);
+
+static CACHE: Mutex<Vec<Cached>> = Mutex::new(Vec::new());
+
+/// Instrument the functions matching the comma-separated `functions` in served modules
+pub fn enable(functions: &str) {
+    let patterns = parse_patterns(functions);
+    if let Ok(mut active) = PATTERNS.write() {
+        *active = (!patterns.is_empty()).then_some(patterns);
+    }
+}
+
+fn parse_patterns(functions: &str) -> Vec<String> {
+    functions
+        .split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+pub fn is_enabled() -> bool {
+    PATTERNS.read().is_ok_and(|patterns| patterns.is_some())
+}
+
+/// The instrumented version of the module at `path`, or `None` when
+/// instrumentation is off or the module can't be instrumented; `everything`
+/// instruments every function when `--instrument` did not choose any
+pub fn instrumented(path: &str, everything: bool) -> Option<Vec<u8>> {
+    let patterns = PATTERNS
+        .read()
+        .ok()?
+        .clone()
+        .or_else(|| everything.then(|| vec!["*".to_string()]))?;
+    let path = PathBuf::from(path);
+    let modified = path.metadata().and_then(|meta| meta.modified()).ok()?;
+
+    let mut cache = CACHE.lock().ok()?;
+    if let Some((_, _, _, bytes)) =
+        cache
+            .iter()
+            .find(|(cached, cached_modified, cached_patterns, _)| {
+                *cached == path && *cached_modified == modified && *cached_patterns == patterns
+            })
+    {
+        return Some(bytes.clone());
+    }
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let instrumented = std::fs::read(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| wasm_instrument::instrument(&bytes, &patterns));
+    let instrumented = match instrumented {
+        Ok(instrumented) => instrumented,
+        Err(e) => {
+            warn_println!("Serving {name} without instrumentation: {e}");
+            return None;
+        }
+    };
+    info_println!(
+        "⏱️  Instrumented {} function{} in {name}: {}",
+        instrumented.functions.len(),
+        if instrumented.functions.len() == 1 {
+            ""
+        } else {
+            "s"
+        },
+        listed(&instrumented.functions)
+    );
+    for pattern in &instrumented.unmatched {
+        warn_println!("No function in {name} matches '{pattern}'");
+    }
+
+    cache.retain(|(cached, _, cached_patterns, _)| *cached != path || *cached_patterns != patterns);
+    cache.push((path, modified, patterns, instrumented.bytes.clone()));
+    Some(instrumented.bytes)
+}
+
+/// Most function names printed when a module is instrumented
+const LISTED_FUNCTIONS: usize = 20;
+
+/// The function names, cut short when `?profile=1` instrumented thousands
+fn listed(functions: &[String]) -> String {
+    if functions.len() <= LISTED_FUNCTIONS {
+        return functions.join(", ");
+    }
+    format!(
+        "{} and {} more",
+        functions[..LISTED_FUNCTIONS].join(", "),
+        functions.len() - LISTED_FUNCTIONS
+    )
+}
+
+/// Add the hook provider and overlay to a served page when instrumentation is
+/// on, or was `requested` for this browser
+pub fn inject(html: &str, requested: bool) -> String {
+    if !is_enabled() && !requested {
+        return html.to_string();
+    }
+    with_overlay(html)
+}
+
+fn with_overlay(html: &str) -> String {
+    let script = format!(
+        "<script>\n{}</script>",
+        OVERLAY_SCRIPT
+            .replace("__IMPORT_MODULE__", PROFILE_IMPORT_MODULE)
+            .replace("__SECTION__", PROFILE_SECTION)
+    );
+    // The hooks must be in place before any script on the page instantiates a module
+    let head = html
+        .find("<head")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+    match head {
+        Some(index) => format!("{}\n{script}{}", &html[..index], &html[index..]),
+        None => format!("{script}\n{html}"),
+    }
+}
+
+const OVERLAY_SCRIPT: &str = r#"(() => {
+  const IMPORT_MODULE = "__IMPORT_MODULE__";
+  const SECTION = "__SECTION__";
+  // Calls kept for the trace; later ones are only counted
+  const MAX_TRACED = 1000000;
+  const modules = [];
+  let traced = 0;
+
+  // Every instantiation gets its own hooks, and a table they record into;
+  // `events` holds id, start and duration of each call, flat
+  function hooks() {
+    const table = { names: [], calls: [], time: [], starts: [], events: [] };
+    modules.push(table);
+    const imports = {
+      enter(id) {
+        table.calls[id] = (table.calls[id] || 0) + 1;
+        table.starts.push(performance.now());
+      },
+      exit(id) {
+        const start = table.starts.pop();
+        if (start === undefined) return;
+        const duration = performance.now() - start;
+        table.time[id] = (table.time[id] || 0) + duration;
+        if (traced < MAX_TRACED) {
+          table.events.push(id, start, duration);
+          traced++;
+        }
+      },
+    };
+    return { table, imports };
+  }
+  const withHooks = (imports, hooks) => Object.assign({}, imports, { [IMPORT_MODULE]: hooks.imports });
+  function name(table, module) {
+    if (!(module instanceof WebAssembly.Module)) return;
+    const [section] = WebAssembly.Module.customSections(module, SECTION);
+    if (section) table.names = JSON.parse(new TextDecoder().decode(section));
+  }
+
+  const { instantiate, instantiateStreaming, Instance } = WebAssembly;
+  WebAssembly.instantiate = function (source, imports) {
+    const hooked = hooks();
+    return instantiate.call(this, source, withHooks(imports, hooked)).then((result) => {
+      name(hooked.table, result instanceof Instance ? source : result.module);
+      return result;
+    });
+  };
+  if (instantiateStreaming) {
+    WebAssembly.instantiateStreaming = function (source, imports) {
+      const hooked = hooks();
+      return instantiateStreaming.call(this, source, withHooks(imports, hooked)).then((result) => {
+        name(hooked.table, result.module);
+        return result;
+      });
+    };
+  }
+  WebAssembly.Instance = new Proxy(Instance, {
+    construct(target, [module, imports]) {
+      const hooked = hooks();
+      name(hooked.table, module);
+      return new target(module, withHooks(imports, hooked));
+    },
+  });
+
+  function rows() {
+    return modules.flatMap((table) => table.names.map((name, id) => ({
+      name,
+      calls: table.calls[id] || 0,
+      time: table.time[id] || 0,
+    }))).filter((row) => row.calls > 0).sort((a, b) => b.time - a.time).slice(0, 15);
+  }
+
+  // Chrome trace_event JSON, one thread per instantiated module, times in µs
+  function chromeTrace() {
+    const events = [{ name: "process_name", ph: "M", pid: 1, tid: 0, args: { name: location.href } }];
+    modules.forEach((table, index) => {
+      const tid = index + 1;
+      events.push({ name: "thread_name", ph: "M", pid: 1, tid, args: { name: `module ${tid}` } });
+      for (let i = 0; i < table.events.length; i += 3) {
+        const id = table.events[i];
+        events.push({
+          name: table.names[id] || `function ${id}`,
+          cat: "wasm",
+          ph: "X",
+          ts: table.events[i + 1] * 1000,
+          dur: table.events[i + 2] * 1000,
+          pid: 1,
+          tid,
+        });
+      }
+    });
+    return { traceEvents: events, displayTimeUnit: "ms" };
+  }
+
+  function download(name, text) {
+    const link = document.createElement("a");
+    link.href = URL.createObjectURL(new Blob([text], { type: "application/json" }));
+    link.download = name;
+    link.click();
+    setTimeout(() => URL.revokeObjectURL(link.href), 1000);
+  }
+
+  function mount() {
+    const host = document.createElement("div");
+    host.setAttribute("data-wasmrun-profiler", "");
+    const root = host.attachShadow({ mode: "open" });
+    root.innerHTML = `<style>
+      .panel { position: fixed; right: 12px; top: 12px; z-index: 2147483647; max-width: 420px;
+        font: 12px system-ui, sans-serif; color: #e2e8f0; background: #1e293bee; padding: 6px 8px; border-radius: 6px; }
+      header { display: flex; gap: 8px; align-items: center; justify-content: space-between; }
+      button { font: inherit; color: inherit; background: #334155; border: 0; border-radius: 4px; padding: 2px 6px; cursor: pointer; }
+      table { border-collapse: collapse; margin-top: 4px; }
+      th, td { padding: 1px 6px; text-align: right; white-space: nowrap; }
+      th:first-child, td:first-child { text-align: left; max-width: 200px; overflow: hidden; text-overflow: ellipsis; }
+      .empty { opacity: 0.7; }
+    </style>
+    <div class="panel" role="region" aria-label="Function profile">
+      <header><strong>Profile</strong>
+        <span><button id="trace" title="Download the recorded calls for chrome://tracing or Perfetto">Trace</button>
+          <button id="reset">Reset</button> <button id="toggle" aria-expanded="true">Hide</button></span></header>
+      <div id="body"></div>
+    </div>`;
+    const body = root.getElementById("body");
+    const toggle = root.getElementById("toggle");
+    root.getElementById("trace").addEventListener("click", () => {
+      download("wasmrun-trace.json", JSON.stringify(chromeTrace()));
+    });
+    root.getElementById("reset").addEventListener("click", () => {
+      modules.forEach((table) => { table.calls = []; table.time = []; table.events = []; });
+      traced = 0;
+      render();
+    });
+    toggle.addEventListener("click", () => {
+      body.hidden = !body.hidden;
+      toggle.textContent = body.hidden ? "Show" : "Hide";
+      toggle.setAttribute("aria-expanded", String(!body.hidden));
+    });
+
+    function render() {
+      if (body.hidden) return;
+      const list = rows();
+      if (list.length === 0) {
+        body.innerHTML = '<div class="empty">No instrumented calls yet</div>';
+        return;
+      }
+      const table = document.createElement("table");
+      table.innerHTML = "<tr><th>Function</th><th>Calls</th><th>Total ms</th><th>Avg µs</th></tr>";
+      list.forEach((row) => {
+        const tr = table.insertRow();
+        tr.insertCell().textContent = row.name;
+        tr.cells[0].title = row.name;
+        tr.insertCell().textContent = row.calls;
+        tr.insertCell().textContent = row.time.toFixed(2);
+        tr.insertCell().textContent = (row.time * 1000 / row.calls).toFixed(1);
+      });
+      body.replaceChildren(table);
+    }
+    render();
+    setInterval(render, 500);
+    document.body.appendChild(host);
+  }
+  if (document.body) mount();
+  else document.addEventListener("DOMContentLoaded", mount);
+})();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inject_into_head() {
+        let html = "<html><head><title>t</title></head><body></body></html>";
+        assert_eq!(inject(html, false), html);
+
+        let page = with_overlay(html);
+        // `?profile=1` gets the overlay without `--instrument`
+        assert_eq!(inject(html, true), page);
+        let script = page.find("<script>").unwrap();
+        assert!(page.find("<head>").unwrap() < script);
+        assert!(script < page.find("<title>").unwrap());
+        assert!(page.contains(r#"const IMPORT_MODULE = "__wasmrun_profile";"#));
+        assert!(page.contains(r#"const SECTION = "wasmrun.profile";"#));
+        assert!(page.contains(r#"<button id="trace""#));
+    }
+
+    #[test]
+    fn test_parse_patterns() {
+        assert_eq!(parse_patterns(" render*, ,update "), ["render*", "update"]);
+        assert!(parse_patterns("").is_empty());
+    }
+
+    #[test]
+    fn test_listed_functions() {
+        let functions: Vec<String> = (0..25).map(|i| format!("f{i}")).collect();
+        assert_eq!(listed(&functions[..2]), "f0, f1");
+        assert!(listed(&functions).ends_with("f19 and 5 more"));
+    }
+}
@@ -0,0 +1,284 @@
+//! Forwarding API requests to a backend (`--proxy /api=http://localhost:8080`)
+//!
+//! A page served by wasmrun can call its API on the same origin, without CORS.
+//! Requests whose path starts with a rule's prefix are sent on to the upstream
+//! with their method, headers and body, keeping the full path, so `/api/users`
+//! goes to `http://localhost:8080/api/users`. The upstream's response, errors
+//! and redirects included, is streamed back as it arrives. With several rules
+//! the longest matching prefix wins.
+
+use super::utils::{content_type_header, respond};
+use crate::{error_println, info_println};
+use std::io::Read;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+use tiny_http::{Header, Request, Response, StatusCode};
+
+/// Headers that only concern a single connection, or that the forwarded
+/// request and response get anew
+const HOP_BY_HOP: &[&str] = &[
+    "Connection",
+    "Keep-Alive",
+    "Proxy-Authenticate",
+    "Proxy-Authorization",
+    "TE",
+    "Trailer",
+    "Transfer-Encoding",
+    "Upgrade",
+    "Host",
+    "Content-Length",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyRule {
+    /// Path prefix, e.g. `/api`
+    pub prefix: String,
+    /// Upstream origin, e.g. `http://localhost:8080`
+    pub upstream: String,
+}
+
+impl ProxyRule {
+    /// Parse a `--proxy` value of the form `/prefix=http://host:port`
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let (prefix, upstream) = rule
+            .split_once('=')
+            .ok_or_else(|| "must look like /api=http://localhost:8080".to_string())?;
+        let prefix = prefix.trim().trim_end_matches('/');
+        if !prefix.starts_with('/') {
+            return Err(format!("prefix '{prefix}' must start with /"));
+        }
+        let upstream = upstream.trim().trim_end_matches('/');
+        let host = upstream
+            .strip_prefix("http://")
+            .or_else(|| upstream.strip_prefix("https://"))
+            .ok_or_else(|| format!("upstream '{upstream}' must be an http:// or https:// URL"))?;
+        if host.is_empty() || host.contains(['?', '#']) {
+            return Err(format!("upstream '{upstream}' must name a host"));
+        }
+        Ok(Self {
+            prefix: prefix.to_string(),
+            upstream: upstream.to_string(),
+        })
+    }
+
+    /// Whether `url` is under this rule's prefix, on a path segment boundary
+    fn matches(&self, url: &str) -> bool {
+        url.strip_prefix(&self.prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?']))
+    }
+}
+
+/// Proxy rules from the command line
+static RULES: RwLock<Vec<ProxyRule>> = RwLock::new(Vec::new());
+
+/// Forward requests matching `rules`
+pub fn enable(rules: Vec<ProxyRule>) {
+    for rule in &rules {
+        println!("🔀 Proxying {} to {}", rule.prefix, rule.upstream);
+    }
+    if let Ok(mut active) = RULES.write() {
+        *active = rules;
+    }
+}
+
+/// The upstream URL for `url`, when a rule covers it
+fn target(rules: &[ProxyRule], url: &str) -> Option<String> {
+    rules
+        .iter()
+        .filter(|rule| rule.matches(url))
+        .max_by_key(|rule| rule.prefix.len())
+        .map(|rule| format!("{}{url}", rule.upstream))
+}
+
+/// Forward `request` when its path, `url`, is proxied, and hand it back otherwise
+pub fn forward(mut request: Request, url: &str) -> Option<Request> {
+    let target = {
+        let rules = RULES.read().ok()?;
+        target(&rules, url)
+    };
+    let Some(target) = target else {
+        return Some(request);
+    };
+    info_println!("🔀 Proxying {} {url} to {target}", request.method());
+
+    let mut body = Vec::new();
+    if let Err(e) = request.as_reader().read_to_end(&mut body) {
+        error_println!("Error reading request body to proxy: {e}");
+        return None;
+    }
+    let mut headers: Vec<(String, String)> = request
+        .headers()
+        .iter()
+        .filter(|header| {
+            // The upstream may only answer in encodings the proxy can decode
+            !is_hop_by_hop(&header.field) && !header.field.equiv("Accept-Encoding")
+        })
+        .map(|header| (header.field.to_string(), header.value.to_string()))
+        .collect();
+    if let Some(addr) = request.remote_addr() {
+        headers.push(("X-Forwarded-For".to_string(), addr.ip().to_string()));
+    }
+    if let Some(host) = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Host"))
+    {
+        headers.push(("X-Forwarded-Host".to_string(), host.value.to_string()));
+    }
+
+    let result = match send(request.method().as_str(), &target, &headers, &body) {
+        Ok(upstream) => respond(request, into_response(upstream)),
+        Err(e) => {
+            error_println!("Proxy request to {target} failed: {e}");
+            let response = Response::from_string(format!("Proxy error: {e}"))
+                .with_status_code(502)
+                .with_header(content_type_header("text/plain"));
+            respond(request, response)
+        }
+    };
+    if let Err(e) = result {
+        error_println!("Error sending proxied response: {e}");
+    }
+    None
+}
+
+fn is_hop_by_hop(field: &tiny_http::HeaderField) -> bool {
+    HOP_BY_HOP.iter().any(|name| field.equiv(name))
+}
+
+/// Shared so connections to the upstream are reused
+fn agent() -> &'static ureq::Agent {
+    static AGENT: OnceLock<ureq::Agent> = OnceLock::new();
+    AGENT.get_or_init(|| {
+        ureq::AgentBuilder::new()
+            .redirects(0)
+            .timeout_connect(Duration::from_secs(5))
+            .build()
+    })
+}
+
+/// Send the request upstream; error statuses are responses too
+fn send(
+    method: &str,
+    target: &str,
+    headers: &[(String, String)],
+    body: &[u8],
+) -> Result<ureq::Response, Box<ureq::Transport>> {
+    let mut upstream = agent().request(method, target);
+    for (name, value) in headers {
+        upstream = upstream.set(name, value);
+    }
+    let result = if body.is_empty() {
+        upstream.call()
+    } else {
+        upstream.send_bytes(body)
+    };
+    match result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => Ok(response),
+        Err(ureq::Error::Transport(transport)) => Err(Box::new(transport)),
+    }
+}
+
+fn into_response(upstream: ureq::Response) -> Response<Box<dyn Read + Send + Sync + 'static>> {
+    // ureq decodes gzip bodies itself, so their length and encoding no longer apply
+    let decoded = upstream.header("Content-Encoding") == Some("gzip");
+    let length = upstream
+        .header("Content-Length")
+        .filter(|_| !decoded)
+        .and_then(|length| length.parse().ok());
+    let headers = upstream
+        .headers_names()
+        .iter()
+        .filter(|name| !(decoded && name.eq_ignore_ascii_case("Content-Encoding")))
+        .flat_map(|name| {
+            upstream
+                .all(name)
+                .into_iter()
+                .filter_map(|value| Header::from_bytes(name.as_bytes(), value.as_bytes()).ok())
+        })
+        .filter(|header| !is_hop_by_hop(&header.field))
+        .collect();
+    Response::new(
+        StatusCode(upstream.status()),
+        headers,
+        upstream.into_reader(),
+        length,
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(prefix: &str, upstream: &str) -> ProxyRule {
+        ProxyRule {
+            prefix: prefix.to_string(),
+            upstream: upstream.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        assert_eq!(
+            ProxyRule::parse("/api/=http://localhost:8080/"),
+            Ok(rule("/api", "http://localhost:8080"))
+        );
+        assert!(ProxyRule::parse("/api").is_err());
+        assert!(ProxyRule::parse("api=http://localhost:8080").is_err());
+        assert!(ProxyRule::parse("/api=localhost:8080").is_err());
+        assert!(ProxyRule::parse("/api=http://").is_err());
+    }
+
+    #[test]
+    fn test_target_prefers_longest_prefix() {
+        let rules = [rule("/api", "http://a:1"), rule("/api/auth", "http://b:2")];
+        assert_eq!(
+            target(&rules, "/api/users?page=2").as_deref(),
+            Some("http://a:1/api/users?page=2")
+        );
+        assert_eq!(
+            target(&rules, "/api/auth/login").as_deref(),
+            Some("http://b:2/api/auth/login")
+        );
+        assert_eq!(target(&rules, "/api").as_deref(), Some("http://a:1/api"));
+        assert_eq!(target(&rules, "/apis"), None);
+        assert_eq!(target(&rules, "/"), None);
+    }
+
+    #[test]
+    fn test_send_forwards_method_headers_and_body() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let upstream = std::thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let token = request
+                .headers()
+                .iter()
+                .find(|header| header.field.equiv("X-Token"))
+                .map(|header| header.value.to_string())
+                .unwrap_or_default();
+            let echo = format!("{} {} {token} {body}", request.method(), request.url());
+            request
+                .respond(Response::from_string(echo).with_status_code(201))
+                .unwrap();
+        });
+
+        let headers = [("X-Token".to_string(), "t1".to_string())];
+        let response = send(
+            "POST",
+            &format!("http://127.0.0.1:{port}/api/items"),
+            &headers,
+            b"name=x",
+        )
+        .unwrap();
+        let response = into_response(response);
+        assert_eq!(response.status_code().0, 201);
+        let mut body = String::new();
+        response.into_reader().read_to_string(&mut body).unwrap();
+        assert_eq!(body, "POST /api/items t1 name=x");
+        upstream.join().unwrap();
+    }
+}
@@ -0,0 +1,118 @@
+//! Running Python apps bundled for Pyodide
+//!
+//! The built-in Python plugin writes a bundle (a module carrying the app's
+//! sources in a custom section) and a bootstrap next to it that runs the
+//! bundle with Pyodide. When the bootstrap is served with the module, the
+//! page imports it through the `window.init` hook, shows what the app prints
+//! in the app area and leaves the interpreter on `window.pyodide`.
+
+use std::fs;
+use std::path::Path;
+
+/// Whether `source` is the bootstrap written by the Python plugin
+pub fn is_pyodide_bootstrap(source: &str) -> bool {
+    source.contains(r#"customSections(module, "wasmrun.python")"#)
+}
+
+/// The bootstrap to serve the bundle at `wasm_path` with, when there is one
+pub fn glue_for(wasm_path: &str) -> Option<String> {
+    let glue = Path::new(wasm_path).with_extension("js");
+    let source = fs::read_to_string(&glue).ok()?;
+    is_pyodide_bootstrap(&source).then(|| glue.to_string_lossy().to_string())
+}
+
+/// Add the loader to a page serving the bundle at `wasm_path` as
+/// `wasm_filename`, with `js_filename` next to it
+pub fn inject(
+    html: &str,
+    wasm_path: &str,
+    wasm_filename: &str,
+    js_filename: Option<&str>,
+) -> String {
+    let Some(js_filename) = js_filename else {
+        return html.to_string();
+    };
+    let is_bootstrap = Path::new(wasm_path)
+        .parent()
+        .and_then(|dir| fs::read_to_string(dir.join(js_filename)).ok())
+        .is_some_and(|source| is_pyodide_bootstrap(&source));
+    if !is_bootstrap {
+        return html.to_string();
+    }
+
+    let module = serde_json::to_string(wasm_filename)
+        .unwrap_or_default()
+        .replace('<', "\\u003c");
+    let href = js_filename.replace('&', "&amp;").replace('"', "&quot;");
+    let script = format!(
+        "<link rel=\"modulepreload\" href=\"{href}\" data-wasmrun-pyodide>\n<script>\n{}</script>",
+        LOADER_SCRIPT.replace("__MODULE__", &module)
+    );
+    let head = html
+        .find("<head")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+    match head {
+        Some(index) => format!("{}\n{script}{}", &html[..index], &html[index..]),
+        None => format!("{script}\n{html}"),
+    }
+}
+
+const LOADER_SCRIPT: &str = r#"window.init = async () => {
+  const bootstrap = document.querySelector("link[data-wasmrun-pyodide]").href;
+  const module = await WebAssembly.compileStreaming(fetch(__MODULE__));
+  const output = document.createElement("pre");
+  output.className = "wasmrun-python-output";
+  (document.getElementById("wasm-app") || document.body).appendChild(output);
+  const write = (text) => { output.textContent += text + "\n"; };
+  output.textContent = "Loading Pyodide...\n";
+  // Pyodide takes a while to start; the page carries on meanwhile
+  import(bootstrap)
+    .then(({ default: run }) => {
+      output.textContent = "";
+      return run(module, {
+        stdout: (text) => { write(text); console.log(text); },
+        stderr: (text) => { write(text); console.error(text); },
+      });
+    })
+    .then((pyodide) => {
+      window.pyodide = pyodide;
+      console.info("wasmrun: Python interpreter available as window.pyodide");
+    })
+    .catch((error) => {
+      write(String(error));
+      console.error("wasmrun: Python app failed", error);
+    });
+  return module;
+};
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loader_only_for_the_bootstrap() {
+        let dir = tempfile::tempdir().unwrap();
+        let wasm = dir.path().join("app.wasm");
+        let wasm_path = wasm.to_str().unwrap();
+        fs::write(&wasm, b"\0asm\x01\0\0\0").unwrap();
+        assert_eq!(glue_for(wasm_path), None);
+        fs::write(dir.path().join("other.js"), "export default 1;").unwrap();
+        fs::write(
+            dir.path().join("app.js"),
+            r#"const [section] = WebAssembly.Module.customSections(module, "wasmrun.python");"#,
+        )
+        .unwrap();
+        assert!(glue_for(wasm_path).unwrap().ends_with("app.js"));
+
+        let page = "<html><head><title>x</title></head><body></body></html>";
+        assert_eq!(inject(page, wasm_path, "app.wasm", None), page);
+        assert_eq!(inject(page, wasm_path, "app.wasm", Some("other.js")), page);
+        let injected = inject(page, wasm_path, "app.wasm", Some("app.js"));
+        assert!(injected.starts_with(
+            "<html><head>\n<link rel=\"modulepreload\" href=\"app.js\" data-wasmrun-pyodide>"
+        ));
+        assert!(injected.contains(r#"fetch("app.wasm")"#));
+        assert!(injected.find("window.init").unwrap() < injected.find("<title>").unwrap());
+    }
+}
@@ -0,0 +1,134 @@
+//! HTTP range requests
+//!
+//! Browsers and download tools ask for byte ranges of large files, to resume a
+//! transfer or to fetch a big module in pieces. A single range is answered with
+//! `206 Partial Content`. Requests for several ranges get the whole file, which
+//! HTTP allows, so there is no multipart encoding to maintain.
+
+use super::conditional::Validators;
+use tiny_http::Request;
+
+/// The part of a file a request asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    Full,
+    /// Inclusive byte offsets, both inside the file
+    Partial {
+        start: u64,
+        end: u64,
+    },
+    /// The range starts past the end of the file
+    Unsatisfiable,
+}
+
+impl ByteRange {
+    /// `Content-Range` value for this range of a file of `length` bytes
+    pub fn content_range(&self, length: u64) -> Option<String> {
+        match self {
+            ByteRange::Full => None,
+            ByteRange::Partial { start, end } => Some(format!("bytes {start}-{end}/{length}")),
+            ByteRange::Unsatisfiable => Some(format!("bytes */{length}")),
+        }
+    }
+}
+
+/// The range `request` asks for in a file of `length` bytes, identified by `validators`
+pub fn requested(request: &Request, length: u64, validators: Option<&Validators>) -> ByteRange {
+    let header = |name: &'static str| {
+        request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv(name))
+            .map(|header| header.value.as_str())
+    };
+    // A range of a version the client no longer has would corrupt its copy; send it all
+    if let Some(condition) = header("If-Range") {
+        if !validators.is_some_and(|validators| validators.if_range_matches(condition)) {
+            return ByteRange::Full;
+        }
+    }
+    parse(header("Range"), length)
+}
+
+/// Parse a `Range` header; anything other than one valid byte range means the whole file
+pub fn parse(header: Option<&str>, length: u64) -> ByteRange {
+    let Some(spec) = header.and_then(|header| header.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        // Suffix range: the last `end` bytes
+        return match end.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if length == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial {
+                start: length.saturating_sub(suffix),
+                end: length - 1,
+            },
+            Err(_) => ByteRange::Full,
+        };
+    }
+
+    let Ok(start) = start.parse::<u64>() else {
+        return ByteRange::Full;
+    };
+    let end = match end {
+        "" => None,
+        end => match end.parse::<u64>() {
+            Ok(end) if end >= start => Some(end),
+            _ => return ByteRange::Full,
+        },
+    };
+    if start >= length {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial {
+        start,
+        end: end.map_or(length - 1, |end| end.min(length - 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ranges() {
+        let partial = |start, end| ByteRange::Partial { start, end };
+        assert_eq!(parse(None, 100), ByteRange::Full);
+        assert_eq!(parse(Some("bytes=0-9"), 100), partial(0, 9));
+        assert_eq!(parse(Some("bytes=90-"), 100), partial(90, 99));
+        assert_eq!(parse(Some("bytes=90-500"), 100), partial(90, 99));
+        assert_eq!(parse(Some("bytes=-10"), 100), partial(90, 99));
+        assert_eq!(parse(Some("bytes=-500"), 100), partial(0, 99));
+        assert_eq!(parse(Some("bytes=100-"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse(Some("bytes=0-"), 0), ByteRange::Unsatisfiable);
+
+        // Invalid or unsupported ranges are ignored
+        assert_eq!(parse(Some("bytes=9-0"), 100), ByteRange::Full);
+        assert_eq!(parse(Some("bytes=0-1,5-9"), 100), ByteRange::Full);
+        assert_eq!(parse(Some("items=0-1"), 100), ByteRange::Full);
+        assert_eq!(parse(Some("bytes=a-b"), 100), ByteRange::Full);
+    }
+
+    #[test]
+    fn test_content_range() {
+        assert_eq!(ByteRange::Full.content_range(100), None);
+        assert_eq!(
+            ByteRange::Partial { start: 0, end: 9 }.content_range(100),
+            Some("bytes 0-9/100".to_string())
+        );
+        assert_eq!(
+            ByteRange::Unsatisfiable.content_range(100),
+            Some("bytes */100".to_string())
+        );
+    }
+}
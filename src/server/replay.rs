@@ -0,0 +1,474 @@
+//! Recording and replaying user input on the runner page
+//!
+//! The served page carries a small agent that records clicks, pointer, wheel,
+//! keyboard and form input, and replays them at a chosen speed after a reload.
+//! Recordings are saved under `~/.wasmrun/recordings`, so an interaction
+//! captured against one build can be replayed against another, either from
+//! the page or with `wasmrun run --replay <name>`.
+
+use super::utils::{content_type_header, respond};
+use crate::config::WasmrunConfig;
+use crate::error::{CommandError, Result, WasmrunError};
+use crate::error_println;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tiny_http::{Method, Request, Response};
+
+/// Directory under the config dir that holds saved recordings
+const RECORDINGS_DIR: &str = "recordings";
+
+/// Routes for listing, loading, saving and deleting recordings
+pub const RECORDINGS_ROUTE: &str = "/__wasmrun/recordings";
+
+/// Replay speeds offered on the page
+const SPEEDS: &[f64] = &[0.25, 0.5, 1.0, 2.0, 4.0];
+
+/// Replay requested with `--replay`, started on the next page load
+static AUTOPLAY: Mutex<Option<Autoplay>> = Mutex::new(None);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Autoplay {
+    name: String,
+    speed: f64,
+}
+
+/// A saved recording as stored on disk
+#[derive(Debug, Serialize, Deserialize)]
+struct Recording {
+    name: String,
+    #[serde(default)]
+    created: String,
+    /// The wasm file the recording was captured against
+    #[serde(default)]
+    page: String,
+    events: Vec<serde_json::Value>,
+}
+
+/// Summary of a saved recording
+#[derive(Debug, Serialize)]
+pub struct RecordingInfo {
+    pub name: String,
+    pub created: String,
+    pub page: String,
+    pub events: usize,
+}
+
+/// Replay speed from `--replay-speed`: a positive factor, optionally with an `x` suffix
+fn parse_speed(speed: &str) -> std::result::Result<f64, String> {
+    let value: f64 = speed
+        .trim()
+        .trim_end_matches(['x', 'X'])
+        .parse()
+        .map_err(|_| format!("'{speed}' is not a number"))?;
+    if !value.is_finite() || value <= 0.0 {
+        return Err(format!("'{speed}' must be greater than 0"));
+    }
+    Ok(value)
+}
+
+/// Replay the saved recording `name` at `speed` on the next page load
+pub fn set_autoplay(name: &str, speed: &str) -> Result<()> {
+    let speed = parse_speed(speed).map_err(|reason| {
+        WasmrunError::Command(CommandError::invalid_arguments(format!(
+            "--replay-speed {reason}"
+        )))
+    })?;
+    let dir = recordings_dir()?;
+    if load_in(&dir, name).is_err() {
+        let available: Vec<String> = list_in(&dir).into_iter().map(|info| info.name).collect();
+        return Err(WasmrunError::Command(CommandError::invalid_arguments(
+            if available.is_empty() {
+                format!("No recording named '{name}'; record one from the page first")
+            } else {
+                format!(
+                    "No recording named '{name}' (available: {})",
+                    available.join(", ")
+                )
+            },
+        )));
+    }
+    if let Ok(mut autoplay) = AUTOPLAY.lock() {
+        *autoplay = Some(Autoplay {
+            name: name.to_string(),
+            speed,
+        });
+    }
+    println!("⏯️  Replaying '{name}' at {speed}x once the page loads");
+    Ok(())
+}
+
+fn recordings_dir() -> Result<PathBuf> {
+    Ok(WasmrunConfig::config_dir()?.join(RECORDINGS_DIR))
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+}
+
+fn recording_path(dir: &Path, name: &str) -> std::result::Result<PathBuf, String> {
+    if !is_valid_name(name) {
+        return Err(format!(
+            "Invalid recording name '{name}': use letters, digits, '-', '_' and '.'"
+        ));
+    }
+    Ok(dir.join(format!("{name}.json")))
+}
+
+fn list_in(dir: &Path) -> Vec<RecordingInfo> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut recordings: Vec<RecordingInfo> = entries
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|content| serde_json::from_str::<Recording>(&content).ok())
+        .map(|recording| RecordingInfo {
+            name: recording.name,
+            created: recording.created,
+            page: recording.page,
+            events: recording.events.len(),
+        })
+        .collect();
+    recordings.sort_by(|a, b| b.created.cmp(&a.created));
+    recordings
+}
+
+fn load_in(dir: &Path, name: &str) -> std::result::Result<String, String> {
+    let path = recording_path(dir, name)?;
+    fs::read_to_string(path).map_err(|_| format!("No recording named '{name}'"))
+}
+
+fn save_in(dir: &Path, name: &str, body: &str) -> std::result::Result<usize, String> {
+    let path = recording_path(dir, name)?;
+    let mut recording: Recording =
+        serde_json::from_str(body).map_err(|e| format!("Invalid recording: {e}"))?;
+    recording.name = name.to_string();
+    if recording.created.is_empty() {
+        recording.created = chrono::Utc::now().to_rfc3339();
+    }
+
+    fs::create_dir_all(dir).map_err(|e| format!("Creating {}: {e}", dir.display()))?;
+    let content = serde_json::to_string(&recording).map_err(|e| e.to_string())?;
+    fs::write(&path, content).map_err(|e| format!("Writing {}: {e}", path.display()))?;
+    Ok(recording.events.len())
+}
+
+fn delete_in(dir: &Path, name: &str) -> std::result::Result<(), String> {
+    let path = recording_path(dir, name)?;
+    fs::remove_file(path).map_err(|_| format!("No recording named '{name}'"))
+}
+
+/// Answer a request under [`RECORDINGS_ROUTE`]; `rest` is the URL after the route
+pub fn handle(mut request: Request, rest: &str) {
+    let name = rest.split('?').next().unwrap_or("").trim_start_matches('/');
+    let result = recordings_dir().map_err(|e| e.to_string()).and_then(|dir| {
+        match (request.method().clone(), name) {
+            (Method::Get, "") => serde_json::to_string(&list_in(&dir)).map_err(|e| e.to_string()),
+            (Method::Get, name) => load_in(&dir, name),
+            (Method::Post | Method::Put, name) if !name.is_empty() => {
+                let mut body = String::new();
+                request
+                    .as_reader()
+                    .read_to_string(&mut body)
+                    .map_err(|e| e.to_string())?;
+                let events = save_in(&dir, name, &body)?;
+                println!("⏺️  Saved recording '{name}' ({events} input(s))");
+                Ok(format!(r#"{{"name":{name:?},"events":{events}}}"#))
+            }
+            (Method::Delete, name) if !name.is_empty() => {
+                delete_in(&dir, name).map(|_| format!(r#"{{"deleted":{name:?}}}"#))
+            }
+            _ => Err("Unsupported recordings request".to_string()),
+        }
+    });
+
+    let response = match result {
+        Ok(json) => {
+            Response::from_string(json).with_header(content_type_header("application/json"))
+        }
+        Err(error) => Response::from_string(error)
+            .with_status_code(400)
+            .with_header(content_type_header("text/plain")),
+    };
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending recordings response: {e}");
+    }
+}
+
+/// Add the recording agent to a served page, starting a `--replay` if one is pending
+pub fn inject(html: &str, page: &str) -> String {
+    let autoplay = AUTOPLAY
+        .lock()
+        .ok()
+        .and_then(|mut autoplay| autoplay.take());
+    agent(html, page, autoplay.as_ref())
+}
+
+fn agent(html: &str, page: &str, autoplay: Option<&Autoplay>) -> String {
+    let config = serde_json::json!({
+        "page": page,
+        "autoplay": autoplay,
+        "speeds": SPEEDS,
+    });
+    let script =
+        format!("<script>\nwindow.__WASMRUN_REPLAY__ = {config};\n{AGENT_SCRIPT}</script>");
+
+    match html.rfind("</body>") {
+        Some(index) => format!("{}{script}\n{}", &html[..index], &html[index..]),
+        None => format!("{html}\n{script}"),
+    }
+}
+
+const AGENT_SCRIPT: &str = r#"(() => {
+  const config = window.__WASMRUN_REPLAY__;
+  const ROUTE = "/__wasmrun/recordings";
+  const PENDING = "wasmrun-replay";
+  const EVENTS = ["click", "dblclick", "pointerdown", "pointerup", "pointermove", "wheel", "keydown", "keyup", "input", "change", "submit"];
+  let recording = null;
+  let replay = null;
+
+  const host = document.createElement("div");
+  host.setAttribute("data-wasmrun-replay", "");
+  const root = host.attachShadow({ mode: "open" });
+  root.innerHTML = `<style>
+    .bar { position: fixed; right: 12px; bottom: 12px; z-index: 2147483647; display: flex; gap: 4px; align-items: center;
+      font: 12px system-ui, sans-serif; color: #e2e8f0; background: #1e293bee; padding: 6px 8px; border-radius: 6px; }
+    button, select { font: inherit; color: inherit; background: #334155; border: 0; border-radius: 4px; padding: 3px 6px; cursor: pointer; }
+    button:disabled { opacity: 0.5; cursor: default; }
+    .status { min-width: 6em; opacity: 0.8; }
+  </style>
+  <div class="bar" role="toolbar" aria-label="Input recording">
+    <button id="record" title="Record input">⏺</button>
+    <select id="recording" aria-label="Saved recording"></select>
+    <select id="speed" aria-label="Replay speed"></select>
+    <button id="replay" title="Reload and replay">▶</button>
+    <button id="pause" title="Pause or resume the replay" disabled>⏸</button>
+    <button id="step" title="Replay the next input" disabled>⏭</button>
+    <span class="status" id="status"></span>
+  </div>`;
+  const $ = (id) => root.getElementById(id);
+  const status = (text) => { $("status").textContent = text; };
+  config.speeds.forEach((speed) => $("speed").add(new Option(speed + "x", speed, false, speed === 1)));
+
+  async function refresh(selected) {
+    const recordings = await fetch(ROUTE).then((response) => response.json()).catch(() => []);
+    $("recording").replaceChildren(...recordings.map((r) => new Option(r.name + " (" + r.events + ")", r.name, false, r.name === selected)));
+    $("replay").disabled = recordings.length === 0;
+  }
+
+  // Identify an element by its child-index path from <body>, which survives reloads and rebuilds
+  function pathOf(element) {
+    const path = [];
+    while (element && element.parentElement && element !== document.body) {
+      path.unshift(Array.prototype.indexOf.call(element.parentElement.children, element));
+      element = element.parentElement;
+    }
+    return path;
+  }
+  function find(path) {
+    let element = document.body;
+    for (const index of path) {
+      element = element && element.children[index];
+    }
+    return element || document.body;
+  }
+
+  function capture(event) {
+    const target = event.target;
+    const input = { type: event.type, path: pathOf(target) };
+    if (event.clientX !== undefined) {
+      // Position relative to the target, so it maps onto a resized canvas
+      const rect = target.getBoundingClientRect();
+      input.x = rect.width ? (event.clientX - rect.left) / rect.width : 0;
+      input.y = rect.height ? (event.clientY - rect.top) / rect.height : 0;
+      input.button = event.button;
+      input.buttons = event.buttons;
+    }
+    if (event.type === "wheel") Object.assign(input, { deltaX: event.deltaX, deltaY: event.deltaY });
+    if (event.key !== undefined) Object.assign(input, { key: event.key, code: event.code });
+    ["ctrlKey", "shiftKey", "altKey", "metaKey"].forEach((key) => { if (event[key]) input[key] = true; });
+    if ("value" in target && typeof target.value === "string") input.value = target.value;
+    if ("checked" in target) input.checked = target.checked;
+    return input;
+  }
+
+  function apply(input) {
+    const target = find(input.path);
+    const init = { bubbles: true, cancelable: true, composed: true,
+      ctrlKey: !!input.ctrlKey, shiftKey: !!input.shiftKey, altKey: !!input.altKey, metaKey: !!input.metaKey };
+    if (input.x !== undefined) {
+      const rect = target.getBoundingClientRect();
+      Object.assign(init, { clientX: rect.left + input.x * rect.width, clientY: rect.top + input.y * rect.height,
+        button: input.button, buttons: input.buttons });
+    }
+    if (input.value !== undefined && "value" in target) {
+      // Use the native setter so framework-controlled inputs notice the change
+      const setter = Object.getOwnPropertyDescriptor(Object.getPrototypeOf(target), "value");
+      if (setter && setter.set) setter.set.call(target, input.value); else target.value = input.value;
+    }
+    if (input.checked !== undefined && "checked" in target) target.checked = input.checked;
+
+    if (input.type.startsWith("pointer")) {
+      target.dispatchEvent(new PointerEvent(input.type, { ...init, pointerId: 1, isPrimary: true }));
+    } else if (input.type === "click" || input.type === "dblclick") {
+      target.dispatchEvent(new MouseEvent(input.type, init));
+    } else if (input.type === "wheel") {
+      target.dispatchEvent(new WheelEvent("wheel", { ...init, deltaX: input.deltaX, deltaY: input.deltaY }));
+    } else if (input.type === "keydown" || input.type === "keyup") {
+      target.dispatchEvent(new KeyboardEvent(input.type, { ...init, key: input.key, code: input.code }));
+    } else if (input.type === "submit" && target.requestSubmit) {
+      target.requestSubmit();
+    } else {
+      target.dispatchEvent(new Event(input.type, { bubbles: true }));
+    }
+  }
+
+  EVENTS.forEach((type) => document.addEventListener(type, (event) => {
+    if (!recording || !event.isTrusted || event.composedPath().includes(host)) return;
+    // Pointer moves only matter while dragging
+    if (type === "pointermove" && event.buttons === 0) return;
+    recording.events.push({ at: Math.round(performance.now() - recording.start), input: capture(event) });
+    status("● " + recording.events.length);
+  }, true));
+
+  $("record").addEventListener("click", async () => {
+    if (!recording) {
+      recording = { start: performance.now(), events: [] };
+      $("record").textContent = "⏹";
+      status("● recording");
+      return;
+    }
+    const events = recording.events;
+    recording = null;
+    $("record").textContent = "⏺";
+    const name = prompt("Save recording as", "recording-" + new Date().toISOString().slice(0, 19).replace(/[:T]/g, "-"));
+    if (!name) return status("Discarded");
+    const response = await fetch(ROUTE + "/" + encodeURIComponent(name), {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({ name, page: config.page, created: new Date().toISOString(), events }),
+    });
+    status(response.ok ? "Saved " + events.length : await response.text());
+    refresh(name);
+  });
+
+  // Replays start from a fresh page, so the reload happens first
+  $("replay").addEventListener("click", () => {
+    sessionStorage.setItem(PENDING, JSON.stringify({ name: $("recording").value, speed: Number($("speed").value) }));
+    location.reload();
+  });
+
+  function schedule() {
+    if (!replay || replay.paused) return;
+    const next = replay.events[replay.index];
+    if (!next) return finish();
+    const elapsed = (performance.now() - replay.started) * replay.speed;
+    replay.timer = setTimeout(() => {
+      apply(next.input);
+      replay.index++;
+      status("▶ " + replay.index + "/" + replay.events.length);
+      schedule();
+    }, Math.max(0, (next.at - elapsed) / replay.speed));
+  }
+  function finish() {
+    status("Replayed " + replay.events.length);
+    replay = null;
+    $("pause").disabled = $("step").disabled = true;
+  }
+  $("pause").addEventListener("click", () => {
+    if (!replay) return;
+    replay.paused = !replay.paused;
+    $("pause").textContent = replay.paused ? "▶" : "⏸";
+    if (replay.paused) {
+      clearTimeout(replay.timer);
+      status("⏸ " + replay.index + "/" + replay.events.length);
+    } else {
+      // Continue from the next input as if no time had passed
+      replay.started = performance.now() - (replay.events[replay.index] || { at: 0 }).at / replay.speed;
+      schedule();
+    }
+  });
+  $("step").addEventListener("click", () => {
+    if (!replay || !replay.paused) return;
+    const next = replay.events[replay.index++];
+    if (!next) return finish();
+    apply(next.input);
+    status("⏸ " + replay.index + "/" + replay.events.length);
+  });
+
+  async function start({ name, speed }) {
+    const recorded = await fetch(ROUTE + "/" + encodeURIComponent(name)).then((response) => response.json()).catch(() => null);
+    if (!recorded) return status("No recording " + name);
+    replay = { events: recorded.events, index: 0, speed, paused: false, started: performance.now() };
+    $("pause").disabled = $("step").disabled = false;
+    status("▶ 0/" + replay.events.length);
+    schedule();
+  }
+
+  window.addEventListener("load", () => {
+    document.documentElement.appendChild(host);
+    const pending = sessionStorage.getItem(PENDING);
+    sessionStorage.removeItem(PENDING);
+    const request = pending ? JSON.parse(pending) : config.autoplay;
+    refresh(request && request.name);
+    // Give the module a moment to render before replaying into it
+    if (request) setTimeout(() => start(request), 500);
+  });
+})();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_speed() {
+        assert_eq!(parse_speed("2"), Ok(2.0));
+        assert_eq!(parse_speed("0.5x"), Ok(0.5));
+        assert!(parse_speed("0").is_err());
+        assert!(parse_speed("fast").is_err());
+    }
+
+    #[test]
+    fn test_save_list_load_delete() {
+        let dir = tempdir().unwrap();
+        let body = r#"{"name":"ignored","page":"app.wasm","events":[{"at":0,"input":{"type":"click","path":[0]}}]}"#;
+
+        assert_eq!(save_in(dir.path(), "bug-42", body), Ok(1));
+        let recordings = list_in(dir.path());
+        assert_eq!(recordings.len(), 1);
+        assert_eq!(recordings[0].name, "bug-42");
+        assert_eq!(recordings[0].page, "app.wasm");
+        assert!(!recordings[0].created.is_empty());
+
+        assert!(load_in(dir.path(), "bug-42").unwrap().contains("\"click\""));
+        assert!(save_in(dir.path(), "../escape", body).is_err());
+        assert!(save_in(dir.path(), "bad", "{}").is_err());
+
+        delete_in(dir.path(), "bug-42").unwrap();
+        assert!(load_in(dir.path(), "bug-42").is_err());
+    }
+
+    #[test]
+    fn test_agent_carries_autoplay() {
+        let autoplay = Autoplay {
+            name: "bug-42".to_string(),
+            speed: 2.0,
+        };
+        let html = agent("<html><body></body></html>", "app.wasm", Some(&autoplay));
+        assert!(html.contains(r#""autoplay":{"name":"bug-42","speed":2.0}"#));
+        assert!(html.find("__WASMRUN_REPLAY__").unwrap() < html.find("</body>").unwrap());
+
+        let html = agent("<html><body></body></html>", "app.wasm", None);
+        assert!(html.contains(r#""autoplay":null"#));
+    }
+}
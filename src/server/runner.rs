@@ -1,10 +1,11 @@
 use crate::config::{
     compile_project, run_server, setup_project_compilation, ServerConfig, ServerInfo,
 };
-use crate::error::{Result, ServerError, WasmrunError};
+use crate::error::{Result, ServerError, WasmError, WasmrunError};
 use crate::server::utils::ServerUtils;
 use crate::server::wasm;
-use crate::{debug_enter, debug_exit, debug_println};
+use crate::utils::{ArtifactKind, ModuleInspection};
+use crate::{debug_enter, debug_exit, debug_println, info_println};
 use std::path::Path;
 
 /// Run a WASM file directly
@@ -19,7 +20,7 @@ pub fn run_wasm_file(path: &str, port: u16, serve: bool) -> Result<()> {
     let path_obj = std::path::Path::new(path);
     debug_println!("Checking file extension for path: {}", path);
 
-    if !path_obj.extension().is_some_and(|ext| ext == "wasm") {
+    if path_obj.extension().is_none_or(|ext| ext != "wasm") {
         if path_obj.extension().is_some_and(|ext| ext == "js") {
             debug_println!("Detected JS file, delegating to handle_js_file");
             return handle_js_file(path, port, serve);
@@ -41,6 +42,8 @@ pub fn run_wasm_file(path: &str, port: u16, serve: bool) -> Result<()> {
         return Ok(());
     }
 
+    super::component::prepare(path)?;
+
     debug_println!("Creating server info for WASM file");
     let server_info = ServerInfo::for_wasm_file(path, final_port, false)?;
     server_info.print_server_startup();
@@ -151,9 +154,9 @@ pub fn run_project(
     };
 
     if is_wasm_bindgen {
-        println!("🔧 Running wasm-bindgen project with JavaScript support");
+        info_println!("🔧 Running wasm-bindgen project with JavaScript support");
     } else {
-        println!("⚡ Running standard WASM project");
+        info_println!("⚡ Running standard WASM project");
     }
 
     run_server(server_config).map_err(|e| {
@@ -218,7 +221,7 @@ fn handle_js_file(path: &str, port: u16, serve: bool) -> Result<()> {
     )))
 }
 
-/// Handle wasm-bindgen files (files ending with _bg.wasm)
+/// Handle wasm-bindgen files (files ending with _bg.wasm, or modules that import the JS glue)
 fn handle_wasm_bindgen_file(
     path_obj: &std::path::Path,
     path: &str,
@@ -230,11 +233,23 @@ fn handle_wasm_bindgen_file(
         .ok_or_else(|| WasmrunError::path("Invalid file path"))?
         .to_string_lossy();
 
-    if file_name.ends_with("_bg.wasm") {
+    // Renamed or hand-placed modules are recognised by their imports and exports
+    let is_bindgen_name = file_name.ends_with("_bg.wasm");
+    if is_bindgen_name
+        || ModuleInspection::from_file(path)
+            .is_ok_and(|inspection| inspection.artifact == ArtifactKind::WasmBindgen)
+    {
         println!("\n\x1b[1;34m╭\x1b[0m");
-        println!("  ℹ️  \x1b[1;34mDetected wasm-bindgen _bg.wasm file: {path}\x1b[0m");
+        if is_bindgen_name {
+            println!("  ℹ️  \x1b[1;34mDetected wasm-bindgen _bg.wasm file: {path}\x1b[0m");
+        } else {
+            println!("  ℹ️  \x1b[1;34mDetected wasm-bindgen module: {path}\x1b[0m");
+        }
 
-        let js_base_name = file_name.replace("_bg.wasm", "");
+        let js_base_name = file_name
+            .strip_suffix("_bg.wasm")
+            .or_else(|| file_name.strip_suffix(".wasm"))
+            .unwrap_or(&file_name);
         let js_file_name = format!("{js_base_name}.js");
         let js_path = path_obj
             .parent()
@@ -268,9 +283,18 @@ fn handle_wasm_bindgen_file(
             })?;
 
             return Ok(true);
-        } else {
+        } else if is_bindgen_name {
             return search_for_js_files(path_obj, path, port, file_name.as_ref(), serve);
         }
+
+        // Without its glue the module may still run on the console page
+        return match search_for_js_files(path_obj, path, port, file_name.as_ref(), serve) {
+            Err(WasmrunError::Wasm(WasmError::WasmBindgenJsNotFound)) => {
+                println!("ℹ️  Serving {path} without wasm-bindgen support");
+                Ok(false)
+            }
+            result => result,
+        };
     }
 
     Ok(false)
@@ -331,9 +355,7 @@ fn search_for_js_files(
     println!("  \x1b[0;37mTry running the .js file directly instead.\x1b[0m");
     println!("\x1b[1;34m╰\x1b[0m\n");
 
-    Err(WasmrunError::Wasm(
-        crate::error::WasmError::WasmBindgenJsNotFound,
-    ))
+    Err(WasmrunError::Wasm(WasmError::WasmBindgenJsNotFound))
 }
 
 #[cfg(test)]
@@ -493,6 +515,36 @@ mod tests {
         assert!(!result.unwrap()); // Regular WASM file
     }
 
+    #[test]
+    fn test_handle_wasm_bindgen_file_detected_by_imports() {
+        let temp_dir = tempdir().unwrap();
+        // A module importing `wbg::f`, as wasm-bindgen's web target emits
+        let mut bytes = VALID_WASM_BYTES.to_vec();
+        bytes.extend([0x01, 0x04, 0x01, 0x60, 0x00, 0x00]);
+        bytes.extend([
+            0x02, 0x09, 0x01, 0x03, b'w', b'b', b'g', 0x01, b'f', 0x00, 0x00,
+        ]);
+        let wasm_file = create_wasm_file_with_content(temp_dir.path(), "renamed.wasm", &bytes);
+
+        // Without the glue it is served like any other module
+        let result = handle_wasm_bindgen_file(
+            wasm_file.as_path(),
+            wasm_file.to_str().unwrap(),
+            8080,
+            false,
+        );
+        assert!(!result.unwrap());
+
+        create_js_file_with_content(temp_dir.path(), "renamed.js", "// wasm_bindgen generated");
+        let result = handle_wasm_bindgen_file(
+            wasm_file.as_path(),
+            wasm_file.to_str().unwrap(),
+            8080,
+            false,
+        );
+        assert!(result.unwrap());
+    }
+
     #[test]
     fn test_search_for_js_files_found() {
         let temp_dir = tempdir().unwrap();
@@ -0,0 +1,93 @@
+//! Graceful shutdown of a running server
+//!
+//! Ctrl+C, a termination signal, `wasmrun stop` and a `POST` to
+//! [`SHUTDOWN_ROUTE`] all end the request loop rather than the process, so the
+//! server unregisters itself and removes its control endpoint on the way out.
+//! A second Ctrl+C exits straight away. The HTTP route is only there when
+//! [`TOKEN_ENV`] is set, and callers must send that token as a bearer token.
+
+use super::demo::same_token;
+use super::utils::{content_type_header, respond};
+use crate::{error_println, info_println, warn_println};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Once, Weak};
+use tiny_http::{Method, Request, Response, Server};
+
+/// Route that shuts the server down over HTTP
+pub const SHUTDOWN_ROUTE: &str = "/api/shutdown";
+
+/// Environment variable holding the token [`SHUTDOWN_ROUTE`] requires
+pub const TOKEN_ENV: &str = "WASMRUN_SHUTDOWN_TOKEN";
+
+/// Servers whose request loops end on shutdown
+static SERVERS: Mutex<Vec<Weak<Server>>> = Mutex::new(Vec::new());
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+static SIGNAL_HANDLER: Once = Once::new();
+
+/// End `server`'s request loop when a shutdown is requested, and handle Ctrl+C
+/// and termination signals from now on
+pub fn stop_on_request(server: &Arc<Server>) {
+    if let Ok(mut servers) = SERVERS.lock() {
+        servers.retain(|server| server.strong_count() > 0);
+        servers.push(Arc::downgrade(server));
+    }
+    SIGNAL_HANDLER.call_once(|| {
+        let installed = ctrlc::set_handler(|| {
+            if REQUESTED.load(Ordering::SeqCst) {
+                std::process::exit(130);
+            }
+            request("Interrupted");
+        });
+        if let Err(e) = installed {
+            warn_println!("Ctrl+C will not shut the server down cleanly: {e}");
+        }
+    });
+}
+
+/// Stop serving; `reason` is shown to the user
+pub fn request(reason: &str) {
+    if REQUESTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    info_println!("\n🛑 {reason}, shutting down...");
+    if let Ok(servers) = SERVERS.lock() {
+        for server in servers.iter().filter_map(Weak::upgrade) {
+            server.unblock();
+        }
+    }
+}
+
+/// Answer a request to [`SHUTDOWN_ROUTE`], shutting down when it carries the token
+pub fn handle(request: Request) {
+    let token = std::env::var(TOKEN_ENV)
+        .ok()
+        .filter(|token| !token.trim().is_empty());
+    let presented = request.headers().iter().find_map(|header| {
+        header
+            .field
+            .equiv("Authorization")
+            .then(|| header.value.as_str().strip_prefix("Bearer "))
+            .flatten()
+            .map(|value| value.trim().to_string())
+    });
+
+    let (status, message) = match token {
+        None => (404, "Not found"),
+        Some(_) if *request.method() != Method::Post => (405, "Use POST to shut the server down"),
+        Some(token) if presented.is_some_and(|presented| same_token(&presented, token.trim())) => {
+            (202, "Shutting down")
+        }
+        Some(_) => (401, "Invalid or missing shutdown token"),
+    };
+    let response = Response::from_string(message)
+        .with_status_code(status)
+        .with_header(content_type_header("text/plain"));
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending shutdown response: {e}");
+    }
+    if status == 202 {
+        self::request("Shutdown requested over HTTP");
+    }
+}
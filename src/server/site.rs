@@ -0,0 +1,279 @@
+//! Directory mode (`wasmrun serve <dir>`)
+//!
+//! Serves a whole build output directory such as `./dist`. Request paths
+//! resolve against the directory root, nested directories included, so the
+//! wasm entry, its JS glue and every other asset load from the same relative
+//! URLs they use in production. An `index.html` at the root replaces the
+//! wasmrun runner page.
+
+use crate::error::{Result, WasmrunError};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+
+/// Page served at `/` when the directory has one
+pub const INDEX_FILE: &str = "index.html";
+
+/// Directories never searched for wasm entries
+const SKIPPED_DIRS: [&str; 2] = ["node_modules", "target"];
+
+/// Deepest directory level searched for wasm entries
+const MAX_DEPTH: usize = 8;
+
+static ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// A directory to serve and the wasm entry found in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Site {
+    pub root: PathBuf,
+    pub wasm_path: PathBuf,
+    /// wasm-bindgen JS glue next to the entry
+    pub js_path: Option<PathBuf>,
+    pub has_index: bool,
+    /// Number of files served from the directory
+    pub file_count: usize,
+}
+
+impl Site {
+    /// Find the wasm entry under `root`, or use `entry` (relative to `root`) when given
+    pub fn discover(root: &Path, entry: Option<&str>) -> Result<Self> {
+        let root = fs::canonicalize(root)
+            .map_err(|_| WasmrunError::path(format!("Directory not found: {}", root.display())))?;
+        let mut files = Vec::new();
+        collect_files(&root, 0, &mut files);
+        let index = fs::read_to_string(root.join(INDEX_FILE)).ok();
+
+        let wasm_path = match entry {
+            Some(entry) => {
+                let path = root.join(entry);
+                if !path.is_file() || path.extension().is_none_or(|ext| ext != "wasm") {
+                    return Err(WasmrunError::path(format!(
+                        "--entry {entry} is not a .wasm file in {}",
+                        root.display()
+                    )));
+                }
+                path
+            }
+            None => pick_entry(&root, &files, index.as_deref())?,
+        };
+        let js_path = glue_for(&wasm_path);
+
+        Ok(Self {
+            has_index: index.is_some(),
+            file_count: files.len(),
+            root,
+            wasm_path,
+            js_path,
+        })
+    }
+
+    /// URL path of `path` relative to the root, without the leading slash
+    pub fn url_path(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// Serve requests from `root` instead of the directory of the wasm file
+pub fn set_root(root: PathBuf) {
+    if let Ok(mut active) = ROOT.write() {
+        *active = Some(root);
+    }
+}
+
+/// The served directory in directory mode
+pub fn root() -> Option<PathBuf> {
+    ROOT.read().ok().and_then(|root| root.clone())
+}
+
+/// The directory's own `index.html`, in directory mode
+pub fn index_html() -> Option<String> {
+    fs::read_to_string(root()?.join(INDEX_FILE)).ok()
+}
+
+/// The file under `root` a request URL names; directories resolve to their `index.html`.
+/// Paths that would leave `root` resolve to nothing.
+pub fn resolve(root: &Path, url: &str) -> Option<PathBuf> {
+    let mut resolved = locate(root, url)?;
+    if resolved.is_dir() {
+        resolved = fs::canonicalize(resolved.join(INDEX_FILE)).ok()?;
+    }
+    (resolved.starts_with(root) && resolved.is_file()).then_some(resolved)
+}
+
+/// The file or directory under `root` a request URL names, canonicalized.
+/// Paths that would leave `root`, with `..` or through a symlink, name nothing.
+pub fn locate(root: &Path, url: &str) -> Option<PathBuf> {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let path = percent_decode(path)?;
+    let mut resolved = root.to_path_buf();
+    for component in Path::new(path.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    // Symlinks may point anywhere; only serve what really lives under the root
+    let canonical = fs::canonicalize(&resolved).ok()?;
+    canonical.starts_with(root).then_some(canonical)
+}
+
+/// Prefer the module the index page loads, then the only module in the directory
+fn pick_entry(root: &Path, files: &[PathBuf], index: Option<&str>) -> Result<PathBuf> {
+    let modules: Vec<&PathBuf> = files
+        .iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+        .collect();
+
+    let referenced: Vec<&PathBuf> = match index {
+        Some(index) => modules
+            .iter()
+            .copied()
+            .filter(|module| {
+                let named = |path: &Path| {
+                    path.file_name()
+                        .is_some_and(|name| index.contains(&*name.to_string_lossy()))
+                };
+                named(module) || glue_for(module).is_some_and(|js| named(&js))
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    match (referenced.as_slice(), modules.as_slice()) {
+        ([module], _) | (_, [module]) => Ok((*module).clone()),
+        (_, []) => Err(WasmrunError::path(format!(
+            "No WASM files found in directory: {}; to build and run a project, use wasmrun run",
+            root.display()
+        ))),
+        (_, modules) => {
+            let listed: Vec<String> = modules
+                .iter()
+                .map(|module| {
+                    module
+                        .strip_prefix(root)
+                        .unwrap_or(module)
+                        .display()
+                        .to_string()
+                })
+                .collect();
+            Err(WasmrunError::path(format!(
+                "Found {} WASM files ({}); choose one with --entry, or list them all with --gallery",
+                listed.len(),
+                listed.join(", ")
+            )))
+        }
+    }
+}
+
+/// `name.js` next to `name.wasm` or wasm-bindgen's `name_bg.wasm`
+pub(super) fn glue_for(wasm_path: &Path) -> Option<PathBuf> {
+    let stem = wasm_path.file_stem()?.to_string_lossy();
+    let stem = stem.strip_suffix("_bg").unwrap_or(&stem);
+    let js_path = wasm_path.with_file_name(format!("{stem}.js"));
+    js_path.is_file().then_some(js_path)
+}
+
+pub(crate) fn collect_files(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    entries.sort();
+    for path in entries {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            if depth < MAX_DEPTH && !SKIPPED_DIRS.contains(&name.as_str()) {
+                collect_files(&path, depth + 1, files);
+            }
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+}
+
+/// Decode `%XX` escapes; `None` for malformed escapes or non-UTF-8 results
+pub(super) fn percent_decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_discover_prefers_module_loaded_by_index() {
+        let dir = tempdir().unwrap();
+        write(
+            dir.path(),
+            "index.html",
+            r#"<script type="module" src="./pkg/app.js"></script>"#,
+        );
+        write(dir.path(), "pkg/app_bg.wasm", "\0asm");
+        write(dir.path(), "pkg/app.js", "export default 1");
+        write(dir.path(), "workers/worker.wasm", "\0asm");
+        write(dir.path(), "node_modules/dep/dep.wasm", "\0asm");
+
+        let site = Site::discover(dir.path(), None).unwrap();
+        assert_eq!(site.url_path(&site.wasm_path), "pkg/app_bg.wasm");
+        assert_eq!(site.url_path(site.js_path.as_ref().unwrap()), "pkg/app.js");
+        assert!(site.has_index);
+        assert_eq!(site.file_count, 4);
+
+        fs::remove_file(dir.path().join("index.html")).unwrap();
+        let error = Site::discover(dir.path(), None).unwrap_err();
+        assert!(error.to_string().contains("--entry"));
+        let site = Site::discover(dir.path(), Some("workers/worker.wasm")).unwrap();
+        assert_eq!(site.url_path(&site.wasm_path), "workers/worker.wasm");
+        assert!(site.js_path.is_none());
+    }
+
+    #[test]
+    fn test_resolve_stays_inside_root() {
+        let dir = tempdir().unwrap();
+        write(dir.path(), "assets/my icon.png", "png");
+        write(dir.path(), "docs/index.html", "<html></html>");
+        let root = fs::canonicalize(dir.path()).unwrap();
+
+        assert_eq!(
+            resolve(&root, "/assets/my%20icon.png?x=1"),
+            Some(root.join("assets/my icon.png"))
+        );
+        assert_eq!(resolve(&root, "/docs/"), Some(root.join("docs/index.html")));
+        assert_eq!(resolve(&root, "/../etc/passwd"), None);
+        assert_eq!(resolve(&root, "/assets/%2e%2e/%2e%2e/secret"), None);
+        assert_eq!(resolve(&root, "/missing.js"), None);
+        assert_eq!(resolve(&root, "/bad%zz"), None);
+    }
+}
@@ -0,0 +1,203 @@
+//! Project status for dashboards and editor status bars (`/__wasmrun/status`)
+//!
+//! One JSON document answers "what is this server doing": the project and the
+//! language and plugin that build it, whether a build is running and how the
+//! last one went, the size of the served artifacts, the uptime, and the
+//! clients that made a request in the last 30 seconds. Requests for the
+//! status itself don't count as clients, so a polling dashboard doesn't show up.
+
+use super::utils::{content_type_header, respond};
+use crate::error_println;
+use crate::registry::BuildRecord;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tiny_http::{Request, Response};
+
+/// Where the status is served
+pub const STATUS_ROUTE: &str = "/__wasmrun/status";
+
+/// Clients seen within this window count as connected
+const CLIENT_WINDOW: Duration = Duration::from_secs(30);
+
+/// Clients forgotten after this long, so the table stays small
+const CLIENT_EXPIRY: Duration = Duration::from_secs(600);
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+static LAST_BUILD: Mutex<Option<BuildRecord>> = Mutex::new(None);
+static CLIENTS: Mutex<BTreeMap<String, Client>> = Mutex::new(BTreeMap::new());
+/// The language and plugin of the project, detected on the first request
+static PROJECT: OnceLock<(String, Option<serde_json::Value>)> = OnceLock::new();
+
+struct Client {
+    requests: u64,
+    last_seen: Instant,
+}
+
+/// Start the uptime clock; called once the server is listening
+pub fn mark_started() {
+    STARTED.get_or_init(Instant::now);
+}
+
+/// Remember the latest finished build
+pub fn record_build(record: &BuildRecord) {
+    if let Ok(mut last) = LAST_BUILD.lock() {
+        *last = Some(record.clone());
+    }
+}
+
+/// Note a request from `client` for `url`
+pub fn record_client(client: Option<std::net::SocketAddr>, url: &str) {
+    if url.starts_with(STATUS_ROUTE) {
+        return;
+    }
+    let Some(client) = client else {
+        return;
+    };
+    if let Ok(mut clients) = CLIENTS.lock() {
+        clients.retain(|_, seen| seen.last_seen.elapsed() < CLIENT_EXPIRY);
+        let entry = clients.entry(client.ip().to_string()).or_insert(Client {
+            requests: 0,
+            last_seen: Instant::now(),
+        });
+        entry.requests += 1;
+        entry.last_seen = Instant::now();
+    }
+}
+
+/// Answer `GET /__wasmrun/status`
+pub fn serve(
+    request: Request,
+    wasm_path: &str,
+    js_filename: Option<&str>,
+    project_path: Option<&str>,
+) {
+    let body = snapshot(wasm_path, js_filename, project_path);
+    let response = Response::from_string(body.to_string())
+        .with_header(content_type_header("application/json"))
+        .with_header(tiny_http::Header::from_bytes("Cache-Control", "no-store").unwrap());
+    if let Err(e) = respond(request, response) {
+        error_println!("Error sending status: {e}");
+    }
+}
+
+fn snapshot(
+    wasm_path: &str,
+    js_filename: Option<&str>,
+    project_path: Option<&str>,
+) -> serde_json::Value {
+    // A project is named after its directory, a bare module after its file
+    let name = match project_path {
+        Some(project_path) => Path::new(project_path)
+            .canonicalize()
+            .unwrap_or_else(|_| project_path.into())
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string()),
+        None => Path::new(wasm_path)
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string()),
+    };
+    let (language, plugin) = PROJECT.get_or_init(|| detect(project_path));
+
+    let output_dir = Path::new(wasm_path).parent().unwrap_or(Path::new("."));
+    let artifacts: Vec<serde_json::Value> = std::iter::once(Path::new(wasm_path).to_path_buf())
+        .chain(js_filename.map(|js| output_dir.join(js)))
+        .filter_map(|path| {
+            let bytes = std::fs::metadata(&path).ok()?.len();
+            Some(serde_json::json!({
+                "file": path.file_name()?.to_string_lossy(),
+                "bytes": bytes,
+            }))
+        })
+        .collect();
+
+    let last_build = LAST_BUILD.lock().ok().and_then(|last| last.clone());
+    let clients: Vec<serde_json::Value> = CLIENTS
+        .lock()
+        .map(|clients| {
+            clients
+                .iter()
+                .filter(|(_, client)| client.last_seen.elapsed() <= CLIENT_WINDOW)
+                .map(|(address, client)| {
+                    serde_json::json!({
+                        "address": address,
+                        "requests": client.requests,
+                        "last_seen_seconds": client.last_seen.elapsed().as_secs(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    serde_json::json!({
+        "project": name,
+        "instance": super::lifecycle::instance_name(),
+        "language": project_path.map(|_| language),
+        "plugin": plugin,
+        "build": {
+            "running": super::build_status::is_building(),
+            "last": last_build.map(|build| serde_json::json!({
+                "success": build.success,
+                "builder": build.builder,
+                "duration_ms": build.duration_ms,
+                "finished_at": build.finished_at,
+                "error": build.error.map(|error| super::ansi::strip(error.lines().next().unwrap_or_default())),
+            })),
+        },
+        "artifacts": artifacts,
+        "uptime_seconds": STARTED.get_or_init(Instant::now).elapsed().as_secs(),
+        "connected_clients": clients.len(),
+        "clients": clients,
+    })
+}
+
+/// The language of the project at `project_path`, and the plugin building it
+fn detect(project_path: Option<&str>) -> (String, Option<serde_json::Value>) {
+    let Some(project_path) = project_path else {
+        return (String::new(), None);
+    };
+    let language = crate::compiler::detect_project_language(project_path).to_string();
+    let plugin = crate::plugin::manager::PluginManager::new()
+        .ok()
+        .and_then(|manager| {
+            let info = manager
+                .find_plugin_for_project(project_path)?
+                .info()
+                .clone();
+            Some(serde_json::json!({ "name": info.name, "version": info.version }))
+        });
+    (language, plugin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_lists_artifacts_and_clients() {
+        let dir = tempfile::tempdir().unwrap();
+        let wasm = dir.path().join("app.wasm");
+        std::fs::write(&wasm, [0u8; 42]).unwrap();
+        std::fs::write(dir.path().join("app.js"), "export {}").unwrap();
+
+        let client = "203.0.113.7:5000".parse().ok();
+        record_client(client, "/app.wasm");
+        record_client(client, STATUS_ROUTE);
+        record_client(client, "/");
+
+        let status = snapshot(wasm.to_str().unwrap(), Some("app.js"), None);
+        assert_eq!(status["project"], "app");
+        assert_eq!(status["language"], serde_json::Value::Null);
+        assert_eq!(status["artifacts"][0]["file"], "app.wasm");
+        assert_eq!(status["artifacts"][0]["bytes"], 42);
+        assert_eq!(status["artifacts"][1]["file"], "app.js");
+        let client = status["clients"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|client| client["address"] == "203.0.113.7")
+            .unwrap();
+        assert_eq!(client["requests"], 2);
+    }
+}
@@ -0,0 +1,139 @@
+//! Timeouts for clients that stop reading
+//!
+//! A browser suspended in the middle of a large module download stops
+//! reading, and the worker writing the response would wait for it forever.
+//! The listening socket therefore carries `server.write_timeout_secs` as its
+//! send timeout, which every connection accepted from it inherits, so a
+//! write the client takes nothing of fails after that long. A client that
+//! keeps reading, only too slowly, is cut off once a response has taken
+//! `server.response_timeout_secs` in total. `0` turns a timeout off.
+//!
+//! There is no read timeout: on Linux a receive timeout on the listening
+//! socket also bounds `accept`, which would end tiny_http's accept loop the
+//! first time the server sat idle that long.
+
+use crate::config::active_settings;
+use socket2::SockRef;
+use std::io::{self, Read};
+use std::net::{TcpListener, ToSocketAddrs};
+use std::time::{Duration, Instant};
+use tiny_http::{Response, ResponseBox};
+
+/// Longest wait for a client to take more of a response
+pub const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Longest a single response may take
+pub const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(600);
+
+fn configured(secs: Option<u64>, default: Duration) -> Option<Duration> {
+    match secs {
+        Some(0) => None,
+        Some(secs) => Some(Duration::from_secs(secs)),
+        None => Some(default),
+    }
+}
+
+/// Bind `address` with the configured write timeout on the socket, for
+/// connections to inherit
+pub fn listen(address: impl ToSocketAddrs) -> io::Result<TcpListener> {
+    let listener = TcpListener::bind(address)?;
+    let write = configured(
+        active_settings().server.write_timeout_secs,
+        DEFAULT_WRITE_TIMEOUT,
+    );
+    SockRef::from(&listener).set_write_timeout(write)?;
+    Ok(listener)
+}
+
+/// Fail the body of `response` once it has been sent for longer than the
+/// response timeout
+pub fn limit(response: ResponseBox) -> ResponseBox {
+    match configured(
+        active_settings().server.response_timeout_secs,
+        DEFAULT_RESPONSE_TIMEOUT,
+    ) {
+        Some(limit) => with_deadline(response, Instant::now() + limit),
+        None => response,
+    }
+}
+
+fn with_deadline(response: ResponseBox, deadline: Instant) -> ResponseBox {
+    let status = response.status_code();
+    let headers = response.headers().to_vec();
+    let length = response.data_length();
+    let chunked_threshold = response.chunked_threshold();
+    let body = Deadline {
+        inner: response.into_reader(),
+        deadline,
+    };
+    Response::new(status, headers, body, length, None)
+        .with_chunked_threshold(chunked_threshold)
+        .boxed()
+}
+
+/// A response body that fails to read past its deadline
+struct Deadline<R> {
+    inner: R,
+    deadline: Instant,
+}
+
+impl<R: Read> Read for Deadline<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if Instant::now() >= self.deadline {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "response took longer than server.response_timeout_secs",
+            ));
+        }
+        self.inner.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpStream;
+
+    #[test]
+    fn test_configured() {
+        assert_eq!(
+            configured(None, DEFAULT_WRITE_TIMEOUT),
+            Some(DEFAULT_WRITE_TIMEOUT)
+        );
+        assert_eq!(
+            configured(Some(5), DEFAULT_WRITE_TIMEOUT),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(configured(Some(0), DEFAULT_WRITE_TIMEOUT), None);
+    }
+
+    #[test]
+    fn test_connections_inherit_the_write_timeout() {
+        let listener = listen("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (accepted, _) = listener.accept().unwrap();
+        assert_eq!(
+            accepted.write_timeout().unwrap(),
+            Some(DEFAULT_WRITE_TIMEOUT)
+        );
+        assert_eq!(accepted.read_timeout().unwrap(), None);
+    }
+
+    #[test]
+    fn test_response_past_its_deadline_fails() {
+        let response = Response::from_data(b"module bytes".to_vec())
+            .with_chunked_threshold(usize::MAX)
+            .boxed();
+        let response = with_deadline(response, Instant::now() + Duration::from_secs(60));
+        assert_eq!(response.data_length(), Some(12));
+        assert_eq!(response.chunked_threshold(), usize::MAX);
+        let mut body = Vec::new();
+        response.into_reader().read_to_end(&mut body).unwrap();
+        assert_eq!(body, b"module bytes");
+
+        let response = Response::from_data(b"module bytes".to_vec()).boxed();
+        let response = with_deadline(response, Instant::now());
+        let error = response.into_reader().read(&mut [0; 4]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+}
@@ -0,0 +1,618 @@
+//! Diagnostics switched on from the address bar
+//!
+//! Opening the page as `/?debug=1`, `/?profile=1`, `/?nocache=1`,
+//! `/?throttle=1` or `/?break=1` turns on a debug panel, profiling hooks for
+//! every function, cache bypass, a slow connection or pausing in DevTools on
+//! traps for that browser, without restarting the server or rebuilding; `=0`
+//! turns one off
+//! again. In debug mode the page also puts the instantiated module on
+//! `window.__wasmrun` for poking at from the browser console. The choice is kept in a cookie, so the module and asset requests the
+//! page makes carry it and the server can act on them. Demo visitors cannot
+//! flip any of them.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Request};
+
+/// Cookie holding the toggles that are on
+const COOKIE: &str = "wasmrun_toggles";
+
+/// How fast files are sent with `throttle` on, roughly a slow 3G connection
+pub const THROTTLED_BYTES_PER_SECOND: u64 = 50 * 1024;
+
+/// Runtime toggles of one browser
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Toggles {
+    /// Show the debug panel and expose the module as `window.__wasmrun`
+    pub debug: bool,
+    /// Serve modules with every function instrumented, and show the profiler
+    pub profile: bool,
+    /// Serve files without validators and with `no-store`
+    pub nocache: bool,
+    /// Send files at [`THROTTLED_BYTES_PER_SECOND`]
+    pub throttle: bool,
+    /// Pause in the debugger when the module traps (see [`traps`](super::traps))
+    pub break_on_trap: bool,
+}
+
+impl Toggles {
+    /// The toggles a request carries in its cookie
+    pub fn from_request(request: &Request) -> Self {
+        if super::demo::is_enabled() {
+            return Self::default();
+        }
+        let cookie = request
+            .headers()
+            .iter()
+            .filter(|header| header.field.equiv("Cookie"))
+            .find_map(|header| {
+                header.value.as_str().split(';').find_map(|cookie| {
+                    cookie
+                        .trim()
+                        .strip_prefix(COOKIE)
+                        .and_then(|rest| rest.strip_prefix('='))
+                        .map(str::to_string)
+                })
+            });
+        cookie.map(|value| Self::parse(&value)).unwrap_or_default()
+    }
+
+    /// The toggles for a page request: the cookie's, changed by the query
+    /// parameters of `url`; true when the query changed any of them
+    pub fn for_page(request: &Request, url: &str) -> (Self, bool) {
+        let current = Self::from_request(request);
+        if super::demo::is_enabled() {
+            return (current, false);
+        }
+        let toggles = current.with_query(url);
+        (toggles, toggles != current)
+    }
+
+    /// Comma-separated names, as stored in the cookie
+    fn parse(value: &str) -> Self {
+        let mut toggles = Self::default();
+        for name in value.split(',') {
+            if let Some(flag) = toggles.flag(name.trim()) {
+                *flag = true;
+            }
+        }
+        toggles
+    }
+
+    fn with_query(mut self, url: &str) -> Self {
+        let Some((_, query)) = url.split_once('?') else {
+            return self;
+        };
+        for pair in query.split('&') {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, "1"));
+            let on = match value {
+                "1" | "true" | "on" | "" => true,
+                "0" | "false" | "off" => false,
+                _ => continue,
+            };
+            if let Some(flag) = self.flag(name) {
+                *flag = on;
+            }
+        }
+        self
+    }
+
+    fn flag(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "debug" => Some(&mut self.debug),
+            "profile" => Some(&mut self.profile),
+            "nocache" => Some(&mut self.nocache),
+            "throttle" => Some(&mut self.throttle),
+            "break" => Some(&mut self.break_on_trap),
+            _ => None,
+        }
+    }
+
+    /// Names of the toggles that are on
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            ("debug", self.debug),
+            ("profile", self.profile),
+            ("nocache", self.nocache),
+            ("throttle", self.throttle),
+            ("break", self.break_on_trap),
+        ]
+        .into_iter()
+        .filter_map(|(name, on)| on.then_some(name))
+        .collect()
+    }
+
+    /// `Set-Cookie` header remembering these toggles for pages under `base`
+    pub fn cookie_header(&self, base: &str) -> Header {
+        let names = self.names();
+        let value = if names.is_empty() {
+            // Nothing left on: drop the cookie
+            format!("{COOKIE}=; Path={base}; Max-Age=0; SameSite=Lax")
+        } else {
+            format!("{COOKIE}={}; Path={base}; SameSite=Lax", names.join(","))
+        };
+        Header::from_bytes("Set-Cookie", value).unwrap()
+    }
+
+    /// Add the debug panel and console API to a served page when `debug` is on
+    pub fn inject(&self, html: &str, wasm_filename: &str) -> String {
+        if !self.debug {
+            return html.to_string();
+        }
+        let config = serde_json::json!({
+            "toggles": self.names(),
+            "module": wasm_filename,
+            "version": env!("CARGO_PKG_VERSION"),
+        })
+        .to_string()
+        // Keep a file name from ending the script element
+        .replace('<', "\\u003c");
+        let script = format!(
+            "<script>\n{CONSOLE_API_SCRIPT}{}{}</script>",
+            DEBUG_PANEL_SCRIPT.replace("__CONFIG__", &config),
+            PALETTE_SCRIPT.replace("__CONFIG__", &config)
+        );
+        // Early in the head, so the panel sees the module being fetched
+        let head = html
+            .find("<head")
+            .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+        match head {
+            Some(index) => format!("{}\n{script}{}", &html[..index], &html[index..]),
+            None => format!("{script}\n{html}"),
+        }
+    }
+}
+
+/// A response body sent no faster than [`THROTTLED_BYTES_PER_SECOND`]
+pub struct Throttled<R> {
+    inner: R,
+    started: Option<Instant>,
+    sent: u64,
+}
+
+impl<R> Throttled<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            started: None,
+            sent: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for Throttled<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        // A tenth of a second's worth at a time, so the body trickles out evenly
+        let chunk = buf.len().min((THROTTLED_BYTES_PER_SECOND / 10) as usize);
+        let read = self.inner.read(&mut buf[..chunk])?;
+        self.sent += read as u64;
+        let due = Duration::from_secs_f64(self.sent as f64 / THROTTLED_BYTES_PER_SECOND as f64);
+        if let Some(wait) = due.checked_sub(started.elapsed()) {
+            std::thread::sleep(wait);
+        }
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for Throttled<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+/// `window.__wasmrun`: the latest instance with its module, exports and
+/// memory, and helpers to read memory and call exports
+const CONSOLE_API_SCRIPT: &str = r#"(() => {
+  const instances = [];
+
+  function importedMemory(imports) {
+    for (const namespace of Object.values(imports || {})) {
+      for (const value of Object.values(namespace || {})) {
+        if (value instanceof WebAssembly.Memory) return value;
+      }
+    }
+  }
+  function remember(instance, module, imports, url) {
+    instances.push({ instance, module, url: url || "", memory: importedMemory(imports) });
+    if (instances.length === 1) {
+      console.info("wasmrun: module available as window.__wasmrun (exports, memory, readString(ptr, len), callExport(name, ...args))");
+    }
+  }
+
+  // Catch every instance, however the glue creates it
+  const { instantiate, instantiateStreaming, Instance } = WebAssembly;
+  WebAssembly.instantiate = function (source, imports) {
+    return instantiate.call(this, source, imports).then((result) => {
+      if (result instanceof Instance) remember(result, source, imports);
+      else remember(result.instance, result.module, imports, source && source.url);
+      return result;
+    });
+  };
+  if (instantiateStreaming) {
+    WebAssembly.instantiateStreaming = function (source, imports) {
+      return Promise.resolve(source).then((response) =>
+        instantiateStreaming.call(this, response, imports).then((result) => {
+          remember(result.instance, result.module, imports, response.url);
+          return result;
+        }));
+    };
+  }
+  WebAssembly.Instance = new Proxy(Instance, {
+    construct(target, args) {
+      const instance = new target(...args);
+      remember(instance, args[0], args[1]);
+      return instance;
+    },
+  });
+
+  function latest() {
+    const entry = instances[instances.length - 1];
+    if (!entry) throw new Error("wasmrun: no module has been instantiated yet");
+    return entry;
+  }
+  const api = {
+    instances,
+    get instance() { return latest().instance; },
+    get module() { return latest().module; },
+    get exports() { return latest().instance.exports; },
+    get memory() {
+      const entry = latest();
+      const memory = Object.values(entry.instance.exports).find((value) => value instanceof WebAssembly.Memory) || entry.memory;
+      if (!memory) throw new Error("wasmrun: the module neither exports nor imports a memory");
+      return memory;
+    },
+    listExports() {
+      return WebAssembly.Module.exports(this.module);
+    },
+    readBytes(ptr, length) {
+      return new Uint8Array(this.memory.buffer).slice(ptr, ptr + length);
+    },
+    // UTF-8 text at `ptr`, `length` bytes long or up to a NUL byte
+    readString(ptr, length) {
+      const bytes = new Uint8Array(this.memory.buffer);
+      let end = length === undefined ? bytes.indexOf(0, ptr) : ptr + length;
+      if (end < 0) end = bytes.length;
+      return new TextDecoder().decode(bytes.slice(ptr, end));
+    },
+    callExport(name, ...args) {
+      const exports = this.exports;
+      if (typeof exports[name] !== "function") {
+        const functions = Object.keys(exports).filter((key) => typeof exports[key] === "function");
+        throw new Error(`wasmrun: no exported function "${name}"; try one of: ${functions.join(", ")}`);
+      }
+      return exports[name](...args);
+    },
+  };
+  Object.defineProperty(window, "__wasmrun", { value: api, configurable: true });
+})();
+"#;
+
+const DEBUG_PANEL_SCRIPT: &str = r#"(() => {
+  const CONFIG = __CONFIG__;
+  const loads = [];
+  let errors = 0;
+
+  // Time every module fetch and compile, whatever glue does it
+  const { compile, compileStreaming, instantiate, instantiateStreaming } = WebAssembly;
+  function track(kind, source, run) {
+    const entry = { kind, url: source && source.url ? source.url : "", started: performance.now(), ms: null, size: null, failed: false };
+    if (source && source.byteLength !== undefined) entry.size = source.byteLength;
+    loads.push(entry);
+    render();
+    return run().then((result) => {
+      entry.ms = performance.now() - entry.started;
+      render();
+      return result;
+    }, (error) => {
+      entry.failed = true;
+      render();
+      throw error;
+    });
+  }
+  WebAssembly.compile = function (bytes) {
+    return track("compile", bytes, () => compile.call(this, bytes));
+  };
+  WebAssembly.instantiate = function (source, imports) {
+    if (source instanceof WebAssembly.Module) return instantiate.call(this, source, imports);
+    return track("instantiate", source, () => instantiate.call(this, source, imports));
+  };
+  if (compileStreaming) {
+    WebAssembly.compileStreaming = function (source) {
+      return Promise.resolve(source).then((response) =>
+        track("compile", response, () => compileStreaming.call(this, response)));
+    };
+  }
+  if (instantiateStreaming) {
+    WebAssembly.instantiateStreaming = function (source, imports) {
+      return Promise.resolve(source).then((response) =>
+        track("instantiate", response, () => instantiateStreaming.call(this, response, imports)));
+    };
+  }
+  window.addEventListener("error", () => { errors++; render(); });
+  window.addEventListener("unhandledrejection", () => { errors++; render(); });
+
+  let root;
+  const escape = (text) => String(text).replace(/[&<>"]/g, (c) => `&#${c.charCodeAt(0)};`);
+  function toggleLink(name) {
+    const on = CONFIG.toggles.includes(name);
+    // Keep the rest of the query, such as a gallery page's module
+    const url = new URL(location.href);
+    url.searchParams.set(name, on ? "0" : "1");
+    return `<a href="${escape(url.search)}" class="${on ? "on" : ""}">${name}</a>`;
+  }
+  function render() {
+    if (!root) return;
+    const rows = loads.map((entry) => {
+      const name = entry.url ? new URL(entry.url, document.baseURI).pathname.split("/").pop() : "(bytes)";
+      const size = entry.size === null ? "" : `${(entry.size / 1024).toFixed(1)} KB`;
+      const time = entry.failed ? "failed" : entry.ms === null ? "…" : `${entry.ms.toFixed(1)} ms`;
+      return `<tr><td>${entry.kind}</td><td>${escape(name)}</td><td>${size}</td><td>${time}</td></tr>`;
+    }).join("");
+    root.querySelector(".body").innerHTML = `
+      <div>module <code>${escape(CONFIG.module)}</code> · wasmrun ${CONFIG.version}</div>
+      <div>toggles ${["debug", "profile", "nocache", "throttle", "break"].map(toggleLink).join(" ")}</div>
+      <table>${rows || "<tr><td>no modules loaded yet</td></tr>"}</table>
+      <div class="hint">${navigator.platform.startsWith("Mac") ? "⌘" : "Ctrl+"}K for commands</div>
+      <div class="${errors ? "errors" : ""}">${errors} error${errors === 1 ? "" : "s"}</div>`;
+  }
+  function mount() {
+    const host = document.createElement("div");
+    host.setAttribute("data-wasmrun-debug", "");
+    root = host.attachShadow({ mode: "open" });
+    root.innerHTML = `<style>
+      .panel { position: fixed; left: 12px; bottom: 12px; z-index: 2147483647; max-width: 420px;
+        font: 12px system-ui, sans-serif; color: #e2e8f0; background: #1e293bee; padding: 6px 8px; border-radius: 6px; }
+      header { display: flex; justify-content: space-between; font-weight: 600; margin-bottom: 4px; }
+      button { font: inherit; color: inherit; background: none; border: 0; cursor: pointer; }
+      a { color: #94a3b8; margin-right: 4px; }
+      a.on { color: #38bdf8; font-weight: 600; }
+      table { border-collapse: collapse; margin: 4px 0; }
+      td { padding: 1px 6px 1px 0; font-family: ui-monospace, monospace; }
+      .errors { color: #f87171; }
+      .hint { color: #94a3b8; }
+      .collapsed .body { display: none; }
+    </style><div class="panel"><header><span>wasmrun debug</span><button title="Collapse">▾</button></header><div class="body"></div></div>`;
+    const panel = root.querySelector(".panel");
+    root.querySelector("button").addEventListener("click", () => panel.classList.toggle("collapsed"));
+    document.body.appendChild(host);
+    render();
+  }
+  if (document.body) mount();
+  else document.addEventListener("DOMContentLoaded", mount);
+})();
+"#;
+
+/// The command palette: Ctrl+K (⌘K on macOS) lists server actions, run
+/// through the control route, and the toggles
+const PALETTE_SCRIPT: &str = r#"(() => {
+  const CONFIG = __CONFIG__;
+  const CONTROL = "/__wasmrun/ctl";
+  const FEATURES = "/__wasmrun/features";
+  const METRICS = "/__wasmrun/metrics";
+  let root, input, list, status, actions = [], shown = [], selected = 0;
+
+  async function control(command) {
+    const response = await fetch(CONTROL, {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({ command }),
+    });
+    const text = await response.text();
+    if (!response.ok) throw new Error(text);
+    return text;
+  }
+  async function switchBuild(name) {
+    const response = await fetch(name ? FEATURES + "/" + encodeURIComponent(name) : FEATURES, { method: name ? "POST" : "DELETE" });
+    if (!response.ok) throw new Error(await response.text());
+    return "Rebuilt with " + (name ? `feature set ${name}` : "the default features");
+  }
+  function toggle(name) {
+    const url = new URL(location.href);
+    url.searchParams.set(name, CONFIG.toggles.includes(name) ? "0" : "1");
+    location.href = url.href;
+  }
+  // The server's address for other machines, at this page, without this browser's toggles
+  function shareUrl(server) {
+    const base = window.__WASMRUN_BASE__ || "/";
+    const path = location.pathname.startsWith(base) ? location.pathname.slice(base.length) : "";
+    const url = new URL(path, server.endsWith("/") ? server : server + "/");
+    new URLSearchParams(location.search).forEach((value, name) => {
+      if (!["debug", "profile", "nocache", "throttle", "break"].includes(name)) url.searchParams.append(name, value);
+    });
+    return url.href;
+  }
+  async function copy(text) {
+    try {
+      await navigator.clipboard.writeText(text);
+      return "Copied " + text;
+    } catch (error) {
+      // No clipboard access outside secure contexts, such as a LAN address
+      window.prompt("Share URL", text);
+      return text;
+    }
+  }
+
+  async function load() {
+    const info = await fetch(CONTROL).then((response) => response.ok ? response.json() : {}).catch(() => ({}));
+    const features = info.project
+      ? await fetch(FEATURES).then((response) => response.json()).catch(() => null)
+      : null;
+    const on = (name) => CONFIG.toggles.includes(name);
+    actions = [
+      info.project && { title: "Force rebuild", run: () => control("rebuild") },
+      { title: "Reload open pages", run: () => control("reload") },
+      { title: on("throttle") ? "Stop throttling" : "Throttle network (slow 3G)", run: () => toggle("throttle") },
+      ...(features && features.sets.length
+        ? [{ name: null }, ...features.sets].filter((set) => set.name !== (features.active || null)).map((set) => ({
+            title: `Switch build: ${set.name || "default features"}`,
+            run: () => switchBuild(set.name),
+          }))
+        : []),
+      { title: "Open metrics", run: () => { window.open(METRICS, "_blank"); } },
+      info.share_url && { title: "Copy share URL", run: () => copy(shareUrl(info.share_url)) },
+      { title: on("profile") ? "Stop profiling" : "Profile every function", run: () => toggle("profile") },
+      { title: on("nocache") ? "Use caches again" : "Bypass caches", run: () => toggle("nocache") },
+      { title: on("break") ? "Stop pausing on traps" : "Pause in DevTools on traps", run: () => toggle("break") },
+    ].filter(Boolean);
+    filter();
+  }
+
+  function filter() {
+    const words = input.value.toLowerCase().split(/\s+/).filter(Boolean);
+    shown = actions.filter((action) => words.every((word) => action.title.toLowerCase().includes(word)));
+    selected = Math.min(selected, Math.max(shown.length - 1, 0));
+    list.replaceChildren(...shown.map((action, index) => {
+      const item = document.createElement("li");
+      item.textContent = action.title;
+      item.className = index === selected ? "selected" : "";
+      item.setAttribute("role", "option");
+      item.addEventListener("mousedown", (event) => { event.preventDefault(); run(action); });
+      return item;
+    }));
+  }
+  async function run(action) {
+    status.textContent = `${action.title}…`;
+    try {
+      const message = await action.run();
+      status.textContent = message || "";
+      if (!message) close();
+    } catch (error) {
+      status.textContent = error.message;
+    }
+  }
+
+  function mount() {
+    const host = document.createElement("div");
+    host.setAttribute("data-wasmrun-palette", "");
+    root = host.attachShadow({ mode: "open" });
+    root.innerHTML = `<style>
+      .palette { position: fixed; top: 15vh; left: 50%; transform: translateX(-50%); z-index: 2147483647; width: min(480px, 90vw);
+        font: 13px system-ui, sans-serif; color: #e2e8f0; background: #1e293bf5; border-radius: 8px; box-shadow: 0 10px 40px #0008; }
+      .palette[hidden] { display: none; }
+      input { box-sizing: border-box; width: 100%; font: inherit; color: inherit; background: none; border: 0;
+        border-bottom: 1px solid #334155; padding: 10px 12px; outline: none; }
+      ul { list-style: none; margin: 0; padding: 4px; max-height: 50vh; overflow: auto; }
+      li { padding: 6px 8px; border-radius: 4px; cursor: pointer; }
+      li.selected { background: #334155; }
+      .status { padding: 0 12px 8px; color: #94a3b8; min-height: 1em; }
+    </style><div class="palette" hidden role="dialog" aria-label="wasmrun commands">
+      <input placeholder="Run a wasmrun command…" aria-label="Command" role="combobox" aria-expanded="true">
+      <ul role="listbox"></ul><div class="status" role="status"></div></div>`;
+    input = root.querySelector("input");
+    list = root.querySelector("ul");
+    status = root.querySelector(".status");
+    input.addEventListener("input", () => { selected = 0; filter(); });
+    input.addEventListener("blur", close);
+    input.addEventListener("keydown", (event) => {
+      if (event.key === "ArrowDown" || event.key === "ArrowUp") {
+        event.preventDefault();
+        selected = (selected + (event.key === "ArrowDown" ? 1 : -1) + shown.length) % Math.max(shown.length, 1);
+        filter();
+      } else if (event.key === "Enter" && shown[selected]) {
+        run(shown[selected]);
+      } else if (event.key === "Escape") {
+        close();
+      }
+    });
+    document.body.appendChild(host);
+  }
+  function open() {
+    if (!root) mount();
+    root.querySelector(".palette").hidden = false;
+    input.value = "";
+    status.textContent = "";
+    selected = 0;
+    input.focus();
+    load();
+  }
+  function close() {
+    if (root) root.querySelector(".palette").hidden = true;
+  }
+
+  document.addEventListener("keydown", (event) => {
+    if ((event.ctrlKey || event.metaKey) && !event.altKey && event.key.toLowerCase() === "k") {
+      event.preventDefault();
+      if (root && !root.querySelector(".palette").hidden) close();
+      else open();
+    }
+  });
+})();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_overrides_cookie() {
+        let cookie = Toggles::parse("debug, nocache");
+        assert!(cookie.debug && cookie.nocache && !cookie.profile);
+
+        let toggles = cookie.with_query("/?nocache=0&profile&module=app.wasm");
+        assert_eq!(
+            toggles,
+            Toggles {
+                debug: true,
+                profile: true,
+                nocache: false,
+                throttle: false,
+                break_on_trap: false,
+            }
+        );
+        assert_eq!(toggles.names(), ["debug", "profile"]);
+        // Values it does not understand leave a toggle as it was
+        assert_eq!(cookie.with_query("/?debug=maybe"), cookie);
+    }
+
+    #[test]
+    fn test_throttled_body_is_paced() {
+        let body = vec![7u8; (THROTTLED_BYTES_PER_SECOND / 5) as usize];
+        let started = Instant::now();
+        let mut sent = Vec::new();
+        Throttled::new(std::io::Cursor::new(body.clone()))
+            .read_to_end(&mut sent)
+            .unwrap();
+        assert_eq!(sent, body);
+        assert!(started.elapsed() >= Duration::from_millis(190));
+    }
+
+    #[test]
+    fn test_cookie_round_trip() {
+        let toggles = Toggles::default().with_query("/?debug=1&profile=true");
+        let header = toggles.cookie_header("/app/");
+        let value = header.value.as_str();
+        assert_eq!(
+            value,
+            "wasmrun_toggles=debug,profile; Path=/app/; SameSite=Lax"
+        );
+        let stored = value
+            .split(';')
+            .next()
+            .and_then(|pair| pair.strip_prefix("wasmrun_toggles="))
+            .unwrap();
+        assert_eq!(Toggles::parse(stored), toggles);
+
+        let cleared = Toggles::default().cookie_header("/");
+        assert!(cleared.value.as_str().contains("Max-Age=0"));
+    }
+
+    #[test]
+    fn test_debug_panel_injected_only_when_on() {
+        let page = "<html><head><title>x</title></head><body></body></html>";
+        assert_eq!(Toggles::default().inject(page, "app.wasm"), page);
+        let toggles = Toggles {
+            debug: true,
+            ..Toggles::default()
+        };
+        let injected = toggles.inject(page, "</script>.wasm");
+        assert!(injected.starts_with("<html><head>\n<script>"));
+        assert!(injected.contains(r#""toggles":["debug"]"#));
+        assert!(injected.contains(r#"Object.defineProperty(window, "__wasmrun""#));
+        // The palette gets the same toggles to offer switching them
+        assert!(injected.contains(r#"const CONTROL = "/__wasmrun/ctl";"#));
+        assert!(!injected.contains("__CONFIG__"));
+        // A file name cannot end the script early
+        assert!(injected.contains(r#""module":"\u003c/script>.wasm""#));
+    }
+}
@@ -0,0 +1,350 @@
+//! Traps in the browser, in the terminal and the debugger
+//!
+//! Served pages get an agent that notices a `WebAssembly.RuntimeError`
+//! reaching the page, dispatches it as a `wasmrun:trap` event with the wasm
+//! frames of its stack and posts those to [`TRAP_ROUTE`]. The server names
+//! the frames from the module's name section and exports and prints them, so
+//! the terminal shows where the module trapped and not only that a page did.
+//!
+//! With `?break=1` the agent also wraps every exported function, so a trap is
+//! caught while its caller is still on the stack, and answers the event with
+//! a `debugger` statement: with DevTools open the page pauses right there.
+
+use super::utils::respond;
+use crate::{error_println, info_println};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tiny_http::{Method, Request, Response};
+use wasmparser::{KnownCustom, Name, Parser, Payload};
+
+pub const TRAP_ROUTE: &str = "/__wasmrun/trap";
+
+/// Frames printed per trap; deeper ones are counted
+const MAX_FRAMES: usize = 12;
+
+/// Whether the `?break=1` hint was printed
+static HINTED: AtomicBool = AtomicBool::new(false);
+
+/// A wasm frame as a browser prints it: `wasm-function[12]:0x1a3`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct BrowserFrame {
+    pub function: u32,
+    /// Byte offset in the module
+    pub offset: Option<u64>,
+}
+
+/// What the page posts about a trap
+#[derive(Deserialize)]
+struct BrowserTrap {
+    message: String,
+    /// The export the page called, when it was wrapped
+    export: Option<String>,
+    #[serde(default)]
+    frames: Vec<BrowserFrame>,
+    /// Whether the page paused in the debugger
+    #[serde(default)]
+    paused: bool,
+}
+
+/// A frame with what the module says about its function
+#[derive(Debug, PartialEq)]
+pub struct DecodedFrame {
+    pub function: u32,
+    pub name: Option<String>,
+    /// Offset into the function's body
+    pub offset: Option<u64>,
+}
+
+impl fmt::Display for DecodedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = match self.offset {
+            Some(offset) => format!("func {} +{offset:#x}", self.function),
+            None => format!("func {}", self.function),
+        };
+        match &self.name {
+            Some(name) => write!(f, "{name} ({location})"),
+            None => f.write_str(&location),
+        }
+    }
+}
+
+/// Function names and body ranges of a module, by function index
+#[derive(Default)]
+struct Functions {
+    names: HashMap<u32, String>,
+    bodies: HashMap<u32, Range<usize>>,
+}
+
+impl Functions {
+    fn of(wasm: &[u8]) -> Self {
+        let mut functions = Self::default();
+        let mut imported = 0;
+        let mut defined = 0;
+        for payload in Parser::new(0).parse_all(wasm) {
+            // A module the browser ran but we cannot read only costs the names
+            let Ok(payload) = payload else { break };
+            match payload {
+                Payload::ImportSection(reader) => {
+                    imported = reader
+                        .into_iter()
+                        .flatten()
+                        .filter(|import| matches!(import.ty, wasmparser::TypeRef::Func(_)))
+                        .count() as u32;
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader.into_iter().flatten() {
+                        if export.kind == wasmparser::ExternalKind::Func {
+                            functions
+                                .names
+                                .entry(export.index)
+                                .or_insert_with(|| export.name.to_string());
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    functions.bodies.insert(imported + defined, body.range());
+                    defined += 1;
+                }
+                Payload::CustomSection(reader) => {
+                    let KnownCustom::Name(names) = reader.as_known() else {
+                        continue;
+                    };
+                    for name in names.into_iter().flatten() {
+                        if let Name::Function(map) = name {
+                            // The name section's names beat export names
+                            for naming in map.into_iter().flatten() {
+                                functions
+                                    .names
+                                    .insert(naming.index, naming.name.to_string());
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        functions
+    }
+
+    fn decode(&self, frame: &BrowserFrame) -> DecodedFrame {
+        let body = self.bodies.get(&frame.function);
+        DecodedFrame {
+            function: frame.function,
+            name: self.names.get(&frame.function).cloned(),
+            offset: frame.offset.map(|offset| match body {
+                Some(body) if body.contains(&(offset as usize)) => offset - body.start as u64,
+                _ => offset,
+            }),
+        }
+    }
+}
+
+/// Name the frames of a trap in `wasm`
+pub fn decode(wasm: &[u8], frames: &[BrowserFrame]) -> Vec<DecodedFrame> {
+    let functions = Functions::of(wasm);
+    frames.iter().map(|frame| functions.decode(frame)).collect()
+}
+
+/// Accept a trap posted by a served page
+pub fn receive(mut request: Request, wasm_path: &str) {
+    if *request.method() != Method::Post {
+        let response = Response::from_string("405 Method Not Allowed").with_status_code(405);
+        if let Err(e) = respond(request, response) {
+            error_println!("Error sending trap report response: {e}");
+        }
+        return;
+    }
+
+    let mut body = String::new();
+    let parsed = request
+        .as_reader()
+        .read_to_string(&mut body)
+        .ok()
+        .and_then(|_| serde_json::from_str::<BrowserTrap>(&body).ok());
+    let status = match parsed {
+        Some(trap) => {
+            report(&trap, wasm_path);
+            204
+        }
+        None => 400,
+    };
+    if let Err(e) = respond(request, Response::empty(status)) {
+        error_println!("Error sending trap report response: {e}");
+    }
+}
+
+fn report(trap: &BrowserTrap, wasm_path: &str) {
+    let name = Path::new(wasm_path)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let export = trap
+        .export
+        .as_deref()
+        .map(|export| format!(" in `{export}`"))
+        .unwrap_or_default();
+    info_println!("💥 {name} trapped{export}: {}", trap.message);
+
+    let wasm = fs::read(wasm_path).unwrap_or_default();
+    let frames = decode(&wasm, &trap.frames);
+    for (depth, frame) in frames.iter().take(MAX_FRAMES).enumerate() {
+        info_println!("   {depth:>2}: {frame}");
+    }
+    if frames.len() > MAX_FRAMES {
+        info_println!("       … {} more frames", frames.len() - MAX_FRAMES);
+    }
+
+    if trap.paused {
+        info_println!("⏸️  The page paused in the debugger where it trapped");
+    } else if !HINTED.swap(true, Ordering::Relaxed) {
+        info_println!("💡 Open the page with ?break=1 to pause in DevTools where it traps");
+    }
+}
+
+/// Add the trap agent to a served page, pausing on traps when `pause` is on
+pub fn inject(html: &str, pause: bool) -> String {
+    let script = format!(
+        "<script>\n{}</script>",
+        AGENT_SCRIPT
+            .replace("__ROUTE__", TRAP_ROUTE)
+            .replace("__PAUSE__", &pause.to_string())
+    );
+    // Before any glue reads `instance.exports`, so it gets the wrapped ones
+    let head = html
+        .find("<head")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+    match head {
+        Some(index) => format!("{}\n{script}{}", &html[..index], &html[index..]),
+        None => format!("{script}\n{html}"),
+    }
+}
+
+const AGENT_SCRIPT: &str = r#"(() => {
+  const ROUTE = "__ROUTE__";
+  const PAUSE = __PAUSE__;
+  const seen = new WeakSet();
+
+  const frames = (error) =>
+    [...String(error.stack || "").matchAll(/wasm-function\[(\d+)\](?::0x([0-9a-f]+))?/gi)].map(
+      ([, index, offset]) => ({ function: Number(index), offset: offset ? parseInt(offset, 16) : null }),
+    );
+
+  // Each trap once, however many wrappers and handlers it passes
+  const emit = (error, exportName) => {
+    if (!(error instanceof WebAssembly.RuntimeError) || seen.has(error)) return;
+    seen.add(error);
+    const detail = { error, message: String(error.message), export: exportName || null, frames: frames(error) };
+    window.dispatchEvent(new CustomEvent("wasmrun:trap", { detail }));
+    fetch(ROUTE, {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({
+        message: detail.message,
+        export: detail.export,
+        frames: detail.frames,
+        paused: PAUSE && detail.export !== null,
+      }),
+    }).catch(() => {});
+  };
+  addEventListener("error", (event) => emit(event.error));
+  addEventListener("unhandledrejection", (event) => emit(event.reason));
+  if (!PAUSE) return;
+
+  addEventListener("wasmrun:trap", ({ detail }) => {
+    if (detail.export === null) return;
+    console.error(`wasmrun: ${detail.export} trapped: ${detail.message}`, detail.error);
+    // Paused: the wrapper around the trapping export and its caller are up the call stack
+    debugger;
+  });
+
+  const exportsOf = Object.getOwnPropertyDescriptor(WebAssembly.Instance.prototype, "exports").get;
+  const wrapped = new WeakMap();
+  Object.defineProperty(WebAssembly.Instance.prototype, "exports", {
+    configurable: true,
+    get() {
+      const exports = exportsOf.call(this);
+      if (!wrapped.has(exports)) {
+        const wrapper = {};
+        for (const [name, value] of Object.entries(exports)) {
+          wrapper[name] = typeof value !== "function" ? value : function (...args) {
+            try {
+              return value.apply(this, args);
+            } catch (error) {
+              emit(error, name);
+              throw error;
+            }
+          };
+        }
+        wrapped.set(exports, Object.freeze(wrapper));
+      }
+      return wrapped.get(exports);
+    },
+  });
+})();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_are_named_and_made_relative() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "env" "log" (func $log))
+                (func $helper unreachable)
+                (func (export "run") call $helper))"#,
+        )
+        .unwrap();
+        let functions = Functions::of(&wasm);
+        let helper = functions.bodies[&1].clone();
+
+        let frames = decode(
+            &wasm,
+            &[
+                BrowserFrame {
+                    function: 1,
+                    offset: Some(helper.start as u64 + 1),
+                },
+                BrowserFrame {
+                    function: 2,
+                    offset: None,
+                },
+                BrowserFrame {
+                    function: 7,
+                    offset: Some(0x99),
+                },
+            ],
+        );
+        assert_eq!(frames[0].to_string(), "helper (func 1 +0x1)");
+        assert_eq!(frames[1].to_string(), "run (func 2)");
+        assert_eq!(frames[2].to_string(), "func 7 +0x99");
+    }
+
+    #[test]
+    fn test_pausing_is_opt_in() {
+        let page = "<html><head><title>t</title></head><body></body></html>";
+        let agent = inject(page, false);
+        assert!(agent.find("<script>").unwrap() < agent.find("<title>").unwrap());
+        assert!(agent.contains(r#"const ROUTE = "/__wasmrun/trap";"#));
+        assert!(agent.contains("const PAUSE = false;"));
+        assert!(inject(page, true).contains("const PAUSE = true;"));
+
+        let trap: BrowserTrap = serde_json::from_str(
+            r#"{"message":"unreachable","export":"run","frames":[{"function":3,"offset":291}],"paused":true}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            trap.frames,
+            [BrowserFrame {
+                function: 3,
+                offset: Some(291)
+            }]
+        );
+    }
+}
@@ -0,0 +1,522 @@
+//! Reachable URLs for the dev server
+//!
+//! The server binds to `server.host` (all interfaces by default). The startup
+//! banner lists every URL it can be reached under: localhost, LAN addresses,
+//! the configured `server.hostname` and a tunnel's `server.public_url`. The
+//! browser is opened on the preferred one. Internationalized host names are
+//! put in URLs in their ASCII (punycode) form and displayed in Unicode.
+//! Terminals known to support OSC 8 hyperlinks get them clickable;
+//! `FORCE_HYPERLINK=1` or `0` overrides the guess.
+
+use crate::config::active_settings;
+use crate::warn_println;
+use std::io::IsTerminal;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+
+/// Bind address used when `server.host` is not set
+pub const DEFAULT_BIND_HOST: &str = "0.0.0.0";
+
+/// Environment variable that overrides `server.public_url`, for tunnels started per session
+pub const PUBLIC_URL_ENV: &str = "WASMRUN_PUBLIC_URL";
+
+/// Where a URL reaches the server from, in order of preference for opening the browser
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UrlKind {
+    Hostname,
+    Local,
+    Network,
+    Public,
+}
+
+impl UrlKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            UrlKind::Hostname => "Hostname",
+            UrlKind::Local => "Local",
+            UrlKind::Network => "Network",
+            UrlKind::Public => "Public",
+        }
+    }
+
+    pub fn icon(self) -> &'static str {
+        match self {
+            UrlKind::Hostname => "🏷️",
+            UrlKind::Local => "🏠",
+            UrlKind::Network => "🌐",
+            UrlKind::Public => "🔗",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerUrl {
+    pub kind: UrlKind,
+    /// URL with an ASCII host, suitable for opening
+    pub url: String,
+    /// URL with the host in Unicode, for display
+    pub display: String,
+}
+
+/// All URLs a running server can be reached under
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerUrls {
+    pub urls: Vec<ServerUrl>,
+}
+
+impl ServerUrls {
+    /// URLs for a server on `port`, using the active project settings and local interfaces
+    pub fn for_port(port: u16) -> Self {
+        let server = active_settings().server;
+        let public_url = std::env::var(PUBLIC_URL_ENV)
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .or(server.public_url);
+        let interfaces: Vec<IpAddr> = if_addrs::get_if_addrs()
+            .map(|interfaces| {
+                interfaces
+                    .into_iter()
+                    .filter(|interface| !interface.is_loopback())
+                    .map(|interface| interface.ip())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self::collect(
+            super::tls::scheme(),
+            &bind_host(),
+            port,
+            &super::base_path::active(),
+            server.hostname.as_deref(),
+            public_url.as_deref(),
+            &interfaces,
+        )
+    }
+
+    pub fn collect(
+        scheme: &str,
+        bind_host: &str,
+        port: u16,
+        base_path: &str,
+        hostname: Option<&str>,
+        public_url: Option<&str>,
+        interfaces: &[IpAddr],
+    ) -> Self {
+        let mut urls = Vec::new();
+        let server_url =
+            |kind, host: &str| ServerUrl::for_host(kind, scheme, host, port, base_path);
+
+        match bind_host.parse::<IpAddr>() {
+            Ok(ip) if ip.is_unspecified() => {
+                urls.push(server_url(UrlKind::Local, "localhost"));
+                // 0.0.0.0 only accepts IPv4; `::` usually accepts both
+                let reachable = interfaces
+                    .iter()
+                    .filter(|addr| ip.is_ipv6() || addr.is_ipv4())
+                    .filter(|addr| !is_link_local(addr));
+                for addr in reachable {
+                    urls.push(server_url(UrlKind::Network, &addr.to_string()));
+                }
+            }
+            Ok(ip) if ip.is_loopback() => {
+                let host = if ip.is_ipv4() {
+                    "localhost".to_string()
+                } else {
+                    ip.to_string()
+                };
+                urls.push(server_url(UrlKind::Local, &host));
+            }
+            Ok(ip) => urls.push(server_url(UrlKind::Network, &ip.to_string())),
+            Err(_) if bind_host.eq_ignore_ascii_case("localhost") => {
+                urls.push(server_url(UrlKind::Local, "localhost"));
+            }
+            Err(_) => urls.push(server_url(UrlKind::Hostname, bind_host)),
+        }
+
+        if let Some(hostname) = hostname {
+            let url = server_url(UrlKind::Hostname, hostname);
+            if !urls.iter().any(|existing| existing.url == url.url) {
+                urls.push(url);
+            }
+        }
+
+        if let Some(public_url) = public_url {
+            match UrlParts::parse(public_url) {
+                Ok(parts) => urls.push(ServerUrl {
+                    kind: UrlKind::Public,
+                    url: parts.to_ascii(),
+                    display: parts.to_unicode(),
+                }),
+                Err(e) => warn_println!("Ignoring invalid public URL '{public_url}': {e}"),
+            }
+        }
+
+        Self { urls }
+    }
+
+    /// The URL to open in the browser
+    pub fn preferred(&self) -> Option<&ServerUrl> {
+        self.urls.iter().min_by_key(|url| url.kind)
+    }
+
+    /// The URL to hand to someone on another machine: a public URL, then a
+    /// host name, then a LAN address, and localhost only when nothing else reaches the server
+    pub fn shareable(&self) -> Option<&ServerUrl> {
+        [
+            UrlKind::Public,
+            UrlKind::Hostname,
+            UrlKind::Network,
+            UrlKind::Local,
+        ]
+        .into_iter()
+        .find_map(|kind| self.urls.iter().find(|url| url.kind == kind))
+    }
+}
+
+impl ServerUrl {
+    fn for_host(kind: UrlKind, scheme: &str, host: &str, port: u16, base_path: &str) -> Self {
+        let parts = UrlParts {
+            scheme: scheme.to_string(),
+            host: host.to_string(),
+            port: Some(port),
+            rest: if base_path == "/" {
+                String::new()
+            } else {
+                base_path.to_string()
+            },
+        };
+        Self {
+            kind,
+            url: parts.to_ascii(),
+            display: parts.to_unicode(),
+        }
+    }
+
+    /// `text`, usually the display form padded for a table, linked to the URL
+    pub fn link(&self, text: &str) -> String {
+        hyperlink(&self.url, text)
+    }
+}
+
+/// `text` as an OSC 8 hyperlink to `url` when the terminal shows them
+pub fn hyperlink(url: &str, text: &str) -> String {
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    let supported = *SUPPORTED.get_or_init(|| {
+        std::io::stdout().is_terminal() && supports_hyperlinks(|name| std::env::var(name).ok())
+    });
+    // The dashboard draws captured output as plain text
+    if supported && !crate::tui::is_active() {
+        format!("\x1b]8;;{url}\x1b\\{text}\x1b]8;;\x1b\\")
+    } else {
+        text.to_string()
+    }
+}
+
+/// Whether the terminal described by the variables `env` returns shows
+/// OSC 8 hyperlinks; others may print the escape codes
+fn supports_hyperlinks(env: impl Fn(&str) -> Option<String>) -> bool {
+    if let Some(force) = env("FORCE_HYPERLINK") {
+        return force.trim() != "0";
+    }
+    let term = env("TERM").unwrap_or_default();
+    if term == "dumb" {
+        return false;
+    }
+    if [
+        "WT_SESSION",
+        "KONSOLE_VERSION",
+        "DOMTERM",
+        "KITTY_WINDOW_ID",
+    ]
+    .iter()
+    .any(|name| env(name).is_some())
+    {
+        return true;
+    }
+    let program = env("TERM_PROGRAM").unwrap_or_default();
+    if [
+        "iTerm.app",
+        "WezTerm",
+        "vscode",
+        "ghostty",
+        "Hyper",
+        "Tabby",
+    ]
+    .contains(&program.as_str())
+    {
+        return true;
+    }
+    if env("VTE_VERSION")
+        .and_then(|version| version.trim().parse::<u32>().ok())
+        .is_some_and(|version| version >= 5000)
+    {
+        return true;
+    }
+    ["kitty", "alacritty", "foot", "wezterm", "ghostty"]
+        .iter()
+        .any(|name| term.contains(name))
+}
+
+/// Host to bind the server to
+pub fn bind_host() -> String {
+    active_settings()
+        .server
+        .host
+        .unwrap_or_else(|| DEFAULT_BIND_HOST.to_string())
+}
+
+/// `host:port` address to bind the server to
+pub fn bind_address(port: u16) -> String {
+    let host = bind_host();
+    let host = host_to_ascii(&host).unwrap_or(host);
+    format!("{}:{port}", bracket_ipv6(&host))
+}
+
+/// Address to connect to when checking whether the server is up
+pub fn probe_address(port: u16) -> String {
+    match bind_host().parse::<IpAddr>() {
+        Ok(ip) if ip.is_unspecified() && ip.is_ipv6() => format!("[::1]:{port}"),
+        Ok(ip) if ip.is_unspecified() => format!("127.0.0.1:{port}"),
+        _ => bind_address(port),
+    }
+}
+
+/// ASCII form of a host name or IP address; internationalized names become punycode
+pub fn host_to_ascii(host: &str) -> std::result::Result<String, String> {
+    let host = host.trim();
+    if host.is_empty() {
+        return Err("host is empty".to_string());
+    }
+    if let Ok(ip) = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+    {
+        return Ok(ip.to_string());
+    }
+    let ascii = idna::domain_to_ascii(host).map_err(|e| format!("invalid domain name ({e})"))?;
+    if let Some(c) = ascii
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_')))
+    {
+        return Err(format!("invalid character '{c}' in host name"));
+    }
+    Ok(ascii)
+}
+
+/// Normalize an `http(s)://` URL so its host is ASCII
+pub fn normalize_url(url: &str) -> std::result::Result<String, String> {
+    UrlParts::parse(url).map(|parts| parts.to_ascii())
+}
+
+fn host_to_unicode(host: &str) -> String {
+    match idna::domain_to_unicode(host) {
+        (unicode, Ok(())) => unicode,
+        _ => host.to_string(),
+    }
+}
+
+fn bracket_ipv6(host: &str) -> String {
+    if host.contains(':') {
+        format!("[{host}]")
+    } else {
+        host.to_string()
+    }
+}
+
+fn is_link_local(addr: &IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(v4) => v4.is_link_local(),
+        IpAddr::V6(v6) => (v6.segments()[0] & 0xffc0) == 0xfe80,
+    }
+}
+
+/// The pieces of a URL the banner needs to rewrite
+#[derive(Debug, Clone, PartialEq)]
+struct UrlParts {
+    scheme: String,
+    host: String,
+    port: Option<u16>,
+    /// Path, query and fragment, unchanged
+    rest: String,
+}
+
+impl UrlParts {
+    fn parse(url: &str) -> std::result::Result<Self, String> {
+        let url = url.trim();
+        let (scheme, remainder) = url
+            .split_once("://")
+            .ok_or_else(|| "missing scheme, expected http:// or https://".to_string())?;
+        let scheme = scheme.to_ascii_lowercase();
+        if scheme != "http" && scheme != "https" {
+            return Err(format!("unsupported scheme '{scheme}'"));
+        }
+
+        let authority_end = remainder.find(['/', '?', '#']).unwrap_or(remainder.len());
+        let (authority, rest) = remainder.split_at(authority_end);
+        if authority.contains('@') {
+            return Err("credentials are not allowed in the URL".to_string());
+        }
+
+        let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+            let (host, after) = bracketed
+                .split_once(']')
+                .ok_or_else(|| "unterminated IPv6 address".to_string())?;
+            (host, after.strip_prefix(':'))
+        } else {
+            match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            }
+        };
+        let port = port
+            .map(|port| {
+                port.parse::<u16>()
+                    .map_err(|_| format!("invalid port '{port}'"))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            scheme,
+            host: host_to_ascii(host)?,
+            port,
+            rest: rest.to_string(),
+        })
+    }
+
+    fn with_host(&self, host: &str) -> String {
+        let mut url = format!("{}://{}", self.scheme, bracket_ipv6(host));
+        if let Some(port) = self.port {
+            url.push_str(&format!(":{port}"));
+        }
+        url.push_str(&self.rest);
+        url
+    }
+
+    fn to_ascii(&self) -> String {
+        let host = host_to_ascii(&self.host).unwrap_or_else(|_| self.host.clone());
+        self.with_host(&host)
+    }
+
+    fn to_unicode(&self) -> String {
+        let host = host_to_ascii(&self.host).unwrap_or_else(|_| self.host.clone());
+        self.with_host(&host_to_unicode(&host))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supports_hyperlinks() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert!(supports_hyperlinks(env(&[("TERM_PROGRAM", "vscode")])));
+        assert!(supports_hyperlinks(env(&[("VTE_VERSION", "7600")])));
+        assert!(supports_hyperlinks(env(&[("TERM", "xterm-kitty")])));
+        assert!(!supports_hyperlinks(env(&[("VTE_VERSION", "4600")])));
+        assert!(!supports_hyperlinks(env(&[("TERM", "xterm-256color")])));
+        assert!(!supports_hyperlinks(env(&[
+            ("TERM_PROGRAM", "WezTerm"),
+            ("FORCE_HYPERLINK", "0")
+        ])));
+        assert!(supports_hyperlinks(env(&[
+            ("TERM", "dumb"),
+            ("FORCE_HYPERLINK", "1")
+        ])));
+    }
+
+    fn lan() -> Vec<IpAddr> {
+        vec![
+            "192.168.1.20".parse().unwrap(),
+            "fe80::1".parse().unwrap(),
+            "2001:db8::5".parse().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_all_interfaces_lists_local_and_lan_urls() {
+        let urls = ServerUrls::collect("http", "0.0.0.0", 8420, "/", None, None, &lan());
+        let listed: Vec<(UrlKind, &str)> = urls
+            .urls
+            .iter()
+            .map(|url| (url.kind, url.url.as_str()))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                (UrlKind::Local, "http://localhost:8420"),
+                (UrlKind::Network, "http://192.168.1.20:8420"),
+            ]
+        );
+        assert_eq!(urls.shareable().unwrap().url, "http://192.168.1.20:8420");
+
+        let dual_stack = ServerUrls::collect("http", "::", 8420, "/", None, None, &lan());
+        assert!(dual_stack
+            .urls
+            .iter()
+            .any(|url| url.url == "http://[2001:db8::5]:8420"));
+        assert!(!dual_stack.urls.iter().any(|url| url.url.contains("fe80")));
+
+        let proxied = ServerUrls::collect("http", "127.0.0.1", 8420, "/myapp/", None, None, &[]);
+        assert_eq!(proxied.urls[0].url, "http://localhost:8420/myapp/");
+    }
+
+    #[test]
+    fn test_specific_bind_address_is_only_url() {
+        let urls = ServerUrls::collect("http", "192.168.1.20", 9000, "/", None, None, &lan());
+        assert_eq!(urls.urls.len(), 1);
+        assert_eq!(urls.urls[0].url, "http://192.168.1.20:9000");
+        assert_eq!(urls.preferred().unwrap().kind, UrlKind::Network);
+    }
+
+    #[test]
+    fn test_internationalized_hostname_and_public_url() {
+        let urls = ServerUrls::collect(
+            "http",
+            "127.0.0.1",
+            8420,
+            "/",
+            Some("bücher.test"),
+            Some("https://Straße.example/app?x=1"),
+            &[],
+        );
+
+        let hostname = &urls.urls[1];
+        assert_eq!(hostname.kind, UrlKind::Hostname);
+        assert_eq!(hostname.url, "http://xn--bcher-kva.test:8420");
+        assert_eq!(hostname.display, "http://bücher.test:8420");
+
+        let public = &urls.urls[2];
+        assert_eq!(public.url, "https://xn--strae-oqa.example/app?x=1");
+
+        assert_eq!(urls.preferred().unwrap().kind, UrlKind::Hostname);
+        assert_eq!(urls.shareable().unwrap().kind, UrlKind::Public);
+    }
+
+    #[test]
+    fn test_normalize_url_rejects_bad_urls() {
+        assert!(normalize_url("ftp://example.com").is_err());
+        assert!(normalize_url("example.com").is_err());
+        assert!(normalize_url("https://user@example.com").is_err());
+        assert!(normalize_url("https://example.com:99999").is_err());
+        assert_eq!(
+            normalize_url("https://[::1]:8443/").unwrap(),
+            "https://[::1]:8443/"
+        );
+    }
+
+    #[test]
+    fn test_host_to_ascii() {
+        assert_eq!(host_to_ascii("münchen.dev").unwrap(), "xn--mnchen-3ya.dev");
+        assert_eq!(host_to_ascii("[::1]").unwrap(), "::1");
+        assert!(host_to_ascii("bad host/name").is_err());
+        assert!(host_to_ascii("").is_err());
+    }
+}
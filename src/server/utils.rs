@@ -1,6 +1,9 @@
+use super::browser::{self, Plan};
 use crate::config::{FileInfo, PortStatus, ServerInfo};
 use crate::error::Result;
+use crate::plugin::events::Event;
 use crate::utils::CommandExecutor;
+use crate::{error_println, warn_println};
 use std::fs;
 use std::net::TcpListener;
 use std::path::Path;
@@ -12,6 +15,60 @@ pub fn content_type_header(value: &str) -> tiny_http::Header {
     tiny_http::Header::from_bytes(&b"Content-Type"[..], value.as_bytes()).unwrap()
 }
 
+/// Send a response, adding any headers configured in wasmrun.toml, applying
+/// preview mode and project middleware, and compressing it
+pub fn respond<R: std::io::Read + Send + 'static>(
+    request: tiny_http::Request,
+    mut response: tiny_http::Response<R>,
+) -> std::io::Result<()> {
+    let mut headers = super::headers::configured();
+    super::isolation::apply(&mut headers);
+    for (name, value) in &headers {
+        match tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            Ok(header) => response.add_header(header),
+            Err(_) => warn_println!("Ignoring invalid configured header: {name}"),
+        }
+    }
+
+    let response = super::preview::apply(response);
+    let response = super::middleware::on_response(&request, response);
+    let response = super::compression::apply(&request, response);
+    let response = super::timeouts::limit(response);
+    super::metrics::record(response.status_code().0, response.data_length());
+    super::status::record_client(request.remote_addr().copied(), request.url());
+    crate::plugin::events::publish(Event::RequestServed {
+        client: request
+            .remote_addr()
+            .map(ToString::to_string)
+            .unwrap_or_else(|| "unknown".to_string()),
+        method: request.method().to_string(),
+        url: request.url().to_string(),
+        status: response.status_code().0,
+        bytes: response.data_length(),
+    });
+
+    if crate::tui::is_active() {
+        crate::tui::emit(crate::tui::DevEvent::Request {
+            client: request
+                .remote_addr()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| "unknown".to_string()),
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            status: response.status_code().0,
+            bytes: response.data_length(),
+        });
+    }
+
+    let access =
+        super::access_log::Pending::new(&request, response.status_code().0, response.data_length());
+    let result = request.respond(response);
+    if let Some(access) = access {
+        access.finish();
+    }
+    result
+}
+
 /// Find WASM files in a directory
 #[allow(dead_code)] // TODO: Future WASM file discovery system
 pub fn find_wasm_files(dir_path: &Path) -> Vec<String> {
@@ -44,12 +101,22 @@ pub fn find_wasm_files(dir_path: &Path) -> Vec<String> {
 
 /// Check if the given port is available
 pub fn is_port_available(port: u16) -> bool {
-    TcpListener::bind(format!("0.0.0.0:{port}")).is_ok()
+    TcpListener::bind(super::urls::bind_address(port)).is_ok()
 }
 
 /// Wait for server to be ready and then open browser
 pub fn open_browser_when_ready(port: u16) {
-    let url = format!("http://localhost:{port}");
+    let (opener, url) = match browser::plan(port) {
+        Plan::Open(opener, url) => (opener, url),
+        Plan::Explain(lines) => {
+            println!();
+            for line in lines {
+                println!("{line}");
+            }
+            return;
+        }
+    };
+    let probe = super::urls::probe_address(port);
 
     thread::spawn(move || {
         let start_time = Instant::now();
@@ -60,15 +127,18 @@ pub fn open_browser_when_ready(port: u16) {
 
         loop {
             // Check if we can connect to the server
-            if let Ok(stream) = std::net::TcpStream::connect(format!("localhost:{port}")) {
+            if let Ok(stream) = std::net::TcpStream::connect(&probe) {
                 drop(stream);
 
                 // Server is ready, open browser
                 println!("✅ \x1b[1;32mServer is ready! Opening browser...\x1b[0m");
 
-                if let Err(e) = webbrowser::open(&url) {
+                if let Err(e) = opener.open(&url) {
                     println!("❗ \x1b[1;33mFailed to open browser automatically: {e}\x1b[0m");
-                    println!("🔗 \x1b[1;34mManually open:\x1b[0m \x1b[4;36m{url}\x1b[0m");
+                    println!(
+                        "🔗 \x1b[1;34mManually open:\x1b[0m \x1b[4;36m{}\x1b[0m",
+                        super::urls::hyperlink(&url, &url)
+                    );
                 } else {
                     println!("✅ \x1b[1;32mBrowser opened successfully!\x1b[0m");
                 }
@@ -77,7 +147,7 @@ pub fn open_browser_when_ready(port: u16) {
 
             // Check timeout
             if start_time.elapsed() > timeout {
-                println!("⏰ \x1b[1;33mTimeout waiting for server. Please open manually:\x1b[0m \x1b[4;36m{url}\x1b[0m");
+                println!("⏰ \x1b[1;33mTimeout waiting for server. Please open manually:\x1b[0m \x1b[4;36m{}\x1b[0m", super::urls::hyperlink(&url, &url));
                 break;
             }
 
@@ -90,32 +160,18 @@ pub fn open_browser_when_ready(port: u16) {
 pub fn check_assets_directory() {
     if let Ok(metadata) = fs::metadata("./assets") {
         if metadata.is_dir() {
-            eprintln!("📁 The assets directory exists, but the specific file wasn't found");
+            warn_println!("The assets directory exists, but the specific file wasn't found");
         } else {
-            eprintln!("❌ Found 'assets' but it's not a directory!");
+            error_println!("Found 'assets' but it's not a directory!");
         }
     } else {
-        eprintln!("❌ The assets directory doesn't exist at the expected location!");
+        error_println!("The assets directory doesn't exist at the expected location!");
     }
 }
 
-/// Function to determine content type based on file extension
-pub fn determine_content_type(path: &Path) -> &'static str {
-    match path.extension().and_then(|ext| ext.to_str()) {
-        Some("html") => "text/html",
-        Some("css") => "text/css",
-        Some("js") => "application/javascript",
-        Some("json") => "application/json",
-        Some("wasm") => "application/wasm",
-        Some("png") => "image/png",
-        Some("jpg") | Some("jpeg") => "image/jpeg",
-        Some("svg") => "image/svg+xml",
-        Some("ico") => "image/x-icon",
-        Some("txt") => "text/plain",
-        Some("md") => "text/markdown",
-        Some("map") => "application/json",
-        _ => "application/octet-stream",
-    }
+/// Content type of a served file, from its extension (see [`super::mime`])
+pub fn determine_content_type(path: &Path) -> String {
+    super::mime::for_path(path)
 }
 
 /// Utility functions for server operations
@@ -147,26 +203,12 @@ impl ServerUtils {
                     }
                 } else {
                     match lang {
-                        crate::compiler::ProjectLanguage::Rust => {
-                            println!("\n  ⚠️  \x1b[1;33mRust plugin not found\x1b[0m");
-                            println!("  💡 \x1b[1;33mInstall the wasmrust plugin:\x1b[0m");
-                            println!("     \x1b[1;37mwasmrun plugin install wasmrust\x1b[0m");
-                            println!("\n  ℹ️  \x1b[1;34mAfter installation, wasmrust will be auto-detected\x1b[0m");
-                            println!("\x1b[1;34m╰\x1b[0m\n");
-                            return;
-                        }
-                        crate::compiler::ProjectLanguage::C
-                        | crate::compiler::ProjectLanguage::Asc => {
+                        crate::compiler::ProjectLanguage::Rust
+                        | crate::compiler::ProjectLanguage::C
+                        | crate::compiler::ProjectLanguage::Asc
+                        | crate::compiler::ProjectLanguage::Python => {
                             println!("  🔧 \x1b[1;34mUsing built-in plugin\x1b[0m");
                         }
-                        crate::compiler::ProjectLanguage::Python => {
-                            println!("\n  ⚠️  \x1b[1;33mPython plugin not found\x1b[0m");
-                            println!("  💡 \x1b[1;33mInstall the waspy plugin:\x1b[0m");
-                            println!("     \x1b[1;37mwasmrun plugin install waspy\x1b[0m");
-                            println!("\n  ℹ️  \x1b[1;34mAfter installation, waspy will be auto-detected\x1b[0m");
-                            println!("\x1b[1;34m╰\x1b[0m\n");
-                            return;
-                        }
                         _ => {}
                     }
                 }
@@ -184,12 +226,10 @@ impl ServerUtils {
             }
         }
 
-        use crate::utils::PathResolver;
-
-        let temp_output_dir = match PathResolver::create_temp_directory("wasmrun_temp") {
-            Ok(dir) => dir,
+        let temp_output_dir = match crate::utils::Workspace::path_for(project_path) {
+            Ok(dir) => dir.display().to_string(),
             Err(e) => {
-                println!("  ❌ \x1b[1;31mFailed to create temporary directory: {e}\x1b[0m");
+                println!("  ❌ \x1b[1;31mFailed to locate project workspace: {e}\x1b[0m");
                 println!("\x1b[1;34m╰\x1b[0m");
                 return;
             }
@@ -263,10 +303,13 @@ impl ServerUtils {
 
     /// Print a warning if the port is not available
     pub fn handle_port_conflict(port: u16) -> Result<u16> {
+        super::lifecycle::begin_startup();
         match Self::check_port_availability(port) {
             PortStatus::Available => Ok(port),
             PortStatus::Unavailable { alternative } => {
-                println!("\n⚠️  \x1b[1;33mPort {port} is already in use\x1b[0m");
+                if Self::diagnose_port_conflict(port)? {
+                    return Ok(port);
+                }
 
                 if let Some(alt_port) = alternative {
                     println!("🔄 \x1b[1;34mTrying alternative port: {alt_port}\x1b[0m");
@@ -278,15 +321,35 @@ impl ServerUtils {
                         port + 10
                     );
                     Err(crate::error::WasmrunError::Server(
-                        crate::error::ServerError::startup_failed(
-                            port,
-                            format!("Port {port} is in use and no alternatives found"),
-                        ),
+                        crate::error::ServerError::PortInUse { port },
                     ))
                 }
             }
         }
     }
+
+    /// Say which process holds the busy `port`, and offer to stop it when it
+    /// is another wasmrun server; true when that freed the port
+    pub fn diagnose_port_conflict(port: u16) -> Result<bool> {
+        println!("\n⚠️  \x1b[1;33mPort {port} is already in use\x1b[0m");
+
+        match super::port::find_owner(port) {
+            Some(owner) => {
+                println!("🔎 Held by {}", owner.describe());
+                if owner.instance.is_some()
+                    && super::port::confirm(&format!("Stop it and use port {port}?"))
+                {
+                    super::lifecycle::stop_server(owner.pid)?;
+                    if super::port::wait_until_free(port) {
+                        return Ok(true);
+                    }
+                    println!("⚠️  Port {port} is still busy");
+                }
+            }
+            None => println!("🔎 Could not identify the process holding it"),
+        }
+        Ok(false)
+    }
 }
 
 /// Get Server Info
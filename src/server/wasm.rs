@@ -1,9 +1,14 @@
 use std::fs;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 use tiny_http::Server;
 
+use super::control::ControlContext;
 use super::handler;
+use super::InstanceGuard;
 use crate::template::{TemplateManager, TemplateType};
+use crate::utils::{ArtifactKind, ModuleInspection};
+use crate::{error_println, success_println};
 
 /// Simple server for non-watching mode
 pub fn serve_wasm_file(
@@ -23,8 +28,26 @@ pub fn serve_wasm_file_with_project(
     project_path: Option<&str>,
     serve: bool,
 ) -> Result<(), String> {
-    let server = Server::http(format!("0.0.0.0:{port}"))
-        .map_err(|e| format!("Failed to start server: {e}"))?;
+    let server = Arc::new(super::tls::bind(super::urls::bind_address(port))?);
+    let _instance = InstanceGuard::register(port, wasm_path, project_path);
+    super::shutdown::stop_on_request(&server);
+    stop_on_dashboard_quit(&server);
+    connect_dashboard_keys(port, wasm_path, project_path);
+    super::clipboard::copy_url(port);
+    super::status::mark_started();
+
+    // Pick up header changes in wasmrun.toml without a restart
+    crate::config::reload::spawn_config_watcher();
+    activate_middleware(wasm_path, project_path);
+    start_plugin_hooks(port, project_path);
+    super::live_reload::record_build(wasm_path);
+    let _control = super::control::start(ControlContext {
+        port,
+        wasm_path: wasm_path.to_string(),
+        project_path: project_path.map(str::to_string),
+    });
+    super::a11y::start_audit(port, project_dir(wasm_path, project_path), &server);
+    let _metrics = super::metrics_export::start(port).map_err(|e| e.to_string())?;
 
     // Server is now ready
     if serve {
@@ -34,8 +57,8 @@ pub fn serve_wasm_file_with_project(
     let template_manager = TemplateManager::default();
     let template_type = TemplateType::Console;
 
-    let mut clients_to_reload = Vec::new();
-    for request in server.incoming_requests() {
+    let clients_to_reload = Mutex::new(Vec::new());
+    super::pool::serve(&server, super::pool::workers(), |request| {
         handler::handle_request(
             request,
             None,
@@ -43,15 +66,56 @@ pub fn serve_wasm_file_with_project(
             wasm_path,
             project_path,
             false,
-            &mut clients_to_reload,
+            &clients_to_reload,
             &template_manager,
             &template_type,
         );
-    }
+    });
 
     Ok(())
 }
 
+/// Load `middleware.rhai` from the project, or from next to the wasm file
+fn activate_middleware(wasm_path: &str, project_path: Option<&str>) {
+    super::middleware::activate(project_dir(wasm_path, project_path));
+}
+
+/// Let the plugin building the project know the server is up, and serve its routes
+fn start_plugin_hooks(port: u16, project_path: Option<&str>) {
+    let urls = super::urls::ServerUrls::for_port(port);
+    if let Some(url) = urls.preferred() {
+        crate::plugin::hooks::serve_start(project_path, &url.url);
+    }
+}
+
+/// The project directory, or the wasm file's directory when serving a bare file
+fn project_dir<'a>(wasm_path: &'a str, project_path: Option<&'a str>) -> &'a Path {
+    match project_path {
+        Some(project_path) => Path::new(project_path),
+        None => Path::new(wasm_path).parent().unwrap_or(Path::new(".")),
+    }
+}
+
+/// Stop serving when the terminal dashboard is closed
+fn stop_on_dashboard_quit(server: &Arc<Server>) {
+    let server = Arc::clone(server);
+    crate::tui::on_quit(move || server.unblock());
+}
+
+/// Point the dashboard's rebuild and open-browser keys at this server
+fn connect_dashboard_keys(port: u16, wasm_path: &str, project_path: Option<&str>) {
+    crate::tui::set_page_port(port);
+    if let Some(project_path) = project_path {
+        let (wasm_path, project_path) = (wasm_path.to_string(), project_path.to_string());
+        crate::tui::on_rebuild(move || {
+            match super::control::rebuild_project(Some(&project_path), &wasm_path) {
+                Ok(message) => success_println!("{message}"),
+                Err(e) => error_println!("{e}"),
+            }
+        });
+    }
+}
+
 /// Server for wasm-bindgen files
 pub fn serve_wasm_bindgen_files(
     wasm_path: &str,
@@ -72,8 +136,26 @@ pub fn serve_wasm_bindgen_files_with_project(
     project_path: Option<&str>,
     serve: bool,
 ) -> Result<(), String> {
-    let server = Server::http(format!("0.0.0.0:{port}"))
-        .map_err(|e| format!("Failed to start server: {e}"))?;
+    let server = Arc::new(super::tls::bind(super::urls::bind_address(port))?);
+    let _instance = InstanceGuard::register(port, wasm_path, project_path);
+    super::shutdown::stop_on_request(&server);
+    stop_on_dashboard_quit(&server);
+    connect_dashboard_keys(port, wasm_path, project_path);
+    super::clipboard::copy_url(port);
+    super::status::mark_started();
+
+    // Pick up header changes in wasmrun.toml without a restart
+    crate::config::reload::spawn_config_watcher();
+    activate_middleware(wasm_path, project_path);
+    start_plugin_hooks(port, project_path);
+    super::live_reload::record_build(wasm_path);
+    let _control = super::control::start(ControlContext {
+        port,
+        wasm_path: wasm_path.to_string(),
+        project_path: project_path.map(str::to_string),
+    });
+    super::a11y::start_audit(port, project_dir(wasm_path, project_path), &server);
+    let _metrics = super::metrics_export::start(port).map_err(|e| e.to_string())?;
 
     // Server is now ready
     if serve {
@@ -90,21 +172,20 @@ pub fn serve_wasm_bindgen_files_with_project(
     let template_manager = TemplateManager::default();
     let template_type = TemplateType::App; // Use App template for wasm-bindgen projects
 
-    let mut clients_to_reload = Vec::new();
-
-    for request in server.incoming_requests() {
+    let clients_to_reload = Mutex::new(Vec::new());
+    super::pool::serve(&server, super::pool::workers(), |request| {
         handler::handle_request(
             request,
-            Some(&js_filename),
+            Some(js_filename.as_str()),
             wasm_filename,
             wasm_path,
             project_path,
             false,
-            &mut clients_to_reload,
+            &clients_to_reload,
             &template_manager,
             &template_type,
         );
-    }
+    });
 
     Ok(())
 }
@@ -127,20 +208,33 @@ pub fn handle_wasm_bindgen_files(
     serve_wasm_bindgen_files(wasm_path, js_path, port, wasm_filename, serve)
 }
 
-/// Inspect a WebAssembly file for wasm-bindgen patterns
-#[allow(dead_code)] // TODO: Future wasm-bindgen pattern detection
-fn check_for_wasm_bindgen_patterns(wasm_bytes: &[u8]) -> bool {
-    // Convert the binary to a string for pattern matching
-    let wasm_content = String::from_utf8_lossy(wasm_bytes);
+/// The JS glue to serve `wasm_path` with, when it is a wasm-bindgen module.
+/// The module is recognised by its `_bg.wasm` name or by what it imports and
+/// exports, so callers don't need to know which kind of build they have.
+pub fn wasm_bindgen_glue(wasm_path: &str) -> Option<String> {
+    let path = Path::new(wasm_path);
+    let is_wasm_bindgen = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with("_bg.wasm"))
+        || ModuleInspection::from_file(path)
+            .is_ok_and(|inspection| inspection.artifact == ArtifactKind::WasmBindgen);
+    if !is_wasm_bindgen {
+        return None;
+    }
+    find_corresponding_js_file(path)
+}
 
-    // Check for common wasm-bindgen patterns
-    wasm_content.contains("wasm-bindgen")
-        || wasm_content.contains("__wbindgen")
-        || wasm_content.contains("wbg")
+/// The glue to serve `wasm_path` with: wasm-bindgen JS, the Go runtime,
+/// Emscripten's loader, AssemblyScript bindings or the Pyodide bootstrap
+pub fn glue_for(wasm_path: &str) -> Option<String> {
+    wasm_bindgen_glue(wasm_path)
+        .or_else(|| super::gojs::wasm_exec_for(wasm_path))
+        .or_else(|| super::emscripten::glue_for(wasm_path))
+        .or_else(|| super::asc::glue_for(wasm_path))
+        .or_else(|| super::pyodide::glue_for(wasm_path))
 }
 
 /// Look for a corresponding JS file for a WASM file
-#[allow(dead_code)] // TODO: Future JS file discovery for WASM modules
 fn find_corresponding_js_file(wasm_path: &Path) -> Option<String> {
     let js_path = wasm_path.with_extension("js");
     if js_path.exists() {
@@ -176,3 +270,44 @@ fn find_corresponding_js_file(wasm_path: &Path) -> Option<String> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_wasm_bindgen_glue() {
+        let dir = tempdir().unwrap();
+        let header = b"\0asm\x01\0\0\0";
+        // Imports `wbg::f`, as wasm-bindgen's web target does
+        let mut bindgen = header.to_vec();
+        bindgen.extend([0x01, 0x04, 0x01, 0x60, 0x00, 0x00]);
+        bindgen.extend([
+            0x02, 0x09, 0x01, 0x03, b'w', b'b', b'g', 0x01, b'f', 0x00, 0x00,
+        ]);
+
+        let plain = dir.path().join("plain.wasm");
+        fs::write(&plain, header).unwrap();
+        fs::write(dir.path().join("plain.js"), "console.log(1)").unwrap();
+        assert_eq!(wasm_bindgen_glue(plain.to_str().unwrap()), None);
+
+        let app = dir.path().join("app.wasm");
+        fs::write(&app, &bindgen).unwrap();
+        let glue = dir.path().join("app.js");
+        fs::write(&glue, "// wasm-bindgen glue").unwrap();
+        assert_eq!(
+            wasm_bindgen_glue(app.to_str().unwrap()),
+            Some(glue.to_string_lossy().to_string())
+        );
+
+        let renamed = dir.path().join("game_bg.wasm");
+        fs::write(&renamed, header).unwrap();
+        let glue = dir.path().join("game.js");
+        fs::write(&glue, "// wasm-bindgen glue").unwrap();
+        assert_eq!(
+            wasm_bindgen_glue(renamed.to_str().unwrap()),
+            Some(glue.to_string_lossy().to_string())
+        );
+    }
+}
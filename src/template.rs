@@ -174,7 +174,7 @@ impl TemplateManager {
             WasmrunError::from(format!("Template not found: {}", template_type.as_str()))
         })?;
 
-        self.render_template(template, filename, false)
+        self.render_template(template, filename, false, true)
     }
 
     pub fn generate_html_with_watch_mode(
@@ -187,7 +187,21 @@ impl TemplateManager {
             WasmrunError::from(format!("Template not found: {}", template_type.as_str()))
         })?;
 
-        self.render_template(template, filename, watch_mode)
+        self.render_template(template, filename, watch_mode, true)
+    }
+
+    /// The page as a file for a static host, without the build error overlay
+    /// that only the dev server can feed
+    pub fn generate_static_html(
+        &self,
+        template_type: &TemplateType,
+        filename: &str,
+    ) -> Result<String> {
+        let template = self.templates.get(template_type).ok_or_else(|| {
+            WasmrunError::from(format!("Template not found: {}", template_type.as_str()))
+        })?;
+
+        self.render_template(template, filename, false, false)
     }
 
     fn render_template(
@@ -195,6 +209,7 @@ impl TemplateManager {
         template: &Template,
         filename: &str,
         watch_mode: bool,
+        dev_server: bool,
     ) -> Result<String> {
         let watch_meta = if watch_mode {
             r#"<meta name="wasmrun-watch" content="true">"#
@@ -228,6 +243,13 @@ impl TemplateManager {
             template.js.replace("$FILENAME$", filename)
         ));
 
+        // Rebuilds that fail cover the page with their diagnostics; demo
+        // visitors keep seeing the last good build instead
+        if dev_server && !crate::server::demo::is_enabled() {
+            script_content.push('\n');
+            script_content.push_str(&crate::server::build_status::error_overlay_script());
+        }
+
         html = html.replace("<!-- @script-placeholder -->", &script_content);
 
         Ok(html)
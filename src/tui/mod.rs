@@ -0,0 +1,347 @@
+//! Terminal dashboard for the dev server (`--tui`)
+//!
+//! While the dashboard is running, everything wasmrun prints is captured into
+//! its log pane instead of scrolling past. Server responses, watched file
+//! changes and builds are reported through [`emit`], which does nothing when
+//! the dashboard is off. The server registers what `r` (rebuild) and `o`
+//! (open the page) act on with [`on_rebuild`] and [`set_page_port`].
+
+mod state;
+mod view;
+
+use crate::registry::BuildRecord;
+use crate::server::browser::{self, Plan};
+use crate::watcher::Change;
+use crate::{error_println, warn_println};
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::crossterm::{cursor, execute};
+use ratatui::Terminal;
+use state::{Action, Dashboard};
+use std::fs::File;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Something the dashboard displays
+#[derive(Debug, Clone)]
+pub enum DevEvent {
+    /// A response sent by the dev server
+    Request {
+        client: String,
+        method: String,
+        url: String,
+        status: u16,
+        bytes: Option<usize>,
+    },
+    /// A shared wasm chunk a client did not have to download again
+    Deduplicated { bytes: u64 },
+    /// A rebuild started, e.g. because files changed
+    BuildStarted { reason: String },
+    /// The rebuild started last finished
+    BuildFinished { success: bool },
+    /// A finished build
+    Build(BuildRecord),
+    /// Files the watcher saw change together
+    Watch(Vec<Change>),
+    /// A line of captured output
+    Log(String),
+}
+
+static EVENTS: Mutex<Option<Sender<DevEvent>>> = Mutex::new(None);
+static QUIT_HOOK: Mutex<Option<Box<dyn Fn() + Send>>> = Mutex::new(None);
+static REBUILD_HOOK: Mutex<Option<Arc<dyn Fn() + Send + Sync>>> = Mutex::new(None);
+static PAGE_PORT: Mutex<Option<u16>> = Mutex::new(None);
+
+/// Report an event to the dashboard, if it is running
+pub fn emit(event: DevEvent) {
+    if let Ok(sender) = EVENTS.lock() {
+        if let Some(sender) = sender.as_ref() {
+            let _ = sender.send(event);
+        }
+    }
+}
+
+pub fn is_active() -> bool {
+    EVENTS
+        .lock()
+        .map(|sender| sender.is_some())
+        .unwrap_or(false)
+}
+
+/// Register how to stop the dev server when the dashboard is quit.
+/// Without a hook, quitting exits the process.
+pub fn on_quit(hook: impl Fn() + Send + 'static) {
+    if let Ok(mut quit_hook) = QUIT_HOOK.lock() {
+        *quit_hook = Some(Box::new(hook));
+    }
+}
+
+/// Register how `r` rebuilds the project; it runs on its own thread
+pub fn on_rebuild(hook: impl Fn() + Send + Sync + 'static) {
+    if let Ok(mut rebuild_hook) = REBUILD_HOOK.lock() {
+        *rebuild_hook = Some(Arc::new(hook));
+    }
+}
+
+/// Register the port of the server whose page `o` opens in the browser
+pub fn set_page_port(port: u16) {
+    if let Ok(mut page_port) = PAGE_PORT.lock() {
+        *page_port = Some(port);
+    }
+}
+
+/// A running dashboard; the terminal is restored when it is dropped
+pub struct Tui {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Tui {
+    pub fn start() -> std::io::Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let terminal_out = capture::start(sender.clone())?;
+        if let Ok(mut events) = EVENTS.lock() {
+            *events = Some(sender);
+        }
+
+        if let Err(e) = enter_terminal(&terminal_out) {
+            restore();
+            return Err(e);
+        }
+
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |panic_info| {
+            restore();
+            previous_hook(panic_info);
+        }));
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                if let Err(e) = run_dashboard(terminal_out, receiver, &stop) {
+                    restore();
+                    warn_println!("Terminal UI stopped: {e}");
+                }
+            })
+        };
+
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        restore();
+    }
+}
+
+fn enter_terminal(mut out: &File) -> std::io::Result<()> {
+    enable_raw_mode()?;
+    execute!(out, EnterAlternateScreen, cursor::Hide)
+}
+
+fn run_dashboard(out: File, events: Receiver<DevEvent>, stop: &AtomicBool) -> std::io::Result<()> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(out))?;
+    let mut dashboard = Dashboard::default();
+
+    while !stop.load(Ordering::SeqCst) {
+        while let Ok(event) = events.try_recv() {
+            dashboard.apply(event);
+        }
+        terminal.draw(|frame| view::render(frame, &dashboard))?;
+
+        if !event::poll(Duration::from_millis(200))? {
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match dashboard.handle_key(key) {
+                Action::Quit => break,
+                Action::Rebuild => rebuild(&mut dashboard),
+                Action::OpenBrowser => open_browser(&mut dashboard),
+                Action::None => {}
+            }
+        }
+    }
+
+    if stop.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    // Quit from the keyboard: give the terminal back, then stop the server
+    restore();
+    match QUIT_HOOK.lock().ok().and_then(|mut hook| hook.take()) {
+        Some(hook) => hook(),
+        None => std::process::exit(0),
+    }
+    Ok(())
+}
+
+fn rebuild(dashboard: &mut Dashboard) {
+    if dashboard.rebuilding.is_some() {
+        dashboard.note("⏳ A rebuild is already running");
+        return;
+    }
+    match REBUILD_HOOK.lock().ok().and_then(|hook| hook.clone()) {
+        Some(hook) => {
+            dashboard.note("🔨 Rebuild requested");
+            thread::spawn(move || hook());
+        }
+        None => dashboard.note("⚠️  Nothing to rebuild: this server isn't serving a project"),
+    }
+}
+
+fn open_browser(dashboard: &mut Dashboard) {
+    let Some(port) = PAGE_PORT.lock().ok().and_then(|port| *port) else {
+        dashboard.note("⚠️  The server isn't listening yet");
+        return;
+    };
+    let (opener, url) = match browser::plan(port) {
+        Plan::Open(opener, url) => (opener, url),
+        Plan::Explain(lines) => {
+            for line in lines {
+                dashboard.note(&line);
+            }
+            return;
+        }
+    };
+    dashboard.note(&format!("🌐 Opening {url}"));
+    // Some openers block until the browser exits
+    thread::spawn(move || {
+        if let Err(e) = opener.open(&url) {
+            error_println!("Failed to open the browser: {e}");
+        }
+    });
+}
+
+/// Leave the dashboard and give stdout/stderr back to the terminal; safe to call repeatedly
+fn restore() {
+    static RESTORED: AtomicBool = AtomicBool::new(false);
+    if RESTORED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    if let Ok(mut events) = EVENTS.lock() {
+        *events = None;
+    }
+    capture::stop();
+    let _ = disable_raw_mode();
+    let _ = execute!(std::io::stdout(), LeaveAlternateScreen, cursor::Show);
+}
+
+#[cfg(unix)]
+mod capture {
+    //! Redirects stdout and stderr into a pipe that feeds the log pane
+
+    use super::DevEvent;
+    use regex::Regex;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::io::{FromRawFd, RawFd};
+    use std::sync::mpsc::Sender;
+    use std::sync::Mutex;
+
+    /// Original stdout and stderr, while output is captured
+    static SAVED_FDS: Mutex<Option<(RawFd, RawFd)>> = Mutex::new(None);
+
+    /// Start capturing; returns a handle to the real terminal for drawing
+    pub fn start(events: Sender<DevEvent>) -> std::io::Result<File> {
+        let _ = std::io::stdout().flush();
+        let _ = std::io::stderr().flush();
+
+        let mut pipe = [0; 2];
+        // SAFETY: plain fd syscalls; every fd created here is owned below or closed on error
+        unsafe {
+            if libc::pipe(pipe.as_mut_ptr()) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let saved_out = libc::dup(libc::STDOUT_FILENO);
+            let saved_err = libc::dup(libc::STDERR_FILENO);
+            let terminal = libc::dup(libc::STDOUT_FILENO);
+            if saved_out < 0 || saved_err < 0 || terminal < 0 {
+                let error = std::io::Error::last_os_error();
+                for fd in [pipe[0], pipe[1], saved_out, saved_err, terminal] {
+                    if fd >= 0 {
+                        libc::close(fd);
+                    }
+                }
+                return Err(error);
+            }
+
+            libc::dup2(pipe[1], libc::STDOUT_FILENO);
+            libc::dup2(pipe[1], libc::STDERR_FILENO);
+            libc::close(pipe[1]);
+            if let Ok(mut saved) = SAVED_FDS.lock() {
+                *saved = Some((saved_out, saved_err));
+            }
+
+            let reader = File::from_raw_fd(pipe[0]);
+            std::thread::spawn(move || forward_lines(reader, events));
+
+            Ok(File::from_raw_fd(terminal))
+        }
+    }
+
+    /// Point stdout and stderr back at the terminal
+    pub fn stop() {
+        let _ = std::io::stdout().flush();
+        let _ = std::io::stderr().flush();
+
+        let Some((saved_out, saved_err)) = SAVED_FDS.lock().ok().and_then(|mut saved| saved.take())
+        else {
+            return;
+        };
+        // SAFETY: the saved fds were duplicated in `start` and are only restored once
+        unsafe {
+            libc::dup2(saved_out, libc::STDOUT_FILENO);
+            libc::dup2(saved_err, libc::STDERR_FILENO);
+            libc::close(saved_out);
+            libc::close(saved_err);
+        }
+    }
+
+    fn forward_lines(reader: File, events: Sender<DevEvent>) {
+        let ansi = Regex::new(r"\x1b\[[0-9;?]*[A-Za-z]").unwrap();
+        for line in BufReader::new(reader).lines().map_while(Result::ok) {
+            let line = ansi.replace_all(&line, "").replace('\r', "");
+            if line.trim().is_empty() {
+                continue;
+            }
+            if events.send(DevEvent::Log(line)).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod capture {
+    use super::DevEvent;
+    use std::fs::File;
+    use std::sync::mpsc::Sender;
+
+    pub fn start(_events: Sender<DevEvent>) -> std::io::Result<File> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "the terminal UI is only available on Unix-like systems",
+        ))
+    }
+
+    pub fn stop() {}
+}
@@ -0,0 +1,435 @@
+//! Dashboard state, updated from dev server events and keyboard input
+
+use super::DevEvent;
+use crate::registry::BuildRecord;
+use crate::watcher::ChangeKind;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+const MAX_BUILDS: usize = 50;
+const MAX_REQUESTS: usize = 500;
+const MAX_LOG_LINES: usize = 1000;
+const MAX_WATCH_EVENTS: usize = 200;
+
+/// Clients that made a request within this window are shown as connected
+pub const CLIENT_ACTIVE_WINDOW: Duration = Duration::from_secs(30);
+
+/// Scrollable dashboard panes, in focus order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Builds,
+    Requests,
+    Clients,
+    Logs,
+    Watch,
+}
+
+impl Pane {
+    pub const ALL: [Pane; 5] = [
+        Pane::Builds,
+        Pane::Requests,
+        Pane::Clients,
+        Pane::Logs,
+        Pane::Watch,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Pane::Builds => "Builds",
+            Pane::Requests => "Requests",
+            Pane::Clients => "Clients",
+            Pane::Logs => "Log",
+            Pane::Watch => "Watch",
+        }
+    }
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|pane| *pane == self).unwrap_or(0)
+    }
+}
+
+/// What the event loop should do after a key press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    None,
+    Quit,
+    Rebuild,
+    OpenBrowser,
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestEntry {
+    pub at: chrono::DateTime<chrono::Local>,
+    pub client: String,
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    pub bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WatchEntry {
+    pub at: chrono::DateTime<chrono::Local>,
+    pub path: String,
+    pub kind: ChangeKind,
+    pub removed: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientStats {
+    pub requests: u64,
+    pub last_seen: Instant,
+    pub last_url: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    pub requests: u64,
+    pub errors: u64,
+    pub bytes_served: u64,
+    /// Bytes of shared wasm chunks clients did not download twice
+    pub bytes_deduplicated: u64,
+    pub builds: u64,
+    pub failed_builds: u64,
+}
+
+pub struct Dashboard {
+    pub started: Instant,
+    pub focus: Pane,
+    pub metrics: Metrics,
+    pub builds: VecDeque<BuildRecord>,
+    pub requests: VecDeque<RequestEntry>,
+    pub clients: BTreeMap<String, ClientStats>,
+    pub logs: VecDeque<String>,
+    pub watch: VecDeque<WatchEntry>,
+    /// The reason for the rebuild that is running, and when it started
+    pub rebuilding: Option<(String, Instant)>,
+    /// Whether the last rebuild succeeded
+    pub last_rebuild: Option<bool>,
+    /// Per-pane scroll offset, in lines up from the newest entry
+    scroll: [usize; 5],
+}
+
+impl Default for Dashboard {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            focus: Pane::Requests,
+            metrics: Metrics::default(),
+            builds: VecDeque::new(),
+            requests: VecDeque::new(),
+            clients: BTreeMap::new(),
+            logs: VecDeque::new(),
+            watch: VecDeque::new(),
+            rebuilding: None,
+            last_rebuild: None,
+            scroll: [0; 5],
+        }
+    }
+}
+
+impl Dashboard {
+    pub fn apply(&mut self, event: DevEvent) {
+        match event {
+            DevEvent::Request {
+                client,
+                method,
+                url,
+                status,
+                bytes,
+            } => {
+                self.metrics.requests += 1;
+                if status >= 400 {
+                    self.metrics.errors += 1;
+                }
+                self.metrics.bytes_served += bytes.unwrap_or(0) as u64;
+
+                let stats = self
+                    .clients
+                    .entry(client_host(&client).to_string())
+                    .or_insert_with(|| ClientStats {
+                        requests: 0,
+                        last_seen: Instant::now(),
+                        last_url: String::new(),
+                    });
+                stats.requests += 1;
+                stats.last_seen = Instant::now();
+                stats.last_url = url.clone();
+
+                push_bounded(
+                    &mut self.requests,
+                    RequestEntry {
+                        at: chrono::Local::now(),
+                        client,
+                        method,
+                        url,
+                        status,
+                        bytes,
+                    },
+                    MAX_REQUESTS,
+                );
+            }
+            DevEvent::Deduplicated { bytes } => self.metrics.bytes_deduplicated += bytes,
+            DevEvent::BuildStarted { reason } => self.rebuilding = Some((reason, Instant::now())),
+            DevEvent::BuildFinished { success } => {
+                self.rebuilding = None;
+                self.last_rebuild = Some(success);
+            }
+            DevEvent::Build(record) => {
+                self.metrics.builds += 1;
+                if !record.success {
+                    self.metrics.failed_builds += 1;
+                }
+                push_bounded(&mut self.builds, record, MAX_BUILDS);
+            }
+            DevEvent::Watch(changes) => {
+                let at = chrono::Local::now();
+                for change in changes {
+                    push_bounded(
+                        &mut self.watch,
+                        WatchEntry {
+                            at,
+                            path: change.path.display().to_string(),
+                            kind: change.kind,
+                            removed: change.removed,
+                        },
+                        MAX_WATCH_EVENTS,
+                    );
+                }
+            }
+            DevEvent::Log(line) => push_bounded(&mut self.logs, line, MAX_LOG_LINES),
+        }
+    }
+
+    /// Add a line of the dashboard's own to the log pane
+    pub fn note(&mut self, line: &str) {
+        push_bounded(&mut self.logs, line.to_string(), MAX_LOG_LINES);
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Tab | KeyCode::Right => self.cycle_focus(1),
+            KeyCode::BackTab | KeyCode::Left => self.cycle_focus(Pane::ALL.len() - 1),
+            KeyCode::Char('r') => return Action::Rebuild,
+            KeyCode::Char('o') => return Action::OpenBrowser,
+            KeyCode::Char(c @ '1'..='5') => {
+                self.focus = Pane::ALL[c as usize - '1' as usize];
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.scroll_by(1),
+            KeyCode::Down | KeyCode::Char('j') => self.scroll_to(self.scroll().saturating_sub(1)),
+            KeyCode::PageUp => self.scroll_by(10),
+            KeyCode::PageDown => self.scroll_to(self.scroll().saturating_sub(10)),
+            KeyCode::Home | KeyCode::Char('g') => self.scroll_to(usize::MAX),
+            KeyCode::End | KeyCode::Char('G') => self.scroll_to(0),
+            KeyCode::Char('c') => self.clear_focused(),
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// Scroll offset of the focused pane
+    pub fn scroll(&self) -> usize {
+        self.scroll[self.focus.index()]
+    }
+
+    pub fn scroll_of(&self, pane: Pane) -> usize {
+        self.scroll[pane.index()]
+    }
+
+    /// Number of clients seen within [`CLIENT_ACTIVE_WINDOW`]
+    pub fn active_clients(&self) -> usize {
+        self.clients
+            .values()
+            .filter(|client| client.last_seen.elapsed() <= CLIENT_ACTIVE_WINDOW)
+            .count()
+    }
+
+    fn cycle_focus(&mut self, step: usize) {
+        self.focus = Pane::ALL[(self.focus.index() + step) % Pane::ALL.len()];
+    }
+
+    fn scroll_by(&mut self, lines: usize) {
+        self.scroll_to(self.scroll().saturating_add(lines));
+    }
+
+    fn scroll_to(&mut self, offset: usize) {
+        let max = self.len_of(self.focus).saturating_sub(1);
+        self.scroll[self.focus.index()] = offset.min(max);
+    }
+
+    fn len_of(&self, pane: Pane) -> usize {
+        match pane {
+            Pane::Builds => self.builds.len(),
+            Pane::Requests => self.requests.len(),
+            Pane::Clients => self.clients.len(),
+            Pane::Logs => self.logs.len(),
+            Pane::Watch => self.watch.len(),
+        }
+    }
+
+    fn clear_focused(&mut self) {
+        match self.focus {
+            Pane::Builds => self.builds.clear(),
+            Pane::Requests => self.requests.clear(),
+            Pane::Clients => self.clients.clear(),
+            Pane::Logs => self.logs.clear(),
+            Pane::Watch => self.watch.clear(),
+        }
+        self.scroll[self.focus.index()] = 0;
+    }
+}
+
+fn push_bounded<T>(entries: &mut VecDeque<T>, entry: T, max: usize) {
+    if entries.len() == max {
+        entries.pop_front();
+    }
+    entries.push_back(entry);
+}
+
+/// Host part of a client address; browsers open several connections from different ports
+fn client_host(addr: &str) -> &str {
+    match addr.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+        _ => addr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::watcher::Change;
+
+    fn request(client: &str, url: &str, status: u16) -> DevEvent {
+        DevEvent::Request {
+            client: client.to_string(),
+            method: "GET".to_string(),
+            url: url.to_string(),
+            status,
+            bytes: Some(100),
+        }
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_requests_update_metrics_and_clients() {
+        let mut dashboard = Dashboard::default();
+        dashboard.apply(request("127.0.0.1:50001", "/", 200));
+        dashboard.apply(request("127.0.0.1:50002", "/app.wasm", 200));
+        dashboard.apply(request("[::1]:50003", "/missing", 404));
+
+        assert_eq!(dashboard.metrics.requests, 3);
+        assert_eq!(dashboard.metrics.errors, 1);
+        assert_eq!(dashboard.metrics.bytes_served, 300);
+        assert_eq!(dashboard.clients.len(), 2);
+        assert_eq!(dashboard.clients["127.0.0.1"].requests, 2);
+        assert_eq!(dashboard.clients["127.0.0.1"].last_url, "/app.wasm");
+        assert_eq!(dashboard.active_clients(), 2);
+
+        dashboard.apply(DevEvent::Deduplicated { bytes: 4096 });
+        assert_eq!(dashboard.metrics.bytes_deduplicated, 4096);
+        assert_eq!(dashboard.metrics.requests, 3);
+    }
+
+    #[test]
+    fn test_builds_and_logs_are_bounded() {
+        let mut dashboard = Dashboard::default();
+        for i in 0..MAX_LOG_LINES + 5 {
+            dashboard.apply(DevEvent::Log(format!("line {i}")));
+        }
+        assert_eq!(dashboard.logs.len(), MAX_LOG_LINES);
+        assert_eq!(dashboard.logs.front().map(String::as_str), Some("line 5"));
+
+        dashboard.apply(DevEvent::Build(BuildRecord::new(
+            "/app",
+            "rust",
+            Duration::from_millis(10),
+            Err("boom".to_string()),
+        )));
+        assert_eq!(dashboard.metrics.builds, 1);
+        assert_eq!(dashboard.metrics.failed_builds, 1);
+    }
+
+    #[test]
+    fn test_keyboard_navigation() {
+        let mut dashboard = Dashboard::default();
+        for i in 0..20 {
+            dashboard.apply(DevEvent::Log(format!("line {i}")));
+        }
+
+        assert_eq!(dashboard.handle_key(key(KeyCode::Char('4'))), Action::None);
+        assert_eq!(dashboard.focus, Pane::Logs);
+        dashboard.handle_key(key(KeyCode::PageUp));
+        assert_eq!(dashboard.scroll(), 10);
+        dashboard.handle_key(key(KeyCode::Home));
+        assert_eq!(dashboard.scroll(), 19);
+        dashboard.handle_key(key(KeyCode::End));
+        assert_eq!(dashboard.scroll(), 0);
+
+        dashboard.handle_key(key(KeyCode::Tab));
+        assert_eq!(dashboard.focus, Pane::Watch);
+        dashboard.handle_key(key(KeyCode::Tab));
+        assert_eq!(dashboard.focus, Pane::Builds);
+        dashboard.handle_key(key(KeyCode::BackTab));
+        assert_eq!(dashboard.focus, Pane::Watch);
+        dashboard.handle_key(key(KeyCode::Char('4')));
+
+        dashboard.handle_key(key(KeyCode::Char('c')));
+        assert!(dashboard.logs.is_empty());
+
+        assert_eq!(
+            dashboard.handle_key(key(KeyCode::Char('r'))),
+            Action::Rebuild
+        );
+        assert_eq!(
+            dashboard.handle_key(key(KeyCode::Char('o'))),
+            Action::OpenBrowser
+        );
+        assert_eq!(dashboard.handle_key(key(KeyCode::Char('q'))), Action::Quit);
+        assert_eq!(
+            dashboard.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Action::Quit
+        );
+    }
+
+    #[test]
+    fn test_rebuild_status_and_watch_events() {
+        let mut dashboard = Dashboard::default();
+        dashboard.apply(DevEvent::Watch(vec![Change {
+            path: "src/lib.rs".into(),
+            kind: ChangeKind::Source,
+            removed: false,
+        }]));
+        dashboard.apply(DevEvent::BuildStarted {
+            reason: "src/lib.rs changed".to_string(),
+        });
+        assert_eq!(dashboard.watch.len(), 1);
+        assert_eq!(dashboard.watch[0].path, "src/lib.rs");
+        assert_eq!(
+            dashboard
+                .rebuilding
+                .as_ref()
+                .map(|(reason, _)| reason.as_str()),
+            Some("src/lib.rs changed")
+        );
+
+        dashboard.apply(DevEvent::BuildFinished { success: false });
+        assert!(dashboard.rebuilding.is_none());
+        assert_eq!(dashboard.last_rebuild, Some(false));
+    }
+
+    #[test]
+    fn test_client_host() {
+        assert_eq!(client_host("127.0.0.1:8420"), "127.0.0.1");
+        assert_eq!(client_host("[::1]:8420"), "[::1]");
+        assert_eq!(client_host("unknown"), "unknown");
+    }
+}
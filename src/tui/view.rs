@@ -0,0 +1,303 @@
+//! Dashboard layout and rendering
+
+use super::state::{Dashboard, Pane, CLIENT_ACTIVE_WINDOW};
+use crate::utils::CommandExecutor;
+use crate::watcher::ChangeKind;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::Frame;
+use std::ops::Range;
+
+const KEY_HELP: &str = " q quit · r rebuild · o open browser · c clear · Tab/1-5 focus · ↑↓ PgUp PgDn scroll · Home/End oldest/newest ";
+
+pub fn render(frame: &mut Frame, dashboard: &Dashboard) {
+    let [header, top, middle, bottom, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(8),
+        Constraint::Min(8),
+        Constraint::Length(10),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [builds, metrics] =
+        Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(top);
+    let [requests, clients] =
+        Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)]).areas(middle);
+    let [log, watch] =
+        Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)]).areas(bottom);
+
+    frame.render_widget(header_line(dashboard), header);
+    render_pane(
+        frame,
+        dashboard,
+        Pane::Builds,
+        builds,
+        build_lines(dashboard),
+    );
+    frame.render_widget(
+        Paragraph::new(metric_lines(dashboard))
+            .block(Block::default().borders(Borders::ALL).title(" Metrics ")),
+        metrics,
+    );
+    render_pane(
+        frame,
+        dashboard,
+        Pane::Requests,
+        requests,
+        request_lines(dashboard),
+    );
+    render_pane(
+        frame,
+        dashboard,
+        Pane::Clients,
+        clients,
+        client_lines(dashboard),
+    );
+    render_pane(frame, dashboard, Pane::Logs, log, log_lines(dashboard));
+    render_pane(frame, dashboard, Pane::Watch, watch, watch_lines(dashboard));
+    frame.render_widget(
+        Paragraph::new(KEY_HELP).style(Style::default().fg(Color::DarkGray)),
+        footer,
+    );
+}
+
+fn header_line(dashboard: &Dashboard) -> Paragraph<'static> {
+    let uptime = dashboard.started.elapsed().as_secs();
+    let build = match (&dashboard.rebuilding, dashboard.last_rebuild) {
+        (Some((reason, started)), _) => Span::styled(
+            format!(
+                " · ⏳ rebuilding: {reason} ({}s)",
+                started.elapsed().as_secs()
+            ),
+            Style::default().fg(Color::Yellow),
+        ),
+        (None, Some(true)) => Span::styled(" · ✔ build ok", Style::default().fg(Color::Green)),
+        (None, Some(false)) => Span::styled(" · ✘ build failed", Style::default().fg(Color::Red)),
+        (None, None) => Span::raw(""),
+    };
+    Paragraph::new(Line::from(vec![
+        Span::styled(
+            format!(" wasmrun v{} ", env!("CARGO_PKG_VERSION")),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!(
+            "  dev server · up {:02}:{:02}:{:02} · {} connected",
+            uptime / 3600,
+            uptime / 60 % 60,
+            uptime % 60,
+            dashboard.active_clients()
+        )),
+        build,
+    ]))
+}
+
+/// Render a scrollable pane; `lines` are ordered oldest first
+fn render_pane(
+    frame: &mut Frame,
+    dashboard: &Dashboard,
+    pane: Pane,
+    area: Rect,
+    lines: Vec<Line<'static>>,
+) {
+    let number = Pane::ALL.iter().position(|p| *p == pane).unwrap_or(0) + 1;
+    let focused = dashboard.focus == pane;
+    let offset = dashboard.scroll_of(pane);
+
+    let mut title = format!(" {number} {} ", pane.title());
+    if offset > 0 {
+        title.push_str(&format!("(+{offset}) "));
+    }
+    let border_style = if focused {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default()
+    };
+
+    let window = visible_window(lines.len(), area.height.saturating_sub(2) as usize, offset);
+    let visible: Vec<Line> = lines[window].to_vec();
+    frame.render_widget(
+        Paragraph::new(visible).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style)
+                .title(title),
+        ),
+        area,
+    );
+}
+
+/// Entries to show for a pane `height` lines tall, scrolled `offset` lines up from the newest
+fn visible_window(len: usize, height: usize, offset: usize) -> Range<usize> {
+    let end = len.saturating_sub(offset);
+    end.saturating_sub(height)..end
+}
+
+fn build_lines(dashboard: &Dashboard) -> Vec<Line<'static>> {
+    dashboard
+        .builds
+        .iter()
+        .map(|build| {
+            let (marker, color, detail) = if build.success {
+                (
+                    "✔",
+                    Color::Green,
+                    build.output_path.clone().unwrap_or_default(),
+                )
+            } else {
+                ("✘", Color::Red, build.error.clone().unwrap_or_default())
+            };
+            let time = chrono::DateTime::parse_from_rfc3339(&build.finished_at)
+                .map(|time| {
+                    time.with_timezone(&chrono::Local)
+                        .format("%H:%M:%S")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            Line::from(vec![
+                Span::styled(format!("{marker} "), Style::default().fg(color)),
+                Span::raw(format!(
+                    "{time} {:<8} {:>6}ms ",
+                    build.builder, build.duration_ms
+                )),
+                Span::styled(
+                    detail.lines().next().unwrap_or_default().to_string(),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect()
+}
+
+fn metric_lines(dashboard: &Dashboard) -> Vec<Line<'static>> {
+    let metrics = &dashboard.metrics;
+    let minutes = (dashboard.started.elapsed().as_secs_f64() / 60.0).max(1.0 / 60.0);
+    vec![
+        Line::from(format!("Requests     {}", metrics.requests)),
+        Line::from(format!(
+            "  per minute {:.1}",
+            metrics.requests as f64 / minutes
+        )),
+        Line::from(format!("  errors     {}", metrics.errors)),
+        Line::from(match metrics.bytes_deduplicated {
+            0 => format!(
+                "Served       {}",
+                CommandExecutor::format_file_size(metrics.bytes_served)
+            ),
+            saved => format!(
+                "Served       {} ({} deduplicated)",
+                CommandExecutor::format_file_size(metrics.bytes_served),
+                CommandExecutor::format_file_size(saved)
+            ),
+        }),
+        Line::from(format!(
+            "Builds       {} ({} failed)",
+            metrics.builds, metrics.failed_builds
+        )),
+        Line::from(format!("Clients      {}", dashboard.clients.len())),
+    ]
+}
+
+fn request_lines(dashboard: &Dashboard) -> Vec<Line<'static>> {
+    dashboard
+        .requests
+        .iter()
+        .map(|request| {
+            let color = match request.status {
+                200..=299 => Color::Green,
+                300..=399 => Color::Yellow,
+                _ => Color::Red,
+            };
+            let size = request
+                .bytes
+                .map(|bytes| CommandExecutor::format_file_size(bytes as u64))
+                .unwrap_or_else(|| "-".to_string());
+            Line::from(vec![
+                Span::raw(format!("{} ", request.at.format("%H:%M:%S"))),
+                Span::styled(format!("{} ", request.status), Style::default().fg(color)),
+                Span::raw(format!("{:<4} {} ", request.method, request.url)),
+                Span::styled(
+                    format!("{size} · {}", request.client),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect()
+}
+
+fn client_lines(dashboard: &Dashboard) -> Vec<Line<'static>> {
+    dashboard
+        .clients
+        .iter()
+        .map(|(host, client)| {
+            let active = client.last_seen.elapsed() <= CLIENT_ACTIVE_WINDOW;
+            let (marker, color) = if active {
+                ("●", Color::Green)
+            } else {
+                ("○", Color::DarkGray)
+            };
+            Line::from(vec![
+                Span::styled(format!("{marker} "), Style::default().fg(color)),
+                Span::raw(format!("{host} {} req ", client.requests)),
+                Span::styled(
+                    format!(
+                        "{}s ago {}",
+                        client.last_seen.elapsed().as_secs(),
+                        client.last_url
+                    ),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect()
+}
+
+fn log_lines(dashboard: &Dashboard) -> Vec<Line<'static>> {
+    dashboard
+        .logs
+        .iter()
+        .map(|line| Line::from(line.clone()))
+        .collect()
+}
+
+fn watch_lines(dashboard: &Dashboard) -> Vec<Line<'static>> {
+    dashboard
+        .watch
+        .iter()
+        .map(|entry| {
+            let (label, color) = match entry.kind {
+                ChangeKind::Source => ("source", Color::Yellow),
+                ChangeKind::Manifest => ("manifest", Color::Magenta),
+                ChangeKind::Stylesheet => ("style", Color::Cyan),
+                ChangeKind::Asset => ("asset", Color::Blue),
+            };
+            let path = if entry.removed {
+                format!("{} (removed)", entry.path)
+            } else {
+                entry.path.clone()
+            };
+            Line::from(vec![
+                Span::raw(format!("{} ", entry.at.format("%H:%M:%S"))),
+                Span::styled(format!("{label:<8} "), Style::default().fg(color)),
+                Span::raw(path),
+            ])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_window() {
+        assert_eq!(visible_window(100, 10, 0), 90..100);
+        assert_eq!(visible_window(100, 10, 5), 85..95);
+        assert_eq!(visible_window(5, 10, 0), 0..5);
+        assert_eq!(visible_window(5, 10, 99), 0..0);
+    }
+}
@@ -0,0 +1,392 @@
+//! Content-addressed store for built artifacts
+//!
+//! Every artifact produced by a build is recorded under its SHA-256 digest in
+//! `<cache_dir>/store/objects`, so identical outputs from different projects
+//! (common in monorepos sharing dependencies) are kept only once. Objects are
+//! copies: build outputs stay files of their own, which compilers and users
+//! can write over without touching the store or another project's output.
+//!
+//! A hit marks its object as used, and `wasmrun gc` removes the objects used
+//! least recently first. Hit/miss counters are persisted next to the objects
+//! and reported by `wasmrun stats`.
+
+use super::lock_file::{write_atomic, LockFile};
+use crate::config::WasmrunConfig;
+use crate::error::{Result, WasmrunError};
+use crate::warn_println;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Directory under the cache dir that holds the artifact store
+pub const STORE_DIR: &str = "store";
+
+const OBJECTS_DIR: &str = "objects";
+const STATS_FILE: &str = "stats.json";
+const STATS_LOCK: &str = "stats.lock";
+
+/// How long to wait for another wasmrun updating the stats
+const STATS_WAIT: Duration = Duration::from_secs(2);
+
+/// Content-addressed artifact store
+#[derive(Debug, Clone)]
+pub struct ArtifactStore {
+    root: PathBuf,
+}
+
+/// Persistent store usage counters
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StoreStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Bytes that did not need to be stored again because of a hit
+    pub bytes_deduplicated: u64,
+}
+
+impl StoreStats {
+    /// Fraction of lookups that were already in the store, if any lookups happened
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        (total > 0).then(|| self.hits as f64 / total as f64)
+    }
+}
+
+/// An artifact recorded in the store
+#[derive(Debug, Clone)]
+pub struct StoredArtifact {
+    pub digest: String,
+    pub object_path: PathBuf,
+    pub hit: bool,
+}
+
+/// Objects removed by [`ArtifactStore::collect_garbage`]
+#[derive(Debug, Clone, Default)]
+pub struct StoreGcReport {
+    pub removed: usize,
+    pub freed_bytes: u64,
+    pub remaining_bytes: u64,
+}
+
+impl ArtifactStore {
+    /// Open the store in the configured cache dir
+    pub fn open() -> Result<Self> {
+        Self::open_in(&WasmrunConfig::cache_dir()?)
+    }
+
+    pub fn open_in(cache_dir: &Path) -> Result<Self> {
+        let root = cache_dir.join(STORE_DIR);
+        fs::create_dir_all(root.join(OBJECTS_DIR)).map_err(|e| {
+            WasmrunError::add_context(format!("Creating artifact store {}", root.display()), e)
+        })?;
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// SHA-256 of a file, as lowercase hex
+    pub fn digest_file(path: &Path) -> Result<String> {
+        let mut file = fs::File::open(path).map_err(|e| {
+            WasmrunError::add_context(format!("Opening artifact {}", path.display()), e)
+        })?;
+        let mut hasher = Sha256::new();
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer).map_err(|e| {
+                WasmrunError::add_context(format!("Reading artifact {}", path.display()), e)
+            })?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Path of the object for a digest, sharded by its first two characters
+    pub fn object_path(&self, digest: &str, extension: Option<&str>) -> PathBuf {
+        let name = match extension {
+            Some(ext) => format!("{digest}.{ext}"),
+            None => digest.to_string(),
+        };
+        self.root.join(OBJECTS_DIR).join(&digest[..2]).join(name)
+    }
+
+    /// Record an artifact, adding a copy of it to the store unless identical
+    /// content is already there
+    pub fn store(&self, artifact: &Path) -> Result<StoredArtifact> {
+        let digest = Self::digest_file(artifact)?;
+        let extension = artifact.extension().and_then(|ext| ext.to_str());
+        let object_path = self.object_path(&digest, extension);
+        let hit = object_path.is_file();
+
+        if hit {
+            if let Err(e) = mark_used(&object_path) {
+                crate::debug_println!("Not marking {} as used: {e}", object_path.display());
+            }
+        } else {
+            if let Some(parent) = object_path.parent() {
+                fs::create_dir_all(parent).map_err(|e| {
+                    WasmrunError::add_context(format!("Creating {}", parent.display()), e)
+                })?;
+            }
+            // Add under a temporary name first so a partial copy is never mistaken for a hit
+            let partial = partial_path(&object_path);
+            fs::copy(artifact, &partial)
+                .and_then(|_| fs::rename(&partial, &object_path))
+                .map_err(|e| {
+                    let _ = fs::remove_file(&partial);
+                    WasmrunError::add_context(format!("Storing artifact {}", artifact.display()), e)
+                })?;
+        }
+
+        let size = fs::metadata(&object_path).map(|m| m.len()).unwrap_or(0);
+        self.update_stats(|stats| {
+            if hit {
+                stats.hits += 1;
+                stats.bytes_deduplicated += size;
+            } else {
+                stats.misses += 1;
+            }
+        });
+
+        Ok(StoredArtifact {
+            digest,
+            object_path,
+            hit,
+        })
+    }
+
+    pub fn stats(&self) -> StoreStats {
+        fs::read_to_string(self.root.join(STATS_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Number of stored objects and their total size in bytes
+    pub fn usage(&self) -> (usize, u64) {
+        let Ok(shards) = fs::read_dir(self.root.join(OBJECTS_DIR)) else {
+            return (0, 0);
+        };
+
+        shards
+            .flatten()
+            .filter_map(|shard| fs::read_dir(shard.path()).ok())
+            .flat_map(|objects| objects.flatten())
+            .filter_map(|object| object.metadata().ok())
+            .filter(|metadata| metadata.is_file())
+            .fold((0, 0), |(count, size), metadata| {
+                (count + 1, size + metadata.len())
+            })
+    }
+
+    /// Remove the objects used least recently until those left take at most
+    /// `max_bytes`
+    pub fn collect_garbage(&self, max_bytes: u64) -> Result<StoreGcReport> {
+        let mut objects: Vec<(PathBuf, u64, SystemTime)> = self
+            .objects()
+            .into_iter()
+            .map(|(path, metadata)| {
+                let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                (path, metadata.len(), used)
+            })
+            .collect();
+        objects.sort_by_key(|(_, _, used)| *used);
+
+        let mut report = StoreGcReport {
+            remaining_bytes: objects.iter().map(|(_, size, _)| size).sum(),
+            ..StoreGcReport::default()
+        };
+        for (path, size, _) in objects {
+            if report.remaining_bytes <= max_bytes {
+                break;
+            }
+            fs::remove_file(&path).map_err(|e| {
+                WasmrunError::add_context(format!("Removing {}", path.display()), e)
+            })?;
+            report.removed += 1;
+            report.remaining_bytes -= size;
+            report.freed_bytes += size;
+        }
+        Ok(report)
+    }
+
+    /// Every stored object with its metadata
+    fn objects(&self) -> Vec<(PathBuf, fs::Metadata)> {
+        let Ok(shards) = fs::read_dir(self.root.join(OBJECTS_DIR)) else {
+            return Vec::new();
+        };
+        shards
+            .flatten()
+            .filter_map(|shard| fs::read_dir(shard.path()).ok())
+            .flat_map(|objects| objects.flatten())
+            .filter_map(|object| Some((object.path(), object.metadata().ok()?)))
+            .filter(|(_, metadata)| metadata.is_file())
+            .collect()
+    }
+
+    /// Update the counters under a lock, so concurrent builds don't lose counts
+    fn update_stats(&self, update: impl FnOnce(&mut StoreStats)) {
+        let _lock = match LockFile::acquire(&self.root.join(STATS_LOCK), STATS_WAIT) {
+            Ok(lock) => lock,
+            Err(e) => {
+                warn_println!("Failed to update artifact store stats: {e}");
+                return;
+            }
+        };
+        let mut stats = self.stats();
+        update(&mut stats);
+        if let Ok(content) = serde_json::to_string_pretty(&stats) {
+            if let Err(e) = write_atomic(&self.root.join(STATS_FILE), content.as_bytes()) {
+                warn_println!("Failed to update artifact store stats: {e}");
+            }
+        }
+    }
+}
+
+/// Temporary name next to `path` for a file about to replace it
+fn partial_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.{}.partial", std::process::id()))
+}
+
+/// Record `object` as used now, for [`ArtifactStore::collect_garbage`]
+fn mark_used(object: &Path) -> std::io::Result<()> {
+    fs::File::options()
+        .write(true)
+        .open(object)?
+        .set_modified(SystemTime::now())
+}
+
+/// Record build outputs in the shared store; directories (web app builds) are skipped
+/// and failures only produce a warning
+pub fn record_build_outputs(paths: &[&str]) {
+    let store = match ArtifactStore::open() {
+        Ok(store) => store,
+        Err(e) => {
+            warn_println!("Failed to open artifact store: {e}");
+            return;
+        }
+    };
+
+    for path in paths.iter().filter(|path| Path::new(path).is_file()) {
+        match store.store(Path::new(path)) {
+            Ok(stored) => crate::debug_println!(
+                "Artifact {} stored as {} at {} (hit: {})",
+                path,
+                stored.digest,
+                stored.object_path.display(),
+                stored.hit
+            ),
+            Err(e) => warn_println!("Failed to store artifact {path}: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_identical_artifacts_are_stored_once() {
+        let cache = tempdir().unwrap();
+        let projects = tempdir().unwrap();
+        let store = ArtifactStore::open_in(cache.path()).unwrap();
+
+        let first = projects.path().join("a.wasm");
+        let second = projects.path().join("b.wasm");
+        fs::write(&first, b"\0asm\x01\0\0\0").unwrap();
+        fs::write(&second, b"\0asm\x01\0\0\0").unwrap();
+
+        let stored_first = store.store(&first).unwrap();
+        let stored_second = store.store(&second).unwrap();
+        assert!(!stored_first.hit);
+        assert!(stored_second.hit);
+        assert_eq!(stored_first.object_path, stored_second.object_path);
+        assert!(stored_first
+            .object_path
+            .to_string_lossy()
+            .ends_with(".wasm"));
+
+        assert_eq!(store.usage(), (1, 8));
+        let stats = store.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.bytes_deduplicated, 8);
+        assert_eq!(stats.hit_rate(), Some(0.5));
+    }
+
+    #[test]
+    fn test_outputs_never_alias_objects() {
+        let cache = tempdir().unwrap();
+        let output = tempdir().unwrap();
+        let store = ArtifactStore::open_in(cache.path()).unwrap();
+
+        let module = output.path().join("app.wasm");
+        fs::write(&module, b"\0asm\x01\0\0\0").unwrap();
+        let stored = store.store(&module).unwrap();
+        store.store(&module).unwrap();
+
+        // A build or the user writing over an output leaves the object alone
+        fs::write(&module, b"\0asm\x01\0\0\0rebuilt").unwrap();
+        assert_eq!(fs::read(&stored.object_path).unwrap(), b"\0asm\x01\0\0\0");
+    }
+
+    #[test]
+    fn test_collect_garbage_removes_least_recently_used() {
+        let cache = tempdir().unwrap();
+        let output = tempdir().unwrap();
+        let store = ArtifactStore::open_in(cache.path()).unwrap();
+
+        let old = output.path().join("old.wasm");
+        let new = output.path().join("new.wasm");
+        fs::write(&old, b"old!").unwrap();
+        fs::write(&new, b"new!").unwrap();
+        let stored_old = store.store(&old).unwrap();
+        let stored_new = store.store(&new).unwrap();
+        let long_ago = SystemTime::now() - Duration::from_secs(3600);
+        for object in [&stored_old.object_path, &stored_new.object_path] {
+            fs::File::options()
+                .write(true)
+                .open(object)
+                .unwrap()
+                .set_modified(long_ago)
+                .unwrap();
+        }
+        // Storing identical content again counts as a use
+        store.store(&old).unwrap();
+
+        assert_eq!(store.collect_garbage(8).unwrap().removed, 0);
+        let report = store.collect_garbage(4).unwrap();
+        assert_eq!((report.removed, report.freed_bytes), (1, 4));
+        assert!(stored_old.object_path.is_file());
+        assert!(!stored_new.object_path.exists());
+        assert_eq!(store.collect_garbage(0).unwrap().removed, 1);
+        assert_eq!(store.usage(), (0, 0));
+    }
+
+    #[test]
+    fn test_digest_is_sha256() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("empty");
+        fs::write(&path, b"").unwrap();
+        assert_eq!(
+            ArtifactStore::digest_file(&path).unwrap(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn test_hit_rate_without_lookups() {
+        assert_eq!(StoreStats::default().hit_rate(), None);
+    }
+}
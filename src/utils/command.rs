@@ -1,4 +1,8 @@
 use crate::error::{CompilationError, CompilationResult};
+use crate::server::build_status;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, Output, Stdio};
+use std::thread::JoinHandle;
 
 /// Shared builder command utilities
 pub struct CommandExecutor;
@@ -38,14 +42,41 @@ impl CommandExecutor {
             println!("🔧 Executing: {} {}", command, args.join(" "));
         }
 
-        std::process::Command::new(command)
-            .args(args)
-            .current_dir(working_dir)
-            .output()
-            .map_err(|e| CompilationError::ToolExecutionFailed {
-                tool: command.to_string(),
-                reason: e.to_string(),
-            })
+        let mut process = Command::new(command);
+        process.args(args).current_dir(working_dir);
+        let output = if build_status::is_building() {
+            Self::output_streamed(&mut process)
+        } else {
+            process.output()
+        };
+        output.map_err(|e| CompilationError::ToolExecutionFailed {
+            tool: command.to_string(),
+            reason: e.to_string(),
+        })
+    }
+
+    /// Run `process` like [`Command::output`], passing each line it prints on
+    /// to the terminal and to the build overlay of open pages as it comes
+    pub fn output_streamed(process: &mut Command) -> std::io::Result<Output> {
+        // Cargo drops its colors when writing to a pipe, but the terminal and
+        // the error overlay both show them
+        if std::env::var_os("CARGO_TERM_COLOR").is_none() && std::env::var_os("NO_COLOR").is_none()
+        {
+            process.env("CARGO_TERM_COLOR", "always");
+        }
+        let mut child = process
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdout = tee(child.stdout.take(), false);
+        let stderr = tee(child.stderr.take(), true);
+        let status = child.wait()?;
+        Ok(Output {
+            status,
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
     }
 
     /// Execute a command with live output
@@ -82,6 +113,40 @@ impl CommandExecutor {
         Ok(())
     }
 
+    /// Run user-provided hook commands through the platform shell
+    pub fn run_hooks(stage: &str, commands: &[String], working_dir: &str) -> CompilationResult<()> {
+        let (shell, flag) = if cfg!(target_os = "windows") {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+
+        for command in commands {
+            println!("🪝 Running {stage} hook: {command}");
+
+            let status = std::process::Command::new(shell)
+                .args([flag, command])
+                .current_dir(working_dir)
+                .status()
+                .map_err(|e| CompilationError::ToolExecutionFailed {
+                    tool: shell.to_string(),
+                    reason: e.to_string(),
+                })?;
+
+            if !status.success() {
+                return Err(CompilationError::BuildFailed {
+                    language: "hook".to_string(),
+                    reason: format!(
+                        "{stage} hook '{command}' failed with exit code: {:?}",
+                        status.code()
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     /// Copy output file to the target directory
     pub fn copy_to_output(
         source: &str,
@@ -108,6 +173,28 @@ impl CommandExecutor {
         Ok(output_path)
     }
 
+    /// `word` quoted for a POSIX shell, when it needs it
+    pub fn shell_quote(word: &str) -> String {
+        let plain = !word.is_empty()
+            && word
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+        if plain {
+            word.to_string()
+        } else {
+            format!("'{}'", word.replace('\'', r"'\''"))
+        }
+    }
+
+    /// `program` and `args` as one line that can be pasted into a shell
+    pub fn display_command<S: AsRef<str>>(program: &str, args: &[S]) -> String {
+        std::iter::once(program)
+            .chain(args.iter().map(AsRef::as_ref))
+            .map(Self::shell_quote)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
     /// Format file size in human readable format
     pub fn format_file_size(bytes: u64) -> String {
         if bytes < 1024 {
@@ -122,6 +209,32 @@ impl CommandExecutor {
     }
 }
 
+/// Echo and record the lines read from `pipe` on a thread, which returns them all
+fn tee<R: Read + Send + 'static>(pipe: Option<R>, is_stderr: bool) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut all = Vec::new();
+        let Some(pipe) = pipe else {
+            return all;
+        };
+        for line in BufReader::new(pipe).split(b'\n') {
+            let Ok(line) = line else {
+                break;
+            };
+            let text = String::from_utf8_lossy(&line);
+            let text = text.trim_end_matches('\r');
+            if is_stderr {
+                eprintln!("   │ {text}");
+            } else {
+                println!("   │ {text}");
+            }
+            build_status::append(text);
+            all.extend_from_slice(&line);
+            all.push(b'\n');
+        }
+        all
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -158,6 +271,29 @@ mod tests {
         assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "hello");
     }
 
+    #[test]
+    fn test_output_streamed_keeps_both_streams() {
+        let output = CommandExecutor::output_streamed(
+            Command::new("sh").args(["-c", "echo one; echo two >&2; echo three; exit 3"]),
+        )
+        .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "one\nthree\n");
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "two\n");
+    }
+
+    #[test]
+    fn test_display_command_quotes_what_the_shell_would_split() {
+        assert_eq!(
+            CommandExecutor::display_command("cargo", &["build", "--target", "wasm32-wasip1"]),
+            "cargo build --target wasm32-wasip1"
+        );
+        assert_eq!(
+            CommandExecutor::display_command("sh", &["-c", "make wasm && echo 'done'", ""]),
+            r"sh -c 'make wasm && echo '\''done'\''' ''"
+        );
+    }
+
     #[test]
     fn test_execute_command_failure() {
         let temp_dir = tempdir().unwrap();
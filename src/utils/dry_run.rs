@@ -0,0 +1,91 @@
+//! `--dry-run`: what a mutating command would do, printed instead of done
+//!
+//! Commands collect their steps in a [`DryRun`] rather than acting, then print
+//! it. Each step is one line starting with what kind of step it is, so
+//! release scripts can check or diff the plan.
+
+use super::CommandExecutor;
+use std::fmt::Display;
+use std::path::Path;
+
+/// The steps a command would take, in order
+#[derive(Debug, Default)]
+pub struct DryRun {
+    steps: Vec<String>,
+}
+
+impl DryRun {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A shell command that would run in `dir`
+    pub fn run(&mut self, dir: impl AsRef<Path>, command: impl Display) {
+        let dir = CommandExecutor::shell_quote(&dir.as_ref().to_string_lossy());
+        self.steps.push(format!("run     (in {dir}) {command}"));
+    }
+
+    pub fn create_dir(&mut self, path: impl AsRef<Path>) {
+        self.push("mkdir  ", path.as_ref());
+    }
+
+    pub fn write(&mut self, path: impl AsRef<Path>) {
+        self.push("write  ", path.as_ref());
+    }
+
+    pub fn remove(&mut self, path: impl AsRef<Path>) {
+        self.push("remove ", path.as_ref());
+    }
+
+    /// Something that isn't a command or a file, such as a settings change
+    pub fn note(&mut self, text: impl Display) {
+        self.steps.push(format!("note    {text}"));
+    }
+
+    fn push(&mut self, kind: &str, path: &Path) {
+        self.steps.push(format!(
+            "{kind} {}",
+            CommandExecutor::shell_quote(&path.to_string_lossy())
+        ));
+    }
+
+    pub fn steps(&self) -> &[String] {
+        &self.steps
+    }
+
+    /// Print the steps of `what`, e.g. `wasmrun clean --all`
+    pub fn print(&self, what: &str) {
+        println!("🔎 Dry run of {what}; nothing was changed");
+        if self.steps().is_empty() {
+            println!("   (nothing to do)");
+        }
+        for step in self.steps() {
+            println!("   {step}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_read_as_one_line_each() {
+        let mut plan = DryRun::new();
+        plan.create_dir("dist");
+        plan.run("/my app", "cargo build --release");
+        plan.write("/home/u/.wasmrun/config.toml");
+        plan.remove("build dir");
+        plan.note("unregister plugin 'wasmgo'");
+        assert_eq!(
+            plan.steps(),
+            [
+                "mkdir   dist",
+                "run     (in '/my app') cargo build --release",
+                "write   /home/u/.wasmrun/config.toml",
+                "remove  'build dir'",
+                "note    unregister plugin 'wasmgo'",
+            ]
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Lock files and atomic writes for state shared between wasmrun processes
+//!
+//! A lock is a file created with `create_new`, so exactly one process gets
+//! it. The file records the holder's process ID. A lock left behind by a
+//! process that crashed, or whose ID now belongs to another program, is
+//! stale. Stale locks are moved aside before they are deleted, so two
+//! processes reclaiming the same lock can't delete each other's fresh one.
+
+use crate::process;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often a busy lock is retried
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A lock file without a readable holder is only stale after this long;
+/// before that its holder may still be writing it
+const WRITE_GRACE: Duration = Duration::from_secs(2);
+
+/// Held until dropped
+#[derive(Debug)]
+pub struct LockFile {
+    path: PathBuf,
+    contents: String,
+}
+
+impl LockFile {
+    /// Take the lock at `path`, waiting up to `timeout` for its holder. The
+    /// error of a timeout names the holder.
+    pub fn acquire(path: &Path, timeout: Duration) -> io::Result<Self> {
+        let contents = format!(
+            "{} {}\n",
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()
+        );
+        let started = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(mut file) => {
+                    file.write_all(contents.as_bytes())?;
+                    file.sync_all()?;
+                    return Ok(Self {
+                        path: path.to_path_buf(),
+                        contents,
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e),
+            }
+
+            let holder = fs::read_to_string(path).ok();
+            let age = fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .unwrap_or_default();
+            match &holder {
+                Some(holder) if is_stale(holder, age, |pid| !holds_lock(pid)) => {
+                    reclaim(path, holder);
+                    continue;
+                }
+                // Deleted in the meantime
+                None if !path.exists() => continue,
+                _ => {}
+            }
+
+            if started.elapsed() >= timeout {
+                let holder = holder
+                    .as_deref()
+                    .and_then(holder_pid)
+                    .map(|pid| format!("process {pid}"))
+                    .unwrap_or_else(|| "another process".to_string());
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("{} is held by {holder}", path.display()),
+                ));
+            }
+            std::thread::sleep(RETRY_INTERVAL);
+        }
+    }
+
+    /// The process holding the lock at `path`, if anyone does
+    pub fn holder(path: &Path) -> Option<u32> {
+        fs::read_to_string(path)
+            .ok()
+            .as_deref()
+            .and_then(holder_pid)
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        // Only remove the lock if it is still ours
+        if fs::read_to_string(&self.path).is_ok_and(|contents| contents == self.contents) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn holder_pid(contents: &str) -> Option<u32> {
+    contents.split_whitespace().next()?.parse().ok()
+}
+
+/// Whether the process `pid` could still hold a wasmrun lock
+fn holds_lock(pid: u32) -> bool {
+    process::is_alive(pid) && process::runs_wasmrun(pid)
+}
+
+/// Whether a lock with `contents`, written `age` ago, was left behind; `gone`
+/// says whether a process ID no longer holds locks
+fn is_stale(contents: &str, age: Duration, gone: impl Fn(u32) -> bool) -> bool {
+    match holder_pid(contents) {
+        Some(pid) => pid != std::process::id() && gone(pid),
+        None => age >= WRITE_GRACE,
+    }
+}
+
+/// Remove the stale lock at `path` that read `stale`
+fn reclaim(path: &Path, stale: &str) {
+    // Renaming is atomic, so only one process gets to move the lock aside
+    let aside = path.with_extension(format!("stale-{}", std::process::id()));
+    if fs::rename(path, &aside).is_err() {
+        return;
+    }
+    let moved = fs::read_to_string(&aside).unwrap_or_default();
+    if moved != stale {
+        // Someone took the lock after it was read; give it back unless the
+        // slot was taken again already
+        let _ = fs::hard_link(&aside, path);
+    }
+    let _ = fs::remove_file(&aside);
+}
+
+/// Replace `path` with `contents` so readers see either the old or the new
+/// file, never a partial one, even if wasmrun crashes halfway
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let partial = path.with_file_name(format!(".{name}.{}.tmp", std::process::id()));
+    let written = fs::File::create(&partial).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    match written.and_then(|_| fs::rename(&partial, path)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_lock_is_exclusive_and_released_on_drop() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("instances.lock");
+
+        let lock = LockFile::acquire(&path, Duration::ZERO).unwrap();
+        assert_eq!(LockFile::holder(&path), Some(std::process::id()));
+        let busy = LockFile::acquire(&path, Duration::from_millis(100)).unwrap_err();
+        assert_eq!(busy.kind(), io::ErrorKind::TimedOut);
+        assert!(busy.to_string().contains(&std::process::id().to_string()));
+
+        drop(lock);
+        assert!(!path.exists());
+        drop(LockFile::acquire(&path, Duration::ZERO).unwrap());
+    }
+
+    #[test]
+    fn test_stale_lock_is_reclaimed() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("instances.lock");
+        // No process runs with the largest ID
+        fs::write(&path, format!("{} 0\n", u32::MAX)).unwrap();
+
+        let lock = LockFile::acquire(&path, Duration::from_secs(1)).unwrap();
+        assert_eq!(LockFile::holder(&path), Some(std::process::id()));
+        drop(lock);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_is_stale() {
+        let dead = |pid| pid == 42;
+        assert!(is_stale("42 1\n", Duration::ZERO, dead));
+        assert!(!is_stale("43 1\n", Duration::ZERO, dead));
+        assert!(!is_stale(
+            &format!("{} 1\n", std::process::id()),
+            Duration::ZERO,
+            |_| true
+        ));
+        // Half-written lock files get time to be finished
+        assert!(!is_stale("", Duration::ZERO, dead));
+        assert!(is_stale("", WRITE_GRACE, dead));
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("endpoint");
+        write_atomic(&path, b"8421").unwrap();
+        write_atomic(&path, b"8422").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "8422");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
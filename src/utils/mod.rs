@@ -1,11 +1,27 @@
+mod artifact_store;
 mod command;
+pub mod download;
+mod dry_run;
+mod lock_file;
 mod path;
 mod plugin_utils;
 mod system;
+pub mod typescript;
 mod wasm_analysis;
+pub mod wasm_bench;
+pub mod wasm_features;
+mod wasm_inspect;
+pub mod wasm_instrument;
+pub mod wasm_optimize;
+pub mod workspace;
 
+pub use artifact_store::{record_build_outputs, ArtifactStore};
 pub use command::CommandExecutor;
+pub use dry_run::DryRun;
+pub use lock_file::{write_atomic, LockFile};
 pub use path::PathResolver;
 pub use plugin_utils::PluginUtils;
 pub use system::SystemUtils;
 pub use wasm_analysis::*;
+pub use wasm_inspect::{ArtifactKind, ImportEntry, Limits, ModuleInspection};
+pub use workspace::{collect_garbage, list_workspaces, Workspace};
@@ -1,6 +1,6 @@
 use crate::error::{Result, WasmrunError};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Utility for resolving and handling file paths
 pub struct PathResolver;
@@ -185,19 +185,6 @@ impl PathResolver {
         Ok(CommandExecutor::format_file_size(metadata.len()))
     }
 
-    /// Create and ensure a temporary directory for wasmrun operations
-    pub fn create_temp_directory(name: &str) -> Result<String> {
-        let temp_dir = std::env::temp_dir().join(name);
-
-        if !temp_dir.exists() {
-            std::fs::create_dir_all(&temp_dir).map_err(|e| {
-                WasmrunError::add_context(format!("Creating temporary directory {name}"), e)
-            })?;
-        }
-
-        Ok(temp_dir.to_str().unwrap_or("/tmp").to_string())
-    }
-
     /// Clean up temporary directory used by wasmrun operations
     pub fn cleanup_temp_directory(name: &str) -> Result<()> {
         let temp_dir = std::env::temp_dir().join(name);
@@ -240,6 +227,34 @@ impl PathResolver {
         Ok(())
     }
 
+    /// What [`cleanup_all_temp_directories`](Self::cleanup_all_temp_directories)
+    /// removes: the files in `wasmrun_temp`, other `wasmrun_*` directories and
+    /// the PID file of older versions
+    pub fn stale_temp_paths() -> Vec<PathBuf> {
+        let temp_base = std::env::temp_dir();
+        let entries = |dir: &Path| {
+            fs::read_dir(dir)
+                .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+                .unwrap_or_else(|_| Vec::new())
+        };
+        let mut paths: Vec<PathBuf> = entries(&temp_base.join("wasmrun_temp"))
+            .into_iter()
+            .filter(|path| path.is_file())
+            .collect();
+        paths.extend(entries(&temp_base).into_iter().filter(|path| {
+            path.is_dir()
+                && path.file_name().is_some_and(|name| {
+                    let name = name.to_string_lossy();
+                    name.starts_with("wasmrun_") && name != "wasmrun_temp"
+                })
+        }));
+        let pid_file = temp_base.join("wasmrun_server.pid");
+        if pid_file.exists() {
+            paths.push(pid_file);
+        }
+        paths
+    }
+
     /// Clean all wasmrun temporary directories and files
     pub fn cleanup_all_temp_directories() -> Result<()> {
         println!("🧹 Starting cleanup of all wasmrun temporary directories...");
@@ -278,9 +293,9 @@ impl PathResolver {
             }
         }
 
-        // Clean the PID file if it exists
-        let pid_file = "/tmp/wasmrun_server.pid";
-        if Path::new(pid_file).exists() {
+        // Older versions tracked the server in a PID file instead of the registry
+        let pid_file = temp_base.join("wasmrun_server.pid");
+        if pid_file.exists() {
             if let Err(e) = fs::remove_file(pid_file) {
                 println!("⚠️  Warning: Failed to remove PID file: {e}");
             } else {
@@ -508,12 +523,4 @@ mod tests {
             "1.50 GB"
         );
     }
-
-    #[test]
-    fn test_create_temp_directory() {
-        let result = PathResolver::create_temp_directory("wasmrun_test");
-        assert!(result.is_ok());
-        let path = result.unwrap();
-        assert!(Path::new(&path).exists());
-    }
 }
@@ -34,6 +34,9 @@ impl SystemUtils {
 
     /// Get the latest version of a crate from crates.io
     pub fn get_latest_crates_version(crate_name: &str) -> Option<String> {
+        if crate::offline::is_offline() {
+            return None;
+        }
         if let Ok(output) = Command::new("cargo")
             .args(["search", crate_name, "--limit", "1"])
             .output()
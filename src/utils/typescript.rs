@@ -0,0 +1,300 @@
+//! TypeScript definitions for built modules
+//!
+//! wasm-bindgen writes `<name>.d.ts` for its JS glue and `<name>_bg.wasm.d.ts`
+//! for the raw module. After a build both are merged into `types.d.ts` next to
+//! the module, which the server also exposes at [`TYPES_ROUTE`], so consumers
+//! find the API at the same place whatever the crate is called. Modules
+//! without glue get definitions generated from their imports and exports.
+
+use crate::compiler::builder::BuildResult;
+use crate::warn_println;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use wasmparser::{ExternalKind, FuncType, Parser, Payload, RefType, TypeRef, ValType};
+
+/// Name of the merged definitions in a build's output directory
+pub const BUNDLED_TYPES: &str = "types.d.ts";
+
+/// Where the server exposes the definitions of the module it serves
+pub const TYPES_ROUTE: &str = "/types.d.ts";
+
+/// Merge the definitions wasm-bindgen generated for `result` into
+/// [`BUNDLED_TYPES`] next to the module, and list the file with the outputs
+pub fn bundle(result: &mut BuildResult) -> Option<PathBuf> {
+    let js_path = result.js_path.as_deref()?;
+    let merged = merge_bindgen_definitions(&bindgen_definitions(Path::new(js_path))?);
+    let target = Path::new(&result.wasm_path).parent()?.join(BUNDLED_TYPES);
+    if let Err(e) = fs::write(&target, merged) {
+        warn_println!("Could not write {}: {e}", target.display());
+        return None;
+    }
+    let target_name = target.to_string_lossy().to_string();
+    if !result.additional_files.contains(&target_name) {
+        result.additional_files.push(target_name);
+    }
+    Some(target)
+}
+
+/// Definitions for the module at `wasm_path`: the bundled ones, else those
+/// wasm-bindgen left next to it, else generated from the module itself
+pub fn for_module(wasm_path: &str) -> Result<String, String> {
+    let path = Path::new(wasm_path);
+    if let Some(bundled) = path
+        .parent()
+        .map(|dir| dir.join(BUNDLED_TYPES))
+        .and_then(|bundled| fs::read_to_string(bundled).ok())
+    {
+        return Ok(bundled);
+    }
+    let glue = path.to_string_lossy();
+    let glue = glue.strip_suffix("_bg.wasm").unwrap_or(&glue);
+    let glue = glue.strip_suffix(".wasm").unwrap_or(glue);
+    if let Some(definitions) = bindgen_definitions(Path::new(&format!("{glue}.js"))) {
+        return Ok(merge_bindgen_definitions(&definitions));
+    }
+
+    let bytes = fs::read(path).map_err(|e| format!("Cannot read {wasm_path}: {e}"))?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    generate(&name, &bytes)
+}
+
+/// The `.d.ts` files wasm-bindgen wrote for the glue at `js_path`: the glue's
+/// own and, when present, the raw module's
+struct BindgenDefinitions {
+    glue: String,
+    raw: Option<String>,
+}
+
+fn bindgen_definitions(js_path: &Path) -> Option<BindgenDefinitions> {
+    let stem = js_path.file_stem()?.to_string_lossy().to_string();
+    let dir = js_path.parent()?;
+    let glue = fs::read_to_string(dir.join(format!("{stem}.d.ts"))).ok()?;
+    let raw = fs::read_to_string(dir.join(format!("{stem}_bg.wasm.d.ts"))).ok();
+    Some(BindgenDefinitions { glue, raw })
+}
+
+fn merge_bindgen_definitions(definitions: &BindgenDefinitions) -> String {
+    let mut merged = String::from("// Generated by wasmrun from the wasm-bindgen definitions\n");
+    merged.push_str(definitions.glue.trim_end());
+    merged.push('\n');
+    // The web target already describes the raw exports as `InitOutput`
+    if let Some(raw) = definitions
+        .raw
+        .as_deref()
+        .filter(|_| !definitions.glue.contains("interface InitOutput"))
+    {
+        merged.push_str("\n/** Exports of the raw module, below the JS glue */\nexport declare namespace raw {\n");
+        for line in raw
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("export "))
+        {
+            let _ = writeln!(merged, "  {line}");
+        }
+        merged.push_str("}\n");
+    }
+    merged
+}
+
+/// Definitions for a core module's imports and exports
+pub fn generate(name: &str, bytes: &[u8]) -> Result<String, String> {
+    let mut types: Vec<Option<FuncType>> = Vec::new();
+    let mut functions: Vec<u32> = Vec::new();
+    let mut imports: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    let mut exports: Vec<(String, String)> = Vec::new();
+
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload.map_err(|e| format!("{name} is not a valid module: {e}"))? {
+            Payload::Version {
+                encoding: wasmparser::Encoding::Component,
+                ..
+            } => {
+                return Err(format!(
+                    "{name} is a component; `jco types` generates TypeScript for components"
+                ))
+            }
+            Payload::TypeSection(reader) => {
+                for group in reader.into_iter_err_on_gc_types() {
+                    types.push(group.ok());
+                }
+            }
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import.map_err(|e| e.to_string())?;
+                    let ty = match import.ty {
+                        TypeRef::Func(index) => {
+                            functions.push(index);
+                            function_signature(types.get(index as usize).cloned().flatten())
+                        }
+                        TypeRef::Memory(_) => ": WebAssembly.Memory".to_string(),
+                        TypeRef::Table(_) => ": WebAssembly.Table".to_string(),
+                        TypeRef::Global(_) => ": WebAssembly.Global | number | bigint".to_string(),
+                        TypeRef::Tag(_) => ": unknown".to_string(),
+                    };
+                    imports
+                        .entry(import.module.to_string())
+                        .or_default()
+                        .push((import.name.to_string(), ty));
+                }
+            }
+            Payload::FunctionSection(reader) => {
+                for index in reader {
+                    functions.push(index.map_err(|e| e.to_string())?);
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export.map_err(|e| e.to_string())?;
+                    let ty = match export.kind {
+                        ExternalKind::Func => function_signature(
+                            functions
+                                .get(export.index as usize)
+                                .and_then(|ty| types.get(*ty as usize).cloned().flatten()),
+                        ),
+                        ExternalKind::Memory => ": WebAssembly.Memory".to_string(),
+                        ExternalKind::Table => ": WebAssembly.Table".to_string(),
+                        ExternalKind::Global => ": WebAssembly.Global".to_string(),
+                        ExternalKind::Tag => ": unknown".to_string(),
+                    };
+                    exports.push((export.name.to_string(), ty));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = format!("// Generated by wasmrun from the imports and exports of {name}\n\n");
+    out.push_str("export interface Exports {\n");
+    for (export, ty) in &exports {
+        let readonly = if ty.starts_with(':') { "readonly " } else { "" };
+        let _ = writeln!(out, "  {readonly}{}{ty};", property(export));
+    }
+    out.push_str("}\n\nexport interface Imports {\n");
+    for (module, entries) in &imports {
+        let _ = writeln!(out, "  {}: {{", property(module));
+        for (import, ty) in entries {
+            let _ = writeln!(out, "    {}{ty};", property(import));
+        }
+        out.push_str("  };\n");
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+/// A method signature, or a property type when the type is unknown
+fn function_signature(ty: Option<FuncType>) -> String {
+    let Some(ty) = ty else {
+        return ": (...args: unknown[]) => unknown".to_string();
+    };
+    let params: Vec<String> = ty
+        .params()
+        .iter()
+        .enumerate()
+        .map(|(index, param)| format!("p{index}: {}", ts_type(param)))
+        .collect();
+    let result = match ty.results() {
+        [] => "void".to_string(),
+        [single] => ts_type(single).to_string(),
+        results => format!(
+            "[{}]",
+            results.iter().map(ts_type).collect::<Vec<_>>().join(", ")
+        ),
+    };
+    format!("({}): {result}", params.join(", "))
+}
+
+fn ts_type(ty: &ValType) -> &'static str {
+    match ty {
+        ValType::I32 | ValType::F32 | ValType::F64 => "number",
+        ValType::I64 => "bigint",
+        // JS cannot pass or receive vectors
+        ValType::V128 => "never",
+        ValType::Ref(reference) if *reference == RefType::FUNCREF => "Function | null",
+        ValType::Ref(_) => "unknown",
+    }
+}
+
+/// `name` as an interface member, quoted unless it is an identifier
+fn property(name: &str) -> String {
+    let identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if identifier {
+        name.to_string()
+    } else {
+        format!("{name:?}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_from_exports_and_imports() {
+        let module = wat::parse_str(
+            r#"(module
+                (import "env" "log" (func (param i32)))
+                (import "my-host" "now" (func (result f64)))
+                (memory (export "memory") 1)
+                (func (export "add") (param i32 i64) (result i64) local.get 1)
+                (func (export "pair") (result i32 f32) i32.const 0 f32.const 0)
+                (export "log-it" (func 0)))"#,
+        )
+        .unwrap();
+        let types = generate("app.wasm", &module).unwrap();
+        assert!(types.contains("  readonly memory: WebAssembly.Memory;\n"));
+        assert!(types.contains("  add(p0: number, p1: bigint): bigint;\n"));
+        assert!(types.contains("  pair(): [number, number];\n"));
+        assert!(types.contains("  \"log-it\"(p0: number): void;\n"));
+        assert!(types.contains("  env: {\n    log(p0: number): void;\n  };\n"));
+        assert!(types.contains("  \"my-host\": {\n    now(): number;\n"));
+    }
+
+    #[test]
+    fn test_bundle_merges_bindgen_definitions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+        fs::write(path("app_bg.wasm"), b"\0asm\x01\0\0\0").unwrap();
+        fs::write(path("app.js"), "").unwrap();
+        fs::write(
+            path("app.d.ts"),
+            "export function greet(name: string): void;\n",
+        )
+        .unwrap();
+        fs::write(
+            path("app_bg.wasm.d.ts"),
+            "/* tslint:disable */\nexport const memory: WebAssembly.Memory;\nexport function greet(a: number, b: number): void;\n",
+        )
+        .unwrap();
+
+        let mut result = BuildResult {
+            wasm_path: path("app_bg.wasm"),
+            js_path: Some(path("app.js")),
+            additional_files: Vec::new(),
+            is_wasm_bindgen: true,
+        };
+        let bundled = bundle(&mut result).unwrap();
+        assert_eq!(bundled, dir.path().join(BUNDLED_TYPES));
+        assert_eq!(result.additional_files, vec![path(BUNDLED_TYPES)]);
+
+        let types = for_module(&path("app_bg.wasm")).unwrap();
+        assert!(types.contains("export function greet(name: string): void;"));
+        assert!(types.contains("export declare namespace raw {\n  export const memory"));
+    }
+
+    #[test]
+    fn test_components_are_refused() {
+        let component = wat::parse_str("(component)").unwrap();
+        assert!(generate("c.wasm", &component).unwrap_err().contains("jco"));
+    }
+}
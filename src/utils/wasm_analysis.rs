@@ -1,6 +1,6 @@
 use crate::commands::{verify_wasm, VerificationResult};
 use crate::error::{Result, WasmrunError};
-use crate::utils::{CommandExecutor, PathResolver};
+use crate::utils::{ArtifactKind, CommandExecutor, ModuleInspection, PathResolver};
 use std::fs;
 use std::path::Path;
 
@@ -76,10 +76,14 @@ impl WasmAnalysis {
         };
 
         // Determine module characteristics
-        let is_wasm_bindgen = detect_wasm_bindgen(path_obj);
-        let is_wasi = verification.as_ref().is_some_and(|v| {
-            v.has_export_section && v.export_names.iter().any(|name| name == "_start")
-        });
+        let inspection = ModuleInspection::from_file(path).ok();
+        let artifact = inspection.as_ref().map(|inspection| inspection.artifact);
+        let is_wasm_bindgen =
+            detect_wasm_bindgen(path_obj) || artifact == Some(ArtifactKind::WasmBindgen);
+        let is_wasi = artifact == Some(ArtifactKind::Wasi)
+            || verification.as_ref().is_some_and(|v| {
+                v.has_export_section && v.export_names.iter().any(|name| name == "_start")
+            });
 
         // Determine module type
         let module_type = determine_module_type(&verification, is_wasm_bindgen, is_wasi);
@@ -87,7 +91,7 @@ impl WasmAnalysis {
         let (imports_count, exports_count, functions_count) =
             if let Some(ref verify_result) = verification {
                 (
-                    0,
+                    inspection.map_or(0, |inspection| inspection.imports.len()),
                     verify_result.export_names.len(),
                     verify_result.function_count,
                 )
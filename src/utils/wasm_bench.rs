@@ -0,0 +1,454 @@
+//! Per-function timings of a module, measured natively
+//!
+//! Every function of the module is wrapped with the hooks of
+//! [`wasm_instrument`](super::wasm_instrument), the module is instantiated
+//! with wasmtime and one export is called repeatedly. The hooks count calls
+//! and add up the self time of each function, the time spent in its own body
+//! rather than in its callees, so two builds benchmarked the same way can be
+//! compared function by function and a slowdown points at the function that
+//! got slower instead of at everything that calls it.
+//!
+//! A traced benchmark also records every timed call, start and duration, for
+//! [`chrome_trace`] to write in the Chrome `trace_event` format that
+//! chrome://tracing and Perfetto open.
+//!
+//! WASI preview 1 imports are provided with no arguments, environment or
+//! open files; any other import traps when called, so exports that never
+//! reach the host can be measured in modules built for the browser.
+
+use super::wasm_instrument::{self, PROFILE_IMPORT_MODULE};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use wasmtime::{Caller, Engine, Linker, Module, Store, Val, ValType};
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+use wasmtime_wasi::WasiCtxBuilder;
+
+/// Calls and self time of one function over a benchmark
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionTiming {
+    pub name: String,
+    pub calls: u64,
+    pub time: Duration,
+}
+
+/// Most calls a traced benchmark records; later ones are only counted
+pub const MAX_TRACED_CALLS: usize = 1_000_000;
+
+/// One finished call in the trace of a benchmark
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TracedCall {
+    /// Index into [`BenchProfile::names`]
+    pub function: usize,
+    /// Since the timed calls began
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+/// What benchmarking one build measured
+#[derive(Debug, Clone)]
+pub struct BenchProfile {
+    pub iterations: u32,
+    /// Wall time of all timed calls to the export
+    pub total: Duration,
+    /// Functions called at least once
+    pub functions: Vec<FunctionTiming>,
+    /// Every instrumented function, by hook id
+    pub names: Vec<String>,
+    /// Timed calls in the order they returned, when traced
+    pub trace: Vec<TracedCall>,
+    /// Calls past [`MAX_TRACED_CALLS`], left out of `trace`
+    pub untraced: u64,
+}
+
+impl BenchProfile {
+    pub fn per_iteration(&self, time: Duration) -> Duration {
+        time / self.iterations.max(1)
+    }
+}
+
+/// How one function changed between two builds, per iteration
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingDelta {
+    pub name: String,
+    pub old: Option<Duration>,
+    pub new: Option<Duration>,
+    pub old_calls: u64,
+    pub new_calls: u64,
+}
+
+impl TimingDelta {
+    /// New minus old time, in seconds; positive is slower
+    pub fn change(&self) -> f64 {
+        self.new.unwrap_or_default().as_secs_f64() - self.old.unwrap_or_default().as_secs_f64()
+    }
+
+    /// The change relative to the old time, when the function ran in both
+    pub fn percent(&self) -> Option<f64> {
+        let old = self.old?.as_secs_f64();
+        self.new?;
+        (old > 0.0).then(|| self.change() / old * 100.0)
+    }
+}
+
+/// A call in progress
+struct Frame {
+    id: usize,
+    start: Instant,
+    /// Time spent in the functions it called
+    callees: Duration,
+}
+
+/// Store state: the WASI context, the instrumented call stack, the hooks'
+/// counters, by hook id, and the calls traced since `origin`
+struct BenchState {
+    wasi: WasiP1Ctx,
+    stack: Vec<Frame>,
+    calls: Vec<u64>,
+    time: Vec<Duration>,
+    trace: Option<Vec<TracedCall>>,
+    untraced: u64,
+    origin: Instant,
+}
+
+impl BenchState {
+    fn enter(&mut self, id: usize) {
+        self.stack.push(Frame {
+            id,
+            start: Instant::now(),
+            callees: Duration::ZERO,
+        });
+    }
+
+    fn exit(&mut self, id: usize) {
+        // Only the frame on top can return; anything else is a stale hook
+        if self.stack.last().map(|frame| frame.id) != Some(id) {
+            return;
+        }
+        let Some(frame) = self.stack.pop() else {
+            return;
+        };
+        let elapsed = frame.start.elapsed();
+        self.calls[id] += 1;
+        self.time[id] += elapsed.saturating_sub(frame.callees);
+        if let Some(caller) = self.stack.last_mut() {
+            caller.callees += elapsed;
+        }
+        match &mut self.trace {
+            Some(trace) if trace.len() < MAX_TRACED_CALLS => trace.push(TracedCall {
+                function: id,
+                start: frame.start.saturating_duration_since(self.origin),
+                duration: elapsed,
+            }),
+            Some(_) => self.untraced += 1,
+            None => {}
+        }
+    }
+
+    fn reset(&mut self) {
+        self.stack.clear();
+        self.calls.iter_mut().for_each(|calls| *calls = 0);
+        self.time.iter_mut().for_each(|time| *time = Duration::ZERO);
+        if let Some(trace) = &mut self.trace {
+            trace.clear();
+        }
+        self.untraced = 0;
+        self.origin = Instant::now();
+    }
+}
+
+/// Parse `text` as a value of type `ty`
+fn parse_arg(text: &str, ty: &ValType) -> Result<Val, String> {
+    let invalid = || format!("'{text}' is not a valid {ty}");
+    Ok(match ty {
+        ValType::I32 => Val::I32(text.parse().map_err(|_| invalid())?),
+        ValType::I64 => Val::I64(text.parse().map_err(|_| invalid())?),
+        ValType::F32 => Val::F32(text.parse::<f32>().map_err(|_| invalid())?.to_bits()),
+        ValType::F64 => Val::F64(text.parse::<f64>().map_err(|_| invalid())?.to_bits()),
+        _ => {
+            return Err(format!(
+                "parameters of type {ty} can't be given on the command line"
+            ))
+        }
+    })
+}
+
+/// Call `export` of the module `wasm` `iterations` times
+/// with `args`, after one untimed warm-up call; `trace` records each call
+pub fn bench(
+    wasm: &[u8],
+    export: &str,
+    args: &[String],
+    iterations: u32,
+    trace: bool,
+) -> Result<BenchProfile, String> {
+    let instrumented = wasm_instrument::instrument(wasm, &["*".to_string()])?;
+    let count = instrumented.functions.len();
+
+    let engine = Engine::default();
+    let module = Module::new(&engine, &instrumented.bytes).map_err(|e| format!("{e:#}"))?;
+    let mut linker: Linker<BenchState> = Linker::new(&engine);
+    preview1::add_to_linker_sync(&mut linker, |state| &mut state.wasi)
+        .map_err(|e| format!("Failed to set up the WASI runtime: {e:#}"))?;
+    linker
+        .func_wrap(
+            PROFILE_IMPORT_MODULE,
+            "enter",
+            |mut caller: Caller<'_, BenchState>, id: u32| caller.data_mut().enter(id as usize),
+        )
+        .and_then(|linker| {
+            linker.func_wrap(
+                PROFILE_IMPORT_MODULE,
+                "exit",
+                |mut caller: Caller<'_, BenchState>, id: u32| caller.data_mut().exit(id as usize),
+            )
+        })
+        .map_err(|e| format!("{e:#}"))?;
+    linker
+        .define_unknown_imports_as_traps(&module)
+        .map_err(|e| format!("{e:#}"))?;
+
+    let state = BenchState {
+        wasi: WasiCtxBuilder::new().build_p1(),
+        stack: Vec::new(),
+        calls: vec![0; count],
+        time: vec![Duration::ZERO; count],
+        trace: trace.then(Vec::new),
+        untraced: 0,
+        origin: Instant::now(),
+    };
+    let mut store = Store::new(&engine, state);
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .map_err(|e| format!("{e:#}"))?;
+    let func = instance
+        .get_func(&mut store, export)
+        .ok_or_else(|| format!("module has no exported function `{export}`"))?;
+
+    let ty = func.ty(&store);
+    if ty.params().len() != args.len() {
+        return Err(format!(
+            "`{export}` takes {} arguments, {} given",
+            ty.params().len(),
+            args.len()
+        ));
+    }
+    let params = args
+        .iter()
+        .zip(ty.params())
+        .map(|(arg, ty)| parse_arg(arg, &ty))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut results: Vec<Val> = ty.results().map(|_| Val::I32(0)).collect();
+
+    let mut call = |store: &mut Store<BenchState>| {
+        func.call(&mut *store, &params, &mut results)
+            .map_err(|e| format!("`{export}` trapped: {e:#}"))
+    };
+    call(&mut store)?;
+    store.data_mut().reset();
+    let started = Instant::now();
+    for _ in 0..iterations {
+        call(&mut store)?;
+    }
+    let total = started.elapsed();
+
+    let state = store.into_data();
+    let functions = instrumented
+        .functions
+        .iter()
+        .enumerate()
+        .filter(|(id, _)| state.calls[*id] > 0)
+        .map(|(id, name)| FunctionTiming {
+            name: name.clone(),
+            calls: state.calls[id],
+            time: state.time[id],
+        })
+        .collect();
+    Ok(BenchProfile {
+        iterations,
+        total,
+        functions,
+        names: instrumented.functions,
+        trace: state.trace.unwrap_or_default(),
+        untraced: state.untraced,
+    })
+}
+
+/// The traced calls of `profiles` in the Chrome `trace_event` JSON format,
+/// each profile as a process named by its label
+pub fn chrome_trace(profiles: &[(&str, &BenchProfile)]) -> Value {
+    let micros = |time: Duration| time.as_secs_f64() * 1e6;
+    let mut events = Vec::new();
+    for (pid, (label, profile)) in (1..).zip(profiles) {
+        events.push(json!({
+            "name": "process_name",
+            "ph": "M",
+            "pid": pid,
+            "tid": 1,
+            "args": { "name": label },
+        }));
+        events.extend(profile.trace.iter().map(|call| {
+            json!({
+                "name": profile.names[call.function],
+                "cat": "wasm",
+                "ph": "X",
+                "ts": micros(call.start),
+                "dur": micros(call.duration),
+                "pid": pid,
+                "tid": 1,
+            })
+        }));
+    }
+    json!({ "traceEvents": events, "displayTimeUnit": "ms" })
+}
+
+/// Pair up the functions of two profiles by name, biggest slowdown first
+pub fn compare(old: &BenchProfile, new: &BenchProfile) -> Vec<TimingDelta> {
+    let mut deltas: Vec<TimingDelta> = Vec::new();
+    let mut by_name: HashMap<&str, usize> = HashMap::new();
+    for timing in &old.functions {
+        by_name.insert(&timing.name, deltas.len());
+        deltas.push(TimingDelta {
+            name: timing.name.clone(),
+            old: Some(old.per_iteration(timing.time)),
+            new: None,
+            old_calls: timing.calls,
+            new_calls: 0,
+        });
+    }
+    for timing in &new.functions {
+        let index = match by_name.get(timing.name.as_str()) {
+            Some(index) => *index,
+            None => {
+                deltas.push(TimingDelta {
+                    name: timing.name.clone(),
+                    old: None,
+                    new: None,
+                    old_calls: 0,
+                    new_calls: 0,
+                });
+                deltas.len() - 1
+            }
+        };
+        deltas[index].new = Some(new.per_iteration(timing.time));
+        deltas[index].new_calls = timing.calls;
+    }
+    deltas.sort_by(|a, b| b.change().total_cmp(&a.change()));
+    deltas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOOP_MODULE: &str = r#"(module
+        (func $step (param i32) (result i32) (i32.add (local.get 0) (i32.const 1)))
+        (func $fact (param i32) (result i32)
+            (if (result i32) (i32.le_s (local.get 0) (i32.const 1))
+                (then (i32.const 1))
+                (else (i32.mul (local.get 0) (call $fact (i32.sub (local.get 0) (i32.const 1)))))))
+        (func (export "run") (param $n i32) (result i32)
+            (local $i i32)
+            (loop $again
+                (local.set $i (call $step (local.get $i)))
+                (br_if $again (i32.lt_s (local.get $i) (local.get $n))))
+            (call $fact (i32.const 5))))"#;
+
+    #[test]
+    fn test_calls_are_counted_per_iteration() {
+        let profile = bench(
+            &wat::parse_str(LOOP_MODULE).unwrap(),
+            "run",
+            &["3".to_string()],
+            4,
+            false,
+        )
+        .unwrap();
+        let calls: HashMap<&str, u64> = profile
+            .functions
+            .iter()
+            .map(|timing| (timing.name.as_str(), timing.calls))
+            .collect();
+        assert_eq!(calls["run"], 4);
+        assert_eq!(calls["step"], 12);
+        assert_eq!(calls["fact"], 20);
+        let time: Duration = profile.functions.iter().map(|timing| timing.time).sum();
+        assert!(time <= profile.total);
+        assert!(profile.trace.is_empty());
+    }
+
+    #[test]
+    fn test_traced_calls_as_chrome_trace() {
+        let profile = bench(
+            &wat::parse_str(LOOP_MODULE).unwrap(),
+            "run",
+            &["3".to_string()],
+            2,
+            true,
+        )
+        .unwrap();
+        // 1 + 3 + 5 calls per iteration, callees returning before their callers
+        assert_eq!(profile.trace.len(), 18);
+        let last = profile.trace[8];
+        assert_eq!(profile.names[last.function], "run");
+        assert!(profile.trace[..8]
+            .iter()
+            .all(|call| call.start >= last.start && call.duration <= last.duration));
+
+        let trace = chrome_trace(&[("Old", &profile)]);
+        let events = trace["traceEvents"].as_array().unwrap();
+        assert_eq!(events.len(), 19);
+        assert_eq!(events[0]["args"]["name"], "Old");
+        assert_eq!(events[9]["name"], "run");
+        assert_eq!(events[9]["ph"], "X");
+        assert_eq!(events[9]["pid"], 1);
+    }
+
+    #[test]
+    fn test_arguments_are_checked_against_the_export() {
+        let bench = |export: &str, args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            bench(
+                &wat::parse_str(LOOP_MODULE).unwrap(),
+                export,
+                &args,
+                1,
+                false,
+            )
+            .unwrap_err()
+        };
+        assert_eq!(bench("run", &[]), "`run` takes 1 arguments, 0 given");
+        assert_eq!(bench("run", &["x"]), "'x' is not a valid i32");
+        assert_eq!(bench("main", &[]), "module has no exported function `main`");
+    }
+
+    #[test]
+    fn test_deltas_pair_functions_by_name() {
+        let profile = |functions: &[(&str, u64)]| BenchProfile {
+            names: Vec::new(),
+            trace: Vec::new(),
+            untraced: 0,
+            iterations: 2,
+            total: Duration::from_millis(10),
+            functions: functions
+                .iter()
+                .map(|(name, millis)| FunctionTiming {
+                    name: name.to_string(),
+                    calls: 2,
+                    time: Duration::from_millis(*millis),
+                })
+                .collect(),
+        };
+        let old = profile(&[("parse", 4), ("render", 8), ("gone", 2)]);
+        let new = profile(&[("render", 4), ("parse", 10), ("added", 2)]);
+
+        let deltas = compare(&old, &new);
+        let names: Vec<&str> = deltas.iter().map(|delta| delta.name.as_str()).collect();
+        assert_eq!(names, ["parse", "added", "gone", "render"]);
+        assert_eq!(deltas[0].old, Some(Duration::from_millis(2)));
+        assert_eq!(deltas[0].new, Some(Duration::from_millis(5)));
+        assert_eq!(deltas[0].percent(), Some(150.0));
+        assert_eq!(deltas[1].percent(), None);
+        assert_eq!(deltas[3].percent(), Some(-50.0));
+    }
+}
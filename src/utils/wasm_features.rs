@@ -0,0 +1,209 @@
+//! WebAssembly proposals a module depends on
+//!
+//! Browsers shipped post-MVP features at different times, so a module that
+//! uses one fails to compile in a browser without it. A module uses a proposal
+//! when it stops validating once that proposal is switched off. Each proposal
+//! also has a probe, a minimal module that only validates where the proposal
+//! is supported, for `WebAssembly.validate` to test a browser with.
+
+use std::fmt;
+use wasm_encoder::{
+    CodeSection, Function, FunctionSection, Instruction, MemorySection, MemoryType, Module,
+    TagKind, TagSection, TagType, TypeSection, ValType,
+};
+use wasmparser::{Validator, WasmFeatures};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Proposal {
+    Simd,
+    RelaxedSimd,
+    Threads,
+    Exceptions,
+    TailCall,
+    Gc,
+    Memory64,
+    MultiMemory,
+}
+
+impl Proposal {
+    pub const ALL: [Proposal; 8] = [
+        Proposal::Simd,
+        Proposal::RelaxedSimd,
+        Proposal::Threads,
+        Proposal::Exceptions,
+        Proposal::TailCall,
+        Proposal::Gc,
+        Proposal::Memory64,
+        Proposal::MultiMemory,
+    ];
+
+    /// Identifier used by the browser probe and in failure reports
+    pub fn key(self) -> &'static str {
+        match self {
+            Proposal::Simd => "simd",
+            Proposal::RelaxedSimd => "relaxed-simd",
+            Proposal::Threads => "threads",
+            Proposal::Exceptions => "exceptions",
+            Proposal::TailCall => "tail-call",
+            Proposal::Gc => "gc",
+            Proposal::Memory64 => "memory64",
+            Proposal::MultiMemory => "multi-memory",
+        }
+    }
+
+    fn features(self) -> WasmFeatures {
+        match self {
+            Proposal::Simd => WasmFeatures::SIMD | WasmFeatures::RELAXED_SIMD,
+            Proposal::RelaxedSimd => WasmFeatures::RELAXED_SIMD,
+            Proposal::Threads => WasmFeatures::THREADS | WasmFeatures::SHARED_EVERYTHING_THREADS,
+            Proposal::Exceptions => WasmFeatures::EXCEPTIONS | WasmFeatures::LEGACY_EXCEPTIONS,
+            Proposal::TailCall => WasmFeatures::TAIL_CALL,
+            Proposal::Gc => WasmFeatures::GC,
+            Proposal::Memory64 => WasmFeatures::MEMORY64,
+            Proposal::MultiMemory => WasmFeatures::MULTI_MEMORY,
+        }
+    }
+
+    /// A module that validates only where this proposal is supported
+    pub fn probe(self) -> Vec<u8> {
+        let mut module = Module::new();
+        let mut types = TypeSection::new();
+        match self {
+            Proposal::Simd => types.ty().function([], [ValType::V128]),
+            Proposal::RelaxedSimd => types.ty().function([ValType::V128], [ValType::V128]),
+            Proposal::Gc => types.ty().struct_([]),
+            _ => types.ty().function([], []),
+        }
+        module.section(&types);
+
+        let body = |instructions: &[Instruction]| {
+            let mut functions = FunctionSection::new();
+            functions.function(0);
+            let mut code = CodeSection::new();
+            let mut function = Function::new([]);
+            for instruction in instructions {
+                function.instruction(instruction);
+            }
+            function.instruction(&Instruction::End);
+            code.function(&function);
+            (functions, code)
+        };
+        let memories = |memories: &[MemoryType]| {
+            let mut section = MemorySection::new();
+            for memory in memories {
+                section.memory(*memory);
+            }
+            section
+        };
+        let memory = |memory64, shared| MemoryType {
+            minimum: 1,
+            maximum: Some(1),
+            memory64,
+            shared,
+            page_size_log2: None,
+        };
+
+        match self {
+            Proposal::Simd => {
+                let (functions, code) = body(&[Instruction::V128Const(0)]);
+                module.section(&functions).section(&code);
+            }
+            Proposal::RelaxedSimd => {
+                let (functions, code) = body(&[
+                    Instruction::LocalGet(0),
+                    Instruction::LocalGet(0),
+                    Instruction::I8x16RelaxedSwizzle,
+                ]);
+                module.section(&functions).section(&code);
+            }
+            Proposal::TailCall => {
+                let (functions, code) = body(&[Instruction::ReturnCall(0)]);
+                module.section(&functions).section(&code);
+            }
+            Proposal::Threads => {
+                module.section(&memories(&[memory(false, true)]));
+            }
+            Proposal::Memory64 => {
+                module.section(&memories(&[memory(true, false)]));
+            }
+            Proposal::MultiMemory => {
+                module.section(&memories(&[memory(false, false), memory(false, false)]));
+            }
+            Proposal::Exceptions => {
+                let mut tags = TagSection::new();
+                tags.tag(TagType {
+                    kind: TagKind::Exception,
+                    func_type_idx: 0,
+                });
+                module.section(&tags);
+            }
+            Proposal::Gc => {}
+        }
+        module.finish()
+    }
+}
+
+impl fmt::Display for Proposal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Proposal::Simd => "SIMD",
+            Proposal::RelaxedSimd => "relaxed SIMD",
+            Proposal::Threads => "threads (shared memory and atomics)",
+            Proposal::Exceptions => "exception handling",
+            Proposal::TailCall => "tail calls",
+            Proposal::Gc => "garbage collection",
+            Proposal::Memory64 => "64-bit memory",
+            Proposal::MultiMemory => "multiple memories",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The proposals a core module needs; empty when it does not validate at all
+pub fn used_proposals(bytes: &[u8]) -> Vec<Proposal> {
+    if !validates(bytes, WasmFeatures::all()) {
+        return Vec::new();
+    }
+    Proposal::ALL
+        .into_iter()
+        .filter(|proposal| !validates(bytes, WasmFeatures::all() - proposal.features()))
+        .collect()
+}
+
+fn validates(bytes: &[u8], features: WasmFeatures) -> bool {
+    Validator::new_with_features(features)
+        .validate_all(bytes)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probes_need_their_proposal() {
+        for proposal in Proposal::ALL {
+            let probe = proposal.probe();
+            assert!(
+                validates(&probe, WasmFeatures::all()),
+                "{proposal} probe is invalid"
+            );
+            assert!(
+                !validates(&probe, WasmFeatures::all() - proposal.features()),
+                "{proposal} probe validates without it"
+            );
+            assert_eq!(used_proposals(&probe).last(), Some(&proposal));
+        }
+    }
+
+    #[test]
+    fn test_mvp_module_uses_nothing() {
+        let module = wat::parse_str(
+            r#"(module (memory 1) (func (export "add") (param i32 i32) (result i32)
+                local.get 0 local.get 1 i32.add))"#,
+        )
+        .unwrap();
+        assert!(used_proposals(&module).is_empty());
+        assert!(used_proposals(b"\0asm\x01\0\0\0\x01").is_empty());
+    }
+}
@@ -0,0 +1,574 @@
+//! Structural inspection of WebAssembly binaries
+//!
+//! Parses a module or component with `wasmparser` and records what it imports
+//! and exports, its memories and tables, custom sections and start function,
+//! and which toolchain convention it follows. The classification is what
+//! `wasmrun inspect` reports and what the server uses to choose between the
+//! plain console page and the wasm-bindgen page. For components, the WIT world
+//! is decoded from the component's own type information.
+
+use std::fmt;
+use std::path::Path;
+use wasmparser::{
+    ComponentExternalKind, ComponentTypeRef, Encoding, ExternalKind, Parser, Payload, TypeRef,
+};
+use wit_parser::decoding::{decode, DecodedWasm};
+use wit_parser::{Handle, Resolve, Results, Type, TypeDefKind, WorldItem, WorldKey};
+
+/// Import modules that wasm-bindgen generated glue provides
+const WASM_BINDGEN_IMPORT_MODULES: [&str; 2] = ["__wbindgen_placeholder__", "wbg"];
+
+/// Import modules of the WASI preview 1 ABI
+const WASI_IMPORT_MODULES: [&str; 2] = ["wasi_snapshot_preview1", "wasi_unstable"];
+
+/// What produced a binary, as far as its imports, exports and encoding tell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    /// A component-model component, which browsers can't instantiate directly
+    Component,
+    /// A core module expecting JS glue generated by wasm-bindgen
+    WasmBindgen,
+    /// A core module importing WASI preview 1
+    Wasi,
+    /// Any other core module
+    Core,
+}
+
+impl fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArtifactKind::Component => write!(f, "Component (component model)"),
+            ArtifactKind::WasmBindgen => write!(f, "wasm-bindgen module"),
+            ArtifactKind::Wasi => write!(f, "WASI module"),
+            ArtifactKind::Core => write!(f, "Core module"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportEntry {
+    /// Empty for component imports, which only have a name
+    pub module: String,
+    pub name: String,
+    pub kind: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportEntry {
+    pub name: String,
+    pub kind: &'static str,
+    pub index: u32,
+}
+
+/// Size limits of a memory (in 64 KiB pages) or a table (in elements)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Limits {
+    pub initial: u64,
+    pub maximum: Option<u64>,
+    /// 64-bit indices (memory64 or table64)
+    pub is_64: bool,
+    pub shared: bool,
+    /// Provided by the host rather than defined by the module
+    pub imported: bool,
+    /// Element type of a table, e.g. `funcref`; `None` for memories
+    pub element_type: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomSectionEntry {
+    pub name: String,
+    pub size: usize,
+}
+
+/// The WIT world a component targets, with each item in WIT syntax,
+/// e.g. `wasi:cli/environment@0.2.0` or `greet: func(name: string) -> string`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WitWorld {
+    /// Qualified world name, e.g. `root:component/root`
+    pub name: String,
+    pub imports: Vec<String>,
+    pub exports: Vec<String>,
+}
+
+/// Everything `wasmrun inspect` reports about a binary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleInspection {
+    pub artifact: ArtifactKind,
+    /// Binary format version: 1 for core modules, 0xd for components
+    pub version: u16,
+    pub imports: Vec<ImportEntry>,
+    pub exports: Vec<ExportEntry>,
+    pub memories: Vec<Limits>,
+    pub tables: Vec<Limits>,
+    pub custom_sections: Vec<CustomSectionEntry>,
+    pub start_function: Option<u32>,
+    /// Functions defined by the module, not counting imported ones
+    pub function_count: u32,
+    /// Core modules nested inside a component
+    pub nested_modules: usize,
+    /// The world of a component, when its types could be decoded
+    pub world: Option<WitWorld>,
+}
+
+impl ModuleInspection {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("Error reading {}: {e}", path.display()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Parse `bytes`; only the outermost module or component is described
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.starts_with(b"\0asm") {
+            return Err("Missing magic bytes '\\0asm', not a WebAssembly binary".to_string());
+        }
+        let mut inspection = ModuleInspection {
+            artifact: ArtifactKind::Core,
+            version: 0,
+            imports: Vec::new(),
+            exports: Vec::new(),
+            memories: Vec::new(),
+            tables: Vec::new(),
+            custom_sections: Vec::new(),
+            start_function: None,
+            function_count: 0,
+            nested_modules: 0,
+            world: None,
+        };
+        let mut component = false;
+        // Modules and components nested in a component are parsed inline; depth
+        // 1 is the outermost binary
+        let mut depth = 0usize;
+
+        for payload in Parser::new(0).parse_all(bytes) {
+            let payload = payload.map_err(|e| {
+                format!(
+                    "Invalid WebAssembly at offset {:#x}: {}",
+                    e.offset(),
+                    e.message()
+                )
+            })?;
+            match payload {
+                Payload::Version { num, encoding, .. } => {
+                    depth += 1;
+                    if depth == 1 {
+                        inspection.version = num;
+                        component = encoding == Encoding::Component;
+                    } else if encoding == Encoding::Module {
+                        inspection.nested_modules += 1;
+                    }
+                }
+                Payload::End(_) => depth = depth.saturating_sub(1),
+                _ if depth != 1 => {}
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        let import = import.map_err(|e| format!("Invalid import: {e}"))?;
+                        inspection.imports.push(ImportEntry {
+                            module: import.module.to_string(),
+                            name: import.name.to_string(),
+                            kind: type_ref_kind(&import.ty),
+                        });
+                        match import.ty {
+                            TypeRef::Memory(memory) => {
+                                inspection.memories.push(Limits {
+                                    initial: memory.initial,
+                                    maximum: memory.maximum,
+                                    is_64: memory.memory64,
+                                    shared: memory.shared,
+                                    imported: true,
+                                    element_type: None,
+                                });
+                            }
+                            TypeRef::Table(table) => {
+                                inspection.tables.push(table_limits(&table, true));
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::ComponentImportSection(reader) => {
+                    for import in reader {
+                        let import = import.map_err(|e| format!("Invalid import: {e}"))?;
+                        inspection.imports.push(ImportEntry {
+                            module: String::new(),
+                            name: import.name.0.to_string(),
+                            kind: component_type_ref_kind(&import.ty),
+                        });
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export.map_err(|e| format!("Invalid export: {e}"))?;
+                        inspection.exports.push(ExportEntry {
+                            name: export.name.to_string(),
+                            kind: external_kind(export.kind),
+                            index: export.index,
+                        });
+                    }
+                }
+                Payload::ComponentExportSection(reader) => {
+                    for export in reader {
+                        let export = export.map_err(|e| format!("Invalid export: {e}"))?;
+                        inspection.exports.push(ExportEntry {
+                            name: export.name.0.to_string(),
+                            kind: component_external_kind(export.kind),
+                            index: export.index,
+                        });
+                    }
+                }
+                Payload::MemorySection(reader) => {
+                    for memory in reader {
+                        let memory = memory.map_err(|e| format!("Invalid memory: {e}"))?;
+                        inspection.memories.push(Limits {
+                            initial: memory.initial,
+                            maximum: memory.maximum,
+                            is_64: memory.memory64,
+                            shared: memory.shared,
+                            imported: false,
+                            element_type: None,
+                        });
+                    }
+                }
+                Payload::TableSection(reader) => {
+                    for table in reader {
+                        let table = table.map_err(|e| format!("Invalid table: {e}"))?;
+                        inspection.tables.push(table_limits(&table.ty, false));
+                    }
+                }
+                Payload::FunctionSection(reader) => inspection.function_count = reader.count(),
+                Payload::StartSection { func, .. } => inspection.start_function = Some(func),
+                Payload::CustomSection(reader) => {
+                    inspection.custom_sections.push(CustomSectionEntry {
+                        name: reader.name().to_string(),
+                        size: reader.data().len(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        inspection.artifact = if component {
+            inspection.world = wit_world(bytes);
+            ArtifactKind::Component
+        } else if inspection.is_wasm_bindgen() {
+            ArtifactKind::WasmBindgen
+        } else if inspection
+            .imports
+            .iter()
+            .any(|import| WASI_IMPORT_MODULES.contains(&import.module.as_str()))
+        {
+            ArtifactKind::Wasi
+        } else {
+            ArtifactKind::Core
+        };
+        Ok(inspection)
+    }
+
+    /// Imports from the generated glue, or the exports wasm-bindgen adds for it
+    fn is_wasm_bindgen(&self) -> bool {
+        self.imports.iter().any(|import| {
+            WASM_BINDGEN_IMPORT_MODULES.contains(&import.module.as_str())
+                || import.module.ends_with("_bg.js")
+        }) || self.exports.iter().any(|export| {
+            export.name.starts_with("__wbindgen_") || export.name.starts_with("__wbg_")
+        }) || self
+            .custom_sections
+            .iter()
+            .any(|section| section.name == "__wasm_bindgen_unstable")
+    }
+
+    /// Imported functions, which share the function index space with defined ones
+    pub fn imported_function_count(&self) -> usize {
+        self.imports
+            .iter()
+            .filter(|import| import.kind == "func")
+            .count()
+    }
+}
+
+/// Decode the world a component implements; `None` for WIT packages and undecodable types
+fn wit_world(bytes: &[u8]) -> Option<WitWorld> {
+    let DecodedWasm::Component(resolve, world_id) = decode(bytes).ok()? else {
+        return None;
+    };
+    let world = &resolve.worlds[world_id];
+    let items = |items: Vec<(&WorldKey, &WorldItem)>| {
+        items
+            .into_iter()
+            .map(|(key, item)| wit_item(&resolve, key, item))
+            .collect()
+    };
+    Some(WitWorld {
+        name: match world.package {
+            Some(package) => resolve.id_of_name(package, &world.name),
+            None => world.name.clone(),
+        },
+        imports: items(world.imports.iter().collect()),
+        exports: items(world.exports.iter().collect()),
+    })
+}
+
+fn wit_item(resolve: &Resolve, key: &WorldKey, item: &WorldItem) -> String {
+    match item {
+        WorldItem::Interface { id, .. } => resolve
+            .id_of(*id)
+            .unwrap_or_else(|| format!("{}: interface", resolve.name_world_key(key))),
+        WorldItem::Function(function) => {
+            let params: Vec<String> = function
+                .params
+                .iter()
+                .map(|(name, ty)| format!("{name}: {}", wit_type(resolve, ty)))
+                .collect();
+            let results = match &function.results {
+                Results::Anon(ty) => format!(" -> {}", wit_type(resolve, ty)),
+                Results::Named(named) if named.is_empty() => String::new(),
+                Results::Named(named) => format!(
+                    " -> ({})",
+                    named
+                        .iter()
+                        .map(|(name, ty)| format!("{name}: {}", wit_type(resolve, ty)))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            };
+            format!("{}: func({}){results}", function.name, params.join(", "))
+        }
+        WorldItem::Type(id) => format!(
+            "type {}",
+            resolve.types[*id]
+                .name
+                .clone()
+                .unwrap_or_else(|| resolve.name_world_key(key))
+        ),
+    }
+}
+
+fn wit_type(resolve: &Resolve, ty: &Type) -> String {
+    let id = match ty {
+        Type::Bool => return "bool".to_string(),
+        Type::U8 => return "u8".to_string(),
+        Type::U16 => return "u16".to_string(),
+        Type::U32 => return "u32".to_string(),
+        Type::U64 => return "u64".to_string(),
+        Type::S8 => return "s8".to_string(),
+        Type::S16 => return "s16".to_string(),
+        Type::S32 => return "s32".to_string(),
+        Type::S64 => return "s64".to_string(),
+        Type::F32 => return "f32".to_string(),
+        Type::F64 => return "f64".to_string(),
+        Type::Char => return "char".to_string(),
+        Type::String => return "string".to_string(),
+        Type::Id(id) => *id,
+    };
+    let definition = &resolve.types[id];
+    if let Some(name) = &definition.name {
+        return name.clone();
+    }
+    let generic = |name: &str, types: &[Option<&Type>]| {
+        let arguments: Vec<String> = types
+            .iter()
+            .map(|ty| ty.map_or("_".to_string(), |ty| wit_type(resolve, ty)))
+            .collect();
+        format!("{name}<{}>", arguments.join(", "))
+    };
+    match &definition.kind {
+        TypeDefKind::List(ty) => generic("list", &[Some(ty)]),
+        TypeDefKind::Option(ty) => generic("option", &[Some(ty)]),
+        TypeDefKind::Result(result) => match (&result.ok, &result.err) {
+            (None, None) => "result".to_string(),
+            (ok, None) => generic("result", &[ok.as_ref()]),
+            (ok, err) => generic("result", &[ok.as_ref(), err.as_ref()]),
+        },
+        TypeDefKind::Tuple(tuple) => {
+            generic("tuple", &tuple.types.iter().map(Some).collect::<Vec<_>>())
+        }
+        TypeDefKind::Future(Some(ty)) => generic("future", &[Some(ty)]),
+        TypeDefKind::Stream(Some(ty)) => generic("stream", &[Some(ty)]),
+        TypeDefKind::Handle(Handle::Own(id)) => wit_type(resolve, &Type::Id(*id)),
+        TypeDefKind::Handle(Handle::Borrow(id)) => generic("borrow", &[Some(&Type::Id(*id))]),
+        TypeDefKind::Type(ty) => wit_type(resolve, ty),
+        kind => kind.as_str().to_string(),
+    }
+}
+
+fn table_limits(table: &wasmparser::TableType, imported: bool) -> Limits {
+    Limits {
+        initial: table.initial,
+        maximum: table.maximum,
+        is_64: table.table64,
+        shared: table.shared,
+        imported,
+        element_type: Some(table.element_type.to_string()),
+    }
+}
+
+fn type_ref_kind(ty: &TypeRef) -> &'static str {
+    match ty {
+        TypeRef::Func(_) => "func",
+        TypeRef::Table(_) => "table",
+        TypeRef::Memory(_) => "memory",
+        TypeRef::Global(_) => "global",
+        TypeRef::Tag(_) => "tag",
+    }
+}
+
+fn external_kind(kind: ExternalKind) -> &'static str {
+    match kind {
+        ExternalKind::Func => "func",
+        ExternalKind::Table => "table",
+        ExternalKind::Memory => "memory",
+        ExternalKind::Global => "global",
+        ExternalKind::Tag => "tag",
+    }
+}
+
+fn component_type_ref_kind(ty: &ComponentTypeRef) -> &'static str {
+    match ty {
+        ComponentTypeRef::Module(_) => "module",
+        ComponentTypeRef::Func(_) => "func",
+        ComponentTypeRef::Value(_) => "value",
+        ComponentTypeRef::Type(_) => "type",
+        ComponentTypeRef::Instance(_) => "instance",
+        ComponentTypeRef::Component(_) => "component",
+    }
+}
+
+fn component_external_kind(kind: ComponentExternalKind) -> &'static str {
+    match kind {
+        ComponentExternalKind::Module => "module",
+        ComponentExternalKind::Func => "func",
+        ComponentExternalKind::Value => "value",
+        ComponentExternalKind::Type => "type",
+        ComponentExternalKind::Instance => "instance",
+        ComponentExternalKind::Component => "component",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00];
+
+    fn name(value: &str) -> Vec<u8> {
+        let mut bytes = vec![value.len() as u8];
+        bytes.extend_from_slice(value.as_bytes());
+        bytes
+    }
+
+    fn section(id: u8, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![id, payload.len() as u8];
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    /// A module importing one function from `import_module`, exporting
+    /// `export` and its memory, with a start function and a custom section
+    fn module(import_module: &str, export: &str) -> Vec<u8> {
+        let mut bytes = HEADER.to_vec();
+        // type 0: [] -> []
+        bytes.extend(section(1, &[0x01, 0x60, 0x00, 0x00]));
+        let mut imports = vec![0x01];
+        imports.extend(name(import_module));
+        imports.extend(name("host"));
+        imports.extend([0x00, 0x00]);
+        bytes.extend(section(2, &imports));
+        bytes.extend(section(3, &[0x01, 0x00]));
+        // memory 0: 1 page, at most 2
+        bytes.extend(section(5, &[0x01, 0x01, 0x01, 0x02]));
+        let mut exports = vec![0x02];
+        exports.extend(name(export));
+        exports.extend([0x00, 0x01]);
+        exports.extend(name("memory"));
+        exports.extend([0x02, 0x00]);
+        bytes.extend(section(7, &exports));
+        bytes.extend(section(8, &[0x01]));
+        bytes.extend(section(10, &[0x01, 0x02, 0x00, 0x0B]));
+        let mut custom = name("producers");
+        custom.extend(b"abc");
+        bytes.extend(section(0, &custom));
+        bytes
+    }
+
+    #[test]
+    fn test_inspect_module() {
+        let inspection = ModuleInspection::from_bytes(&module("env", "run")).unwrap();
+
+        assert_eq!(inspection.artifact, ArtifactKind::Core);
+        assert_eq!(inspection.version, 1);
+        assert_eq!(
+            inspection.imports,
+            [ImportEntry {
+                module: "env".to_string(),
+                name: "host".to_string(),
+                kind: "func",
+            }]
+        );
+        assert_eq!(inspection.imported_function_count(), 1);
+        assert_eq!(inspection.function_count, 1);
+        let exports: Vec<(&str, &str)> = inspection
+            .exports
+            .iter()
+            .map(|export| (export.name.as_str(), export.kind))
+            .collect();
+        assert_eq!(exports, [("run", "func"), ("memory", "memory")]);
+        assert_eq!(inspection.memories.len(), 1);
+        assert_eq!(inspection.memories[0].initial, 1);
+        assert_eq!(inspection.memories[0].maximum, Some(2));
+        assert!(!inspection.memories[0].imported);
+        assert!(inspection.tables.is_empty());
+        assert_eq!(inspection.start_function, Some(1));
+        assert_eq!(
+            inspection.custom_sections,
+            [CustomSectionEntry {
+                name: "producers".to_string(),
+                size: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_artifact_kind() {
+        let kind = |import_module: &str, export: &str| {
+            ModuleInspection::from_bytes(&module(import_module, export))
+                .unwrap()
+                .artifact
+        };
+        assert_eq!(kind("wasi_snapshot_preview1", "_start"), ArtifactKind::Wasi);
+        assert_eq!(kind("wbg", "greet"), ArtifactKind::WasmBindgen);
+        assert_eq!(kind("./app_bg.js", "greet"), ArtifactKind::WasmBindgen);
+        assert_eq!(kind("env", "__wbindgen_malloc"), ArtifactKind::WasmBindgen);
+
+        let component = [0x00, 0x61, 0x73, 0x6D, 0x0D, 0x00, 0x01, 0x00];
+        let inspection = ModuleInspection::from_bytes(&component).unwrap();
+        assert_eq!(inspection.artifact, ArtifactKind::Component);
+        assert_eq!(inspection.version, 0x0D);
+    }
+
+    #[test]
+    fn test_inspect_invalid() {
+        assert!(ModuleInspection::from_bytes(b"not wasm").is_err());
+        let mut truncated = module("env", "run");
+        truncated.truncate(truncated.len() - 2);
+        assert!(ModuleInspection::from_bytes(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_component_world() {
+        let component = wat::parse_str(
+            r#"(component
+                (import "wasi:cli/environment@0.2.0" (instance))
+                (core module $m (func (export "greet") (param i32) (result i32) local.get 0))
+                (core instance $i (instantiate $m))
+                (func (export "double") (param "n" u32) (result u32)
+                    (canon lift (core func $i "greet"))))"#,
+        )
+        .unwrap();
+        let inspection = ModuleInspection::from_bytes(&component).unwrap();
+        assert_eq!(inspection.artifact, ArtifactKind::Component);
+        let world = inspection.world.unwrap();
+        assert_eq!(world.imports, ["wasi:cli/environment@0.2.0"]);
+        assert_eq!(world.exports, ["double: func(n: u32) -> u32"]);
+    }
+}
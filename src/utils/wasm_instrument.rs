@@ -0,0 +1,523 @@
+//! Function-level instrumentation of WebAssembly modules
+//!
+//! Each selected function is wrapped: its body moves to a new function at the
+//! end of the module, and its original slot gets a small body that calls an
+//! `enter` hook, forwards its parameters to the moved body, then calls an
+//! `exit` hook. Every reference to the function (calls, exports, tables, the
+//! start function) keeps its index and so goes through the wrapper, and the
+//! bodies themselves are left untouched. The hooks are imported from
+//! [`PROFILE_IMPORT_MODULE`], which the page served with `--instrument`
+//! provides.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use wasm_encoder::reencode::Reencode;
+use wasm_encoder::{
+    CodeSection, DataCountSection, DataSection, ElementSection, EntityType, ExportSection,
+    Function, FunctionSection, GlobalSection, ImportSection, Instruction, MemorySection, Module,
+    StartSection, TableSection, TagSection, TypeSection, ValType,
+};
+use wasmparser::{CompositeInnerType, Encoding, KnownCustom, Name, Parser, Payload};
+
+/// Import module of the `enter` and `exit` hooks, both `(func (param i32))`
+pub const PROFILE_IMPORT_MODULE: &str = "__wasmrun_profile";
+
+/// Custom section listing the instrumented functions as a JSON array; a
+/// function's position in it is the id its hooks are called with
+pub const PROFILE_SECTION: &str = "wasmrun.profile";
+
+/// An instrumented module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instrumented {
+    pub bytes: Vec<u8>,
+    /// Names of the wrapped functions, by hook id
+    pub functions: Vec<String>,
+    /// Patterns that matched no function
+    pub unmatched: Vec<String>,
+}
+
+/// Whether `pattern` selects a function called `name`: `*` selects every
+/// function, a trailing `*` matches a prefix, anything else the whole name
+fn matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+/// Keeps imported functions where they are and moves defined ones past the two hooks
+struct Reindex {
+    imported_functions: u32,
+}
+
+impl Reencode for Reindex {
+    type Error = Infallible;
+
+    fn function_index(&mut self, func: u32) -> u32 {
+        if func < self.imported_functions {
+            func
+        } else {
+            func + 2
+        }
+    }
+}
+
+/// What the rewrite needs to know before emitting anything
+#[derive(Default)]
+struct Layout {
+    imported_functions: u32,
+    type_count: u32,
+    /// Parameter count of each type, `None` for non-function types
+    type_params: Vec<Option<u32>>,
+    /// Type index of each defined function
+    function_types: Vec<u32>,
+    /// Names from the name section and exports, by function index
+    names: HashMap<u32, Vec<String>>,
+}
+
+fn scan(bytes: &[u8]) -> Result<Layout, String> {
+    let mut layout = Layout::default();
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload.map_err(|e| e.to_string())? {
+            Payload::Version {
+                encoding: Encoding::Component,
+                ..
+            } => return Err("Components can't be instrumented, only core modules".to_string()),
+            Payload::TypeSection(reader) => {
+                for group in reader {
+                    for sub_type in group.map_err(|e| e.to_string())?.into_types() {
+                        layout.type_count += 1;
+                        layout
+                            .type_params
+                            .push(match &sub_type.composite_type.inner {
+                                CompositeInnerType::Func(func) => Some(func.params().len() as u32),
+                                _ => None,
+                            });
+                    }
+                }
+            }
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import.map_err(|e| e.to_string())?;
+                    if matches!(import.ty, wasmparser::TypeRef::Func(_)) {
+                        layout.imported_functions += 1;
+                    }
+                }
+            }
+            Payload::FunctionSection(reader) => {
+                for ty in reader {
+                    layout.function_types.push(ty.map_err(|e| e.to_string())?);
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export.map_err(|e| e.to_string())?;
+                    if export.kind == wasmparser::ExternalKind::Func {
+                        layout
+                            .names
+                            .entry(export.index)
+                            .or_default()
+                            .push(export.name.to_string());
+                    }
+                }
+            }
+            Payload::CustomSection(reader) => {
+                let KnownCustom::Name(names) = reader.as_known() else {
+                    continue;
+                };
+                // A malformed name section only costs the names
+                for name in names.into_iter().flatten() {
+                    if let Name::Function(functions) = name {
+                        for naming in functions.into_iter().flatten() {
+                            // The name section's name goes first; it is the one shown
+                            layout
+                                .names
+                                .entry(naming.index)
+                                .or_default()
+                                .insert(0, naming.name.to_string());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(layout)
+}
+
+/// Wrap the functions of `bytes` selected by `patterns` with profiling hooks
+pub fn instrument(bytes: &[u8], patterns: &[String]) -> Result<Instrumented, String> {
+    let layout = scan(bytes)?;
+    let imported = layout.imported_functions;
+    let defined = layout.function_types.len() as u32;
+
+    // Original index -> (hook id, index the body moves to)
+    let mut wrapped: HashMap<u32, (u32, u32)> = HashMap::new();
+    let mut functions = Vec::new();
+    let mut used = vec![false; patterns.len()];
+    for index in imported..imported + defined {
+        let names = layout.names.get(&index).map(Vec::as_slice).unwrap_or(&[]);
+        let mut selected = false;
+        for (pattern, used) in patterns.iter().zip(used.iter_mut()) {
+            if pattern == "*" || names.iter().any(|name| matches(pattern, name)) {
+                *used = true;
+                selected = true;
+            }
+        }
+        if selected {
+            let id = functions.len() as u32;
+            wrapped.insert(index, (id, imported + 2 + defined + id));
+            functions.push(
+                names
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| format!("func[{index}]")),
+            );
+        }
+    }
+    let unmatched: Vec<String> = patterns
+        .iter()
+        .zip(used)
+        .filter(|(_, used)| !used)
+        .map(|(pattern, _)| pattern.clone())
+        .collect();
+    if functions.is_empty() {
+        return Err(format!(
+            "No function matches {}",
+            patterns
+                .iter()
+                .map(|pattern| format!("'{pattern}'"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    let hook_type = layout.type_count;
+    let mut reindex = Reindex {
+        imported_functions: imported,
+    };
+    let mut module = Module::new();
+    let mut imports_written = false;
+    let mut code = CodeSection::new();
+    let mut bodies_left = 0u32;
+    let mut moved = Vec::new();
+    let mut defined_index = imported;
+
+    // The hooks go at the end of the import section, which is created if the module has none
+    let write_imports = |module: &mut Module, imports: &mut ImportSection| {
+        for hook in ["enter", "exit"] {
+            imports.import(PROFILE_IMPORT_MODULE, hook, EntityType::Function(hook_type));
+        }
+        module.section(imports);
+    };
+    let error = |e: wasm_encoder::reencode::Error<Infallible>| e.to_string();
+
+    for payload in Parser::new(0).parse_all(bytes) {
+        let payload = payload.map_err(|e| e.to_string())?;
+        let before_imports = matches!(
+            payload,
+            Payload::Version { .. } | Payload::TypeSection(_) | Payload::CustomSection(_)
+        );
+        if !imports_written && !before_imports && !matches!(payload, Payload::ImportSection(_)) {
+            write_imports(&mut module, &mut ImportSection::new());
+            imports_written = true;
+        }
+        match payload {
+            Payload::Version { .. } => {}
+            Payload::TypeSection(reader) => {
+                let mut types = TypeSection::new();
+                reindex
+                    .parse_type_section(&mut types, reader)
+                    .map_err(error)?;
+                types.ty().function([ValType::I32], []);
+                module.section(&types);
+            }
+            Payload::ImportSection(reader) => {
+                let mut imports = ImportSection::new();
+                reindex
+                    .parse_import_section(&mut imports, reader)
+                    .map_err(error)?;
+                write_imports(&mut module, &mut imports);
+                imports_written = true;
+            }
+            Payload::FunctionSection(reader) => {
+                let mut section = FunctionSection::new();
+                reindex
+                    .parse_function_section(&mut section, reader)
+                    .map_err(error)?;
+                // The moved bodies keep their types, in hook id order
+                let mut moved_types: Vec<(u32, u32)> = wrapped
+                    .iter()
+                    .map(|(index, (id, _))| {
+                        (*id, layout.function_types[(index - imported) as usize])
+                    })
+                    .collect();
+                moved_types.sort_unstable();
+                for (_, ty) in moved_types {
+                    section.function(ty);
+                }
+                module.section(&section);
+            }
+            Payload::TableSection(reader) => {
+                let mut section = TableSection::new();
+                reindex
+                    .parse_table_section(&mut section, reader)
+                    .map_err(error)?;
+                module.section(&section);
+            }
+            Payload::MemorySection(reader) => {
+                let mut section = MemorySection::new();
+                reindex
+                    .parse_memory_section(&mut section, reader)
+                    .map_err(error)?;
+                module.section(&section);
+            }
+            Payload::TagSection(reader) => {
+                let mut section = TagSection::new();
+                reindex
+                    .parse_tag_section(&mut section, reader)
+                    .map_err(error)?;
+                module.section(&section);
+            }
+            Payload::GlobalSection(reader) => {
+                let mut section = GlobalSection::new();
+                reindex
+                    .parse_global_section(&mut section, reader)
+                    .map_err(error)?;
+                module.section(&section);
+            }
+            Payload::ExportSection(reader) => {
+                let mut section = ExportSection::new();
+                reindex
+                    .parse_export_section(&mut section, reader)
+                    .map_err(error)?;
+                module.section(&section);
+            }
+            Payload::StartSection { func, .. } => {
+                module.section(&StartSection {
+                    function_index: reindex.function_index(func),
+                });
+            }
+            Payload::ElementSection(reader) => {
+                let mut section = ElementSection::new();
+                reindex
+                    .parse_element_section(&mut section, reader)
+                    .map_err(error)?;
+                module.section(&section);
+            }
+            Payload::DataCountSection { count, .. } => {
+                module.section(&DataCountSection { count });
+            }
+            Payload::CodeSectionStart { count, .. } => bodies_left = count,
+            Payload::CodeSectionEntry(body) => {
+                match wrapped.get(&defined_index) {
+                    Some(&(id, moved_to)) => {
+                        let params = layout
+                            .type_params
+                            .get(
+                                layout.function_types[(defined_index - imported) as usize] as usize,
+                            )
+                            .copied()
+                            .flatten()
+                            .ok_or("Function with a non-function type")?;
+                        code.function(&wrapper(imported, id, params, moved_to));
+                        moved.push((id, body));
+                    }
+                    None => reindex
+                        .parse_function_body(&mut code, body)
+                        .map_err(error)?,
+                }
+                defined_index += 1;
+                bodies_left -= 1;
+                if bodies_left == 0 {
+                    moved.sort_unstable_by_key(|(id, _)| *id);
+                    for (_, body) in moved.drain(..) {
+                        reindex
+                            .parse_function_body(&mut code, body)
+                            .map_err(error)?;
+                    }
+                    module.section(&code);
+                }
+            }
+            Payload::DataSection(reader) => {
+                let mut section = DataSection::new();
+                reindex
+                    .parse_data_section(&mut section, reader)
+                    .map_err(error)?;
+                module.section(&section);
+            }
+            Payload::CustomSection(reader) => {
+                reindex
+                    .parse_custom_section(&mut module, reader)
+                    .map_err(error)?;
+            }
+            Payload::End(_) => {}
+            other => {
+                return Err(format!(
+                    "Unsupported section at offset {:#x}",
+                    other.as_section().map_or(0, |(_, range)| range.start)
+                ))
+            }
+        }
+    }
+
+    let names = serde_json::to_vec(&functions).map_err(|e| e.to_string())?;
+    module.section(&wasm_encoder::CustomSection {
+        name: PROFILE_SECTION.into(),
+        data: names.into(),
+    });
+
+    Ok(Instrumented {
+        bytes: module.finish(),
+        functions,
+        unmatched,
+    })
+}
+
+/// `enter(id)`, call the moved body with every parameter, `exit(id)`; the
+/// results stay on the stack across the exit hook
+fn wrapper(imported: u32, id: u32, params: u32, moved_to: u32) -> Function {
+    let mut function = Function::new([]);
+    function.instruction(&Instruction::I32Const(id as i32));
+    function.instruction(&Instruction::Call(imported));
+    for param in 0..params {
+        function.instruction(&Instruction::LocalGet(param));
+    }
+    function.instruction(&Instruction::Call(moved_to));
+    function.instruction(&Instruction::I32Const(id as i32));
+    function.instruction(&Instruction::Call(imported + 1));
+    function.instruction(&Instruction::End);
+    function
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmtime::{Caller, Engine, Linker, Store};
+
+    fn section(id: u8, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![id, payload.len() as u8];
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    /// Imports `env.log`, exports `add(a, b)` and `run(a, b)`, which returns
+    /// `add(a, b) + add(a, b)`
+    fn module() -> Vec<u8> {
+        let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+        bytes.extend(section(
+            1,
+            &[
+                0x02, 0x60, 0x01, 0x7F, 0x00, 0x60, 0x02, 0x7F, 0x7F, 0x01, 0x7F,
+            ],
+        ));
+        bytes.extend(section(
+            2,
+            &[
+                0x01, 0x03, b'e', b'n', b'v', 0x03, b'l', b'o', b'g', 0x00, 0x00,
+            ],
+        ));
+        bytes.extend(section(3, &[0x02, 0x01, 0x01]));
+        bytes.extend(section(
+            7,
+            &[
+                0x02, 0x03, b'a', b'd', b'd', 0x00, 0x01, 0x03, b'r', b'u', b'n', 0x00, 0x02,
+            ],
+        ));
+        let mut code = vec![0x02];
+        code.extend([0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6A, 0x0B]);
+        code.extend([
+            0x0F, 0x00, 0x20, 0x00, 0x20, 0x01, 0x10, 0x01, 0x20, 0x00, 0x20, 0x01, 0x10, 0x01,
+            0x6A, 0x0B,
+        ]);
+        bytes.extend(section(10, &code));
+        bytes
+    }
+
+    /// Run `run(1, 2)` and return its result with the hook calls it made
+    fn run(bytes: &[u8]) -> (i32, Vec<(&'static str, i32)>) {
+        let engine = Engine::default();
+        let module = wasmtime::Module::new(&engine, bytes).unwrap();
+        let mut linker: Linker<Vec<(&'static str, i32)>> = Linker::new(&engine);
+        linker.func_wrap("env", "log", |_: i32| {}).unwrap();
+        for hook in ["enter", "exit"] {
+            linker
+                .func_wrap(
+                    PROFILE_IMPORT_MODULE,
+                    hook,
+                    move |mut caller: Caller<'_, Vec<(&'static str, i32)>>, id: i32| {
+                        caller.data_mut().push((hook, id));
+                    },
+                )
+                .unwrap();
+        }
+        let mut store = Store::new(&engine, Vec::new());
+        let instance = linker.instantiate(&mut store, &module).unwrap();
+        let run = instance
+            .get_typed_func::<(i32, i32), i32>(&mut store, "run")
+            .unwrap();
+        let result = run.call(&mut store, (1, 2)).unwrap();
+        (result, store.into_data())
+    }
+
+    #[test]
+    fn test_instrument_selected_function() {
+        let instrumented = instrument(&module(), &["add".to_string()]).unwrap();
+        wasmparser::Validator::new()
+            .validate_all(&instrumented.bytes)
+            .unwrap();
+        assert_eq!(instrumented.functions, ["add"]);
+        assert!(instrumented.unmatched.is_empty());
+
+        let (result, hooks) = run(&instrumented.bytes);
+        assert_eq!(result, 6);
+        assert_eq!(
+            hooks,
+            [("enter", 0), ("exit", 0), ("enter", 0), ("exit", 0)]
+        );
+    }
+
+    #[test]
+    fn test_instrument_every_function() {
+        let instrumented =
+            instrument(&module(), &["*".to_string(), "missing".to_string()]).unwrap();
+        assert_eq!(instrumented.functions, ["add", "run"]);
+        assert_eq!(instrumented.unmatched, ["missing"]);
+
+        let (result, hooks) = run(&instrumented.bytes);
+        assert_eq!(result, 6);
+        assert_eq!(
+            hooks,
+            [
+                ("enter", 1),
+                ("enter", 0),
+                ("exit", 0),
+                ("enter", 0),
+                ("exit", 0),
+                ("exit", 1)
+            ]
+        );
+
+        let section = wasmparser::Parser::new(0)
+            .parse_all(&instrumented.bytes)
+            .find_map(|payload| match payload {
+                Ok(Payload::CustomSection(reader)) if reader.name() == PROFILE_SECTION => {
+                    Some(reader.data().to_vec())
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(section, br#"["add","run"]"#);
+    }
+
+    #[test]
+    fn test_instrument_patterns() {
+        assert!(matches("ad*", "add"));
+        assert!(!matches("ad", "add"));
+        assert!(instrument(&module(), &["nothing*".to_string()])
+            .unwrap_err()
+            .contains("No function matches 'nothing*'"));
+        let component = b"\0asm\x0d\0\x01\0";
+        assert!(instrument(component, &["*".to_string()]).is_err());
+    }
+}
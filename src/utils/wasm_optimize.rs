@@ -0,0 +1,163 @@
+//! Binaryen's wasm-opt, linked in through the `wasm-opt` crate
+//!
+//! Release builds and `wasmrun optimize` run the optimizer without a
+//! `wasm-opt` binary on the PATH. All WebAssembly features are enabled, so
+//! modules using threads, SIMD or reference types pass through as they are.
+
+use crate::error::{CompilationError, Result, WasmrunError};
+use clap::ValueEnum;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use wasm_opt::OptimizationOptions;
+
+/// Optimization level, as wasm-opt's `-O2`, `-O3`, `-Os` and `-Oz`; the
+/// aliases let `-Oz` be written as on wasm-opt's command line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OptLevel {
+    #[value(name = "O2", alias = "2")]
+    O2,
+    #[value(name = "O3", alias = "3")]
+    O3,
+    #[default]
+    #[value(name = "Os", alias = "s")]
+    Os,
+    #[value(name = "Oz", alias = "z")]
+    Oz,
+}
+
+impl OptLevel {
+    fn options(self) -> OptimizationOptions {
+        match self {
+            Self::O2 => OptimizationOptions::new_opt_level_2(),
+            Self::O3 => OptimizationOptions::new_opt_level_3(),
+            Self::Os => OptimizationOptions::new_optimize_for_size(),
+            Self::Oz => OptimizationOptions::new_optimize_for_size_aggressively(),
+        }
+    }
+}
+
+impl fmt::Display for OptLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::O2 => "O2",
+            Self::O3 => "O3",
+            Self::Os => "Os",
+            Self::Oz => "Oz",
+        };
+        write!(f, "-{name}")
+    }
+}
+
+/// Sizes of a module before and after optimizing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeDelta {
+    pub before: u64,
+    pub after: u64,
+}
+
+impl SizeDelta {
+    /// Change in percent of the original size; negative when it shrank
+    pub fn percent(&self) -> f64 {
+        if self.before == 0 {
+            return 0.0;
+        }
+        (self.after as f64 - self.before as f64) * 100.0 / self.before as f64
+    }
+}
+
+/// Optimize the module at `input` into `output`, which may be the same file.
+/// `output` is only replaced once the optimized module validated.
+pub fn optimize(input: &Path, output: &Path, level: OptLevel) -> Result<SizeDelta> {
+    let before = fs::metadata(input)
+        .map_err(|e| WasmrunError::add_context(format!("Reading {}", input.display()), e))?
+        .len();
+    let name = output
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let partial = output.with_file_name(format!(".{name}.{}.opt", std::process::id()));
+
+    let mut options = level.options();
+    options.all_features();
+    let optimized = options
+        .run(input, &partial)
+        .map_err(|e| {
+            WasmrunError::Compilation(CompilationError::ToolExecutionFailed {
+                tool: format!("wasm-opt {level}"),
+                reason: format!("{}: {}", input.display(), error_chain(&e)),
+            })
+        })
+        .and_then(|()| {
+            // Keep the permissions of a module optimized in place
+            if let Ok(metadata) = fs::metadata(output) {
+                let _ = fs::set_permissions(&partial, metadata.permissions());
+            }
+            fs::rename(&partial, output)
+                .map_err(|e| WasmrunError::add_context(format!("Writing {}", output.display()), e))
+        });
+    if let Err(e) = optimized {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+
+    let after = fs::metadata(output)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    Ok(SizeDelta { before, after })
+}
+
+/// An error with its sources, which carry the useful part of wasm-opt's errors
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {cause}"));
+        source = cause.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_level_names() {
+        assert_eq!(OptLevel::from_str("Oz", false), Ok(OptLevel::Oz));
+        assert_eq!(OptLevel::from_str("3", false), Ok(OptLevel::O3));
+        assert!(OptLevel::from_str("O1", false).is_err());
+        assert_eq!(OptLevel::default().to_string(), "-Os");
+    }
+
+    #[test]
+    fn test_optimize_shrinks_module() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("app.wasm");
+        let module = wat::parse_str(
+            r#"(module
+                (func $unused (result i32) (i32.add (i32.const 1) (i32.const 2)))
+                (func (export "answer") (result i32)
+                    (local $x i32)
+                    (local.set $x (i32.const 40))
+                    (i32.add (local.get $x) (i32.const 2))))"#,
+        )
+        .unwrap();
+        fs::write(&input, &module).unwrap();
+
+        let output = dir.path().join("app.opt.wasm");
+        let delta = optimize(&input, &output, OptLevel::Oz).unwrap();
+        assert_eq!(delta.before, module.len() as u64);
+        assert!(delta.after < delta.before);
+        assert!(delta.percent() < 0.0);
+        assert!(wasmparser::validate(&fs::read(&output).unwrap()).is_ok());
+
+        // In place, and a broken module leaves the file as it was
+        optimize(&output, &output, OptLevel::O3).unwrap();
+        fs::write(&input, b"\0asm broken").unwrap();
+        assert!(optimize(&input, &input, OptLevel::O2).is_err());
+        assert_eq!(fs::read(&input).unwrap(), b"\0asm broken");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+}
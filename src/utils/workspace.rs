@@ -0,0 +1,328 @@
+//! Managed per-project workspace directories
+//!
+//! Build outputs and temporary copies live in `<cache_dir>/workspaces/<project>-<hash>`
+//! instead of ad-hoc locations under the system temp directory. Workspaces are
+//! evicted least-recently-used first once the total size exceeds the configured limit.
+
+use crate::config::WasmrunConfig;
+use crate::error::{Result, WasmrunError};
+use crate::warn_println;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Directory under the cache dir that holds all project workspaces
+pub const WORKSPACES_DIR: &str = "workspaces";
+
+/// Default size limit for all workspaces combined, in megabytes
+pub const DEFAULT_WORKSPACE_MAX_MB: u64 = 1024;
+
+/// File touched every time a workspace is opened, used to order eviction
+const LAST_USED_MARKER: &str = ".last-used";
+
+/// Build output directory inside a workspace
+const BUILD_DIR: &str = "build";
+
+/// Frozen release build served by `wasmrun preview`
+const PREVIEW_DIR: &str = "preview";
+
+/// Build exported by `wasmrun bundle`
+const BUNDLE_DIR: &str = "bundle";
+
+/// A project's workspace directory
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    root: PathBuf,
+}
+
+/// A workspace found on disk
+#[derive(Debug, Clone)]
+pub struct WorkspaceEntry {
+    pub path: PathBuf,
+    pub size: u64,
+    pub last_used: SystemTime,
+}
+
+/// Outcome of a garbage collection run
+#[derive(Debug, Default)]
+pub struct GcReport {
+    pub removed: Vec<PathBuf>,
+    pub freed_bytes: u64,
+    pub remaining_bytes: u64,
+}
+
+impl Workspace {
+    /// Open (creating if needed) the workspace for a project in the configured cache dir
+    pub fn for_project(project_path: &str) -> Result<Self> {
+        Self::open_in(&WasmrunConfig::cache_dir()?, project_path)
+    }
+
+    /// Open (creating if needed) the workspace for a project under `cache_dir`
+    pub fn open_in(cache_dir: &Path, project_path: &str) -> Result<Self> {
+        let root = Self::path_in(cache_dir, project_path);
+        fs::create_dir_all(&root).map_err(|e| {
+            WasmrunError::add_context(format!("Creating workspace {}", root.display()), e)
+        })?;
+        fs::write(root.join(LAST_USED_MARKER), project_path).map_err(|e| {
+            WasmrunError::add_context(format!("Updating workspace {}", root.display()), e)
+        })?;
+        Ok(Self { root })
+    }
+
+    /// Location of a project's workspace in the configured cache dir, without creating it
+    pub fn path_for(project_path: &str) -> Result<PathBuf> {
+        Ok(Self::path_in(&WasmrunConfig::cache_dir()?, project_path))
+    }
+
+    /// Location of the build `wasmrun bundle` exports for a project, without creating it
+    pub fn bundle_dir_for(project_path: &str) -> Result<PathBuf> {
+        Ok(Self::path_for(project_path)?.join(BUNDLE_DIR))
+    }
+
+    fn path_in(cache_dir: &Path, project_path: &str) -> PathBuf {
+        let canonical =
+            fs::canonicalize(project_path).unwrap_or_else(|_| PathBuf::from(project_path));
+        let name: String = canonical
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "project".to_string())
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let hash = fnv1a(canonical.to_string_lossy().as_bytes());
+
+        cache_dir
+            .join(WORKSPACES_DIR)
+            .join(format!("{name}-{hash:016x}"))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Build output directory, created if missing
+    pub fn output_dir(&self) -> Result<String> {
+        self.dir(BUILD_DIR)
+    }
+
+    /// Build output directory with stale files from previous runs removed
+    pub fn fresh_output_dir(&self) -> Result<String> {
+        self.fresh_dir(BUILD_DIR)
+    }
+
+    /// Empty directory for a preview snapshot, separate from the build output
+    pub fn fresh_preview_dir(&self) -> Result<String> {
+        self.fresh_dir(PREVIEW_DIR)
+    }
+
+    /// Empty directory for the build `wasmrun bundle` exports
+    pub fn fresh_bundle_dir(&self) -> Result<String> {
+        self.fresh_dir(BUNDLE_DIR)
+    }
+
+    fn dir(&self, name: &str) -> Result<String> {
+        let dir = self.root.join(name);
+        fs::create_dir_all(&dir).map_err(|e| {
+            WasmrunError::add_context(format!("Creating output directory {}", dir.display()), e)
+        })?;
+        Ok(dir.to_string_lossy().to_string())
+    }
+
+    fn fresh_dir(&self, name: &str) -> Result<String> {
+        let dir = self.root.join(name);
+        if dir.exists() {
+            fs::remove_dir_all(&dir).map_err(|e| {
+                WasmrunError::add_context(format!("Cleaning output directory {}", dir.display()), e)
+            })?;
+        }
+        self.dir(name)
+    }
+
+    /// Evict other workspaces until the configured size limit is respected, and
+    /// stored artifacts no build links to beyond the same limit
+    pub fn enforce_retention(&self) {
+        let max_mb = WasmrunConfig::load_or_default()
+            .map(|config| config.settings.workspace_max_size_mb)
+            .unwrap_or(DEFAULT_WORKSPACE_MAX_MB);
+        let Some(cache_dir) = self.root().parent().and_then(Path::parent) else {
+            return;
+        };
+
+        match collect_garbage(cache_dir, max_mb * 1024 * 1024, Some(self.root())) {
+            Ok(report) if !report.removed.is_empty() => {
+                println!(
+                    "🧹 Removed {} stale workspace(s), freed {}",
+                    report.removed.len(),
+                    super::CommandExecutor::format_file_size(report.freed_bytes)
+                );
+            }
+            Ok(_) => {}
+            Err(e) => warn_println!("Failed to enforce workspace retention: {e}"),
+        }
+        let collected = super::ArtifactStore::open_in(cache_dir)
+            .and_then(|store| store.collect_garbage(max_mb * 1024 * 1024));
+        if let Err(e) = collected {
+            warn_println!("Failed to collect the artifact store: {e}");
+        }
+    }
+}
+
+/// All workspaces under `cache_dir`, least recently used first
+pub fn list_workspaces(cache_dir: &Path) -> Vec<WorkspaceEntry> {
+    let Ok(entries) = fs::read_dir(cache_dir.join(WORKSPACES_DIR)) else {
+        return Vec::new();
+    };
+
+    let mut workspaces: Vec<WorkspaceEntry> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .map(|path| {
+            let last_used = fs::metadata(path.join(LAST_USED_MARKER))
+                .or_else(|_| fs::metadata(&path))
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            WorkspaceEntry {
+                size: dir_size(&path),
+                path,
+                last_used,
+            }
+        })
+        .collect();
+
+    workspaces.sort_by_key(|workspace| workspace.last_used);
+    workspaces
+}
+
+/// Remove least recently used workspaces until their total size is at most `max_bytes`.
+/// The workspace at `keep`, if any, is never removed.
+pub fn collect_garbage(cache_dir: &Path, max_bytes: u64, keep: Option<&Path>) -> Result<GcReport> {
+    let workspaces = list_workspaces(cache_dir);
+    let mut report = GcReport {
+        remaining_bytes: workspaces.iter().map(|workspace| workspace.size).sum(),
+        ..GcReport::default()
+    };
+
+    for workspace in workspaces {
+        if report.remaining_bytes <= max_bytes {
+            break;
+        }
+        if keep == Some(workspace.path.as_path()) {
+            continue;
+        }
+
+        fs::remove_dir_all(&workspace.path).map_err(|e| {
+            WasmrunError::add_context(
+                format!("Removing workspace {}", workspace.path.display()),
+                e,
+            )
+        })?;
+        report.remaining_bytes -= workspace.size;
+        report.freed_bytes += workspace.size;
+        report.removed.push(workspace.path);
+    }
+
+    Ok(report)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(_) => entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// FNV-1a, so workspace names stay stable across Rust releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_workspace_path_is_stable_per_project() {
+        let cache = tempdir().unwrap();
+        let project = tempdir().unwrap();
+        let project_path = project.path().to_str().unwrap();
+
+        let first = Workspace::open_in(cache.path(), project_path).unwrap();
+        let second = Workspace::open_in(cache.path(), project_path).unwrap();
+        assert_eq!(first.root(), second.root());
+        assert!(first.root().starts_with(cache.path().join(WORKSPACES_DIR)));
+
+        let output_dir = first.output_dir().unwrap();
+        assert!(Path::new(&output_dir).is_dir());
+    }
+
+    #[test]
+    fn test_fresh_output_dir_removes_stale_files() {
+        let cache = tempdir().unwrap();
+        let project = tempdir().unwrap();
+        let workspace = Workspace::open_in(cache.path(), project.path().to_str().unwrap()).unwrap();
+
+        let stale = Path::new(&workspace.output_dir().unwrap()).join("old.wasm");
+        fs::write(&stale, b"\0asm").unwrap();
+
+        workspace.fresh_output_dir().unwrap();
+        assert!(!stale.exists());
+    }
+
+    #[test]
+    fn test_preview_dir_is_separate_from_build_output() {
+        let cache = tempdir().unwrap();
+        let project = tempdir().unwrap();
+        let workspace = Workspace::open_in(cache.path(), project.path().to_str().unwrap()).unwrap();
+
+        let build = Path::new(&workspace.output_dir().unwrap()).join("app.wasm");
+        fs::write(&build, b"\0asm").unwrap();
+        let preview = workspace.fresh_preview_dir().unwrap();
+        fs::write(Path::new(&preview).join("app.wasm"), b"\0asm").unwrap();
+
+        let preview = workspace.fresh_preview_dir().unwrap();
+        assert!(build.exists());
+        assert!(fs::read_dir(preview).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_collect_garbage_evicts_least_recently_used() {
+        let cache = tempdir().unwrap();
+        let old_project = tempdir().unwrap();
+        let new_project = tempdir().unwrap();
+
+        let old = Workspace::open_in(cache.path(), old_project.path().to_str().unwrap()).unwrap();
+        fs::write(old.root().join("blob"), vec![0u8; 4096]).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        let new = Workspace::open_in(cache.path(), new_project.path().to_str().unwrap()).unwrap();
+        fs::write(new.root().join("blob"), vec![0u8; 4096]).unwrap();
+
+        let report = collect_garbage(cache.path(), 6000, None).unwrap();
+        assert_eq!(report.removed, vec![old.root().to_path_buf()]);
+        assert!(!old.root().exists());
+        assert!(new.root().exists());
+
+        let report = collect_garbage(cache.path(), 0, Some(new.root())).unwrap();
+        assert!(report.removed.is_empty());
+        assert!(new.root().exists());
+    }
+}
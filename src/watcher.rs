@@ -1,19 +1,312 @@
+//! Watching a project for changes
+//!
+//! A recursive `notify` watcher whose events are debounced, filtered through
+//! ignore globs and classified, so a burst of saves arrives as one
+//! [`ChangeSet`]. Watch mode rebuilds when sources or manifests are in it and
+//! hands it to live reload, which reloads open pages or, when only
+//! stylesheets changed, just swaps their styles.
+
 use notify::{RecommendedWatcher, RecursiveMode};
-use notify_debouncer_mini::{new_debouncer, DebouncedEvent, DebouncedEventKind};
-use std::path::Path;
+use notify_debouncer_mini::{new_debouncer, DebouncedEvent, Debouncer};
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver};
 use std::time::Duration;
 
-#[allow(dead_code)]
+/// How long the watcher waits for a burst of events to settle
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Paths that never trigger a rebuild or reload, on top of `watch.ignore`
+pub const DEFAULT_IGNORE: &[&str] = &["**/target/**", "**/node_modules/**", "**/.git/**"];
+
+/// Files that only configure wasmrun and have their own watcher
+const CONFIG_FILES: &[&str] = &["wasmrun.toml"];
+
+const MANIFESTS: &[&str] = &[
+    "cargo.toml",
+    "cargo.lock",
+    "go.mod",
+    "go.sum",
+    "package.json",
+    "asconfig.json",
+    "makefile",
+    "cmakelists.txt",
+    "pyproject.toml",
+];
+
+const SOURCE_EXTENSIONS: &[&str] = &[
+    "rs", "go", "c", "cc", "cpp", "h", "hpp", "ts", "js", "py", "zig",
+];
+
+const ASSET_EXTENSIONS: &[&str] = &[
+    "html", "htm", "svg", "png", "jpg", "jpeg", "gif", "webp", "ico", "json", "txt", "wav", "mp3",
+    "ogg", "woff", "woff2", "ttf",
+];
+
+/// What kind of file changed, which decides what has to happen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Code that is compiled into the module
+    Source,
+    /// A build manifest, e.g. `Cargo.toml` or `go.mod`
+    Manifest,
+    /// A stylesheet the page can swap without reloading
+    Stylesheet,
+    /// Any other file served as is
+    Asset,
+}
+
+impl ChangeKind {
+    /// Classify `relative`, a path below the project root; `None` for files
+    /// nothing depends on, such as editor swap files
+    fn of(relative: &Path) -> Option<Self> {
+        if relative
+            .components()
+            .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+        {
+            return None;
+        }
+        let name = relative.file_name()?.to_string_lossy().to_lowercase();
+        if CONFIG_FILES.contains(&name.as_str()) {
+            return None;
+        }
+        if MANIFESTS.contains(&name.as_str()) {
+            return Some(ChangeKind::Manifest);
+        }
+        let extension = relative.extension()?.to_string_lossy().to_lowercase();
+        if SOURCE_EXTENSIONS.contains(&extension.as_str()) {
+            Some(ChangeKind::Source)
+        } else if extension == "toml" {
+            Some(ChangeKind::Manifest)
+        } else if extension == "css" {
+            Some(ChangeKind::Stylesheet)
+        } else if ASSET_EXTENSIONS.contains(&extension.as_str()) {
+            Some(ChangeKind::Asset)
+        } else {
+            None
+        }
+    }
+}
+
+/// One changed file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// Path below the project root
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+    pub removed: bool,
+}
+
+/// The files changed in one debounced burst
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangeSet {
+    pub changes: Vec<Change>,
+}
+
+impl ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Whether the module has to be built again
+    pub fn needs_rebuild(&self) -> bool {
+        self.changes
+            .iter()
+            .any(|change| matches!(change.kind, ChangeKind::Source | ChangeKind::Manifest))
+    }
+
+    /// Add the changes of `other` to these, each path once
+    pub fn merge(&mut self, other: ChangeSet) {
+        for change in other.changes {
+            if let Some(known) = self
+                .changes
+                .iter_mut()
+                .find(|known| known.path == change.path)
+            {
+                *known = change;
+            } else {
+                self.changes.push(change);
+            }
+        }
+    }
+
+    /// Whether open pages can keep running and only swap their styles
+    pub fn styles_only(&self) -> bool {
+        !self.is_empty()
+            && self
+                .changes
+                .iter()
+                .all(|change| change.kind == ChangeKind::Stylesheet && !change.removed)
+    }
+}
+
+impl fmt::Display for ChangeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(first) = self.changes.first() else {
+            return f.write_str("nothing");
+        };
+        write!(f, "{}", first.path.display())?;
+        if first.removed {
+            f.write_str(" (removed)")?;
+        }
+        match self.changes.len() {
+            1 => Ok(()),
+            2 => f.write_str(" and 1 other file"),
+            count => write!(f, " and {} other files", count - 1),
+        }
+    }
+}
+
+/// Glob patterns, relative to the project root, for paths the watcher skips
+///
+/// `*` and `?` match within a path segment and `**` matches any number of
+/// segments. As in `.gitignore`, a pattern with no `/` except at its end
+/// matches a file or directory of that name anywhere, other patterns are
+/// relative to the root, and everything below a matched directory is skipped.
+#[derive(Debug, Clone)]
+pub struct IgnoreSet {
+    patterns: Vec<Vec<String>>,
+}
+
+impl IgnoreSet {
+    /// The default patterns plus `extra`
+    pub fn new(extra: &[String]) -> Self {
+        Self {
+            patterns: DEFAULT_IGNORE
+                .iter()
+                .copied()
+                .chain(extra.iter().map(String::as_str))
+                .filter_map(Self::parse)
+                .collect(),
+        }
+    }
+
+    /// These patterns plus `extra`
+    pub fn with(mut self, extra: &[String]) -> Self {
+        self.patterns
+            .extend(extra.iter().filter_map(|pattern| Self::parse(pattern)));
+        self
+    }
+
+    /// A pattern as segments, anchored at the root
+    fn parse(pattern: &str) -> Option<Vec<String>> {
+        let pattern = pattern.trim();
+        let pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+        let anchored = pattern.trim_end_matches('/').contains('/');
+        let pattern = pattern.trim_start_matches('/');
+        if pattern.is_empty() {
+            return None;
+        }
+        let mut segments: Vec<String> = Vec::new();
+        if !anchored {
+            segments.push("**".to_string());
+        }
+        segments.extend(
+            pattern
+                .split('/')
+                .filter(|segment| !segment.is_empty())
+                .map(str::to_string),
+        );
+        Some(segments)
+    }
+
+    /// Whether `relative` or a directory it is in matches a pattern
+    pub fn is_ignored(&self, relative: &Path) -> bool {
+        let segments: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        (1..=segments.len()).any(|end| {
+            self.patterns
+                .iter()
+                .any(|pattern| matches_segments(pattern, &segments[..end]))
+        })
+    }
+}
+
+fn matches_segments(pattern: &[String], path: &[String]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| matches_segments(rest, &path[skip..]))
+        }
+        Some((first, rest)) => path.split_first().is_some_and(|(segment, path)| {
+            matches_glob(first, segment) && matches_segments(rest, path)
+        }),
+    }
+}
+
+/// Match one path segment against a pattern with `*` and `?`
+fn matches_glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// How a project is watched, from the `[watch]` section of `wasmrun.toml`
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// Paths below the project to watch; the whole project when empty
+    pub paths: Vec<String>,
+    pub debounce: Duration,
+    pub ignore: IgnoreSet,
+}
+
+impl WatchOptions {
+    pub fn from_settings(settings: &crate::config::WatchSection) -> Self {
+        Self {
+            paths: settings.paths.clone().unwrap_or_default(),
+            debounce: settings
+                .debounce_ms
+                .map_or(DEFAULT_DEBOUNCE, Duration::from_millis),
+            ignore: IgnoreSet::new(settings.ignore.as_deref().unwrap_or_default()),
+        }
+    }
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            debounce: DEFAULT_DEBOUNCE,
+            ignore: IgnoreSet::new(&[]),
+        }
+    }
+}
+
+type DebouncedEvents = Result<Vec<DebouncedEvent>, Vec<notify::Error>>;
+
 pub struct ProjectWatcher {
-    debounced_receiver: Option<Receiver<Result<Vec<DebouncedEvent>, Vec<notify::Error>>>>,
-    #[allow(dead_code)]
-    watcher: Option<notify_debouncer_mini::Debouncer<RecommendedWatcher>>,
+    root: PathBuf,
+    ignore: IgnoreSet,
+    receiver: Receiver<DebouncedEvents>,
+    _debouncer: Debouncer<RecommendedWatcher>,
 }
 
 impl ProjectWatcher {
-    #[allow(dead_code)]
-    pub fn new(project_path: &str) -> Result<Self, String> {
+    pub fn new(project_path: &str, options: WatchOptions) -> Result<Self, String> {
         let path = Path::new(project_path);
 
         if !path.exists() {
@@ -24,66 +317,204 @@ impl ProjectWatcher {
             return Err(format!("Path is not a directory: {project_path}"));
         }
 
-        // Channel for events
+        // Events carry absolute paths, so compare against the canonical root
+        let root = path.canonicalize().map_err(|e| e.to_string())?;
         let (tx, rx) = channel();
 
-        let mut debouncer = new_debouncer(Duration::from_millis(500), None, tx)
+        let mut debouncer = new_debouncer(options.debounce, None, tx)
             .map_err(|e| format!("Failed to create file watcher: {e}"))?;
 
-        debouncer
-            .watcher()
-            .watch(path, RecursiveMode::Recursive)
-            .map_err(|e| format!("Failed to watch directory: {e}"))?;
+        let watched: Vec<PathBuf> = if options.paths.is_empty() {
+            vec![root.clone()]
+        } else {
+            options.paths.iter().map(|path| root.join(path)).collect()
+        };
+        for path in &watched {
+            if !path.exists() {
+                return Err(format!(
+                    "watch.paths entry does not exist: {}",
+                    path.display()
+                ));
+            }
+            debouncer
+                .watcher()
+                .watch(path, RecursiveMode::Recursive)
+                .map_err(|e| format!("Failed to watch {}: {e}", path.display()))?;
+        }
 
-        println!("🔍 Watching directory: {project_path}");
+        let what = if options.paths.is_empty() {
+            project_path.to_string()
+        } else {
+            options.paths.join(", ")
+        };
+        println!(
+            "🔍 Watching {what} (debounce {}ms)",
+            options.debounce.as_millis()
+        );
 
         Ok(Self {
-            debounced_receiver: Some(rx),
-            watcher: Some(debouncer),
+            root,
+            ignore: options.ignore,
+            receiver: rx,
+            _debouncer: debouncer,
         })
     }
 
-    #[allow(dead_code)]
-    pub fn wait_for_change(&self) -> Option<Result<Vec<DebouncedEvent>, Vec<notify::Error>>> {
-        if let Some(rx) = &self.debounced_receiver {
-            rx.recv().ok()
-        } else {
-            None
+    /// Block until files that matter change; `None` once the watcher stops
+    pub fn wait_for_changes(&self) -> Option<Result<ChangeSet, Vec<notify::Error>>> {
+        loop {
+            let changes = match self.receiver.recv().ok()? {
+                Ok(events) => self.classify(&events),
+                Err(errors) => return Some(Err(errors)),
+            };
+            if !changes.is_empty() {
+                return Some(Ok(changes));
+            }
         }
     }
 
-    #[allow(dead_code)]
-    pub fn should_recompile(&self, events: &[DebouncedEvent]) -> bool {
-        for event in events {
-            if event.kind == DebouncedEventKind::Any {
-                let path = &event.path;
-
-                if path.components().any(|c| {
-                    let s = c.as_os_str().to_string_lossy();
-                    s == "target" || s.starts_with(".")
-                }) {
-                    continue;
-                }
+    /// Changes already waiting, without blocking, so bursts that arrived
+    /// while a build ran are handled together
+    pub fn pending_changes(&self) -> ChangeSet {
+        let mut pending = ChangeSet::default();
+        while let Ok(result) = self.receiver.try_recv() {
+            if let Ok(events) = result {
+                pending.merge(self.classify(&events));
+            }
+        }
+        pending
+    }
 
-                if let Some(ext) = path.extension() {
-                    let ext = ext.to_string_lossy().to_lowercase();
-
-                    if [
-                        "rs", "go", "c", "cpp", "h", "hpp", "ts", "js", "toml", "py", "mod",
-                    ]
-                    .contains(&ext.as_str())
-                    {
-                        return true;
-                    }
-                } else if path.file_name().is_some_and(|f| {
-                    let name = f.to_string_lossy().to_lowercase();
-                    ["cargo.toml", "makefile", "go.mod", "package.json"].contains(&name.as_str())
-                }) {
-                    return true;
-                }
+    fn classify(&self, events: &[DebouncedEvent]) -> ChangeSet {
+        let mut changes: Vec<Change> = Vec::new();
+        for event in events {
+            let relative = event.path.strip_prefix(&self.root).unwrap_or(&event.path);
+            if self.ignore.is_ignored(relative) {
+                continue;
+            }
+            let Some(kind) = ChangeKind::of(relative) else {
+                continue;
+            };
+            if changes.iter().any(|change| change.path == relative) {
+                continue;
             }
+            changes.push(Change {
+                path: relative.to_path_buf(),
+                kind,
+                removed: !event.path.exists(),
+            });
         }
+        ChangeSet { changes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ignored(extra: &[&str], path: &str) -> bool {
+        let extra: Vec<String> = extra.iter().map(|p| p.to_string()).collect();
+        IgnoreSet::new(&extra).is_ignored(Path::new(path))
+    }
+
+    #[test]
+    fn test_default_ignores() {
+        assert!(ignored(&[], "target/debug/app.wasm"));
+        assert!(ignored(&[], "crates/core/target/release/x.rs"));
+        assert!(ignored(&[], "web/node_modules/lib/index.js"));
+        assert!(ignored(&[], ".git/HEAD"));
+        assert!(!ignored(&[], "src/targets.rs"));
+        assert!(!ignored(&[], "src/lib.rs"));
+    }
+
+    #[test]
+    fn test_extra_ignore_globs() {
+        assert!(ignored(&["*.log"], "logs/build.log"));
+        assert!(ignored(&["dist/"], "dist/app.js"));
+        assert!(ignored(&["dist/"], "web/dist/app.js"));
+        assert!(!ignored(&["/dist"], "web/dist/app.js"));
+        assert!(ignored(&["**/generated/*.rs"], "src/generated/bindings.rs"));
+        assert!(!ignored(
+            &["**/generated/*.rs"],
+            "src/generated/nested/x.rs"
+        ));
+        assert!(ignored(&["src/gen?.rs"], "src/gen1.rs"));
+        assert!(!ignored(&["src/gen?.rs"], "src/gen12.rs"));
+
+        // What a build writes into the project comes on top of the settings
+        let ignore = IgnoreSet::new(&["*.log".to_string()]).with(&["/build/".to_string()]);
+        assert!(ignore.is_ignored(Path::new("build/release.js")));
+        assert!(ignore.is_ignored(Path::new("x.log")));
+        assert!(!ignore.is_ignored(Path::new("assembly/build/index.ts")));
+    }
+
+    #[test]
+    fn test_change_kinds() {
+        let kind = |path: &str| ChangeKind::of(Path::new(path));
+        assert_eq!(kind("src/main.rs"), Some(ChangeKind::Source));
+        assert_eq!(kind("Cargo.toml"), Some(ChangeKind::Manifest));
+        assert_eq!(kind("go.mod"), Some(ChangeKind::Manifest));
+        assert_eq!(kind("static/site.css"), Some(ChangeKind::Stylesheet));
+        assert_eq!(kind("index.html"), Some(ChangeKind::Asset));
+        assert_eq!(kind("wasmrun.toml"), None);
+        assert_eq!(kind("src/.main.rs.swp"), None);
+        assert_eq!(kind("src/main.rs~"), None);
+    }
+
+    #[test]
+    fn test_change_set_decisions() {
+        let change = |path: &str, kind| Change {
+            path: PathBuf::from(path),
+            kind,
+            removed: false,
+        };
+        let styles = ChangeSet {
+            changes: vec![change("a.css", ChangeKind::Stylesheet)],
+        };
+        assert!(styles.styles_only());
+        assert!(!styles.needs_rebuild());
+        assert_eq!(styles.to_string(), "a.css");
+
+        let mixed = ChangeSet {
+            changes: vec![
+                change("a.css", ChangeKind::Stylesheet),
+                change("src/lib.rs", ChangeKind::Source),
+                change("index.html", ChangeKind::Asset),
+            ],
+        };
+        assert!(!mixed.styles_only());
+        assert!(mixed.needs_rebuild());
+        assert_eq!(mixed.to_string(), "a.css and 2 other files");
+
+        let mut merged = styles.clone();
+        merged.merge(mixed);
+        merged.merge(ChangeSet {
+            changes: vec![Change {
+                removed: true,
+                ..change("a.css", ChangeKind::Stylesheet)
+            }],
+        });
+        assert_eq!(merged.changes.len(), 3);
+        assert!(merged.changes[0].removed);
+        assert!(merged.needs_rebuild());
+    }
+
+    #[test]
+    fn test_watcher_reports_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("target")).unwrap();
+        let options = WatchOptions {
+            paths: Vec::new(),
+            debounce: Duration::from_millis(50),
+            ignore: IgnoreSet::new(&[]),
+        };
+        let watcher = ProjectWatcher::new(dir.path().to_str().unwrap(), options).unwrap();
+        std::fs::write(dir.path().join("target").join("out.rs"), "").unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "").unwrap();
 
-        false
+        let changes = watcher.wait_for_changes().unwrap().unwrap();
+        assert_eq!(changes.changes.len(), 1);
+        assert_eq!(changes.changes[0].path, PathBuf::from("lib.rs"));
+        assert!(changes.needs_rebuild());
     }
 }